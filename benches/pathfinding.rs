@@ -0,0 +1,37 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use tgf::ext::glam::{ivec2, IVec2};
+use tgf::utils::pathfinding::{astar, dijkstra, FlowField, Neighborhood};
+
+const SIZE: IVec2 = ivec2(256, 256);
+
+/// Walls every 8 columns with alternating gaps at the top and bottom, so paths have to zig-zag.
+fn maze_cost(p: IVec2) -> Option<f32> {
+    let is_wall = p.x % 8 == 4
+        && if (p.x / 8) % 2 == 0 {
+            p.y > 2
+        } else {
+            p.y < SIZE.y - 3
+        };
+    (!is_wall).then_some(1.0 + (p.y % 3) as f32)
+}
+
+fn astar_maze(c: &mut Criterion) {
+    c.bench_function("astar_maze", |b| {
+        b.iter(|| astar(SIZE, ivec2(0, 0), SIZE - 1, Neighborhood::Eight, maze_cost))
+    });
+}
+
+fn dijkstra_maze(c: &mut Criterion) {
+    c.bench_function("dijkstra_maze", |b| {
+        b.iter(|| dijkstra(SIZE, ivec2(0, 0), Neighborhood::Eight, maze_cost))
+    });
+}
+
+fn flow_field_maze(c: &mut Criterion) {
+    c.bench_function("flow_field_maze", |b| {
+        b.iter(|| FlowField::new(SIZE, &[SIZE - 1], Neighborhood::Eight, maze_cost))
+    });
+}
+
+criterion_group!(benches, astar_maze, dijkstra_maze, flow_field_maze);
+criterion_main!(benches);
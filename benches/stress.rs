@@ -0,0 +1,159 @@
+use std::rc::Rc;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use tgf::ext::ahash::AHashMap;
+use tgf::ext::glam::{dvec2, vec2, vec3};
+use tgf::ext::{pollster, wgpu};
+use tgf::renderer::gizmos::GizmosLineQueue;
+use tgf::renderer::sdf_sprite::batch_sprites;
+use tgf::ui::{
+    div, font::SdfFontRef, Axis, Board, Corners, Div, IntoElementBox, Len, SdfFont, TextSection,
+    UiEffect, VerticalAlign,
+};
+use tgf::{
+    create_white_px_texture, leak, Aabb, AlphaSdfParams, BindableTexture, Camera3d, Color,
+    SdfSprite, Transform,
+};
+
+/// 100 rows of 100 rounded rects, the ui part of the `stress` example.
+fn grid_10k() -> Div {
+    let mut grid = div().full();
+    for row in 0..100 {
+        let mut r = div().style(|s| {
+            s.axis = Axis::X;
+            s.gap = 2.0;
+        });
+        for col in 0..100 {
+            r.push(div().style(|s| {
+                s.width = Some(Len::Px(8.0));
+                s.height = Some(Len::Px(8.0));
+                s.color = Color::from_hsv((row * 100 + col) as f64 * 0.036, 0.8, 0.9);
+                s.border.radius = Corners::all(3.0);
+            }));
+        }
+        grid.push(r);
+    }
+    grid
+}
+
+/// The same sprites as in the `stress` example.
+fn sprites_50k(texture: Rc<BindableTexture>) -> Vec<SdfSprite> {
+    (0..50_000)
+        .map(|i| {
+            let pos = vec3((i % 250) as f32, (i % 7) as f32 * 0.3, (i / 250) as f32);
+            SdfSprite {
+                texture: texture.clone(),
+                transform: Transform {
+                    position: pos * 0.4,
+                    ..Default::default()
+                },
+                offset: vec2(0.0, 0.0),
+                size: vec2(0.3, 0.3),
+                uv: Aabb::UNIT,
+                color: Color::from_hsv((i % 360) as f64, 0.7, 1.0),
+                sdf_params: AlphaSdfParams::default(),
+                layer: 0,
+                order_in_layer: 0,
+                drivers: Default::default(),
+            }
+        })
+        .collect()
+}
+
+/// `depth` nested divs, each with a paragraph of text that wraps in the shrinking width.
+fn nested_text(font: SdfFontRef, depth: usize) -> Div {
+    let mut inner = div();
+    for level in (0..depth).rev() {
+        inner = div()
+            .style(|s| {
+                s.padding.left = 8.0;
+                s.color = Color::from_hsv(level as f64 * 15.0, 0.3, 0.2);
+            })
+            .child(TextSection {
+                string: format!("Level {level}: {}", LOREM).into(),
+                font: font.into(),
+                color: Color::WHITE,
+                font_size: 14.0,
+                shadow_intensity: 0.0,
+                hinting: None,
+                vertical_align: VerticalAlign::Baseline,
+                effect: UiEffect::NONE,
+            })
+            .child(inner);
+    }
+    inner
+}
+
+const LOREM: &str = "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor \
+incididunt ut labore et dolore magna aliqua. Ut enim ad minim veniam, quis nostrud exercitation ullamco \
+laboris nisi ut aliquip ex ea commodo consequat.";
+
+/// Textures and font atlases need a device, the benches that use them are skipped on machines without an adapter.
+fn headless_device() -> Option<(wgpu::Device, wgpu::Queue)> {
+    let instance = wgpu::Instance::default();
+    let adapter = pollster::block_on(instance.request_adapter(&Default::default()))?;
+    pollster::block_on(adapter.request_device(&Default::default(), None)).ok()
+}
+
+fn ui_10k_divs_layout_and_batching(c: &mut Criterion) {
+    let mut board = Board::new(div().store(), dvec2(1920.0, 1080.0));
+    c.bench_function("ui_10k_divs_layout_and_batching", |b| {
+        b.iter(|| board.set_element(grid_10k().store()))
+    });
+}
+
+fn gizmos_100k_lines(c: &mut Criterion) {
+    let mut queue = GizmosLineQueue::new();
+    c.bench_function("gizmos_100k_lines", |b| {
+        b.iter(|| {
+            queue.clear();
+            for i in 0..100_000 {
+                let x = (i % 1000) as f32;
+                let z = (i / 1000) as f32;
+                queue.draw_line(vec3(x, 0.0, z), vec3(x, 1.0, z), Color::WHITE);
+            }
+            queue.lines.len()
+        })
+    });
+}
+
+fn sprites_50k_sorting_and_batching(c: &mut Criterion) {
+    let Some((device, queue)) = headless_device() else {
+        eprintln!("no gpu adapter, skipping sprites_50k_sorting_and_batching");
+        return;
+    };
+    let sprites = sprites_50k(Rc::new(create_white_px_texture(&device, &queue)));
+    let camera = Camera3d::new(1920, 1080);
+    let layer_sorts = AHashMap::new();
+    c.bench_function("sprites_50k_sorting_and_batching", |b| {
+        b.iter(|| {
+            let mut sprites: Vec<&SdfSprite> = sprites.iter().collect();
+            batch_sprites(&mut sprites, &camera, &layer_sorts, 1.0)
+        })
+    });
+}
+
+fn ui_deep_text_layout(c: &mut Criterion) {
+    let Some((device, queue)) = headless_device() else {
+        eprintln!("no gpu adapter, skipping ui_deep_text_layout");
+        return;
+    };
+    let font: SdfFontRef = leak(SdfFont::from_bytes(
+        include_bytes!("../assets/MarkoOne-Regular.ttf"),
+        &device,
+        &queue,
+    ));
+    let mut board = Board::new(div().store(), dvec2(1920.0, 1080.0));
+    c.bench_function("ui_deep_text_layout", |b| {
+        b.iter(|| board.set_element(nested_text(font, 24).store()))
+    });
+}
+
+criterion_group!(
+    benches,
+    ui_10k_divs_layout_and_batching,
+    gizmos_100k_lines,
+    sprites_50k_sorting_and_batching,
+    ui_deep_text_layout
+);
+criterion_main!(benches);
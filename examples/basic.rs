@@ -5,7 +5,9 @@ use rand::{thread_rng, Rng};
 use tgf::{
     edit, leak,
     renderer::screen_textures,
-    ui::{div, font::SdfFontRef, Align, IntoElementBox, SdfFont, TextSection},
+    ui::{
+        div, font::SdfFontRef, Align, IntoElementBox, SdfFont, TextSection, UiEffect, VerticalAlign,
+    },
     utils::camera_controllers::FlyCamController,
     AppT, Bloom, Camera3d, Camera3dGR, Color, ColorMeshRenderer, DefaultWorld, Egui, Gizmos,
     GraphicsContext, Input, KeyCode, Lerp, RenderFormat, Runner, Screen, ScreenGR, ScreenTextures,
@@ -30,6 +32,10 @@ impl AppT for App {
         self.world.receive_window_event(event);
     }
 
+    fn receive_device_event(&mut self, event: &tgf::DeviceEvent) {
+        self.world.receive_device_event(event);
+    }
+
     fn update(&mut self, cb: &mut tgf::RunnerCallbacks) {
         self.world.start_frame();
         self.main_update(cb);
@@ -73,15 +79,18 @@ impl App {
                 })
                 .child(TextSection {
                     string: "Move with WASD. Turn with arrow keys.".into(),
-                    font: self.font,
+                    font: self.font.into(),
                     color: Color::WHITE,
                     font_size,
                     shadow_intensity,
+                    hinting: None,
+                    vertical_align: VerticalAlign::Baseline,
+                    effect: UiEffect::NONE,
                 })
                 .store(),
         );
 
-        self.world.gizmos.draw_xyz();
+        self.world.gizmos.borrow_mut().draw_xyz();
 
         if self.world.input.keys().just_pressed(KeyCode::Space) {
             self.some_cubes = random_cubes();
@@ -106,11 +115,25 @@ impl App {
                 )
             })
             .collect();
-        self.world.color_renderer.draw_cubes(&cube_instances);
+        self.world
+            .color_renderer
+            .borrow_mut()
+            .draw_cubes(&cube_instances);
 
         let speed: f32 = edit!(10.0, 0.0..100.0, "speed");
         let angle_speed: f32 = edit!(2.0, "angle speed");
-        let cam_controller = FlyCamController { speed, angle_speed };
+        // mouselook while the cursor is locked:
+        if self.world.input.keys().just_pressed(KeyCode::Tab) {
+            let locked = !self.world.input.cursor_locked();
+            self.world
+                .input
+                .set_cursor_locked(locked, &self.world.window);
+        }
+        let cam_controller = FlyCamController {
+            speed,
+            angle_speed,
+            ..Default::default()
+        };
         cam_controller.update(&self.world.input, &self.world.time, &mut self.world.camera);
     }
 }
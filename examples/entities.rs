@@ -0,0 +1,105 @@
+use std::sync::Arc;
+
+use glam::{Quat, Vec3};
+use rand::{thread_rng, Rng};
+use tgf::{
+    utils::camera_controllers::FlyCamController, AppT, Color, DefaultWorld, EntityStore, KeyCode,
+    Runner, Transform, Window,
+};
+
+pub fn main() {
+    let runner = Runner::new(Default::default());
+    let mut app = App::new(runner.window());
+    runner.run(&mut app).unwrap();
+}
+
+struct Velocity(Vec3);
+
+/// Seconds until the entity is despawned.
+struct Lifetime(f32);
+
+struct App {
+    world: DefaultWorld,
+    entities: EntityStore,
+}
+
+impl AppT for App {
+    fn receive_window_event(&mut self, event: &tgf::WindowEvent) {
+        self.world.receive_window_event(event);
+    }
+
+    fn update(&mut self, cb: &mut tgf::RunnerCallbacks) {
+        self.world.start_frame();
+        self.main_update(cb);
+        self.world.render();
+        self.world.end_frame();
+    }
+}
+
+impl App {
+    fn new(window: Arc<Window>) -> Self {
+        Self {
+            world: DefaultWorld::new(window),
+            entities: EntityStore::new(),
+        }
+    }
+
+    fn spawn_cubes(&mut self, n: usize) {
+        let mut rng = thread_rng();
+        for _ in 0..n {
+            let e = self.entities.spawn();
+            let position = rng.gen::<Vec3>() * 20.0 - 10.0;
+            self.entities
+                .insert(e, Transform::new(position.x, position.y, position.z));
+            self.entities
+                .insert(e, Velocity(rng.gen::<Vec3>() * 2.0 - 1.0));
+            self.entities
+                .insert(e, Color::from_hsv(rng.gen::<f64>() * 360.0, 1.0, 1.0));
+            self.entities.insert(e, Lifetime(rng.gen::<f32>() * 10.0));
+        }
+    }
+
+    fn main_update(&mut self, cb: &mut tgf::RunnerCallbacks) {
+        if self.world.input.close_requested() {
+            cb.exit("exit");
+        }
+        let delta = self.world.time.delta().as_secs_f32();
+
+        if self.world.input.keys().just_pressed(KeyCode::Space) || self.entities.is_empty() {
+            self.spawn_cubes(256);
+        }
+
+        // systems are just functions over component queries:
+        self.entities
+            .for_each2_mut::<Transform, Velocity>(|_, transform, velocity| {
+                transform.position += velocity.0 * delta;
+                transform.rotation = Quat::from_scaled_axis(transform.position * 0.2);
+            });
+
+        let mut dead = vec![];
+        for (e, lifetime) in self.entities.query_mut::<Lifetime>() {
+            lifetime.0 -= delta;
+            if lifetime.0 <= 0.0 {
+                dead.push(e);
+            }
+        }
+        for e in dead {
+            self.entities.despawn(e);
+        }
+
+        // wire renderers to component queries:
+        let cubes: Vec<(Transform, Color)> = self
+            .entities
+            .query2::<Transform, Color>()
+            .map(|(_, transform, color)| (*transform, *color))
+            .collect();
+        self.world.color_renderer.borrow_mut().draw_cubes(&cubes);
+
+        let cam_controller = FlyCamController {
+            speed: 10.0,
+            angle_speed: 2.0,
+            ..Default::default()
+        };
+        cam_controller.update(&self.world.input, &self.world.time, &mut self.world.camera);
+    }
+}
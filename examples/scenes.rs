@@ -0,0 +1,140 @@
+use std::{sync::Arc, time::Duration};
+
+use glam::{Quat, Vec3};
+use tgf::{
+    AppT, Color, DefaultWorld, Easing, KeyCode, Runner, Scene, SceneCommand, SceneStack, Transform,
+    TransitionKind, Window,
+};
+
+pub fn main() {
+    let runner = Runner::new(Default::default());
+    let mut app = App::new(runner.window());
+    runner.run(&mut app).unwrap();
+}
+
+struct App {
+    world: DefaultWorld,
+    scenes: SceneStack<DefaultWorld>,
+}
+
+impl AppT for App {
+    fn receive_window_event(&mut self, event: &tgf::WindowEvent) {
+        self.world.receive_window_event(event);
+        self.scenes.receive_window_event(event, &mut self.world);
+    }
+
+    fn update(&mut self, cb: &mut tgf::RunnerCallbacks) {
+        self.world.start_frame();
+        if self.world.input.close_requested() || self.scenes.is_empty() {
+            cb.exit("exit");
+        }
+        self.scenes.update(&mut self.world);
+        self.scenes.render(&mut self.world);
+        self.world.render();
+        self.world.end_frame();
+    }
+}
+
+impl App {
+    fn new(window: Arc<Window>) -> Self {
+        let mut world = DefaultWorld::new(window);
+        world.camera.transform.pos = Vec3::new(-8.0, 2.0, 0.0);
+        let mut scenes = SceneStack::new();
+        scenes.apply(SceneCommand::push(Menu), &mut world);
+        Self { world, scenes }
+    }
+}
+
+/// Enter: start the game, Escape: exit.
+struct Menu;
+
+impl Scene<DefaultWorld> for Menu {
+    fn update(&mut self, world: &mut DefaultWorld) -> SceneCommand<DefaultWorld> {
+        let keys = world.input.keys();
+        if keys.just_pressed(KeyCode::Enter) {
+            let fade = TransitionKind::FadeThrough(Color::BLACK);
+            return SceneCommand::replace(Game { angle: 0.0 }).with_transition(
+                fade,
+                Duration::from_millis(800),
+                Easing::EaseInOut,
+            );
+        }
+        if keys.just_pressed(KeyCode::Escape) {
+            return SceneCommand::Pop;
+        }
+        SceneCommand::None
+    }
+
+    fn render(&mut self, world: &mut DefaultWorld) {
+        let rotation = Quat::from_rotation_y(world.time.total().as_secs_f32());
+        let transform = Transform {
+            rotation,
+            ..Transform::new(0.0, 0.0, 0.0)
+        };
+        world
+            .color_renderer
+            .borrow_mut()
+            .draw_cubes(&[(transform, Color::RED)]);
+    }
+}
+
+/// Escape: pause, Tab: wipe back to the menu.
+struct Game {
+    angle: f32,
+}
+
+impl Scene<DefaultWorld> for Game {
+    fn update(&mut self, world: &mut DefaultWorld) -> SceneCommand<DefaultWorld> {
+        self.angle += world.time.delta().as_secs_f32();
+        let keys = world.input.keys();
+        if keys.just_pressed(KeyCode::Escape) {
+            return SceneCommand::push(Pause);
+        }
+        if keys.just_pressed(KeyCode::Tab) {
+            let wipe = TransitionKind::Wipe {
+                direction: glam::Vec2::X,
+                softness: 0.1,
+            };
+            return SceneCommand::replace_all(Menu).with_transition(
+                wipe,
+                Duration::from_millis(600),
+                Easing::EaseOutCubic,
+            );
+        }
+        SceneCommand::None
+    }
+
+    fn render(&mut self, world: &mut DefaultWorld) {
+        let cubes: Vec<(Transform, Color)> = (0..8)
+            .map(|i| {
+                let angle = self.angle + i as f32 * std::f32::consts::TAU / 8.0;
+                let transform = Transform::new(angle.cos() * 3.0, 0.0, angle.sin() * 3.0);
+                (transform, Color::from_hsv(i as f64 * 45.0, 1.0, 1.0))
+            })
+            .collect();
+        world.color_renderer.borrow_mut().draw_cubes(&cubes);
+    }
+}
+
+/// Overlay on top of the game, which is still rendered but not updated. Escape: resume.
+struct Pause;
+
+impl Scene<DefaultWorld> for Pause {
+    fn update(&mut self, world: &mut DefaultWorld) -> SceneCommand<DefaultWorld> {
+        if world.input.keys().just_pressed(KeyCode::Escape) {
+            return SceneCommand::Pop;
+        }
+        SceneCommand::None
+    }
+
+    fn render(&mut self, world: &mut DefaultWorld) {
+        world
+            .gizmos
+            .borrow_mut()
+            .draw_cube(Vec3::ZERO, 7.0, Color::WHITE);
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,262 @@
+//! Stress scenes to spot performance regressions in batching, layout and buffer uploads.
+//! Switch scenes with 1-4, the render stats and the fps are shown in the egui windows.
+//! The same workloads without rendering are measured by `cargo bench --bench stress`.
+
+use std::{rc::Rc, sync::Arc};
+
+use glam::{vec2, vec3, Vec3};
+use tgf::{
+    create_white_px_texture, leak, show,
+    ui::{
+        div, font::SdfFontRef, Axis, Corners, Div, IntoElementBox, Len, SdfFont, TextSection,
+        UiEffect, VerticalAlign,
+    },
+    utils::camera_controllers::FlyCamController,
+    Aabb, AlphaSdfParams, AppT, BindableTexture, Camera3dGR, Color, DefaultWorld, KeyCode,
+    RenderFormat, Runner, SdfSprite, SdfSpriteRenderer, Transform, Window,
+};
+
+pub fn main() {
+    let runner = Runner::new(Default::default());
+    let mut app = App::new(runner.window());
+    runner.run(&mut app).unwrap();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StressScene {
+    /// 100k gizmo lines.
+    GizmoLines,
+    /// 50k sprites, sorted by distance every frame.
+    Sprites,
+    /// 10k rounded rects in the ui.
+    UiElements,
+    /// Deeply nested divs with long paragraphs.
+    TextLayout,
+}
+
+struct App {
+    world: DefaultWorld,
+    scene: StressScene,
+    font: SdfFontRef,
+    camera_gr: Camera3dGR,
+    sprite_renderer: SdfSpriteRenderer,
+    sprites: Vec<SdfSprite>,
+}
+
+impl AppT for App {
+    fn receive_window_event(&mut self, event: &tgf::WindowEvent) {
+        self.world.receive_window_event(event);
+    }
+
+    fn update(&mut self, cb: &mut tgf::RunnerCallbacks) {
+        self.world.start_frame();
+        self.main_update(cb);
+        self.render();
+        self.world.end_frame();
+    }
+}
+
+impl App {
+    fn new(window: Arc<Window>) -> Self {
+        let mut world = DefaultWorld::new(window);
+        let font = SdfFont::from_bytes(
+            include_bytes!("../assets/MarkoOne-Regular.ttf"),
+            &world.ctx.device,
+            &world.ctx.queue,
+        );
+        let camera_gr = Camera3dGR::new(&world.ctx, &world.camera);
+        let sprite_renderer = SdfSpriteRenderer::new(
+            &world.ctx,
+            &camera_gr,
+            RenderFormat::HDR_MSAA4,
+            &mut world.shader_cache,
+        );
+        let texture = Rc::new(create_white_px_texture(&world.ctx.device, &world.ctx.queue));
+        let sprites = sprites_50k(texture);
+        Self {
+            world,
+            scene: StressScene::GizmoLines,
+            font: leak(font),
+            camera_gr,
+            sprite_renderer,
+            sprites,
+        }
+    }
+
+    fn main_update(&mut self, cb: &mut tgf::RunnerCallbacks) {
+        if self.world.input.close_requested() {
+            cb.exit("exit");
+        }
+        let keys = self.world.input.keys();
+        for (key, scene) in [
+            (KeyCode::Digit1, StressScene::GizmoLines),
+            (KeyCode::Digit2, StressScene::Sprites),
+            (KeyCode::Digit3, StressScene::UiElements),
+            (KeyCode::Digit4, StressScene::TextLayout),
+        ] {
+            if keys.just_pressed(key) {
+                self.scene = scene;
+            }
+        }
+        let scene = self.scene;
+        show!(scene);
+
+        let total = self.world.time.total().as_secs_f32();
+        match self.scene {
+            StressScene::GizmoLines => {
+                let mut gizmos = self.world.gizmos.borrow_mut();
+                for i in 0..100_000 {
+                    let pos = vec3((i % 316) as f32, 0.0, (i / 316) as f32) * 0.5;
+                    let height = 1.0 + (pos.x * 0.2 + total).sin() * (pos.z * 0.1).cos();
+                    let color = Color::from_hsv((i % 360) as f64, 0.8, 1.0);
+                    gizmos.draw_line(pos, pos + Vec3::Y * height, color);
+                }
+            }
+            StressScene::Sprites => {
+                let mut sprites: Vec<&SdfSprite> = self.sprites.iter().collect();
+                self.sprite_renderer
+                    .prepare(&mut sprites, &self.world.camera, &self.world.time);
+            }
+            StressScene::UiElements => self.world.ui.set_element(grid_10k(total).store()),
+            StressScene::TextLayout => self
+                .world
+                .ui
+                .set_element(nested_text(self.font, 24).store()),
+        }
+        if !matches!(
+            self.scene,
+            StressScene::UiElements | StressScene::TextLayout
+        ) {
+            self.world.ui.set_element(div().store());
+        }
+        if self.scene != StressScene::Sprites {
+            self.sprite_renderer
+                .prepare(&mut [], &self.world.camera, &self.world.time);
+        }
+
+        let cam_controller = FlyCamController {
+            speed: 20.0,
+            angle_speed: 2.0,
+            ..Default::default()
+        };
+        cam_controller.update(&self.world.input, &self.world.time, &mut self.world.camera);
+    }
+
+    /// Like [`DefaultWorld::render`] without post processing, but with a sprite pass.
+    fn render(&mut self) {
+        let world = &mut self.world;
+        tgf::global_vals_window(&mut world.egui.context());
+        world.show_fps();
+        world.show_render_stats();
+        let sprites = self.sprite_renderer.stats();
+        show!(sprites);
+
+        let mut encoder = world.ctx.device.create_command_encoder(&Default::default());
+        world.prepare(&mut encoder);
+        self.camera_gr.prepare(&world.ctx.queue, &world.camera);
+
+        let color_renderer = world.color_renderer.borrow();
+        let gizmos = world.gizmos.borrow();
+        let ui_renderer = world.ui_renderer.borrow();
+        let (surface, view) = world.ctx.new_surface_texture_and_view();
+        let mut pass = world
+            .screen_textures
+            .new_hdr_target_render_pass(&mut encoder, Color::DARKGREY * 0.1);
+        color_renderer.render(&mut pass, &world.uniforms);
+        gizmos.render(&mut pass, &world.uniforms);
+        self.sprite_renderer.render(&mut pass, &self.camera_gr);
+        drop(pass);
+
+        world.tone_mapping.borrow_mut().apply(
+            &mut encoder,
+            world.screen_textures.resolved().bind_group(),
+            &view,
+        );
+        let mut pass = ui_renderer.new_render_pass(&mut encoder, &view);
+        ui_renderer.render_batches(
+            &mut pass,
+            &world.ui_gr,
+            &world.ui.batches.batches,
+            &world.uniforms,
+            Color::WHITE,
+        );
+        drop(pass);
+        world.egui.render(&mut encoder, &view);
+
+        world.ctx.queue.submit([encoder.finish()]);
+        surface.present();
+    }
+}
+
+fn sprites_50k(texture: Rc<BindableTexture>) -> Vec<SdfSprite> {
+    (0..50_000)
+        .map(|i| {
+            let pos = vec3((i % 250) as f32, (i % 7) as f32 * 0.3, (i / 250) as f32);
+            SdfSprite {
+                texture: texture.clone(),
+                transform: Transform {
+                    position: pos * 0.4,
+                    ..Default::default()
+                },
+                offset: vec2(0.0, 0.0),
+                size: vec2(0.3, 0.3),
+                uv: Aabb::UNIT,
+                color: Color::from_hsv((i % 360) as f64, 0.7, 1.0),
+                sdf_params: AlphaSdfParams::default(),
+                layer: 0,
+                order_in_layer: 0,
+                drivers: Default::default(),
+            }
+        })
+        .collect()
+}
+
+/// 100 rows of 100 rounded rects, the same as in `benches/stress.rs` but animated.
+fn grid_10k(total: f32) -> Div {
+    let mut grid = div().full();
+    for row in 0..100 {
+        let mut r = div().style(|s| {
+            s.axis = Axis::X;
+            s.gap = 2.0;
+        });
+        for col in 0..100 {
+            let hue = (row * 100 + col) as f64 * 0.036 + total as f64 * 60.0;
+            r.push(div().style(|s| {
+                s.width = Some(Len::Px(8.0));
+                s.height = Some(Len::Px(8.0));
+                s.color = Color::from_hsv(hue % 360.0, 0.8, 0.9);
+                s.border.radius = Corners::all(3.0);
+            }));
+        }
+        grid.push(r);
+    }
+    grid
+}
+
+/// `depth` nested divs, each with a paragraph of text that wraps in the shrinking width.
+fn nested_text(font: SdfFontRef, depth: usize) -> Div {
+    let mut inner = div();
+    for level in (0..depth).rev() {
+        inner = div()
+            .style(|s| {
+                s.padding.left = 8.0;
+                s.color = Color::from_hsv(level as f64 * 15.0, 0.3, 0.2);
+            })
+            .child(TextSection {
+                string: format!("Level {level}: {}", LOREM).into(),
+                font: font.into(),
+                color: Color::WHITE,
+                font_size: 14.0,
+                shadow_intensity: 0.0,
+                hinting: None,
+                vertical_align: VerticalAlign::Baseline,
+                effect: UiEffect::NONE,
+            })
+            .child(inner);
+    }
+    inner
+}
+
+const LOREM: &str = "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do eiusmod tempor \
+incididunt ut labore et dolore magna aliqua. Ut enim ad minim veniam, quis nostrud exercitation ullamco \
+laboris nisi ut aliquip ex ea commodo consequat.";
@@ -67,3 +67,29 @@ pub fn derive_lerp(input: TokenStream) -> TokenStream {
     )
     .into()
 }
+
+/// Derives `EditableEnum` for enums without fields, such that they can be used with the `edit!` macro.
+/// The trait `EditableEnum` needs to be in scope, just like `Lerp` for the Lerp derive.
+#[proc_macro_derive(EditableEnum)]
+pub fn derive_editable_enum(input: TokenStream) -> TokenStream {
+    let derive_input: syn::DeriveInput = syn::parse(input).unwrap();
+    let enu = match &derive_input.data {
+        syn::Data::Enum(e) => e,
+        _ => panic!("EditableEnum can only be derived for enums"),
+    };
+    let enum_ident = derive_input.ident;
+    let variants = enu.variants.iter().map(|variant| {
+        if !matches!(variant.fields, syn::Fields::Unit) {
+            panic!("EditableEnum can only be derived for enums without fields");
+        }
+        let ident = &variant.ident;
+        quote!(#enum_ident::#ident)
+    });
+
+    quote!(
+        impl EditableEnum for #enum_ident {
+            const VARIANTS: &'static [Self] = &[#(#variants),*];
+        }
+    )
+    .into()
+}
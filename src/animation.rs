@@ -0,0 +1,386 @@
+use ahash::{AHashMap, AHashSet};
+
+use crate::{KeyFrames, Lerp};
+
+/// [`KeyFrames`] played over a duration, optionally looping.
+///
+/// The times of the key frames are in 0.0..=1.0 (see [`KeyFrames::normalize_time`]) and scaled to the duration.
+#[derive(Debug, Clone)]
+pub struct AnimationClip<T: Clone + Lerp> {
+    pub key_frames: KeyFrames<T>,
+    /// in seconds
+    pub duration: f32,
+    pub looping: bool,
+}
+
+impl<T: Clone + Lerp> AnimationClip<T> {
+    pub fn new(key_frames: KeyFrames<T>, duration: f32, looping: bool) -> Self {
+        Self {
+            key_frames,
+            duration,
+            looping,
+        }
+    }
+
+    /// A clip that always holds the same value, e.g. for idle states.
+    pub fn constant(value: T) -> Self {
+        Self::new(
+            KeyFrames::new(vec![(0.0, value, Default::default())]),
+            1.0,
+            true,
+        )
+    }
+
+    /// Progress in 0.0..=1.0 after `time` seconds of playing.
+    pub fn normalized_time(&self, time: f32) -> f32 {
+        if self.duration <= 0.0 {
+            return 1.0;
+        }
+        let t = time / self.duration;
+        if self.looping {
+            t.fract()
+        } else {
+            t.min(1.0)
+        }
+    }
+
+    pub fn sample(&self, time: f32) -> T {
+        self.key_frames.sample(self.normalized_time(time))
+    }
+}
+
+/// Index of a state in an [`AnimationStateMachine`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StateId(usize);
+
+#[derive(Debug, Clone)]
+pub struct AnimationState<T: Clone + Lerp> {
+    pub name: String,
+    pub clip: AnimationClip<T>,
+    /// Playback speed of the clip, 1.0 by default.
+    pub speed: f32,
+}
+
+/// Condition on the [`AnimationParams`] for a [`Transition`] to be taken.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    Always,
+    Bool(String, bool),
+    FloatGreater(String, f32),
+    FloatLess(String, f32),
+    /// Consumed when the transition is taken, see [`AnimationParams::set_trigger`].
+    Trigger(String),
+    All(Vec<Condition>),
+}
+
+impl Condition {
+    fn is_met(&self, params: &AnimationParams) -> bool {
+        match self {
+            Condition::Always => true,
+            Condition::Bool(name, value) => params.bool(name) == *value,
+            Condition::FloatGreater(name, value) => params.float(name) > *value,
+            Condition::FloatLess(name, value) => params.float(name) < *value,
+            Condition::Trigger(name) => params.triggers.contains(name),
+            Condition::All(conditions) => conditions.iter().all(|c| c.is_met(params)),
+        }
+    }
+
+    fn consume_triggers(&self, params: &mut AnimationParams) {
+        match self {
+            Condition::Trigger(name) => {
+                params.triggers.remove(name);
+            }
+            Condition::All(conditions) => {
+                for c in conditions {
+                    c.consume_triggers(params);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Transition {
+    /// None means the transition can be taken from any state.
+    pub from: Option<StateId>,
+    pub to: StateId,
+    pub condition: Condition,
+    /// Seconds over which the values of the old and new state are blended.
+    pub blend_duration: f32,
+    /// If set, the transition is only taken once the current state reached this normalized time (0.0..=1.0),
+    /// e.g. 1.0 to let a non-looping clip finish first.
+    pub exit_time: Option<f32>,
+}
+
+impl Transition {
+    pub fn new(from: StateId, to: StateId) -> Self {
+        Self {
+            from: Some(from),
+            to,
+            condition: Condition::Always,
+            blend_duration: 0.0,
+            exit_time: None,
+        }
+    }
+
+    pub fn from_any(to: StateId) -> Self {
+        Self {
+            from: None,
+            ..Self::new(to, to)
+        }
+    }
+
+    pub fn when(mut self, condition: Condition) -> Self {
+        self.condition = condition;
+        self
+    }
+
+    pub fn blend(mut self, duration: f32) -> Self {
+        self.blend_duration = duration;
+        self
+    }
+
+    pub fn exit_time(mut self, normalized_time: f32) -> Self {
+        self.exit_time = Some(normalized_time);
+        self
+    }
+}
+
+/// Parameters set from game code, that the conditions of transitions are checked against.
+#[derive(Debug, Clone, Default)]
+pub struct AnimationParams {
+    floats: AHashMap<String, f32>,
+    bools: AHashMap<String, bool>,
+    triggers: AHashSet<String>,
+}
+
+impl AnimationParams {
+    pub fn set_float(&mut self, name: &str, value: f32) {
+        self.floats.insert(name.to_string(), value);
+    }
+
+    pub fn set_bool(&mut self, name: &str, value: bool) {
+        self.bools.insert(name.to_string(), value);
+    }
+
+    /// Triggers stay set until a transition with a [`Condition::Trigger`] for them is taken.
+    pub fn set_trigger(&mut self, name: &str) {
+        self.triggers.insert(name.to_string());
+    }
+
+    pub fn reset_trigger(&mut self, name: &str) {
+        self.triggers.remove(name);
+    }
+
+    /// 0.0 if not set.
+    pub fn float(&self, name: &str) -> f32 {
+        self.floats.get(name).copied().unwrap_or(0.0)
+    }
+
+    /// false if not set.
+    pub fn bool(&self, name: &str) -> bool {
+        self.bools.get(name).copied().unwrap_or(false)
+    }
+}
+
+#[derive(Debug, Clone)]
+enum BlendFrom<T> {
+    State {
+        state: StateId,
+        time: f32,
+    },
+    /// a transition started while another blend was still running, so blend from the value at that moment.
+    Frozen(T),
+}
+
+#[derive(Debug, Clone)]
+struct Blend<T> {
+    from: BlendFrom<T>,
+    elapsed: f32,
+    duration: f32,
+}
+
+/// Plays [`AnimationClip`]s of any lerpable value (transforms, colors, ui properties, ...) and transitions
+/// between them based on parameters set from game code, blending the values during transitions.
+///
+/// ```rust,ignore
+/// let mut sm = AnimationStateMachine::new();
+/// let idle = sm.add_state("idle", idle_clip);
+/// let walk = sm.add_state("walk", walk_clip);
+/// sm.add_transition(Transition::new(idle, walk).when(Condition::FloatGreater("speed".into(), 0.1)).blend(0.2));
+/// sm.add_transition(Transition::new(walk, idle).when(Condition::FloatLess("speed".into(), 0.1)).blend(0.2));
+/// // every frame:
+/// sm.params.set_float("speed", speed);
+/// sm.update(time.delta().as_secs_f32());
+/// let value = sm.sample();
+/// ```
+///
+/// The first added state is the initial state.
+#[derive(Debug, Clone)]
+pub struct AnimationStateMachine<T: Clone + Lerp> {
+    states: Vec<AnimationState<T>>,
+    transitions: Vec<Transition>,
+    pub params: AnimationParams,
+    current: StateId,
+    /// seconds the current state has been playing, scaled by its speed.
+    time: f32,
+    blend: Option<Blend<T>>,
+}
+
+impl<T: Clone + Lerp> Default for AnimationStateMachine<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone + Lerp> AnimationStateMachine<T> {
+    pub fn new() -> Self {
+        Self {
+            states: vec![],
+            transitions: vec![],
+            params: AnimationParams::default(),
+            current: StateId(0),
+            time: 0.0,
+            blend: None,
+        }
+    }
+
+    pub fn add_state(&mut self, name: impl Into<String>, clip: AnimationClip<T>) -> StateId {
+        self.states.push(AnimationState {
+            name: name.into(),
+            clip,
+            speed: 1.0,
+        });
+        StateId(self.states.len() - 1)
+    }
+
+    /// Transitions are checked in the order they were added, the first one whose condition is met is taken.
+    pub fn add_transition(&mut self, transition: Transition) {
+        self.transitions.push(transition);
+    }
+
+    pub fn state(&self, id: StateId) -> &AnimationState<T> {
+        &self.states[id.0]
+    }
+
+    pub fn state_mut(&mut self, id: StateId) -> &mut AnimationState<T> {
+        &mut self.states[id.0]
+    }
+
+    pub fn state_by_name(&self, name: &str) -> Option<StateId> {
+        self.states.iter().position(|s| s.name == name).map(StateId)
+    }
+
+    pub fn current_state(&self) -> StateId {
+        self.current
+    }
+
+    pub fn current_state_name(&self) -> &str {
+        &self.states[self.current.0].name
+    }
+
+    /// Progress of the current state in 0.0..=1.0.
+    pub fn normalized_time(&self) -> f32 {
+        self.states[self.current.0].clip.normalized_time(self.time)
+    }
+
+    pub fn is_blending(&self) -> bool {
+        self.blend.is_some()
+    }
+
+    /// Switches to the state immediately (or blended over `blend_duration`), ignoring transitions.
+    pub fn play(&mut self, state: StateId, blend_duration: f32) {
+        self.start_transition(state, blend_duration);
+    }
+
+    /// Advances time and takes at most one transition.
+    pub fn update(&mut self, delta: f32) {
+        assert!(
+            !self.states.is_empty(),
+            "AnimationStateMachine has no states"
+        );
+        self.time += delta * self.states[self.current.0].speed;
+        if let Some(blend) = &mut self.blend {
+            blend.elapsed += delta;
+            if let BlendFrom::State { state, time } = &mut blend.from {
+                *time += delta * self.states[state.0].speed;
+            }
+            if blend.elapsed >= blend.duration {
+                self.blend = None;
+            }
+        }
+
+        let normalized_time = self.normalized_time();
+        let taken = self.transitions.iter().find(|t| {
+            t.from
+                .map_or(t.to != self.current, |from| from == self.current)
+                && t.exit_time.is_none_or(|exit| normalized_time >= exit)
+                && t.condition.is_met(&self.params)
+        });
+        if let Some(transition) = taken.cloned() {
+            transition.condition.consume_triggers(&mut self.params);
+            self.start_transition(transition.to, transition.blend_duration);
+        }
+    }
+
+    fn start_transition(&mut self, to: StateId, blend_duration: f32) {
+        let from = if self.blend.is_some() {
+            BlendFrom::Frozen(self.sample())
+        } else {
+            BlendFrom::State {
+                state: self.current,
+                time: self.time,
+            }
+        };
+        self.blend = (blend_duration > 0.0).then_some(Blend {
+            from,
+            elapsed: 0.0,
+            duration: blend_duration,
+        });
+        self.current = to;
+        self.time = 0.0;
+    }
+
+    /// The value of the current state, blended with the previous state during transitions.
+    pub fn sample(&self) -> T {
+        let current = self.states[self.current.0].clip.sample(self.time);
+        let Some(blend) = &self.blend else {
+            return current;
+        };
+        let from = match &blend.from {
+            BlendFrom::State { state, time } => self.states[state.0].clip.sample(*time),
+            BlendFrom::Frozen(value) => value.clone(),
+        };
+        from.lerp(&current, (blend.elapsed / blend.duration).clamp(0.0, 1.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AnimationClip, AnimationStateMachine, Condition, Transition};
+
+    #[test]
+    fn trigger_transition_blends() {
+        let mut sm = AnimationStateMachine::<f32>::new();
+        let idle = sm.add_state("idle", AnimationClip::constant(0.0));
+        let jump = sm.add_state("jump", AnimationClip::constant(10.0));
+        sm.add_transition(
+            Transition::new(idle, jump)
+                .when(Condition::Trigger("jump".into()))
+                .blend(1.0),
+        );
+
+        sm.update(0.5);
+        assert_eq!(sm.current_state(), idle);
+        sm.params.set_trigger("jump");
+        sm.update(0.5);
+        assert_eq!(sm.current_state_name(), "jump");
+        assert_eq!(sm.sample(), 0.0);
+        sm.update(0.5);
+        assert_eq!(sm.sample(), 5.0);
+        sm.update(0.5);
+        assert_eq!(sm.sample(), 10.0);
+        assert!(!sm.is_blending());
+    }
+}
@@ -1,17 +1,30 @@
 use std::sync::Arc;
 
+use glam::DVec2;
+use log::warn;
 use winit::{
     dpi::PhysicalSize,
-    event::{Event, WindowEvent},
-    event_loop::EventLoop,
+    event::{DeviceEvent, Event, WindowEvent},
+    event_loop::{DeviceEvents, EventLoop, EventLoopBuilder, EventLoopProxy},
     monitor::MonitorHandle,
     platform::x11::WindowBuilderExtX11,
-    window::{Window, WindowBuilder},
+    window::{Window, WindowBuilder, WindowLevel},
 };
 
 pub trait AppT {
     fn receive_window_event(&mut self, event: &WindowEvent);
 
+    /// Events of input devices that are not tied to the window, e.g. raw mouse motion for
+    /// [`crate::Input::receive_device_event`]. Only sent while the window is focused.
+    fn receive_device_event(&mut self, _event: &DeviceEvent) {}
+
+    /// For [`ClickThrough::OutsideOfUi`]: whether the window should catch the cursor at `cursor_pos` in physical
+    /// pixels of the window, e.g. via [`crate::ui::ElementContext::catches_cursor`]. The position is estimated from
+    /// raw mouse motion while the cursor goes through the window, so it can be a bit off.
+    fn catches_cursor(&self, _cursor_pos: DVec2) -> bool {
+        true
+    }
+
     fn update(&mut self, cb: &mut RunnerCallbacks);
 }
 
@@ -20,6 +33,41 @@ pub struct WindowConfig {
     pub width: u32,
     pub height: u32,
     pub fullscreen: Option<MonitorPreference>,
+    pub redraw: RedrawMode,
+    /// Lets the desktop shine through where the app renders transparent pixels. Also needs a surface with
+    /// a transparent alpha mode, see [`crate::GraphicsContextConfig::alpha_mode`].
+    pub transparent: bool,
+    /// Keeps the window above all normal windows, e.g. for desktop widgets and overlays.
+    pub always_on_top: bool,
+    /// The title bar and border of the os. Undecorated windows can be moved by dragging elements
+    /// registered with [`crate::ui::ElementContext::window_drag_area`].
+    pub decorations: bool,
+    pub click_through: ClickThrough,
+}
+
+/// Whether clicks on the window go to the windows behind it instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClickThrough {
+    #[default]
+    Never,
+    /// The window never receives cursor events, e.g. for a hud over another app.
+    Always,
+    /// Only elements with an id catch the cursor, see [`crate::ui::ElementContext::update_window`].
+    ///
+    /// Click-through windows get no cursor events from the os, so the [`Runner`] follows the raw mouse motion
+    /// and catches the cursor again when [`AppT::catches_cursor`] is true at the new position.
+    OutsideOfUi,
+}
+
+/// When [`AppT::update`] is called.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RedrawMode {
+    /// Redraw as fast as the present mode allows.
+    #[default]
+    Continuous,
+    /// Only redraw after window events, wake ups from an [`EventLoopWaker`] or if the last update
+    /// called [`RunnerCallbacks::request_redraw`]. Saves power for tools and menus that idle most of the time.
+    Reactive,
 }
 
 pub enum MonitorPreference {
@@ -35,9 +83,47 @@ impl WindowConfig {
             width: 1200,
             height: 700,
             fullscreen: None,
+            redraw: RedrawMode::Continuous,
+            transparent: false,
+            always_on_top: false,
+            decorations: true,
+            click_through: ClickThrough::Never,
         }
     }
 
+    /// A transparent, undecorated window above all other windows, for desktop widgets and overlays.
+    pub fn overlay(mut self) -> Self {
+        self.transparent = true;
+        self.always_on_top = true;
+        self.decorations = false;
+        self
+    }
+
+    pub fn transparent(mut self) -> Self {
+        self.transparent = true;
+        self
+    }
+
+    pub fn always_on_top(mut self) -> Self {
+        self.always_on_top = true;
+        self
+    }
+
+    pub fn undecorated(mut self) -> Self {
+        self.decorations = false;
+        self
+    }
+
+    pub fn click_through(mut self, click_through: ClickThrough) -> Self {
+        self.click_through = click_through;
+        self
+    }
+
+    pub fn reactive(mut self) -> Self {
+        self.redraw = RedrawMode::Reactive;
+        self
+    }
+
     pub fn fullscreen(mut self) -> Self {
         self.fullscreen = Some(MonitorPreference::Primary);
         self
@@ -59,9 +145,54 @@ impl Default for WindowConfig {
     }
 }
 
+/// Sent to the event loop by an [`EventLoopWaker`].
+pub enum RunnerEvent {
+    Redraw,
+    Callback(Box<dyn FnOnce() + Send>),
+}
+
+/// Wakes up the event loop of a [`Runner`] from other threads or async tasks, e.g. when a background asset load is done
+/// while the app idles in [`RedrawMode::Reactive`].
+///
+/// ```rust,ignore
+/// let waker = runner.waker();
+/// std::thread::spawn(move || {
+///     let level = load_level();
+///     *shared_level.lock().unwrap() = Some(level);
+///     waker.request_redraw();
+/// });
+/// ```
+#[derive(Clone)]
+pub struct EventLoopWaker {
+    proxy: EventLoopProxy<RunnerEvent>,
+}
+
+impl EventLoopWaker {
+    /// Schedules a call to [`AppT::update`]. Returns false if the event loop does not exist anymore.
+    pub fn request_redraw(&self) -> bool {
+        self.proxy.send_event(RunnerEvent::Redraw).is_ok()
+    }
+
+    /// Runs `f` on the main thread before the next update, which is scheduled too.
+    /// Returns false if the event loop does not exist anymore.
+    pub fn run_on_main_thread(&self, f: impl FnOnce() + Send + 'static) -> bool {
+        self.proxy
+            .send_event(RunnerEvent::Callback(Box::new(f)))
+            .is_ok()
+    }
+}
+
+impl std::fmt::Debug for EventLoopWaker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventLoopWaker").finish()
+    }
+}
+
 pub struct Runner {
-    event_loop: EventLoop<()>,
+    event_loop: EventLoop<RunnerEvent>,
     window: Arc<Window>,
+    redraw: RedrawMode,
+    click_through: ClickThrough,
 }
 
 impl Runner {
@@ -69,25 +200,50 @@ impl Runner {
         self.window.clone()
     }
 
+    /// Can be cloned and sent to other threads to wake up the event loop.
+    pub fn waker(&self) -> EventLoopWaker {
+        EventLoopWaker {
+            proxy: self.event_loop.create_proxy(),
+        }
+    }
+
     pub fn new(config: WindowConfig) -> Self {
+        let redraw = config.redraw;
+        let click_through = config.click_through;
         let (window, event_loop) = create_window_and_event_loop(config);
         let window = Arc::new(window);
+        if click_through == ClickThrough::OutsideOfUi {
+            // mouse motion is reported even while the window lets the cursor through.
+            event_loop.listen_device_events(DeviceEvents::Always);
+        }
 
-        Self { event_loop, window }
+        Self {
+            event_loop,
+            window,
+            redraw,
+            click_through,
+        }
     }
 
     pub fn run(self, app: &mut dyn AppT) -> anyhow::Result<()> {
         let window = self.window.clone();
+        let redraw = self.redraw;
+        let click_through = self.click_through;
+        // last position reported by the window, moved by the raw mouse motion while the cursor goes through.
+        let mut cursor_pos: Option<DVec2> = None;
         self.event_loop.run(move |event, window_target| {
             // check what kinds of events received:
-            match &event {
+            match event {
                 Event::NewEvents(_) => {}
                 Event::WindowEvent { window_id, event } => {
-                    if *window_id != self.window.id() {
+                    if window_id != self.window.id() {
                         return;
                     }
 
-                    app.receive_window_event(event);
+                    if let WindowEvent::CursorMoved { position, .. } = event {
+                        cursor_pos = Some(DVec2::new(position.x, position.y));
+                    }
+                    app.receive_window_event(&event);
 
                     if matches!(event, WindowEvent::RedrawRequested) {
                         //  this is called every frame:
@@ -97,13 +253,34 @@ impl Runner {
                         if let Some(reason) = cb.exit {
                             println!("Exit: {reason}");
                             window_target.exit();
-                        } else {
+                        } else if redraw == RedrawMode::Continuous || cb.redraw_requested {
                             window.request_redraw()
                         }
+                    } else if redraw == RedrawMode::Reactive {
+                        // the app should see the effect of every input.
+                        window.request_redraw()
                     }
                 }
-                Event::DeviceEvent { .. } => {}
-                Event::UserEvent(_) => {}
+                Event::DeviceEvent { event, .. } => {
+                    if let (ClickThrough::OutsideOfUi, DeviceEvent::MouseMotion { delta }) =
+                        (click_through, &event)
+                    {
+                        if let Some(pos) = &mut cursor_pos {
+                            *pos += DVec2::new(delta.0, delta.1);
+                            if app.catches_cursor(*pos) {
+                                _ = window.set_cursor_hittest(true);
+                            }
+                        }
+                    }
+                    if window.has_focus() {
+                        app.receive_device_event(&event);
+                    }
+                }
+                Event::UserEvent(RunnerEvent::Redraw) => window.request_redraw(),
+                Event::UserEvent(RunnerEvent::Callback(f)) => {
+                    f();
+                    window.request_redraw()
+                }
                 Event::Suspended => {}
                 Event::Resumed => {}
                 Event::AboutToWait => {}
@@ -115,7 +292,10 @@ impl Runner {
     }
 }
 
-fn select_monitor(event_loop: &EventLoop<()>, preference: MonitorPreference) -> MonitorHandle {
+fn select_monitor(
+    event_loop: &EventLoop<RunnerEvent>,
+    preference: MonitorPreference,
+) -> MonitorHandle {
     if let MonitorPreference::Primary = preference {
         return event_loop.primary_monitor().unwrap();
     }
@@ -140,11 +320,20 @@ fn select_monitor(event_loop: &EventLoop<()>, preference: MonitorPreference) ->
 pub struct RunnerCallbacks {
     /// String is the exit reason
     exit: Option<String>,
+    redraw_requested: bool,
 }
 
 impl RunnerCallbacks {
     fn new() -> Self {
-        Self { exit: None }
+        Self {
+            exit: None,
+            redraw_requested: false,
+        }
+    }
+
+    /// In [`RedrawMode::Reactive`], updates again right away, e.g. while an animation is playing.
+    pub fn request_redraw(&mut self) {
+        self.redraw_requested = true;
     }
 
     pub fn exit(&mut self, s: &str) {
@@ -152,8 +341,10 @@ impl RunnerCallbacks {
     }
 }
 
-pub fn create_window_and_event_loop(config: WindowConfig) -> (Window, EventLoop<()>) {
-    let event_loop = EventLoop::new().unwrap();
+pub fn create_window_and_event_loop(config: WindowConfig) -> (Window, EventLoop<RunnerEvent>) {
+    let event_loop = EventLoopBuilder::<RunnerEvent>::with_user_event()
+        .build()
+        .unwrap();
 
     // let _video_mode = monitor.video_modes().next();
     // // let size = video_mode
@@ -165,13 +356,25 @@ pub fn create_window_and_event_loop(config: WindowConfig) -> (Window, EventLoop<
         .with_visible(true)
         .with_title(config.window_name)
         .with_inner_size(size)
-        .with_resizable(true); //
-                               // .with_base_size(size)
+        .with_resizable(true)
+        .with_transparent(config.transparent)
+        .with_decorations(config.decorations)
+        .with_window_level(if config.always_on_top {
+            WindowLevel::AlwaysOnTop
+        } else {
+            WindowLevel::Normal
+        });
 
     if let Some(monitor) = config.fullscreen {
         let monitor = select_monitor(&event_loop, monitor);
         window = window.with_fullscreen(Some(winit::window::Fullscreen::Borderless(Some(monitor))));
     };
 
-    (window.build(&event_loop).unwrap(), event_loop)
+    let window = window.build(&event_loop).unwrap();
+    if config.click_through == ClickThrough::Always {
+        if let Err(err) = window.set_cursor_hittest(false) {
+            warn!("Window cannot be click-through: {err}");
+        }
+    }
+    (window, event_loop)
 }
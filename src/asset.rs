@@ -1,4 +1,10 @@
-use std::path::PathBuf;
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    hash::{Hash, Hasher},
+    marker::PhantomData,
+    rc::{Rc, Weak},
+};
 
 use image::RgbaImage;
 
@@ -27,3 +33,399 @@ impl AssetT for String {
         Ok(text)
     }
 }
+
+/// Identifies an asset in an [`AssetServer`]. Contains a generation, such that ids of unloaded assets
+/// are never confused with assets that were added later into the same slot.
+pub struct AssetId<T> {
+    index: u32,
+    generation: u32,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> AssetId<T> {
+    fn new(index: u32, generation: u32) -> Self {
+        AssetId {
+            index,
+            generation,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Clone for AssetId<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for AssetId<T> {}
+
+impl<T> PartialEq for AssetId<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index && self.generation == other.generation
+    }
+}
+
+impl<T> Eq for AssetId<T> {}
+
+impl<T> Hash for AssetId<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.index.hash(state);
+        self.generation.hash(state);
+    }
+}
+
+impl<T> Debug for AssetId<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "AssetId({}v{})", self.index, self.generation)
+    }
+}
+
+/// A strong, reference counted handle to an asset in an [`AssetServer`].
+/// As long as any strong handle exists, the asset is not unloaded by [`AssetServer::unload_unused`].
+pub struct Handle<T> {
+    id: AssetId<T>,
+    refs: Rc<()>,
+}
+
+impl<T> Handle<T> {
+    pub fn id(&self) -> AssetId<T> {
+        self.id
+    }
+
+    pub fn downgrade(&self) -> WeakHandle<T> {
+        WeakHandle {
+            id: self.id,
+            refs: Rc::downgrade(&self.refs),
+        }
+    }
+}
+
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        Handle {
+            id: self.id,
+            refs: self.refs.clone(),
+        }
+    }
+}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl<T> Debug for Handle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Handle({:?})", self.id)
+    }
+}
+
+/// A handle that does not keep the asset alive, e.g. for caches.
+pub struct WeakHandle<T> {
+    id: AssetId<T>,
+    refs: Weak<()>,
+}
+
+impl<T> WeakHandle<T> {
+    pub fn id(&self) -> AssetId<T> {
+        self.id
+    }
+
+    /// Returns None if all strong handles were dropped.
+    pub fn upgrade(&self) -> Option<Handle<T>> {
+        Some(Handle {
+            id: self.id,
+            refs: self.refs.upgrade()?,
+        })
+    }
+}
+
+impl<T> Clone for WeakHandle<T> {
+    fn clone(&self) -> Self {
+        WeakHandle {
+            id: self.id,
+            refs: self.refs.clone(),
+        }
+    }
+}
+
+impl<T> Debug for WeakHandle<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "WeakHandle({:?})", self.id)
+    }
+}
+
+#[derive(Debug)]
+pub enum AssetEvent<T> {
+    Loaded(AssetId<T>),
+    Modified(AssetId<T>),
+    Unloaded(AssetId<T>),
+}
+
+impl<T> Clone for AssetEvent<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for AssetEvent<T> {}
+
+impl<T> PartialEq for AssetEvent<T> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (AssetEvent::Loaded(a), AssetEvent::Loaded(b)) => a == b,
+            (AssetEvent::Modified(a), AssetEvent::Modified(b)) => a == b,
+            (AssetEvent::Unloaded(a), AssetEvent::Unloaded(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl<T> Eq for AssetEvent<T> {}
+
+struct Slot<T> {
+    generation: u32,
+    /// None if the slot is free.
+    asset: Option<SlotAsset<T>>,
+}
+
+struct SlotAsset<T> {
+    value: T,
+    refs: Weak<()>,
+    path: Option<String>,
+}
+
+/// Stores assets of one type in a generational arena and hands out reference counted [`Handle`]s to them.
+///
+/// Assets are not dropped automatically when the last handle is dropped, call [`AssetServer::unload_unused`]
+/// at a convenient time (e.g. between levels) to free them. For assets holding GPU resources like textures
+/// this also frees the GPU memory.
+pub struct AssetServer<T> {
+    slots: Vec<Slot<T>>,
+    free_slots: Vec<u32>,
+    paths: HashMap<String, AssetId<T>>,
+    events: Vec<AssetEvent<T>>,
+}
+
+impl<T> Default for AssetServer<T> {
+    fn default() -> Self {
+        Self {
+            slots: vec![],
+            free_slots: vec![],
+            paths: HashMap::new(),
+            events: vec![],
+        }
+    }
+}
+
+impl<T> AssetServer<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, asset: T) -> Handle<T> {
+        self.insert(asset, None)
+    }
+
+    pub(crate) fn insert(&mut self, value: T, path: Option<String>) -> Handle<T> {
+        let refs = Rc::new(());
+        let asset = SlotAsset {
+            value,
+            refs: Rc::downgrade(&refs),
+            path: path.clone(),
+        };
+        let id = match self.free_slots.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.asset = Some(asset);
+                AssetId::new(index, slot.generation)
+            }
+            None => {
+                self.slots.push(Slot {
+                    generation: 0,
+                    asset: Some(asset),
+                });
+                AssetId::new(self.slots.len() as u32 - 1, 0)
+            }
+        };
+        if let Some(path) = path {
+            self.paths.insert(path, id);
+        }
+        self.events.push(AssetEvent::Loaded(id));
+        Handle { id, refs }
+    }
+
+    fn slot_asset(&self, id: AssetId<T>) -> Option<&SlotAsset<T>> {
+        let slot = self.slots.get(id.index as usize)?;
+        if slot.generation != id.generation {
+            return None;
+        }
+        slot.asset.as_ref()
+    }
+
+    pub fn get(&self, handle: &Handle<T>) -> Option<&T> {
+        self.get_by_id(handle.id)
+    }
+
+    /// Returns None if the asset with this id was unloaded.
+    pub fn get_by_id(&self, id: AssetId<T>) -> Option<&T> {
+        self.slot_asset(id).map(|e| &e.value)
+    }
+
+    /// Marks the asset as modified, see [`AssetServer::drain_events`].
+    pub fn get_mut(&mut self, handle: &Handle<T>) -> Option<&mut T> {
+        let id = handle.id;
+        let slot = self.slots.get_mut(id.index as usize)?;
+        if slot.generation != id.generation {
+            return None;
+        }
+        let asset = slot.asset.as_mut()?;
+        self.events.push(AssetEvent::Modified(id));
+        Some(&mut asset.value)
+    }
+
+    /// Returns a strong handle to an asset that is still loaded, e.g. to get a handle back from a [`WeakHandle`] id.
+    pub fn handle(&self, id: AssetId<T>) -> Option<Handle<T>> {
+        let asset = self.slot_asset(id)?;
+        Some(Handle {
+            id,
+            refs: asset.refs.upgrade()?,
+        })
+    }
+
+    /// Returns the handle of an asset that was loaded from this path and is still in use.
+    pub fn handle_for_path(&self, path: &str) -> Option<Handle<T>> {
+        self.handle(*self.paths.get(path)?)
+    }
+
+    /// Like [`AssetServer::handle_for_path`], but also returns a new handle for an asset from this path that
+    /// has no handles left but was not unloaded yet, instead of loading it again into another slot.
+    /// Weak handles from before the asset became unused stay dead.
+    pub(crate) fn reuse_path(&mut self, path: &str) -> Option<Handle<T>> {
+        let id = *self.paths.get(path)?;
+        let slot = self.slots.get_mut(id.index as usize)?;
+        if slot.generation != id.generation {
+            return None;
+        }
+        let asset = slot.asset.as_mut()?;
+        let refs = match asset.refs.upgrade() {
+            Some(refs) => refs,
+            None => {
+                let refs = Rc::new(());
+                asset.refs = Rc::downgrade(&refs);
+                refs
+            }
+        };
+        Some(Handle { id, refs })
+    }
+
+    /// Number of assets currently loaded, including unused ones.
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free_slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drops all assets that have no strong handles left. Returns the number of unloaded assets.
+    pub fn unload_unused(&mut self) -> usize {
+        let mut unloaded: usize = 0;
+        for (index, slot) in self.slots.iter_mut().enumerate() {
+            let Some(asset) = &slot.asset else {
+                continue;
+            };
+            if asset.refs.strong_count() > 0 {
+                continue;
+            }
+            let id = AssetId::new(index as u32, slot.generation);
+            if let Some(path) = &asset.path {
+                self.paths.remove(path);
+            }
+            slot.asset = None;
+            slot.generation += 1;
+            self.free_slots.push(index as u32);
+            self.events.push(AssetEvent::Unloaded(id));
+            unloaded += 1;
+        }
+        unloaded
+    }
+
+    /// Returns all events since the last call.
+    pub fn drain_events(&mut self) -> std::vec::Drain<'_, AssetEvent<T>> {
+        self.events.drain(..)
+    }
+}
+
+impl<T: AssetT> AssetServer<T> {
+    /// Loads the asset from the path. If an asset from this path is still loaded, a handle to it is returned instead.
+    pub fn load(&mut self, path: &str) -> Result<Handle<T>, anyhow::Error> {
+        if let Some(handle) = self.reuse_path(path) {
+            return Ok(handle);
+        }
+        let asset = T::load(path)?;
+        Ok(self.insert(asset, Some(path.to_string())))
+    }
+
+    /// Loads the asset from disk again, e.g. after the file changed, and replaces the value behind all handles.
+    pub fn reload(&mut self, handle: &Handle<T>) -> Result<(), anyhow::Error> {
+        let Some(path) = self.slot_asset(handle.id).and_then(|e| e.path.clone()) else {
+            return Err(anyhow::anyhow!(
+                "{:?} was not loaded from a path",
+                handle.id
+            ));
+        };
+        let asset = T::load(&path)?;
+        if let Some(value) = self.get_mut(handle) {
+            *value = asset;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AssetEvent, AssetServer};
+
+    #[test]
+    fn unload_unused_respects_handles() {
+        let mut server: AssetServer<String> = AssetServer::new();
+        let a = server.add("a".into());
+        let b = server.add("b".into());
+        let weak_b = b.downgrade();
+        drop(b);
+
+        assert_eq!(server.unload_unused(), 1);
+        assert_eq!(server.get(&a).map(|s| s.as_str()), Some("a"));
+        assert!(weak_b.upgrade().is_none());
+        assert!(server.get_by_id(weak_b.id()).is_none());
+
+        // the freed slot is reused, but the old id stays invalid:
+        let c = server.add("c".into());
+        assert!(server.get_by_id(weak_b.id()).is_none());
+        assert_eq!(server.get(&c).map(|s| s.as_str()), Some("c"));
+
+        let events: Vec<_> = server.drain_events().collect();
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[2], AssetEvent::Unloaded(weak_b.id()));
+    }
+
+    #[test]
+    fn loading_an_unused_asset_reuses_its_slot() {
+        let path = std::env::temp_dir().join("tgf_asset_reuse_test.txt");
+        std::fs::write(&path, "text").unwrap();
+        let path = path.to_str().unwrap();
+
+        let mut server: AssetServer<String> = AssetServer::new();
+        let id = server.load(path).unwrap().id();
+        // the handle is dropped, but the asset is still loaded:
+        let handle = server.load(path).unwrap();
+        assert_eq!(handle.id(), id);
+        assert_eq!(server.len(), 1);
+        assert_eq!(server.unload_unused(), 0);
+        drop(handle);
+        assert_eq!(server.unload_unused(), 1);
+    }
+}
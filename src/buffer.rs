@@ -1,4 +1,11 @@
-use std::{borrow::Cow, marker::PhantomData};
+use std::{
+    borrow::Cow,
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
 
 use wgpu::util::{BufferInitDescriptor, DeviceExt};
 
@@ -207,6 +214,12 @@ impl<T: bytemuck::Pod + bytemuck::Zeroable> GrowableBuffer<T> {
         self.buffer_len
     }
 
+    /// Bytes written by the last [`GrowableBuffer::prepare`].
+    #[inline]
+    pub fn byte_len(&self) -> u64 {
+        (self.buffer_len * std::mem::size_of::<T>()) as u64
+    }
+
     /// updates the gpu buffer, growing it, when not having enough space for data.
     ///
     /// Todo! do not write, if empty!!
@@ -267,3 +280,283 @@ impl<T: bytemuck::Pod + bytemuck::Zeroable> GrowableBuffer<T> {
         &self.buffer
     }
 }
+
+/// Reads data of gpu buffers back to the cpu, e.g. picking ids or histogram results.
+///
+/// Keeps `frames_in_flight` staging buffers, so copies of later frames can be recorded while earlier ones are
+/// still being mapped, and the gpu is never waited on:
+///
+/// ```rust,ignore
+/// // while recording the frame:
+/// readback.copy_from_buffer(&mut encoder, picking_buffer.buffer(), 0);
+/// queue.submit([encoder.finish()]);
+/// readback.after_submit();
+/// // a few frames later:
+/// if let Some(ids) = readback.try_read(&device) { ... }
+/// ```
+pub struct ReadbackBuffer<T: bytemuck::Pod> {
+    len: usize,
+    slots: Vec<ReadbackSlot>,
+    /// counts the copies, to return the newest data first.
+    copies: u64,
+    phantom: PhantomData<T>,
+}
+
+struct ReadbackSlot {
+    staging: wgpu::Buffer,
+    state: SlotState,
+    copy_index: u64,
+    /// elements copied into the staging buffer, less than `len` if the source ends before.
+    copied: usize,
+}
+
+enum SlotState {
+    Free,
+    /// Copy is recorded, but the command buffer was not submitted yet, so mapping cannot start.
+    Copied,
+    Mapping(Arc<Mutex<MapState>>),
+}
+
+#[derive(Debug, Default)]
+struct MapState {
+    result: Option<Result<(), wgpu::BufferAsyncError>>,
+    waker: Option<Waker>,
+}
+
+impl<T: bytemuck::Pod> ReadbackBuffer<T> {
+    /// Reads `len` elements of type T per copy. With `frames_in_flight` of 2 or 3 the gpu is usually done
+    /// with a copy when the next free staging buffer is needed.
+    pub fn new(device: &wgpu::Device, len: usize, frames_in_flight: usize) -> Self {
+        let slots = (0..frames_in_flight.max(1))
+            .map(|_| ReadbackSlot {
+                staging: create_staging_buffer(device, staging_size::<T>(len)),
+                state: SlotState::Free,
+                copy_index: 0,
+                copied: 0,
+            })
+            .collect();
+        ReadbackBuffer {
+            len,
+            slots,
+            copies: 0,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Number of elements read per copy.
+    pub fn elements_per_copy(&self) -> usize {
+        self.len
+    }
+
+    /// Number of copies that were recorded but not read yet.
+    pub fn pending(&self) -> usize {
+        self.slots
+            .iter()
+            .filter(|s| !matches!(s.state, SlotState::Free))
+            .count()
+    }
+
+    /// Records a copy of `len` elements starting at byte `offset` of `source`, which needs `COPY_SRC` usage.
+    /// If `source` ends before, only the elements up to its end are copied and read.
+    ///
+    /// Returns false if all staging buffers are still in use, then nothing is copied in this frame.
+    pub fn copy_from_buffer(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        source: &wgpu::Buffer,
+        offset: u64,
+    ) -> bool {
+        let Some(slot) = self
+            .slots
+            .iter_mut()
+            .find(|s| matches!(s.state, SlotState::Free))
+        else {
+            return false;
+        };
+        slot.copied = copy_to_staging::<T>(encoder, source, offset, &slot.staging, self.len);
+        self.copies += 1;
+        slot.copy_index = self.copies;
+        slot.state = SlotState::Copied;
+        true
+    }
+
+    /// Starts mapping the staging buffers copied to this frame, call this after the encoder was submitted.
+    pub fn after_submit(&mut self) {
+        for slot in self.slots.iter_mut() {
+            if matches!(slot.state, SlotState::Copied) {
+                slot.state = SlotState::Mapping(map_staging_buffer(&slot.staging));
+            }
+        }
+    }
+
+    /// Calls `f` for every finished copy, oldest first, and frees their staging buffers.
+    /// Polls the device without blocking.
+    pub fn read_each(&mut self, device: &wgpu::Device, mut f: impl FnMut(&[T])) {
+        device.poll(wgpu::Maintain::Poll);
+        let mut finished: Vec<usize> = (0..self.slots.len())
+            .filter(|i| match &self.slots[*i].state {
+                SlotState::Mapping(state) => state.lock().unwrap().result.is_some(),
+                _ => false,
+            })
+            .collect();
+        finished.sort_by_key(|i| self.slots[*i].copy_index);
+        for i in finished {
+            let slot = &mut self.slots[i];
+            let SlotState::Mapping(state) = std::mem::replace(&mut slot.state, SlotState::Free)
+            else {
+                continue;
+            };
+            let result = state.lock().unwrap().result.take();
+            match result {
+                Some(Ok(())) => {
+                    let data = read_staging_buffer::<T>(&slot.staging, slot.copied);
+                    f(&data);
+                }
+                Some(Err(err)) => log::warn!("Could not map readback buffer: {err}"),
+                None => {}
+            }
+        }
+    }
+
+    /// The data of the newest finished copy, older finished copies are dropped.
+    pub fn try_read(&mut self, device: &wgpu::Device) -> Option<Vec<T>> {
+        let mut newest: Option<Vec<T>> = None;
+        self.read_each(device, |data| newest = Some(data.to_vec()));
+        newest
+    }
+}
+
+/// Copies `len` elements from `source` into a new staging buffer and waits for the result.
+/// If `source` ends before, only the elements up to its end are returned.
+///
+/// The future polls the device whenever it is polled itself, but also resolves if the device is polled elsewhere,
+/// e.g. by the next `queue.submit` of the render loop. So it can be awaited on an async runtime like tokio
+/// without blocking a thread on the gpu.
+pub async fn read_buffer_async<T: bytemuck::Pod>(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    source: &wgpu::Buffer,
+    offset: u64,
+    len: usize,
+) -> anyhow::Result<Vec<T>> {
+    let (staging, state, copied) = submit_readback::<T>(device, queue, source, offset, len);
+    MapFuture { device, state }.await?;
+    Ok(read_staging_buffer(&staging, copied))
+}
+
+/// Like [`read_buffer_async`], but blocks until the gpu is done.
+pub fn read_buffer_blocking<T: bytemuck::Pod>(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    source: &wgpu::Buffer,
+    offset: u64,
+    len: usize,
+) -> anyhow::Result<Vec<T>> {
+    let (staging, state, copied) = submit_readback::<T>(device, queue, source, offset, len);
+    device.poll(wgpu::Maintain::Wait);
+    let result = state.lock().unwrap().result.take();
+    match result {
+        Some(result) => result?,
+        None => anyhow::bail!("readback buffer was not mapped after waiting for the device"),
+    }
+    Ok(read_staging_buffer(&staging, copied))
+}
+
+fn submit_readback<T: bytemuck::Pod>(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    source: &wgpu::Buffer,
+    offset: u64,
+    len: usize,
+) -> (wgpu::Buffer, Arc<Mutex<MapState>>, usize) {
+    let staging = create_staging_buffer(device, staging_size::<T>(len));
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("readback"),
+    });
+    let copied = copy_to_staging::<T>(&mut encoder, source, offset, &staging, len);
+    queue.submit([encoder.finish()]);
+    let state = map_staging_buffer(&staging);
+    (staging, state, copied)
+}
+
+struct MapFuture<'a> {
+    device: &'a wgpu::Device,
+    state: Arc<Mutex<MapState>>,
+}
+
+impl<'a> Future for MapFuture<'a> {
+    type Output = Result<(), wgpu::BufferAsyncError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.device.poll(wgpu::Maintain::Poll);
+        let mut state = self.state.lock().unwrap();
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Copy sizes need to be a multiple of [`wgpu::COPY_BUFFER_ALIGNMENT`].
+fn staging_size<T>(len: usize) -> u64 {
+    let size = (std::mem::size_of::<T>() * len) as u64;
+    size.next_multiple_of(wgpu::COPY_BUFFER_ALIGNMENT)
+        .max(wgpu::COPY_BUFFER_ALIGNMENT)
+}
+
+/// Returns the number of whole elements that are copied. Staging buffers are reused, so the bytes after them
+/// can be left over from an earlier copy and must not be read.
+fn copy_to_staging<T>(
+    encoder: &mut wgpu::CommandEncoder,
+    source: &wgpu::Buffer,
+    offset: u64,
+    staging: &wgpu::Buffer,
+    len: usize,
+) -> usize {
+    let size = staging_size::<T>(len).min(source.size().saturating_sub(offset));
+    // copies need to be a multiple of 4 bytes.
+    let size = size - size % wgpu::COPY_BUFFER_ALIGNMENT;
+    if size > 0 {
+        encoder.copy_buffer_to_buffer(source, offset, staging, 0, size);
+    }
+    (size as usize / std::mem::size_of::<T>().max(1)).min(len)
+}
+
+fn create_staging_buffer(device: &wgpu::Device, size: u64) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("readback staging buffer"),
+        size,
+        usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
+
+fn map_staging_buffer(staging: &wgpu::Buffer) -> Arc<Mutex<MapState>> {
+    let state = Arc::new(Mutex::new(MapState::default()));
+    let callback_state = state.clone();
+    staging
+        .slice(..)
+        .map_async(wgpu::MapMode::Read, move |result| {
+            let mut state = callback_state.lock().unwrap();
+            state.result = Some(result);
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        });
+    state
+}
+
+/// Reads the mapped staging buffer and unmaps it again.
+fn read_staging_buffer<T: bytemuck::Pod>(staging: &wgpu::Buffer, len: usize) -> Vec<T> {
+    let n_bytes = std::mem::size_of::<T>() * len;
+    let data = {
+        let mapped = staging.slice(..).get_mapped_range();
+        // the mapped range is not necessarily aligned for T.
+        bytemuck::pod_collect_to_vec(&mapped[..n_bytes])
+    };
+    staging.unmap();
+    data
+}
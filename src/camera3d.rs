@@ -3,7 +3,7 @@ use std::sync::Arc;
 use glam::{vec2, vec3, Mat4, Quat, Vec2, Vec3, Vec4Swizzles};
 use winit::dpi::PhysicalSize;
 
-use crate::{GraphicsContext, Lerp, ToRaw};
+use crate::{GraphicsContext, Lerp, Resizable, ToRaw};
 
 use crate::UniformBuffer;
 
@@ -116,6 +116,12 @@ impl Camera3d {
     }
 }
 
+impl Resizable for Camera3d {
+    fn on_resize(&mut self, size: PhysicalSize<u32>, _device: &wgpu::Device) {
+        self.resize(size);
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct Camera3DTransform {
     pub pos: Vec3,
@@ -181,6 +187,12 @@ impl Camera3DTransform {
         Quat::from_axis_angle(self.right(), self.pitch)
             * Quat::from_axis_angle(Vec3::Y, -self.yaw - std::f32::consts::PI * 0.5)
     }
+
+    /// Like [`Camera3DTransform::rotation_facing_camera`], but only rotated around the y axis, so things stay upright.
+    #[inline(always)]
+    pub fn rotation_facing_camera_upright(&self) -> Quat {
+        Quat::from_axis_angle(Vec3::Y, -self.yaw - std::f32::consts::PI * 0.5)
+    }
 }
 
 #[inline(always)]
@@ -199,6 +211,18 @@ pub struct Projection {
     pub znear: f32,
     pub zfar: f32,
     pub kind: ProjectionKind,
+    /// Blends from another kind to `kind`, see [`Projection::start_kind_transition`].
+    pub transition: Option<ProjectionTransition>,
+}
+
+/// An animated switch from the projection kind `from` to the current kind of a [`Projection`].
+#[derive(Debug, Clone, Copy)]
+pub struct ProjectionTransition {
+    pub from: ProjectionKind,
+    /// In seconds.
+    pub duration: f32,
+    /// From 0.0 (only `from`) to 1.0 (only the current kind).
+    pub progress: f32,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -227,6 +251,35 @@ impl ProjectionKind {
             ProjectionKind::Orthographic { .. } => None,
         }
     }
+
+    pub fn is_orthographic(&self) -> bool {
+        matches!(self, ProjectionKind::Orthographic { .. })
+    }
+
+    /// The height of the area seen in world units, at `focus_distance` in front of the camera.
+    pub fn view_height_at(&self, focus_distance: f32) -> f32 {
+        match *self {
+            ProjectionKind::Perspective { fov_y_radians } => {
+                2.0 * focus_distance * (fov_y_radians * 0.5).tan()
+            }
+            ProjectionKind::Orthographic { y_height } => y_height,
+        }
+    }
+
+    /// The perspective (or orthographic) kind that shows objects at `focus_distance` in front of the camera
+    /// at the same size on screen as this one. Returns `self` if it already is of the requested kind.
+    pub fn with_matched_framing(&self, orthographic: bool, focus_distance: f32) -> ProjectionKind {
+        if self.is_orthographic() == orthographic {
+            return *self;
+        }
+        let y_height = self.view_height_at(focus_distance);
+        if orthographic {
+            ProjectionKind::Orthographic { y_height }
+        } else {
+            let fov_y_radians = 2.0 * (y_height * 0.5 / focus_distance.max(f32::EPSILON)).atan();
+            ProjectionKind::Perspective { fov_y_radians }
+        }
+    }
 }
 
 impl Projection {
@@ -236,9 +289,58 @@ impl Projection {
         self.aspect = width as f32 / height as f32;
     }
 
-    /// Projection Matrix
+    /// Projection Matrix. Blends between the matrices of both kinds during a transition.
     pub fn calc_matrix(&self) -> Mat4 {
-        match self.kind {
+        let matrix = self.matrix_for_kind(self.kind);
+        match self.transition {
+            Some(transition) => {
+                let t = transition.progress.clamp(0.0, 1.0);
+                let smooth = t * t * (3.0 - 2.0 * t);
+                self.matrix_for_kind(transition.from).lerp(&matrix, smooth)
+            }
+            None => matrix,
+        }
+    }
+
+    /// Switches between perspective and orthographic such that objects at `focus_distance` in front of
+    /// the camera keep their size on screen, e.g. the distance to the object the camera orbits around.
+    pub fn set_kind_preserving_framing(&mut self, orthographic: bool, focus_distance: f32) {
+        self.kind = self.kind.with_matched_framing(orthographic, focus_distance);
+        self.transition = None;
+    }
+
+    /// Like [`Projection::set_kind_preserving_framing`], but blends the projection matrices over `duration` seconds.
+    /// Objects at `focus_distance` keep their size during the whole transition. Advance it every frame with
+    /// [`Projection::update_transition`].
+    pub fn start_kind_transition(
+        &mut self,
+        orthographic: bool,
+        focus_distance: f32,
+        duration: f32,
+    ) {
+        let from = self.kind;
+        self.set_kind_preserving_framing(orthographic, focus_distance);
+        if duration > 0.0 {
+            self.transition = Some(ProjectionTransition {
+                from,
+                duration,
+                progress: 0.0,
+            });
+        }
+    }
+
+    /// Advances the transition by `dt` seconds and ends it when it is done.
+    pub fn update_transition(&mut self, dt: f32) {
+        if let Some(transition) = &mut self.transition {
+            transition.progress += dt / transition.duration;
+            if transition.progress >= 1.0 {
+                self.transition = None;
+            }
+        }
+    }
+
+    fn matrix_for_kind(&self, kind: ProjectionKind) -> Mat4 {
+        match kind {
             ProjectionKind::Perspective { fov_y_radians } => {
                 // perspective transform
                 Mat4::perspective_rh(fov_y_radians, self.aspect, self.znear, self.zfar)
@@ -272,6 +374,7 @@ impl Projection {
             znear,
             zfar,
             kind: ProjectionKind::Perspective { fov_y_radians },
+            transition: None,
         }
     }
 
@@ -283,6 +386,7 @@ impl Projection {
             znear,
             zfar,
             kind: ProjectionKind::Orthographic { y_height },
+            transition: None,
         }
     }
 }
@@ -342,6 +446,17 @@ impl Camera3dRaw {
         new
     }
 
+    /// From custom view and projection matrices that can not be expressed by a [`Camera3d`],
+    /// e.g. the mirrored camera of a [`crate::PlanarReflection`].
+    pub fn from_matrices(view_position: Vec3, view: Mat4, projection: Mat4) -> Self {
+        Camera3dRaw {
+            view_position: view_position.extend(1.0).into(),
+            view_proj: (projection * view).to_cols_array_2d(),
+            view: view.to_cols_array_2d(),
+            proj: projection.to_cols_array_2d(),
+        }
+    }
+
     fn update_view_proj(&mut self, camera: &Camera3DTransform, projection: &Projection) {
         // homogenous position:
         self.view_position = camera.pos.extend(1.0).into();
@@ -354,3 +469,47 @@ impl Camera3dRaw {
         self.proj = projection.to_cols_array_2d();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use glam::{vec3, Vec3};
+
+    use super::{Camera3DTransform, Projection};
+
+    #[test]
+    fn upright_billboards_face_the_camera_horizontally() {
+        let camera = Camera3DTransform::new(Vec3::ZERO, -0.8, 0.3);
+        let rotation = camera.rotation_facing_camera_upright();
+        assert!((rotation * Vec3::Y).abs_diff_eq(Vec3::Y, 1e-5));
+        let dir = camera.direction();
+        let towards_camera = -vec3(dir.x, 0.0, dir.z).normalize();
+        assert!((rotation * Vec3::Z).abs_diff_eq(towards_camera, 1e-5));
+        // the spherical billboard faces the camera completely:
+        assert!((camera.rotation_facing_camera() * Vec3::Z).abs_diff_eq(-dir, 1e-5));
+    }
+
+    #[test]
+    fn framing_is_kept_at_the_focus_distance() {
+        let mut projection = Projection::new_perspective(1600, 900, 0.8, 0.1, 1000.0);
+        let focus = vec3(1.0, 2.0, -10.0);
+        let screen_pos = |projection: &Projection| projection.calc_matrix().project_point3(focus);
+        let before = screen_pos(&projection);
+
+        projection.start_kind_transition(true, 10.0, 1.0);
+        for _ in 0..4 {
+            assert!(screen_pos(&projection)
+                .truncate()
+                .abs_diff_eq(before.truncate(), 1e-4));
+            projection.update_transition(0.3);
+        }
+        assert!(projection.transition.is_none());
+        assert!(projection.kind.is_orthographic());
+        assert!(screen_pos(&projection)
+            .truncate()
+            .abs_diff_eq(before.truncate(), 1e-4));
+
+        projection.set_kind_preserving_framing(false, 10.0);
+        let fov = projection.kind.fov_y_radians().unwrap();
+        assert!((fov - 0.8).abs() < 1e-5);
+    }
+}
@@ -0,0 +1,1007 @@
+//! Collision shapes for simple games: overlap tests, raycasts and swept movement.
+//!
+//! 2d shapes ([`Shape2`]) live in the xy plane, like the [`Aabb`] and [`Obb2`] they are built from.
+//! 3d shapes ([`Shape3`]) are tested against the [`Ray`]s of [`crate::Camera3d`].
+//! There is no broad phase, the queries test one pair of shapes at a time:
+//!
+//! ```rust,ignore
+//! let player = Shape2::from(Capsule2::new(pos, pos + vec2(0.0, 1.0), 0.4));
+//! let motion = player.move_and_slide(velocity * time.delta().as_secs_f32(), &walls);
+//! pos += motion;
+//! gizmos.draw_shape2(player.translated(motion), Color::GREEN);
+//! ```
+//!
+//! All shapes are a core (a point, a segment or a convex polygon / box) with a radius around it.
+//! Sweeps advance the moving shape until its distance to the other one is zero, this is exact for
+//! translations and needs only a few steps.
+
+use std::f32::consts::{PI, TAU};
+
+use glam::{vec2, Vec2, Vec3};
+
+use crate::{Aabb, Obb2, Ray};
+
+/// Shapes closer than this count as touching in sweeps.
+const CONTACT_EPSILON: f32 = 1e-4;
+
+/// Max number of steps a sweep takes towards the contact.
+const SWEEP_ITERATIONS: usize = 32;
+
+/// Distance that [`Shape2::move_and_slide`] keeps to obstacles, such that the next move does not start
+/// touching them.
+pub const SLIDE_SKIN: f32 = 1e-3;
+
+/// How often [`Shape2::move_and_slide`] changes direction along obstacles in one move.
+const SLIDE_ITERATIONS: usize = 4;
+
+/// Number of lines for a full circle in the gizmo outlines.
+const CIRCLE_SEGMENTS: usize = 32;
+
+// /////////////////////////////////////////////////////////////////////////////
+// 2d shapes
+// /////////////////////////////////////////////////////////////////////////////
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Circle {
+    pub center: Vec2,
+    pub radius: f32,
+}
+
+impl Circle {
+    pub const fn new(center: Vec2, radius: f32) -> Self {
+        Circle { center, radius }
+    }
+}
+
+/// All points within `radius` of the segment from `a` to `b`, e.g. for upright characters.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Capsule2 {
+    pub a: Vec2,
+    pub b: Vec2,
+    pub radius: f32,
+}
+
+impl Capsule2 {
+    pub const fn new(a: Vec2, b: Vec2, radius: f32) -> Self {
+        Capsule2 { a, b, radius }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Shape2 {
+    Circle(Circle),
+    Aabb(Aabb),
+    Obb(Obb2),
+    Capsule(Capsule2),
+}
+
+impl From<Circle> for Shape2 {
+    fn from(circle: Circle) -> Self {
+        Shape2::Circle(circle)
+    }
+}
+
+impl From<Aabb> for Shape2 {
+    fn from(aabb: Aabb) -> Self {
+        Shape2::Aabb(aabb)
+    }
+}
+
+impl From<Obb2> for Shape2 {
+    fn from(obb: Obb2) -> Self {
+        Shape2::Obb(obb)
+    }
+}
+
+impl From<Capsule2> for Shape2 {
+    fn from(capsule: Capsule2) -> Self {
+        Shape2::Capsule(capsule)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RayHit2 {
+    /// Distance from the origin of the ray, 0 if the ray starts inside of the shape.
+    pub distance: f32,
+    pub point: Vec2,
+    /// Unit normal of the surface at `point`, the negative ray direction if the ray starts inside.
+    pub normal: Vec2,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SweepHit2 {
+    /// Fraction of the motion in `0..=1` after which the shapes touch.
+    pub time: f32,
+    /// Unit normal of the contact, pointing from the other shape towards the moving one.
+    pub normal: Vec2,
+}
+
+impl Shape2 {
+    pub fn translated(&self, offset: Vec2) -> Shape2 {
+        match *self {
+            Shape2::Circle(c) => Shape2::Circle(Circle::new(c.center + offset, c.radius)),
+            Shape2::Aabb(aabb) => Shape2::Aabb(aabb + offset),
+            Shape2::Obb(obb) => Shape2::Obb(Obb2 {
+                center: obb.center + offset,
+                ..obb
+            }),
+            Shape2::Capsule(c) => {
+                Shape2::Capsule(Capsule2::new(c.a + offset, c.b + offset, c.radius))
+            }
+        }
+    }
+
+    pub fn bounding_aabb(&self) -> Aabb {
+        let (core, radius) = self.core();
+        let aabb = Aabb::bounding_aabb_for_points(core.points());
+        Aabb {
+            min: aabb.min - radius,
+            max: aabb.max + radius,
+        }
+    }
+
+    /// Points on the border count as contained.
+    pub fn contains(&self, point: Vec2) -> bool {
+        let (core, radius) = self.core();
+        closest2(&core, &Core2::segment(point, point)).2 <= radius
+    }
+
+    /// Distance between the borders of the shapes, 0 if they overlap.
+    pub fn distance(&self, other: &Shape2) -> f32 {
+        let (a, ra) = self.core();
+        let (b, rb) = other.core();
+        (closest2(&a, &b).2 - ra - rb).max(0.0)
+    }
+
+    /// Touching shapes overlap, like for [`Aabb::intersects`].
+    pub fn overlaps(&self, other: &Shape2) -> bool {
+        let (a, ra) = self.core();
+        let (b, rb) = other.core();
+        closest2(&a, &b).2 <= ra + rb
+    }
+
+    /// The first point where the ray from `origin` in `direction` enters the shape, if it is
+    /// at most `max_distance` away. `direction` does not need to be normalized.
+    pub fn raycast(&self, origin: Vec2, direction: Vec2, max_distance: f32) -> Option<RayHit2> {
+        let direction = direction.try_normalize()?;
+        let (core, radius) = self.core();
+        let (distance, normal) = if core.len == 4 {
+            ray_polygon(origin, direction, &core.points)
+        } else {
+            ray_capsule2(origin, direction, core.points[0], core.points[1], radius)
+        }?;
+        (distance <= max_distance).then(|| RayHit2 {
+            distance,
+            point: origin + direction * distance,
+            normal,
+        })
+    }
+
+    /// Moves the shape along `motion` and returns when it starts touching `other`. Shapes that already
+    /// overlap hit at time 0, unless the motion moves them apart.
+    pub fn sweep(&self, motion: Vec2, other: &Shape2) -> Option<SweepHit2> {
+        let (a, ra) = self.core();
+        let (b, rb) = other.core();
+        let fallback_normal = -motion.normalize_or_zero();
+        let mut time = 0.0;
+        let mut normal = fallback_normal;
+        for _ in 0..SWEEP_ITERATIONS {
+            let (pa, pb, distance) = closest2(&a.translated(motion * time), &b);
+            let gap = distance - ra - rb;
+            normal = (pa - pb).try_normalize().unwrap_or(fallback_normal);
+            // how fast the gap shrinks, the distance is convex in time, so this never overshoots.
+            let closing = -normal.dot(motion);
+            if gap <= CONTACT_EPSILON {
+                return (closing > 0.0).then_some(SweepHit2 { time, normal });
+            }
+            if closing <= 0.0 {
+                return None;
+            }
+            time += gap / closing;
+            if time > 1.0 {
+                return None;
+            }
+        }
+        Some(SweepHit2 { time, normal })
+    }
+
+    /// Moves the shape by `motion`, stopping at the `obstacles` and sliding along them, like a simple
+    /// character controller. Returns the motion that was possible, it keeps [`SLIDE_SKIN`] distance to the obstacles.
+    pub fn move_and_slide(&self, motion: Vec2, obstacles: &[Shape2]) -> Vec2 {
+        let mut moved = Vec2::ZERO;
+        let mut remaining = motion;
+        for _ in 0..SLIDE_ITERATIONS {
+            let Some(direction) = remaining.try_normalize() else {
+                break;
+            };
+            let shape = self.translated(moved);
+            let hit = obstacles
+                .iter()
+                .filter_map(|obstacle| shape.sweep(remaining, obstacle))
+                .min_by(|a, b| a.time.total_cmp(&b.time));
+            let Some(hit) = hit else {
+                moved += remaining;
+                break;
+            };
+            // stop such that the distance to the obstacle along its normal is the skin.
+            let approach = (-direction.dot(hit.normal)).max(CONTACT_EPSILON);
+            let travel = (remaining.length() * hit.time - SLIDE_SKIN / approach).max(0.0);
+            moved += direction * travel;
+            remaining *= 1.0 - hit.time;
+            remaining -= hit.normal * remaining.dot(hit.normal).min(0.0);
+        }
+        moved
+    }
+
+    /// Lines along the border of the shape in the xy plane, see [`crate::renderer::gizmos::Gizmos::draw_shape2`].
+    pub fn outline(&self) -> Vec<(Vec3, Vec3)> {
+        let mut lines = vec![];
+        match *self {
+            Shape2::Circle(c) => arc(
+                &mut lines,
+                c.center.extend(0.0),
+                Vec3::X,
+                Vec3::Y,
+                c.radius,
+                0.0,
+                TAU,
+            ),
+            Shape2::Aabb(_) | Shape2::Obb(_) => {
+                let (core, _) = self.core();
+                for (from, to) in core.edges() {
+                    lines.push((from.extend(0.0), to.extend(0.0)));
+                }
+            }
+            Shape2::Capsule(c) => {
+                let dir = (c.b - c.a).try_normalize().unwrap_or(Vec2::Y);
+                let side = dir.perp() * c.radius;
+                let angle = dir.to_angle();
+                lines.push(((c.a - side).extend(0.0), (c.b - side).extend(0.0)));
+                lines.push(((c.a + side).extend(0.0), (c.b + side).extend(0.0)));
+                let (a, b) = (c.a.extend(0.0), c.b.extend(0.0));
+                arc(
+                    &mut lines,
+                    b,
+                    Vec3::X,
+                    Vec3::Y,
+                    c.radius,
+                    angle - PI / 2.0,
+                    PI,
+                );
+                arc(
+                    &mut lines,
+                    a,
+                    Vec3::X,
+                    Vec3::Y,
+                    c.radius,
+                    angle + PI / 2.0,
+                    PI,
+                );
+            }
+        }
+        lines
+    }
+
+    /// The shape as a core and the radius around it.
+    fn core(&self) -> (Core2, f32) {
+        match *self {
+            Shape2::Circle(c) => (Core2::segment(c.center, c.center), c.radius),
+            Shape2::Aabb(aabb) => {
+                let Aabb { min, max } = aabb.standard_form();
+                let corners = [min, vec2(max.x, min.y), max, vec2(min.x, max.y)];
+                (Core2::polygon(corners), 0.0)
+            }
+            Shape2::Obb(obb) => (Core2::polygon(obb.corners()), 0.0),
+            Shape2::Capsule(c) => (Core2::segment(c.a, c.b), c.radius),
+        }
+    }
+}
+
+/// A segment (`len` 2) or a counter clockwise convex polygon (`len` 4).
+#[derive(Debug, Clone, Copy)]
+struct Core2 {
+    points: [Vec2; 4],
+    len: usize,
+}
+
+impl Core2 {
+    fn segment(a: Vec2, b: Vec2) -> Self {
+        Core2 {
+            points: [a, b, b, b],
+            len: 2,
+        }
+    }
+
+    fn polygon(points: [Vec2; 4]) -> Self {
+        Core2 { points, len: 4 }
+    }
+
+    fn points(&self) -> &[Vec2] {
+        &self.points[..self.len]
+    }
+
+    fn translated(mut self, offset: Vec2) -> Self {
+        for p in self.points.iter_mut() {
+            *p += offset;
+        }
+        self
+    }
+
+    fn edges(&self) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+        let n_edges = if self.len == 2 { 1 } else { self.len };
+        (0..n_edges).map(|i| (self.points[i], self.points[(i + 1) % self.len]))
+    }
+
+    /// Only polygons contain points, segments are handled by their distance.
+    fn polygon_contains(&self, point: Vec2) -> bool {
+        self.len == 4
+            && self
+                .edges()
+                .all(|(a, b)| (b - a).perp_dot(point - a) >= 0.0)
+    }
+}
+
+/// Closest points on the two cores and their distance, 0 if they overlap.
+fn closest2(a: &Core2, b: &Core2) -> (Vec2, Vec2, f32) {
+    let mut closest = (a.points[0], b.points[0], f32::MAX);
+    for (a0, a1) in a.edges() {
+        for (b0, b1) in b.edges() {
+            let (pa, pb) = closest_on_segments(
+                a0.extend(0.0),
+                a1.extend(0.0),
+                b0.extend(0.0),
+                b1.extend(0.0),
+            );
+            let distance = pa.distance(pb);
+            if distance < closest.2 {
+                closest = (pa.truncate(), pb.truncate(), distance);
+            }
+        }
+    }
+    // the borders do not touch, one of them could still be inside of the other.
+    if closest.2 > 0.0 {
+        if b.polygon_contains(a.points[0]) {
+            return (a.points[0], a.points[0], 0.0);
+        }
+        if a.polygon_contains(b.points[0]) {
+            return (b.points[0], b.points[0], 0.0);
+        }
+    }
+    closest
+}
+
+/// Distance and normal where the ray enters the counter clockwise convex polygon.
+fn ray_polygon(origin: Vec2, direction: Vec2, points: &[Vec2; 4]) -> Option<(f32, Vec2)> {
+    let mut t_enter = 0.0;
+    let mut t_exit = f32::MAX;
+    let mut normal = -direction;
+    for i in 0..4 {
+        let from = points[i];
+        let edge = points[(i + 1) % 4] - from;
+        let Some(edge_normal) = vec2(edge.y, -edge.x).try_normalize() else {
+            continue;
+        };
+        // the ray is inside of the edge for t * denom <= dist.
+        let denom = edge_normal.dot(direction);
+        let dist = edge_normal.dot(from - origin);
+        if denom.abs() < f32::EPSILON {
+            if dist < 0.0 {
+                return None;
+            }
+        } else if denom < 0.0 {
+            let t = dist / denom;
+            if t > t_enter {
+                t_enter = t;
+                normal = edge_normal;
+            }
+        } else {
+            t_exit = t_exit.min(dist / denom);
+        }
+        if t_enter > t_exit {
+            return None;
+        }
+    }
+    Some((t_enter, normal))
+}
+
+fn ray_capsule2(
+    origin: Vec2,
+    direction: Vec2,
+    a: Vec2,
+    b: Vec2,
+    radius: f32,
+) -> Option<(f32, Vec2)> {
+    let circle = |center: Vec2| {
+        ray_sphere(
+            origin.extend(0.0),
+            direction.extend(0.0),
+            center.extend(0.0),
+            radius,
+        )
+        .map(|(t, normal)| (t, normal.truncate()))
+    };
+    let mut hit = circle(a);
+    if let Some(dir) = (b - a).try_normalize() {
+        let side = dir.perp() * radius;
+        let rect = [a - side, b - side, b + side, a + side];
+        hit = closer(hit, ray_polygon(origin, direction, &rect));
+        hit = closer(hit, circle(b));
+    }
+    hit
+}
+
+// /////////////////////////////////////////////////////////////////////////////
+// 3d shapes
+// /////////////////////////////////////////////////////////////////////////////
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Sphere {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+impl Sphere {
+    pub const fn new(center: Vec3, radius: f32) -> Self {
+        Sphere { center, radius }
+    }
+}
+
+/// Axis aligned box in 3d, the 3d version of [`Aabb`].
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Aabb3 {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb3 {
+    pub const fn new(min: Vec3, max: Vec3) -> Self {
+        Aabb3 { min, max }
+    }
+
+    pub fn from_center(center: Vec3, half_extents: Vec3) -> Self {
+        Aabb3 {
+            min: center - half_extents,
+            max: center + half_extents,
+        }
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn size(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    pub fn contains(&self, pos: Vec3) -> bool {
+        pos.cmpge(self.min).all() && pos.cmple(self.max).all()
+    }
+
+    /// Touching boxes intersect.
+    pub fn intersects(&self, other: &Aabb3) -> bool {
+        self.min.cmple(other.max).all() && other.min.cmple(self.max).all()
+    }
+
+    /// The point in the box that is closest to `pos`.
+    pub fn closest_point(&self, pos: Vec3) -> Vec3 {
+        pos.clamp(self.min, self.max)
+    }
+
+    /// The corners, the bits of the index select min (0) or max (1) for x, y and z.
+    pub fn corners(&self) -> [Vec3; 8] {
+        std::array::from_fn(|i| {
+            Vec3::select(
+                glam::BVec3::new(i & 1 != 0, i & 2 != 0, i & 4 != 0),
+                self.max,
+                self.min,
+            )
+        })
+    }
+}
+
+/// All points within `radius` of the segment from `a` to `b`.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Capsule3 {
+    pub a: Vec3,
+    pub b: Vec3,
+    pub radius: f32,
+}
+
+impl Capsule3 {
+    pub const fn new(a: Vec3, b: Vec3, radius: f32) -> Self {
+        Capsule3 { a, b, radius }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Shape3 {
+    Sphere(Sphere),
+    Aabb(Aabb3),
+    Capsule(Capsule3),
+}
+
+impl From<Sphere> for Shape3 {
+    fn from(sphere: Sphere) -> Self {
+        Shape3::Sphere(sphere)
+    }
+}
+
+impl From<Aabb3> for Shape3 {
+    fn from(aabb: Aabb3) -> Self {
+        Shape3::Aabb(aabb)
+    }
+}
+
+impl From<Capsule3> for Shape3 {
+    fn from(capsule: Capsule3) -> Self {
+        Shape3::Capsule(capsule)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RayHit3 {
+    /// Distance from the origin of the ray, 0 if the ray starts inside of the shape.
+    pub distance: f32,
+    pub point: Vec3,
+    /// Unit normal of the surface at `point`, the negative ray direction if the ray starts inside.
+    pub normal: Vec3,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SweepHit3 {
+    /// Fraction of the motion in `0..=1` after which the shapes touch.
+    pub time: f32,
+    /// Unit normal of the contact, pointing from the other shape towards the moving one.
+    pub normal: Vec3,
+}
+
+impl Shape3 {
+    pub fn translated(&self, offset: Vec3) -> Shape3 {
+        match *self {
+            Shape3::Sphere(s) => Shape3::Sphere(Sphere::new(s.center + offset, s.radius)),
+            Shape3::Aabb(aabb) => Shape3::Aabb(Aabb3::new(aabb.min + offset, aabb.max + offset)),
+            Shape3::Capsule(c) => {
+                Shape3::Capsule(Capsule3::new(c.a + offset, c.b + offset, c.radius))
+            }
+        }
+    }
+
+    /// Points on the border count as contained.
+    pub fn contains(&self, point: Vec3) -> bool {
+        let (core, radius) = self.core();
+        closest3(&core, &Core3::Segment(point, point)).2 <= radius
+    }
+
+    /// Distance between the borders of the shapes, 0 if they overlap.
+    pub fn distance(&self, other: &Shape3) -> f32 {
+        let (a, ra) = self.core();
+        let (b, rb) = other.core();
+        (closest3(&a, &b).2 - ra - rb).max(0.0)
+    }
+
+    /// Touching shapes overlap.
+    pub fn overlaps(&self, other: &Shape3) -> bool {
+        let (a, ra) = self.core();
+        let (b, rb) = other.core();
+        closest3(&a, &b).2 <= ra + rb
+    }
+
+    /// The first point where the ray enters the shape, if it is at most `max_distance` away.
+    /// The direction of the ray does not need to be normalized.
+    pub fn raycast(&self, ray: &Ray, max_distance: f32) -> Option<RayHit3> {
+        let origin = ray.origin;
+        let direction = ray.direction.try_normalize()?;
+        let (distance, normal) = match *self {
+            Shape3::Sphere(s) => ray_sphere(origin, direction, s.center, s.radius),
+            Shape3::Aabb(aabb) => ray_aabb3(origin, direction, &aabb),
+            Shape3::Capsule(c) => {
+                let hit = ray_sphere(origin, direction, c.a, c.radius);
+                let hit = closer(hit, ray_sphere(origin, direction, c.b, c.radius));
+                closer(hit, ray_cylinder(origin, direction, c.a, c.b, c.radius))
+            }
+        }?;
+        (distance <= max_distance).then(|| RayHit3 {
+            distance,
+            point: origin + direction * distance,
+            normal,
+        })
+    }
+
+    /// Moves the shape along `motion` and returns when it starts touching `other`, like [`Shape2::sweep`].
+    pub fn sweep(&self, motion: Vec3, other: &Shape3) -> Option<SweepHit3> {
+        let (a, ra) = self.core();
+        let (b, rb) = other.core();
+        let fallback_normal = -motion.normalize_or_zero();
+        let mut time = 0.0;
+        let mut normal = fallback_normal;
+        for _ in 0..SWEEP_ITERATIONS {
+            let (pa, pb, distance) = closest3(&a.translated(motion * time), &b);
+            let gap = distance - ra - rb;
+            normal = (pa - pb).try_normalize().unwrap_or(fallback_normal);
+            let closing = -normal.dot(motion);
+            if gap <= CONTACT_EPSILON {
+                return (closing > 0.0).then_some(SweepHit3 { time, normal });
+            }
+            if closing <= 0.0 {
+                return None;
+            }
+            time += gap / closing;
+            if time > 1.0 {
+                return None;
+            }
+        }
+        Some(SweepHit3 { time, normal })
+    }
+
+    /// Lines along the shape, see [`crate::renderer::gizmos::Gizmos::draw_shape3`].
+    /// Spheres and capsules are drawn as circles around their axes.
+    pub fn outline(&self) -> Vec<(Vec3, Vec3)> {
+        let mut lines = vec![];
+        match *self {
+            Shape3::Sphere(s) => {
+                arc(&mut lines, s.center, Vec3::X, Vec3::Y, s.radius, 0.0, TAU);
+                arc(&mut lines, s.center, Vec3::Y, Vec3::Z, s.radius, 0.0, TAU);
+                arc(&mut lines, s.center, Vec3::Z, Vec3::X, s.radius, 0.0, TAU);
+            }
+            Shape3::Aabb(aabb) => {
+                let corners = aabb.corners();
+                for i in 0..8 {
+                    for bit in [1, 2, 4] {
+                        if i & bit == 0 {
+                            lines.push((corners[i], corners[i | bit]));
+                        }
+                    }
+                }
+            }
+            Shape3::Capsule(c) => {
+                let dir = (c.b - c.a).try_normalize().unwrap_or(Vec3::Y);
+                let (u, v) = dir.any_orthonormal_pair();
+                arc(&mut lines, c.a, u, v, c.radius, 0.0, TAU);
+                arc(&mut lines, c.b, u, v, c.radius, 0.0, TAU);
+                for side in [u, -u, v, -v] {
+                    lines.push((c.a + side * c.radius, c.b + side * c.radius));
+                }
+                for side in [u, v] {
+                    arc(&mut lines, c.b, side, dir, c.radius, 0.0, PI);
+                    arc(&mut lines, c.a, side, -dir, c.radius, 0.0, PI);
+                }
+            }
+        }
+        lines
+    }
+
+    fn core(&self) -> (Core3, f32) {
+        match *self {
+            Shape3::Sphere(s) => (Core3::Segment(s.center, s.center), s.radius),
+            Shape3::Aabb(aabb) => (Core3::Box(aabb), 0.0),
+            Shape3::Capsule(c) => (Core3::Segment(c.a, c.b), c.radius),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Core3 {
+    Segment(Vec3, Vec3),
+    Box(Aabb3),
+}
+
+impl Core3 {
+    fn translated(self, offset: Vec3) -> Self {
+        match self {
+            Core3::Segment(a, b) => Core3::Segment(a + offset, b + offset),
+            Core3::Box(aabb) => Core3::Box(Aabb3::new(aabb.min + offset, aabb.max + offset)),
+        }
+    }
+}
+
+/// Closest points on the two cores and their distance, 0 if they overlap.
+fn closest3(a: &Core3, b: &Core3) -> (Vec3, Vec3, f32) {
+    let (pa, pb) = match (*a, *b) {
+        (Core3::Segment(a0, a1), Core3::Segment(b0, b1)) => closest_on_segments(a0, a1, b0, b1),
+        (Core3::Segment(a0, a1), Core3::Box(aabb)) => closest_on_segment_and_box(a0, a1, &aabb),
+        (Core3::Box(aabb), Core3::Segment(b0, b1)) => {
+            let (pb, pa) = closest_on_segment_and_box(b0, b1, &aabb);
+            (pa, pb)
+        }
+        (Core3::Box(a), Core3::Box(b)) => {
+            // on each axis the boxes are either separated or share an interval.
+            let mut pa = Vec3::ZERO;
+            let mut pb = Vec3::ZERO;
+            for axis in 0..3 {
+                (pa[axis], pb[axis]) = if a.max[axis] < b.min[axis] {
+                    (a.max[axis], b.min[axis])
+                } else if b.max[axis] < a.min[axis] {
+                    (a.min[axis], b.max[axis])
+                } else {
+                    let shared =
+                        (a.min[axis].max(b.min[axis]) + a.max[axis].min(b.max[axis])) * 0.5;
+                    (shared, shared)
+                };
+            }
+            (pa, pb)
+        }
+    };
+    (pa, pb, pa.distance(pb))
+}
+
+/// The distance of the points on a segment to a box is convex, so a ternary search finds the closest one.
+fn closest_on_segment_and_box(a: Vec3, b: Vec3, aabb: &Aabb3) -> (Vec3, Vec3) {
+    let distance = |s: f32| {
+        let p = a.lerp(b, s);
+        p.distance_squared(aabb.closest_point(p))
+    };
+    let (mut lo, mut hi) = (0.0f32, 1.0f32);
+    for _ in 0..48 {
+        let m0 = lo + (hi - lo) / 3.0;
+        let m1 = hi - (hi - lo) / 3.0;
+        if distance(m0) <= distance(m1) {
+            hi = m1;
+        } else {
+            lo = m0;
+        }
+    }
+    let p = a.lerp(b, (lo + hi) * 0.5);
+    (p, aabb.closest_point(p))
+}
+
+/// Closest points on the segments `p1..q1` and `p2..q2`, from Real-Time Collision Detection (Ericson, 5.1.9).
+fn closest_on_segments(p1: Vec3, q1: Vec3, p2: Vec3, q2: Vec3) -> (Vec3, Vec3) {
+    let d1 = q1 - p1;
+    let d2 = q2 - p2;
+    let r = p1 - p2;
+    let a = d1.dot(d1);
+    let e = d2.dot(d2);
+    let f = d2.dot(r);
+    if a <= f32::EPSILON && e <= f32::EPSILON {
+        return (p1, p2);
+    }
+    let (s, t);
+    if a <= f32::EPSILON {
+        s = 0.0;
+        t = (f / e).clamp(0.0, 1.0);
+    } else {
+        let c = d1.dot(r);
+        if e <= f32::EPSILON {
+            t = 0.0;
+            s = (-c / a).clamp(0.0, 1.0);
+        } else {
+            let b = d1.dot(d2);
+            let denom = a * e - b * b;
+            // parallel segments: any s works, take the start of the first one.
+            let s0 = if denom > f32::EPSILON * a * e {
+                ((b * f - c * e) / denom).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let t0 = (b * s0 + f) / e;
+            if t0 < 0.0 {
+                t = 0.0;
+                s = (-c / a).clamp(0.0, 1.0);
+            } else if t0 > 1.0 {
+                t = 1.0;
+                s = ((b - c) / a).clamp(0.0, 1.0);
+            } else {
+                t = t0;
+                s = s0;
+            }
+        }
+    }
+    (p1 + d1 * s, p2 + d2 * t)
+}
+
+/// Distance and normal where the ray with normalized `direction` enters the sphere.
+fn ray_sphere(origin: Vec3, direction: Vec3, center: Vec3, radius: f32) -> Option<(f32, Vec3)> {
+    let m = origin - center;
+    let b = m.dot(direction);
+    let c = m.dot(m) - radius * radius;
+    if c <= 0.0 {
+        return Some((0.0, -direction));
+    }
+    if b > 0.0 {
+        return None;
+    }
+    let discriminant = b * b - c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let t = -b - discriminant.sqrt();
+    let normal = (m + direction * t).try_normalize().unwrap_or(-direction);
+    Some((t.max(0.0), normal))
+}
+
+fn ray_aabb3(origin: Vec3, direction: Vec3, aabb: &Aabb3) -> Option<(f32, Vec3)> {
+    let mut t_enter = 0.0;
+    let mut t_exit = f32::MAX;
+    let mut normal = -direction;
+    for axis in 0..3 {
+        let (o, d) = (origin[axis], direction[axis]);
+        if d.abs() < f32::EPSILON {
+            if o < aabb.min[axis] || o > aabb.max[axis] {
+                return None;
+            }
+            continue;
+        }
+        let mut t0 = (aabb.min[axis] - o) / d;
+        let mut t1 = (aabb.max[axis] - o) / d;
+        let mut sign = -1.0;
+        if t0 > t1 {
+            std::mem::swap(&mut t0, &mut t1);
+            sign = 1.0;
+        }
+        if t0 > t_enter {
+            t_enter = t0;
+            normal = Vec3::ZERO;
+            normal[axis] = sign;
+        }
+        t_exit = t_exit.min(t1);
+        if t_enter > t_exit {
+            return None;
+        }
+    }
+    Some((t_enter, normal))
+}
+
+/// The side of the capsule between its end spheres.
+fn ray_cylinder(
+    origin: Vec3,
+    direction: Vec3,
+    a: Vec3,
+    b: Vec3,
+    radius: f32,
+) -> Option<(f32, Vec3)> {
+    let len = a.distance(b);
+    let axis = (b - a).try_normalize()?;
+    let m = origin - a;
+    let height = m.dot(axis);
+    // parts perpendicular to the axis
+    let m_perp = m - axis * height;
+    let d_perp = direction - axis * direction.dot(axis);
+    let qa = d_perp.dot(d_perp);
+    let qb = m_perp.dot(d_perp);
+    let qc = m_perp.dot(m_perp) - radius * radius;
+    if qc <= 0.0 && (0.0..=len).contains(&height) {
+        return Some((0.0, -direction));
+    }
+    if qa < f32::EPSILON {
+        return None;
+    }
+    let discriminant = qb * qb - qa * qc;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let t = (-qb - discriminant.sqrt()) / qa;
+    if t < 0.0 {
+        return None;
+    }
+    let point = origin + direction * t;
+    let height = (point - a).dot(axis);
+    if !(0.0..=len).contains(&height) {
+        return None;
+    }
+    let normal = (point - (a + axis * height)).try_normalize()?;
+    Some((t, normal))
+}
+
+fn closer<N>(a: Option<(f32, N)>, b: Option<(f32, N)>) -> Option<(f32, N)> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(if b.0 < a.0 { b } else { a }),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Lines along the arc `center + (x * cos(angle) + y * sin(angle)) * radius` from `start` to `start + angle`.
+fn arc(
+    lines: &mut Vec<(Vec3, Vec3)>,
+    center: Vec3,
+    x: Vec3,
+    y: Vec3,
+    radius: f32,
+    start: f32,
+    angle: f32,
+) {
+    let n = ((CIRCLE_SEGMENTS as f32 * angle / TAU).ceil() as usize).max(1);
+    let point = |i: usize| {
+        let (sin, cos) = (start + angle * i as f32 / n as f32).sin_cos();
+        center + (x * cos + y * sin) * radius
+    };
+    for i in 0..n {
+        lines.push((point(i), point(i + 1)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{vec2, vec3, Vec2, Vec3};
+
+    use crate::{Aabb, Obb2, Ray};
+
+    use super::{Aabb3, Capsule2, Capsule3, Circle, Shape2, Shape3, Sphere};
+
+    fn wall() -> Shape2 {
+        Aabb::new(vec2(2.0, -10.0), vec2(3.0, 10.0)).into()
+    }
+
+    #[test]
+    fn overlaps_2d() {
+        let circle = Shape2::from(Circle::new(Vec2::ZERO, 1.0));
+        let capsule = Shape2::from(Capsule2::new(vec2(1.5, -1.0), vec2(1.5, 1.0), 0.5));
+        let obb = Shape2::from(Obb2::new(vec2(0.0, 3.0), vec2(1.0, 1.0), 0.5));
+        assert!(circle.overlaps(&capsule));
+        assert!(!circle.overlaps(&obb));
+        assert!(!circle.overlaps(&wall()));
+        assert!((circle.distance(&wall()) - 1.0).abs() < 1e-5);
+        // fully inside
+        let small = Shape2::from(Circle::new(vec2(2.5, 0.0), 0.1));
+        assert!(small.overlaps(&wall()));
+        assert!(wall().contains(vec2(2.5, 5.0)));
+        assert!(capsule.contains(vec2(1.9, 1.0)));
+        assert!(!capsule.contains(vec2(1.9, 1.4)));
+    }
+
+    #[test]
+    fn raycasts_2d() {
+        let hit = wall().raycast(Vec2::ZERO, vec2(2.0, 0.0), 10.0).unwrap();
+        assert_eq!(hit.distance, 2.0);
+        assert_eq!(hit.normal, vec2(-1.0, 0.0));
+        assert!(wall().raycast(Vec2::ZERO, vec2(1.0, 0.0), 1.5).is_none());
+        assert!(wall().raycast(Vec2::ZERO, vec2(-1.0, 0.0), 10.0).is_none());
+
+        let capsule = Shape2::from(Capsule2::new(vec2(0.0, 2.0), vec2(0.0, 4.0), 1.0));
+        let hit = capsule.raycast(Vec2::ZERO, Vec2::Y, 10.0).unwrap();
+        assert!((hit.distance - 1.0).abs() < 1e-5);
+        assert!((hit.normal - vec2(0.0, -1.0)).length() < 1e-5);
+        let hit = capsule.raycast(vec2(-5.0, 3.0), Vec2::X, 10.0).unwrap();
+        assert!((hit.distance - 4.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn sweep_and_slide_2d() {
+        let circle = Shape2::from(Circle::new(Vec2::ZERO, 1.0));
+        let hit = circle.sweep(vec2(4.0, 0.0), &wall()).unwrap();
+        assert!((hit.time - 0.25).abs() < 1e-4);
+        assert!((hit.normal - vec2(-1.0, 0.0)).length() < 1e-4);
+        assert!(circle.sweep(vec2(-4.0, 0.0), &wall()).is_none());
+        assert!(circle.sweep(vec2(0.5, 0.0), &wall()).is_none());
+
+        // moving diagonally into the wall keeps the motion along it
+        let moved = circle.move_and_slide(vec2(4.0, 3.0), &[wall()]);
+        assert!(moved.x < 1.0 && moved.x > 0.99);
+        assert!((moved.y - 3.0).abs() < 1e-3);
+        assert!(!circle.translated(moved).overlaps(&wall()));
+    }
+
+    #[test]
+    fn queries_3d() {
+        let sphere = Shape3::from(Sphere::new(Vec3::ZERO, 1.0));
+        let aabb = Shape3::from(Aabb3::new(vec3(2.0, -1.0, -1.0), vec3(3.0, 1.0, 1.0)));
+        let capsule = Shape3::from(Capsule3::new(vec3(0.0, 3.0, 0.0), vec3(0.0, 5.0, 0.0), 0.5));
+        assert!(!sphere.overlaps(&aabb));
+        assert!(sphere.translated(vec3(1.5, 0.5, 0.0)).overlaps(&aabb));
+        assert!((capsule.distance(&sphere) - 1.5).abs() < 1e-5);
+        assert!(!capsule.overlaps(&aabb));
+        assert!(capsule.translated(vec3(2.0, -3.0, 0.0)).overlaps(&aabb));
+
+        let ray = Ray {
+            origin: vec3(-5.0, 0.0, 0.0),
+            direction: Vec3::X,
+        };
+        let hit = aabb.raycast(&ray, 100.0).unwrap();
+        assert_eq!(hit.distance, 7.0);
+        assert_eq!(hit.normal, -Vec3::X);
+        let hit = sphere.raycast(&ray, 100.0).unwrap();
+        assert!((hit.distance - 4.0).abs() < 1e-5);
+        let side = Ray {
+            origin: vec3(-5.0, 4.0, 0.0),
+            direction: Vec3::X,
+        };
+        let hit = capsule.raycast(&side, 100.0).unwrap();
+        assert!((hit.distance - 4.5).abs() < 1e-5);
+        assert!((hit.normal + Vec3::X).length() < 1e-5);
+
+        let hit = sphere.sweep(vec3(4.0, 0.0, 0.0), &aabb).unwrap();
+        assert!((hit.time - 0.25).abs() < 1e-4);
+        let hit = capsule.sweep(vec3(0.0, -4.0, 0.0), &sphere).unwrap();
+        assert!((hit.time - 0.375).abs() < 1e-4);
+        assert!((hit.normal - Vec3::Y).length() < 1e-4);
+    }
+}
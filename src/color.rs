@@ -1,8 +1,14 @@
-use std::ops::{Add, Mul};
+use std::{
+    collections::BTreeMap,
+    ops::{Add, Mul},
+};
 
+use anyhow::anyhow;
 use glam::{Vec3, Vec4};
+use log::warn;
 
 use super::lerp::Lerp;
+use crate::AssetT;
 
 use serde::{Deserialize, Serialize};
 
@@ -84,33 +90,29 @@ impl Color {
         hsv_to_rgb(hue, saturation, value)
     }
 
-    #[inline]
-    pub fn from_hex(hex: &str) -> Color {
-        const fn hex_digit_value(c: char) -> u8 {
-            match c {
-                '0'..='9' => c as u8 - b'0',
-                'a'..='f' => c as u8 - b'a' + 10,
-                'A'..='F' => c as u8 - b'A' + 10,
-                _ => 0,
-            }
-        }
-
-        const fn parse_hex_pair(s: &str, start: usize) -> u8 {
-            16 * hex_digit_value(s.as_bytes()[start] as char)
-                + hex_digit_value(s.as_bytes()[start + 1] as char)
-        }
-
-        if hex.as_bytes()[0] != "#".as_bytes()[0] {
-            panic!("Hex string needs to start with #")
+    /// Parses `#RGB`, `#RRGGBB` or `#RRGGBBAA` and maps the rgb channels into srgb space like [`Color::u8_srgb`],
+    /// alpha is taken as is. Panics on invalid input, which is a compile error when used in a constant:
+    ///
+    /// ```rust,ignore
+    /// const ACCENT: Color = Color::from_hex("#ff8800");
+    /// ```
+    pub const fn from_hex(hex: &str) -> Color {
+        match parse_hex_rgba(hex) {
+            Ok([r, g, b, a]) => Color {
+                r: color_map_to_srgb(r),
+                g: color_map_to_srgb(g),
+                b: color_map_to_srgb(b),
+                a: a as f32 / 255.0,
+            },
+            Err(err) => panic!("{}", err),
         }
+    }
 
-        if hex.len() == 7 {
-            let r = color_map_to_srgb(parse_hex_pair(hex, 1));
-            let g = color_map_to_srgb(parse_hex_pair(hex, 3));
-            let b = color_map_to_srgb(parse_hex_pair(hex, 5));
-            Color { r, g, b, a: 1.0 }
-        } else {
-            panic!("Cannot create Color! Expects a hex string")
+    /// [`Color::from_hex`] that returns an error instead of panicking, e.g. for colors from config files.
+    pub fn try_from_hex(hex: &str) -> anyhow::Result<Color> {
+        match parse_hex_rgba(hex) {
+            Ok(_) => Ok(Color::from_hex(hex)),
+            Err(err) => Err(anyhow!("Invalid hex color {hex:?}: {err}")),
         }
     }
 
@@ -126,16 +128,141 @@ impl Color {
         }
     }
 
+    /// the inverse of [`Color::u8_srgb`], with the alpha as the last byte, e.g. to fill an rgba image.
+    ///
+    /// rgb_color = (srgb_color ^ (1 / 2.4) * 1.055 - 0.055) * 255
+    pub fn to_u8_srgb(&self) -> [u8; 4] {
+        let map =
+            |c: f32| ((c.clamp(0.0, 1.0).powf(1.0 / 2.4) * 1.055 - 0.055) * 255.0).round() as u8;
+        [
+            map(self.r),
+            map(self.g),
+            map(self.b),
+            (self.a.clamp(0.0, 1.0) * 255.0).round() as u8,
+        ]
+    }
+
     pub const fn alpha(self, a: f32) -> Self {
         Self { a, ..self }
     }
+
+    /// Converts values in HSL color space to RGB, like [`Color::from_hsv`].
+    ///
+    /// * hue: f64 - the position of the color on the color wheel. Between 0 and 360
+    /// * saturation: f64 - Between 0, grey, and 1, full color
+    /// * lightness: f64 - Between 0, black, 0.5, full color and 1, white
+    pub fn from_hsl(hue: f64, saturation: f64, lightness: f64) -> Self {
+        // see https://en.wikipedia.org/wiki/HSL_and_HSV#HSL_to_HSV
+        let value = lightness + saturation * lightness.min(1.0 - lightness);
+        let saturation = if value == 0.0 {
+            0.0
+        } else {
+            2.0 * (1.0 - lightness / value)
+        };
+        hsv_to_rgb(hue.rem_euclid(360.0), saturation, value)
+    }
+
+    /// Creates a color from the perceptually uniform Oklab color space, see https://bottosson.github.io/posts/oklab/
+    ///
+    /// * l: perceived lightness, between 0 and 1
+    /// * a: green (negative) to red (positive), roughly between -0.4 and 0.4
+    /// * b: blue (negative) to yellow (positive), roughly between -0.4 and 0.4
+    pub fn from_oklab(l: f32, a: f32, b: f32) -> Self {
+        Oklab { l, a, b }.into()
+    }
+
+    /// Creates a color from Oklch, the polar form of Oklab. Colors with the same `l` and `c` look equally bright
+    /// and saturated, no matter the hue, which makes it great for generating palettes.
+    ///
+    /// * l: perceived lightness, between 0 and 1
+    /// * c: chroma, between 0 (grey) and roughly 0.37
+    /// * hue: in degrees, between 0 and 360
+    pub fn from_oklch(l: f32, c: f32, hue: f32) -> Self {
+        Oklch { l, c, hue }.into()
+    }
+
+    pub fn to_oklab(&self) -> Oklab {
+        Oklab::from(*self)
+    }
+
+    pub fn to_oklch(&self) -> Oklch {
+        Oklch::from(*self)
+    }
+
+    /// Interpolates in Oklab space, which has no muddy or overly dark colors in between, unlike interpolating in rgb.
+    pub fn lerp_oklab(&self, other: &Color, factor: f32) -> Color {
+        let mut color: Color = self.to_oklab().lerp(&other.to_oklab(), factor).into();
+        color.a = self.a.lerp(&other.a, factor);
+        color
+    }
 }
 
 /// srgb_color = ((rgb_color / 255 + 0.055) / 1.055) ^ 2.4
 #[inline]
-pub fn color_map_to_srgb(u: u8) -> f32 {
+pub const fn color_map_to_srgb(u: u8) -> f32 {
     // u as f32 / 255.0
-    ((u as f32 / 255.0 + 0.055) / 1.055).powf(2.4)
+    pow_2_4((u as f32 / 255.0 + 0.055) / 1.055)
+}
+
+/// `x^2.4` for x in 0..=1 that can be used in constants, as `x^2 * (x^2)^(1/5)` with newton iterations
+/// for the fifth root. Starting above the root they decrease monotonically until the precision runs out.
+const fn pow_2_4(x: f32) -> f32 {
+    let sq = x * x;
+    if sq <= 0.0 {
+        return 0.0;
+    }
+    let mut root = 1.0f32;
+    loop {
+        let root4 = root * root * root * root;
+        let next = root - (root4 * root - sq) / (5.0 * root4);
+        if next >= root {
+            return sq * root;
+        }
+        root = next;
+    }
+}
+
+/// The bytes of `#RGB`, `#RRGGBB` or `#RRGGBBAA`, alpha is 255 if not given.
+const fn parse_hex_rgba(hex: &str) -> Result<[u8; 4], &'static str> {
+    const fn digit(c: u8) -> Result<u8, &'static str> {
+        match c {
+            b'0'..=b'9' => Ok(c - b'0'),
+            b'a'..=b'f' => Ok(c - b'a' + 10),
+            b'A'..=b'F' => Ok(c - b'A' + 10),
+            _ => Err("expected only hex digits after #"),
+        }
+    }
+
+    let bytes = hex.as_bytes();
+    if bytes.is_empty() || bytes[0] != b'#' {
+        return Err("expected # at the start");
+    }
+    let short = match bytes.len() {
+        4 => true,
+        7 | 9 => false,
+        _ => return Err("expected #RGB, #RRGGBB or #RRGGBBAA"),
+    };
+    let mut rgba = [255u8; 4];
+    let mut i = 0;
+    while i < bytes.len() - 1 {
+        let value = match digit(bytes[i + 1]) {
+            Ok(value) => value,
+            Err(err) => return Err(err),
+        };
+        // #RGB repeats every digit: #f80 is #ff8800.
+        if short {
+            rgba[i] = value * 17;
+            i += 1;
+        } else {
+            let low = match digit(bytes[i + 2]) {
+                Ok(low) => low,
+                Err(err) => return Err(err),
+            };
+            rgba[i / 2] = value * 16 + low;
+            i += 2;
+        }
+    }
+    Ok(rgba)
 }
 
 impl From<Color> for wgpu::Color {
@@ -258,6 +385,248 @@ impl Hsv {
     }
 }
 
+/// Color in the Oklab color space. Does not store alpha, converting it into a [`Color`] gives an alpha of 1.0.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize, Lerp)]
+pub struct Oklab {
+    pub l: f32,
+    pub a: f32,
+    pub b: f32,
+}
+
+/// Color in the Oklch color space (Oklab in polar coordinates). `hue` is in degrees.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct Oklch {
+    pub l: f32,
+    pub c: f32,
+    pub hue: f32,
+}
+
+// constants are taken as is from https://bottosson.github.io/posts/oklab/
+#[allow(clippy::excessive_precision)]
+impl From<Color> for Oklab {
+    /// Note: the rgb values of a [`Color`] are linear, which is what the Oklab conversion expects.
+    fn from(color: Color) -> Self {
+        let Color { r, g, b, .. } = color;
+        let l = 0.4122214708 * r + 0.5363325363 * g + 0.0514459929 * b;
+        let m = 0.2119034982 * r + 0.6806995451 * g + 0.1073969566 * b;
+        let s = 0.0883024619 * r + 0.2817188376 * g + 0.6299787005 * b;
+
+        let l = l.cbrt();
+        let m = m.cbrt();
+        let s = s.cbrt();
+
+        Oklab {
+            l: 0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+            a: 1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+            b: 0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+        }
+    }
+}
+
+// constants are taken as is from https://bottosson.github.io/posts/oklab/
+#[allow(clippy::excessive_precision)]
+impl From<Oklab> for Color {
+    fn from(lab: Oklab) -> Self {
+        let l = lab.l + 0.3963377774 * lab.a + 0.2158037573 * lab.b;
+        let m = lab.l - 0.1055613458 * lab.a - 0.0638541728 * lab.b;
+        let s = lab.l - 0.0894841775 * lab.a - 1.2914855480 * lab.b;
+
+        let l = l * l * l;
+        let m = m * m * m;
+        let s = s * s * s;
+
+        Color::new(
+            4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+            -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+            -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+        )
+    }
+}
+
+impl From<Oklch> for Oklab {
+    fn from(lch: Oklch) -> Self {
+        let (sin, cos) = lch.hue.to_radians().sin_cos();
+        Oklab {
+            l: lch.l,
+            a: lch.c * cos,
+            b: lch.c * sin,
+        }
+    }
+}
+
+impl From<Oklab> for Oklch {
+    fn from(lab: Oklab) -> Self {
+        Oklch {
+            l: lab.l,
+            c: (lab.a * lab.a + lab.b * lab.b).sqrt(),
+            hue: lab.b.atan2(lab.a).to_degrees().rem_euclid(360.0),
+        }
+    }
+}
+
+impl From<Oklch> for Color {
+    fn from(lch: Oklch) -> Self {
+        Oklab::from(lch).into()
+    }
+}
+
+impl From<Color> for Oklch {
+    fn from(color: Color) -> Self {
+        Oklab::from(color).into()
+    }
+}
+
+/// The color space in which a [`Gradient`] interpolates between its stops.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ColorSpace {
+    /// Plain lerp of the (linear) rgb values of [`Color`].
+    LinearRgb,
+    /// Perceptually uniform, see [`Color::lerp_oklab`].
+    #[default]
+    Oklab,
+}
+
+/// Multiple colors at positions between 0.0 and 1.0, e.g. for the color of particles over their lifetime or health bars.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Gradient {
+    /// (position, color), sorted by position.
+    stops: Vec<(f32, Color)>,
+    pub space: ColorSpace,
+}
+
+impl Gradient {
+    pub fn new(space: ColorSpace) -> Self {
+        Gradient {
+            stops: vec![],
+            space,
+        }
+    }
+
+    /// Evenly spaced stops from 0.0 to 1.0
+    pub fn from_colors(colors: &[Color], space: ColorSpace) -> Self {
+        let mut gradient = Gradient::new(space);
+        let n = colors.len();
+        for (i, color) in colors.iter().enumerate() {
+            let pos = if n <= 1 {
+                0.0
+            } else {
+                i as f32 / (n - 1) as f32
+            };
+            gradient.add_stop(pos, *color);
+        }
+        gradient
+    }
+
+    pub fn with_stop(mut self, pos: f32, color: Color) -> Self {
+        self.add_stop(pos, color);
+        self
+    }
+
+    pub fn add_stop(&mut self, pos: f32, color: Color) {
+        let idx = self.stops.partition_point(|(p, _)| *p <= pos);
+        self.stops.insert(idx, (pos, color));
+    }
+
+    pub fn stops(&self) -> &[(f32, Color)] {
+        &self.stops
+    }
+
+    /// Positions before the first or after the last stop get the color of that stop.
+    /// Returns [`Color::TRANSPARENT`] if the gradient has no stops.
+    pub fn sample(&self, pos: f32) -> Color {
+        let Some(first) = self.stops.first() else {
+            return Color::TRANSPARENT;
+        };
+        let idx = self.stops.partition_point(|(p, _)| *p <= pos);
+        if idx == 0 {
+            return first.1;
+        }
+        if idx == self.stops.len() {
+            return self.stops[idx - 1].1;
+        }
+        let (p0, c0) = self.stops[idx - 1];
+        let (p1, c1) = self.stops[idx];
+        if pos == p0 {
+            // exactly on a stop, avoid imprecision of the oklab roundtrip.
+            return c0;
+        }
+        let factor = (pos - p0) / (p1 - p0);
+        match self.space {
+            ColorSpace::LinearRgb => c0.lerp(&c1, factor),
+            ColorSpace::Oklab => c0.lerp_oklab(&c1, factor),
+        }
+    }
+}
+
+/// Named colors, e.g. the color scheme of a game, such that colors are not scattered across the code as hex literals.
+///
+/// Can be loaded from a json file (see [`AssetT`]), which maps names to either hex strings (`"#ff8800"`) or color objects:
+/// ```json
+/// { "background": "#1a1c2c", "accent": { "r": 1.0, "g": 0.5, "b": 0.0, "a": 1.0 } }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Palette {
+    colors: BTreeMap<String, Color>,
+}
+
+impl Palette {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, name: impl Into<String>, color: Color) -> Self {
+        self.insert(name, color);
+        self
+    }
+
+    pub fn insert(&mut self, name: impl Into<String>, color: Color) {
+        self.colors.insert(name.into(), color);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Color> {
+        self.colors.get(name).copied()
+    }
+
+    /// Like [`Palette::get`], but returns [`Color::PURPLE`] for missing colors, so they are easy to spot.
+    pub fn color(&self, name: &str) -> Color {
+        match self.get(name) {
+            Some(color) => color,
+            None => {
+                warn!("Color {name} not found in palette");
+                Color::PURPLE
+            }
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Color)> {
+        self.colors.iter().map(|(k, v)| (k.as_str(), *v))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PaletteColor {
+    Hex(String),
+    Color(Color),
+}
+
+impl AssetT for Palette {
+    fn from_bytes(bytes: &[u8]) -> Result<Self, anyhow::Error> {
+        let colors: BTreeMap<String, PaletteColor> = serde_json::from_slice(bytes)?;
+        let mut palette = Palette::new();
+        for (name, color) in colors {
+            let color = match color {
+                PaletteColor::Color(color) => color,
+                PaletteColor::Hex(hex) => {
+                    Color::try_from_hex(&hex).map_err(|err| anyhow!("{err} for {name:?}"))?
+                }
+            };
+            palette.insert(name, color);
+        }
+        Ok(palette)
+    }
+}
+
 impl From<Hsv> for Color {
     fn from(value: Hsv) -> Self {
         Color::from_hsv(value.hue, value.saturation, value.value)
@@ -309,3 +678,48 @@ pub fn hsv_to_rgb(hue: f64, saturation: f64, value: f64) -> Color {
 
     return Color::new((r + m) as f32, (g + m) as f32, (b + m) as f32);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Color, Gradient};
+
+    #[test]
+    fn oklab_roundtrip() {
+        for color in [Color::RED, Color::ORANGE, Color::LIGHTBLUE, Color::DARKGREY] {
+            let back: Color = color.to_oklch().into();
+            assert!((back.r - color.r).abs() < 1e-4);
+            assert!((back.g - color.g).abs() < 1e-4);
+            assert!((back.b - color.b).abs() < 1e-4);
+        }
+        let white = Color::WHITE.to_oklab();
+        assert!((white.l - 1.0).abs() < 1e-4 && white.a.abs() < 1e-4 && white.b.abs() < 1e-4);
+    }
+
+    #[test]
+    fn hex_parsing() {
+        const ORANGE: Color = Color::from_hex("#FF8800");
+        assert_eq!(Color::from_hex("#f80"), ORANGE);
+        assert_eq!(
+            Color::try_from_hex("#ff880080").unwrap(),
+            ORANGE.alpha(128.0 / 255.0)
+        );
+        assert_eq!(Color::from_hex("#ffffff"), Color::WHITE);
+        for u in 0..=255u8 {
+            let expected = ((u as f32 / 255.0 + 0.055) / 1.055).powf(2.4);
+            assert!((super::color_map_to_srgb(u) - expected).abs() < 1e-6);
+            assert_eq!(Color::u8_srgb(u, u, u).to_u8_srgb(), [u, u, u, 255]);
+        }
+        for invalid in ["", "ff8800", "#ff88", "#ff880g", "#ff8800800"] {
+            assert!(Color::try_from_hex(invalid).is_err());
+        }
+    }
+
+    #[test]
+    fn gradient_sampling() {
+        let gradient = Gradient::from_colors(&[Color::BLACK, Color::WHITE], Default::default())
+            .with_stop(0.5, Color::RED);
+        assert_eq!(gradient.sample(-1.0), Color::BLACK);
+        assert_eq!(gradient.sample(0.5), Color::RED);
+        assert_eq!(gradient.sample(2.0), Color::WHITE);
+    }
+}
@@ -1,18 +1,29 @@
-use std::sync::Arc;
+use std::{
+    cell::{RefCell, RefMut},
+    rc::Rc,
+    sync::Arc,
+};
+
+use glam::DVec2;
 
 use crate::{
     edit,
-    renderer::{ui_3d::Ui3DRenderer, ui_screen::UiScreenRenderer},
+    renderer::ui_screen::UiScreenRenderer,
+    show,
     ui::{
-        batching::ElementBatchesGR, div, Board, ElementContext, IntoElementBox,
-        REFERENCE_SCREEN_SIZE_D,
+        batching::ElementBatchesGR, div, with_fonts, Board, IntoElementBox, UiCompositing,
+        DEFAULT_REFERENCE_SIZE,
     },
     uniforms::Uniforms,
-    AppT, Bloom, Camera3d, Color, ColorMeshRenderer, Egui, Gizmos, GraphicsContext, Input,
-    RenderFormat, Runner, RunnerCallbacks, Screen, ScreenTextures, ShaderCache, Time, ToneMapping,
-    Window,
+    AppT, BackdropBlur, Bloom, Camera3d, Color, ColorMeshRenderer, DebugRenderMode, Egui,
+    FrameStats, Gizmos, GraphicsContext, Input, InputStack, RenderFormat, ResizeDispatcher, Runner,
+    RunnerCallbacks, SceneContext, Screen, ScreenTextures, ScreenTransition, ShaderCache,
+    TexturePool, Time, ToneMapping, Window,
+};
+use winit::{
+    dpi::PhysicalSize,
+    event::{DeviceEvent, WindowEvent},
 };
-use winit::{dpi::PhysicalSize, event::WindowEvent};
 
 /// use it like this.
 pub fn main() {
@@ -29,18 +40,28 @@ pub struct DefaultWorld {
     pub shader_cache: ShaderCache,
     pub time: Time,
     pub input: Input,
+    /// which parts of the app get the input, modals built in `ui` are on top automatically.
+    pub input_layers: InputStack,
     pub screen_textures: ScreenTextures,
+    pub texture_pool: TexturePool,
     pub camera: Camera3d,
     pub screen: Screen,
     pub uniforms: Uniforms,
-    pub bloom: Bloom,
-    pub tone_mapping: ToneMapping,
+    /// the renderers are shared with the `shader_cache`, which hot reloads them in [`DefaultWorld::start_frame`].
+    pub bloom: Rc<RefCell<Bloom>>,
+    pub backdrop_blur: Rc<RefCell<BackdropBlur>>,
+    pub tone_mapping: Rc<RefCell<ToneMapping>>,
+    /// fades and wipes between scenes, drawn after tone mapping and before the ui.
+    pub transition: Rc<RefCell<ScreenTransition>>,
     pub egui: crate::Egui,
-    pub color_renderer: ColorMeshRenderer,
-    pub gizmos: Gizmos,
-    pub ui_renderer: UiScreenRenderer,
+    pub color_renderer: Rc<RefCell<ColorMeshRenderer>>,
+    pub gizmos: Rc<RefCell<Gizmos>>,
+    pub ui_renderer: Rc<RefCell<UiScreenRenderer>>,
+    /// renders boards with [`UiCompositing::PreToneMapping`] into the hdr scene.
+    pub hdr_ui_renderer: Rc<RefCell<UiScreenRenderer>>,
     pub ui: Board,
     pub ui_gr: ElementBatchesGR,
+    pub resize_dispatcher: ResizeDispatcher,
 }
 
 impl AppT for DefaultWorld {
@@ -52,6 +73,10 @@ impl AppT for DefaultWorld {
         }
     }
 
+    fn receive_device_event(&mut self, event: &DeviceEvent) {
+        self.input.receive_device_event(event);
+    }
+
     fn update(&mut self, _cb: &mut RunnerCallbacks) {
         self.start_frame();
         // /////////////////////////////////////////////////////////////////////////////
@@ -62,6 +87,12 @@ impl AppT for DefaultWorld {
     }
 }
 
+impl SceneContext for DefaultWorld {
+    fn screen_transition(&mut self) -> Option<RefMut<'_, ScreenTransition>> {
+        Some(self.transition.borrow_mut())
+    }
+}
+
 impl DefaultWorld {
     pub fn new(window: Arc<Window>) -> Self {
         let ctx = GraphicsContext::new(Default::default(), &window).unwrap();
@@ -89,6 +120,7 @@ impl DefaultWorld {
             RenderFormat::LDR_NO_MSAA.color,
             &mut shader_cache,
         );
+        let tone_mapping = shader_cache.hot_reloaded(tone_mapping);
         let bloom = Bloom::new(
             &ctx.device,
             size.width,
@@ -96,13 +128,36 @@ impl DefaultWorld {
             RenderFormat::HDR_MSAA4.color,
             &mut shader_cache,
         );
+        let bloom = shader_cache.hot_reloaded(bloom);
+        let backdrop_blur = BackdropBlur::new(
+            &ctx.device,
+            size.width,
+            size.height,
+            RenderFormat::LDR_NO_MSAA.color,
+            &mut shader_cache,
+        );
+        let backdrop_blur = shader_cache.hot_reloaded(backdrop_blur);
+        let transition = ScreenTransition::new(
+            &ctx.device,
+            size.width,
+            size.height,
+            RenderFormat::LDR_NO_MSAA.color,
+            &mut shader_cache,
+        );
+        let transition = shader_cache.hot_reloaded(transition);
         let egui = Egui::new(&ctx.device, ctx.surface_format, &window);
         let color_renderer = ColorMeshRenderer::new(&ctx, Default::default(), &mut shader_cache);
+        let color_renderer = shader_cache.hot_reloaded(color_renderer);
         let gizmos = Gizmos::new(&ctx, RenderFormat::HDR_MSAA4, &mut shader_cache);
+        let gizmos = shader_cache.hot_reloaded(gizmos);
 
         let ui_renderer =
             UiScreenRenderer::new(&ctx.device, &mut shader_cache, RenderFormat::LDR_NO_MSAA);
-        let ui = Board::new(div().store(), REFERENCE_SCREEN_SIZE_D);
+        let ui_renderer = shader_cache.hot_reloaded(ui_renderer);
+        let hdr_ui_renderer =
+            UiScreenRenderer::new(&ctx.device, &mut shader_cache, RenderFormat::HDR_MSAA4);
+        let hdr_ui_renderer = shader_cache.hot_reloaded(hdr_ui_renderer);
+        let ui = Board::new(div().store(), DEFAULT_REFERENCE_SIZE);
         let ui_gr = ElementBatchesGR::new(&ui.batches, &ctx.device);
 
         Self {
@@ -111,58 +166,79 @@ impl DefaultWorld {
             shader_cache,
             time,
             input,
+            input_layers: InputStack::new(),
             egui,
             screen_textures,
+            texture_pool: TexturePool::new(),
             camera,
             screen,
             uniforms,
             bloom,
+            backdrop_blur,
             tone_mapping,
+            transition,
             color_renderer,
             gizmos,
             ui_renderer,
+            hdr_ui_renderer,
             ui,
             ui_gr,
+            resize_dispatcher: ResizeDispatcher::new(),
         }
     }
 
     pub fn start_frame(&mut self) {
         self.time.start_frame();
+        self.transition.borrow_mut().update(&self.time);
+        self.camera
+            .projection
+            .update_transition(self.time.delta().as_secs_f32());
         self.egui.begin_frame();
-        self.shader_cache.hot_reload(
-            &mut [
-                &mut self.color_renderer,
-                &mut self.gizmos,
-                &mut self.bloom,
-                &mut self.tone_mapping,
-                &mut self.ui_renderer,
-            ],
-            &self.ctx.device,
-        );
-        self.ui.ctx.start_frame_scaled_to_fixed_height(
+        with_fonts(|fonts| fonts.update(self.time.delta().as_secs_f32()));
+        // the renderers of the world and shared ones (e.g. of scenes) are registered in the shader cache.
+        self.shader_cache.hot_reload_all(&self.ctx.device);
+        self.ui.ctx.start_frame_with_transform(
             self.input.cursor_pos().as_dvec2(),
             self.input.mouse_buttons(),
-            PhysicalSize::new(self.screen.width, self.screen.height),
-            REFERENCE_SCREEN_SIZE_D.y,
+            self.time.delta().as_secs_f32(),
+            &self.screen.ui_transform(),
         );
+        self.input_layers.update_from_ui(&self.ui.ctx);
     }
 
     pub fn end_frame(&mut self) {
         self.input.end_frame();
+        self.texture_pool.end_frame();
     }
 
     pub fn resize(&mut self, size: PhysicalSize<u32>) {
-        self.ctx.resize(size);
-        self.camera.resize(size);
-        self.screen.resize(size);
-        self.bloom.resize(size, &self.ctx.device);
-        self.screen_textures.resize(&self.ctx.device, size);
-        self.ui.resize_scaled_to_fixed_height(size);
+        let ctx = self.ctx.clone();
+        self.resize_dispatcher.resize(
+            size,
+            &ctx.device,
+            &mut [
+                &mut self.ctx,
+                &mut self.camera,
+                &mut self.screen,
+                &mut *self.bloom.borrow_mut(),
+                &mut *self.backdrop_blur.borrow_mut(),
+                &mut *self.transition.borrow_mut(),
+                &mut self.screen_textures,
+            ],
+        );
+        self.ui.resize_to_ui_transform(&self.screen.ui_transform());
+    }
+
+    /// Sets the design resolution of the ui for the screen and the ui board, see [`Screen::set_reference_size`].
+    pub fn set_reference_size(&mut self, reference_size: DVec2) {
+        self.screen.set_reference_size(reference_size);
+        self.ui.set_reference_size(reference_size);
+        self.ui.resize_to_ui_transform(&self.screen.ui_transform());
     }
 
     pub fn prepare(&mut self, encoder: &mut wgpu::CommandEncoder) {
-        self.color_renderer.prepare();
-        self.gizmos.prepare();
+        self.color_renderer.borrow_mut().prepare();
+        self.gizmos.borrow_mut().prepare();
 
         self.egui
             .prepare(&self.ctx.device, &self.ctx.queue, encoder);
@@ -178,54 +254,151 @@ impl DefaultWorld {
     }
 
     pub fn render(&mut self) {
-        self.gizmos.draw_xyz();
+        self.gizmos.borrow_mut().draw_xyz();
         crate::utils::global_vals_window(&mut self.egui.context());
         self.show_fps();
+        self.show_shader_errors();
 
         let mut encoder = self.ctx.device.create_command_encoder(&Default::default());
         self.prepare(&mut encoder);
 
+        let color_renderer = self.color_renderer.borrow();
+        let gizmos = self.gizmos.borrow();
+        let hdr_ui_renderer = self.hdr_ui_renderer.borrow();
+        let ui_renderer = self.ui_renderer.borrow();
+        let mut bloom = self.bloom.borrow_mut();
+        let mut tone_mapping = self.tone_mapping.borrow_mut();
+        let mut transition = self.transition.borrow_mut();
+        let mut backdrop_blur = self.backdrop_blur.borrow_mut();
+
         let (surface, view) = self.ctx.new_surface_texture_and_view();
-        let clear_color = edit!(Color::DARKGREY * 0.1, "clear color");
+        let clear_color = if self.ctx.is_transparent() {
+            Color::TRANSPARENT
+        } else {
+            edit!(Color::DARKGREY * 0.1, "clear color")
+        };
         let mut pass = self
             .screen_textures
             .new_hdr_target_render_pass(&mut encoder, clear_color);
-        self.color_renderer.render(&mut pass, &self.uniforms);
-        self.gizmos.render(&mut pass, &self.uniforms);
+        color_renderer.render(&mut pass, &self.uniforms);
+        gizmos.render(&mut pass, &self.uniforms);
+        let ui_pre_tone_mapping = self.ui.compositing == UiCompositing::PreToneMapping;
+        if ui_pre_tone_mapping {
+            hdr_ui_renderer.render_batches(
+                &mut pass,
+                &self.ui_gr,
+                &self.ui.batches.batches,
+                &self.uniforms,
+                Color::WHITE,
+            );
+        }
         drop(pass);
 
-        self.bloom.apply(
+        bloom.apply(
             &mut encoder,
-            &self.screen_textures.hdr_resolve_target.bind_group(),
-            &self.screen_textures.hdr_resolve_target.view(),
+            &self.ctx.device,
+            &mut self.texture_pool,
+            self.screen_textures.resolved().bind_group(),
+            self.screen_textures.resolved().view(),
             &self.uniforms,
         );
-        self.tone_mapping.apply(
+        tone_mapping.apply(
             &mut encoder,
-            self.screen_textures.hdr_resolve_target.bind_group(),
+            self.screen_textures.resolved().bind_group(),
             &view,
         );
-        self.ui_renderer.render_in_new_pass(
+        transition.capture(
             &mut encoder,
-            &view,
-            &self.ui_gr,
-            &self.ui.batches.batches,
-            &self.uniforms,
-            Color::WHITE,
+            &self.ctx.device,
+            &mut tone_mapping,
+            self.screen_textures.resolved().bind_group(),
         );
+        transition.render(&mut encoder, &view);
+        let backdrop = if !ui_pre_tone_mapping && self.ui.batches.has_backdrop() {
+            backdrop_blur.apply(
+                &mut encoder,
+                &self.ctx.device,
+                &mut self.texture_pool,
+                &mut tone_mapping,
+                self.screen_textures.resolved().bind_group(),
+                &self.uniforms,
+            );
+            backdrop_blur.bind_group()
+        } else {
+            None
+        };
+        if !ui_pre_tone_mapping {
+            let mut pass = ui_renderer.new_render_pass(&mut encoder, &view);
+            ui_renderer.render_batches_with_backdrop(
+                &mut pass,
+                &self.ui_gr,
+                &self.ui.batches.batches,
+                &self.uniforms,
+                Color::WHITE,
+                backdrop,
+            );
+        }
         self.egui.render(&mut encoder, &view);
 
         self.ctx.queue.submit([encoder.finish()]);
         surface.present();
     }
 
+    /// Shows the fps and lets the [`DebugRenderMode`] of the `color_renderer` be picked.
     pub fn show_fps(&mut self) {
+        let mut debug_mode = self.color_renderer.borrow().debug_mode();
         egui::Window::new("Fps").show(&self.egui.context(), |ui| {
             ui.label(format!(
                 "Fps: {:.0} / {:.3} ms",
                 self.time.fps(),
                 self.time.delta().as_secs_f32() * 1000.0
             ));
+            egui::ComboBox::from_label("Debug view")
+                .selected_text(format!("{debug_mode:?}"))
+                .show_ui(ui, |ui| {
+                    for mode in DebugRenderMode::ALL {
+                        ui.selectable_value(&mut debug_mode, mode, format!("{mode:?}"));
+                    }
+                });
+        });
+        self.color_renderer
+            .borrow_mut()
+            .set_debug_mode(debug_mode, &mut self.shader_cache);
+    }
+
+    /// The [`crate::RenderStats`] of the built-in renderers in the last frame.
+    pub fn frame_stats(&self) -> FrameStats {
+        FrameStats {
+            color_mesh: self.color_renderer.borrow().stats(),
+            gizmos: self.gizmos.borrow().stats(),
+            ui: self.ui.batches.render_stats(),
+            ..Default::default()
+        }
+    }
+
+    /// Shows the [`crate::RenderStats`] of the built-in renderers in the `edit!` window.
+    pub fn show_render_stats(&self) {
+        let stats = self.frame_stats();
+        let color_mesh = stats.color_mesh;
+        let gizmos = stats.gizmos;
+        let ui = stats.ui;
+        let total = stats.total();
+        show!(color_mesh);
+        show!(gizmos);
+        show!(ui);
+        show!(total);
+    }
+
+    /// Lists the shaders that failed to hot reload, until they are fixed.
+    pub fn show_shader_errors(&mut self) {
+        let errors = self.shader_cache.hot_reload_errors();
+        if errors.is_empty() {
+            return;
+        }
+        egui::Window::new("Shader Errors").show(&self.egui.context(), |ui| {
+            for err in errors {
+                ui.colored_label(egui::Color32::RED, err.to_string());
+            }
         });
     }
 }
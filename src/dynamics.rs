@@ -0,0 +1,367 @@
+//! Small physics helpers for juice: springs that follow a target and verlet chains for ropes and tails.
+//!
+//! Both are updated from [`Time`] and give the same motion at every frame rate:
+//!
+//! ```rust,ignore
+//! let mut camera_pos = Spring::new(player.pos).with_stiffness(60.0);
+//! let mut health_bar = Spring::new(1.0).with_damping(6.0); // bouncy
+//! // every frame:
+//! camera_pos.target = player.pos;
+//! camera.transform.pos = *camera_pos.update(&time);
+//! ```
+
+use crate::{lerp::Lerp, spline::SplinePoint, Time};
+
+/// Stiffness of [`Spring::new`], in 1/s².
+pub const DEFAULT_STIFFNESS: f32 = 150.0;
+
+/// A value that moves towards its `target` like a mass on a spring with `stiffness` and `damping`.
+///
+/// Works for everything that is [`Lerp`] (numbers, vectors, colors, rects, ...): the velocity is kept as the
+/// value of the last update, like in verlet integration, and each update is an exact step of the
+/// damped spring, blending target, value and last value. So it can't explode for large time steps,
+/// and changing the target keeps the current velocity.
+#[derive(Debug, Clone)]
+pub struct Spring<T: Lerp + Clone> {
+    pub target: T,
+    current: T,
+    /// value one `step` ago at the current velocity.
+    previous: T,
+    step: f32,
+    /// Pull towards the target per unit of distance, in 1/s².
+    pub stiffness: f32,
+    /// Loss of velocity, in 1/s. `2 * sqrt(stiffness)` is critically damped: the fastest movement to the
+    /// target without overshooting. Less than that overshoots and bounces around the target.
+    pub damping: f32,
+}
+
+impl<T: Lerp + Clone> Spring<T> {
+    /// A resting spring, critically damped with [`DEFAULT_STIFFNESS`].
+    pub fn new(value: T) -> Self {
+        Spring {
+            target: value.clone(),
+            current: value.clone(),
+            previous: value,
+            step: 1.0,
+            stiffness: DEFAULT_STIFFNESS,
+            damping: critical_damping(DEFAULT_STIFFNESS),
+        }
+    }
+
+    /// Sets the stiffness and critical damping for it.
+    pub fn with_stiffness(mut self, stiffness: f32) -> Self {
+        self.stiffness = stiffness;
+        self.damping = critical_damping(stiffness);
+        self
+    }
+
+    pub fn with_damping(mut self, damping: f32) -> Self {
+        self.damping = damping;
+        self
+    }
+
+    #[inline]
+    pub fn current(&self) -> &T {
+        &self.current
+    }
+
+    pub fn set_target(&mut self, target: T) {
+        self.target = target;
+    }
+
+    /// Jumps to `value` and stops there, without moving the target.
+    pub fn set_current(&mut self, value: T) {
+        self.current = value.clone();
+        self.previous = value;
+    }
+
+    /// Jumps to `value` and stays there.
+    pub fn reset(&mut self, value: T) {
+        self.target = value.clone();
+        self.set_current(value);
+    }
+
+    pub fn update(&mut self, time: &Time) -> &T {
+        self.update_dt(time.delta().as_secs_f32())
+    }
+
+    /// Moves the value `dt` seconds further.
+    pub fn update_dt(&mut self, dt: f32) -> &T {
+        if dt <= 0.0 {
+            return &self.current;
+        }
+        // offset y = current - target and velocity v = (current - previous) / step after dt:
+        // y' = a * y + b * v and v' = c * y + d * v
+        let [a, b, c, d] = spring_step(self.stiffness, self.damping, dt);
+        let b = b / self.step;
+        let d = d * dt / self.step;
+        let c = c * dt;
+        let next = combine(
+            (&self.target, 1.0 - a),
+            (&self.current, a + b),
+            (&self.previous, -b),
+        );
+        // the value dt ago at the new velocity: next - v' * dt
+        let previous = combine(
+            (&self.target, 1.0 - a + c),
+            (&self.current, a + b - c - d),
+            (&self.previous, d - b),
+        );
+        self.current = next;
+        self.previous = previous;
+        self.step = dt;
+        &self.current
+    }
+}
+
+#[inline]
+pub fn critical_damping(stiffness: f32) -> f32 {
+    2.0 * stiffness.max(0.0).sqrt()
+}
+
+/// Exact solution of `y'' = -stiffness * y - damping * y'` after `dt` for offset and velocity,
+/// as the factors `[a, b, c, d]` with `y' = a * y + b * v` and `v' = c * y + d * v`.
+fn spring_step(stiffness: f32, damping: f32, dt: f32) -> [f32; 4] {
+    if stiffness <= 0.0 {
+        // no spring, the velocity just decays
+        let e = (-damping * dt).exp();
+        let b = if damping > 0.0 {
+            (1.0 - e) / damping
+        } else {
+            dt
+        };
+        return [1.0, b, 0.0, e];
+    }
+    let omega = stiffness.sqrt();
+    let zeta = damping / (2.0 * omega);
+    if (zeta - 1.0).abs() < 1e-4 {
+        let e = (-omega * dt).exp();
+        [
+            (1.0 + omega * dt) * e,
+            dt * e,
+            -stiffness * dt * e,
+            (1.0 - omega * dt) * e,
+        ]
+    } else if zeta < 1.0 {
+        let omega_d = omega * (1.0 - zeta * zeta).sqrt();
+        let e = (-zeta * omega * dt).exp();
+        let (sin, cos) = (omega_d * dt).sin_cos();
+        let z = zeta * omega / omega_d;
+        [
+            e * (cos + z * sin),
+            e * sin / omega_d,
+            -e * stiffness / omega_d * sin,
+            e * (cos - z * sin),
+        ]
+    } else {
+        let s = (zeta * zeta - 1.0).sqrt();
+        let r1 = -omega * (zeta - s);
+        let r2 = -omega * (zeta + s);
+        let e1 = (r1 * dt).exp();
+        let e2 = (r2 * dt).exp();
+        let div = r1 - r2;
+        [
+            (r1 * e2 - r2 * e1) / div,
+            (e1 - e2) / div,
+            r1 * r2 * (e2 - e1) / div,
+            (r1 * e1 - r2 * e2) / div,
+        ]
+    }
+}
+
+/// The sum of the values times their weights, for weights that add up to 1, built from two lerps.
+fn combine<T: Lerp>(a: (&T, f32), b: (&T, f32), c: (&T, f32)) -> T {
+    // lerp between the two values whose weights add up to the most, to not divide by ~0.
+    let (a, b, c) = if (b.1 + c.1).abs() >= (a.1 + c.1).abs().max((a.1 + b.1).abs()) {
+        (a, b, c)
+    } else if (a.1 + c.1).abs() >= (a.1 + b.1).abs() {
+        (b, a, c)
+    } else {
+        (c, a, b)
+    };
+    let bc = b.1 + c.1;
+    let inner = b.0.lerp(c.0, c.1 / bc);
+    a.0.lerp(&inner, bc)
+}
+
+/// Verlet steps per second of [`VerletChain`].
+const VERLET_STEPS_PER_SECOND: f32 = 120.0;
+
+/// Max number of steps in one update, such that a long frame does not cause even longer ones.
+const MAX_VERLET_STEPS: usize = 8;
+
+/// A point moved by verlet integration: the velocity is the difference to the position of the last step.
+#[derive(Debug, Clone, Copy)]
+pub struct VerletPoint<P: SplinePoint> {
+    pub pos: P,
+    pub previous: P,
+    /// Pinned points are not moved by the simulation, only by setting `pos`.
+    pub pinned: bool,
+}
+
+impl<P: SplinePoint> VerletPoint<P> {
+    pub fn new(pos: P) -> Self {
+        VerletPoint {
+            pos,
+            previous: pos,
+            pinned: false,
+        }
+    }
+
+    /// Moves the point one step of `dt` seconds. `friction` is the fraction of the velocity kept per step.
+    pub fn integrate(&mut self, acceleration: P, friction: f32, dt: f32) {
+        if self.pinned {
+            self.previous = self.pos;
+            return;
+        }
+        let velocity = self.pos - self.previous;
+        self.previous = self.pos;
+        self.pos = self.pos + velocity * friction + acceleration * (dt * dt);
+    }
+}
+
+/// Points connected by segments of fixed length, e.g. for ropes, tails or dangling ui decorations.
+/// The first point is pinned, move it with [`VerletChain::set_anchor`].
+#[derive(Debug, Clone)]
+pub struct VerletChain<P: SplinePoint> {
+    pub points: Vec<VerletPoint<P>>,
+    pub segment_len: f32,
+    pub gravity: P,
+    /// Loss of velocity, in 1/s.
+    pub damping: f32,
+    /// How often the segment lengths are corrected per step, more makes the chain less stretchy.
+    pub iterations: usize,
+    /// time that was not simulated yet, less than one step.
+    accumulated: f32,
+}
+
+impl<P: SplinePoint> VerletChain<P> {
+    /// A chain of `n_points` (at least 2) on a straight line from `start` to `end`, without gravity.
+    pub fn new(start: P, end: P, n_points: usize) -> Self {
+        let n_points = n_points.max(2);
+        let points: Vec<VerletPoint<P>> = (0..n_points)
+            .map(|i| {
+                let t = i as f32 / (n_points - 1) as f32;
+                VerletPoint::new(start + (end - start) * t)
+            })
+            .collect();
+        let mut chain = VerletChain {
+            points,
+            segment_len: (end - start).length() / (n_points - 1) as f32,
+            gravity: start * 0.0,
+            damping: 1.0,
+            iterations: 8,
+            accumulated: 0.0,
+        };
+        chain.points[0].pinned = true;
+        chain
+    }
+
+    pub fn with_gravity(mut self, gravity: P) -> Self {
+        self.gravity = gravity;
+        self
+    }
+
+    /// Moves the first point, the rest of the chain follows in the next updates.
+    pub fn set_anchor(&mut self, pos: P) {
+        self.points[0].pos = pos;
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = P> + '_ {
+        self.points.iter().map(|p| p.pos)
+    }
+
+    pub fn update(&mut self, time: &Time) {
+        self.update_dt(time.delta().as_secs_f32());
+    }
+
+    /// Simulates `dt` seconds in fixed steps, so the chain moves the same at every frame rate.
+    pub fn update_dt(&mut self, dt: f32) {
+        let step = 1.0 / VERLET_STEPS_PER_SECOND;
+        self.accumulated = (self.accumulated + dt).min(step * MAX_VERLET_STEPS as f32);
+        let friction = (-self.damping * step).exp();
+        while self.accumulated >= step {
+            self.accumulated -= step;
+            for point in self.points.iter_mut() {
+                point.integrate(self.gravity, friction, step);
+            }
+            for _ in 0..self.iterations {
+                self.constrain();
+            }
+        }
+    }
+
+    /// Moves the ends of each segment towards each other or apart, to restore its length.
+    fn constrain(&mut self) {
+        for i in 1..self.points.len() {
+            let (a, b) = (self.points[i - 1], self.points[i]);
+            let diff = b.pos - a.pos;
+            let len = diff.length();
+            if len <= f32::EPSILON {
+                continue;
+            }
+            let correction = diff * ((len - self.segment_len) / len);
+            match (a.pinned, b.pinned) {
+                (true, true) => {}
+                (true, false) => self.points[i].pos = b.pos - correction,
+                (false, true) => self.points[i - 1].pos = a.pos + correction,
+                (false, false) => {
+                    self.points[i - 1].pos = a.pos + correction * 0.5;
+                    self.points[i].pos = b.pos - correction * 0.5;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{vec2, Vec2};
+
+    use super::{Spring, VerletChain};
+
+    #[test]
+    fn springs_are_frame_rate_independent() {
+        for damping in [4.0, 20.0, 40.0] {
+            let run = |steps: usize| {
+                let mut spring = Spring::new(Vec2::ZERO)
+                    .with_stiffness(100.0)
+                    .with_damping(damping);
+                spring.target = vec2(10.0, 0.0);
+                for i in 0..steps {
+                    if i == steps / 2 {
+                        spring.target = vec2(0.0, 5.0);
+                    }
+                    spring.update_dt(1.0 / steps as f32);
+                }
+                *spring.current()
+            };
+            let slow = run(30);
+            let fast = run(240);
+            assert!(slow.distance(fast) < 1e-3, "{slow} != {fast}");
+        }
+
+        let mut spring = Spring::new(0.0f32);
+        spring.target = 1.0;
+        for _ in 0..100 {
+            spring.update_dt(0.05);
+            assert!(
+                *spring.current() <= 1.0 + 1e-5,
+                "critically damped springs overshoot"
+            );
+        }
+        assert!((spring.current() - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn chains_hang_down() {
+        let mut chain =
+            VerletChain::new(Vec2::ZERO, vec2(4.0, 0.0), 5).with_gravity(vec2(0.0, -10.0));
+        for _ in 0..600 {
+            chain.update_dt(1.0 / 60.0);
+        }
+        let end = chain.points.last().unwrap().pos;
+        assert!(end.x.abs() < 0.1 && (end.y + 4.0).abs() < 0.1, "{end}");
+        assert_eq!(chain.points[0].pos, Vec2::ZERO);
+    }
+}
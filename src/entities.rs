@@ -0,0 +1,315 @@
+use std::{
+    any::{Any, TypeId},
+    fmt::Debug,
+};
+
+use ahash::AHashMap;
+
+/// Identifies an entity in an [`EntityStore`]. Contains a generation, such that handles to despawned entities
+/// are never confused with entities that were spawned later into the same slot.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Entity {
+    index: u32,
+    generation: u32,
+}
+
+impl Entity {
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+}
+
+impl Debug for Entity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Entity({}v{})", self.index, self.generation)
+    }
+}
+
+/// Components of one type, stored densely (sparse set), such that iterating over them is fast.
+#[derive(Debug)]
+pub struct ComponentStorage<T> {
+    /// entity index -> index into `dense`
+    sparse: Vec<Option<u32>>,
+    entities: Vec<Entity>,
+    dense: Vec<T>,
+}
+
+impl<T> Default for ComponentStorage<T> {
+    fn default() -> Self {
+        Self {
+            sparse: vec![],
+            entities: vec![],
+            dense: vec![],
+        }
+    }
+}
+
+impl<T> ComponentStorage<T> {
+    fn dense_index(&self, entity: Entity) -> Option<usize> {
+        let i = (*self.sparse.get(entity.index as usize)?)? as usize;
+        (self.entities[i] == entity).then_some(i)
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        self.dense_index(entity).map(|i| &self.dense[i])
+    }
+
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        self.dense_index(entity).map(|i| &mut self.dense[i])
+    }
+
+    fn insert(&mut self, entity: Entity, component: T) -> Option<T> {
+        if let Some(i) = self.dense_index(entity) {
+            return Some(std::mem::replace(&mut self.dense[i], component));
+        }
+        let index = entity.index as usize;
+        if self.sparse.len() <= index {
+            self.sparse.resize(index + 1, None);
+        }
+        self.sparse[index] = Some(self.dense.len() as u32);
+        self.entities.push(entity);
+        self.dense.push(component);
+        None
+    }
+
+    fn remove(&mut self, entity: Entity) -> Option<T> {
+        let i = self.dense_index(entity)?;
+        self.sparse[entity.index as usize] = None;
+        self.entities.swap_remove(i);
+        let component = self.dense.swap_remove(i);
+        if let Some(moved) = self.entities.get(i) {
+            self.sparse[moved.index as usize] = Some(i as u32);
+        }
+        Some(component)
+    }
+
+    pub fn len(&self) -> usize {
+        self.dense.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dense.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &T)> {
+        self.entities.iter().copied().zip(self.dense.iter())
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+        self.entities.iter().copied().zip(self.dense.iter_mut())
+    }
+}
+
+/// Type erased [`ComponentStorage`], such that despawning can remove components of all types.
+trait AnyStorage: Any {
+    fn remove_entity(&mut self, entity: Entity);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: 'static> AnyStorage for ComponentStorage<T> {
+    fn remove_entity(&mut self, entity: Entity) {
+        self.remove(entity);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Minimal entity component store: entities are generational ids, components of any `'static` type
+/// can be attached to them and iterated over per type.
+///
+/// ```rust,ignore
+/// let mut entities = EntityStore::new();
+/// let e = entities.spawn();
+/// entities.insert(e, Transform::new(0.0, 1.0, 0.0));
+/// entities.insert(e, Color::RED);
+/// for (_, transform, color) in entities.query2::<Transform, Color>() {
+///     world.color_renderer.borrow_mut().draw_cubes(&[(*transform, *color)]);
+/// }
+/// ```
+#[derive(Default)]
+pub struct EntityStore {
+    /// generation of each slot. Odd generations are alive, even ones free.
+    generations: Vec<u32>,
+    free: Vec<u32>,
+    storages: AHashMap<TypeId, Box<dyn AnyStorage>>,
+    len: usize,
+}
+
+impl Debug for EntityStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EntityStore")
+            .field("len", &self.len)
+            .field("component_types", &self.storages.len())
+            .finish()
+    }
+}
+
+impl EntityStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn spawn(&mut self) -> Entity {
+        self.len += 1;
+        if let Some(index) = self.free.pop() {
+            let generation = &mut self.generations[index as usize];
+            *generation += 1;
+            return Entity {
+                index,
+                generation: *generation,
+            };
+        }
+        self.generations.push(1);
+        Entity {
+            index: self.generations.len() as u32 - 1,
+            generation: 1,
+        }
+    }
+
+    /// Removes the entity and all of its components. Returns false if it was not alive.
+    pub fn despawn(&mut self, entity: Entity) -> bool {
+        if !self.is_alive(entity) {
+            return false;
+        }
+        for storage in self.storages.values_mut() {
+            storage.remove_entity(entity);
+        }
+        self.generations[entity.index as usize] += 1;
+        self.free.push(entity.index);
+        self.len -= 1;
+        true
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.generations.get(entity.index as usize) == Some(&entity.generation)
+    }
+
+    /// Number of alive entities.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Attaches a component to the entity, returns the component of the same type it had before.
+    ///
+    /// Panics if the entity is not alive.
+    pub fn insert<T: 'static>(&mut self, entity: Entity, component: T) -> Option<T> {
+        assert!(self.is_alive(entity), "{entity:?} is not alive");
+        self.storage_or_default::<T>().insert(entity, component)
+    }
+
+    pub fn remove<T: 'static>(&mut self, entity: Entity) -> Option<T> {
+        self.storage_mut::<T>()?.remove(entity)
+    }
+
+    pub fn get<T: 'static>(&self, entity: Entity) -> Option<&T> {
+        self.storage::<T>()?.get(entity)
+    }
+
+    pub fn get_mut<T: 'static>(&mut self, entity: Entity) -> Option<&mut T> {
+        self.storage_mut::<T>()?.get_mut(entity)
+    }
+
+    pub fn has<T: 'static>(&self, entity: Entity) -> bool {
+        self.get::<T>(entity).is_some()
+    }
+
+    pub fn storage<T: 'static>(&self) -> Option<&ComponentStorage<T>> {
+        let storage = self.storages.get(&TypeId::of::<T>())?;
+        storage.as_any().downcast_ref()
+    }
+
+    pub fn storage_mut<T: 'static>(&mut self) -> Option<&mut ComponentStorage<T>> {
+        let storage = self.storages.get_mut(&TypeId::of::<T>())?;
+        storage.as_any_mut().downcast_mut()
+    }
+
+    fn storage_or_default<T: 'static>(&mut self) -> &mut ComponentStorage<T> {
+        self.storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::<ComponentStorage<T>>::default())
+            .as_any_mut()
+            .downcast_mut()
+            .unwrap()
+    }
+
+    /// All entities with a component of type `T`.
+    pub fn query<T: 'static>(&self) -> impl Iterator<Item = (Entity, &T)> {
+        self.storage::<T>().into_iter().flat_map(|s| s.iter())
+    }
+
+    pub fn query_mut<T: 'static>(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+        self.storage_mut::<T>()
+            .into_iter()
+            .flat_map(|s| s.iter_mut())
+    }
+
+    /// All entities with components of both types.
+    pub fn query2<A: 'static, B: 'static>(&self) -> impl Iterator<Item = (Entity, &A, &B)> {
+        let b = self.storage::<B>();
+        self.query::<A>()
+            .filter_map(move |(e, a)| Some((e, a, b?.get(e)?)))
+    }
+
+    /// Calls `f` for all entities with components of both types, with mutable access to the `A` component.
+    ///
+    /// Panics if `A` and `B` are the same type.
+    pub fn for_each2_mut<A: 'static, B: 'static>(&mut self, mut f: impl FnMut(Entity, &mut A, &B)) {
+        assert_ne!(TypeId::of::<A>(), TypeId::of::<B>());
+        // take out the storage of B, such that A can be borrowed mutably at the same time:
+        let Some(b) = self.storages.remove(&TypeId::of::<B>()) else {
+            return;
+        };
+        let b_storage: &ComponentStorage<B> = b.as_any().downcast_ref().unwrap();
+        for (e, a) in self.query_mut::<A>() {
+            if let Some(b) = b_storage.get(e) {
+                f(e, a, b);
+            }
+        }
+        self.storages.insert(TypeId::of::<B>(), b);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EntityStore;
+
+    #[test]
+    fn despawn_removes_components_and_invalidates_entity() {
+        let mut store = EntityStore::new();
+        let a = store.spawn();
+        let b = store.spawn();
+        store.insert(a, 1u32);
+        store.insert(b, 2u32);
+        store.insert(b, "b");
+
+        assert!(store.despawn(a));
+        assert!(!store.is_alive(a));
+        assert_eq!(store.get::<u32>(a), None);
+
+        // the slot of a is reused, but the old entity does not see the new components:
+        let c = store.spawn();
+        assert_eq!(c.index(), a.index());
+        store.insert(c, 3u32);
+        assert_eq!(store.get::<u32>(a), None);
+        assert_eq!(store.get::<u32>(c), Some(&3));
+
+        store.for_each2_mut::<u32, &str>(|_, n, _| *n += 10);
+        let pairs: Vec<_> = store
+            .query2::<u32, &str>()
+            .map(|(e, n, _)| (e, *n))
+            .collect();
+        assert_eq!(pairs, vec![(b, 12)]);
+        assert_eq!(store.len(), 2);
+    }
+}
@@ -0,0 +1,38 @@
+/// Errors of the fallible constructors and loaders of tgf, so applications can handle failures
+/// like a missing gpu gracefully instead of panicking.
+///
+/// Apis that are fallible only because of programming errors (e.g. invalid built-in shaders) have panicking
+/// convenience variants next to the `try_` ones, like [`crate::ShaderCache::register`].
+/// Errors of loaders that still return `anyhow::Result` convert into [`Error::Other`] with `?`.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("no graphics adapter compatible with the window surface was found")]
+    NoAdapter,
+    #[error("failed to get the handle of the window: {0}")]
+    WindowHandle(String),
+    #[error("failed to create the window surface: {0}")]
+    CreateSurface(#[from] wgpu::CreateSurfaceError),
+    #[error("failed to request the graphics device: {0}")]
+    RequestDevice(#[from] wgpu::RequestDeviceError),
+    #[error("surface format {format:?} is not supported, the surface supports {supported:?}")]
+    UnsupportedSurfaceFormat {
+        format: wgpu::TextureFormat,
+        supported: Vec<wgpu::TextureFormat>,
+    },
+    #[error("invalid wgsl in shader of {files:?}: {message}")]
+    InvalidShader {
+        files: Vec<&'static str>,
+        message: String,
+    },
+    #[error("invalid font data: {0}")]
+    InvalidFont(&'static str),
+    #[cfg(feature = "clipboard")]
+    #[error("clipboard error: {0}")]
+    Clipboard(#[from] arboard::Error),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
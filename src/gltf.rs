@@ -0,0 +1,661 @@
+//! Loading of static meshes from glTF 2.0 files (`.gltf` and `.glb`), to draw them with a
+//! [`crate::MeshRenderer`].
+//!
+//! The meshes of the default scene are read with their positions, normals, uvs and indices, node transforms are
+//! baked into the vertices. Materials keep only the base color and base color texture. Animations, skins, morph
+//! targets and sparse accessors are not supported.
+//!
+//! ```rust,ignore
+//! let mut models: AssetServer<GltfModel> = AssetServer::new();
+//! let ship = models.load("assets/ship.glb")?;
+//! let ship_gpu = GpuModel::new(&ctx, models.get(&ship).unwrap());
+//! // in the render pass:
+//! mesh_renderer.render(&mut pass, &camera_gr, &ship_gpu, &transform);
+//! ```
+
+use std::{collections::HashMap, path::Path};
+
+use anyhow::{anyhow, bail};
+use glam::{Mat4, Quat, Vec2, Vec3};
+use image::RgbaImage;
+use serde::Deserialize;
+
+use crate::{AssetT, Color, MeshData};
+
+/// The triangles of a glTF file, split by material.
+#[derive(Debug, Clone, Default)]
+pub struct GltfModel {
+    pub primitives: Vec<GltfPrimitive>,
+    /// Images referenced by [`GltfMaterial::base_color_texture`].
+    pub images: Vec<RgbaImage>,
+}
+
+/// One primitive of a glTF mesh, in the space of the scene.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GltfPrimitive {
+    pub mesh: MeshData,
+    pub material: GltfMaterial,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GltfMaterial {
+    /// Multiplied with the texture, white if the file does not say otherwise.
+    pub base_color: Color,
+    /// Index into [`GltfModel::images`].
+    pub base_color_texture: Option<usize>,
+}
+
+impl Default for GltfMaterial {
+    fn default() -> Self {
+        GltfMaterial {
+            base_color: Color::WHITE,
+            base_color_texture: None,
+        }
+    }
+}
+
+impl AssetT for GltfModel {
+    /// Only supports files that contain all their data, glb files or gltf files with data uris.
+    fn from_bytes(bytes: &[u8]) -> Result<Self, anyhow::Error> {
+        GltfModel::parse(bytes, |uri| {
+            Err(anyhow!(
+                "external file {uri:?} can only be resolved when loading from a path"
+            ))
+        })
+    }
+
+    /// Resolves external buffers and images relative to the directory of the file.
+    fn load(path: &str) -> Result<Self, anyhow::Error> {
+        let bytes = std::fs::read(path)?;
+        let dir = Path::new(path).parent().unwrap_or(Path::new(""));
+        GltfModel::parse(&bytes, |uri| Ok(std::fs::read(dir.join(uri))?))
+    }
+}
+
+impl GltfModel {
+    /// Parses a glb file or gltf json. `resolve` reads the uris of external buffers and images that are not
+    /// data uris.
+    pub fn parse(
+        bytes: &[u8],
+        resolve: impl Fn(&str) -> anyhow::Result<Vec<u8>>,
+    ) -> anyhow::Result<GltfModel> {
+        let (json, bin) = split_glb(bytes)?;
+        let root: Root = serde_json::from_slice(json)?;
+        let load_uri = |uri: &str| match uri.strip_prefix("data:") {
+            Some(data) => {
+                let (_, base64) = data
+                    .split_once(";base64,")
+                    .ok_or_else(|| anyhow!("data uri is not base64 encoded"))?;
+                decode_base64(base64)
+            }
+            None => resolve(uri),
+        };
+
+        let buffers: Vec<Vec<u8>> = root
+            .buffers
+            .iter()
+            .map(|buffer| match &buffer.uri {
+                Some(uri) => load_uri(uri),
+                None => bin
+                    .map(|bin| bin.to_vec())
+                    .ok_or_else(|| anyhow!("buffer without uri outside of a glb file")),
+            })
+            .collect::<anyhow::Result<_>>()?;
+        let reader = Reader {
+            root: &root,
+            buffers: &buffers,
+        };
+
+        let images = root
+            .images
+            .iter()
+            .map(|image| {
+                let bytes = match (&image.uri, image.buffer_view) {
+                    (Some(uri), _) => load_uri(uri)?,
+                    (None, Some(view)) => reader.view_bytes(view)?.to_vec(),
+                    (None, None) => bail!("image without uri or buffer view"),
+                };
+                RgbaImage::from_bytes(&bytes)
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        let mut primitives = vec![];
+        for (mesh, transform) in reader.mesh_instances()? {
+            let Some(json) = root.meshes.get(mesh) else {
+                bail!("mesh {mesh} does not exist");
+            };
+            for primitive in json.primitives.iter() {
+                primitives.push(reader.primitive(primitive, transform)?);
+            }
+        }
+        Ok(GltfModel { primitives, images })
+    }
+}
+
+/// The json and the binary chunk of a glb file, or the bytes as json if they are no glb file.
+fn split_glb(bytes: &[u8]) -> anyhow::Result<(&[u8], Option<&[u8]>)> {
+    const MAGIC: u32 = 0x46546C67; // "glTF"
+    const JSON: u32 = 0x4E4F534A;
+    const BIN: u32 = 0x004E4942;
+    let u32_at = |offset: usize| -> Option<u32> {
+        let bytes = bytes.get(offset..offset + 4)?;
+        Some(u32::from_le_bytes(bytes.try_into().unwrap()))
+    };
+    if u32_at(0) != Some(MAGIC) {
+        return Ok((bytes, None));
+    }
+    let mut json = None;
+    let mut bin = None;
+    let mut offset = 12;
+    while let (Some(len), Some(ty)) = (u32_at(offset), u32_at(offset + 4)) {
+        let start = offset + 8;
+        let chunk = bytes
+            .get(start..start + len as usize)
+            .ok_or_else(|| anyhow!("glb chunk is out of bounds"))?;
+        match ty {
+            JSON => json = Some(chunk),
+            BIN => bin = Some(chunk),
+            _ => {}
+        }
+        offset = start + len as usize;
+    }
+    let json = json.ok_or_else(|| anyhow!("glb file without json chunk"))?;
+    Ok((json, bin))
+}
+
+struct Reader<'a> {
+    root: &'a Root,
+    buffers: &'a [Vec<u8>],
+}
+
+impl<'a> Reader<'a> {
+    /// The meshes of the default scene with the world transforms of their nodes. All meshes without
+    /// transform if the file has no scenes.
+    fn mesh_instances(&self) -> anyhow::Result<Vec<(usize, Mat4)>> {
+        let root = self.root;
+        let scene = root.scene.or((!root.scenes.is_empty()).then_some(0));
+        let Some(scene) = scene else {
+            return Ok((0..root.meshes.len())
+                .map(|i| (i, Mat4::IDENTITY))
+                .collect());
+        };
+        let Some(scene) = root.scenes.get(scene) else {
+            bail!("scene {scene} does not exist");
+        };
+        let mut instances = vec![];
+        let mut stack: Vec<(usize, Mat4, usize)> = scene
+            .nodes
+            .iter()
+            .map(|node| (*node, Mat4::IDENTITY, 0))
+            .collect();
+        while let Some((index, parent, depth)) = stack.pop() {
+            let Some(node) = root.nodes.get(index) else {
+                bail!("node {index} does not exist");
+            };
+            // the nodes form a tree, deeper nesting than the number of nodes means there is a cycle.
+            if depth > root.nodes.len() {
+                bail!("node {index} is its own ancestor");
+            }
+            let transform = parent * node.local_transform();
+            if let Some(mesh) = node.mesh {
+                instances.push((mesh, transform));
+            }
+            stack.extend(node.children.iter().map(|c| (*c, transform, depth + 1)));
+        }
+        Ok(instances)
+    }
+
+    fn primitive(&self, json: &PrimitiveJson, transform: Mat4) -> anyhow::Result<GltfPrimitive> {
+        if json.mode.is_some_and(|mode| mode != TRIANGLES) {
+            bail!("only triangle primitives are supported");
+        }
+        let attribute = |name: &str| json.attributes.get(name).copied();
+        let position =
+            attribute("POSITION").ok_or_else(|| anyhow!("primitive without POSITION attribute"))?;
+        let mut mesh = MeshData {
+            positions: self.read_floats(position, 3, Vec3::from_slice)?,
+            ..Default::default()
+        };
+        mesh.indices = match json.indices {
+            Some(indices) => self.read_indices(indices)?,
+            None => (0..mesh.positions.len() as u32).collect(),
+        };
+        if let Some(max) = mesh.indices.iter().max() {
+            if *max as usize >= mesh.positions.len() {
+                bail!("index {max} is out of bounds");
+            }
+        }
+        mesh.normals = match attribute("NORMAL") {
+            Some(normals) => self.read_floats(normals, 3, Vec3::from_slice)?,
+            None => smooth_normals(&mesh.positions, &mesh.indices),
+        };
+        mesh.uvs = match attribute("TEXCOORD_0") {
+            Some(uvs) => self.read_floats(uvs, 2, Vec2::from_slice)?,
+            None => vec![Vec2::ZERO; mesh.positions.len()],
+        };
+        if mesh.normals.len() != mesh.positions.len() || mesh.uvs.len() != mesh.positions.len() {
+            bail!("attributes of a primitive have different lengths");
+        }
+        bake_transform(&mut mesh, transform);
+
+        let mut material = GltfMaterial::default();
+        if let Some(index) = json.material {
+            let Some(json) = self.root.materials.get(index) else {
+                bail!("material {index} does not exist");
+            };
+            let pbr = &json.pbr_metallic_roughness;
+            let [r, g, b, a] = pbr.base_color_factor;
+            material.base_color = Color { r, g, b, a };
+            if let Some(info) = &pbr.base_color_texture {
+                material.base_color_texture =
+                    self.root.textures.get(info.index).and_then(|t| t.source);
+            }
+        }
+        Ok(GltfPrimitive { mesh, material })
+    }
+
+    fn view_bytes(&self, index: usize) -> anyhow::Result<&'a [u8]> {
+        let view = self
+            .root
+            .buffer_views
+            .get(index)
+            .ok_or_else(|| anyhow!("buffer view {index} does not exist"))?;
+        self.buffers
+            .get(view.buffer)
+            .and_then(|b| b.get(view.byte_offset..view.byte_offset + view.byte_length))
+            .ok_or_else(|| anyhow!("buffer view {index} is out of bounds"))
+    }
+
+    /// Reads the elements of the accessor as floats, converting integers and normalizing them if the accessor
+    /// says so. Each element is `n_components` floats, that `f` turns into a value.
+    fn read_floats<T>(
+        &self,
+        index: usize,
+        n_components: usize,
+        f: impl Fn(&[f32]) -> T,
+    ) -> anyhow::Result<Vec<T>> {
+        let accessor = self.accessor(index)?;
+        let components = accessor.n_components()?;
+        if components != n_components {
+            bail!("accessor {index} has {components} components instead of {n_components}");
+        }
+        let mut element = vec![0.0; n_components];
+        self.read_elements(accessor, |bytes, size| {
+            for (i, c) in element.iter_mut().enumerate() {
+                let bytes = &bytes[i * size..];
+                *c = read_component(bytes, accessor.component_type, accessor.normalized);
+            }
+            f(&element)
+        })
+    }
+
+    fn read_indices(&self, index: usize) -> anyhow::Result<Vec<u32>> {
+        let accessor = self.accessor(index)?;
+        if !matches!(accessor.component_type, U8 | U16 | U32) {
+            bail!("indices need an unsigned integer type");
+        }
+        self.read_elements(accessor, |bytes, _| {
+            read_index(bytes, accessor.component_type)
+        })
+    }
+
+    fn accessor(&self, index: usize) -> anyhow::Result<&'a Accessor> {
+        let accessor = self
+            .root
+            .accessors
+            .get(index)
+            .ok_or_else(|| anyhow!("accessor {index} does not exist"))?;
+        if accessor.sparse.is_some() {
+            bail!("sparse accessors are not supported");
+        }
+        Ok(accessor)
+    }
+
+    /// Calls `f` with the bytes of each element of the accessor and the size of its components.
+    fn read_elements<T>(
+        &self,
+        accessor: &Accessor,
+        mut f: impl FnMut(&[u8], usize) -> T,
+    ) -> anyhow::Result<Vec<T>> {
+        let component_size = match accessor.component_type {
+            I8 | U8 => 1,
+            I16 | U16 => 2,
+            U32 | F32 => 4,
+            other => bail!("unknown component type {other}"),
+        };
+        let element_size = component_size * accessor.n_components()?;
+        let Some(view_index) = accessor.buffer_view else {
+            // accessors without buffer view are all zeros.
+            let zeros = vec![0u8; element_size];
+            return Ok((0..accessor.count)
+                .map(|_| f(&zeros, component_size))
+                .collect());
+        };
+        let bytes = self.view_bytes(view_index)?;
+        let stride = self.root.buffer_views[view_index]
+            .byte_stride
+            .unwrap_or(element_size);
+        (0..accessor.count)
+            .map(|i| {
+                let start = accessor.byte_offset + i * stride;
+                let element = bytes
+                    .get(start..start + element_size)
+                    .ok_or_else(|| anyhow!("accessor is out of bounds of its buffer view"))?;
+                Ok(f(element, component_size))
+            })
+            .collect()
+    }
+}
+
+/// The first component in `bytes` as a float.
+fn read_component(bytes: &[u8], component_type: u32, normalized: bool) -> f32 {
+    let (value, max) = match component_type {
+        I8 => (bytes[0] as i8 as f32, 127.0),
+        U8 => (bytes[0] as f32, 255.0),
+        I16 => (i16::from_le_bytes([bytes[0], bytes[1]]) as f32, 32767.0),
+        U16 => (u16::from_le_bytes([bytes[0], bytes[1]]) as f32, 65535.0),
+        U32 => (read_index(bytes, U32) as f32, 1.0),
+        _ => (f32::from_le_bytes(bytes[..4].try_into().unwrap()), 1.0),
+    };
+    if normalized {
+        (value / max).max(-1.0)
+    } else {
+        value
+    }
+}
+
+/// The first component in `bytes`, which is an unsigned integer.
+fn read_index(bytes: &[u8], component_type: u32) -> u32 {
+    match component_type {
+        U8 => bytes[0] as u32,
+        U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as u32,
+        _ => u32::from_le_bytes(bytes[..4].try_into().unwrap()),
+    }
+}
+
+/// Moves the mesh into the space of the scene. Mirroring transforms flip the triangles, so that they
+/// stay counter clockwise.
+fn bake_transform(mesh: &mut MeshData, transform: Mat4) {
+    if transform == Mat4::IDENTITY {
+        return;
+    }
+    let normal_matrix = transform.inverse().transpose();
+    for pos in mesh.positions.iter_mut() {
+        *pos = transform.transform_point3(*pos);
+    }
+    for normal in mesh.normals.iter_mut() {
+        *normal = normal_matrix.transform_vector3(*normal).normalize_or_zero();
+    }
+    if transform.determinant() < 0.0 {
+        for triangle in mesh.indices.chunks_exact_mut(3) {
+            triangle.swap(1, 2);
+        }
+    }
+}
+
+/// Normals averaged over the triangles around each vertex, weighted by their area.
+fn smooth_normals(positions: &[Vec3], indices: &[u32]) -> Vec<Vec3> {
+    let mut normals = vec![Vec3::ZERO; positions.len()];
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| positions[triangle[i] as usize]);
+        let normal = (b - a).cross(c - a);
+        for i in triangle {
+            normals[*i as usize] += normal;
+        }
+    }
+    normals.iter().map(|n| n.normalize_or_zero()).collect()
+}
+
+fn decode_base64(text: &str) -> anyhow::Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let mut bits: u32 = 0;
+    let mut n_bits = 0;
+    for ch in text.bytes().take_while(|ch| *ch != b'=') {
+        let value = match ch {
+            b'A'..=b'Z' => ch - b'A',
+            b'a'..=b'z' => ch - b'a' + 26,
+            b'0'..=b'9' => ch - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => bail!("invalid base64 character {:?}", ch as char),
+        };
+        bits = (bits << 6) | value as u32;
+        n_bits += 6;
+        if n_bits >= 8 {
+            n_bits -= 8;
+            bytes.push((bits >> n_bits) as u8);
+        }
+    }
+    Ok(bytes)
+}
+
+const TRIANGLES: u32 = 4;
+const I8: u32 = 5120;
+const U8: u32 = 5121;
+const I16: u32 = 5122;
+const U16: u32 = 5123;
+const U32: u32 = 5125;
+const F32: u32 = 5126;
+
+// /////////////////////////////////////////////////////////////////////////////
+// The parts of the glTF json that are read
+// /////////////////////////////////////////////////////////////////////////////
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Root {
+    #[serde(default)]
+    buffers: Vec<BufferJson>,
+    #[serde(default)]
+    buffer_views: Vec<BufferView>,
+    #[serde(default)]
+    accessors: Vec<Accessor>,
+    #[serde(default)]
+    meshes: Vec<MeshJson>,
+    #[serde(default)]
+    materials: Vec<MaterialJson>,
+    #[serde(default)]
+    textures: Vec<TextureJson>,
+    #[serde(default)]
+    images: Vec<ImageJson>,
+    #[serde(default)]
+    nodes: Vec<Node>,
+    #[serde(default)]
+    scenes: Vec<SceneJson>,
+    scene: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BufferJson {
+    uri: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BufferView {
+    buffer: usize,
+    #[serde(default)]
+    byte_offset: usize,
+    byte_length: usize,
+    byte_stride: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Accessor {
+    buffer_view: Option<usize>,
+    #[serde(default)]
+    byte_offset: usize,
+    component_type: u32,
+    #[serde(default)]
+    normalized: bool,
+    count: usize,
+    #[serde(rename = "type")]
+    ty: String,
+    sparse: Option<serde_json::Value>,
+}
+
+impl Accessor {
+    fn n_components(&self) -> anyhow::Result<usize> {
+        Ok(match self.ty.as_str() {
+            "SCALAR" => 1,
+            "VEC2" => 2,
+            "VEC3" => 3,
+            "VEC4" | "MAT2" => 4,
+            "MAT3" => 9,
+            "MAT4" => 16,
+            other => bail!("unknown accessor type {other}"),
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct MeshJson {
+    primitives: Vec<PrimitiveJson>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PrimitiveJson {
+    attributes: HashMap<String, usize>,
+    indices: Option<usize>,
+    material: Option<usize>,
+    mode: Option<u32>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MaterialJson {
+    #[serde(default)]
+    pbr_metallic_roughness: Pbr,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Pbr {
+    #[serde(default = "white")]
+    base_color_factor: [f32; 4],
+    base_color_texture: Option<TextureInfo>,
+}
+
+impl Default for Pbr {
+    fn default() -> Self {
+        Pbr {
+            base_color_factor: white(),
+            base_color_texture: None,
+        }
+    }
+}
+
+fn white() -> [f32; 4] {
+    [1.0; 4]
+}
+
+#[derive(Debug, Deserialize)]
+struct TextureInfo {
+    index: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct TextureJson {
+    source: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ImageJson {
+    uri: Option<String>,
+    buffer_view: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Node {
+    mesh: Option<usize>,
+    #[serde(default)]
+    children: Vec<usize>,
+    matrix: Option<[f32; 16]>,
+    translation: Option<[f32; 3]>,
+    rotation: Option<[f32; 4]>,
+    scale: Option<[f32; 3]>,
+}
+
+impl Node {
+    fn local_transform(&self) -> Mat4 {
+        if let Some(matrix) = &self.matrix {
+            return Mat4::from_cols_array(matrix);
+        }
+        Mat4::from_scale_rotation_translation(
+            self.scale.map(Vec3::from).unwrap_or(Vec3::ONE),
+            self.rotation
+                .map(Quat::from_array)
+                .unwrap_or(Quat::IDENTITY),
+            self.translation.map(Vec3::from).unwrap_or(Vec3::ZERO),
+        )
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SceneJson {
+    #[serde(default)]
+    nodes: Vec<usize>,
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{vec3, Vec3};
+
+    use super::{decode_base64, GltfModel};
+    use crate::AssetT;
+
+    #[test]
+    fn base64_data_is_decoded() {
+        assert_eq!(decode_base64("aGVsbG8=").unwrap(), b"hello");
+        assert_eq!(decode_base64("AAEC").unwrap(), vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn glb_meshes_are_moved_by_their_nodes() {
+        let mut bin: Vec<u8> = vec![];
+        for v in [Vec3::ZERO, Vec3::X, Vec3::Y] {
+            bin.extend_from_slice(bytemuck::bytes_of(&v));
+        }
+        for i in [0u16, 1, 2, 0] {
+            bin.extend_from_slice(&i.to_le_bytes());
+        }
+        let json = r#"{
+            "asset": {"version": "2.0"},
+            "buffers": [{"byteLength": 44}],
+            "bufferViews": [
+                {"buffer": 0, "byteLength": 36},
+                {"buffer": 0, "byteOffset": 36, "byteLength": 6}
+            ],
+            "accessors": [
+                {"bufferView": 0, "componentType": 5126, "count": 3, "type": "VEC3"},
+                {"bufferView": 1, "componentType": 5123, "count": 3, "type": "SCALAR"}
+            ],
+            "meshes": [{"primitives": [{"attributes": {"POSITION": 0}, "indices": 1, "material": 0}]}],
+            "materials": [{"pbrMetallicRoughness": {"baseColorFactor": [1, 0, 0, 1]}}],
+            "nodes": [{"translation": [0, 0, 5], "children": [1]}, {"mesh": 0, "scale": [2, 2, 2]}],
+            "scenes": [{"nodes": [0]}]
+        }"#;
+        let mut json = json.as_bytes().to_vec();
+        json.resize(json.len().next_multiple_of(4), b' ');
+        let mut glb: Vec<u8> = vec![];
+        let total = 12 + 8 + json.len() + 8 + bin.len();
+        for word in [0x46546C67, 2, total as u32, json.len() as u32, 0x4E4F534A] {
+            glb.extend_from_slice(&u32::to_le_bytes(word));
+        }
+        glb.extend_from_slice(&json);
+        glb.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        glb.extend_from_slice(&0x004E4942u32.to_le_bytes());
+        glb.extend_from_slice(&bin);
+
+        let model = GltfModel::from_bytes(&glb).unwrap();
+        assert_eq!(model.primitives.len(), 1);
+        let primitive = &model.primitives[0];
+        assert_eq!(primitive.mesh.indices, vec![0, 1, 2]);
+        assert_eq!(primitive.mesh.positions[1], vec3(2.0, 0.0, 5.0));
+        // no normals in the file, they are computed from the counter clockwise triangle.
+        assert_eq!(primitive.mesh.normals[0], Vec3::Z);
+        assert_eq!(primitive.material.base_color.g, 0.0);
+    }
+}
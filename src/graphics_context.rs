@@ -3,9 +3,12 @@ use std::{
     sync::{Arc, Mutex},
 };
 
+use log::warn;
 use wgpu::SurfaceConfiguration;
 use winit::{dpi::PhysicalSize, window::Window};
 
+use crate::{Error, Resizable};
+
 #[derive(Debug, Clone)]
 pub struct GraphicsContext(Arc<GraphicsContextInner>);
 
@@ -26,6 +29,43 @@ pub struct GraphicsContextInner {
     pub surface: wgpu::Surface<'static>,
     pub surface_format: wgpu::TextureFormat,
     pub surface_config: Mutex<SurfaceConfiguration>,
+    /// The output mode that is actually used, can differ from the configured one if the surface does not support it.
+    pub output_mode: OutputMode,
+    /// Output modes the surface supports, e.g. to only offer a HDR toggle where it works.
+    pub supported_output_modes: Vec<OutputMode>,
+}
+
+/// How the final image is encoded for the display.
+///
+/// Switching the output mode changes the surface format, so the graphics context and all renderers writing
+/// to the surface (tone mapping, ui, egui) need to be recreated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u32)]
+pub enum OutputMode {
+    /// The `surface_format` of the [`GraphicsContextConfig`], usually sRGB.
+    #[default]
+    Sdr,
+    /// Rgba16Float surface in extended linear sRGB (scRGB), where 1.0 is 80 nits. Mostly supported on Windows.
+    ScRgb,
+    /// Rgb10a2Unorm surface with Rec.2020 primaries and ST.2084 (PQ) encoding.
+    Hdr10,
+}
+
+impl OutputMode {
+    pub const ALL: [OutputMode; 3] = [OutputMode::Sdr, OutputMode::ScRgb, OutputMode::Hdr10];
+
+    pub fn is_hdr(&self) -> bool {
+        *self != OutputMode::Sdr
+    }
+
+    /// Surface format of the HDR modes, None for [`OutputMode::Sdr`].
+    pub fn hdr_surface_format(&self) -> Option<wgpu::TextureFormat> {
+        match self {
+            OutputMode::Sdr => None,
+            OutputMode::ScRgb => Some(wgpu::TextureFormat::Rgba16Float),
+            OutputMode::Hdr10 => Some(wgpu::TextureFormat::Rgb10a2Unorm),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Copy)]
@@ -34,6 +74,12 @@ pub struct GraphicsContextConfig {
     pub present_mode: wgpu::PresentMode,
     pub max_push_constant_size: u32,
     pub surface_format: wgpu::TextureFormat,
+    /// Falls back to [`OutputMode::Sdr`] if the surface does not support it.
+    pub output_mode: OutputMode,
+    /// How the surface is composited with the desktop. `Auto` picks what the surface prefers, which is
+    /// usually opaque. Transparent windows need `PreMultiplied` or `PostMultiplied`, if one of them is not
+    /// supported the other one is tried.
+    pub alpha_mode: wgpu::CompositeAlphaMode,
 }
 
 impl Default for GraphicsContextConfig {
@@ -44,20 +90,38 @@ impl Default for GraphicsContextConfig {
                 | wgpu::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
                 | wgpu::Features::TEXTURE_BINDING_ARRAY,
             present_mode: wgpu::PresentMode::AutoNoVsync,
-            max_push_constant_size: 80,
+            max_push_constant_size: 96,
             surface_format: wgpu::TextureFormat::Bgra8UnormSrgb,
+            output_mode: OutputMode::Sdr,
+            alpha_mode: wgpu::CompositeAlphaMode::Auto,
         }
     }
 }
 
+impl GraphicsContextConfig {
+    /// For windows created with [`crate::WindowConfig::transparent`]. The renderers output premultiplied colors
+    /// when rendering onto a target cleared to [`crate::Color::TRANSPARENT`].
+    pub fn transparent(mut self) -> Self {
+        self.alpha_mode = wgpu::CompositeAlphaMode::PreMultiplied;
+        self
+    }
+}
+
+impl Resizable for GraphicsContext {
+    fn on_resize(&mut self, size: PhysicalSize<u32>, _device: &wgpu::Device) {
+        self.resize(size);
+    }
+}
+
 impl GraphicsContext {
-    pub fn new(config: GraphicsContextConfig, window: &Window) -> anyhow::Result<Self> {
+    /// Fails if there is no suitable gpu or the surface does not support the configured format.
+    pub fn new(config: GraphicsContextConfig, window: &Window) -> crate::Result<Self> {
         let graphics_context =
             pollster::block_on(async move { new_graphics_context(config, window).await })?;
         Ok(graphics_context)
     }
 
-    pub async fn new_async(config: GraphicsContextConfig, window: &Window) -> anyhow::Result<Self> {
+    pub async fn new_async(config: GraphicsContextConfig, window: &Window) -> crate::Result<Self> {
         new_graphics_context(config, window).await
     }
 }
@@ -91,6 +155,20 @@ impl GraphicsContextInner {
         self.surface.configure(&self.device, &config);
     }
 
+    pub fn supports_hdr_output(&self) -> bool {
+        self.supported_output_modes.iter().any(|m| m.is_hdr())
+    }
+
+    /// True if the desktop shines through transparent pixels, see [`GraphicsContextConfig::alpha_mode`].
+    /// Clear to [`crate::Color::TRANSPARENT`] then.
+    pub fn is_transparent(&self) -> bool {
+        let config = self.surface_config.lock().unwrap();
+        matches!(
+            config.alpha_mode,
+            wgpu::CompositeAlphaMode::PreMultiplied | wgpu::CompositeAlphaMode::PostMultiplied
+        )
+    }
+
     pub fn set_present_mode(&mut self, present_mode: wgpu::PresentMode) {
         let mut config = self.surface_config.lock().unwrap();
         config.present_mode = present_mode;
@@ -101,7 +179,7 @@ impl GraphicsContextInner {
 pub async fn new_graphics_context(
     config: GraphicsContextConfig,
     window: &Window,
-) -> anyhow::Result<GraphicsContext> {
+) -> crate::Result<GraphicsContext> {
     let ctx = new_graphics_context_inner(config, window).await?;
     Ok(GraphicsContext(Arc::new(ctx)))
 }
@@ -109,14 +187,14 @@ pub async fn new_graphics_context(
 pub async fn new_graphics_context_inner(
     config: GraphicsContextConfig,
     window: &Window,
-) -> anyhow::Result<GraphicsContextInner> {
+) -> crate::Result<GraphicsContextInner> {
     let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
         backends: wgpu::Backends::all(),
         ..Default::default()
     });
-    let surface = unsafe {
-        instance.create_surface_unsafe(wgpu::SurfaceTargetUnsafe::from_window(&window)?)?
-    };
+    let target = unsafe { wgpu::SurfaceTargetUnsafe::from_window(&window) }
+        .map_err(|err| Error::WindowHandle(err.to_string()))?;
+    let surface = unsafe { instance.create_surface_unsafe(target)? };
     let adapter = instance
         .request_adapter(&wgpu::RequestAdapterOptions {
             power_preference: wgpu::PowerPreference::default(),
@@ -124,7 +202,7 @@ pub async fn new_graphics_context_inner(
             force_fallback_adapter: false,
         })
         .await
-        .unwrap();
+        .ok_or(Error::NoAdapter)?;
 
     let (device, queue) = adapter
         .request_device(
@@ -138,18 +216,39 @@ pub async fn new_graphics_context_inner(
             },
             None,
         )
-        .await
-        .unwrap();
+        .await?;
 
-    let surface_format = config.surface_format;
     let surface_caps = surface.get_capabilities(&adapter);
-    if surface_caps
-        .formats
-        .iter()
-        .all(|f| *f != config.surface_format)
-    {
-        panic!("SURFACE_FORMAT {surface_format:?} not found in surface caps ",)
+    if !surface_caps.formats.contains(&config.surface_format) {
+        return Err(Error::UnsupportedSurfaceFormat {
+            format: config.surface_format,
+            supported: surface_caps.formats,
+        });
     }
+    let supported_output_modes: Vec<OutputMode> = OutputMode::ALL
+        .into_iter()
+        .filter(|mode| match mode.hdr_surface_format() {
+            Some(format) => surface_caps.formats.contains(&format),
+            None => true,
+        })
+        .collect();
+    let output_mode = if supported_output_modes.contains(&config.output_mode) {
+        config.output_mode
+    } else {
+        warn!(
+            "Output mode {:?} is not supported by the surface, falling back to Sdr",
+            config.output_mode
+        );
+        OutputMode::Sdr
+    };
+    let surface_format = output_mode
+        .hdr_surface_format()
+        .unwrap_or(config.surface_format);
+    let view_formats = if output_mode.is_hdr() {
+        vec![]
+    } else {
+        vec![wgpu::TextureFormat::Bgra8UnormSrgb]
+    };
 
     let size = window.inner_size();
     let surface_config = wgpu::SurfaceConfiguration {
@@ -158,8 +257,8 @@ pub async fn new_graphics_context_inner(
         width: size.width,
         height: size.height,
         present_mode: config.present_mode,
-        alpha_mode: surface_caps.alpha_modes[0],
-        view_formats: vec![wgpu::TextureFormat::Bgra8UnormSrgb],
+        alpha_mode: select_alpha_mode(config.alpha_mode, &surface_caps.alpha_modes),
+        view_formats,
         desired_maximum_frame_latency: 2,
     };
     surface.configure(&device, &surface_config);
@@ -173,6 +272,46 @@ pub async fn new_graphics_context_inner(
         surface,
         surface_config,
         surface_format,
+        output_mode,
+        supported_output_modes,
     };
     Ok(ctx)
 }
+
+fn select_alpha_mode(
+    requested: wgpu::CompositeAlphaMode,
+    supported: &[wgpu::CompositeAlphaMode],
+) -> wgpu::CompositeAlphaMode {
+    use wgpu::CompositeAlphaMode::*;
+    let candidates: &[wgpu::CompositeAlphaMode] = match requested {
+        Auto => &[],
+        PreMultiplied => &[PreMultiplied, PostMultiplied],
+        PostMultiplied => &[PostMultiplied, PreMultiplied],
+        Opaque => &[Opaque],
+        Inherit => &[Inherit],
+    };
+    if let Some(mode) = candidates.iter().find(|m| supported.contains(m)) {
+        return *mode;
+    }
+    if requested != Auto {
+        warn!("Alpha mode {requested:?} is not supported by the surface, supported: {supported:?}");
+    }
+    supported[0]
+}
+
+#[cfg(test)]
+mod tests {
+    use wgpu::CompositeAlphaMode::*;
+
+    use super::select_alpha_mode;
+
+    #[test]
+    fn transparent_alpha_modes_fall_back_to_each_other() {
+        assert_eq!(select_alpha_mode(Auto, &[Opaque, PreMultiplied]), Opaque);
+        assert_eq!(
+            select_alpha_mode(PreMultiplied, &[Opaque, PostMultiplied]),
+            PostMultiplied
+        );
+        assert_eq!(select_alpha_mode(PreMultiplied, &[Opaque]), Opaque);
+    }
+}
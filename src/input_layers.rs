@@ -0,0 +1,189 @@
+//! Layers of the app that get input one after another, such that a pause menu can stop the game from
+//! reacting to WASD.
+//!
+//! An [`InputStack`] holds layers from bottom (the game) to top (e.g. a debug console). Each layer consumes
+//! some [`InputCategories`], layers below it don't see them anymore. Modals in the ui are pushed on top
+//! automatically while they are built, see [`crate::ui::ElementContext::modal`]:
+//!
+//! ```rust,ignore
+//! let mut layers = InputStack::new();
+//! layers.push("game", InputCategories::NONE);
+//! // every frame, after the ui started its frame:
+//! layers.update_from_ui(&board.ctx);
+//! let input = layers.input_for("game", &input);
+//! player.walk(input.wasd_vec()); // zero while the pause menu is open
+//! // while building the ui:
+//! if paused {
+//!     ctx.modal("pause menu", InputCategories::ALL);
+//! }
+//! ```
+
+use std::ops::BitOr;
+
+use crate::Input;
+
+/// Kinds of input a layer can consume. Window events like resizing or dropped files are never consumed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct InputCategories {
+    pub keyboard: bool,
+    /// Buttons, scrolling and cursor movement. The cursor position itself stays visible to all layers.
+    pub mouse: bool,
+}
+
+impl InputCategories {
+    pub const NONE: InputCategories = InputCategories {
+        keyboard: false,
+        mouse: false,
+    };
+    pub const KEYBOARD: InputCategories = InputCategories {
+        keyboard: true,
+        mouse: false,
+    };
+    pub const MOUSE: InputCategories = InputCategories {
+        keyboard: false,
+        mouse: true,
+    };
+    pub const ALL: InputCategories = InputCategories {
+        keyboard: true,
+        mouse: true,
+    };
+
+    /// The categories in `self` that are not in `other`.
+    pub fn without(self, other: InputCategories) -> InputCategories {
+        InputCategories {
+            keyboard: self.keyboard && !other.keyboard,
+            mouse: self.mouse && !other.mouse,
+        }
+    }
+}
+
+impl BitOr for InputCategories {
+    type Output = InputCategories;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        InputCategories {
+            keyboard: self.keyboard || rhs.keyboard,
+            mouse: self.mouse || rhs.mouse,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputLayer {
+    pub name: &'static str,
+    /// Input that does not reach the layers below this one.
+    pub consumes: InputCategories,
+}
+
+/// Layers that consume or pass through input, see the module docs.
+#[derive(Debug, Clone, Default)]
+pub struct InputStack {
+    /// bottom to top.
+    layers: Vec<InputLayer>,
+    /// modals of the ui, always above all other layers. Set in [`InputStack::update_from_ui`].
+    ui_layers: Vec<InputLayer>,
+}
+
+impl InputStack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Pushes a layer on top, replacing the layer with the same name if there is one.
+    pub fn push(&mut self, name: &'static str, consumes: InputCategories) {
+        self.remove(name);
+        self.layers.push(InputLayer { name, consumes });
+    }
+
+    pub fn pop(&mut self) -> Option<InputLayer> {
+        self.layers.pop()
+    }
+
+    /// Removes the layer with the `name`, returns false if there was none.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let len = self.layers.len();
+        self.layers.retain(|l| l.name != name);
+        self.layers.len() != len
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.layers().any(|l| l.name == name)
+    }
+
+    /// All layers from bottom to top, including the modals of the ui.
+    pub fn layers(&self) -> impl DoubleEndedIterator<Item = &InputLayer> {
+        self.layers.iter().chain(self.ui_layers.iter())
+    }
+
+    /// The top layer, the one that gets all input.
+    pub fn top(&self) -> Option<&InputLayer> {
+        self.layers().last()
+    }
+
+    /// Replaces the modals of the ui with the ones built in the last frame, see [`crate::ui::ElementContext::modal`].
+    #[cfg(feature = "ui")]
+    pub fn update_from_ui(&mut self, ctx: &crate::ui::ElementContext) {
+        self.ui_layers.clear();
+        self.ui_layers.extend_from_slice(ctx.modals());
+    }
+
+    /// The categories that reach the layer with the `name`: all that are not consumed by the layers above it.
+    /// Layers that are not in the stack are treated like the bottom layer.
+    pub fn passes_to(&self, name: &str) -> InputCategories {
+        let mut passes = InputCategories::ALL;
+        for layer in self.layers().rev() {
+            if layer.name == name {
+                break;
+            }
+            passes = passes.without(layer.consumes);
+        }
+        passes
+    }
+
+    /// The input as the layer with the `name` sees it, without the categories consumed above it.
+    pub fn input_for(&self, name: &str, input: &Input) -> Input {
+        input.filtered(self.passes_to(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use winit::{event::ElementState, keyboard::KeyCode};
+
+    use super::{InputCategories, InputStack};
+    use crate::{input::InputEvent, Input};
+
+    #[test]
+    fn layers_consume_input_for_the_layers_below() {
+        let mut stack = InputStack::new();
+        stack.push("game", InputCategories::NONE);
+        stack.push("ui", InputCategories::MOUSE);
+        stack.push("console", InputCategories::KEYBOARD);
+        assert_eq!(stack.passes_to("console"), InputCategories::ALL);
+        assert_eq!(stack.passes_to("ui"), InputCategories::MOUSE);
+        assert_eq!(stack.passes_to("game"), InputCategories::NONE);
+
+        let mut input = Input::new();
+        input.receive_input_event(&InputEvent::Key {
+            key: KeyCode::KeyW,
+            state: ElementState::Pressed,
+            repeat: false,
+            text: None,
+        });
+        assert!(!stack
+            .input_for("ui", &input)
+            .keys()
+            .is_pressed(KeyCode::KeyW));
+        assert!(stack
+            .input_for("console", &input)
+            .keys()
+            .is_pressed(KeyCode::KeyW));
+
+        assert!(stack.remove("console"));
+        assert_eq!(stack.passes_to("game"), InputCategories::KEYBOARD);
+        assert!(stack
+            .input_for("game", &input)
+            .keys()
+            .is_pressed(KeyCode::KeyW));
+    }
+}
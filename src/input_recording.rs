@@ -0,0 +1,132 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use winit::event::{DeviceEvent, WindowEvent};
+
+use crate::{input::InputEvent, Input};
+
+/// All input events of one frame (or one fixed timestep).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RecordedFrame {
+    /// Delta time of this frame in seconds. Use this instead of the measured delta time when replaying,
+    /// such that simulations run exactly the same as during recording.
+    pub delta: f32,
+    pub events: Vec<InputEvent>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InputRecording {
+    pub frames: Vec<RecordedFrame>,
+}
+
+impl InputRecording {
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let json = serde_json::to_string(self)?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let json = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+}
+
+/// Records all input events, grouped by frame, for reproducing bugs or automated testing.
+///
+/// Usage: pass all window events to [`InputRecorder::record_window_event`] (in addition to [`Input`])
+/// and call [`InputRecorder::end_frame`] once per frame. If your app uses a fixed timestep,
+/// call it once per fixed update instead, such that frames line up with simulation steps.
+#[derive(Debug, Default)]
+pub struct InputRecorder {
+    recording: InputRecording,
+    current_frame: Vec<InputEvent>,
+}
+
+impl InputRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_window_event(&mut self, event: &WindowEvent) {
+        if let Some(event) = InputEvent::from_window_event(event) {
+            self.current_frame.push(event);
+        }
+    }
+
+    /// Records raw mouse motion, see [`Input::receive_device_event`].
+    pub fn record_device_event(&mut self, event: &DeviceEvent) {
+        if let Some(event) = InputEvent::from_device_event(event) {
+            self.current_frame.push(event);
+        }
+    }
+
+    pub fn end_frame(&mut self, delta: f32) {
+        let events = std::mem::take(&mut self.current_frame);
+        self.recording.frames.push(RecordedFrame { delta, events });
+    }
+
+    /// Number of recorded frames.
+    pub fn frame_count(&self) -> usize {
+        self.recording.frames.len()
+    }
+
+    pub fn recording(&self) -> &InputRecording {
+        &self.recording
+    }
+
+    pub fn into_recording(self) -> InputRecording {
+        self.recording
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        self.recording.save(path)
+    }
+}
+
+/// Replays an [`InputRecording`] into an [`Input`], instead of live winit events.
+///
+/// Call [`InputPlayer::play_frame`] at the start of every frame (or fixed update), where you would
+/// otherwise have received window events. Do not forward live window events to the [`Input`] while playing.
+#[derive(Debug)]
+pub struct InputPlayer {
+    recording: InputRecording,
+    frame: usize,
+}
+
+impl InputPlayer {
+    pub fn new(recording: InputRecording) -> Self {
+        Self {
+            recording,
+            frame: 0,
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        Ok(Self::new(InputRecording::load(path)?))
+    }
+
+    /// Applies all events of the next recorded frame to `input` and returns the recorded delta time.
+    /// Returns None if the recording is finished.
+    pub fn play_frame(&mut self, input: &mut Input) -> Option<f32> {
+        let frame = self.recording.frames.get(self.frame)?;
+        for event in frame.events.iter() {
+            input.receive_input_event(event);
+        }
+        self.frame += 1;
+        Some(frame.delta)
+    }
+
+    /// Index of the next frame that is played.
+    pub fn frame(&self) -> usize {
+        self.frame
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.frame >= self.recording.frames.len()
+    }
+
+    pub fn restart(&mut self) {
+        self.frame = 0;
+    }
+}
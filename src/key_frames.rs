@@ -99,7 +99,7 @@ pub enum Easing {
 
 impl Easing {
     #[inline(always)]
-    fn y(&self, x: f32) -> f32 {
+    pub(crate) fn y(&self, x: f32) -> f32 {
         match self {
             Easing::Linear => x,
             Easing::Step => x.round(),
@@ -0,0 +1,370 @@
+//! Localized strings, loaded from one file per locale.
+//!
+//! The files use a small subset of [Fluent](https://projectfluent.org) (FTL), which also covers simple key-value files:
+//!
+//! ```ftl
+//! # comments start with a #
+//! menu-start = Start game
+//! greeting = Hello { $name }, you have {count} coins.
+//! credits =
+//!     Multiline values continue
+//!     on indented lines.
+//! ```
+//!
+//! Set up a [`Lang`] once with [`set_global_lang`] and look up strings with the [`t!`](crate::t) macro:
+//!
+//! ```rust,ignore
+//! let mut lang = Lang::new("assets/lang", "en").with_hot_reload();
+//! lang.set_locale("de-AT")?; // tries de-AT.ftl, then de.ftl, then en.ftl
+//! set_global_lang(lang);
+//!
+//! let title: UiString = t!("menu-start");
+//! let greeting: UiString = t!("greeting", name = player.name, count = player.coins);
+//! ```
+
+use std::{
+    cell::RefCell,
+    fmt::{Debug, Display, Write},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use ahash::AHashMap;
+use anyhow::{anyhow, bail};
+use log::error;
+
+use crate::{ui::element::UiString, AssetServer, AssetT, FileChangeWatcher, Handle};
+
+/// Translations of one locale, maps keys to (unformatted) values.
+#[derive(Debug, Clone, Default)]
+pub struct StringTable {
+    entries: AHashMap<String, Arc<str>>,
+}
+
+impl StringTable {
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let mut entries: AHashMap<String, Arc<str>> = AHashMap::new();
+        // key and value of the entry that indented lines are appended to:
+        let mut current: Option<(String, String)> = None;
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim_end();
+            let trimmed = line.trim_start();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            if trimmed.len() != line.len() {
+                let Some((_, value)) = &mut current else {
+                    bail!("line {}: indented line does not belong to a key", i + 1);
+                };
+                if !value.is_empty() {
+                    value.push('\n');
+                }
+                value.push_str(trimmed);
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                bail!("line {}: expected `key = value`", i + 1);
+            };
+            let key = key.trim();
+            if key.is_empty() {
+                bail!("line {}: empty key", i + 1);
+            }
+            if let Some((key, value)) = current.take() {
+                entries.insert(key, value.into());
+            }
+            current = Some((key.to_string(), value.trim().to_string()));
+        }
+        if let Some((key, value)) = current {
+            entries.insert(key, value.into());
+        }
+        Ok(Self { entries })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Arc<str>> {
+        self.entries.get(key)
+    }
+
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<Arc<str>>) {
+        self.entries.insert(key.into(), value.into());
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(|k| k.as_str())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl AssetT for StringTable {
+    fn from_bytes(bytes: &[u8]) -> Result<Self, anyhow::Error> {
+        StringTable::parse(std::str::from_utf8(bytes)?)
+    }
+}
+
+/// Replaces placeholders like `{ $name }` or `{name}` in `value` by the matching argument.
+/// Placeholders without a matching argument are kept as they are.
+pub fn format_value(value: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        let placeholder = &rest[start..start + len + 1];
+        let name = placeholder[1..placeholder.len() - 1].trim();
+        let name = name.strip_prefix('$').unwrap_or(name);
+        match args.iter().find(|(n, _)| *n == name) {
+            Some((_, arg)) => {
+                _ = write!(result, "{arg}");
+            }
+            None => result.push_str(placeholder),
+        }
+        rest = &rest[start + len + 1..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Locales to look up keys in, from the most specific to the `fallback`, e.g. `de-AT` -> `de-AT`, `de`, `en`.
+pub fn fallback_chain(locale: &str, fallback: &str) -> Vec<String> {
+    let mut chain: Vec<String> = vec![];
+    let mut locale = locale;
+    loop {
+        chain.push(locale.to_string());
+        match locale.rfind(['-', '_']) {
+            Some(i) => locale = &locale[..i],
+            None => break,
+        }
+    }
+    if !chain.iter().any(|l| l == fallback) {
+        chain.push(fallback.to_string());
+    }
+    chain
+}
+
+/// String tables of a locale and its fallbacks, loaded from `<dir>/<locale>.<extension>` files.
+pub struct Lang {
+    dir: PathBuf,
+    extension: String,
+    fallback: String,
+    locale: String,
+    tables: AssetServer<StringTable>,
+    /// path and table of each locale in the fallback chain that has a file, most specific first.
+    chain: Vec<(String, Handle<StringTable>)>,
+    watcher: Option<FileChangeWatcher>,
+}
+
+impl Debug for Lang {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let chain: Vec<&str> = self.chain.iter().map(|(path, _)| path.as_str()).collect();
+        f.debug_struct("Lang")
+            .field("dir", &self.dir)
+            .field("locale", &self.locale)
+            .field("chain", &chain)
+            .finish()
+    }
+}
+
+impl Lang {
+    /// No tables are loaded until [`Lang::set_locale`] is called.
+    pub fn new(dir: impl Into<PathBuf>, fallback: &str) -> Self {
+        Self {
+            dir: dir.into(),
+            extension: "ftl".into(),
+            fallback: fallback.to_string(),
+            locale: fallback.to_string(),
+            tables: AssetServer::new(),
+            chain: vec![],
+            watcher: None,
+        }
+    }
+
+    /// Extension of the table files, `ftl` by default.
+    pub fn with_extension(mut self, extension: &str) -> Self {
+        self.extension = extension.to_string();
+        self
+    }
+
+    /// Reloads table files when they change on disk, see [`Lang::update`].
+    pub fn with_hot_reload(mut self) -> Self {
+        self.watcher = Some(FileChangeWatcher::new(&[]));
+        self
+    }
+
+    pub fn locale(&self) -> &str {
+        self.locale.as_str()
+    }
+
+    pub fn fallback(&self) -> &str {
+        self.fallback.as_str()
+    }
+
+    fn path(&self, locale: &str) -> PathBuf {
+        self.dir.join(format!("{locale}.{}", self.extension))
+    }
+
+    /// Locales that have a file in the directory.
+    pub fn available_locales(&self) -> Vec<String> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return vec![];
+        };
+        let mut locales: Vec<String> = entries
+            .filter_map(|e| {
+                let path = e.ok()?.path();
+                if path.extension()?.to_str()? != self.extension {
+                    return None;
+                }
+                Some(path.file_stem()?.to_str()?.to_string())
+            })
+            .collect();
+        locales.sort();
+        locales
+    }
+
+    /// Loads the tables of the locale and its fallbacks. Locales in the fallback chain without a file are skipped.
+    /// Fails if a file cannot be parsed or none of the locales has a file, the current tables are kept in that case.
+    pub fn set_locale(&mut self, locale: &str) -> anyhow::Result<()> {
+        let mut chain = vec![];
+        for l in fallback_chain(locale, &self.fallback) {
+            let path = self.path(&l);
+            if !path.exists() {
+                continue;
+            }
+            let path = path
+                .to_str()
+                .ok_or_else(|| anyhow!("path {path:?} is not utf8"))?
+                .to_string();
+            let handle = self.tables.load(&path)?;
+            chain.push((path, handle));
+        }
+        if chain.is_empty() {
+            bail!("no string table for locale {locale} in {:?}", self.dir);
+        }
+        if let Some(watcher) = &mut self.watcher {
+            for (path, _) in chain.iter() {
+                watcher.watch(path);
+            }
+        }
+        self.chain = chain;
+        self.locale = locale.to_string();
+        self.tables.unload_unused();
+        Ok(())
+    }
+
+    /// Reloads tables whose files changed, if hot reloading is enabled. Call this once per frame.
+    pub fn update(&mut self) {
+        let Some(changed) = self.watcher.as_ref().and_then(|w| w.check_for_changes()) else {
+            return;
+        };
+        for changed in changed {
+            let Some((path, handle)) = self
+                .chain
+                .iter()
+                .find(|(p, _)| changed.to_str() == Some(p.as_str()))
+            else {
+                continue;
+            };
+            match self.tables.reload(handle) {
+                Ok(()) => log::info!("Hot reloaded string table {path}"),
+                Err(err) => error!("Could not reload string table {path}: {err}"),
+            }
+        }
+    }
+
+    /// The unformatted value of the key in the most specific locale that has it.
+    pub fn get(&self, key: &str) -> Option<&Arc<str>> {
+        self.chain
+            .iter()
+            .find_map(|(_, handle)| self.tables.get(handle)?.get(key))
+    }
+
+    /// Looks up the key and fills in the arguments. Missing keys are returned as they are, so they are easy to spot in the UI.
+    pub fn translate(&self, key: &str, args: &[(&str, &dyn Display)]) -> UiString {
+        match self.get(key) {
+            Some(value) if args.is_empty() => UiString::Arc(value.clone()),
+            Some(value) => UiString::String(format_value(value, args)),
+            None => UiString::String(key.to_string()),
+        }
+    }
+}
+
+thread_local! {
+    static GLOBAL_LANG: RefCell<Option<Lang>> = const { RefCell::new(None) };
+}
+
+/// Sets the [`Lang`] used by the [`t!`](crate::t) macro.
+pub fn set_global_lang(lang: Lang) {
+    GLOBAL_LANG.with(|l| *l.borrow_mut() = Some(lang));
+}
+
+/// Access to the global [`Lang`], e.g. to change the locale or call [`Lang::update`]. None if it was not set.
+pub fn with_global_lang<R>(f: impl FnOnce(&mut Lang) -> R) -> Option<R> {
+    GLOBAL_LANG.with(|l| l.borrow_mut().as_mut().map(f))
+}
+
+/// Translates with the global [`Lang`], used by the [`t!`](crate::t) macro. Returns the key if no global lang is set.
+pub fn translate(key: &str, args: &[(&str, &dyn Display)]) -> UiString {
+    GLOBAL_LANG.with(|l| match l.borrow().as_ref() {
+        Some(lang) => lang.translate(key, args),
+        None => UiString::String(key.to_string()),
+    })
+}
+
+/// Translates a key with the global [`Lang`](crate::lang::Lang) into a [`UiString`](crate::ui::element::UiString):
+/// `t!("menu-start")` or `t!("greeting", name = player.name, count = 3)`.
+#[macro_export]
+macro_rules! t {
+    ($key:expr) => {
+        $crate::lang::translate($key, &[])
+    };
+    ($key:expr, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::lang::translate(
+            $key,
+            &[$((stringify!($name), &$value as &dyn ::std::fmt::Display)),+],
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fallback_chain, Lang};
+
+    #[test]
+    fn lookup_with_fallback_and_args() {
+        assert_eq!(fallback_chain("de-AT", "en"), vec!["de-AT", "de", "en"]);
+
+        let dir = std::env::temp_dir().join(format!("tgf-lang-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("en.ftl"),
+            "# english\nhello = Hello { $name }!\nbye = Bye\nmulti =\n    line 1\n    line 2\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("de.ftl"), "hello = Hallo {name}!\n").unwrap();
+
+        let mut lang = Lang::new(&dir, "en");
+        lang.set_locale("de-AT").unwrap();
+        assert_eq!(lang.available_locales(), vec!["de", "en"]);
+        assert_eq!(
+            &*lang.translate("hello", &[("name", &"Tadeo")]),
+            "Hallo Tadeo!"
+        );
+        assert_eq!(&*lang.translate("bye", &[]), "Bye");
+        assert_eq!(&*lang.translate("multi", &[]), "line 1\nline 2");
+        assert_eq!(&*lang.translate("missing", &[]), "missing");
+        assert!(lang.set_locale("fr").is_ok());
+        assert_eq!(
+            &*lang.translate("hello", &[("count", &3)]),
+            "Hello { $name }!"
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
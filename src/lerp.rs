@@ -1,4 +1,4 @@
-use glam::{DVec2, DVec3, Quat, Vec2, Vec3};
+use glam::{DVec2, DVec3, Mat4, Quat, Vec2, Vec3};
 
 pub use tgf_macros::Lerp;
 
@@ -101,6 +101,14 @@ impl Lerp for Quat {
     }
 }
 
+/// Element wise, e.g. to blend between two projection matrices.
+impl Lerp for Mat4 {
+    #[inline(always)]
+    fn lerp(&self, other: &Self, factor: f32) -> Self {
+        *self * (1.0 - factor) + *other * factor
+    }
+}
+
 macro_rules! impl_tuples {
     ($($id:ident $n:tt),*) => {
         impl<$( $id: Lerp ),*> Lerp for ($($id),*)
@@ -5,20 +5,36 @@ pub mod app;
 pub mod buffer;
 pub mod camera3d;
 
+pub mod animation;
 pub mod asset;
 pub mod bucket_array;
+pub mod collision;
 pub mod color;
 pub mod default_world;
+pub mod dynamics;
+pub mod entities;
+pub mod error;
+pub mod gltf;
 pub mod graphics_context;
 pub mod immediate_geometry;
 pub mod input;
+pub mod input_layers;
+pub mod input_recording;
 pub mod key_frames;
+#[cfg(feature = "ui")]
+pub mod lang;
 pub mod lerp;
+pub mod mesh_gen;
 pub mod rect;
 pub mod renderer;
+pub mod resize;
+pub mod saves;
+pub mod scene;
 pub mod screen;
 pub mod shader;
+pub mod spline;
 pub mod texture;
+pub mod texture_streaming;
 pub mod time;
 pub mod transform;
 
@@ -36,43 +52,97 @@ pub use renderer::egui::Egui;
 pub use utils::global_values::{global_vals_get, global_vals_window};
 
 pub use renderer::{
+    backdrop_blur::BackdropBlur,
     bloom::{Bloom, BloomSettings, BloomTextures},
-    gizmos::Gizmos,
-    particles::{ParticleRenderer, ParticleSystem, ParticleSystemT, RawParticle},
+    compute_task::ComputeTask,
+    gizmos::{Gizmos, LineWidth},
+    minimap::{Minimap, MinimapMarker},
+    offscreen_camera::OffscreenCamera,
+    particles::{
+        AtlasMode, GpuEmitter, GpuParticleSimulator, GpuParticleSystem, ParticleAtlas,
+        ParticleRenderer, ParticleSorter, ParticleSystem, ParticleSystemPool, ParticleSystemT,
+        RawParticle,
+    },
+    pixel_perfect::{PixelPerfect, PixelScaling},
+    planar_reflection::PlanarReflection,
+    render_target::{RenderTarget, RenderTargetPass},
     screen_textures::{DepthTexture, HdrTexture, ScreenTextures},
-    sdf_sprite::{AlphaSdfParams, SdfSprite, SdfSpriteRenderer},
-    tone_mapping::ToneMapping,
-    RenderFormat,
+    sdf_sprite::{AlphaSdfParams, SdfSprite, SdfSpriteRenderer, SpriteSort},
+    sprite_drivers::{SpriteDriver, SpriteDriverHandle, SpriteDrivers},
+    texture_pool::{TexturePool, TransientTextureDesc},
+    tone_mapping::{HdrDisplaySettings, ToneMapping},
+    transition::{ScreenTransition, TransitionKind},
+    ui_cache::UiCache,
+    world_labels::WorldLabels,
+    world_text::{WorldText, WorldTextRenderer, WorldTextSize},
+    AlphaMode, FrameStats, RenderFormat, RenderStats,
 };
 
 pub use ui::element_context::{ElementContext, HotActive, HotState, Interaction};
 
-pub use app::{AppT, Runner, RunnerCallbacks, WindowConfig};
-pub use asset::AssetT;
+pub use animation::{AnimationClip, AnimationParams, AnimationState, AnimationStateMachine};
+pub use app::{
+    AppT, ClickThrough, EventLoopWaker, RedrawMode, Runner, RunnerCallbacks, RunnerEvent,
+    WindowConfig,
+};
+pub use asset::{AssetEvent, AssetId, AssetServer, AssetT, Handle, WeakHandle};
 pub use bucket_array::BucketArray;
-pub use buffer::{GrowableBuffer, IndexBuffer, InstanceBuffer, ToRaw, UniformBuffer, VertexBuffer};
+pub use buffer::{
+    read_buffer_async, read_buffer_blocking, GrowableBuffer, IndexBuffer, InstanceBuffer,
+    ReadbackBuffer, ToRaw, UniformBuffer, VertexBuffer,
+};
 pub use camera3d::{Camera3DTransform, Camera3d, Camera3dGR, Camera3dRaw, Projection, Ray};
-pub use color::Color;
+pub use collision::{
+    Aabb3, Capsule2, Capsule3, Circle, RayHit2, RayHit3, Shape2, Shape3, Sphere, SweepHit2,
+    SweepHit3,
+};
+pub use color::{Color, ColorSpace, Gradient, Oklab, Oklch, Palette};
 pub use default_world::DefaultWorld;
-pub use graphics_context::{GraphicsContext, GraphicsContextConfig};
+pub use dynamics::{Spring, VerletChain, VerletPoint};
+pub use entities::{ComponentStorage, Entity, EntityStore};
+pub use error::{Error, Result};
+pub use gltf::{GltfMaterial, GltfModel, GltfPrimitive};
+pub use graphics_context::{GraphicsContext, GraphicsContextConfig, OutputMode};
 pub use immediate_geometry::{ImmediateMeshQueue, ImmediateMeshRanges};
-pub use input::{Input, KeyState, MouseButton, MouseButtonState, PressState};
+pub use input::{
+    ImePreedit, Input, InputEvent, KeyState, Modifiers, MouseButton, MouseButtonState, PressState,
+    ScrollDelta, SCROLL_PIXELS_PER_LINE,
+};
+pub use input_layers::{InputCategories, InputLayer, InputStack};
+pub use input_recording::{InputPlayer, InputRecorder, InputRecording, RecordedFrame};
 pub use key_frames::{Easing, KeyFrames};
+#[cfg(feature = "ui")]
+pub use lang::{set_global_lang, with_global_lang, Lang, StringTable};
 pub use lerp::{Lerp, Lerped};
-pub use rect::{Aabb, Rect};
-pub use renderer::color_mesh::ColorMeshRenderer;
-pub use screen::{Screen, ScreenGR, ScreenRaw};
-pub use shader::{HotReload, ShaderCache, ShaderFile, ShaderSource};
+pub use mesh_gen::MeshData;
+pub use rect::{Aabb, DAabb, DRect, Obb2, Rect};
+pub use renderer::color_mesh::{ColorMeshRenderer, DebugRenderMode};
+pub use renderer::mesh::{GpuMesh, GpuModel, MeshRenderer, MeshVertex};
+pub use resize::{Resizable, ResizeDispatcher};
+pub use saves::SaveFile;
+pub use scene::{Scene, SceneCommand, SceneContext, SceneStack, SceneTransition};
+pub use screen::{Screen, ScreenGR, ScreenRaw, UiScaling, UiTransform, UiViewport};
+pub use shader::{
+    HotReload, HotReloadError, HotReloadErrorKind, HotReloadRegistry, ShaderCache, ShaderFile,
+    ShaderSource,
+};
+pub use spline::{ArcLengthTable, CatmullRom, CubicBezier, Curve, SplinePoint};
 pub use texture::{
     create_white_px_texture, rgba_bind_group_layout_cached, rgba_bind_group_layout_msaa4_cached,
-    BindableTexture, Texture,
+    BindableTexture, Texture, TextureViewBinding, UiTexture,
 };
+pub use texture_streaming::{StreamState, StreamedTexture, TextureStreamer};
 pub use time::{Time, TimeGR, TimeRaw, TimeStats};
 pub use transform::{Transform, TransformRaw};
 pub use uniforms::Uniforms;
 pub use vertex::{VertexT, VertsLayout};
 pub use watcher::FileChangeWatcher;
-pub use winit::{dpi::PhysicalSize, event::WindowEvent, keyboard::KeyCode, window::Window};
+pub use winit::{
+    dpi::PhysicalSize,
+    event::{DeviceEvent, WindowEvent},
+    keyboard::KeyCode,
+    window::Window,
+};
 pub use yolo::{extend_lifetime, leak, YoloCell, YoloRc};
 
 pub mod ext {
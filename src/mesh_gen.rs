@@ -0,0 +1,231 @@
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+use glam::{vec2, vec3, UVec2, Vec2, Vec3};
+
+use crate::{renderer::color_mesh, Color};
+
+/// Positions, normals, uvs and indices of a triangle mesh. Triangles are counter clockwise when seen from the outside.
+///
+/// The generators create standard primitives centered at the origin, e.g. for prototyping or to visualize colliders:
+///
+/// ```rust,ignore
+/// let sphere = MeshData::uv_sphere(0.5, 32, 16);
+/// color_mesh_renderer.draw_geometry(&sphere.color_mesh_vertices(Color::WHITE), &sphere.indices, &instances);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MeshData {
+    pub positions: Vec<Vec3>,
+    pub normals: Vec<Vec3>,
+    pub uvs: Vec<Vec2>,
+    pub indices: Vec<u32>,
+}
+
+impl MeshData {
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    /// Vertices for [`color_mesh::ColorMeshRenderer::draw_geometry`], all in the same color.
+    pub fn color_mesh_vertices(&self, color: Color) -> Vec<color_mesh::Vertex> {
+        self.positions
+            .iter()
+            .map(|pos| color_mesh::Vertex { pos: *pos, color })
+            .collect()
+    }
+
+    /// Vertices with uvs for [`color_mesh::ColorMeshRenderer::draw_textured_geometry`], all in the same color.
+    pub fn textured_color_mesh_vertices(&self, color: Color) -> Vec<color_mesh::TexturedVertex> {
+        self.positions
+            .iter()
+            .zip(self.uvs.iter())
+            .map(|(pos, uv)| color_mesh::TexturedVertex {
+                pos: *pos,
+                color,
+                uv: *uv,
+            })
+            .collect()
+    }
+
+    /// Sphere with `sectors` segments around the y axis and `stacks` segments from pole to pole.
+    pub fn uv_sphere(radius: f32, sectors: u32, stacks: u32) -> MeshData {
+        let sectors = sectors.max(3);
+        let stacks = stacks.max(2);
+        let mut mesh = MeshData::default();
+        for i in 0..=stacks {
+            let v = i as f32 / stacks as f32;
+            mesh.add_ring(radius, v * PI, 0.0, sectors, v);
+        }
+        mesh.add_lattice_indices(stacks, sectors);
+        mesh
+    }
+
+    /// Capsule along the y axis. `height` is the length of the cylinder between the two half spheres,
+    /// so the whole capsule is `height + 2 * radius` tall. `rings` is the number of segments of each half sphere.
+    pub fn capsule(radius: f32, height: f32, sectors: u32, rings: u32) -> MeshData {
+        let sectors = sectors.max(3);
+        let rings = rings.max(1);
+        let half = height * 0.5;
+        let total = height + 2.0 * radius;
+        let mut mesh = MeshData::default();
+        // the equator ring exists twice, the cylinder is the quads between them.
+        for (center, from) in [(half, 0.0), (-half, FRAC_PI_2)] {
+            for i in 0..=rings {
+                let polar = from + FRAC_PI_2 * i as f32 / rings as f32;
+                let y = center + radius * polar.cos();
+                mesh.add_ring(radius, polar, center, sectors, (total * 0.5 - y) / total);
+            }
+        }
+        mesh.add_lattice_indices(2 * rings + 1, sectors);
+        mesh
+    }
+
+    /// Torus around the y axis. `major_radius` is the distance from the center to the middle of the tube,
+    /// `minor_radius` the radius of the tube.
+    pub fn torus(
+        major_radius: f32,
+        minor_radius: f32,
+        major_segments: u32,
+        minor_segments: u32,
+    ) -> MeshData {
+        let major_segments = major_segments.max(3);
+        let minor_segments = minor_segments.max(3);
+        let mut mesh = MeshData::default();
+        for i in 0..=minor_segments {
+            let v = i as f32 / minor_segments as f32;
+            // going around the tube downwards from the outer equator, like the rings of a sphere.
+            let (sin_minor, cos_minor) = (-v * TAU).sin_cos();
+            for j in 0..=major_segments {
+                let u = j as f32 / major_segments as f32;
+                let (sin_major, cos_major) = (u * TAU).sin_cos();
+                let normal = vec3(cos_minor * cos_major, sin_minor, cos_minor * sin_major);
+                let ring_center = vec3(cos_major, 0.0, sin_major) * major_radius;
+                mesh.positions.push(ring_center + normal * minor_radius);
+                mesh.normals.push(normal);
+                mesh.uvs.push(vec2(u, v));
+            }
+        }
+        mesh.add_lattice_indices(minor_segments, major_segments);
+        mesh
+    }
+
+    /// Flat grid in the xz plane facing up (+y), with `subdivisions` quads along x and z.
+    pub fn plane_grid(size: Vec2, subdivisions: UVec2) -> MeshData {
+        let subdivisions = subdivisions.max(UVec2::ONE);
+        let mut mesh = MeshData::default();
+        for i in 0..=subdivisions.y {
+            let v = i as f32 / subdivisions.y as f32;
+            for j in 0..=subdivisions.x {
+                let u = j as f32 / subdivisions.x as f32;
+                mesh.positions
+                    .push(vec3((u - 0.5) * size.x, 0.0, (0.5 - v) * size.y));
+                mesh.normals.push(Vec3::Y);
+                mesh.uvs.push(vec2(u, v));
+            }
+        }
+        mesh.add_lattice_indices(subdivisions.y, subdivisions.x);
+        mesh
+    }
+
+    /// Cone along the y axis with the tip at `height / 2` and a closed base at `-height / 2`.
+    pub fn cone(radius: f32, height: f32, sectors: u32) -> MeshData {
+        let sectors = sectors.max(3);
+        let half = height * 0.5;
+        let mut mesh = MeshData::default();
+        // the tip is a ring of vertices, so each side quad gets its own normal at the tip.
+        for (ring, y, ring_radius) in [(0.0, half, 0.0), (1.0, -half, radius)] {
+            for j in 0..=sectors {
+                let u = j as f32 / sectors as f32;
+                let (sin, cos) = (u * TAU).sin_cos();
+                mesh.positions
+                    .push(vec3(cos * ring_radius, y, sin * ring_radius));
+                mesh.normals
+                    .push(vec3(cos * height, radius, sin * height).normalize_or_zero());
+                mesh.uvs.push(vec2(u, ring));
+            }
+        }
+        mesh.add_lattice_indices(1, sectors);
+
+        let center = mesh.positions.len() as u32;
+        mesh.positions.push(vec3(0.0, -half, 0.0));
+        mesh.normals.push(Vec3::NEG_Y);
+        mesh.uvs.push(vec2(0.5, 0.5));
+        for j in 0..=sectors {
+            let (sin, cos) = (j as f32 / sectors as f32 * TAU).sin_cos();
+            mesh.positions.push(vec3(cos * radius, -half, sin * radius));
+            mesh.normals.push(Vec3::NEG_Y);
+            mesh.uvs.push(vec2(0.5 + cos * 0.5, 0.5 + sin * 0.5));
+        }
+        for j in 0..sectors {
+            mesh.indices
+                .extend([center, center + 1 + j, center + 2 + j]);
+        }
+        mesh
+    }
+
+    /// Ring of `sectors + 1` vertices (the first and last at the same position for the uv seam) on a sphere
+    /// of `radius` around `(0, center_y, 0)`, at the angle `polar` from the top.
+    fn add_ring(&mut self, radius: f32, polar: f32, center_y: f32, sectors: u32, v: f32) {
+        let (sin_polar, cos_polar) = polar.sin_cos();
+        for j in 0..=sectors {
+            let u = j as f32 / sectors as f32;
+            let (sin, cos) = (u * TAU).sin_cos();
+            let normal = vec3(sin_polar * cos, cos_polar, sin_polar * sin);
+            self.positions
+                .push(normal * radius + vec3(0.0, center_y, 0.0));
+            self.normals.push(normal);
+            self.uvs.push(vec2(u, v));
+        }
+    }
+
+    /// Two triangles for each quad of a lattice of `(rows + 1) * (cols + 1)` vertices.
+    /// Counter clockwise seen from the side where going along a row is to the right and to the next row is down.
+    fn add_lattice_indices(&mut self, rows: u32, cols: u32) {
+        for i in 0..rows {
+            for j in 0..cols {
+                let a = i * (cols + 1) + j;
+                let b = a + cols + 1;
+                self.indices.extend([a, a + 1, b, a + 1, b + 1, b]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{uvec2, vec2};
+
+    use super::MeshData;
+
+    /// every triangle with an area faces in the direction of its vertex normals.
+    fn assert_faces_outward(mesh: &MeshData) {
+        assert_eq!(mesh.positions.len(), mesh.normals.len());
+        assert_eq!(mesh.positions.len(), mesh.uvs.len());
+        for tri in mesh.indices.chunks(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| tri[i] as usize);
+            let face = (mesh.positions[b] - mesh.positions[a])
+                .cross(mesh.positions[c] - mesh.positions[a]);
+            if face.length() < 1e-6 {
+                continue;
+            }
+            let normal = mesh.normals[a] + mesh.normals[b] + mesh.normals[c];
+            assert!(face.dot(normal) > 0.0, "triangle {tri:?} faces inward");
+        }
+    }
+
+    #[test]
+    fn primitives_face_outward() {
+        assert_faces_outward(&MeshData::uv_sphere(1.0, 16, 8));
+        assert_faces_outward(&MeshData::capsule(0.5, 2.0, 16, 4));
+        assert_faces_outward(&MeshData::torus(2.0, 0.5, 24, 12));
+        assert_faces_outward(&MeshData::plane_grid(vec2(4.0, 2.0), uvec2(4, 2)));
+        assert_faces_outward(&MeshData::cone(1.0, 2.0, 16));
+
+        let grid = MeshData::plane_grid(vec2(4.0, 2.0), uvec2(4, 2));
+        assert_eq!(grid.vertex_count(), 15);
+        assert_eq!(grid.triangle_count(), 16);
+    }
+}
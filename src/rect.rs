@@ -1,7 +1,8 @@
 use std::ops::{Add, Div, Mul};
 
 use super::lerp::Lerp;
-use glam::{vec2, DVec2, Vec2};
+use crate::Transform;
+use glam::{dvec2, vec2, DVec2, Vec2, Vec3};
 
 ///  min_x, min_y form the top left corner.
 #[repr(C)]
@@ -45,6 +46,10 @@ impl Rect {
     pub fn d_size(&self) -> DVec2 {
         self.size.as_dvec2()
     }
+
+    pub fn as_drect(&self) -> DRect {
+        DRect::new(self.pos.as_dvec2(), self.size.as_dvec2())
+    }
 }
 
 impl Add<Vec2> for Rect {
@@ -216,6 +221,14 @@ impl Aabb {
             && self.max.y.min(other.max.y) >= self.min.y.max(other.min.y)
     }
 
+    /// The area covered by both, None if they do not intersect.
+    pub fn intersection(&self, other: &Aabb) -> Option<Aabb> {
+        self.intersects(other).then(|| Aabb {
+            min: self.min.max(other.min),
+            max: self.max.min(other.max),
+        })
+    }
+
     pub const UNIT: Aabb = Aabb {
         min: Vec2::ZERO,
         max: Vec2::ONE,
@@ -251,6 +264,10 @@ impl Aabb {
         vec2(1.0, size.y / size.x)
     }
 
+    pub fn as_daabb(&self) -> DAabb {
+        DAabb::new(self.min.as_dvec2(), self.max.as_dvec2())
+    }
+
     pub fn join(&mut self, other: Aabb) {
         if other.min.x < self.min.x {
             self.min.x = other.min.x;
@@ -290,12 +307,406 @@ impl From<Aabb> for Rect {
     }
 }
 
+/// [`Rect`] with f64 coordinates, like the layout of the ui. `pos` is the top left corner.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct DRect {
+    pub pos: DVec2,
+    pub size: DVec2,
+}
+
+impl Lerp for DRect {
+    fn lerp(&self, other: &Self, factor: f32) -> Self {
+        DRect {
+            pos: self.pos.lerp(other.pos, factor as f64),
+            size: self.size.lerp(other.size, factor as f64),
+        }
+    }
+}
+
+impl DRect {
+    pub const UNIT: DRect = DRect {
+        pos: DVec2::ZERO,
+        size: DVec2::ONE,
+    };
+
+    pub const ZERO: DRect = DRect {
+        pos: DVec2::ZERO,
+        size: DVec2::ZERO,
+    };
+
+    pub const fn new(pos: DVec2, size: DVec2) -> Self {
+        Self { pos, size }
+    }
+
+    #[inline(always)]
+    pub fn contains(&self, pos: DVec2) -> bool {
+        pos.x >= self.pos.x
+            && pos.y >= self.pos.y
+            && pos.x <= self.pos.x + self.size.x
+            && pos.y <= self.pos.y + self.size.y
+    }
+
+    /// Lossy conversion to f32, e.g. for batching.
+    #[inline]
+    pub fn as_rect(&self) -> Rect {
+        Rect::new(self.pos.as_vec2(), self.size.as_vec2())
+    }
+
+    /// Lossy conversion to an f32 [`Aabb`], e.g. for batching.
+    #[inline]
+    pub fn as_aabb(&self) -> Aabb {
+        let min = self.pos.as_vec2();
+        Aabb::new(min, min + self.size.as_vec2())
+    }
+}
+
+impl Add<DVec2> for DRect {
+    type Output = DRect;
+
+    fn add(self, rhs: DVec2) -> Self::Output {
+        DRect {
+            pos: self.pos + rhs,
+            size: self.size,
+        }
+    }
+}
+
+/// [`Aabb`] with f64 coordinates, like the layout of the ui.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct DAabb {
+    pub min: DVec2,
+    pub max: DVec2,
+}
+
+impl Lerp for DAabb {
+    fn lerp(&self, other: &Self, factor: f32) -> Self {
+        DAabb {
+            min: self.min.lerp(other.min, factor as f64),
+            max: self.max.lerp(other.max, factor as f64),
+        }
+    }
+}
+
+impl Add<DVec2> for DAabb {
+    type Output = DAabb;
+
+    fn add(self, rhs: DVec2) -> Self::Output {
+        DAabb {
+            min: self.min + rhs,
+            max: self.max + rhs,
+        }
+    }
+}
+
+impl Mul<f64> for DAabb {
+    type Output = DAabb;
+
+    fn mul(mut self, rhs: f64) -> Self::Output {
+        self.min *= rhs;
+        self.max *= rhs;
+        self
+    }
+}
+
+impl Div<f64> for DAabb {
+    type Output = DAabb;
+
+    fn div(mut self, rhs: f64) -> Self::Output {
+        self.min /= rhs;
+        self.max /= rhs;
+        self
+    }
+}
+
+impl Div<DVec2> for DAabb {
+    type Output = DAabb;
+
+    fn div(mut self, rhs: DVec2) -> Self::Output {
+        self.min /= rhs;
+        self.max /= rhs;
+        self
+    }
+}
+
+impl DAabb {
+    pub const UNIT: DAabb = DAabb {
+        min: DVec2::ZERO,
+        max: DVec2::ONE,
+    };
+
+    pub const ZERO: DAabb = DAabb {
+        min: DVec2::ZERO,
+        max: DVec2::ZERO,
+    };
+
+    pub const fn new(min: DVec2, max: DVec2) -> Self {
+        Self { min, max }
+    }
+
+    #[inline]
+    pub fn quad(center: DVec2, size: DVec2) -> Self {
+        Self {
+            min: center - size / 2.0,
+            max: center + size / 2.0,
+        }
+    }
+
+    #[inline]
+    pub fn square(center: DVec2, len: f64) -> DAabb {
+        DAabb::quad(center, DVec2::splat(len))
+    }
+
+    pub fn standard_form(&self) -> DAabb {
+        DAabb {
+            min: self.min.min(self.max),
+            max: self.min.max(self.max),
+        }
+    }
+
+    pub fn overlap_area(&self, other: &DAabb) -> f64 {
+        let width_overlap = self.max.x.min(other.max.x) - self.min.x.max(other.min.x);
+        let height_overlap = self.max.y.min(other.max.y) - self.min.y.max(other.min.y);
+        width_overlap.max(0.0) * height_overlap.max(0.0)
+    }
+
+    /// scales the DAabb around its center, see [`Aabb::scale`].
+    pub fn scale(mut self, factor: f64) -> Self {
+        let center = (self.min + self.max) * 0.5;
+        self.min = center + (self.min - center) * factor;
+        self.max = center + (self.max - center) * factor;
+        self
+    }
+
+    #[inline(always)]
+    pub fn contains(&self, pos: DVec2) -> bool {
+        pos.x >= self.min.x && pos.y >= self.min.y && pos.x <= self.max.x && pos.y <= self.max.y
+    }
+
+    #[inline(always)]
+    pub fn intersects(&self, other: &DAabb) -> bool {
+        self.max.x.min(other.max.x) >= self.min.x.max(other.min.x)
+            && self.max.y.min(other.max.y) >= self.min.y.max(other.min.y)
+    }
+
+    #[inline]
+    pub fn size(&self) -> DVec2 {
+        self.max - self.min
+    }
+
+    #[inline]
+    pub fn center(&self) -> DVec2 {
+        (self.max + self.min) / 2.0
+    }
+
+    /// returns a vector where x is guaranteed to be 1.0 and y is y/x so the aspect ratio.
+    #[inline]
+    pub fn aspect_ratio(&self) -> DVec2 {
+        let size = self.size();
+        dvec2(1.0, size.y / size.x)
+    }
+
+    pub fn join(&mut self, other: DAabb) {
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+
+    /// Lossy conversion to f32, e.g. for batching.
+    #[inline]
+    pub fn as_aabb(&self) -> Aabb {
+        Aabb::new(self.min.as_vec2(), self.max.as_vec2())
+    }
+}
+
+impl From<DRect> for DAabb {
+    fn from(rect: DRect) -> Self {
+        DAabb {
+            min: rect.pos,
+            max: rect.pos + rect.size,
+        }
+    }
+}
+
+impl From<DAabb> for DRect {
+    fn from(aabb: DAabb) -> Self {
+        DRect {
+            pos: aabb.min,
+            size: aabb.max - aabb.min,
+        }
+    }
+}
+
+/// Oriented bounding box in 2d, e.g. for the hitboxes of rotated sprites.
+///
+/// Like an [`Aabb`] of size `2 * half_extents` around `center`, rotated by `rotation` radians.
+/// Positive rotations turn the x axis towards the y axis.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Obb2 {
+    pub center: Vec2,
+    pub half_extents: Vec2,
+    pub rotation: f32,
+}
+
+impl Lerp for Obb2 {
+    fn lerp(&self, other: &Self, factor: f32) -> Self {
+        Obb2 {
+            center: self.center.lerp(other.center, factor),
+            half_extents: self.half_extents.lerp(other.half_extents, factor),
+            rotation: self.rotation.lerp(&other.rotation, factor),
+        }
+    }
+}
+
+impl From<Aabb> for Obb2 {
+    fn from(aabb: Aabb) -> Self {
+        let aabb = aabb.standard_form();
+        Obb2::new(aabb.center(), aabb.size() * 0.5, 0.0)
+    }
+}
+
+impl Obb2 {
+    pub const fn new(center: Vec2, half_extents: Vec2, rotation: f32) -> Self {
+        Obb2 {
+            center,
+            half_extents,
+            rotation,
+        }
+    }
+
+    /// The box of a sprite of `size` (before scaling) with the `transform`, projected onto the xy plane.
+    /// Only the rotation around the z axis is kept.
+    pub fn from_transform(transform: &Transform, size: Vec2) -> Self {
+        let x_axis = transform.rotation * Vec3::X;
+        Obb2 {
+            center: transform.position.truncate(),
+            half_extents: size * transform.scale.truncate().abs() * 0.5,
+            rotation: x_axis.y.atan2(x_axis.x),
+        }
+    }
+
+    /// The local x and y axis of the box, as unit vectors.
+    #[inline]
+    pub fn axes(&self) -> [Vec2; 2] {
+        let x = Vec2::from_angle(self.rotation);
+        [x, x.perp()]
+    }
+
+    /// The corners, going around the box from the one at local (-x, -y).
+    pub fn corners(&self) -> [Vec2; 4] {
+        let [x, y] = self.axes();
+        let x = x * self.half_extents.x;
+        let y = y * self.half_extents.y;
+        let c = self.center;
+        [c - x - y, c + x - y, c + x + y, c - x + y]
+    }
+
+    /// The smallest [`Aabb`] containing the box.
+    pub fn bounding_aabb(&self) -> Aabb {
+        Aabb::bounding_aabb_for_points(&self.corners())
+    }
+
+    /// Position in the space of the box, where the box goes from `-half_extents` to `half_extents`.
+    #[inline]
+    pub fn to_local(&self, pos: Vec2) -> Vec2 {
+        Vec2::from_angle(-self.rotation).rotate(pos - self.center)
+    }
+
+    #[inline]
+    pub fn contains(&self, pos: Vec2) -> bool {
+        let local = self.to_local(pos).abs();
+        local.x <= self.half_extents.x && local.y <= self.half_extents.y
+    }
+
+    /// Touching boxes intersect, like for [`Aabb::intersects`].
+    pub fn intersects(&self, other: &Obb2) -> bool {
+        sat_intersects(
+            &self.corners(),
+            &other.corners(),
+            self.axes().into_iter().chain(other.axes()),
+        )
+    }
+
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.intersects(&Obb2::from(*aabb))
+    }
+}
+
+/// Separating axis test for two convex polygons: they intersect, if their projections onto
+/// all `axes` (the edge normals of both) overlap.
+fn sat_intersects(a: &[Vec2], b: &[Vec2], axes: impl Iterator<Item = Vec2>) -> bool {
+    fn project(points: &[Vec2], axis: Vec2) -> (f32, f32) {
+        points.iter().fold((f32::MAX, f32::MIN), |(min, max), p| {
+            let d = p.dot(axis);
+            (min.min(d), max.max(d))
+        })
+    }
+    for axis in axes {
+        let (a_min, a_max) = project(a, axis);
+        let (b_min, b_max) = project(b, axis);
+        if a_max < b_min || b_max < a_min {
+            return false;
+        }
+    }
+    true
+}
+
 #[cfg(test)]
 mod tests {
-    use super::Aabb;
+    use std::f32::consts::FRAC_PI_4;
+
+    use glam::{dvec2, vec2, Quat, Vec2, Vec3};
+
+    use super::{Aabb, DAabb, DRect, Obb2};
+    use crate::Transform;
     #[test]
     fn scale_aabb() {
         let aabb = Aabb::UNIT.scale(0.5);
         dbg!(aabb);
     }
+
+    #[test]
+    fn drect_conversions() {
+        let rect = DRect::new(dvec2(10.0, 20.0), dvec2(30.0, 40.0));
+        assert!(rect.contains(dvec2(40.0, 60.0)));
+        assert!(!rect.contains(dvec2(40.0, 60.001)));
+        let aabb = DAabb::from(rect);
+        assert_eq!(aabb, DAabb::new(dvec2(10.0, 20.0), dvec2(40.0, 60.0)));
+        assert_eq!(DRect::from(aabb), rect);
+        assert_eq!(rect.as_aabb(), aabb.as_aabb());
+        assert_eq!(rect.as_aabb().as_daabb(), aabb);
+    }
+
+    #[test]
+    fn obb_overlaps() {
+        // a diamond with corners at distance 2 from the center:
+        let diamond = Obb2::new(Vec2::ZERO, Vec2::splat(2f32.sqrt()), FRAC_PI_4);
+        assert!(diamond.contains(vec2(1.9, 0.0)));
+        assert!(!diamond.contains(vec2(1.1, 1.1)));
+        assert!(diamond
+            .bounding_aabb()
+            .max
+            .abs_diff_eq(vec2(2.0, 2.0), 1e-5));
+
+        // the aabbs overlap, but the boxes don't:
+        let corner = Aabb::new(vec2(1.1, 1.1), vec2(3.0, 3.0));
+        assert!(diamond.bounding_aabb().intersects(&corner));
+        assert!(!diamond.intersects_aabb(&corner));
+        assert!(diamond.intersects_aabb(&Aabb::new(vec2(1.9, -0.1), vec2(3.0, 0.1))));
+
+        let other = Obb2::new(vec2(3.0, 0.0), vec2(1.5, 0.2), 0.0);
+        assert!(diamond.intersects(&other));
+        assert!(other.intersects(&diamond));
+        let rotated = Obb2 {
+            rotation: FRAC_PI_4,
+            ..Obb2::new(vec2(3.5, 0.0), vec2(1.5, 0.2), 0.0)
+        };
+        assert!(!diamond.intersects(&rotated));
+
+        let mut transform = Transform::new(1.0, 2.0, 5.0);
+        transform.rotation = Quat::from_rotation_z(FRAC_PI_4);
+        transform.scale = Vec3::new(2.0, 1.0, 1.0);
+        let obb = Obb2::from_transform(&transform, vec2(1.0, 1.0));
+        assert_eq!(obb.center, vec2(1.0, 2.0));
+        assert_eq!(obb.half_extents, vec2(1.0, 0.5));
+        assert!((obb.rotation - FRAC_PI_4).abs() < 1e-5);
+    }
 }
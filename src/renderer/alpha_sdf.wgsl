@@ -9,6 +9,16 @@ struct AlphaSdfVertexOutput {
 
 @fragment
 fn alpha_sdf_fs(in: AlphaSdfVertexOutput) -> @location(0) vec4<f32> {   
+    let color = alpha_sdf_color(in);
+    if color.a == 0.0{
+        discard;
+    }
+
+    return color; // in.color for transparency, todo! maybe not multiply
+}
+
+/// The color of the shape with its border, alpha is 0.0 outside of it.
+fn alpha_sdf_color(in: AlphaSdfVertexOutput) -> vec4<f32> {
     // border inside:
     let in_cutoff = in.params.x;
     let in_smooth = in.params.y;
@@ -26,10 +36,5 @@ fn alpha_sdf_fs(in: AlphaSdfVertexOutput) -> @location(0) vec4<f32> {
 
     var color = mix(in.border_color, image_color, inside_factor);
     color.a = inside_border_factor * in.color.a;
-
-    if color.a == 0.0{
-        discard;
-    }
-
-    return color; // in.color for transparency, todo! maybe not multiply
+    return color;
 }
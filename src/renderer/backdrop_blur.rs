@@ -0,0 +1,182 @@
+use std::rc::Rc;
+
+use winit::dpi::PhysicalSize;
+
+use crate::{
+    renderer::{
+        bloom::BLOOM_SHADER_SOURCE,
+        texture_pool::{TexturePool, TransientTextureDesc},
+    },
+    rgba_bind_group_layout_cached,
+    uniforms::Uniforms,
+    HdrTexture, HotReload, Resizable, ShaderCache, ShaderSource, ToneMapping,
+};
+
+/// Creates a blurred copy of the scene that is sampled by ui divs with `backdrop_blur` set (frosted glass panels).
+///
+/// The steps, each bullet point is one render pass:
+///
+/// - tone map the hdr scene into levels[0] which has 1/2 the resolution of the original image.
+/// - downsample levels[0] into levels[1] with the 13 tap filter from the bloom shader.
+/// - downsample levels[1] into levels[2] and so on...
+///
+/// The last level is the blurred backdrop. Because it is already tone mapped, it can be sampled
+/// directly by the ui that is rendered on top of the tone mapped image.
+///
+/// All levels are taken from a [`TexturePool`], only the last one is kept until the next [`BackdropBlur::apply`].
+pub struct BackdropBlur {
+    size: PhysicalSize<u32>,
+    blurred: Option<Rc<HdrTexture>>,
+    downsample_pipeline: wgpu::RenderPipeline,
+    color_format: wgpu::TextureFormat,
+}
+
+const N_LEVELS: usize = 4;
+
+impl BackdropBlur {
+    /// The `color_format` should be the output format of the [`ToneMapping`] passed to [`BackdropBlur::apply`].
+    pub fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        color_format: wgpu::TextureFormat,
+        shader_cache: &mut ShaderCache,
+    ) -> Self {
+        let shader = shader_cache.register(BLOOM_SHADER_SOURCE, device);
+        let downsample_pipeline = create_downsample_pipeline(&shader, device, color_format);
+        Self {
+            size: PhysicalSize::new(width, height),
+            blurred: None,
+            downsample_pipeline,
+            color_format,
+        }
+    }
+
+    /// make sure this is called after graphics context is reconfigured (to match the ctx configs size)
+    pub fn resize(&mut self, size: PhysicalSize<u32>, _device: &wgpu::Device) {
+        self.size = size;
+        self.blurred = None;
+    }
+
+    /// Bind group of the blurred backdrop, to be passed to [`crate::renderer::ui_screen::UiScreenRenderer::render_batches_with_backdrop`].
+    /// None if [`BackdropBlur::apply`] was not called since the last resize.
+    pub fn bind_group(&self) -> Option<&wgpu::BindGroup> {
+        self.blurred.as_ref().map(|e| e.bind_group())
+    }
+
+    /// Note: input texture should be hdr, it is tone mapped with the given `tone_mapping` before blurring.
+    pub fn apply(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        device: &wgpu::Device,
+        pool: &mut TexturePool,
+        tone_mapping: &mut ToneMapping,
+        input_texture: &wgpu::BindGroup,
+        uniforms: &Uniforms,
+    ) {
+        // give the last result back to the pool first, it might be reused as one of the levels.
+        self.blurred = None;
+        let levels: [Rc<HdrTexture>; N_LEVELS] = std::array::from_fn(|level| {
+            let size = u32::pow(2, level as u32 + 1); // level 0 -> 2, level 1 -> 4, etc..
+            let desc = TransientTextureDesc {
+                width: (self.size.width / size).max(1),
+                height: (self.size.height / size).max(1),
+                sample_count: 1,
+                format: self.color_format,
+            };
+            pool.acquire(
+                device,
+                desc,
+                &format!("backdrop blur texture level {level} (1/{size})"),
+            )
+        });
+
+        tone_mapping.apply(encoder, input_texture, levels[0].view());
+
+        for i in 1..N_LEVELS {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Backdrop blur downsample"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: levels[i].view(),
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_pipeline(&self.downsample_pipeline);
+            pass.set_bind_group(0, uniforms.bind_group(), &[]);
+            pass.set_bind_group(1, levels[i - 1].bind_group(), &[]);
+            pass.draw(0..3, 0..1);
+        }
+
+        let [.., blurred] = levels;
+        self.blurred = Some(blurred);
+    }
+}
+
+impl Resizable for BackdropBlur {
+    fn on_resize(&mut self, size: PhysicalSize<u32>, device: &wgpu::Device) {
+        self.resize(size, device);
+    }
+}
+
+impl HotReload for BackdropBlur {
+    fn source(&self) -> ShaderSource {
+        BLOOM_SHADER_SOURCE
+    }
+
+    fn hot_reload(&mut self, shader: &wgpu::ShaderModule, device: &wgpu::Device) {
+        self.downsample_pipeline = create_downsample_pipeline(shader, device, self.color_format);
+    }
+}
+
+fn create_downsample_pipeline(
+    shader: &wgpu::ShaderModule,
+    device: &wgpu::Device,
+    color_format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[
+            Uniforms::cached_layout(),
+            rgba_bind_group_layout_cached(device),
+        ],
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Backdrop blur downsample"),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "downsample",
+            targets: &[Some(wgpu::ColorTargetState {
+                format: color_format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
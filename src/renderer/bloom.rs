@@ -1,6 +1,11 @@
+use std::rc::Rc;
+
 use crate::{
-    make_shader_source, rgba_bind_group_layout_cached, uniforms::Uniforms, HdrTexture, HotReload,
-    ShaderCache, ShaderSource,
+    make_shader_source,
+    renderer::texture_pool::{TexturePool, TransientTextureDesc},
+    rgba_bind_group_layout_cached,
+    uniforms::Uniforms,
+    HdrTexture, HotReload, Resizable, ShaderCache, ShaderSource,
 };
 use wgpu::{BlendComponent, BlendFactor, BlendOperation, BlendState};
 use winit::dpi::PhysicalSize;
@@ -9,6 +14,10 @@ use winit::dpi::PhysicalSize;
 pub struct BloomSettings {
     pub activated: bool,
     pub blend_factor: f64,
+    /// How often the image is halved in size, the smallest level has 1/2^n_levels of the resolution.
+    /// More levels make the glow spread wider, e.g. on large screens. Levels that would be smaller than
+    /// a pixel are skipped on small windows.
+    pub n_levels: usize,
 }
 
 impl Default for BloomSettings {
@@ -16,6 +25,7 @@ impl Default for BloomSettings {
         Self {
             activated: true,
             blend_factor: 0.10,
+            n_levels: 9,
         }
     }
 }
@@ -24,7 +34,8 @@ impl Default for BloomSettings {
 /// We need to be able to use this texture A as a render attachment.
 /// The steps this bloom pipeline takes, each bullet point is one render pass:
 ///
-/// B1 has 1/2 the resolution of the original image, levels[0] has 1/4 the resolution and so on...
+/// B1 has 1/2 the resolution of the original image, levels[0] has 1/4 the resolution and so on,
+/// for [`BloomSettings::n_levels`] levels.
 ///
 /// # 1. Downsampling:
 ///
@@ -42,14 +53,16 @@ impl Default for BloomSettings {
 /// - upsample B1 and add it to the original HDR image A.
 ///
 /// This should result in a bloom.
+///
+/// The textures B1..BX are only needed during [`Bloom::apply`], so they are taken from a [`TexturePool`].
 pub struct Bloom {
-    bloom_textures: BloomTextures,
+    size: PhysicalSize<u32>,
     bloom_pipelines: BloomPipelines,
     settings: BloomSettings,
     color_format: wgpu::TextureFormat,
 }
 
-const SHADER_SOURCE: ShaderSource =
+pub(crate) const BLOOM_SHADER_SOURCE: ShaderSource =
     make_shader_source!("uniforms.wgsl", "screen.wgsl", "bloom.wgsl");
 
 impl Bloom {
@@ -60,13 +73,11 @@ impl Bloom {
         color_format: wgpu::TextureFormat,
         shader_cache: &mut ShaderCache,
     ) -> Self {
-        let bloom_textures = BloomTextures::create(device, width, height, color_format);
-
-        let shader = shader_cache.register(SHADER_SOURCE, device);
+        let shader = shader_cache.register(BLOOM_SHADER_SOURCE, device);
         let bloom_pipelines = BloomPipelines::new(&shader, device, color_format);
 
         Bloom {
-            bloom_textures,
+            size: PhysicalSize::new(width, height),
             bloom_pipelines,
             settings: Default::default(),
             color_format,
@@ -78,16 +89,17 @@ impl Bloom {
     }
 
     /// make sure this is called after graphics context is reconfigured (to match the ctx configs size)
-    pub fn resize(&mut self, size: PhysicalSize<u32>, device: &wgpu::Device) {
-        // recreate the textures on the gpu with the appropriate sizes
-        let width = size.width;
-        let height = size.height;
-        self.bloom_textures = BloomTextures::create(device, width, height, self.color_format);
+    pub fn resize(&mut self, size: PhysicalSize<u32>, _device: &wgpu::Device) {
+        // the textures are taken from the pool with the new size in the next `apply`.
+        self.size = size;
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn apply<'e>(
         &'e mut self,
         encoder: &'e mut wgpu::CommandEncoder,
+        device: &wgpu::Device,
+        pool: &mut TexturePool,
         input_texture: &wgpu::BindGroup,
         output_texture: &wgpu::TextureView,
         uniforms: &'e Uniforms,
@@ -95,6 +107,14 @@ impl Bloom {
         if !self.settings.activated {
             return;
         }
+        let bloom_textures = BloomTextures::acquire(
+            device,
+            pool,
+            self.size.width,
+            self.size.height,
+            self.color_format,
+            self.settings.n_levels,
+        );
 
         fn run_screen_render_pass<'e>(
             label: &str,
@@ -128,159 +148,40 @@ impl Bloom {
         // downsample
         // /////////////////////////////////////////////////////////////////////////////
 
+        let levels = &bloom_textures.levels;
         run_screen_render_pass(
             "1 -> 1/2 downsample and threshold",
             encoder,
             input_texture,
-            self.bloom_textures.levels[0].view(),
+            levels[0].view(),
             uniforms,
             &self.bloom_pipelines.downsample_threshold_pipeline,
         );
-        run_screen_render_pass(
-            "1/2 -> 1/4 downsample",
-            encoder,
-            self.bloom_textures.levels[0].bind_group(),
-            self.bloom_textures.levels[1].view(),
-            uniforms,
-            &self.bloom_pipelines.downsample_pipeline,
-        );
-        run_screen_render_pass(
-            "1/4 -> 1/8 downsample",
-            encoder,
-            self.bloom_textures.levels[1].bind_group(),
-            self.bloom_textures.levels[2].view(),
-            uniforms,
-            &self.bloom_pipelines.downsample_pipeline,
-        );
-        run_screen_render_pass(
-            "1/8 -> 1/16 downsample",
-            encoder,
-            self.bloom_textures.levels[2].bind_group(),
-            self.bloom_textures.levels[3].view(),
-            uniforms,
-            &self.bloom_pipelines.downsample_pipeline,
-        );
-
-        run_screen_render_pass(
-            "1/16 -> 1/32 downsample",
-            encoder,
-            self.bloom_textures.levels[3].bind_group(),
-            self.bloom_textures.levels[4].view(),
-            uniforms,
-            &self.bloom_pipelines.downsample_pipeline,
-        );
-
-        run_screen_render_pass(
-            "1/32 -> 1/64 downsample",
-            encoder,
-            self.bloom_textures.levels[4].bind_group(),
-            self.bloom_textures.levels[5].view(),
-            uniforms,
-            &self.bloom_pipelines.downsample_pipeline,
-        );
-
-        run_screen_render_pass(
-            "1/64 -> 1/128 downsample",
-            encoder,
-            self.bloom_textures.levels[5].bind_group(),
-            self.bloom_textures.levels[6].view(),
-            uniforms,
-            &self.bloom_pipelines.downsample_pipeline,
-        );
-
-        run_screen_render_pass(
-            "1/128 -> 1/256 downsample",
-            encoder,
-            self.bloom_textures.levels[6].bind_group(),
-            self.bloom_textures.levels[7].view(),
-            uniforms,
-            &self.bloom_pipelines.downsample_pipeline,
-        );
-
-        run_screen_render_pass(
-            "1/256 -> 1/512 downsample",
-            encoder,
-            self.bloom_textures.levels[7].bind_group(),
-            self.bloom_textures.levels[8].view(),
-            uniforms,
-            &self.bloom_pipelines.downsample_pipeline,
-        );
+        for (level, pair) in levels.windows(2).enumerate() {
+            run_screen_render_pass(
+                &format!("1/{} -> 1/{} downsample", 2 << level, 4 << level),
+                encoder,
+                pair[0].bind_group(),
+                pair[1].view(),
+                uniforms,
+                &self.bloom_pipelines.downsample_pipeline,
+            );
+        }
 
         // /////////////////////////////////////////////////////////////////////////////
         // upsample
         // /////////////////////////////////////////////////////////////////////////////
 
-        run_screen_render_pass(
-            "1/512 -> 1/256 upsample and add",
-            encoder,
-            self.bloom_textures.levels[8].bind_group(),
-            self.bloom_textures.levels[7].view(),
-            uniforms,
-            &self.bloom_pipelines.upsample_pipeline,
-        );
-
-        run_screen_render_pass(
-            "1/256 -> 1/128 upsample and add",
-            encoder,
-            self.bloom_textures.levels[7].bind_group(),
-            self.bloom_textures.levels[6].view(),
-            uniforms,
-            &self.bloom_pipelines.upsample_pipeline,
-        );
-
-        run_screen_render_pass(
-            "1/128 -> 1/64 upsample and add",
-            encoder,
-            self.bloom_textures.levels[6].bind_group(),
-            self.bloom_textures.levels[5].view(),
-            uniforms,
-            &self.bloom_pipelines.upsample_pipeline,
-        );
-
-        run_screen_render_pass(
-            "1/64 -> 1/32 upsample and add",
-            encoder,
-            self.bloom_textures.levels[5].bind_group(),
-            self.bloom_textures.levels[4].view(),
-            uniforms,
-            &self.bloom_pipelines.upsample_pipeline,
-        );
-
-        run_screen_render_pass(
-            "1/32 -> 1/16 upsample and add",
-            encoder,
-            self.bloom_textures.levels[4].bind_group(),
-            self.bloom_textures.levels[3].view(),
-            uniforms,
-            &self.bloom_pipelines.upsample_pipeline,
-        );
-
-        run_screen_render_pass(
-            "1/16 -> 1/8 upsample and add",
-            encoder,
-            self.bloom_textures.levels[3].bind_group(),
-            self.bloom_textures.levels[2].view(),
-            uniforms,
-            &self.bloom_pipelines.upsample_pipeline,
-        );
-
-        run_screen_render_pass(
-            "1/8 -> 1/4 upsample and add",
-            encoder,
-            self.bloom_textures.levels[2].bind_group(),
-            self.bloom_textures.levels[1].view(),
-            uniforms,
-            &self.bloom_pipelines.upsample_pipeline,
-        );
-
-        run_screen_render_pass(
-            "1/4 -> 1/2 upsample and add",
-            encoder,
-            self.bloom_textures.levels[1].bind_group(),
-            self.bloom_textures.levels[0].view(),
-            uniforms,
-            &self.bloom_pipelines.upsample_pipeline,
-        );
+        for (level, pair) in levels.windows(2).enumerate().rev() {
+            run_screen_render_pass(
+                &format!("1/{} -> 1/{} upsample and add", 4 << level, 2 << level),
+                encoder,
+                pair[1].bind_group(),
+                pair[0].view(),
+                uniforms,
+                &self.bloom_pipelines.upsample_pipeline,
+            );
+        }
 
         // /////////////////////////////////////////////////////////////////////////////
         // Final pass, now with blend factor to add to original image
@@ -311,7 +212,7 @@ impl Bloom {
         pass.set_pipeline(&self.bloom_pipelines.final_upsample_pipeline);
         pass.set_blend_constant(blend_factor);
         pass.set_bind_group(0, uniforms.bind_group(), &[]);
-        pass.set_bind_group(1, self.bloom_textures.levels[0].bind_group(), &[]);
+        pass.set_bind_group(1, bloom_textures.levels[0].bind_group(), &[]);
         pass.draw(0..3, 0..1);
     }
 }
@@ -410,52 +311,75 @@ impl BloomPipelines {
     }
 }
 
-const N_SIZES: usize = 9;
 pub struct BloomTextures {
-    levels: [HdrTexture; N_SIZES],
+    /// at least one, level 0 has 1/2 the resolution, level 1 1/4 and so on.
+    levels: Vec<Rc<HdrTexture>>,
 }
 
 impl BloomTextures {
-    pub fn create(
+    /// The textures are given back to the pool when the [`BloomTextures`] are dropped.
+    pub fn acquire(
         device: &wgpu::Device,
+        pool: &mut TexturePool,
         width: u32,
         height: u32,
         color_format: wgpu::TextureFormat,
+        n_levels: usize,
     ) -> Self {
-        let level = |level: u32| -> HdrTexture {
-            let size = u32::pow(2, level + 1); // level 0 -> 2, level 1 -> 4, etc..
-            HdrTexture::create(
+        let n_levels = usable_levels(width, height, n_levels);
+        let levels = (0..n_levels).map(|level| {
+            let size = u32::pow(2, level as u32 + 1); // level 0 -> 2, level 1 -> 4, etc..
+            let desc = TransientTextureDesc {
+                width: (width / size).max(1),
+                height: (height / size).max(1),
+                sample_count: 1,
+                format: color_format,
+            };
+            pool.acquire(
                 device,
-                width / size,
-                height / size,
-                1,
-                color_format,
-                format!("bloom texture level {level} (1/{})", u32::pow(2, level + 1)),
+                desc,
+                &format!("bloom texture level {level} (1/{size})"),
             )
-        };
-
+        });
         BloomTextures {
-            levels: [
-                level(0),
-                level(1),
-                level(2),
-                level(3),
-                level(4),
-                level(5),
-                level(6),
-                level(7),
-                level(8),
-            ],
+            levels: levels.collect(),
         }
     }
 }
 
+/// `n_levels`, but at least 1 and only as many as can be halved before the smaller side is below a pixel.
+fn usable_levels(width: u32, height: u32, n_levels: usize) -> usize {
+    let max_levels = width.min(height).max(2).ilog2() as usize;
+    n_levels.clamp(1, max_levels)
+}
+
+impl Resizable for Bloom {
+    fn on_resize(&mut self, size: PhysicalSize<u32>, device: &wgpu::Device) {
+        self.resize(size, device);
+    }
+}
+
 impl HotReload for Bloom {
     fn source(&self) -> ShaderSource {
-        SHADER_SOURCE
+        BLOOM_SHADER_SOURCE
     }
 
     fn hot_reload(&mut self, shader: &wgpu::ShaderModule, device: &wgpu::Device) {
         self.bloom_pipelines = BloomPipelines::new(shader, device, self.color_format);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::usable_levels;
+
+    #[test]
+    fn levels_stop_before_textures_vanish() {
+        assert_eq!(usable_levels(1920, 1080, 9), 9);
+        assert_eq!(usable_levels(3440, 1440, 12), 10);
+        // 1/32 of 40 pixels is the last level that is at least a pixel tall.
+        assert_eq!(usable_levels(400, 40, 9), 5);
+        assert_eq!(usable_levels(1, 1, 9), 1);
+        assert_eq!(usable_levels(1920, 1080, 0), 1);
+    }
+}
@@ -1,27 +1,86 @@
 
-use glam::{vec3, Vec3};
+use std::{rc::Rc, time::Instant};
+
+use glam::{vec3, Vec2, Vec3};
 use wgpu::{
     BufferUsages, FragmentState, PrimitiveState,
     RenderPipelineDescriptor, VertexState,
 };
 
 use crate::{
-    make_shader_source, uniforms::Uniforms, Color, GraphicsContext, GrowableBuffer,
+    make_shader_file, make_shader_source, rgba_bind_group_layout_cached, uniforms::Uniforms,
+    utils::rc_addr_as_u64, BindableTexture, Color, GraphicsContext, GrowableBuffer,
     HotReload, ImmediateMeshQueue, ImmediateMeshRanges, RenderFormat, ShaderCache, ShaderSource,
-    ToRaw, Transform, TransformRaw, VertexT, VertsLayout,
+    ShaderFile, ToRaw, Transform, TransformRaw, VertexT, VertsLayout,
 };
 
+use super::RenderStats;
+
 const SHADER_SOURCE: ShaderSource = make_shader_source!("uniforms.wgsl", "color_mesh.wgsl");
+/// Used if no `shader_hooks` are given in the [`ColorMeshRendererConfig`].
+pub const DEFAULT_SHADER_HOOKS: ShaderFile = make_shader_file!("color_mesh_hooks.wgsl");
 
+/// Draws meshes with per-vertex colors, multiplied by the color of each instance.
+///
+/// Meshes with uvs can be drawn with a texture via [`ColorMeshRenderer::draw_textured_geometry`],
+/// the texture is multiplied by the vertex and instance color.
+///
+/// The indices of meshes are triangles unless the [`ColorMeshRendererConfig::topology`] says otherwise.
+/// Lines and points for wireframes and point clouds can be drawn next to them with
+/// [`ColorMeshRenderer::draw_lines`] and [`ColorMeshRenderer::draw_points`].
+///
+/// To see what makes a scene slow, the meshes can be drawn in a [`DebugRenderMode`] instead.
 #[derive(Debug)]
 pub struct ColorMeshRenderer {
     pipeline: wgpu::RenderPipeline,
+    textured_pipeline: wgpu::RenderPipeline,
+    line_pipeline: wgpu::RenderPipeline,
+    point_pipeline: wgpu::RenderPipeline,
+    debug_mode: DebugRenderMode,
+    /// the pipelines of the `debug_mode` in the same order as the ones above, None if it is off or not supported.
+    debug_pipelines: Option<[wgpu::RenderPipeline; 4]>,
     /// immediate geometry, cleared every frame
     color_mesh_queue: ImmediateMeshQueue<Vertex, (Transform, Color)>,
+    textured_mesh_queue: ImmediateMeshQueue<TexturedVertex, (Transform, Color)>,
+    line_queue: ImmediateMeshQueue<Vertex, (Transform, Color)>,
+    point_queue: ImmediateMeshQueue<Vertex, (Transform, Color)>,
+    /// one per mesh in the `textured_mesh_queue`.
+    queued_textures: Vec<Rc<BindableTexture>>,
     /// information about index ranges
-    render_data: RenderData,
+    render_data: RenderData<Vertex>,
+    textured_render_data: RenderData<TexturedVertex>,
+    line_render_data: RenderData<Vertex>,
+    point_render_data: RenderData<Vertex>,
+    /// one per mesh in the `textured_render_data`.
+    textures: Vec<Rc<BindableTexture>>,
     ctx: GraphicsContext,
     config: ColorMeshRendererConfig,
+    stats: RenderStats,
+}
+
+/// Alternative ways to draw the meshes of a [`ColorMeshRenderer`] when optimizing a scene,
+/// switched at runtime with [`ColorMeshRenderer::set_debug_mode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DebugRenderMode {
+    #[default]
+    Off,
+    /// Every fragment adds a bit of light regardless of depth, so the more often a pixel is drawn,
+    /// the brighter it gets: from dark red over yellow to white.
+    Overdraw,
+    /// Draws the edges of the triangles over the meshes. Needs [`wgpu::Features::POLYGON_MODE_LINE`],
+    /// without it the meshes are drawn as usual.
+    Wireframe,
+    /// Draws each draw call in a flat color of its own, to see which meshes are batched together.
+    BatchTint,
+}
+
+impl DebugRenderMode {
+    pub const ALL: [DebugRenderMode; 4] = [
+        DebugRenderMode::Off,
+        DebugRenderMode::Overdraw,
+        DebugRenderMode::Wireframe,
+        DebugRenderMode::BatchTint,
+    ];
 }
 
 #[derive(Debug, Clone)]
@@ -30,6 +89,18 @@ pub struct ColorMeshRendererConfig {
     pub depth_write_enabled: bool,
     pub depth_compare: wgpu::CompareFunction,
     pub blend_state: wgpu::BlendState,
+    /// How the indices of [`ColorMeshRenderer::draw_geometry`] and [`ColorMeshRenderer::draw_textured_geometry`]
+    /// are assembled, e.g. `LineStrip` for paths. Back faces are only culled for triangles.
+    pub topology: wgpu::PrimitiveTopology,
+    /// wgsl that defines the hook functions called by the color mesh shader,
+    /// see `color_mesh_hooks.wgsl` for the functions and their default implementation.
+    pub shader_hooks: Option<ShaderFile>,
+}
+
+impl ColorMeshRendererConfig {
+    fn shader_source(&self) -> ShaderSource {
+        SHADER_SOURCE.with_user_chunk(self.shader_hooks.unwrap_or(DEFAULT_SHADER_HOOKS))
+    }
 }
 
 impl Default for ColorMeshRendererConfig {
@@ -39,6 +110,8 @@ impl Default for ColorMeshRendererConfig {
             depth_write_enabled: true,
             depth_compare: wgpu::CompareFunction::LessEqual,
             blend_state: wgpu::BlendState::ALPHA_BLENDING,
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            shader_hooks: None,
         }
     }
 }
@@ -49,16 +122,53 @@ impl ColorMeshRenderer {
         config: ColorMeshRendererConfig,
         cache: &mut ShaderCache,
     ) -> Self {
-        let shader = cache.register(SHADER_SOURCE, &ctx.device);
-        let pipeline = create_render_pipeline(&shader, &ctx.device, &config);
+        let shader = cache.register(config.shader_source(), &ctx.device);
+        let [pipeline, textured_pipeline, line_pipeline, point_pipeline] =
+            create_render_pipelines(&shader, &ctx.device, &config, DebugRenderMode::Off);
 
         ColorMeshRenderer {
             pipeline,
+            textured_pipeline,
+            line_pipeline,
+            point_pipeline,
+            debug_mode: DebugRenderMode::Off,
+            debug_pipelines: None,
             color_mesh_queue: ImmediateMeshQueue::default(),
+            textured_mesh_queue: ImmediateMeshQueue::default(),
+            line_queue: ImmediateMeshQueue::default(),
+            point_queue: ImmediateMeshQueue::default(),
+            queued_textures: vec![],
             render_data: RenderData::new(&ctx.device),
+            textured_render_data: RenderData::new(&ctx.device),
+            line_render_data: RenderData::new(&ctx.device),
+            point_render_data: RenderData::new(&ctx.device),
+            textures: vec![],
             ctx: ctx.clone(),
             config,
+            stats: RenderStats::default(),
+        }
+    }
+
+    /// Creates the pipelines of the `mode`, they are hot reloaded together with the regular ones.
+    pub fn set_debug_mode(&mut self, mode: DebugRenderMode, cache: &mut ShaderCache) {
+        if mode == self.debug_mode {
+            return;
         }
+        self.debug_mode = mode;
+        let device = &self.ctx.device;
+        self.debug_pipelines = if debug_mode_supported(mode, device) {
+            let shader = cache.register(self.config.shader_source(), device);
+            Some(create_render_pipelines(&shader, device, &self.config, mode))
+        } else {
+            if mode == DebugRenderMode::Wireframe {
+                log::warn!("DebugRenderMode::Wireframe needs the POLYGON_MODE_LINE feature");
+            }
+            None
+        };
+    }
+
+    pub fn debug_mode(&self) -> DebugRenderMode {
+        self.debug_mode
     }
 
     #[inline(always)]
@@ -71,33 +181,53 @@ impl ColorMeshRenderer {
         self.color_mesh_queue.add_mesh(vertices, indices, instances);
     }
 
-    pub fn draw_cubes(&mut self, instances: &[(Transform, Color)]) {
-        const P: f32 = 0.5;
-        const M: f32 = -0.5;
-        let positions = vec![
-            [M, M, M],
-            [P, M, M],
-            [P, M, P],
-            [M, M, P],
-            [M, P, M],
-            [P, P, M],
-            [P, P, P],
-            [M, P, P],
-        ];
+    /// Like [`ColorMeshRenderer::draw_geometry`], but the color of the vertices is multiplied by the `texture`
+    /// sampled at their uvs, e.g. with the vertices from [`crate::MeshData::textured_color_mesh_vertices`].
+    /// Consecutive meshes with the same texture share the bind group.
+    pub fn draw_textured_geometry(
+        &mut self,
+        texture: &Rc<BindableTexture>,
+        vertices: &[TexturedVertex],
+        indices: &[u32],
+        instances: &[(Transform, Color)],
+    ) {
+        self.textured_mesh_queue
+            .add_mesh(vertices, indices, instances);
+        self.queued_textures.push(texture.clone());
+    }
 
-        let vertices: Vec<Vertex> = positions
-            .into_iter()
-            .map(|p| {
-                let x = p[0];
-                let y = p[1];
-                let z = p[2];
-                Vertex {
-                    pos: vec3(x, y, z),
-                    color: Color::WHITE,
-                }
-            })
-            .collect();
+    /// Draws a line between each pair of `indices`, no matter which topology is configured.
+    pub fn draw_lines(
+        &mut self,
+        vertices: &[Vertex],
+        indices: &[u32],
+        instances: &[(Transform, Color)],
+    ) {
+        self.line_queue.add_mesh(vertices, indices, instances);
+    }
+
+    /// Draws a point for each of the `indices`, one pixel in size. No matter which topology is configured.
+    pub fn draw_points(
+        &mut self,
+        vertices: &[Vertex],
+        indices: &[u32],
+        instances: &[(Transform, Color)],
+    ) {
+        self.point_queue.add_mesh(vertices, indices, instances);
+    }
 
+    /// The 12 edges of the unit cubes of [`ColorMeshRenderer::draw_cubes`] as lines.
+    pub fn draw_wire_cubes(&mut self, instances: &[(Transform, Color)]) {
+        let vertices = cube_vertices();
+        let indices = [
+            0, 1, 1, 2, 2, 3, 3, 0, 4, 5, 5, 6, 6, 7, 7, 4, 0, 4, 1, 5, 2, 6, 3, 7,
+        ];
+        self.draw_lines(&vertices, &indices, instances)
+    }
+
+    /// Unit cubes as triangles, which needs the default `TriangleList` topology.
+    pub fn draw_cubes(&mut self, instances: &[(Transform, Color)]) {
+        let vertices = cube_vertices();
         let indices = vec![
             0, 1, 2, 0, 2, 3, 4, 7, 6, 4, 6, 5, 1, 5, 6, 1, 6, 2, 0, 3, 7, 0, 7, 4, 2, 6, 3, 6, 7,
             3, 0, 4, 1, 4, 5, 1,
@@ -106,19 +236,45 @@ impl ColorMeshRenderer {
     }
 
     pub fn prepare(&mut self) {
+        let start = Instant::now();
         let device = &self.ctx.device;
         let queue = &self.ctx.queue;
         self.render_data
-            .vertex_buffer
-            .prepare(self.color_mesh_queue.vertices(), device, queue);
-        self.render_data
-            .index_buffer
-            .prepare(self.color_mesh_queue.indices(), device, queue);
-        self.render_data
-            .instance_buffer
-            .prepare(self.color_mesh_queue.instances(), device, queue);
-        self.color_mesh_queue
-            .clear_and_take_meshes(&mut self.render_data.mesh_ranges);
+            .prepare(&mut self.color_mesh_queue, device, queue);
+        self.textured_render_data
+            .prepare(&mut self.textured_mesh_queue, device, queue);
+        self.line_render_data
+            .prepare(&mut self.line_queue, device, queue);
+        self.point_render_data
+            .prepare(&mut self.point_queue, device, queue);
+        std::mem::swap(&mut self.textures, &mut self.queued_textures);
+        self.queued_textures.clear();
+        let mesh_ranges = self
+            .render_data
+            .mesh_ranges
+            .iter()
+            .chain(self.textured_render_data.mesh_ranges.iter())
+            .chain(self.line_render_data.mesh_ranges.iter())
+            .chain(self.point_render_data.mesh_ranges.iter());
+        self.stats = RenderStats {
+            upload_bytes: self.render_data.byte_len()
+                + self.textured_render_data.byte_len()
+                + self.line_render_data.byte_len()
+                + self.point_render_data.byte_len(),
+            prepare_time: start.elapsed(),
+            ..Default::default()
+        };
+        for mesh in mesh_ranges {
+            let instances = mesh.instance_range.len() as u32;
+            self.stats.draw_calls += 1;
+            self.stats.instances += instances;
+            self.stats.vertices += mesh.index_range.len() as u32 * instances;
+        }
+    }
+
+    /// Stats of the meshes of the last [`ColorMeshRenderer::prepare`].
+    pub fn stats(&self) -> RenderStats {
+        self.stats
     }
 
     pub fn render<'encoder>(
@@ -126,27 +282,130 @@ impl ColorMeshRenderer {
         render_pass: &mut wgpu::RenderPass<'encoder>,
         uniforms: &'encoder Uniforms,
     ) {
-        render_pass.set_pipeline(&self.pipeline);
-        render_pass.set_bind_group(0, uniforms.bind_group(), &[]);
-        render_pass.set_vertex_buffer(0, self.render_data.vertex_buffer.buffer().slice(..));
-        render_pass.set_index_buffer(
-            self.render_data.index_buffer.buffer().slice(..),
-            wgpu::IndexFormat::Uint32,
-        );
-        render_pass.set_vertex_buffer(1, self.render_data.instance_buffer.buffer().slice(..));
-        for mesh in self.render_data.mesh_ranges.iter() {
-            render_pass.draw_indexed(mesh.index_range.clone(), 0, mesh.instance_range.clone())
+        let regular = [
+            &self.pipeline,
+            &self.textured_pipeline,
+            &self.line_pipeline,
+            &self.point_pipeline,
+        ];
+        match (self.debug_mode, &self.debug_pipelines) {
+            (DebugRenderMode::Wireframe, Some(debug)) => {
+                self.render_with(render_pass, uniforms, regular, [true; 4], false);
+                // lines and points are no triangles, their regular drawing already shows them.
+                let debug = [&debug[0], &debug[1], &debug[2], &debug[3]];
+                self.render_with(
+                    render_pass,
+                    uniforms,
+                    debug,
+                    [true, true, false, false],
+                    false,
+                );
+            }
+            (mode, Some(debug)) => {
+                let debug = [&debug[0], &debug[1], &debug[2], &debug[3]];
+                let tint = mode == DebugRenderMode::BatchTint;
+                self.render_with(render_pass, uniforms, debug, [true; 4], tint);
+            }
+            (_, None) => self.render_with(render_pass, uniforms, regular, [true; 4], false),
+        }
+    }
+
+    /// Draws the meshes with the `pipelines` (untextured, textured, lines, points) that are `enabled`.
+    /// With `tint`, the color of each draw call is set as a push constant.
+    fn render_with<'encoder>(
+        &'encoder self,
+        render_pass: &mut wgpu::RenderPass<'encoder>,
+        uniforms: &'encoder Uniforms,
+        pipelines: [&'encoder wgpu::RenderPipeline; 4],
+        enabled: [bool; 4],
+        tint: bool,
+    ) {
+        let mut draw_call: usize = 0;
+        let mut draw = |render_pass: &mut wgpu::RenderPass<'encoder>,
+                        mesh: &ImmediateMeshRanges| {
+            if tint {
+                let color = batch_tint_color(draw_call);
+                render_pass.set_push_constants(
+                    wgpu::ShaderStages::FRAGMENT,
+                    0,
+                    bytemuck::bytes_of(&color),
+                );
+            }
+            render_pass.draw_indexed(mesh.index_range.clone(), 0, mesh.instance_range.clone());
+            draw_call += 1;
+        };
+
+        for (i, render_data) in [
+            (0, &self.render_data),
+            (2, &self.line_render_data),
+            (3, &self.point_render_data),
+        ] {
+            if !enabled[i] || render_data.mesh_ranges.is_empty() {
+                continue;
+            }
+            render_pass.set_pipeline(pipelines[i]);
+            render_pass.set_bind_group(0, uniforms.bind_group(), &[]);
+            render_data.set_buffers(render_pass);
+            for mesh in render_data.mesh_ranges.iter() {
+                draw(render_pass, mesh);
+            }
+        }
+
+        if enabled[1] && !self.textured_render_data.mesh_ranges.is_empty() {
+            render_pass.set_pipeline(pipelines[1]);
+            render_pass.set_bind_group(0, uniforms.bind_group(), &[]);
+            self.textured_render_data.set_buffers(render_pass);
+            let mut last_texture: Option<u64> = None;
+            let meshes = self.textured_render_data.mesh_ranges.iter();
+            for (mesh, texture) in meshes.zip(self.textures.iter()) {
+                let texture_id = rc_addr_as_u64(texture);
+                if last_texture != Some(texture_id) {
+                    render_pass.set_bind_group(1, &texture.bind_group, &[]);
+                    last_texture = Some(texture_id);
+                }
+                draw(render_pass, mesh);
+            }
         }
     }
 }
 
+/// A color for the `index`th draw call, consecutive ones are far apart on the color wheel.
+fn batch_tint_color(index: usize) -> Color {
+    // steps of the golden angle never repeat a hue and spread evenly.
+    let hue = (index as f64 * 137.507_764) % 360.0;
+    Color::from_hsv(hue, 0.75, 0.95)
+}
+
+fn debug_mode_supported(mode: DebugRenderMode, device: &wgpu::Device) -> bool {
+    match mode {
+        DebugRenderMode::Off => false,
+        DebugRenderMode::Wireframe => device
+            .features()
+            .contains(wgpu::Features::POLYGON_MODE_LINE),
+        DebugRenderMode::Overdraw | DebugRenderMode::BatchTint => true,
+    }
+}
+
 impl HotReload for ColorMeshRenderer {
     fn source(&self) -> crate::ShaderSource {
-        SHADER_SOURCE
+        self.config.shader_source()
     }
 
     fn hot_reload(&mut self, shader: &wgpu::ShaderModule, device: &wgpu::Device) {
-        self.pipeline = create_render_pipeline(shader, device, &self.config)
+        [
+            self.pipeline,
+            self.textured_pipeline,
+            self.line_pipeline,
+            self.point_pipeline,
+        ] = create_render_pipelines(shader, device, &self.config, DebugRenderMode::Off);
+        if self.debug_pipelines.is_some() {
+            self.debug_pipelines = Some(create_render_pipelines(
+                shader,
+                device,
+                &self.config,
+                self.debug_mode,
+            ));
+        }
     }
 }
 
@@ -156,14 +415,45 @@ impl HotReload for ColorMeshRenderer {
 
 /// buffers for immediate geometry
 #[derive(Debug)]
-struct RenderData {
+struct RenderData<V: bytemuck::Pod> {
     mesh_ranges: Vec<ImmediateMeshRanges>,
-    vertex_buffer: GrowableBuffer<Vertex>,
+    vertex_buffer: GrowableBuffer<V>,
     index_buffer: GrowableBuffer<u32>,
     instance_buffer: GrowableBuffer<Instance>,
 }
 
-impl RenderData {
+impl<V: bytemuck::Pod> RenderData<V> {
+    fn prepare(
+        &mut self,
+        mesh_queue: &mut ImmediateMeshQueue<V, (Transform, Color)>,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) {
+        self.vertex_buffer
+            .prepare(mesh_queue.vertices(), device, queue);
+        self.index_buffer
+            .prepare(mesh_queue.indices(), device, queue);
+        self.instance_buffer
+            .prepare(mesh_queue.instances(), device, queue);
+        mesh_queue.clear_and_take_meshes(&mut self.mesh_ranges);
+    }
+
+    /// Bytes written by the last prepare.
+    fn byte_len(&self) -> u64 {
+        self.vertex_buffer.byte_len()
+            + self.index_buffer.byte_len()
+            + self.instance_buffer.byte_len()
+    }
+
+    fn set_buffers<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>) {
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.buffer().slice(..));
+        render_pass.set_index_buffer(
+            self.index_buffer.buffer().slice(..),
+            wgpu::IndexFormat::Uint32,
+        );
+        render_pass.set_vertex_buffer(1, self.instance_buffer.buffer().slice(..));
+    }
+
     fn new(device: &wgpu::Device) -> Self {
         Self {
             mesh_ranges: vec![],
@@ -188,6 +478,23 @@ impl VertexT for Vertex {
     ];
 }
 
+/// Vertex of [`ColorMeshRenderer::draw_textured_geometry`].
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TexturedVertex {
+    pub pos: Vec3,
+    pub color: Color,
+    pub uv: Vec2,
+}
+
+impl VertexT for TexturedVertex {
+    const ATTRIBUTES: &'static [wgpu::VertexFormat] = &[
+        wgpu::VertexFormat::Float32x3, // "pos"
+        wgpu::VertexFormat::Float32x4, // "color"
+        wgpu::VertexFormat::Float32x2, // "uv"
+    ];
+}
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod, PartialEq)]
 pub struct Instance {
@@ -216,19 +523,123 @@ impl ToRaw for (Transform, Color) {
     }
 }
 
+fn cube_vertices() -> Vec<Vertex> {
+    const P: f32 = 0.5;
+    const M: f32 = -0.5;
+    let positions = [
+        [M, M, M],
+        [P, M, M],
+        [P, M, P],
+        [M, M, P],
+        [M, P, M],
+        [P, P, M],
+        [P, P, P],
+        [M, P, P],
+    ];
+    positions
+        .into_iter()
+        .map(|[x, y, z]| Vertex {
+            pos: vec3(x, y, z),
+            color: Color::WHITE,
+        })
+        .collect()
+}
+
+/// The pipelines of the configured topology (untextured and textured), for lines and for points,
+/// drawing in the `debug` mode.
+fn create_render_pipelines(
+    shader: &wgpu::ShaderModule,
+    device: &wgpu::Device,
+    config: &ColorMeshRendererConfig,
+    debug: DebugRenderMode,
+) -> [wgpu::RenderPipeline; 4] {
+    use wgpu::PrimitiveTopology::{LineList, PointList};
+    [
+        create_render_pipeline(shader, device, config, config.topology, false, debug),
+        create_render_pipeline(shader, device, config, config.topology, true, debug),
+        create_render_pipeline(shader, device, config, LineList, false, debug),
+        create_render_pipeline(shader, device, config, PointList, false, debug),
+    ]
+}
+
 fn create_render_pipeline(
     shader: &wgpu::ShaderModule,
     device: &wgpu::Device,
     config: &ColorMeshRendererConfig,
+    topology: wgpu::PrimitiveTopology,
+    textured: bool,
+    debug: DebugRenderMode,
 ) -> wgpu::RenderPipeline {
-    let label = "ColorMeshRenderer";
+    let (label, vs_entry_point, mut fs_entry_point) = if textured {
+        ("ColorMeshRenderer textured", "vs_textured", "fs_textured")
+    } else {
+        ("ColorMeshRenderer", "vs_main", "fs_main")
+    };
+    let mut blend_state = config.blend_state;
+    let mut polygon_mode = wgpu::PolygonMode::Fill;
+    let mut depth_write_enabled = config.depth_write_enabled;
+    let mut depth_compare = config.depth_compare;
+    let mut depth_bias = wgpu::DepthBiasState::default();
+    let mut push_constant_ranges: &[wgpu::PushConstantRange] = &[];
+    match debug {
+        DebugRenderMode::Off => {}
+        DebugRenderMode::Overdraw => {
+            fs_entry_point = "fs_overdraw";
+            blend_state = wgpu::BlendState {
+                color: wgpu::BlendComponent {
+                    src_factor: wgpu::BlendFactor::One,
+                    dst_factor: wgpu::BlendFactor::One,
+                    operation: wgpu::BlendOperation::Add,
+                },
+                alpha: wgpu::BlendComponent::OVER,
+            };
+            depth_write_enabled = false;
+            depth_compare = wgpu::CompareFunction::Always;
+        }
+        DebugRenderMode::Wireframe => {
+            fs_entry_point = "fs_wireframe";
+            blend_state = wgpu::BlendState::REPLACE;
+            polygon_mode = wgpu::PolygonMode::Line;
+            depth_write_enabled = false;
+            // pulls the edges towards the camera, such that they are not hidden by their own triangles.
+            let reversed_z = matches!(
+                depth_compare,
+                wgpu::CompareFunction::Greater | wgpu::CompareFunction::GreaterEqual
+            );
+            depth_bias.constant = if reversed_z { 2 } else { -2 };
+        }
+        DebugRenderMode::BatchTint => {
+            fs_entry_point = "fs_batch_tint";
+            push_constant_ranges = &[wgpu::PushConstantRange {
+                stages: wgpu::ShaderStages::FRAGMENT,
+                range: 0..std::mem::size_of::<Color>() as u32,
+            }];
+        }
+    }
 
-    let verts = VertsLayout::new().vertex::<Vertex>().instance::<Instance>();
+    let verts = if textured {
+        VertsLayout::new()
+            .vertex::<TexturedVertex>()
+            .instance::<Instance>()
+    } else {
+        VertsLayout::new().vertex::<Vertex>().instance::<Instance>()
+    };
 
+    let is_triangles = matches!(
+        topology,
+        wgpu::PrimitiveTopology::TriangleList | wgpu::PrimitiveTopology::TriangleStrip
+    );
+    let uniforms_layout = Uniforms::cached_layout();
+    let texture_layout = rgba_bind_group_layout_cached(device);
+    let bind_group_layouts: &[&wgpu::BindGroupLayout] = if textured {
+        &[uniforms_layout, texture_layout]
+    } else {
+        &[uniforms_layout]
+    };
     let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
         label: Some(&format!("{label} PipelineLayout")),
-        bind_group_layouts: &[Uniforms::cached_layout()],
-        push_constant_ranges: &[],
+        bind_group_layouts,
+        push_constant_ranges,
     });
 
     device.create_render_pipeline(&RenderPipelineDescriptor {
@@ -236,25 +647,25 @@ fn create_render_pipeline(
         layout: Some(&layout),
         vertex: VertexState {
             module: &shader,
-            entry_point: "vs_main",
+            entry_point: vs_entry_point,
             buffers: verts.layout(),
         },
         fragment: Some(FragmentState {
             module: &shader,
-            entry_point: "fs_main",
+            entry_point: fs_entry_point,
             targets: &[Some(wgpu::ColorTargetState {
                 format: config.render_format.color,
-                blend: Some(config.blend_state),
+                blend: Some(blend_state),
                 write_mask: wgpu::ColorWrites::ALL,
             })],
         }),
         primitive: PrimitiveState {
-            topology: wgpu::PrimitiveTopology::TriangleList,
-            strip_index_format: None,
+            topology,
+            strip_index_format: topology.is_strip().then_some(wgpu::IndexFormat::Uint32),
             front_face: wgpu::FrontFace::Ccw,
-            cull_mode: Some(wgpu::Face::Back),
+            cull_mode: is_triangles.then_some(wgpu::Face::Back),
             unclipped_depth: false,
-            polygon_mode: wgpu::PolygonMode::Fill,
+            polygon_mode,
             conservative: false,
         },
         depth_stencil: config
@@ -262,10 +673,10 @@ fn create_render_pipeline(
             .depth
             .map(|format| wgpu::DepthStencilState {
                 format,
-                depth_write_enabled: config.depth_write_enabled,
-                depth_compare: config.depth_compare,
+                depth_write_enabled,
+                depth_compare,
                 stencil: wgpu::StencilState::default(),
-                bias: wgpu::DepthBiasState::default(),
+                bias: depth_bias,
             }),
         multisample: wgpu::MultisampleState {
             count: config.render_format.msaa_sample_count,
@@ -274,3 +685,17 @@ fn create_render_pipeline(
         multiview: None,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::batch_tint_color;
+
+    #[test]
+    fn neighboring_draw_calls_get_distinct_tints() {
+        for i in 0..64 {
+            let (a, b) = (batch_tint_color(i), batch_tint_color(i + 1));
+            let difference = (a.r - b.r).abs() + (a.g - b.g).abs() + (a.b - b.b).abs();
+            assert!(difference > 0.3, "draw calls {i} and {} look alike", i + 1);
+        }
+    }
+}
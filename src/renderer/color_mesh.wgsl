@@ -12,6 +12,7 @@ struct Instance {
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) color: vec4<f32>,
+    @location(1) world_pos: vec3<f32>,
 };
 
 @vertex
@@ -25,15 +26,92 @@ fn vs_main(
         instance.col3,
         instance.translation,
     );
-    let world_position = vec4<f32>(vertex.position, 1.0);
+    let world_position = model_matrix * vec4<f32>(vertex.position, 1.0);
 
     var out: VertexOutput;
-    out.clip_position = camera.view_proj * model_matrix * world_position;
+    out.clip_position = camera.view_proj * world_position;
     out.color = vertex.color * instance.color;
+    out.world_pos = world_position.xyz;
     return out;
 }
  
 @fragment
 fn fs_main(fragment: VertexOutput) -> @location(0) vec4<f32> {
-    return fragment.color;
-}
\ No newline at end of file
+    // see color_mesh_hooks.wgsl for the default implementation
+    return user_modify_color(fragment.color, fragment.world_pos);
+}
+
+// The textured variant, the vertices have an uv after the color, which shifts the instance locations by one.
+
+@group(1) @binding(0)
+var t_diffuse: texture_2d<f32>;
+@group(1) @binding(1)
+var s_diffuse: sampler;
+
+struct TexturedVertex {
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) uv: vec2<f32>,
+}
+struct TexturedInstance {
+    @location(3) col1: vec4<f32>,
+    @location(4) col2: vec4<f32>,
+    @location(5) col3: vec4<f32>,
+    @location(6) translation: vec4<f32>,
+    @location(7) color: vec4<f32>,
+}
+struct TexturedVertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) world_pos: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+};
+
+@vertex
+fn vs_textured(
+    vertex: TexturedVertex,
+    instance: TexturedInstance,
+) -> TexturedVertexOutput {
+    let model_matrix = mat4x4<f32>(
+        instance.col1,
+        instance.col2,
+        instance.col3,
+        instance.translation,
+    );
+    let world_position = model_matrix * vec4<f32>(vertex.position, 1.0);
+
+    var out: TexturedVertexOutput;
+    out.clip_position = camera.view_proj * world_position;
+    out.color = vertex.color * instance.color;
+    out.world_pos = world_position.xyz;
+    out.uv = vertex.uv;
+    return out;
+}
+
+@fragment
+fn fs_textured(fragment: TexturedVertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(t_diffuse, s_diffuse, fragment.uv) * fragment.color;
+    return user_modify_color(color, fragment.world_pos);
+}
+
+// Debug views, see `DebugRenderMode` in color_mesh.rs. They work for both variants and skip the hooks.
+
+var<push_constant> batch_color: vec4<f32>;
+
+// Every fragment adds a bit of light, so pixels drawn often glow from dark red over yellow to white.
+@fragment
+fn fs_overdraw(fragment: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(0.08, 0.025, 0.008, 0.0);
+}
+
+@fragment
+fn fs_wireframe(fragment: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4<f32>(0.0, 1.0, 0.4, 1.0);
+}
+
+// The color of the draw call, shaded a bit by the brightness of the mesh to keep its shape visible.
+@fragment
+fn fs_batch_tint(fragment: VertexOutput) -> @location(0) vec4<f32> {
+    let brightness = dot(fragment.color.rgb, vec3<f32>(0.2126, 0.7152, 0.0722));
+    return vec4<f32>(batch_color.rgb * (0.6 + 0.4 * brightness), 1.0);
+}
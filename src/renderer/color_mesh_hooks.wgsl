@@ -0,0 +1,6 @@
+// Default hook functions called by color_mesh.wgsl.
+// To customize them, pass a ShaderFile defining the same functions as `shader_hooks` in the ColorMeshRendererConfig.
+
+fn user_modify_color(color: vec4<f32>, world_pos: vec3<f32>) -> vec4<f32> {
+    return color;
+}
@@ -0,0 +1,150 @@
+use glam::{uvec3, UVec3};
+
+use crate::{HotReload, ShaderCache, ShaderSource};
+
+/// A compute pipeline for one entry point of a shader, that is rebuilt on hot reload.
+///
+/// The workgroup size needs to match the `@workgroup_size` of the entry point, it is used to compute
+/// the number of workgroups from the number of invocations:
+///
+/// ```rust,ignore
+/// const SOURCE: ShaderSource = make_shader_source!("exposure.wgsl");
+/// let task = ComputeTask::new(&device, &mut cache, SOURCE, "histogram", &[&layout], 0, uvec3(16, 16, 1));
+/// let mut pass = encoder.begin_compute_pass(&Default::default());
+/// task.dispatch(&mut pass, &[&bind_group], uvec3(width, height, 1));
+/// ```
+///
+/// Entry points don't know how many invocations were requested, so they need to skip the
+/// invocations outside of the data themselves, the last workgroup is usually only partially used.
+pub struct ComputeTask {
+    source: ShaderSource,
+    entry_point: &'static str,
+    workgroup_size: UVec3,
+    layout: wgpu::PipelineLayout,
+    pipeline: wgpu::ComputePipeline,
+}
+
+impl ComputeTask {
+    /// `push_constant_size` in bytes, 0 for no push constants.
+    pub fn new(
+        device: &wgpu::Device,
+        cache: &mut ShaderCache,
+        source: ShaderSource,
+        entry_point: &'static str,
+        bind_group_layouts: &[&wgpu::BindGroupLayout],
+        push_constant_size: u32,
+        workgroup_size: UVec3,
+    ) -> Self {
+        let push_constant_range = wgpu::PushConstantRange {
+            stages: wgpu::ShaderStages::COMPUTE,
+            range: 0..push_constant_size,
+        };
+        let push_constant_ranges: &[wgpu::PushConstantRange] = if push_constant_size == 0 {
+            &[]
+        } else {
+            std::slice::from_ref(&push_constant_range)
+        };
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some(entry_point),
+            bind_group_layouts,
+            push_constant_ranges,
+        });
+        let shader = cache.register(source, device);
+        let pipeline = create_pipeline(&shader, device, &layout, entry_point);
+        ComputeTask {
+            source,
+            entry_point,
+            workgroup_size: workgroup_size.max(UVec3::ONE),
+            layout,
+            pipeline,
+        }
+    }
+
+    pub fn pipeline(&self) -> &wgpu::ComputePipeline {
+        &self.pipeline
+    }
+
+    pub fn workgroup_size(&self) -> UVec3 {
+        self.workgroup_size
+    }
+
+    /// Number of workgroups needed to cover all `invocations`, rounded up.
+    pub fn workgroups_for(&self, invocations: UVec3) -> UVec3 {
+        workgroups_for(invocations, self.workgroup_size)
+    }
+
+    /// Sets the pipeline and the bind groups (starting at group 0) and dispatches enough workgroups for `invocations`.
+    pub fn dispatch<'a>(
+        &'a self,
+        pass: &mut wgpu::ComputePass<'a>,
+        bind_groups: &[&'a wgpu::BindGroup],
+        invocations: UVec3,
+    ) {
+        self.dispatch_with_push_constants(pass, bind_groups, &[], invocations);
+    }
+
+    /// [`ComputeTask::dispatch`] for 1d data like buffers.
+    pub fn dispatch_1d<'a>(
+        &'a self,
+        pass: &mut wgpu::ComputePass<'a>,
+        bind_groups: &[&'a wgpu::BindGroup],
+        invocations: u32,
+    ) {
+        self.dispatch(pass, bind_groups, uvec3(invocations, 1, 1));
+    }
+
+    /// Like [`ComputeTask::dispatch`], but sets the push constants first if `push_constants` is not empty.
+    pub fn dispatch_with_push_constants<'a>(
+        &'a self,
+        pass: &mut wgpu::ComputePass<'a>,
+        bind_groups: &[&'a wgpu::BindGroup],
+        push_constants: &[u8],
+        invocations: UVec3,
+    ) {
+        let workgroups = self.workgroups_for(invocations);
+        if workgroups.cmpeq(UVec3::ZERO).any() {
+            return;
+        }
+        pass.set_pipeline(&self.pipeline);
+        for (i, bind_group) in bind_groups.iter().enumerate() {
+            pass.set_bind_group(i as u32, bind_group, &[]);
+        }
+        if !push_constants.is_empty() {
+            pass.set_push_constants(0, push_constants);
+        }
+        pass.dispatch_workgroups(workgroups.x, workgroups.y, workgroups.z);
+    }
+}
+
+impl HotReload for ComputeTask {
+    fn source(&self) -> ShaderSource {
+        self.source
+    }
+
+    fn hot_reload(&mut self, shader: &wgpu::ShaderModule, device: &wgpu::Device) {
+        self.pipeline = create_pipeline(shader, device, &self.layout, self.entry_point);
+    }
+}
+
+/// Workgroups along each axis to cover `invocations`, the last one of each axis can be partially used.
+pub fn workgroups_for(invocations: UVec3, workgroup_size: UVec3) -> UVec3 {
+    uvec3(
+        invocations.x.div_ceil(workgroup_size.x),
+        invocations.y.div_ceil(workgroup_size.y),
+        invocations.z.div_ceil(workgroup_size.z),
+    )
+}
+
+fn create_pipeline(
+    shader: &wgpu::ShaderModule,
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    entry_point: &str,
+) -> wgpu::ComputePipeline {
+    device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+        label: Some(entry_point),
+        layout: Some(layout),
+        module: shader,
+        entry_point,
+    })
+}
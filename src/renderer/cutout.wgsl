@@ -0,0 +1,16 @@
+/// Alpha test of `AlphaMode::Cutout`: discards the fragment below the `threshold`, otherwise it is opaque.
+/// With `alpha_to_coverage`, the alpha is sharpened to a ramp of about one pixel around the threshold instead,
+/// such that the msaa samples covered by it antialias the edge.
+fn alpha_cutout(color: vec4<f32>, threshold: f32, alpha_to_coverage: bool) -> vec4<f32> {
+    if !alpha_to_coverage {
+        if color.a < threshold {
+            discard;
+        }
+        return vec4(color.rgb, 1.0);
+    }
+    let alpha = clamp((color.a - threshold) / max(fwidth(color.a), 0.0001) + 0.5, 0.0, 1.0);
+    if alpha <= 0.0 {
+        discard;
+    }
+    return vec4(color.rgb, alpha);
+}
@@ -1,72 +1,104 @@
 
+use std::time::Instant;
+
 use glam::vec2;
 use glam::vec3;
+use glam::IVec2;
+use glam::Vec2;
 use glam::Vec3;
 use wgpu::BufferUsages;
 use wgpu::FragmentState;
 use wgpu::PrimitiveState;
 use wgpu::VertexState;
 
+use crate::collision::Shape2;
+use crate::collision::Shape3;
 use crate::make_shader_source;
+use crate::spline::Curve;
 use crate::uniforms::Uniforms;
+use crate::utils::grid::GridMap;
 use crate::Aabb;
 use crate::Color;
 use crate::GraphicsContext;
 use crate::GrowableBuffer;
 use crate::HotReload;
+use crate::Obb2;
 use crate::ShaderCache;
 use crate::ShaderSource;
 use crate::VertexT;
 use crate::VertsLayout;
 
 use super::RenderFormat;
+use super::RenderStats;
 
 const SHADER_SOURCE: ShaderSource = make_shader_source!("uniforms.wgsl", "gizmos.wgsl");
 
-pub struct GizmosVertexQueue(pub Vec<Vertex>);
+/// Width of gizmo lines.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineWidth {
+    /// Same width on screen regardless of the distance to the camera.
+    Pixels(f32),
+    /// Width in world units, so lines get thinner further away from the camera.
+    World(f32),
+}
+
+impl Default for LineWidth {
+    fn default() -> Self {
+        LineWidth::Pixels(2.0)
+    }
+}
+
+impl LineWidth {
+    fn to_raw(self) -> Vec2 {
+        match self {
+            LineWidth::Pixels(px) => vec2(px, 0.0),
+            LineWidth::World(world) => vec2(0.0, world),
+        }
+    }
+}
 
-impl GizmosVertexQueue {
+pub struct GizmosLineQueue {
+    pub lines: Vec<LineInstance>,
+    /// Width of lines drawn with [`GizmosLineQueue::draw_line`] and the other draw functions.
+    pub line_width: LineWidth,
+}
+
+impl Default for GizmosLineQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GizmosLineQueue {
     pub fn new() -> Self {
-        Self(vec![])
+        Self {
+            lines: vec![],
+            line_width: LineWidth::default(),
+        }
     }
 
     #[inline(always)]
     pub fn clear(&mut self) {
-        self.0.clear();
+        self.lines.clear();
     }
 
     pub fn draw_line(&mut self, from: Vec3, to: Vec3, color: Color) {
-        self.0.push(Vertex { pos: from, color });
-        self.0.push(Vertex { pos: to, color });
+        self.draw_line_with_width(from, to, color, self.line_width);
     }
 
-    pub fn draw_xyz(&mut self) {
-        self.0.push(Vertex {
-            pos: Vec3::ZERO,
-            color: Color::RED,
-        });
-        self.0.push(Vertex {
-            pos: Vec3::X,
-            color: Color::RED,
-        });
-
-        self.0.push(Vertex {
-            pos: Vec3::ZERO,
-            color: Color::GREEN,
-        });
-        self.0.push(Vertex {
-            pos: Vec3::Y,
-            color: Color::GREEN,
+    pub fn draw_line_with_width(&mut self, from: Vec3, to: Vec3, color: Color, width: LineWidth) {
+        self.lines.push(LineInstance {
+            from,
+            to,
+            color,
+            width: width.to_raw(),
         });
+    }
 
-        self.0.push(Vertex {
-            pos: Vec3::ZERO,
-            color: Color::BLUE,
-        });
-        self.0.push(Vertex {
-            pos: Vec3::Z,
-            color: Color::BLUE,
-        });
+    pub fn draw_xyz(&mut self) {
+        self.draw_line(Vec3::ZERO, Vec3::X, Color::RED);
+        self.draw_line(Vec3::ZERO, Vec3::Y, Color::GREEN);
+        self.draw_line(Vec3::ZERO, Vec3::Z, Color::BLUE);
     }
 
     pub fn draw_cube(&mut self, position: Vec3, side_len: f32, color: Color) {
@@ -96,8 +128,7 @@ impl GizmosVertexQueue {
         ];
 
         for (from, to) in lines {
-            self.0.push(Vertex { pos: from, color });
-            self.0.push(Vertex { pos: to, color });
+            self.draw_line(from, to, color);
         }
     }
 
@@ -112,15 +143,67 @@ impl GizmosVertexQueue {
         self.draw_line(c, d, color);
         self.draw_line(d, a, color);
     }
+
+    /// Draws the outline of the box in the xy plane, like [`GizmosLineQueue::draw_aabb`].
+    pub fn draw_obb(&mut self, obb: Obb2, color: Color) {
+        let corners = obb.corners();
+        for i in 0..4 {
+            let from = corners[i].extend(0.0);
+            let to = corners[(i + 1) % 4].extend(0.0);
+            self.draw_line(from, to, color);
+        }
+    }
+
+    pub fn draw_spline(&mut self, curve: &impl Curve<Vec3>, color: Color) {
+        let points = curve.polyline(SPLINE_SAMPLES_PER_SEGMENT);
+        for w in points.windows(2) {
+            self.draw_line(w[0], w[1], color);
+        }
+    }
+
+    /// Draws the outline of the collision shape in the xy plane.
+    pub fn draw_shape2(&mut self, shape: impl Into<Shape2>, color: Color) {
+        for (from, to) in shape.into().outline() {
+            self.draw_line(from, to, color);
+        }
+    }
+
+    pub fn draw_shape3(&mut self, shape: impl Into<Shape3>, color: Color) {
+        for (from, to) in shape.into().outline() {
+            self.draw_line(from, to, color);
+        }
+    }
+
+    /// Draws the lines between all cells from `min` to `max` (inclusive) in the plane of the grid.
+    pub fn draw_grid_region(&mut self, grid: &GridMap, min: IVec2, max: IVec2, color: Color) {
+        let bounds = grid.region_bounds(min, max);
+        for x in min.x..=max.x + 1 {
+            let x = x as f32 * grid.cell_size.x;
+            let from = grid.from_plane(vec2(x, bounds.min.y));
+            let to = grid.from_plane(vec2(x, bounds.max.y));
+            self.draw_line(from, to, color);
+        }
+        for y in min.y..=max.y + 1 {
+            let y = y as f32 * grid.cell_size.y;
+            let from = grid.from_plane(vec2(bounds.min.x, y));
+            let to = grid.from_plane(vec2(bounds.max.x, y));
+            self.draw_line(from, to, color);
+        }
+    }
 }
 
+/// Number of lines [`Gizmos::draw_spline`] uses for each segment of a curve.
+const SPLINE_SAMPLES_PER_SEGMENT: usize = 16;
+
+/// Draws lines as anti-aliased quads with round caps, see `gizmos.wgsl`.
 pub struct Gizmos {
-    /// immediate vertices, written to vertex_buffer every frame.
-    vertex_queue: GizmosVertexQueue,
+    /// immediate lines, written to instance_buffer every frame.
+    line_queue: GizmosLineQueue,
     pipeline: wgpu::RenderPipeline,
-    vertex_buffer: GrowableBuffer<Vertex>,
+    instance_buffer: GrowableBuffer<LineInstance>,
     ctx: GraphicsContext,
     render_format: RenderFormat,
+    stats: RenderStats,
 }
 
 impl Gizmos {
@@ -129,16 +212,17 @@ impl Gizmos {
         render_format: RenderFormat,
         shader_cache: &mut ShaderCache,
     ) -> Self {
-        let vertex_buffer = GrowableBuffer::new(&ctx.device, 256, BufferUsages::VERTEX);
+        let instance_buffer = GrowableBuffer::new(&ctx.device, 256, BufferUsages::VERTEX);
 
         let shader = shader_cache.register(SHADER_SOURCE, &ctx.device);
         let pipeline = create_pipeline(&shader, &ctx.device, render_format);
         Gizmos {
             pipeline,
-            vertex_queue: GizmosVertexQueue::new(),
-            vertex_buffer,
+            line_queue: GizmosLineQueue::new(),
+            instance_buffer,
             ctx: ctx.clone(),
             render_format,
+            stats: RenderStats::default(),
         }
     }
 
@@ -147,39 +231,102 @@ impl Gizmos {
         render_pass: &mut wgpu::RenderPass<'encoder>,
         uniforms: &'encoder Uniforms,
     ) {
-        if self.vertex_buffer.len() == 0 {
+        if self.instance_buffer.len() == 0 {
             return;
         }
+        const VERTEX_COUNT: u32 = 4;
         render_pass.set_pipeline(&self.pipeline);
         render_pass.set_bind_group(0, uniforms.bind_group(), &[]);
-        render_pass.set_vertex_buffer(0, self.vertex_buffer.buffer().slice(..));
-        render_pass.draw(0..self.vertex_buffer.len() as u32, 0..1);
+        render_pass.set_vertex_buffer(0, self.instance_buffer.buffer().slice(..));
+        render_pass.draw(0..VERTEX_COUNT, 0..self.instance_buffer.len() as u32);
     }
 
     pub fn prepare(&mut self) {
-        self.vertex_buffer
-            .prepare(&self.vertex_queue.0, &self.ctx.device, &self.ctx.queue);
-        self.vertex_queue.0.clear();
+        let start = Instant::now();
+        self.instance_buffer
+            .prepare(&self.line_queue.lines, &self.ctx.device, &self.ctx.queue);
+        self.stats = RenderStats {
+            draw_calls: (!self.line_queue.lines.is_empty()) as u32,
+            instances: self.line_queue.lines.len() as u32,
+            vertices: 4 * self.line_queue.lines.len() as u32,
+            upload_bytes: self.instance_buffer.byte_len(),
+            prepare_time: start.elapsed(),
+        };
+        self.line_queue.clear();
+    }
+
+    /// Stats of the lines of the last [`Gizmos::prepare`].
+    pub fn stats(&self) -> RenderStats {
+        self.stats
+    }
+
+    /// Width of lines drawn with [`Gizmos::draw_line`] and the other draw functions, 2px by default.
+    pub fn set_line_width(&mut self, width: LineWidth) {
+        self.line_queue.line_width = width;
+    }
+
+    pub fn line_width(&self) -> LineWidth {
+        self.line_queue.line_width
     }
 
     #[inline]
     pub fn draw_line(&mut self, from: Vec3, to: Vec3, color: Color) {
-        self.vertex_queue.draw_line(from, to, color)
+        self.line_queue.draw_line(from, to, color)
+    }
+
+    #[inline]
+    pub fn draw_line_with_width(&mut self, from: Vec3, to: Vec3, color: Color, width: LineWidth) {
+        self.line_queue.draw_line_with_width(from, to, color, width)
     }
 
     #[inline]
     pub fn draw_xyz(&mut self) {
-        self.vertex_queue.draw_xyz();
+        self.line_queue.draw_xyz();
     }
 
     #[inline]
     pub fn draw_cube(&mut self, position: Vec3, side_len: f32, color: Color) {
-        self.vertex_queue.draw_cube(position, side_len, color)
+        self.line_queue.draw_cube(position, side_len, color)
     }
 
     #[inline]
     pub fn draw_aabb(&mut self, aabb: Aabb, color: Color) {
-        self.vertex_queue.draw_aabb(aabb, color);
+        self.line_queue.draw_aabb(aabb, color);
+    }
+
+    #[inline]
+    pub fn draw_obb(&mut self, obb: Obb2, color: Color) {
+        self.line_queue.draw_obb(obb, color);
+    }
+
+    /// Draws a [`crate::spline::CubicBezier`], [`crate::spline::CatmullRom`] or any other [`Curve`] as lines.
+    #[inline]
+    pub fn draw_spline(&mut self, curve: &impl Curve<Vec3>, color: Color) {
+        self.line_queue.draw_spline(curve, color);
+    }
+
+    /// Draws a [`crate::collision::Circle`], [`Aabb`], [`Obb2`] or [`crate::collision::Capsule2`] in the xy plane.
+    #[inline]
+    pub fn draw_shape2(&mut self, shape: impl Into<Shape2>, color: Color) {
+        self.line_queue.draw_shape2(shape, color);
+    }
+
+    /// Draws a [`crate::collision::Sphere`], [`crate::collision::Aabb3`] or [`crate::collision::Capsule3`].
+    #[inline]
+    pub fn draw_shape3(&mut self, shape: impl Into<Shape3>, color: Color) {
+        self.line_queue.draw_shape3(shape, color);
+    }
+
+    /// Draws the cells from `min` to `max` (inclusive) of a [`GridMap`].
+    #[inline]
+    pub fn draw_grid_region(&mut self, grid: &GridMap, min: IVec2, max: IVec2, color: Color) {
+        self.line_queue.draw_grid_region(grid, min, max, color);
+    }
+
+    /// Draws the outline of one cell of a [`GridMap`].
+    #[inline]
+    pub fn draw_grid_cell(&mut self, grid: &GridMap, cell: IVec2, color: Color) {
+        self.line_queue.draw_grid_region(grid, cell, cell, color);
     }
 }
 
@@ -199,14 +346,21 @@ impl HotReload for Gizmos {
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
-pub struct Vertex {
-    pub pos: Vec3,
+pub struct LineInstance {
+    pub from: Vec3,
+    pub to: Vec3,
     pub color: Color,
+    /// x: width in pixels, y: width in world units
+    pub width: Vec2,
 }
 
-impl VertexT for Vertex {
-    const ATTRIBUTES: &'static [wgpu::VertexFormat] =
-        &[wgpu::VertexFormat::Float32x3, wgpu::VertexFormat::Float32x4];
+impl VertexT for LineInstance {
+    const ATTRIBUTES: &'static [wgpu::VertexFormat] = &[
+        wgpu::VertexFormat::Float32x3,
+        wgpu::VertexFormat::Float32x3,
+        wgpu::VertexFormat::Float32x4,
+        wgpu::VertexFormat::Float32x2,
+    ];
 }
 
 pub fn create_pipeline(
@@ -215,7 +369,7 @@ pub fn create_pipeline(
     render_format: RenderFormat,
 ) -> wgpu::RenderPipeline {
     let label = "Gizmos";
-    let vertexes = VertsLayout::new().vertex::<Vertex>();
+    let vertexes = VertsLayout::new().instance::<LineInstance>();
 
     let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
         label: Some(&format!("{label} PipelineLayout")),
@@ -236,18 +390,15 @@ pub fn create_pipeline(
             entry_point: "fs_main",
             targets: &[Some(wgpu::ColorTargetState {
                 format: render_format.color,
-                blend: Some(wgpu::BlendState {
-                    alpha: wgpu::BlendComponent::REPLACE,
-                    color: wgpu::BlendComponent::REPLACE,
-                }),
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
                 write_mask: wgpu::ColorWrites::ALL,
             })],
         }),
         primitive: PrimitiveState {
-            topology: wgpu::PrimitiveTopology::LineList,
+            topology: wgpu::PrimitiveTopology::TriangleStrip,
             strip_index_format: None,
             front_face: wgpu::FrontFace::Ccw,
-            cull_mode: Some(wgpu::Face::Back),
+            cull_mode: None,
             unclipped_depth: false,
             polygon_mode: wgpu::PolygonMode::Fill,
             conservative: false,
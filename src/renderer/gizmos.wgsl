@@ -1,26 +1,98 @@
-struct Vertex {
-    @location(0) pos: vec3<f32>,
-    @location(1) color: vec4<f32>,
+// Each line is an instance, drawn as a screen space quad (triangle strip with 4 vertices) around the segment,
+// extended by the line radius at both ends for the round caps.
+// The fragment shader computes the distance to the segment for the anti-aliased edges.
+
+struct LineInstance {
+    @location(0) start: vec3<f32>,
+    @location(1) end: vec3<f32>,
+    @location(2) color: vec4<f32>,
+    // x: width in pixels, y: width in world units. Both are added.
+    @location(3) width: vec2<f32>,
 }
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) color: vec4<f32>,
+    // position of the vertex in pixels, relative to the screen center.
+    @location(1) @interpolate(linear) pos: vec2<f32>,
+    // half width of the line in pixels at this vertex
+    @location(2) @interpolate(linear) radius: f32,
+    @location(3) @interpolate(flat) segment: vec4<f32>,
 };
 
+// extra pixels around the line for the anti-aliasing
+const AA_PADDING: f32 = 1.0;
+// points closer to the camera than this (in clip space w) are clipped away.
+const MIN_W: f32 = 0.0001;
+
 @vertex
 fn vs_main(
-    vertex: Vertex,
+    @builtin(vertex_index) vertex_index: u32,
+    line: LineInstance,
 ) -> VertexOutput {
-    let world_position = vec4<f32>(vertex.pos, 1.0);
-    
     var out: VertexOutput;
-    out.clip_position = camera.view_proj * world_position;
-    out.color = vertex.color;
+    var c0 = camera.view_proj * vec4<f32>(line.start, 1.0);
+    var c1 = camera.view_proj * vec4<f32>(line.end, 1.0);
+
+    // clip the segment against the near plane, otherwise the projection flips points behind the camera.
+    if c0.w < MIN_W && c1.w < MIN_W {
+        out.clip_position = vec4<f32>(0.0, 0.0, 0.0, 0.0);
+        return out;
+    }
+    if c0.w < MIN_W {
+        c0 = mix(c0, c1, (MIN_W - c0.w) / (c1.w - c0.w));
+    } else if c1.w < MIN_W {
+        c1 = mix(c1, c0, (MIN_W - c1.w) / (c0.w - c1.w));
+    }
+
+    let half_screen = vec2<f32>(screen.width, screen.height) * 0.5;
+    let p0 = c0.xy / c0.w * half_screen;
+    let p1 = c1.xy / c1.w * half_screen;
+
+    // camera.proj[1][1] is 1 / tan(fov_y / 2) for perspective and 2 / y_height for orthographic projections.
+    let px_per_world_unit_at_w1 = camera.proj[1][1] * half_screen.y;
+    let r0 = 0.5 * (line.width.x + line.width.y * px_per_world_unit_at_w1 / c0.w);
+    let r1 = 0.5 * (line.width.x + line.width.y * px_per_world_unit_at_w1 / c1.w);
+
+    var dir = p1 - p0;
+    let len = length(dir);
+    if len < 0.0001 {
+        dir = vec2<f32>(1.0, 0.0);
+    } else {
+        dir = dir / len;
+    }
+    let normal = vec2<f32>(-dir.y, dir.x);
+
+    let side = select(-1.0, 1.0, (vertex_index & 1u) == 1u);
+    let is_end = vertex_index >= 2u;
+    let c = select(c0, c1, is_end);
+    let p = select(p0, p1, is_end);
+    let r = select(r0, r1, is_end);
+    let along = select(-1.0, 1.0, is_end);
+    let extent = r + AA_PADDING;
+    let pos = p + (normal * side + dir * along) * extent;
+
+    out.clip_position = vec4<f32>(pos / half_screen * c.w, c.z, c.w);
+    out.color = line.color;
+    out.pos = pos;
+    out.radius = r;
+    out.segment = vec4<f32>(p0, p1);
     return out;
 }
- 
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    return in.color;
+    let a = in.segment.xy;
+    let b = in.segment.zw;
+    let ab = b - a;
+    let t = clamp(dot(in.pos - a, ab) / max(dot(ab, ab), 0.0001), 0.0, 1.0);
+    let dist = length(in.pos - (a + ab * t));
+    // lines thinner than a pixel are faded out instead of becoming thinner, to avoid flickering.
+    let radius = max(in.radius, 0.5);
+    let thin_factor = min(in.radius / 0.5, 1.0);
+    let alpha = clamp(radius - dist + 0.5, 0.0, 1.0) * thin_factor;
+    if alpha <= 0.0 {
+        discard;
+    }
+    return vec4<f32>(in.color.rgb, in.color.a * alpha);
 }
@@ -0,0 +1,294 @@
+use std::{cell::Cell, rc::Rc, sync::Arc};
+
+use glam::{Vec2, Vec3};
+use wgpu::{util::DeviceExt, ShaderStages};
+
+use crate::{
+    make_shader_source, rgba_bind_group_layout_cached, texture::white_px_texture_cached,
+    BindableTexture, Camera3dGR, Color, GltfModel, GraphicsContext, HotReload, MeshData,
+    RenderFormat, RenderStats, ShaderCache, ShaderSource, Texture, ToRaw, Transform, TransformRaw,
+    VertexT, VertsLayout,
+};
+
+const SHADER_SOURCE: ShaderSource = make_shader_source!("uniforms.wgsl", "mesh.wgsl");
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct MeshVertex {
+    pub pos: Vec3,
+    pub normal: Vec3,
+    pub uv: Vec2,
+}
+
+impl VertexT for MeshVertex {
+    const ATTRIBUTES: &'static [wgpu::VertexFormat] = &[
+        wgpu::VertexFormat::Float32x3, // pos
+        wgpu::VertexFormat::Float32x3, // normal
+        wgpu::VertexFormat::Float32x2, // uv
+    ];
+}
+
+/// `PushData` in `mesh.wgsl`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Zeroable)]
+struct PushData {
+    transform: TransformRaw,
+    base_color: Color,
+    light: [f32; 4],
+}
+
+unsafe impl bytemuck::Pod for PushData {}
+
+/// A mesh in gpu buffers with its material, drawn by a [`MeshRenderer`].
+#[derive(Debug)]
+pub struct GpuMesh {
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    n_indices: u32,
+    pub base_color: Color,
+    /// Multiplied with the base color, white if None.
+    pub texture: Option<Rc<BindableTexture>>,
+}
+
+impl GpuMesh {
+    pub fn new(
+        device: &wgpu::Device,
+        mesh: &MeshData,
+        base_color: Color,
+        texture: Option<Rc<BindableTexture>>,
+    ) -> Self {
+        let vertices: Vec<MeshVertex> = (0..mesh.vertex_count())
+            .map(|i| MeshVertex {
+                pos: mesh.positions[i],
+                normal: mesh.normals.get(i).copied().unwrap_or(Vec3::ZERO),
+                uv: mesh.uvs.get(i).copied().unwrap_or(Vec2::ZERO),
+            })
+            .collect();
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("mesh vertices"),
+            contents: bytemuck::cast_slice(&vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("mesh indices"),
+            contents: bytemuck::cast_slice(&mesh.indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        GpuMesh {
+            vertex_buffer,
+            index_buffer,
+            n_indices: mesh.indices.len() as u32,
+            base_color,
+            texture,
+        }
+    }
+
+    pub fn n_indices(&self) -> u32 {
+        self.n_indices
+    }
+}
+
+/// All primitives of a [`GltfModel`] on the gpu, primitives with the same image share the texture.
+#[derive(Debug)]
+pub struct GpuModel {
+    pub meshes: Vec<GpuMesh>,
+}
+
+impl GpuModel {
+    pub fn new(ctx: &GraphicsContext, model: &GltfModel) -> Self {
+        let textures: Vec<Rc<BindableTexture>> = model
+            .images
+            .iter()
+            .map(|image| {
+                let texture = Texture::from_image(
+                    &ctx.device,
+                    &ctx.queue,
+                    image,
+                    wgpu::FilterMode::Linear,
+                    wgpu::AddressMode::Repeat,
+                );
+                Rc::new(BindableTexture::new(&ctx.device, texture))
+            })
+            .collect();
+        let meshes = model
+            .primitives
+            .iter()
+            .map(|primitive| {
+                let material = &primitive.material;
+                let texture = material
+                    .base_color_texture
+                    .and_then(|i| textures.get(i).cloned());
+                GpuMesh::new(&ctx.device, &primitive.mesh, material.base_color, texture)
+            })
+            .collect();
+        GpuModel { meshes }
+    }
+}
+
+/// Draws static meshes, e.g. [`GpuModel`]s loaded from glTF files, with their base color and texture.
+///
+/// Meshes are lit by one directional light and some ambient light, they write depth and cull back faces.
+/// Unlike the [`crate::ColorMeshRenderer`], meshes are not queued: their buffers stay on the gpu and each
+/// [`MeshRenderer::render`] records their draw calls right away.
+pub struct MeshRenderer {
+    pipeline: wgpu::RenderPipeline,
+    render_format: RenderFormat,
+    ctx: GraphicsContext,
+    camera_layout: Arc<wgpu::BindGroupLayout>,
+    /// Direction the light shines in.
+    pub light_dir: Vec3,
+    /// Light that also reaches faces turned away from the light, between 0.0 and 1.0.
+    pub ambient: f32,
+    /// counted in `render`, which only gets `&self` to keep the borrows of the render pass.
+    stats: Cell<RenderStats>,
+}
+
+impl MeshRenderer {
+    pub fn new(
+        ctx: &GraphicsContext,
+        camera: &Camera3dGR,
+        render_format: RenderFormat,
+        cache: &mut ShaderCache,
+    ) -> Self {
+        let shader = cache.register(SHADER_SOURCE, &ctx.device);
+        let pipeline = create_pipeline(&shader, ctx, camera.bind_group_layout(), render_format);
+        MeshRenderer {
+            pipeline,
+            render_format,
+            ctx: ctx.clone(),
+            camera_layout: camera.bind_group_layout().clone(),
+            light_dir: Vec3::new(-0.3, -1.0, -0.5).normalize(),
+            ambient: 0.3,
+            stats: Cell::new(RenderStats::default()),
+        }
+    }
+
+    /// Stats of the meshes rendered since the last call, call it once per frame after rendering.
+    pub fn take_stats(&self) -> RenderStats {
+        self.stats.take()
+    }
+
+    /// Draws all meshes of the model with the `transform`.
+    pub fn render<'a>(
+        &'a self,
+        pass: &mut wgpu::RenderPass<'a>,
+        camera: &'a Camera3dGR,
+        model: &'a GpuModel,
+        transform: &Transform,
+    ) {
+        for mesh in model.meshes.iter() {
+            self.render_mesh(pass, camera, mesh, transform);
+        }
+    }
+
+    pub fn render_mesh<'a>(
+        &'a self,
+        pass: &mut wgpu::RenderPass<'a>,
+        camera: &'a Camera3dGR,
+        mesh: &'a GpuMesh,
+        transform: &Transform,
+    ) {
+        let texture = match &mesh.texture {
+            Some(texture) => texture,
+            None => white_px_texture_cached(&self.ctx),
+        };
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, camera.bind_group(), &[]);
+        pass.set_bind_group(1, &texture.bind_group, &[]);
+        let push = PushData {
+            transform: transform.to_raw(),
+            base_color: mesh.base_color,
+            light: self
+                .light_dir
+                .normalize_or_zero()
+                .extend(self.ambient)
+                .into(),
+        };
+        pass.set_push_constants(
+            ShaderStages::VERTEX | ShaderStages::FRAGMENT,
+            0,
+            bytemuck::bytes_of(&push),
+        );
+        pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+        pass.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        pass.draw_indexed(0..mesh.n_indices, 0, 0..1);
+
+        let mut stats = self.stats.get();
+        stats += RenderStats {
+            draw_calls: 1,
+            instances: 1,
+            vertices: mesh.n_indices,
+            ..Default::default()
+        };
+        self.stats.set(stats);
+    }
+}
+
+fn create_pipeline(
+    shader: &wgpu::ShaderModule,
+    ctx: &GraphicsContext,
+    camera_layout: &wgpu::BindGroupLayout,
+    render_format: RenderFormat,
+) -> wgpu::RenderPipeline {
+    let layout = ctx
+        .device
+        .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("mesh pipeline"),
+            bind_group_layouts: &[camera_layout, rgba_bind_group_layout_cached(&ctx.device)],
+            push_constant_ranges: &[wgpu::PushConstantRange {
+                stages: ShaderStages::VERTEX | ShaderStages::FRAGMENT,
+                range: 0..std::mem::size_of::<PushData>() as u32,
+            }],
+        });
+    let vertexes = VertsLayout::new().vertex::<MeshVertex>();
+    ctx.device
+        .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("mesh pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                buffers: vertexes.layout(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: render_format.color,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                unclipped_depth: false,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                conservative: false,
+            },
+            depth_stencil: render_format.depth.map(|format| wgpu::DepthStencilState {
+                format,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: render_format.msaa_sample_count,
+                ..Default::default()
+            },
+            multiview: None,
+        })
+}
+
+impl HotReload for MeshRenderer {
+    fn source(&self) -> ShaderSource {
+        SHADER_SOURCE
+    }
+
+    fn hot_reload(&mut self, shader: &wgpu::ShaderModule, _device: &wgpu::Device) {
+        self.pipeline = create_pipeline(shader, &self.ctx, &self.camera_layout, self.render_format);
+    }
+}
@@ -0,0 +1,53 @@
+@group(1) @binding(0)
+var t_diffuse: texture_2d<f32>;
+@group(1) @binding(1)
+var s_diffuse: sampler;
+
+struct PushData {
+    col1: vec4<f32>,
+    col2: vec4<f32>,
+    col3: vec4<f32>,
+    translation: vec4<f32>,
+    base_color: vec4<f32>,
+    // xyz: direction the light shines in, w: ambient light.
+    light: vec4<f32>,
+}
+var<push_constant> push: PushData;
+
+struct Vertex {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) normal: vec3<f32>,
+    @location(1) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(vertex: Vertex) -> VertexOutput {
+    let model_matrix = mat4x4<f32>(
+        push.col1,
+        push.col2,
+        push.col3,
+        push.translation,
+    );
+    let world_position = model_matrix * vec4<f32>(vertex.position, 1.0);
+
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * world_position;
+    // only exact for uniform scales, which is what transforms of meshes usually have.
+    out.normal = (model_matrix * vec4<f32>(vertex.normal, 0.0)).xyz;
+    out.uv = vertex.uv;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(t_diffuse, s_diffuse, in.uv) * push.base_color;
+    let diffuse = max(dot(normalize(in.normal), -push.light.xyz), 0.0);
+    let light = push.light.w + (1.0 - push.light.w) * diffuse;
+    return vec4<f32>(color.rgb * light, color.a);
+}
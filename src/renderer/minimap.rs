@@ -0,0 +1,299 @@
+use glam::{vec2, vec3, DVec2, Mat4, Vec2, Vec3};
+
+use crate::{
+    ui::{div, element::ComputedBounds, Corners, Div, Len, TextureRegion},
+    Camera3d, Camera3dRaw, Color, Input, OffscreenCamera, RenderFormat, Time, ToneMapping,
+};
+
+/// A top-down map of the scene with markers, shown as a div in the ui.
+///
+/// The scene is rendered from an orthographic camera looking straight down, with an [`OffscreenCamera`].
+/// That is only done every `update_interval` frames, while the markers are ui elements placed on top of
+/// the image every frame, so moving units stay smooth on a map that is cheap to keep up to date:
+///
+/// ```rust,ignore
+/// let mut minimap = Minimap::new(&device, 256, 256, RenderFormat::HDR_MSAA4, output_format);
+/// // every frame:
+/// minimap.center = player.pos;
+/// minimap.markers.clear();
+/// minimap.markers.push(MinimapMarker::new(player.pos, Color::GREEN));
+/// if minimap.tick() {
+///     minimap.prepare(&queue, &time, &input);
+///     let mut pass = minimap.new_render_pass(&mut encoder, Color::BLACK);
+///     terrain_renderer.render(&mut pass, minimap.uniforms());
+///     drop(pass);
+///     minimap.finish(&mut encoder, &mut tone_mapping);
+/// }
+/// // in the ui:
+/// div().child_with_id(MINIMAP_ID, minimap.element(200.0, 200.0))
+/// // clicking on the map:
+/// if ctx.state_of(MINIMAP_ID).just_started_click {
+///     let bounds = ctx.computed_bounds(MINIMAP_ID).unwrap();
+///     let target = minimap.layout_to_world(ctx.cursor_pos(), bounds);
+/// }
+/// ```
+///
+/// For a map that only shows markers, render nothing into the pass, the clear color becomes the background.
+pub struct Minimap {
+    /// The world position shown in the center of the map. Its y is the height of the ground for
+    /// [`Minimap::uv_to_world`].
+    pub center: Vec3,
+    /// How many world units the map covers from top to bottom. The width follows from the aspect ratio.
+    pub world_height: f32,
+    /// Rotation of the map in radians around the y axis. At 0.0 the top of the map is towards -z.
+    pub rotation: f32,
+    /// The camera is this far above the center. Everything between `altitude` above and `altitude` below
+    /// the center is rendered.
+    pub altitude: f32,
+    /// The scene is rendered every this many frames, see [`Minimap::tick`].
+    pub update_interval: u32,
+    /// Drawn on top of the map by [`Minimap::element`]. Markers outside of the map are hidden.
+    pub markers: Vec<MinimapMarker>,
+    offscreen: OffscreenCamera,
+    size: Vec2,
+    frames_until_update: u32,
+}
+
+/// An icon or dot on a [`Minimap`] at a position in the world.
+#[derive(Debug, Clone)]
+pub struct MinimapMarker {
+    pub pos: Vec3,
+    /// Multiplies the `icon`, or the color of the dot without an icon.
+    pub color: Color,
+    pub icon: Option<TextureRegion>,
+    /// Width and height in layout units.
+    pub size: f32,
+    /// Shows markers outside of the map at its edge instead of hiding them, e.g. for objectives.
+    pub clamp_to_edge: bool,
+}
+
+impl MinimapMarker {
+    pub fn new(pos: Vec3, color: Color) -> Self {
+        MinimapMarker {
+            pos,
+            color,
+            icon: None,
+            size: 8.0,
+            clamp_to_edge: false,
+        }
+    }
+
+    pub fn icon(mut self, icon: TextureRegion) -> Self {
+        self.icon = Some(icon);
+        self
+    }
+
+    pub fn size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn clamp_to_edge(mut self) -> Self {
+        self.clamp_to_edge = true;
+        self
+    }
+}
+
+impl Minimap {
+    /// The map texture has a size of `width` x `height` px. The scene is rendered in the `render_format`
+    /// of the renderers and tone mapped into the `output_format`, like for an [`OffscreenCamera`].
+    /// The texture is leaked, so create minimaps once.
+    pub fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        render_format: RenderFormat,
+        output_format: wgpu::TextureFormat,
+    ) -> Self {
+        let camera = Camera3d::new(width, height);
+        Minimap {
+            center: Vec3::ZERO,
+            world_height: 100.0,
+            rotation: 0.0,
+            altitude: 500.0,
+            update_interval: 10,
+            markers: vec![],
+            offscreen: OffscreenCamera::new(
+                device,
+                camera,
+                width,
+                height,
+                render_format,
+                output_format,
+            ),
+            size: vec2(width as f32, height as f32),
+            frames_until_update: 0,
+        }
+    }
+
+    /// Call once per frame. Returns true every `update_interval` frames (and after [`Minimap::request_update`]),
+    /// when the scene should be rendered into the map.
+    pub fn tick(&mut self) -> bool {
+        if self.frames_until_update == 0 {
+            self.frames_until_update = self.update_interval.max(1) - 1;
+            true
+        } else {
+            self.frames_until_update -= 1;
+            false
+        }
+    }
+
+    /// Renders the scene in the next [`Minimap::tick`], e.g. after the map was moved far.
+    pub fn request_update(&mut self) {
+        self.frames_until_update = 0;
+    }
+
+    /// The world direction that points to the top of the map, in the xz plane.
+    pub fn up_direction(&self) -> Vec3 {
+        up_direction(self.rotation)
+    }
+
+    pub fn view_matrix(&self) -> Mat4 {
+        top_down_view(self.center, self.rotation, self.altitude)
+    }
+
+    pub fn projection_matrix(&self) -> Mat4 {
+        top_down_projection(self.world_height, self.size.x / self.size.y, self.altitude)
+    }
+
+    /// The top-down camera, see [`Minimap::prepare`].
+    pub fn camera_raw(&self) -> Camera3dRaw {
+        let eye = self.center + Vec3::Y * self.altitude;
+        Camera3dRaw::from_matrices(eye, self.view_matrix(), self.projection_matrix())
+    }
+
+    /// Where a world position is on the map, (0,0) at the top left and (1,1) at the bottom right corner.
+    pub fn world_to_uv(&self, world_pos: Vec3) -> Vec2 {
+        let view_proj = self.projection_matrix() * self.view_matrix();
+        ndc_to_uv(view_proj.project_point3(world_pos))
+    }
+
+    /// The world position on the ground (at the height of `center`) at a position on the map.
+    pub fn uv_to_world(&self, uv: Vec2) -> Vec3 {
+        let view_proj = self.projection_matrix() * self.view_matrix();
+        let ndc = vec3(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 0.5);
+        let world_pos = view_proj.inverse().project_point3(ndc);
+        vec3(world_pos.x, self.center.y, world_pos.z)
+    }
+
+    /// The world position at a position in layout space (e.g. the cursor position of the
+    /// [`crate::ui::ElementContext`]), on the div of the map with the computed `bounds`. None if outside of the map.
+    pub fn layout_to_world(&self, layout_pos: DVec2, bounds: ComputedBounds) -> Option<Vec3> {
+        if !bounds.contains(layout_pos) {
+            return None;
+        }
+        let uv = ((layout_pos - bounds.pos) / bounds.size).as_vec2();
+        Some(self.uv_to_world(uv))
+    }
+
+    /// Writes the top-down camera into the uniforms. Call before rendering, only needed in frames where
+    /// [`Minimap::tick`] returned true.
+    pub fn prepare(&mut self, queue: &wgpu::Queue, time: &Time, input: &Input) {
+        let camera = self.camera_raw();
+        self.offscreen
+            .prepare_camera_raw(queue, camera, time, input);
+    }
+
+    /// Uniforms with the top-down camera, to be passed to the `render` functions of renderers.
+    pub fn uniforms(&self) -> &crate::uniforms::Uniforms {
+        self.offscreen.uniforms()
+    }
+
+    pub fn new_render_pass<'e>(
+        &'e self,
+        encoder: &'e mut wgpu::CommandEncoder,
+        clear_color: Color,
+    ) -> wgpu::RenderPass<'e> {
+        self.offscreen.new_render_pass(encoder, clear_color)
+    }
+
+    /// Tone maps the rendered scene into the texture of the map.
+    pub fn finish(&self, encoder: &mut wgpu::CommandEncoder, tone_mapping: &mut ToneMapping) {
+        self.offscreen.finish(encoder, tone_mapping);
+    }
+
+    /// The entire map texture, without markers.
+    pub fn texture_region(&self) -> TextureRegion {
+        self.offscreen.texture_region()
+    }
+
+    /// A div of `width` x `height` layout units that shows the map with the markers on top.
+    /// Store it with an id to get its bounds for [`Minimap::layout_to_world`].
+    pub fn element(&self, width: f64, height: f64) -> Div {
+        let mut map = div().style(|s| {
+            s.width = Some(Len::Px(width));
+            s.height = Some(Len::Px(height));
+            s.color = Color::WHITE;
+            s.texture(self.texture_region());
+        });
+        let map_size = DVec2::new(width, height);
+        for marker in self.markers.iter() {
+            let mut uv = self.world_to_uv(marker.pos);
+            if marker.clamp_to_edge {
+                uv = uv.clamp(Vec2::ZERO, Vec2::ONE);
+            } else if uv.cmplt(Vec2::ZERO).any() || uv.cmpgt(Vec2::ONE).any() {
+                continue;
+            }
+            let size = marker.size as f64;
+            map.push(div().style(|s| {
+                s.width = Some(Len::Px(size));
+                s.height = Some(Len::Px(size));
+                s.absolute = Some(Vec2::ZERO);
+                s.offset = uv.as_dvec2() * map_size - DVec2::splat(size * 0.5);
+                s.color = marker.color;
+                match &marker.icon {
+                    Some(icon) => s.texture(*icon),
+                    None => s.border.radius = Corners::all(marker.size * 0.5),
+                }
+            }));
+        }
+        map
+    }
+}
+
+fn up_direction(rotation: f32) -> Vec3 {
+    let (sin, cos) = rotation.sin_cos();
+    vec3(sin, 0.0, -cos)
+}
+
+fn top_down_view(center: Vec3, rotation: f32, altitude: f32) -> Mat4 {
+    let eye = center + Vec3::Y * altitude;
+    Mat4::look_to_rh(eye, Vec3::NEG_Y, up_direction(rotation))
+}
+
+fn top_down_projection(world_height: f32, aspect: f32, altitude: f32) -> Mat4 {
+    let top = world_height * 0.5;
+    let right = top * aspect;
+    Mat4::orthographic_rh(-right, right, -top, top, 0.0, altitude * 2.0)
+}
+
+fn ndc_to_uv(ndc: Vec3) -> Vec2 {
+    vec2(ndc.x * 0.5 + 0.5, 0.5 - ndc.y * 0.5)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use glam::{vec2, vec3, Vec2, Vec3};
+
+    use super::{ndc_to_uv, top_down_projection, top_down_view};
+
+    fn uv(center: Vec3, rotation: f32, world_pos: Vec3) -> Vec2 {
+        let view_proj =
+            top_down_projection(20.0, 1.0, 50.0) * top_down_view(center, rotation, 50.0);
+        ndc_to_uv(view_proj.project_point3(world_pos))
+    }
+
+    #[test]
+    fn north_is_up_and_east_is_right() {
+        let center = vec3(5.0, 0.0, 5.0);
+        assert!(uv(center, 0.0, center).abs_diff_eq(vec2(0.5, 0.5), 1e-5));
+        assert!(uv(center, 0.0, center + vec3(10.0, 3.0, 0.0)).abs_diff_eq(vec2(1.0, 0.5), 1e-5));
+        assert!(uv(center, 0.0, center + vec3(0.0, 0.0, -10.0)).abs_diff_eq(vec2(0.5, 0.0), 1e-5));
+        // rotated such that +x is up:
+        assert!(
+            uv(center, FRAC_PI_2, center + vec3(10.0, 0.0, 0.0)).abs_diff_eq(vec2(0.5, 0.0), 1e-5)
+        );
+    }
+}
@@ -1,15 +1,83 @@
+use std::{ops::AddAssign, time::Duration};
+
 pub mod color_mesh;
+pub mod compute_task;
 #[cfg(feature = "eguimod")]
 pub mod egui;
 pub mod gizmos;
+pub mod mesh;
+pub mod minimap;
+pub mod offscreen_camera;
 
+pub mod backdrop_blur;
 pub mod bloom;
 pub mod particles;
+pub mod pixel_perfect;
+pub mod planar_reflection;
+pub mod render_target;
 pub mod screen_textures;
 pub mod sdf_sprite;
+pub mod sprite_drivers;
+pub mod texture_pool;
 pub mod tone_mapping;
+pub mod transition;
 pub mod ui_3d;
+pub mod ui_cache;
 pub mod ui_screen;
+pub mod world_labels;
+pub mod world_text;
+
+/// What a renderer submits in a frame, to make performance regressions visible, e.g. with `show!(stats)`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RenderStats {
+    pub draw_calls: u32,
+    pub instances: u32,
+    /// Vertices processed by the vertex shader: vertices (or indices) per instance times instances, summed over all draw calls.
+    pub vertices: u32,
+    /// Bytes written to gpu buffers in the last `prepare`.
+    pub upload_bytes: u64,
+    /// Cpu time of the last `prepare`, with sorting, batching and buffer uploads.
+    pub prepare_time: Duration,
+}
+
+impl AddAssign for RenderStats {
+    fn add_assign(&mut self, rhs: Self) {
+        self.draw_calls += rhs.draw_calls;
+        self.instances += rhs.instances;
+        self.vertices += rhs.vertices;
+        self.upload_bytes += rhs.upload_bytes;
+        self.prepare_time += rhs.prepare_time;
+    }
+}
+
+/// The [`RenderStats`] of the built-in renderers in one frame, to see where draw calls and uploads come from
+/// without a gpu debugger, e.g. with `show!(world.frame_stats())`.
+///
+/// [`crate::DefaultWorld::frame_stats`] fills in the renderers it owns, the others can be set from their `stats()`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FrameStats {
+    pub color_mesh: RenderStats,
+    pub meshes: RenderStats,
+    pub ui: RenderStats,
+    pub sdf_sprites: RenderStats,
+    pub gizmos: RenderStats,
+    pub particles: RenderStats,
+    pub world_text: RenderStats,
+}
+
+impl FrameStats {
+    /// All renderers summed up.
+    pub fn total(&self) -> RenderStats {
+        let mut total = self.color_mesh;
+        total += self.meshes;
+        total += self.ui;
+        total += self.sdf_sprites;
+        total += self.gizmos;
+        total += self.particles;
+        total += self.world_text;
+        total
+    }
+}
 
 #[derive(Debug, Clone, Copy)]
 pub struct RenderFormat {
@@ -31,3 +99,59 @@ impl RenderFormat {
         msaa_sample_count: 1,
     };
 }
+
+/// How the sprites of a [`crate::SdfSpriteRenderer`] or the particles of a [`crate::ParticleRenderer`]
+/// are combined with the scene.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum AlphaMode {
+    /// Alpha blended over the scene without writing depth. Soft edges, but only correct if everything
+    /// is drawn back to front, intersecting sprites overlap wrongly.
+    #[default]
+    Blend,
+    /// Opaque where the alpha is at least `threshold` and discarded elsewhere, e.g. for foliage.
+    /// Writes depth, so the order does not matter. With `alpha_to_coverage` and a [`RenderFormat`] with msaa,
+    /// the alpha around the threshold decides how many samples are covered, which antialiases the edges.
+    Cutout {
+        threshold: f32,
+        alpha_to_coverage: bool,
+    },
+}
+
+impl AlphaMode {
+    pub const CUTOUT: AlphaMode = AlphaMode::Cutout {
+        threshold: 0.5,
+        alpha_to_coverage: true,
+    };
+
+    pub fn is_cutout(&self) -> bool {
+        matches!(self, AlphaMode::Cutout { .. })
+    }
+
+    /// Alpha to coverage needs more than one sample per pixel.
+    pub fn alpha_to_coverage(&self, render_format: RenderFormat) -> bool {
+        match self {
+            AlphaMode::Blend => false,
+            AlphaMode::Cutout {
+                alpha_to_coverage, ..
+            } => *alpha_to_coverage && render_format.msaa_sample_count > 1,
+        }
+    }
+
+    /// The threshold and 0.0 or 1.0 for alpha to coverage, for the `alpha_cutout` function of `cutout.wgsl`.
+    pub(crate) fn cutout_params(&self, render_format: RenderFormat) -> [f32; 2] {
+        match self {
+            AlphaMode::Blend => [0.0, 0.0],
+            AlphaMode::Cutout { threshold, .. } => {
+                let coverage = self.alpha_to_coverage(render_format);
+                [*threshold, if coverage { 1.0 } else { 0.0 }]
+            }
+        }
+    }
+
+    pub(crate) fn blend_state(&self) -> Option<wgpu::BlendState> {
+        match self {
+            AlphaMode::Blend => Some(wgpu::BlendState::ALPHA_BLENDING),
+            AlphaMode::Cutout { .. } => None,
+        }
+    }
+}
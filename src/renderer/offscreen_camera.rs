@@ -0,0 +1,120 @@
+use winit::dpi::PhysicalSize;
+
+use crate::{
+    leak, ui::TextureRegion, uniforms::Uniforms, Aabb, BindableTexture, Camera3d, Camera3dRaw,
+    Color, HdrTexture, Input, RenderFormat, Screen, ScreenTextures, Time, ToneMapping,
+};
+
+/// Renders the scene from a secondary camera into an offscreen texture, e.g. for minimaps, portals or mirrors.
+///
+/// Each [`OffscreenCamera`] has its own [`Uniforms`] (camera, screen size, ...). Renderers take the uniforms
+/// per render call, so the same renderers can draw the scene once for the main camera and once for every offscreen camera:
+///
+/// ```rust,ignore
+/// offscreen.prepare(&ctx.queue, &time, &input);
+/// let mut pass = offscreen.new_render_pass(&mut encoder, Color::BLACK);
+/// color_renderer.render(&mut pass, offscreen.uniforms());
+/// drop(pass);
+/// offscreen.finish(&mut encoder, &mut tone_mapping);
+/// // later in the ui:
+/// div().style(|s| s.texture(offscreen.texture_region()))
+/// ```
+///
+/// The output is tone mapped by the [`ToneMapping`] passed to [`OffscreenCamera::finish`], so its `output_format`
+/// needs to match the output format of the tone mapping.
+/// The output texture is leaked, such that it can be used as a [`crate::ui::DivTexture`] which needs a `'static` reference.
+/// So create offscreen cameras once and do not recreate them every frame.
+pub struct OffscreenCamera {
+    pub camera: Camera3d,
+    screen: Screen,
+    uniforms: Uniforms,
+    screen_textures: ScreenTextures,
+    output: &'static HdrTexture,
+}
+
+impl OffscreenCamera {
+    pub fn new(
+        device: &wgpu::Device,
+        camera: Camera3d,
+        width: u32,
+        height: u32,
+        render_format: RenderFormat,
+        output_format: wgpu::TextureFormat,
+    ) -> Self {
+        let output = leak(HdrTexture::create(
+            device,
+            width,
+            height,
+            1,
+            output_format,
+            "offscreen camera output",
+        ));
+        Self {
+            camera,
+            screen: Screen::new(PhysicalSize::new(width, height), 1.0),
+            uniforms: Uniforms::new(device),
+            screen_textures: ScreenTextures::new(device, width, height, render_format),
+            output,
+        }
+    }
+
+    /// Writes the camera of this offscreen camera into its uniforms. Call once per frame before rendering.
+    pub fn prepare(&mut self, queue: &wgpu::Queue, time: &Time, input: &Input) {
+        self.uniforms
+            .prepare(queue, &self.camera, &self.screen, time, input);
+    }
+
+    /// Like [`OffscreenCamera::prepare`], but with custom camera matrices instead of the `camera`,
+    /// e.g. the top-down view of a [`crate::Minimap`].
+    pub fn prepare_camera_raw(
+        &mut self,
+        queue: &wgpu::Queue,
+        camera: Camera3dRaw,
+        time: &Time,
+        input: &Input,
+    ) {
+        self.uniforms
+            .prepare_camera_raw(queue, camera, &self.screen, time, input);
+    }
+
+    /// Uniforms with this camera, to be passed to the `render` functions of renderers instead of the main uniforms.
+    pub fn uniforms(&self) -> &Uniforms {
+        &self.uniforms
+    }
+
+    /// Hdr render target, the same as for the main camera with [`ScreenTextures::new_hdr_target_render_pass`].
+    pub fn new_render_pass<'e>(
+        &'e self,
+        encoder: &'e mut wgpu::CommandEncoder,
+        clear_color: Color,
+    ) -> wgpu::RenderPass<'e> {
+        self.screen_textures
+            .new_hdr_target_render_pass(encoder, clear_color)
+    }
+
+    /// Hdr texture the scene was rendered into, e.g. to apply bloom to it before calling [`OffscreenCamera::finish`].
+    pub fn hdr_texture(&self) -> &HdrTexture {
+        self.screen_textures.resolved()
+    }
+
+    /// Tone maps the rendered scene into the output texture.
+    pub fn finish(&self, encoder: &mut wgpu::CommandEncoder, tone_mapping: &mut ToneMapping) {
+        tone_mapping.apply(
+            encoder,
+            self.screen_textures.resolved().bind_group(),
+            self.output.view(),
+        );
+    }
+
+    pub fn texture(&self) -> &'static BindableTexture {
+        self.output.bindable()
+    }
+
+    /// The entire output texture, to be used in [`crate::ui::element::DivStyle::texture`].
+    pub fn texture_region(&self) -> TextureRegion {
+        TextureRegion {
+            texture: self.output.bindable(),
+            uv: Aabb::new(glam::Vec2::ZERO, glam::Vec2::ONE),
+        }
+    }
+}
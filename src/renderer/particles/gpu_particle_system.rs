@@ -0,0 +1,432 @@
+use std::sync::OnceLock;
+
+use glam::{uvec3, Vec2, Vec3, Vec4};
+use wgpu::BindGroupLayout;
+
+use crate::{
+    make_shader_source, texture::BindableTextureRef, Aabb, Color, ComputeTask, GraphicsContext,
+    HotReload, ShaderCache, ShaderSource, Time, Transform,
+};
+
+use super::particle_sort::particle_buffer_size;
+
+const SHADER_SOURCE: ShaderSource = make_shader_source!("gpu_particles.wgsl");
+
+const WORKGROUP_SIZE: u32 = 256;
+
+/// Bytes of a `GpuParticle` in `gpu_particles.wgsl`: position, age, velocity and lifetime.
+const GPU_PARTICLE_SIZE: u64 = 32;
+
+/// How a [`GpuParticleSystem`] spawns and moves its particles. Positions are relative to the transform of
+/// the system, random values are picked per particle on the gpu.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GpuEmitter {
+    /// Particles spawned per second, on top of the ones from [`GpuParticleSystem::burst`].
+    pub spawn_rate: f32,
+    /// Half size of the box around the origin that particles spawn in.
+    pub spawn_extent: Vec3,
+    pub velocity: Vec3,
+    /// Up to this much velocity is added or subtracted in each direction.
+    pub velocity_randomness: f32,
+    /// E.g. gravity.
+    pub acceleration: Vec3,
+    /// Fraction of the velocity lost per second.
+    pub drag: f32,
+    /// Min and max lifetime in seconds.
+    pub lifetime: (f32, f32),
+    /// Size at the start and end of the lifetime, interpolated linearly.
+    pub size: (Vec2, Vec2),
+    /// Color at the start and end of the lifetime, interpolated linearly.
+    pub color: (Color, Color),
+    pub uv: Aabb,
+}
+
+impl Default for GpuEmitter {
+    fn default() -> Self {
+        GpuEmitter {
+            spawn_rate: 100.0,
+            spawn_extent: Vec3::ZERO,
+            velocity: Vec3::Y,
+            velocity_randomness: 0.5,
+            acceleration: Vec3::ZERO,
+            drag: 0.0,
+            lifetime: (1.0, 2.0),
+            size: (Vec2::splat(0.1), Vec2::splat(0.1)),
+            color: (Color::WHITE, Color::WHITE.alpha(0.0)),
+            uv: Aabb::UNIT,
+        }
+    }
+}
+
+/// `Emitter` in `gpu_particles.wgsl`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct EmitterRaw {
+    spawn_extent: Vec4,
+    velocity: Vec4,
+    acceleration: Vec4,
+    lifetime: Vec4,
+    size: Vec4,
+    color_start: Vec4,
+    color_end: Vec4,
+    uv: Vec4,
+}
+
+impl From<&GpuEmitter> for EmitterRaw {
+    fn from(e: &GpuEmitter) -> Self {
+        let color = |c: Color| Vec4::new(c.r, c.g, c.b, c.a);
+        EmitterRaw {
+            spawn_extent: e.spawn_extent.extend(0.0),
+            velocity: e.velocity.extend(e.velocity_randomness),
+            acceleration: e.acceleration.extend(e.drag),
+            lifetime: Vec4::new(e.lifetime.0, e.lifetime.1, 0.0, 0.0),
+            size: Vec4::new(e.size.0.x, e.size.0.y, e.size.1.x, e.size.1.y),
+            color_start: color(e.color.0),
+            color_end: color(e.color.1),
+            uv: Vec4::new(e.uv.min.x, e.uv.min.y, e.uv.max.x, e.uv.max.y),
+        }
+    }
+}
+
+/// `FrameParams` in `gpu_particles.wgsl`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, bytemuck::Pod, bytemuck::Zeroable)]
+struct FrameParams {
+    dt: f32,
+    n_spawn: u32,
+    src: u32,
+    seed: u32,
+    capacity: u32,
+    _pad: [u32; 3],
+}
+
+/// A particle system that is simulated completely on the gpu, for effects with hundreds of thousands of
+/// particles like rain, sparks or dust.
+///
+/// Unlike a [`super::ParticleSystem`], no particles are uploaded per frame, only the [`GpuEmitter`] when it
+/// changes. A [`GpuParticleSimulator`] moves the particles in a compute pass, between two storage buffers
+/// that swap every frame, and writes the number of alive particles into the arguments of an indirect draw:
+///
+/// ```rust,ignore
+/// let mut rain = GpuParticleSystem::new(transform, emitter, 200_000, &device);
+/// // every frame:
+/// rain.update(&time);
+/// rain.prepare(&queue);
+/// simulator.simulate(&mut encoder, &rain);
+/// // then in the render pass:
+/// particle_renderer.render_gpu(&mut pass, &camera_gr, &rain);
+/// ```
+///
+/// The number of alive particles is only known on the gpu, so these systems can't be sorted by a
+/// [`super::ParticleSorter`]. Use additive blending or the cutout mode for them.
+#[derive(Debug)]
+pub struct GpuParticleSystem {
+    pub transform: Transform,
+    pub emitter: GpuEmitter,
+    pub texture: Option<BindableTextureRef>,
+    /// the emitter that is in the uniform buffer right now.
+    uploaded_emitter: Option<GpuEmitter>,
+    emitting: bool,
+    /// seconds since the emitter stopped, all particles are dead once it is over the max lifetime.
+    stopped_for: f32,
+    /// fraction of a particle that was not spawned yet, carried over to the next frame.
+    spawn_carry: f32,
+    burst: u32,
+    frame: FrameParams,
+    capacity: u32,
+    upload_bytes: u64,
+    emitter_buffer: wgpu::Buffer,
+    counters: wgpu::Buffer,
+    draw_args: wgpu::Buffer,
+    /// [`super::RawParticle`]s written by the simulation, the instance buffer of the draw.
+    raw_particles: wgpu::Buffer,
+    /// bind group `i` reads the particle buffer `i` and writes the other one.
+    bind_groups: [wgpu::BindGroup; 2],
+}
+
+impl GpuParticleSystem {
+    pub fn new(
+        transform: Transform,
+        emitter: GpuEmitter,
+        max_particles: u32,
+        device: &wgpu::Device,
+    ) -> Self {
+        let capacity = max_particles.max(1);
+        let buffer = |label, size, usage| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size,
+                usage,
+                mapped_at_creation: false,
+            })
+        };
+        let emitter_buffer = buffer(
+            "gpu particle emitter",
+            std::mem::size_of::<EmitterRaw>() as u64,
+            wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        );
+        let particles = [(); 2].map(|_| {
+            buffer(
+                "gpu particles",
+                capacity as u64 * GPU_PARTICLE_SIZE,
+                wgpu::BufferUsages::STORAGE,
+            )
+        });
+        // alive particles in the two particle buffers, zero initialized like all buffers.
+        let counters = buffer(
+            "gpu particle counters",
+            8,
+            wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::COPY_DST,
+        );
+        let draw_args = buffer(
+            "gpu particle draw args",
+            std::mem::size_of::<wgpu::util::DrawIndirectArgs>() as u64,
+            wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::INDIRECT,
+        );
+        let raw_particles = buffer(
+            "gpu raw particles",
+            particle_buffer_size(capacity as usize),
+            wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX,
+        );
+        let bind_groups = [0, 1].map(|src| {
+            let entries = [
+                emitter_buffer.as_entire_binding(),
+                particles[src].as_entire_binding(),
+                particles[1 - src].as_entire_binding(),
+                raw_particles.as_entire_binding(),
+                counters.as_entire_binding(),
+                draw_args.as_entire_binding(),
+            ];
+            let entries: Vec<wgpu::BindGroupEntry> = entries
+                .into_iter()
+                .enumerate()
+                .map(|(binding, resource)| wgpu::BindGroupEntry {
+                    binding: binding as u32,
+                    resource,
+                })
+                .collect();
+            device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("gpu particles"),
+                layout: simulation_bind_group_layout_cached(device),
+                entries: &entries,
+            })
+        });
+
+        GpuParticleSystem {
+            transform,
+            emitter,
+            texture: None,
+            uploaded_emitter: None,
+            emitting: true,
+            stopped_for: 0.0,
+            spawn_carry: 0.0,
+            burst: 0,
+            frame: FrameParams {
+                capacity,
+                ..Default::default()
+            },
+            capacity,
+            upload_bytes: 0,
+            emitter_buffer,
+            counters,
+            draw_args,
+            raw_particles,
+            bind_groups,
+        }
+    }
+
+    pub fn with_texture(mut self, texture: BindableTextureRef) -> Self {
+        self.texture = Some(texture);
+        self
+    }
+
+    /// Decides how many particles spawn in this frame. Returns true once the emitter is stopped and all
+    /// particles are dead, then the system can be dropped.
+    pub fn update(&mut self, time: &Time) -> bool {
+        let dt = time.delta().as_secs_f32();
+        let mut n_spawn = std::mem::take(&mut self.burst);
+        if self.emitting {
+            n_spawn += spawn_count(&mut self.spawn_carry, self.emitter.spawn_rate, dt);
+        } else {
+            self.stopped_for += dt;
+        }
+        self.frame = FrameParams {
+            dt,
+            n_spawn: n_spawn.min(self.capacity),
+            src: 1 - self.frame.src,
+            seed: self.frame.seed.wrapping_add(1),
+            capacity: self.capacity,
+            _pad: [0; 3],
+        };
+        self.is_finished()
+    }
+
+    /// Spawns `n` particles at once in the next frame, e.g. for an explosion.
+    pub fn burst(&mut self, n: u32) {
+        self.burst += n;
+        self.stopped_for = 0.0;
+    }
+
+    /// Stops or restarts the continuous spawning, the alive particles live on.
+    pub fn set_emitting(&mut self, emitting: bool) {
+        self.emitting = emitting;
+        self.stopped_for = 0.0;
+    }
+
+    pub fn emitting(&self) -> bool {
+        self.emitting
+    }
+
+    /// True if the emitter is stopped for longer than the max lifetime of its particles.
+    pub fn is_finished(&self) -> bool {
+        !self.emitting && self.burst == 0 && self.stopped_for > self.emitter.lifetime.1
+    }
+
+    /// Uploads the emitter if it changed since the last prepare.
+    pub fn prepare(&mut self, queue: &wgpu::Queue) {
+        self.upload_bytes = 0;
+        if self.uploaded_emitter != Some(self.emitter) {
+            let raw = EmitterRaw::from(&self.emitter);
+            queue.write_buffer(&self.emitter_buffer, 0, bytemuck::bytes_of(&raw));
+            self.uploaded_emitter = Some(self.emitter);
+            self.upload_bytes = std::mem::size_of::<EmitterRaw>() as u64;
+        }
+    }
+
+    /// Bytes written by the last [`GpuParticleSystem::prepare`].
+    pub fn upload_bytes(&self) -> u64 {
+        self.upload_bytes
+    }
+
+    /// Max number of alive particles, more are not spawned.
+    pub fn capacity(&self) -> u32 {
+        self.capacity
+    }
+
+    /// The [`super::RawParticle`]s of the alive particles, written by the simulation.
+    pub fn raw_particles(&self) -> &wgpu::Buffer {
+        &self.raw_particles
+    }
+
+    /// The arguments of the indirect draw of the particles, [`wgpu::util::DrawIndirectArgs`].
+    pub fn draw_args(&self) -> &wgpu::Buffer {
+        &self.draw_args
+    }
+}
+
+/// Whole particles to spawn for `rate` particles per second over `dt`, the rest is kept in `carry`.
+fn spawn_count(carry: &mut f32, rate: f32, dt: f32) -> u32 {
+    let n = *carry + rate.max(0.0) * dt;
+    let whole = n.floor();
+    *carry = n - whole;
+    whole as u32
+}
+
+/// Runs the compute passes of [`GpuParticleSystem`]s.
+pub struct GpuParticleSimulator {
+    simulate: ComputeTask,
+    finish: ComputeTask,
+}
+
+impl GpuParticleSimulator {
+    pub fn new(ctx: &GraphicsContext, cache: &mut ShaderCache) -> Self {
+        let mut task = |entry_point, workgroup_size| {
+            ComputeTask::new(
+                &ctx.device,
+                cache,
+                SHADER_SOURCE,
+                entry_point,
+                &[simulation_bind_group_layout_cached(&ctx.device)],
+                std::mem::size_of::<FrameParams>() as u32,
+                uvec3(workgroup_size, 1, 1),
+            )
+        };
+        GpuParticleSimulator {
+            simulate: task("simulate", WORKGROUP_SIZE),
+            finish: task("finish", 1),
+        }
+    }
+
+    /// Records the simulation of one frame, needs to be called once per [`GpuParticleSystem::update`],
+    /// after [`GpuParticleSystem::prepare`].
+    pub fn simulate(&self, encoder: &mut wgpu::CommandEncoder, system: &GpuParticleSystem) {
+        let params = system.frame;
+        let dst = 1 - params.src as u64;
+        encoder.clear_buffer(&system.counters, dst * 4, Some(4));
+
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("gpu particles"),
+            timestamp_writes: None,
+        });
+        let bind_groups = [&system.bind_groups[params.src as usize]];
+        let push_constants = bytemuck::bytes_of(&params);
+        // the alive particles are only known on the gpu, so invocations go up to the capacity.
+        self.simulate.dispatch_with_push_constants(
+            &mut pass,
+            &bind_groups,
+            push_constants,
+            uvec3(system.capacity + params.n_spawn, 1, 1),
+        );
+        self.finish.dispatch_with_push_constants(
+            &mut pass,
+            &bind_groups,
+            push_constants,
+            uvec3(1, 1, 1),
+        );
+    }
+}
+
+impl HotReload for GpuParticleSimulator {
+    fn source(&self) -> ShaderSource {
+        SHADER_SOURCE
+    }
+
+    fn hot_reload(&mut self, shader: &wgpu::ShaderModule, device: &wgpu::Device) {
+        self.simulate.hot_reload(shader, device);
+        self.finish.hot_reload(shader, device);
+    }
+}
+
+fn simulation_bind_group_layout_cached(device: &wgpu::Device) -> &'static BindGroupLayout {
+    static LAYOUT: OnceLock<BindGroupLayout> = OnceLock::new();
+    LAYOUT.get_or_init(|| {
+        let entry = |binding, ty| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let storage = |read_only| wgpu::BufferBindingType::Storage { read_only };
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("gpu particles"),
+            entries: &[
+                entry(0, wgpu::BufferBindingType::Uniform),
+                entry(1, storage(true)),
+                entry(2, storage(false)),
+                entry(3, storage(false)),
+                entry(4, storage(false)),
+                entry(5, storage(false)),
+            ],
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::spawn_count;
+
+    #[test]
+    fn fractional_spawns_carry_over() {
+        let mut carry = 0.0;
+        let spawned: u32 = (0..10)
+            .map(|_| spawn_count(&mut carry, 25.0, 1.0 / 60.0))
+            .sum();
+        // 10 frames at 60 fps are 4.17 particles.
+        assert_eq!(spawned, 4);
+        assert!((carry - 0.1667).abs() < 0.001);
+    }
+}
@@ -0,0 +1,145 @@
+// Simulates the particles of a `GpuParticleSystem`. Alive particles are read from `src`, moved and written
+// compacted to `dst` together with the RawParticle the renderer draws. New particles are spawned after them.
+// `finish` then clamps the new count and writes it into the indirect draw args.
+
+struct Emitter {
+    // xyz: half size of the box particles spawn in.
+    spawn_extent: vec4<f32>,
+    // xyz: start velocity, w: random velocity added in each direction.
+    velocity: vec4<f32>,
+    // xyz: acceleration, w: drag.
+    acceleration: vec4<f32>,
+    // x: min lifetime, y: max lifetime.
+    lifetime: vec4<f32>,
+    // xy: start size, zw: end size.
+    size: vec4<f32>,
+    color_start: vec4<f32>,
+    color_end: vec4<f32>,
+    uv: vec4<f32>,
+}
+
+struct GpuParticle {
+    pos: vec3<f32>,
+    age: f32,
+    vel: vec3<f32>,
+    lifetime: f32,
+}
+
+struct DrawArgs {
+    vertex_count: u32,
+    instance_count: u32,
+    first_vertex: u32,
+    first_instance: u32,
+}
+
+struct FrameParams {
+    dt: f32,
+    n_spawn: u32,
+    // 0 or 1, index of the counter of `src`. The counter of `dst` is the other one.
+    src: u32,
+    seed: u32,
+    capacity: u32,
+}
+var<push_constant> params: FrameParams;
+
+@group(0) @binding(0)
+var<uniform> emitter: Emitter;
+@group(0) @binding(1)
+var<storage, read> src: array<GpuParticle>;
+@group(0) @binding(2)
+var<storage, read_write> dst: array<GpuParticle>;
+@group(0) @binding(3)
+var<storage, read_write> raw_particles: array<f32>;
+@group(0) @binding(4)
+var<storage, read_write> counters: array<atomic<u32>, 2>;
+@group(0) @binding(5)
+var<storage, read_write> draw_args: DrawArgs;
+
+// RawParticle is 15 floats: pos, rotation, size, color, uv and atlas frame.
+const PARTICLE_FLOATS: u32 = 15u;
+
+@compute @workgroup_size(256)
+fn simulate(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    let n_alive = atomicLoad(&counters[params.src]);
+    var p: GpuParticle;
+    if i < n_alive {
+        p = src[i];
+        p.age += params.dt;
+        if p.age >= p.lifetime {
+            return;
+        }
+        let drag = max(1.0 - emitter.acceleration.w * params.dt, 0.0);
+        p.vel = (p.vel + emitter.acceleration.xyz * params.dt) * drag;
+        p.pos += p.vel * params.dt;
+    } else if i < n_alive + params.n_spawn {
+        p = spawn(i - n_alive);
+    } else {
+        return;
+    }
+    let j = atomicAdd(&counters[1u - params.src], 1u);
+    if j >= params.capacity {
+        return;
+    }
+    dst[j] = p;
+    write_raw_particle(j, p);
+}
+
+@compute @workgroup_size(1)
+fn finish() {
+    let n = min(atomicLoad(&counters[1u - params.src]), params.capacity);
+    atomicStore(&counters[1u - params.src], n);
+    draw_args.vertex_count = 4u;
+    draw_args.instance_count = n;
+    draw_args.first_vertex = 0u;
+    draw_args.first_instance = 0u;
+}
+
+fn spawn(k: u32) -> GpuParticle {
+    var state = hash(params.seed ^ hash(k));
+    var p: GpuParticle;
+    p.pos = (random3(&state) * 2.0 - 1.0) * emitter.spawn_extent.xyz;
+    p.vel = emitter.velocity.xyz + (random3(&state) * 2.0 - 1.0) * emitter.velocity.w;
+    p.age = 0.0;
+    p.lifetime = mix(emitter.lifetime.x, emitter.lifetime.y, random(&state));
+    return p;
+}
+
+fn write_raw_particle(j: u32, p: GpuParticle) {
+    let t = min(p.age / max(p.lifetime, 0.0001), 1.0);
+    let size = mix(emitter.size.xy, emitter.size.zw, t);
+    let color = mix(emitter.color_start, emitter.color_end, t);
+    let base = j * PARTICLE_FLOATS;
+    raw_particles[base] = p.pos.x;
+    raw_particles[base + 1u] = p.pos.y;
+    raw_particles[base + 2u] = p.pos.z;
+    raw_particles[base + 3u] = 0.0;
+    raw_particles[base + 4u] = size.x;
+    raw_particles[base + 5u] = size.y;
+    raw_particles[base + 6u] = color.r;
+    raw_particles[base + 7u] = color.g;
+    raw_particles[base + 8u] = color.b;
+    raw_particles[base + 9u] = color.a;
+    raw_particles[base + 10u] = emitter.uv.x;
+    raw_particles[base + 11u] = emitter.uv.y;
+    raw_particles[base + 12u] = emitter.uv.z;
+    raw_particles[base + 13u] = emitter.uv.w;
+    raw_particles[base + 14u] = 0.0;
+}
+
+// pcg hash, see "Hash Functions for GPU Rendering" (Jarzynski, Olano).
+fn hash(x: u32) -> u32 {
+    let state = x * 747796405u + 2891336453u;
+    let word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+// uniform in [0, 1), advances the state.
+fn random(state: ptr<function, u32>) -> f32 {
+    *state = hash(*state);
+    return f32(*state >> 8u) / 16777216.0;
+}
+
+fn random3(state: ptr<function, u32>) -> vec3<f32> {
+    return vec3(random(state), random(state), random(state));
+}
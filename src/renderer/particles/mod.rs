@@ -11,6 +11,18 @@ pub use particle_renderer::ParticleRenderer;
 mod particle_system;
 pub use particle_system::{ParticleSystem, ParticleSystemT};
 
+mod particle_sort;
+pub use particle_sort::ParticleSorter;
+
+mod particle_pool;
+pub use particle_pool::ParticleSystemPool;
+
+mod particle_atlas;
+pub use particle_atlas::{AtlasMode, ParticleAtlas};
+
+mod gpu_particle_system;
+pub use gpu_particle_system::{GpuEmitter, GpuParticleSimulator, GpuParticleSystem};
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct RawParticle {
@@ -19,6 +31,8 @@ pub struct RawParticle {
     pub size: Vec2,
     pub color: Color,
     pub uv: Aabb,
+    /// Frame of the [`ParticleAtlas`] of the system, ignored for systems without one.
+    pub frame: f32,
 }
 
 impl VertexT for RawParticle {
@@ -27,5 +41,6 @@ impl VertexT for RawParticle {
         wgpu::VertexFormat::Float32x2, // scale
         wgpu::VertexFormat::Float32x4, // color
         wgpu::VertexFormat::Float32x4, // uv aabb
+        wgpu::VertexFormat::Float32,   // atlas frame
     ];
 }
@@ -8,6 +8,10 @@ struct PushData {
    col2: vec4<f32>,
    col3: vec4<f32>,
    translation: vec4<f32>,
+   // threshold and alpha to coverage (0.0 or 1.0) of the cutout mode, see `AlphaMode::Cutout`.
+   cutout: vec2<f32>,
+   // columns and rows of the sprite sheet the particles play, 0.0 if there is none.
+   atlas: vec2<f32>,
 }
 var<push_constant> push: PushData;
 
@@ -16,6 +20,7 @@ struct Particle {
    @location(1) size: vec2<f32>,       // scale
    @location(2) color: vec4<f32>,       // color
    @location(3) uv: vec4<f32>,          // uv aabb
+   @location(4) frame: f32,             // frame in the atlas
 }
 
 struct ParticleVertexOutput{
@@ -25,13 +30,37 @@ struct ParticleVertexOutput{
 }
 
 
+// only bound for systems with gpu sorting, the particle buffer read as raw floats and the sorted indices into it.
+@group(2) @binding(0)
+var<storage, read> sorted_particles: array<f32>;
+@group(2) @binding(1)
+var<storage, read> sorted_indices: array<u32>;
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32, particle: Particle) -> ParticleVertexOutput {
+    return particle_vertex(vertex_index, particle);
+}
+
+/// draws the particles in the order of `sorted_indices`, back to front.
+@vertex
+fn vs_sorted(@builtin(vertex_index) vertex_index: u32, @builtin(instance_index) instance_index: u32) -> ParticleVertexOutput {
+    let base = sorted_indices[instance_index] * 15u;
+    var particle: Particle;
+    particle.pos_and_rot = vec4(sorted_particles[base], sorted_particles[base + 1u], sorted_particles[base + 2u], sorted_particles[base + 3u]);
+    particle.size = vec2(sorted_particles[base + 4u], sorted_particles[base + 5u]);
+    particle.color = vec4(sorted_particles[base + 6u], sorted_particles[base + 7u], sorted_particles[base + 8u], sorted_particles[base + 9u]);
+    particle.uv = vec4(sorted_particles[base + 10u], sorted_particles[base + 11u], sorted_particles[base + 12u], sorted_particles[base + 13u]);
+    particle.frame = sorted_particles[base + 14u];
+    return particle_vertex(vertex_index, particle);
+}
+
 /// instead of using billboarding, we have all particles face the same direction.
 /// the rotation is the rotation passed in the push constant transform.
 /// we do not apply rotation and scale to all particles together, so you cannot e.g. rotate all particles together around some point.
-@vertex
-fn vs_main(@builtin(vertex_index) vertex_index: u32, particle: Particle) -> ParticleVertexOutput {
+fn particle_vertex(vertex_index: u32, particle: Particle) -> ParticleVertexOutput {
     let u_uv = unit_uv_from_idx(vertex_index); // in unit space
-    let uv: vec2<f32> = ((vec2(1.0) - u_uv) * particle.uv.zw) + (u_uv * particle.uv.xy); // mapped to the actual uv coords in the texture
+    let uv_aabb = atlas_frame_uv(particle.uv, particle.frame);
+    let uv: vec2<f32> = ((vec2(1.0) - u_uv) * uv_aabb.zw) + (u_uv * uv_aabb.xy); // mapped to the actual uv coords in the texture
     let size = particle.size;
     let size_half: vec2<f32> = particle.size / 2.0;
     
@@ -67,14 +96,33 @@ fn vs_main(@builtin(vertex_index) vertex_index: u32, particle: Particle) -> Part
     return out;
 }
 
+/// the part of the uv aabb that shows the frame of the atlas, like `ParticleAtlas::frame_uv`.
+fn atlas_frame_uv(uv: vec4<f32>, frame: f32) -> vec4<f32> {
+    if push.atlas.x == 0.0 {
+        return uv;
+    }
+    let n_frames = push.atlas.x * push.atlas.y;
+    let f = floor(frame) - floor(frame / n_frames) * n_frames;
+    let cell = vec2(f - floor(f / push.atlas.x) * push.atlas.x, floor(f / push.atlas.x));
+    let size = (uv.zw - uv.xy) / push.atlas;
+    let corner = uv.xy + cell * size;
+    return vec4(corner, corner + size);
+}
+
 @fragment
 fn fs_main(in: ParticleVertexOutput) -> @location(0) vec4<f32> {
     // return vec4(1.0,0.0,0.0,1.0);
+    return particle_color(in);
+}
+
+@fragment
+fn fs_cutout(in: ParticleVertexOutput) -> @location(0) vec4<f32> {
+    return alpha_cutout(particle_color(in), push.cutout.x, push.cutout.y > 0.5);
+}
 
-    // todo! use in.uv
+fn particle_color(in: ParticleVertexOutput) -> vec4<f32> {
     let image_color = textureSample(t_diffuse, s_diffuse, in.uv);
-    let color = in.color * image_color;
-    return color;
+    return in.color * image_color;
 }
 
 fn unit_uv_from_idx(idx: u32) ->  vec2<f32> {
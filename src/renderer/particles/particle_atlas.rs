@@ -0,0 +1,102 @@
+use glam::{vec2, Vec2};
+
+use crate::Aabb;
+
+/// A sprite sheet of `cols` x `rows` equally sized frames that particles play as a flipbook, e.g. for fire
+/// or smoke. Frames go row by row, starting at the top left.
+///
+/// Returned from [`super::ParticleSystemT::atlas`], the vertex shader then shows the frame
+/// [`super::RawParticle::frame`] of the sheet that covers the `uv` of the particle. Systems without an atlas
+/// can still animate on the cpu, by writing [`ParticleAtlas::uv`] into the `uv` of their particles:
+///
+/// ```rust,ignore
+/// let atlas = ParticleAtlas::new(8, 8, 30.0, AtlasMode::Once);
+/// // in fill_raw_particles, if `atlas()` returns the atlas:
+/// raw.push(RawParticle { uv: Aabb::UNIT, frame: atlas.frame(p.age) as f32, ..raw_particle(p) });
+/// // or on the cpu, if it returns None:
+/// raw.push(RawParticle { uv: atlas.uv(p.age, Aabb::UNIT), ..raw_particle(p) });
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParticleAtlas {
+    pub cols: u32,
+    pub rows: u32,
+    /// Frames per second of particle age.
+    pub fps: f32,
+    pub mode: AtlasMode,
+}
+
+/// What a [`ParticleAtlas`] shows after its last frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AtlasMode {
+    /// Starts over with the first frame.
+    #[default]
+    Loop,
+    /// Stays on the last frame.
+    Once,
+}
+
+impl ParticleAtlas {
+    pub fn new(cols: u32, rows: u32, fps: f32, mode: AtlasMode) -> Self {
+        ParticleAtlas {
+            cols,
+            rows,
+            fps,
+            mode,
+        }
+    }
+
+    pub fn n_frames(&self) -> u32 {
+        (self.cols * self.rows).max(1)
+    }
+
+    /// The frame shown by a particle that is `age` seconds old.
+    pub fn frame(&self, age: f32) -> u32 {
+        let frame = (age.max(0.0) * self.fps) as u32;
+        match self.mode {
+            AtlasMode::Loop => frame % self.n_frames(),
+            AtlasMode::Once => frame.min(self.n_frames() - 1),
+        }
+    }
+
+    /// The part of `region` that shows the `frame`, `region` is where the sheet is in the texture.
+    pub fn frame_uv(&self, frame: u32, region: Aabb) -> Aabb {
+        let grid = vec2(self.cols.max(1) as f32, self.rows.max(1) as f32);
+        let frame = frame % self.n_frames();
+        let cell = vec2(
+            (frame % self.cols.max(1)) as f32,
+            (frame / self.cols.max(1)) as f32,
+        );
+        let size: Vec2 = (region.max - region.min) / grid;
+        let min = region.min + cell * size;
+        Aabb::new(min, min + size)
+    }
+
+    /// [`ParticleAtlas::frame_uv`] for a particle that is `age` seconds old.
+    pub fn uv(&self, age: f32, region: Aabb) -> Aabb {
+        self.frame_uv(self.frame(age), region)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::vec2;
+
+    use super::{AtlasMode, ParticleAtlas};
+    use crate::Aabb;
+
+    #[test]
+    fn frames_follow_the_age() {
+        let looping = ParticleAtlas::new(4, 2, 10.0, AtlasMode::Loop);
+        assert_eq!(looping.frame(0.55), 5);
+        assert_eq!(looping.frame(0.85), 0);
+        let once = ParticleAtlas {
+            mode: AtlasMode::Once,
+            ..looping
+        };
+        assert_eq!(once.frame(0.85), 7);
+        assert_eq!(
+            once.uv(0.55, Aabb::UNIT),
+            Aabb::new(vec2(0.25, 0.5), vec2(0.5, 1.0))
+        );
+    }
+}
@@ -0,0 +1,78 @@
+use crate::Transform;
+
+use super::{particle_system::create_particle_buffer, ParticleSystem, ParticleSystemT};
+
+/// Keeps particle buffers of finished systems around, so spawning a new system does not create a buffer.
+///
+/// Creating buffers mid-game (e.g. for an explosion in a fight) can cause a hitch. Preallocate the
+/// buffers while loading, spawn systems from the pool and give finished systems back:
+///
+/// ```rust,ignore
+/// let mut pool = ParticleSystemPool::new();
+/// pool.preallocate(&device, 2000, 16);
+/// // later:
+/// let mut fire = pool.spawn(transform, Box::new(Fire::new()), &device);
+/// fire.warm_up(3.0);
+/// // once fire.update(&time) returns true:
+/// pool.release(fire);
+/// ```
+#[derive(Debug, Default)]
+pub struct ParticleSystemPool {
+    /// sorted by capacity, so the smallest fitting buffer is found first.
+    free: Vec<(wgpu::Buffer, usize)>,
+}
+
+impl ParticleSystemPool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates `count` buffers with space for `max_particles` each.
+    pub fn preallocate(&mut self, device: &wgpu::Device, max_particles: usize, count: usize) {
+        for _ in 0..count {
+            self.insert(create_particle_buffer(device, max_particles), max_particles);
+        }
+    }
+
+    /// Creates a particle system with the smallest free buffer that fits its max particles.
+    /// Only creates a new buffer if there is no such buffer.
+    pub fn spawn(
+        &mut self,
+        transform: Transform,
+        system: Box<dyn ParticleSystemT>,
+        device: &wgpu::Device,
+    ) -> ParticleSystem {
+        let max_particles = system.max_particles_number();
+        let fitting = self
+            .free
+            .iter()
+            .position(|(_, capacity)| *capacity >= max_particles);
+        let (buffer, capacity) = match fitting {
+            Some(i) => self.free.remove(i),
+            None => (create_particle_buffer(device, max_particles), max_particles),
+        };
+        ParticleSystem::with_buffer(transform, system, buffer, capacity)
+    }
+
+    /// Keeps the buffer of the system for the next [`ParticleSystemPool::spawn`].
+    /// Gpu sorting buffers of the system are dropped.
+    pub fn release(&mut self, system: ParticleSystem) {
+        let (buffer, capacity) = system.into_buffer();
+        self.insert(buffer, capacity);
+    }
+
+    /// Number of buffers that are ready for reuse.
+    pub fn n_free(&self) -> usize {
+        self.free.len()
+    }
+
+    /// Drops all free buffers.
+    pub fn clear(&mut self) {
+        self.free.clear();
+    }
+
+    fn insert(&mut self, buffer: wgpu::Buffer, capacity: usize) {
+        let i = self.free.partition_point(|(_, c)| *c <= capacity);
+        self.free.insert(i, (buffer, capacity));
+    }
+}
@@ -1,21 +1,42 @@
-use std::sync::Arc;
+use std::{cell::Cell, sync::Arc};
 
 use crate::{
-    make_shader_source, rgba_bind_group_layout_cached, texture::white_px_texture_cached,
-    Camera3dGR, GraphicsContext, HotReload, RenderFormat, ShaderCache, ShaderSource, ToRaw,
-    TransformRaw, VertsLayout,
+    make_shader_source, rgba_bind_group_layout_cached, texture::white_px_texture_cached, AlphaMode,
+    Camera3dGR, GraphicsContext, HotReload, RenderFormat, RenderStats, ShaderCache, ShaderSource,
+    ToRaw, TransformRaw, VertsLayout,
 };
 use wgpu::ShaderStages;
 
-use super::{ParticleSystem, RawParticle};
+use super::{
+    particle_sort::sorted_particles_bind_group_layout_cached, GpuParticleSystem, ParticleAtlas,
+    ParticleSystem, RawParticle,
+};
+
+const SHADER_SOURCE: ShaderSource =
+    make_shader_source!("../uniforms.wgsl", "../cutout.wgsl", "particle.wgsl");
+
+/// `PushData` in `particle.wgsl`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Zeroable)]
+struct PushData {
+    transform: TransformRaw,
+    cutout: [f32; 2],
+    /// columns and rows of the [`ParticleAtlas`], 0 without one.
+    atlas: [f32; 2],
+}
 
-const SHADER_SOURCE: ShaderSource = make_shader_source!("../uniforms.wgsl", "particle.wgsl");
+unsafe impl bytemuck::Pod for PushData {}
 
 pub struct ParticleRenderer {
     pipeline: wgpu::RenderPipeline,
+    /// for systems with gpu sorting, reads the particles in sorted order from storage buffers.
+    sorted_pipeline: wgpu::RenderPipeline,
     render_format: RenderFormat,
+    alpha_mode: AlphaMode,
     ctx: GraphicsContext,
     camera_layout: Arc<wgpu::BindGroupLayout>,
+    /// counted in `render`, which only gets `&self` to keep the borrows of the render pass.
+    stats: Cell<RenderStats>,
 }
 
 impl ParticleRenderer {
@@ -27,15 +48,72 @@ impl ParticleRenderer {
     ) -> ParticleRenderer {
         let ctx = ctx.clone();
         let shader = cache.register(SHADER_SOURCE, &ctx.device);
-        let pipeline = create_pipeline(&shader, &ctx, camera.bind_group_layout(), render_format);
+        let alpha_mode = AlphaMode::Blend;
+        let pipeline = create_pipeline(
+            &shader,
+            &ctx,
+            camera.bind_group_layout(),
+            render_format,
+            alpha_mode,
+            false,
+        );
+        let sorted_pipeline = create_pipeline(
+            &shader,
+            &ctx,
+            camera.bind_group_layout(),
+            render_format,
+            alpha_mode,
+            true,
+        );
         let camera_layout = camera.bind_group_layout().clone();
 
         ParticleRenderer {
             pipeline,
+            sorted_pipeline,
             render_format,
+            alpha_mode,
             ctx,
             camera_layout,
+            stats: Cell::new(RenderStats::default()),
+        }
+    }
+
+    /// Switches all particles between alpha blending and the cutout mode, which writes depth, see [`AlphaMode`].
+    pub fn set_alpha_mode(&mut self, alpha_mode: AlphaMode, cache: &mut ShaderCache) {
+        if alpha_mode == self.alpha_mode {
+            return;
         }
+        self.alpha_mode = alpha_mode;
+        let shader = cache.register(SHADER_SOURCE, &self.ctx.device);
+        self.create_pipelines(&shader);
+    }
+
+    pub fn alpha_mode(&self) -> AlphaMode {
+        self.alpha_mode
+    }
+
+    /// Stats of the systems rendered since the last call, call it once per frame after rendering.
+    pub fn take_stats(&self) -> RenderStats {
+        self.stats.take()
+    }
+
+    fn create_pipelines(&mut self, shader: &wgpu::ShaderModule) {
+        self.pipeline = create_pipeline(
+            shader,
+            &self.ctx,
+            &self.camera_layout,
+            self.render_format,
+            self.alpha_mode,
+            false,
+        );
+        self.sorted_pipeline = create_pipeline(
+            shader,
+            &self.ctx,
+            &self.camera_layout,
+            self.render_format,
+            self.alpha_mode,
+            true,
+        );
     }
 
     pub fn render<'a>(
@@ -48,16 +126,79 @@ impl ParticleRenderer {
             .texture()
             .unwrap_or_else(|| white_px_texture_cached(&self.ctx));
 
-        pass.set_pipeline(&self.pipeline);
+        if let Some(sort_buffers) = particle_system.sort_buffers() {
+            pass.set_pipeline(&self.sorted_pipeline);
+            pass.set_bind_group(2, &sort_buffers.render_bind_group, &[]);
+        } else {
+            pass.set_pipeline(&self.pipeline);
+            pass.set_vertex_buffer(0, particle_system.buffer().slice(..));
+        }
         pass.set_bind_group(0, camera.bind_group(), &[]);
         pass.set_bind_group(1, &texture.bind_group, &[]);
+        let push = PushData {
+            transform: particle_system.transform.to_raw(),
+            cutout: self.alpha_mode.cutout_params(self.render_format),
+            atlas: atlas_params(particle_system.atlas()),
+        };
         pass.set_push_constants(
-            ShaderStages::VERTEX,
+            ShaderStages::VERTEX | ShaderStages::FRAGMENT,
             0,
-            bytemuck::cast_slice(&[particle_system.transform.to_raw()]),
+            bytemuck::bytes_of(&push),
         );
-        pass.set_vertex_buffer(0, particle_system.buffer().slice(..));
         pass.draw(0..4, 0..particle_system.n_particles() as u32);
+
+        let mut stats = self.stats.get();
+        stats += RenderStats {
+            draw_calls: 1,
+            instances: particle_system.n_particles() as u32,
+            vertices: 4 * particle_system.n_particles() as u32,
+            upload_bytes: particle_system.upload_bytes(),
+            prepare_time: Default::default(),
+        };
+        self.stats.set(stats);
+    }
+
+    /// Draws the particles of a [`GpuParticleSystem`] with an indirect draw, after its simulation was recorded.
+    /// Its particles are not counted in the stats, only the gpu knows how many are alive.
+    pub fn render_gpu<'a>(
+        &'a self,
+        pass: &mut wgpu::RenderPass<'a>,
+        camera: &'a Camera3dGR,
+        particle_system: &'a GpuParticleSystem,
+    ) {
+        let texture = particle_system
+            .texture
+            .unwrap_or_else(|| white_px_texture_cached(&self.ctx));
+        pass.set_pipeline(&self.pipeline);
+        pass.set_vertex_buffer(0, particle_system.raw_particles().slice(..));
+        pass.set_bind_group(0, camera.bind_group(), &[]);
+        pass.set_bind_group(1, &texture.bind_group, &[]);
+        let push = PushData {
+            transform: particle_system.transform.to_raw(),
+            cutout: self.alpha_mode.cutout_params(self.render_format),
+            atlas: atlas_params(None),
+        };
+        pass.set_push_constants(
+            ShaderStages::VERTEX | ShaderStages::FRAGMENT,
+            0,
+            bytemuck::bytes_of(&push),
+        );
+        pass.draw_indirect(particle_system.draw_args(), 0);
+
+        let mut stats = self.stats.get();
+        stats += RenderStats {
+            draw_calls: 1,
+            upload_bytes: particle_system.upload_bytes(),
+            ..Default::default()
+        };
+        self.stats.set(stats);
+    }
+}
+
+fn atlas_params(atlas: Option<ParticleAtlas>) -> [f32; 2] {
+    match atlas {
+        Some(atlas) => [atlas.cols.max(1) as f32, atlas.rows.max(1) as f32],
+        None => [0.0; 2],
     }
 }
 
@@ -66,19 +207,42 @@ fn create_pipeline(
     ctx: &GraphicsContext,
     camera_layout: &wgpu::BindGroupLayout,
     render_format: RenderFormat,
+    alpha_mode: AlphaMode,
+    sorted: bool,
 ) -> wgpu::RenderPipeline {
+    let texture_layout = rgba_bind_group_layout_cached(&ctx.device);
+    let bind_group_layouts: &[&wgpu::BindGroupLayout] = if sorted {
+        &[
+            camera_layout,
+            texture_layout,
+            sorted_particles_bind_group_layout_cached(&ctx.device),
+        ]
+    } else {
+        &[camera_layout, texture_layout]
+    };
     let layout = ctx
         .device
         .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("particles pipeline"),
-            bind_group_layouts: &[camera_layout, rgba_bind_group_layout_cached(&ctx.device)],
+            bind_group_layouts,
             push_constant_ranges: &[wgpu::PushConstantRange {
-                stages: wgpu::ShaderStages::VERTEX,
-                range: 0..std::mem::size_of::<TransformRaw>() as u32,
+                stages: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                range: 0..std::mem::size_of::<PushData>() as u32,
             }],
         });
 
-    let vertexes = VertsLayout::new().instance::<RawParticle>();
+    // sorted particles are read from a storage buffer instead.
+    let vertexes = if sorted {
+        VertsLayout::new()
+    } else {
+        VertsLayout::new().instance::<RawParticle>()
+    };
+    let entry_point = if sorted { "vs_sorted" } else { "vs_main" };
+    let fs_entry_point = if alpha_mode.is_cutout() {
+        "fs_cutout"
+    } else {
+        "fs_main"
+    };
 
     ctx.device
         .create_render_pipeline(&wgpu::RenderPipelineDescriptor {
@@ -86,15 +250,15 @@ fn create_pipeline(
             layout: Some(&layout),
             vertex: wgpu::VertexState {
                 module: shader,
-                entry_point: "vs_main",
+                entry_point,
                 buffers: vertexes.layout(),
             },
             fragment: Some(wgpu::FragmentState {
                 module: shader,
-                entry_point: "fs_main",
+                entry_point: fs_entry_point,
                 targets: &[Some(wgpu::ColorTargetState {
                     format: render_format.color,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    blend: alpha_mode.blend_state(),
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
             }),
@@ -109,13 +273,14 @@ fn create_pipeline(
             },
             depth_stencil: render_format.depth.map(|format| wgpu::DepthStencilState {
                 format,
-                depth_write_enabled: false,
+                depth_write_enabled: alpha_mode.is_cutout(),
                 depth_compare: wgpu::CompareFunction::Less,
                 stencil: wgpu::StencilState::default(),
                 bias: wgpu::DepthBiasState::default(),
             }),
             multisample: wgpu::MultisampleState {
                 count: render_format.msaa_sample_count,
+                alpha_to_coverage_enabled: alpha_mode.alpha_to_coverage(render_format),
                 ..Default::default()
             },
             multiview: None,
@@ -128,6 +293,6 @@ impl HotReload for ParticleRenderer {
     }
 
     fn hot_reload(&mut self, shader: &wgpu::ShaderModule, _device: &wgpu::Device) {
-        self.pipeline = create_pipeline(shader, &self.ctx, &self.camera_layout, self.render_format);
+        self.create_pipelines(shader);
     }
 }
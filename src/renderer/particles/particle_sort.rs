@@ -0,0 +1,242 @@
+use std::sync::OnceLock;
+
+use glam::{uvec3, Vec3, Vec4};
+use wgpu::BindGroupLayout;
+
+use crate::{
+    make_shader_source, ComputeTask, GraphicsContext, HotReload, ShaderCache, ShaderSource,
+};
+
+use super::{ParticleSystem, RawParticle};
+
+const SHADER_SOURCE: ShaderSource = make_shader_source!("particle_sort.wgsl");
+
+const WORKGROUP_SIZE: u32 = 256;
+
+/// Sorts the particles of systems with gpu sorting enabled back to front, by their distance to the camera.
+///
+/// Alpha blended particles only look right if far particles are drawn before near ones. A bitonic sort
+/// needs about `log2(n)^2 / 2` compute passes over all particles, so it is opt-in per system with
+/// [`ParticleSystem::set_gpu_sorting`]. Additive particles don't need it.
+///
+/// ```rust,ignore
+/// system.set_gpu_sorting(true, &device);
+/// // every frame, after system.prepare(&queue):
+/// particle_sorter.sort(&mut encoder, camera.transform.pos, &system);
+/// // then in the render pass, the renderer picks the sorted order up:
+/// particle_renderer.render(&mut pass, &camera_gr, &system);
+/// ```
+pub struct ParticleSorter {
+    keys: ComputeTask,
+    bitonic_step: ComputeTask,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct SortParams {
+    translation: Vec4,
+    camera_pos: Vec4,
+    n_particles: u32,
+    n_padded: u32,
+    k: u32,
+    j: u32,
+}
+
+impl ParticleSorter {
+    pub fn new(ctx: &GraphicsContext, cache: &mut ShaderCache) -> Self {
+        let mut task = |entry_point| {
+            ComputeTask::new(
+                &ctx.device,
+                cache,
+                SHADER_SOURCE,
+                entry_point,
+                &[sort_bind_group_layout_cached(&ctx.device)],
+                std::mem::size_of::<SortParams>() as u32,
+                uvec3(WORKGROUP_SIZE, 1, 1),
+            )
+        };
+        ParticleSorter {
+            keys: task("compute_keys"),
+            bitonic_step: task("bitonic_step"),
+        }
+    }
+
+    /// Records the compute passes that sort the particles of the system. Does nothing if gpu sorting
+    /// is not enabled for it. Needs to be called after [`ParticleSystem::prepare`] in the same frame.
+    pub fn sort(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        camera_pos: Vec3,
+        particle_system: &ParticleSystem,
+    ) {
+        let Some(buffers) = particle_system.sort_buffers() else {
+            return;
+        };
+        let n_particles = particle_system.n_particles() as u32;
+        if n_particles == 0 {
+            return;
+        }
+        let n_padded = n_particles.next_power_of_two();
+        let mut params = SortParams {
+            translation: particle_system.transform.position.extend(0.0),
+            camera_pos: camera_pos.extend(0.0),
+            n_particles,
+            n_padded,
+            k: 0,
+            j: 0,
+        };
+        let bind_groups = [&buffers.compute_bind_group];
+
+        let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("particle sort"),
+            timestamp_writes: None,
+        });
+        let invocations = uvec3(n_padded, 1, 1);
+        self.keys.dispatch_with_push_constants(
+            &mut pass,
+            &bind_groups,
+            bytemuck::bytes_of(&params),
+            invocations,
+        );
+        let mut k = 2;
+        while k <= n_padded {
+            let mut j = k / 2;
+            while j > 0 {
+                params.k = k;
+                params.j = j;
+                self.bitonic_step.dispatch_with_push_constants(
+                    &mut pass,
+                    &bind_groups,
+                    bytemuck::bytes_of(&params),
+                    invocations,
+                );
+                j /= 2;
+            }
+            k *= 2;
+        }
+    }
+}
+
+impl HotReload for ParticleSorter {
+    fn source(&self) -> ShaderSource {
+        SHADER_SOURCE
+    }
+
+    fn hot_reload(&mut self, shader: &wgpu::ShaderModule, device: &wgpu::Device) {
+        self.keys.hot_reload(shader, device);
+        self.bitonic_step.hot_reload(shader, device);
+    }
+}
+
+/// Keys and indices of a [`ParticleSystem`] with gpu sorting, sized for its max number of particles.
+pub(super) struct ParticleSortBuffers {
+    pub compute_bind_group: wgpu::BindGroup,
+    /// particles and sorted indices, group 2 of the sorted particle render pipeline.
+    pub render_bind_group: wgpu::BindGroup,
+}
+
+impl ParticleSortBuffers {
+    pub fn new(device: &wgpu::Device, particles: &wgpu::Buffer, max_particles: usize) -> Self {
+        let capacity = max_particles.max(1).next_power_of_two() as u64;
+        let keys = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particle sort keys"),
+            size: capacity * std::mem::size_of::<f32>() as u64,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let indices = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particle sort indices"),
+            size: capacity * std::mem::size_of::<u32>() as u64,
+            usage: wgpu::BufferUsages::STORAGE,
+            mapped_at_creation: false,
+        });
+        let compute_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("particle sort"),
+            layout: sort_bind_group_layout_cached(device),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: particles.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: keys.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: indices.as_entire_binding(),
+                },
+            ],
+        });
+        let render_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("sorted particles"),
+            layout: sorted_particles_bind_group_layout_cached(device),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: particles.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: indices.as_entire_binding(),
+                },
+            ],
+        });
+        ParticleSortBuffers {
+            compute_bind_group,
+            render_bind_group,
+        }
+    }
+}
+
+/// Size of the particle buffer of a system, it is bound as a storage buffer if the system uses gpu sorting.
+pub(super) fn particle_buffer_size(max_particles: usize) -> u64 {
+    (max_particles.max(1) * std::mem::size_of::<RawParticle>()) as u64
+}
+
+fn storage_entry(
+    binding: u32,
+    visibility: wgpu::ShaderStages,
+    read_only: bool,
+) -> wgpu::BindGroupLayoutEntry {
+    wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility,
+        ty: wgpu::BindingType::Buffer {
+            ty: wgpu::BufferBindingType::Storage { read_only },
+            has_dynamic_offset: false,
+            min_binding_size: None,
+        },
+        count: None,
+    }
+}
+
+fn sort_bind_group_layout_cached(device: &wgpu::Device) -> &'static BindGroupLayout {
+    static LAYOUT: OnceLock<BindGroupLayout> = OnceLock::new();
+    LAYOUT.get_or_init(|| {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("particle sort"),
+            entries: &[
+                storage_entry(0, wgpu::ShaderStages::COMPUTE, true),
+                storage_entry(1, wgpu::ShaderStages::COMPUTE, false),
+                storage_entry(2, wgpu::ShaderStages::COMPUTE, false),
+            ],
+        })
+    })
+}
+
+/// Layout of group 2 in the sorted particle render pipeline.
+pub(super) fn sorted_particles_bind_group_layout_cached(
+    device: &wgpu::Device,
+) -> &'static BindGroupLayout {
+    static LAYOUT: OnceLock<BindGroupLayout> = OnceLock::new();
+    LAYOUT.get_or_init(|| {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("sorted particles"),
+            entries: &[
+                storage_entry(0, wgpu::ShaderStages::VERTEX, true),
+                storage_entry(1, wgpu::ShaderStages::VERTEX, true),
+            ],
+        })
+    })
+}
@@ -0,0 +1,63 @@
+// Sorts the particles of a system back to front, so that alpha blending is correct.
+// `compute_keys` writes the squared camera distance of each particle, then `bitonic_step` is dispatched
+// once for every (k, j) pair of a bitonic sort over the indices.
+
+@group(0) @binding(0)
+var<storage, read> particles: array<f32>;
+@group(0) @binding(1)
+var<storage, read_write> keys: array<f32>;
+@group(0) @binding(2)
+var<storage, read_write> indices: array<u32>;
+
+struct SortParams {
+    translation: vec4<f32>,
+    camera_pos: vec4<f32>,
+    n_particles: u32,
+    // n_particles rounded up to a power of two, the bitonic sort only works on those.
+    n_padded: u32,
+    k: u32,
+    j: u32,
+}
+var<push_constant> params: SortParams;
+
+// RawParticle is 15 floats, the first 3 are the position.
+const PARTICLE_FLOATS: u32 = 15u;
+
+@compute @workgroup_size(256)
+fn compute_keys(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if i >= params.n_padded {
+        return;
+    }
+    indices[i] = i;
+    if i < params.n_particles {
+        let base = i * PARTICLE_FLOATS;
+        let pos = vec3(particles[base], particles[base + 1u], particles[base + 2u]) + params.translation.xyz;
+        let diff = pos - params.camera_pos.xyz;
+        keys[i] = dot(diff, diff);
+    } else {
+        // padding ends up behind the last particle, where it is never drawn.
+        keys[i] = -1.0;
+    }
+}
+
+@compute @workgroup_size(256)
+fn bitonic_step(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    let l = i ^ params.j;
+    if i >= params.n_padded || l <= i {
+        return;
+    }
+    let key_i = keys[i];
+    let key_l = keys[l];
+    // blocks alternate between descending and ascending, the final merge is descending: far particles first.
+    let descending = (i & params.k) == 0u;
+    let swap = select(key_i > key_l, key_i < key_l, descending);
+    if swap {
+        keys[i] = key_l;
+        keys[l] = key_i;
+        let index_i = indices[i];
+        indices[i] = indices[l];
+        indices[l] = index_i;
+    }
+}
@@ -1,8 +1,11 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, time::Duration};
 
 use crate::{BindableTexture, Time, Transform};
 
-use super::RawParticle;
+use super::{
+    particle_sort::{particle_buffer_size, ParticleSortBuffers},
+    ParticleAtlas, RawParticle,
+};
 
 pub trait ParticleSystemT {
     /// Returns true if the system is finished and should be deallocated.
@@ -18,6 +21,12 @@ pub trait ParticleSystemT {
     fn texture(&self) -> Option<&BindableTexture> {
         None
     }
+
+    /// The sprite sheet that the `frame` of the raw particles picks from, see [`ParticleAtlas`].
+    /// Should stay the same throughout the lifetime of the system, like the texture.
+    fn atlas(&self) -> Option<ParticleAtlas> {
+        None
+    }
 }
 
 pub struct ParticleSystem {
@@ -25,9 +34,14 @@ pub struct ParticleSystem {
     pub transform: Transform,
     raw_particles: Vec<RawParticle>,
     buffer: wgpu::Buffer,
+    /// number of particles that fit into the buffer, at least `max_particles`, more if the buffer came from a pool.
+    buffer_capacity: usize,
     max_particles: usize,
     system: Box<dyn ParticleSystemT>,
     changed_since_last_prepare: bool,
+    /// bytes written in the last prepare, 0 if nothing changed.
+    upload_bytes: u64,
+    sort_buffers: Option<ParticleSortBuffers>,
 }
 
 impl Debug for ParticleSystem {
@@ -38,6 +52,7 @@ impl Debug for ParticleSystem {
             .field("raw_particles", &self.raw_particles)
             .field("buffer", &self.buffer)
             .field("max_particles", &self.max_particles)
+            .field("gpu_sorting", &self.gpu_sorting())
             .finish()
     }
 }
@@ -45,47 +60,97 @@ impl Debug for ParticleSystem {
 impl ParticleSystem {
     pub fn new(
         transform: Transform,
-        mut system: Box<dyn ParticleSystemT>,
+        system: Box<dyn ParticleSystemT>,
         device: &wgpu::Device,
     ) -> Self {
+        let max_number = system.max_particles_number();
+        let buffer = create_particle_buffer(device, max_number);
+        Self::with_buffer(transform, system, buffer, max_number)
+    }
+
+    /// Uses an existing buffer with space for `buffer_capacity` particles, e.g. from a [`super::ParticleSystemPool`].
+    pub(super) fn with_buffer(
+        transform: Transform,
+        mut system: Box<dyn ParticleSystemT>,
+        buffer: wgpu::Buffer,
+        buffer_capacity: usize,
+    ) -> Self {
+        let max_number = system.max_particles_number();
+        assert!(
+            max_number <= buffer_capacity,
+            "particle buffer for {buffer_capacity} particles is too small for {max_number}"
+        );
         let mut raw_particles: Vec<RawParticle> = vec![];
         system.fill_raw_particles(&mut raw_particles);
-        let max_number = system.max_particles_number();
-        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
-            label: None,
-            size: (max_number * std::mem::size_of::<RawParticle>()) as u64,
-            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::VERTEX,
-            mapped_at_creation: false,
-        });
 
         Self {
             transform,
             raw_particles,
             buffer,
+            buffer_capacity,
             max_particles: max_number,
             system,
             face_camera_flag: true,
             changed_since_last_prepare: true,
+            upload_bytes: 0,
+            sort_buffers: None,
         }
     }
 
+    /// Gives back the buffer and its capacity, for reuse by another system.
+    pub(super) fn into_buffer(self) -> (wgpu::Buffer, usize) {
+        (self.buffer, self.buffer_capacity)
+    }
+
     /// Returns true if the system is finished and should be deallocated.
     pub fn update(&mut self, time: &Time) -> bool {
         let finished = self.system.update(time);
+        self.refill_raw_particles();
+        finished
+    }
+
+    /// Simulates the system for `seconds` in steps of 1/30 s, so that effects like fires or smoke columns
+    /// look established from the first frame on. Call it right after creating the system.
+    ///
+    /// Returns true if the system finished during the warm up.
+    pub fn warm_up(&mut self, seconds: f32) -> bool {
+        const STEP: Duration = Duration::from_nanos(1_000_000_000 / 30);
+        let mut time = Time::new();
+        let mut remaining = Duration::from_secs_f32(seconds.max(0.0));
+        while !remaining.is_zero() {
+            let step = remaining.min(STEP);
+            remaining -= step;
+            time.advance(step);
+            if self.system.update(&time) {
+                self.refill_raw_particles();
+                return true;
+            }
+        }
+        self.refill_raw_particles();
+        false
+    }
+
+    fn refill_raw_particles(&mut self) {
         self.raw_particles.clear();
         self.system.fill_raw_particles(&mut self.raw_particles);
         self.changed_since_last_prepare = true;
-        finished
     }
 
     /// writes the raw particles to the queue.
     pub fn prepare(&mut self, queue: &wgpu::Queue) {
+        self.upload_bytes = 0;
         if self.changed_since_last_prepare {
             queue.write_buffer(&self.buffer, 0, bytemuck::cast_slice(&self.raw_particles));
             self.changed_since_last_prepare = false;
+            self.upload_bytes = std::mem::size_of_val(&self.raw_particles[..]) as u64;
         }
     }
 
+    /// Bytes written to the buffer by the last [`ParticleSystem::prepare`].
+    pub fn upload_bytes(&self) -> u64 {
+        self.upload_bytes
+    }
+
     pub fn buffer(&self) -> &wgpu::Buffer {
         &self.buffer
     }
@@ -97,4 +162,37 @@ impl ParticleSystem {
     pub fn texture(&self) -> Option<&BindableTexture> {
         self.system.texture()
     }
+
+    pub fn atlas(&self) -> Option<ParticleAtlas> {
+        self.system.atlas()
+    }
+
+    /// Enables sorting the particles back to front on the gpu with a [`super::ParticleSorter`], for correct
+    /// alpha blending. Costs some compute passes per frame, so only worth it for alpha blended systems.
+    pub fn set_gpu_sorting(&mut self, enabled: bool, device: &wgpu::Device) {
+        if enabled == self.gpu_sorting() {
+            return;
+        }
+        self.sort_buffers =
+            enabled.then(|| ParticleSortBuffers::new(device, &self.buffer, self.buffer_capacity));
+    }
+
+    pub fn gpu_sorting(&self) -> bool {
+        self.sort_buffers.is_some()
+    }
+
+    pub(super) fn sort_buffers(&self) -> Option<&ParticleSortBuffers> {
+        self.sort_buffers.as_ref()
+    }
+}
+
+pub(super) fn create_particle_buffer(device: &wgpu::Device, capacity: usize) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("particles"),
+        size: particle_buffer_size(capacity),
+        usage: wgpu::BufferUsages::COPY_DST
+            | wgpu::BufferUsages::VERTEX
+            | wgpu::BufferUsages::STORAGE,
+        mapped_at_creation: false,
+    })
 }
@@ -0,0 +1,350 @@
+use glam::{UVec2, Vec2, Vec3};
+use winit::dpi::PhysicalSize;
+
+use crate::{
+    camera3d::ProjectionKind, make_shader_source, rgba_bind_group_layout_cached,
+    uniforms::Uniforms, Aabb, Camera3d, Color, HdrTexture, HotReload, Input, RenderFormat,
+    Resizable, Screen, ScreenTextures, ShaderCache, ShaderSource, Time, ToneMapping,
+};
+
+const SHADER_SOURCE: ShaderSource = make_shader_source!("screen.wgsl", "pixel_perfect.wgsl");
+
+/// How the low resolution target is scaled up to the window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PixelScaling {
+    /// Largest whole number scale that fits into the window, so all pixels have the same size.
+    /// Falls back to [`PixelScaling::Fit`] if the window is smaller than the target.
+    #[default]
+    Integer,
+    /// Fills as much of the window as possible, pixels can differ in size by one screen pixel.
+    Fit,
+}
+
+/// Pixel art mode: the scene is rendered into a small target (e.g. 640x360) which is scaled up with
+/// nearest filtering and letterboxed into the window.
+///
+/// Works like an [`crate::OffscreenCamera`] with its own camera and [`Uniforms`]:
+///
+/// ```rust,ignore
+/// let mut pixel_perfect = PixelPerfect::new(&device, &mut shader_cache, camera, 640, 360, RenderFormat::HDR_MSAA4, surface_format, window_size);
+/// // every frame:
+/// pixel_perfect.snap_camera();
+/// pixel_perfect.prepare(&queue, &time, &input);
+/// let mut pass = pixel_perfect.new_render_pass(&mut encoder, Color::BLACK);
+/// color_renderer.render(&mut pass, pixel_perfect.uniforms());
+/// drop(pass);
+/// pixel_perfect.finish(&mut encoder, &mut tone_mapping, &surface_view);
+/// // the ui is rendered on top at the full window resolution.
+/// ```
+///
+/// For crisp pixels, use a render format without msaa, otherwise edges are anti aliased at the low resolution.
+pub struct PixelPerfect {
+    pub camera: Camera3d,
+    pub scaling: PixelScaling,
+    /// color of the bars around the scaled target.
+    pub letterbox_color: Color,
+    screen: Screen,
+    uniforms: Uniforms,
+    screen_textures: ScreenTextures,
+    /// tone mapped low resolution image.
+    output: HdrTexture,
+    output_nearest_bind_group: wgpu::BindGroup,
+    window_size: PhysicalSize<u32>,
+    pipeline: wgpu::RenderPipeline,
+    output_format: wgpu::TextureFormat,
+}
+
+impl PixelPerfect {
+    /// The camera is resized to the target size. `output_format` needs to match the output format of the
+    /// [`ToneMapping`] passed to [`PixelPerfect::finish`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        device: &wgpu::Device,
+        shader_cache: &mut ShaderCache,
+        mut camera: Camera3d,
+        width: u32,
+        height: u32,
+        render_format: RenderFormat,
+        output_format: wgpu::TextureFormat,
+        window_size: PhysicalSize<u32>,
+    ) -> Self {
+        let size = PhysicalSize::new(width, height);
+        camera.resize(size);
+        let output = HdrTexture::create(
+            device,
+            width,
+            height,
+            1,
+            output_format,
+            "pixel perfect output",
+        );
+        let output_nearest_bind_group = create_nearest_bind_group(device, &output);
+        let shader = shader_cache.register(SHADER_SOURCE, device);
+        let pipeline = create_pipeline(&shader, device, output_format);
+        Self {
+            camera,
+            scaling: PixelScaling::Integer,
+            letterbox_color: Color::BLACK,
+            screen: Screen::new(size, 1.0),
+            uniforms: Uniforms::new(device),
+            screen_textures: ScreenTextures::new(device, width, height, render_format),
+            output,
+            output_nearest_bind_group,
+            window_size,
+            pipeline,
+            output_format,
+        }
+    }
+
+    /// Size of the low resolution target in pixels.
+    pub fn target_size(&self) -> UVec2 {
+        UVec2::new(self.screen.width, self.screen.height)
+    }
+
+    /// Where the scaled target is drawn in the window, in physical pixels.
+    pub fn viewport(&self) -> Aabb {
+        letterbox_viewport(
+            self.target_size(),
+            UVec2::new(self.window_size.width, self.window_size.height),
+            self.scaling,
+        )
+    }
+
+    /// Window pixels per target pixel.
+    pub fn scale(&self) -> f32 {
+        self.viewport().size().x / self.screen.width as f32
+    }
+
+    /// Converts a position in the window (e.g. the cursor) into target pixels. None if it is on the letterbox bars.
+    pub fn window_to_target(&self, window_pos: Vec2) -> Option<Vec2> {
+        let viewport = self.viewport();
+        if !viewport.contains(window_pos) {
+            return None;
+        }
+        Some((window_pos - viewport.min) / self.scale())
+    }
+
+    /// Size of one target pixel in world units, for orthographic cameras. None for perspective cameras.
+    pub fn world_units_per_pixel(&self) -> Option<f32> {
+        match self.camera.projection.kind {
+            ProjectionKind::Orthographic { y_height } => Some(y_height / self.screen.height as f32),
+            ProjectionKind::Perspective { .. } => None,
+        }
+    }
+
+    /// Moves `world_pos` in the view plane of the camera, such that it lands exactly on a target pixel.
+    /// Snapping sprites and the camera keeps pixel art from shimmering while moving. Only has an effect for orthographic cameras.
+    pub fn snap_to_pixels(&self, world_pos: Vec3) -> Vec3 {
+        let Some(pixel) = self.world_units_per_pixel() else {
+            return world_pos;
+        };
+        let forward = self.camera.transform.direction();
+        let right = self.camera.transform.right();
+        let up = right.cross(forward).normalize_or_zero();
+        let x = world_pos.dot(right);
+        let y = world_pos.dot(up);
+        world_pos
+            + right * ((x / pixel).round() * pixel - x)
+            + up * ((y / pixel).round() * pixel - y)
+    }
+
+    /// Snaps the camera position to the pixel grid, see [`PixelPerfect::snap_to_pixels`].
+    pub fn snap_camera(&mut self) {
+        self.camera.transform.pos = self.snap_to_pixels(self.camera.transform.pos);
+    }
+
+    /// Writes the camera into the uniforms. Call once per frame before rendering.
+    pub fn prepare(&mut self, queue: &wgpu::Queue, time: &Time, input: &Input) {
+        self.uniforms
+            .prepare(queue, &self.camera, &self.screen, time, input);
+    }
+
+    /// Uniforms with the low resolution camera, to be passed to the `render` functions of renderers.
+    pub fn uniforms(&self) -> &Uniforms {
+        &self.uniforms
+    }
+
+    /// Hdr render pass into the low resolution target.
+    pub fn new_render_pass<'e>(
+        &'e self,
+        encoder: &'e mut wgpu::CommandEncoder,
+        clear_color: Color,
+    ) -> wgpu::RenderPass<'e> {
+        self.screen_textures
+            .new_hdr_target_render_pass(encoder, clear_color)
+    }
+
+    /// Hdr texture the scene was rendered into, e.g. to apply bloom to it before calling [`PixelPerfect::finish`].
+    pub fn hdr_texture(&self) -> &HdrTexture {
+        self.screen_textures.resolved()
+    }
+
+    /// Tone maps the low resolution image and scales it up into the `output_texture`, clearing the letterbox bars.
+    pub fn finish(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        tone_mapping: &mut ToneMapping,
+        output_texture: &wgpu::TextureView,
+    ) {
+        tone_mapping.apply(
+            encoder,
+            self.screen_textures.resolved().bind_group(),
+            self.output.view(),
+        );
+
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Pixel perfect upscale"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output_texture,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(self.letterbox_color.into()),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        let viewport = self.viewport();
+        let size = viewport.size();
+        if size.x < 1.0 || size.y < 1.0 {
+            return;
+        }
+        pass.set_viewport(viewport.min.x, viewport.min.y, size.x, size.y, 0.0, 1.0);
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, &self.output_nearest_bind_group, &[]);
+        pass.draw(0..3, 0..1);
+    }
+}
+
+impl Resizable for PixelPerfect {
+    /// Only the window changes size, the target keeps its resolution.
+    fn on_resize(&mut self, size: PhysicalSize<u32>, _device: &wgpu::Device) {
+        self.window_size = size;
+    }
+}
+
+impl HotReload for PixelPerfect {
+    fn source(&self) -> ShaderSource {
+        SHADER_SOURCE
+    }
+
+    fn hot_reload(&mut self, shader: &wgpu::ShaderModule, device: &wgpu::Device) {
+        self.pipeline = create_pipeline(shader, device, self.output_format);
+    }
+}
+
+/// Rect in the window that a target of `target` pixels is scaled into, centered with bars on the sides.
+/// The corners are on whole pixels, so nearest sampling maps target pixels to screen pixels evenly.
+pub fn letterbox_viewport(target: UVec2, window: UVec2, scaling: PixelScaling) -> Aabb {
+    let target_f = target.as_vec2().max(Vec2::ONE);
+    let window_f = window.as_vec2();
+    let fit = (window_f / target_f).min_element();
+    let scale = match scaling {
+        PixelScaling::Integer if fit >= 1.0 => fit.floor(),
+        _ => fit,
+    };
+    let size = (target_f * scale).round().min(window_f);
+    let min = ((window_f - size) * 0.5).floor();
+    Aabb::new(min, min + size)
+}
+
+/// Rounds a 2d position to the pixel grid, where `pixel_size` is the size of one pixel in the units of `pos`.
+pub fn snap_to_pixel_grid(pos: Vec2, pixel_size: f32) -> Vec2 {
+    if pixel_size <= 0.0 {
+        return pos;
+    }
+    (pos / pixel_size).round() * pixel_size
+}
+
+fn create_nearest_bind_group(device: &wgpu::Device, texture: &HdrTexture) -> wgpu::BindGroup {
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("pixel perfect nearest sampler"),
+        address_mode_u: wgpu::AddressMode::ClampToEdge,
+        address_mode_v: wgpu::AddressMode::ClampToEdge,
+        address_mode_w: wgpu::AddressMode::ClampToEdge,
+        mag_filter: wgpu::FilterMode::Nearest,
+        min_filter: wgpu::FilterMode::Nearest,
+        mipmap_filter: wgpu::FilterMode::Nearest,
+        ..Default::default()
+    });
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("pixel perfect output"),
+        layout: rgba_bind_group_layout_cached(device),
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(texture.view()),
+            },
+            wgpu::BindGroupEntry {
+                binding: 1,
+                resource: wgpu::BindingResource::Sampler(&sampler),
+            },
+        ],
+    })
+}
+
+fn create_pipeline(
+    shader: &wgpu::ShaderModule,
+    device: &wgpu::Device,
+    output_format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[rgba_bind_group_layout_cached(device)],
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Pixel perfect upscale"),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format: output_format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{uvec2, vec2};
+
+    use super::{letterbox_viewport, PixelScaling};
+
+    #[test]
+    fn integer_scaling_letterboxes() {
+        let viewport =
+            letterbox_viewport(uvec2(640, 360), uvec2(1920, 1200), PixelScaling::Integer);
+        assert_eq!(viewport.min, vec2(0.0, 60.0));
+        assert_eq!(viewport.size(), vec2(1920.0, 1080.0));
+
+        let viewport =
+            letterbox_viewport(uvec2(640, 360), uvec2(1900, 1000), PixelScaling::Integer);
+        assert_eq!(viewport.size(), vec2(1280.0, 720.0));
+        let fit = letterbox_viewport(uvec2(640, 360), uvec2(1900, 1000), PixelScaling::Fit);
+        assert_eq!(fit.size().y, 1000.0);
+    }
+}
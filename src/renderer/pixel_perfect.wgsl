@@ -0,0 +1,13 @@
+@group(0)
+@binding(0)
+var low_res_image: texture_2d<f32>;
+
+@group(0)
+@binding(1)
+var nearest_sampler: sampler;
+
+// the viewport of the pass is set to the scaled target, so the fullscreen triangle covers exactly the target.
+@fragment
+fn fs_main(vs: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(low_res_image, nearest_sampler, vs.uv);
+}
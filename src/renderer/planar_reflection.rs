@@ -0,0 +1,206 @@
+use glam::{vec3, vec4, Mat4, Vec3, Vec4};
+use winit::dpi::PhysicalSize;
+
+use crate::{
+    uniforms::Uniforms, Camera3d, Camera3dRaw, Color, HdrTexture, Input, RenderFormat,
+    RenderTarget, Resizable, Screen, Time,
+};
+
+/// Renders the scene mirrored about a plane into an offscreen [`RenderTarget`], for water and mirror surfaces.
+///
+/// The mirrored camera has an oblique near plane that lies in the reflection plane, so everything below the
+/// plane (e.g. the ground under the water) is clipped without any changes to the shaders of the renderers.
+/// Renderers draw the reflection like with an [`crate::OffscreenCamera`], with the [`PlanarReflection::uniforms`]:
+///
+/// ```rust,ignore
+/// let mut reflection = PlanarReflection::new(&device, window_size, 0.5, RenderFormat::HDR_MSAA4);
+/// reflection.plane_origin = vec3(0.0, water_level, 0.0);
+/// // every frame:
+/// reflection.prepare(&queue, &camera, &time, &input);
+/// let mut pass = reflection.new_render_pass(&mut encoder, sky_color);
+/// color_renderer.render(&mut pass, reflection.uniforms());
+/// drop(pass);
+/// // then bind `reflection.texture()` in the water material.
+/// ```
+///
+/// The image is flipped horizontally, which keeps the winding order of triangles the same as for the main camera,
+/// so back face culling still works. A material samples it at the mirrored screen position of the fragment:
+///
+/// ```wgsl
+/// let screen_uv = in.clip_position.xy / vec2(screen.width, screen.height);
+/// let reflection = textureSample(t_reflection, s_reflection, vec2(1.0 - screen_uv.x, screen_uv.y));
+/// ```
+pub struct PlanarReflection {
+    /// any point on the reflection plane.
+    pub plane_origin: Vec3,
+    /// the side the normal points to is reflected, the other side is clipped.
+    pub plane_normal: Vec3,
+    /// moves the clip plane along the normal. A small negative value hides gaps at the edges of
+    /// surfaces that are distorted in the material, e.g. by waves.
+    pub clip_offset: f32,
+    resolution_scale: f32,
+    screen: Screen,
+    uniforms: Uniforms,
+    target: RenderTarget,
+}
+
+impl PlanarReflection {
+    /// Horizontal plane at y = 0, rendered at `resolution_scale` times the window size, e.g. 0.5 for blurry water.
+    pub fn new(
+        device: &wgpu::Device,
+        window_size: PhysicalSize<u32>,
+        resolution_scale: f32,
+        render_format: RenderFormat,
+    ) -> Self {
+        let size = scaled_size(window_size, resolution_scale);
+        Self {
+            plane_origin: Vec3::ZERO,
+            plane_normal: Vec3::Y,
+            clip_offset: 0.0,
+            resolution_scale,
+            screen: Screen::new(size, 1.0),
+            uniforms: Uniforms::new(device),
+            target: RenderTarget::new(
+                device,
+                size.width,
+                size.height,
+                render_format,
+                "planar reflection",
+            ),
+        }
+    }
+
+    /// Writes the mirrored `camera` into the uniforms. Call once per frame before rendering.
+    pub fn prepare(&mut self, queue: &wgpu::Queue, camera: &Camera3d, time: &Time, input: &Input) {
+        let camera_raw = self.mirrored_camera(camera);
+        self.uniforms
+            .prepare_camera_raw(queue, camera_raw, &self.screen, time, input);
+    }
+
+    /// Camera matrices of the reflection, see [`PlanarReflection::prepare`].
+    pub fn mirrored_camera(&self, camera: &Camera3d) -> Camera3dRaw {
+        let normal = self.plane_normal.normalize_or_zero();
+        let reflection = reflection_matrix(self.plane_origin, normal);
+        let view = camera.transform.calc_matrix() * reflection;
+        let clip_origin = self.plane_origin + normal * self.clip_offset;
+        let clip_plane_world = normal.extend(-normal.dot(clip_origin));
+        // planes transform with the inverse transpose of the point transform.
+        let clip_plane_view = view.inverse().transpose() * clip_plane_world;
+        let projection = oblique_projection(camera.projection.calc_matrix(), clip_plane_view);
+        let flip_x = Mat4::from_scale(vec3(-1.0, 1.0, 1.0));
+        let view_position = reflection.transform_point3(camera.transform.pos);
+        Camera3dRaw::from_matrices(view_position, view, flip_x * projection)
+    }
+
+    /// Uniforms with the mirrored camera, to be passed to the `render` functions of renderers.
+    pub fn uniforms(&self) -> &Uniforms {
+        &self.uniforms
+    }
+
+    /// Render pass into the reflection target, clearing it to `clear_color`, e.g. the color of the sky.
+    pub fn new_render_pass<'e>(
+        &'e self,
+        encoder: &'e mut wgpu::CommandEncoder,
+        clear_color: Color,
+    ) -> wgpu::RenderPass<'e> {
+        self.target.begin_pass(encoder, clear_color)
+    }
+
+    /// The rendered reflection in the hdr render format, to be sampled by materials in the main hdr pass.
+    pub fn texture(&self) -> &HdrTexture {
+        self.target.resolved()
+    }
+
+    pub fn resolution_scale(&self) -> f32 {
+        self.resolution_scale
+    }
+
+    /// Recreates the target, so bind groups of [`PlanarReflection::texture`] need to be recreated too.
+    pub fn set_resolution_scale(
+        &mut self,
+        resolution_scale: f32,
+        window_size: PhysicalSize<u32>,
+        device: &wgpu::Device,
+    ) {
+        self.resolution_scale = resolution_scale;
+        self.on_resize(window_size, device);
+    }
+}
+
+impl Resizable for PlanarReflection {
+    /// `size` is the size of the window, the target is resized to the window size times the resolution scale.
+    fn on_resize(&mut self, size: PhysicalSize<u32>, device: &wgpu::Device) {
+        let size = scaled_size(size, self.resolution_scale);
+        self.screen = Screen::new(size, 1.0);
+        self.target.resize(device, size);
+    }
+}
+
+fn scaled_size(window_size: PhysicalSize<u32>, resolution_scale: f32) -> PhysicalSize<u32> {
+    PhysicalSize::new(
+        ((window_size.width as f32 * resolution_scale) as u32).max(1),
+        ((window_size.height as f32 * resolution_scale) as u32).max(1),
+    )
+}
+
+/// Mirrors points about the plane through `origin` with the unit `normal`.
+pub fn reflection_matrix(origin: Vec3, normal: Vec3) -> Mat4 {
+    let n = normal;
+    let d = -n.dot(origin);
+    Mat4::from_cols(
+        vec4(
+            1.0 - 2.0 * n.x * n.x,
+            -2.0 * n.y * n.x,
+            -2.0 * n.z * n.x,
+            0.0,
+        ),
+        vec4(
+            -2.0 * n.x * n.y,
+            1.0 - 2.0 * n.y * n.y,
+            -2.0 * n.z * n.y,
+            0.0,
+        ),
+        vec4(
+            -2.0 * n.x * n.z,
+            -2.0 * n.y * n.z,
+            1.0 - 2.0 * n.z * n.z,
+            0.0,
+        ),
+        vec4(-2.0 * d * n.x, -2.0 * d * n.y, -2.0 * d * n.z, 1.0),
+    )
+}
+
+/// Replaces the near plane of a projection with a 0..1 depth range by `clip_plane` (in view space, xyz normal and w distance),
+/// so everything on the negative side of the plane is clipped. See Eric Lengyel, "Oblique View Frustum Depth Projection and Clipping".
+pub fn oblique_projection(projection: Mat4, clip_plane: Vec4) -> Mat4 {
+    let corner =
+        projection.inverse() * vec4(clip_plane.x.signum(), clip_plane.y.signum(), 1.0, 1.0);
+    let scaled_plane = clip_plane / clip_plane.dot(corner);
+    let mut rows = projection.transpose();
+    rows.z_axis = scaled_plane;
+    rows.transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{vec3, Mat4, Vec3};
+
+    use super::{oblique_projection, reflection_matrix};
+
+    #[test]
+    fn oblique_near_plane_clips_below_the_plane() {
+        let reflection = reflection_matrix(vec3(0.0, 2.0, 0.0), Vec3::Y);
+        assert!(reflection
+            .transform_point3(vec3(1.0, 5.0, 3.0))
+            .abs_diff_eq(vec3(1.0, -1.0, 3.0), 1e-5));
+
+        // like the mirrored camera, the camera is below the plane and looks up through it.
+        let view = Mat4::look_to_rh(vec3(0.0, -1.0, 0.0), vec3(1.0, 0.5, 0.0), Vec3::Y);
+        let plane_view = view.inverse().transpose() * vec3(0.0, 1.0, 0.0).extend(-2.0);
+        let projection = oblique_projection(Mat4::perspective_rh(0.8, 1.5, 0.1, 100.0), plane_view);
+        let depth = |p: Vec3| projection.project_point3(view.transform_point3(p)).z;
+        assert!(depth(vec3(10.0, 2.0, 1.0)).abs() < 1e-4);
+        assert!(depth(vec3(10.0, 2.5, 1.0)) > 0.0);
+        assert!(depth(vec3(10.0, 1.5, 1.0)) < 0.0);
+    }
+}
@@ -0,0 +1,275 @@
+use winit::dpi::PhysicalSize;
+
+use crate::{Color, DepthTexture, HdrTexture, RenderFormat, Resizable};
+
+/// Color and optional depth texture of a [`RenderFormat`], that can be rendered into and sampled afterwards,
+/// e.g. for minimaps, portals or previews of 3d objects in the ui.
+///
+/// With a `msaa_sample_count` > 1 the color is rendered into a multisampled texture and resolved into
+/// a single sampled one, which is what [`RenderTarget::resolved`] returns.
+pub struct RenderTarget {
+    render_format: RenderFormat,
+    size: PhysicalSize<u32>,
+    label: String,
+    color: HdrTexture,
+    /// only for msaa.
+    resolve: Option<HdrTexture>,
+    depth: Option<DepthTexture>,
+}
+
+impl RenderTarget {
+    pub fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        render_format: RenderFormat,
+        label: impl Into<String>,
+    ) -> Self {
+        let label: String = label.into();
+        let (color, resolve, depth) = create_textures(device, width, height, render_format, &label);
+        RenderTarget {
+            render_format,
+            size: PhysicalSize::new(width, height),
+            label,
+            color,
+            resolve,
+            depth,
+        }
+    }
+
+    pub fn render_format(&self) -> RenderFormat {
+        self.render_format
+    }
+
+    pub fn size(&self) -> PhysicalSize<u32> {
+        self.size
+    }
+
+    /// The single sampled color texture, that can be bound after rendering.
+    pub fn resolved(&self) -> &HdrTexture {
+        self.resolve.as_ref().unwrap_or(&self.color)
+    }
+
+    /// The multisampled color texture, None without msaa.
+    pub fn msaa_texture(&self) -> Option<&HdrTexture> {
+        self.resolve.as_ref().map(|_| &self.color)
+    }
+
+    pub fn depth(&self) -> Option<&DepthTexture> {
+        self.depth.as_ref()
+    }
+
+    /// Color attachment that resolves into [`RenderTarget::resolved`] if msaa is used.
+    pub fn color_attachment(
+        &self,
+        load: wgpu::LoadOp<wgpu::Color>,
+    ) -> wgpu::RenderPassColorAttachment<'_> {
+        wgpu::RenderPassColorAttachment {
+            view: self.color.view(),
+            resolve_target: self.resolve.as_ref().map(|r| r.view()),
+            ops: wgpu::Operations {
+                load,
+                store: wgpu::StoreOp::Store,
+            },
+        }
+    }
+
+    pub fn depth_attachment(
+        &self,
+        load: wgpu::LoadOp<f32>,
+    ) -> Option<wgpu::RenderPassDepthStencilAttachment<'_>> {
+        self.depth
+            .as_ref()
+            .map(|depth| wgpu::RenderPassDepthStencilAttachment {
+                view: depth.view(),
+                depth_ops: Some(wgpu::Operations {
+                    load,
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            })
+    }
+
+    /// Render pass that clears the color to `clear_color` and the depth to 1.0.
+    pub fn begin_pass<'e>(
+        &'e self,
+        encoder: &'e mut wgpu::CommandEncoder,
+        clear_color: Color,
+    ) -> wgpu::RenderPass<'e> {
+        self.begin_pass_with_load(
+            encoder,
+            wgpu::LoadOp::Clear(clear_color.into()),
+            wgpu::LoadOp::Clear(1.0),
+        )
+    }
+
+    /// Render pass that keeps what was rendered into the target before, e.g. to draw on top of the scene.
+    pub fn begin_pass_load<'e>(
+        &'e self,
+        encoder: &'e mut wgpu::CommandEncoder,
+    ) -> wgpu::RenderPass<'e> {
+        self.begin_pass_with_load(encoder, wgpu::LoadOp::Load, wgpu::LoadOp::Load)
+    }
+
+    /// Render pass that keeps the color but clears the depth to 1.0, for a second pass that is drawn on top
+    /// of everything before, e.g. first-person weapons that should not clip into walls.
+    pub fn begin_pass_clear_depth<'e>(
+        &'e self,
+        encoder: &'e mut wgpu::CommandEncoder,
+    ) -> wgpu::RenderPass<'e> {
+        self.begin_pass_with_load(encoder, wgpu::LoadOp::Load, wgpu::LoadOp::Clear(1.0))
+    }
+
+    pub fn begin_pass_with_load<'e>(
+        &'e self,
+        encoder: &'e mut wgpu::CommandEncoder,
+        color_load: wgpu::LoadOp<wgpu::Color>,
+        depth_load: wgpu::LoadOp<f32>,
+    ) -> wgpu::RenderPass<'e> {
+        self.pass()
+            .color_load(color_load)
+            .depth_load(depth_load)
+            .begin(encoder)
+    }
+
+    /// Builder for a render pass into this target with a load operation per attachment.
+    /// Both attachments are loaded by default:
+    ///
+    /// ```rust,ignore
+    /// // the scene:
+    /// let mut pass = target.pass().clear_color(Color::BLACK).clear_depth().begin(&mut encoder);
+    /// // then something on top, with its own depth:
+    /// let mut pass = target.pass().clear_depth().label("Overlay").begin(&mut encoder);
+    /// ```
+    pub fn pass(&self) -> RenderTargetPass<'_> {
+        RenderTargetPass {
+            target: self,
+            color_load: wgpu::LoadOp::Load,
+            depth_load: wgpu::LoadOp::Load,
+            label: None,
+        }
+    }
+
+    /// Recreates all textures, so bind groups of the old ones need to be recreated too.
+    pub fn resize(&mut self, device: &wgpu::Device, size: PhysicalSize<u32>) {
+        if size == self.size {
+            return;
+        }
+        self.size = size;
+        let (color, resolve, depth) = create_textures(
+            device,
+            size.width,
+            size.height,
+            self.render_format,
+            &self.label,
+        );
+        self.color = color;
+        self.resolve = resolve;
+        self.depth = depth;
+    }
+}
+
+/// A render pass into a [`RenderTarget`] before it is started, see [`RenderTarget::pass`].
+#[derive(Clone, Copy)]
+pub struct RenderTargetPass<'t> {
+    target: &'t RenderTarget,
+    color_load: wgpu::LoadOp<wgpu::Color>,
+    depth_load: wgpu::LoadOp<f32>,
+    label: Option<&'t str>,
+}
+
+impl<'t> RenderTargetPass<'t> {
+    pub fn clear_color(self, color: Color) -> Self {
+        self.color_load(wgpu::LoadOp::Clear(color.into()))
+    }
+
+    /// Keeps the color that was rendered before, the default.
+    pub fn load_color(self) -> Self {
+        self.color_load(wgpu::LoadOp::Load)
+    }
+
+    /// Clears the depth to 1.0, the far plane.
+    pub fn clear_depth(self) -> Self {
+        self.depth_load(wgpu::LoadOp::Clear(1.0))
+    }
+
+    /// Keeps the depth that was rendered before, the default.
+    pub fn load_depth(self) -> Self {
+        self.depth_load(wgpu::LoadOp::Load)
+    }
+
+    pub fn color_load(mut self, load: wgpu::LoadOp<wgpu::Color>) -> Self {
+        self.color_load = load;
+        self
+    }
+
+    /// Ignored for targets without depth.
+    pub fn depth_load(mut self, load: wgpu::LoadOp<f32>) -> Self {
+        self.depth_load = load;
+        self
+    }
+
+    /// Defaults to the label of the target.
+    pub fn label(mut self, label: &'t str) -> Self {
+        self.label = Some(label);
+        self
+    }
+
+    pub fn begin<'e>(self, encoder: &'e mut wgpu::CommandEncoder) -> wgpu::RenderPass<'e>
+    where
+        't: 'e,
+    {
+        let target = self.target;
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(self.label.unwrap_or(&target.label)),
+            color_attachments: &[Some(target.color_attachment(self.color_load))],
+            depth_stencil_attachment: target.depth_attachment(self.depth_load),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        })
+    }
+}
+
+impl Resizable for RenderTarget {
+    fn on_resize(&mut self, size: PhysicalSize<u32>, device: &wgpu::Device) {
+        self.resize(device, size);
+    }
+}
+
+fn create_textures(
+    device: &wgpu::Device,
+    width: u32,
+    height: u32,
+    render_format: RenderFormat,
+    label: &str,
+) -> (HdrTexture, Option<HdrTexture>, Option<DepthTexture>) {
+    let sample_count = render_format.msaa_sample_count;
+    let color = HdrTexture::create(
+        device,
+        width,
+        height,
+        sample_count,
+        render_format.color,
+        label,
+    );
+    let resolve = (sample_count > 1).then(|| {
+        HdrTexture::create(
+            device,
+            width,
+            height,
+            1,
+            render_format.color,
+            format!("{label} resolve"),
+        )
+    });
+    let depth = render_format.depth.map(|depth_format| {
+        DepthTexture::create(
+            device,
+            width.max(1),
+            height.max(1),
+            depth_format,
+            sample_count,
+        )
+    });
+    (color, resolve, depth)
+}
@@ -1,16 +1,16 @@
 
+use std::ops::Deref;
+
 use crate::{
     rgba_bind_group_layout_cached,
     rgba_bind_group_layout_msaa4_cached, BindableTexture, Color, RenderFormat,
-    Texture,
+    RenderTarget, Resizable, Texture,
 };
 use log::warn;
 use winit::dpi::PhysicalSize;
+/// The hdr [`RenderTarget`] the main camera renders the scene into.
 pub struct ScreenTextures {
-    pub render_format: RenderFormat,
-    pub depth_texture: Option<DepthTexture>,
-    pub hdr_msaa_texture: HdrTexture,
-    pub hdr_resolve_target: HdrTexture,
+    target: RenderTarget,
 }
 
 impl ScreenTextures {
@@ -20,25 +20,8 @@ impl ScreenTextures {
         height: u32,
         render_format: RenderFormat,
     ) -> Self {
-        let depth_texture = render_format.depth.map(|depth_format| {
-            DepthTexture::create(
-                device,
-                width,
-                height,
-                depth_format,
-                render_format.msaa_sample_count,
-            )
-        });
-        let hdr_msaa_texture =
-            HdrTexture::create(device, width, height, 4, render_format.color, "");
-        let hdr_resolve_target =
-            HdrTexture::create(device, width, height, 1, render_format.color, "");
-
         Self {
-            render_format,
-            depth_texture,
-            hdr_msaa_texture,
-            hdr_resolve_target,
+            target: RenderTarget::new(device, width, height, render_format, "Hdr Renderpass"),
         }
     }
 
@@ -47,54 +30,28 @@ impl ScreenTextures {
         encoder: &'e mut wgpu::CommandEncoder,
         color: Color,
     ) -> wgpu::RenderPass<'e> {
-        let color_attachment = wgpu::RenderPassColorAttachment {
-            view: self.hdr_msaa_texture.view(),
-            resolve_target: Some(self.hdr_resolve_target.view()),
-            ops: wgpu::Operations {
-                load: wgpu::LoadOp::Clear(color.into()),
-                store: wgpu::StoreOp::Store,
-            },
-        };
-        let main_render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("Hdr Renderpass"),
-            color_attachments: &[Some(color_attachment)],
-            depth_stencil_attachment: self.depth_texture.as_ref().map(|depth_texture| {
-                wgpu::RenderPassDepthStencilAttachment {
-                    view: depth_texture.view(),
-                    depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(1.0),
-                        store: wgpu::StoreOp::Store,
-                    }),
-                    stencil_ops: None,
-                }
-            }),
-            occlusion_query_set: None,
-            timestamp_writes: None,
-        });
-        main_render_pass
+        self.target.begin_pass(encoder, color)
+    }
+
+    /// Keeps the rendered scene but clears the depth, e.g. for a second pass with first-person weapons.
+    /// For other combinations of clearing and loading, use [`RenderTarget::pass`].
+    pub fn new_hdr_target_render_pass_clear_depth<'e>(
+        &'e self,
+        encoder: &'e mut wgpu::CommandEncoder,
+    ) -> wgpu::RenderPass<'e> {
+        self.target.begin_pass_clear_depth(encoder)
     }
 
     pub fn resize(&mut self, device: &wgpu::Device, size: PhysicalSize<u32>) {
-        if let Some(depth_texture) = &mut self.depth_texture {
-            depth_texture.recreate(device, size.width, size.height);
-        }
+        self.target.resize(device, size);
+    }
+}
+
+impl Deref for ScreenTextures {
+    type Target = RenderTarget;
 
-        self.hdr_msaa_texture = HdrTexture::create(
-            device,
-            size.width,
-            size.height,
-            self.render_format.msaa_sample_count,
-            self.render_format.color,
-            "",
-        );
-        self.hdr_resolve_target = HdrTexture::create(
-            device,
-            size.width,
-            size.height,
-            1,
-            self.render_format.color,
-            "",
-        );
+    fn deref(&self) -> &Self::Target {
+        &self.target
     }
 }
 
@@ -165,6 +122,12 @@ impl DepthTexture {
     }
 }
 
+impl Resizable for ScreenTextures {
+    fn on_resize(&mut self, size: PhysicalSize<u32>, device: &wgpu::Device) {
+        self.resize(device, size);
+    }
+}
+
 #[derive(Debug)]
 pub struct HdrTexture {
     texture: BindableTexture,
@@ -181,6 +144,10 @@ impl HdrTexture {
         &self.texture.bind_group
     }
 
+    pub fn bindable(&self) -> &BindableTexture {
+        &self.texture
+    }
+
     pub fn create(
         device: &wgpu::Device,
         mut width: u32,
@@ -1,16 +1,28 @@
-use std::{ops::Range, rc::Rc, sync::Arc, vec};
+use std::{
+    ops::Range,
+    rc::Rc,
+    sync::Arc,
+    time::{Duration, Instant},
+    vec,
+};
 
 use crate::{
     make_shader_source, rgba_bind_group_layout_cached,
     shader::{ShaderCache},
     utils::rc_addr_as_u64,
     Aabb, BindableTexture, Camera3d, Camera3dGR, Color, GraphicsContext, GrowableBuffer, HotReload,
-    RenderFormat, ShaderSource, ToRaw, Transform, TransformRaw, VertexT, VertsLayout,
+    RenderFormat, ShaderSource, Time, ToRaw, Transform, TransformRaw, VertexT, VertsLayout,
 };
 
+use ahash::AHashMap;
 use glam::Vec2;
 use wgpu::{BindGroupLayout, BufferUsages, RenderPipeline};
 
+use super::{
+    sprite_drivers::{SpriteDrivers, SpriteProps},
+    AlphaMode, RenderStats,
+};
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable, PartialEq)]
 pub struct AlphaSdfParams {
@@ -33,8 +45,12 @@ impl Default for AlphaSdfParams {
     }
 }
 
-const SHADER_SOURCE: ShaderSource =
-    make_shader_source!("uniforms.wgsl", "alpha_sdf.wgsl", "sdf_sprite.wgsl");
+const SHADER_SOURCE: ShaderSource = make_shader_source!(
+    "uniforms.wgsl",
+    "alpha_sdf.wgsl",
+    "cutout.wgsl",
+    "sdf_sprite.wgsl"
+);
 /// Immediate Mode batches Sprite Rendering.
 pub struct SdfSpriteRenderer {
     instances: Vec<SpriteRaw>,
@@ -42,8 +58,21 @@ pub struct SdfSpriteRenderer {
     batches: Vec<SpriteBatch>,
     ctx: GraphicsContext,
     render_format: RenderFormat,
+    alpha_mode: AlphaMode,
     pipeline: RenderPipeline,
     camera_layout: Arc<wgpu::BindGroupLayout>,
+    layer_sorts: AHashMap<i16, SpriteSort>,
+    prepare_time: Duration,
+}
+
+/// How the sprites of one layer are ordered, after their `order_in_layer`. See [`SdfSprite::layer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpriteSort {
+    /// Back to front by the distance to the camera, for correct blending of sprites in 3d space.
+    #[default]
+    Distance,
+    /// Sprites lower on the screen are drawn in front of higher ones, for top-down 2d games.
+    ScreenY,
 }
 
 impl SdfSpriteRenderer {
@@ -58,7 +87,14 @@ impl SdfSpriteRenderer {
         let shader = cache.register(SHADER_SOURCE, &ctx.device);
 
         let camera_layout = camera.bind_group_layout().clone();
-        let pipeline = create_pipeline(&shader, &ctx.device, &camera_layout, render_format);
+        let alpha_mode = AlphaMode::Blend;
+        let pipeline = create_pipeline(
+            &shader,
+            &ctx.device,
+            &camera_layout,
+            render_format,
+            alpha_mode,
+        );
 
         SdfSpriteRenderer {
             instances: vec![],
@@ -67,23 +103,75 @@ impl SdfSpriteRenderer {
             ctx,
             pipeline,
             render_format,
+            alpha_mode,
             camera_layout,
+            layer_sorts: AHashMap::new(),
+            prepare_time: Duration::ZERO,
         }
     }
 
+    /// Switches all sprites between alpha blending and the cutout mode, which writes depth, see [`AlphaMode`].
+    pub fn set_alpha_mode(&mut self, alpha_mode: AlphaMode, cache: &mut ShaderCache) {
+        if alpha_mode == self.alpha_mode {
+            return;
+        }
+        let shader = cache.register(SHADER_SOURCE, &self.ctx.device);
+        self.pipeline = create_pipeline(
+            &shader,
+            &self.ctx.device,
+            &self.camera_layout,
+            self.render_format,
+            alpha_mode,
+        );
+        self.alpha_mode = alpha_mode;
+    }
+
+    pub fn alpha_mode(&self) -> AlphaMode {
+        self.alpha_mode
+    }
+
+    /// Sets how the sprites within a layer are sorted, layers without a sort set use [`SpriteSort::Distance`].
+    pub fn set_layer_sort(&mut self, layer: i16, sort: SpriteSort) {
+        self.layer_sorts.insert(layer, sort);
+    }
+
+    pub fn layer_sort(&self, layer: i16) -> SpriteSort {
+        self.layer_sorts.get(&layer).copied().unwrap_or_default()
+    }
+
     /// pass the unsorted sprites to this, they will be sorted in here.
-    pub fn prepare(&mut self, sprites: &mut [&SdfSprite], camera: &Camera3d) {
+    /// The [`SdfSprite::drivers`] are evaluated at the total time of the `time`.
+    pub fn prepare(&mut self, sprites: &mut [&SdfSprite], camera: &Camera3d, time: &Time) {
         // todo! frustum culling and all..
-        let (instances, batches) = batch_sprites(sprites, camera);
+        let start = Instant::now();
+        let now = time.total().as_secs_f32();
+        let (instances, batches) = batch_sprites(sprites, camera, &self.layer_sorts, now);
         self.instances = instances;
         self.batches = batches;
         self.instance_buffer
             .prepare(&self.instances, &self.ctx.device, &self.ctx.queue);
+        self.prepare_time = start.elapsed();
+    }
+
+    /// Stats of the sprites of the last [`SdfSpriteRenderer::prepare`], one draw call per batch.
+    pub fn stats(&self) -> RenderStats {
+        RenderStats {
+            draw_calls: self.batches.len() as u32,
+            instances: self.instances.len() as u32,
+            vertices: 4 * self.instances.len() as u32,
+            upload_bytes: self.instance_buffer.byte_len(),
+            prepare_time: self.prepare_time,
+        }
     }
 
     pub fn render<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, camera: &'a Camera3dGR) {
         pass.set_pipeline(&self.pipeline);
         pass.set_bind_group(0, camera.bind_group(), &[]);
+        pass.set_push_constants(
+            wgpu::ShaderStages::FRAGMENT,
+            0,
+            bytemuck::cast_slice(&self.alpha_mode.cutout_params(self.render_format)),
+        );
         pass.set_vertex_buffer(0, self.instance_buffer.buffer().slice(..));
         for batch in self.batches.iter() {
             pass.set_bind_group(1, &batch.texture.bind_group, &[]);
@@ -98,22 +186,40 @@ impl HotReload for SdfSpriteRenderer {
     }
 
     fn hot_reload(&mut self, shader: &wgpu::ShaderModule, device: &wgpu::Device) {
-        self.pipeline = create_pipeline(shader, device, &self.camera_layout, self.render_format)
+        self.pipeline = create_pipeline(
+            shader,
+            device,
+            &self.camera_layout,
+            self.render_format,
+            self.alpha_mode,
+        )
     }
 }
 
+/// Sorts the sprites by layer, then by order in layer, then as set in `layer_sorts` (distance by default),
+/// and merges consecutive sprites with the same texture into batches. Drivers are applied at `now` in seconds.
 pub fn batch_sprites(
     sprites: &mut [&SdfSprite],
     camera: &Camera3d,
+    layer_sorts: &AHashMap<i16, SpriteSort>,
+    now: f32,
 ) -> (Vec<SpriteRaw>, Vec<SpriteBatch>) {
     if sprites.is_empty() {
         return (vec![], vec![]);
     }
 
+    let view_proj = camera.projection.calc_matrix() * camera.transform.calc_matrix();
+    // sprites with a smaller key are drawn first.
+    let sort_key = |s: &SdfSprite| -> f32 {
+        match layer_sorts.get(&s.layer).copied().unwrap_or_default() {
+            SpriteSort::Distance => -s.transform.position.distance_squared(camera.transform.pos),
+            SpriteSort::ScreenY => -view_proj.project_point3(s.transform.position).y,
+        }
+    };
     sprites.sort_by(|a, b| {
-        let da = a.transform.position.distance_squared(camera.transform.pos);
-        let db = b.transform.position.distance_squared(camera.transform.pos);
-        db.partial_cmp(&da).unwrap()
+        (a.layer, a.order_in_layer)
+            .cmp(&(b.layer, b.order_in_layer))
+            .then_with(|| sort_key(a).total_cmp(&sort_key(b)))
     });
 
     let mut instances: Vec<SpriteRaw> = vec![];
@@ -123,7 +229,7 @@ pub fn batch_sprites(
         texture: sprites.first().unwrap().texture.clone(),
     };
     for s in sprites {
-        instances.push(s.to_raw());
+        instances.push(s.to_raw_at(now));
 
         if s.batch_key() != current_batch.batch_key() {
             let new_batch = SpriteBatch {
@@ -186,24 +292,34 @@ pub struct SdfSprite {
     pub uv: Aabb,
     pub color: Color,
     pub sdf_params: AlphaSdfParams,
+    /// Sprites in higher layers are drawn in front of sprites in lower layers, regardless of their distance.
+    pub layer: i16,
+    /// Within a layer, sprites with a higher order are drawn in front. Sprites with the same order are
+    /// sorted by the [`SpriteSort`] of the layer, see [`SdfSpriteRenderer::set_layer_sort`].
+    pub order_in_layer: i32,
+    /// Animations of the color, uv and size, e.g. hit flashes and fades. See [`crate::SpriteDriver`].
+    pub drivers: SpriteDrivers,
 }
 
 impl SdfSprite {
     fn batch_key(&self) -> u64 {
         rc_addr_as_u64(&self.texture)
     }
-}
-
-impl ToRaw for SdfSprite {
-    type Raw = SpriteRaw;
 
-    fn to_raw(&self) -> Self::Raw {
+    /// The instance data with the drivers applied at `now`, the total time in seconds.
+    fn to_raw_at(&self, now: f32) -> SpriteRaw {
+        let mut props = SpriteProps {
+            color: self.color,
+            uv: self.uv,
+            size: self.size,
+        };
+        self.drivers.apply(now, &mut props);
         SpriteRaw {
             transform: self.transform.to_raw(),
             offset: self.offset,
-            size: self.size,
-            uv: self.uv,
-            color: self.color,
+            size: props.size,
+            uv: props.uv,
+            color: props.color,
             sdf_params: self.sdf_params,
         }
     }
@@ -214,6 +330,7 @@ fn create_pipeline(
     device: &wgpu::Device,
     camera_layout: &BindGroupLayout,
     render_format: RenderFormat,
+    alpha_mode: AlphaMode,
 ) -> wgpu::RenderPipeline {
     let bind_group_layouts = &[
         camera_layout,
@@ -223,10 +340,18 @@ fn create_pipeline(
     let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
         label: Some("sprite pipeline layout"),
         bind_group_layouts,
-        push_constant_ranges: &[],
+        push_constant_ranges: &[wgpu::PushConstantRange {
+            stages: wgpu::ShaderStages::FRAGMENT,
+            range: 0..std::mem::size_of::<[f32; 2]>() as u32,
+        }],
     });
 
     let verts_layout = VertsLayout::new().instance::<SpriteRaw>(); // no vertex type and no instances!
+    let fs_entry = if alpha_mode.is_cutout() {
+        "alpha_sdf_cutout_fs"
+    } else {
+        "alpha_sdf_fs"
+    };
     let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
         label: Some("sprite pipeline"),
         layout: Some(&layout),
@@ -237,10 +362,10 @@ fn create_pipeline(
         },
         fragment: Some(wgpu::FragmentState {
             module: shader,
-            entry_point: "alpha_sdf_fs",
+            entry_point: fs_entry,
             targets: &[Some(wgpu::ColorTargetState {
                 format: render_format.color,
-                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                blend: alpha_mode.blend_state(),
                 write_mask: wgpu::ColorWrites::ALL,
             })],
         }),
@@ -255,13 +380,13 @@ fn create_pipeline(
         },
         depth_stencil: render_format.depth.map(|format| wgpu::DepthStencilState {
             format,
-            depth_write_enabled: false,
+            depth_write_enabled: alpha_mode.is_cutout(),
             depth_compare: wgpu::CompareFunction::Less,
             stencil: wgpu::StencilState::default(),
             bias: wgpu::DepthBiasState::default(),
         }),
         multisample: wgpu::MultisampleState {
-            alpha_to_coverage_enabled: false,
+            alpha_to_coverage_enabled: alpha_mode.alpha_to_coverage(render_format),
             count: render_format.msaa_sample_count,
             mask: !0,
         },
@@ -15,6 +15,9 @@ struct SpriteInstance {
    @location(8) params: vec4<f32>,       // in_to_border_cutoff, in_to_border_smooth, border_to_out_cutof, border_to_out_smooth
 }
 
+// threshold and alpha to coverage (0.0 or 1.0) of the cutout mode, see `AlphaMode::Cutout`.
+var<push_constant> cutout: vec2<f32>;
+
 @vertex
 fn vs_main(@builtin(vertex_index) vertex_index: u32, sprite: SpriteInstance) -> AlphaSdfVertexOutput {
 
@@ -49,6 +52,12 @@ fn vs_main(@builtin(vertex_index) vertex_index: u32, sprite: SpriteInstance) ->
 
 
 // for fs: see alpha_sdf_fs in alpha_sdf.wgsl          
+
+@fragment
+fn alpha_sdf_cutout_fs(in: AlphaSdfVertexOutput) -> @location(0) vec4<f32> {
+    return alpha_cutout(alpha_sdf_color(in), cutout.x, cutout.y > 0.5);
+}
+
 fn unit_uv_from_idx(idx: u32) ->  vec2<f32> {
     var out: vec2<f32>;
     switch idx {
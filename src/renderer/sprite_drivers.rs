@@ -0,0 +1,269 @@
+use std::{f32::consts::TAU, rc::Rc};
+
+use glam::{vec2, Vec2};
+use smallvec::SmallVec;
+
+use crate::{Aabb, Color, Lerp, Time};
+
+/// Animates the color, uv or size of a [`crate::SdfSprite`] without changing the sprite itself.
+/// Started on the [`SpriteDrivers`] of a sprite and evaluated by the [`crate::SdfSpriteRenderer`] in `prepare`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpriteDriver {
+    /// The color starts as `color` and goes back to the color of the sprite within `duration` seconds,
+    /// e.g. a white flash when the sprite is hit.
+    Flash { color: Color, duration: f32 },
+    /// Multiplies the color of the sprite with a color going from `from` to `to` within `duration` seconds,
+    /// and keeps `to` afterwards.
+    ColorOverTime {
+        from: Color,
+        to: Color,
+        duration: f32,
+    },
+    /// Multiplies the alpha from `from` to `to` within `duration` seconds, and keeps `to` afterwards.
+    Fade { from: f32, to: f32, duration: f32 },
+    /// Replaces the uv with one of the `frames` per `1/fps` seconds. Without `looping`, the last frame is kept.
+    Flipbook {
+        frames: Rc<[Aabb]>,
+        fps: f32,
+        looping: bool,
+    },
+    /// Scales the size by `1 + amplitude * sin(t * frequency * TAU)`, forever with no `duration`.
+    Pulse {
+        amplitude: f32,
+        frequency: f32,
+        duration: Option<f32>,
+    },
+}
+
+impl SpriteDriver {
+    pub fn flash(color: Color, duration: f32) -> Self {
+        SpriteDriver::Flash { color, duration }
+    }
+
+    pub fn fade_out(duration: f32) -> Self {
+        SpriteDriver::Fade {
+            from: 1.0,
+            to: 0.0,
+            duration,
+        }
+    }
+
+    pub fn fade_in(duration: f32) -> Self {
+        SpriteDriver::Fade {
+            from: 0.0,
+            to: 1.0,
+            duration,
+        }
+    }
+
+    /// Flipbook over a sprite sheet in the `uv` region of the texture with `columns` x `rows` frames,
+    /// played row by row from the top left.
+    pub fn flipbook_grid(uv: Aabb, columns: u32, rows: u32, fps: f32, looping: bool) -> Self {
+        let columns = columns.max(1);
+        let rows = rows.max(1);
+        let frame_size = uv.size() / vec2(columns as f32, rows as f32);
+        let frames: Rc<[Aabb]> = (0..rows)
+            .flat_map(|row| (0..columns).map(move |col| (col, row)))
+            .map(|(col, row)| {
+                let min = uv.min + frame_size * vec2(col as f32, row as f32);
+                Aabb::new(min, min + frame_size)
+            })
+            .collect();
+        SpriteDriver::Flipbook {
+            frames,
+            fps,
+            looping,
+        }
+    }
+
+    /// Seconds until the driver has no further effect on the sprite, None if it runs forever.
+    pub fn duration(&self) -> Option<f32> {
+        match self {
+            SpriteDriver::Flash { duration, .. }
+            | SpriteDriver::ColorOverTime { duration, .. }
+            | SpriteDriver::Fade { duration, .. } => Some(*duration),
+            SpriteDriver::Flipbook {
+                frames,
+                fps,
+                looping,
+            } => (!looping).then(|| frames.len() as f32 / fps.max(f32::EPSILON)),
+            SpriteDriver::Pulse { duration, .. } => *duration,
+        }
+    }
+
+    /// Applies the driver `t` seconds after it was started.
+    fn apply(&self, t: f32, props: &mut SpriteProps) {
+        let progress = |duration: f32| (t / duration.max(f32::EPSILON)).clamp(0.0, 1.0);
+        match self {
+            SpriteDriver::Flash { color, duration } => {
+                props.color = color.lerp(&props.color, progress(*duration));
+            }
+            SpriteDriver::ColorOverTime { from, to, duration } => {
+                props.color = props.color * from.lerp(to, progress(*duration));
+            }
+            SpriteDriver::Fade { from, to, duration } => {
+                props.color.a *= from.lerp(to, progress(*duration));
+            }
+            SpriteDriver::Flipbook {
+                frames,
+                fps,
+                looping,
+            } => {
+                if frames.is_empty() {
+                    return;
+                }
+                let frame = (t * fps).max(0.0) as usize;
+                let frame = if *looping {
+                    frame % frames.len()
+                } else {
+                    frame.min(frames.len() - 1)
+                };
+                props.uv = frames[frame];
+            }
+            SpriteDriver::Pulse {
+                amplitude,
+                frequency,
+                duration,
+            } => {
+                if duration.is_some_and(|d| t > d) {
+                    return;
+                }
+                props.size *= 1.0 + amplitude * (t * frequency * TAU).sin();
+            }
+        }
+    }
+}
+
+/// The properties of a sprite that drivers change.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct SpriteProps {
+    pub color: Color,
+    pub uv: Aabb,
+    pub size: Vec2,
+}
+
+/// Identifies a driver started with [`SpriteDrivers::start`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SpriteDriverHandle(u32);
+
+/// The drivers running on a sprite, applied in the order they were started:
+///
+/// ```rust,ignore
+/// // on hit:
+/// enemy.sprite.drivers.start(SpriteDriver::flash(Color::WHITE, 0.1), &time);
+/// // on death:
+/// let fade = enemy.sprite.drivers.start(SpriteDriver::fade_out(0.5), &time);
+/// // later:
+/// if enemy.sprite.drivers.is_finished(fade, &time) { despawn(enemy) }
+/// ```
+///
+/// Finished drivers stay until they are stopped, so a faded out sprite stays invisible.
+/// [`SpriteDrivers::remove_finished`] removes those that have no lasting effect anymore.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SpriteDrivers {
+    running: SmallVec<[RunningDriver; 2]>,
+    next_id: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct RunningDriver {
+    id: u32,
+    /// total time in seconds when the driver was started.
+    start: f32,
+    driver: SpriteDriver,
+}
+
+impl SpriteDrivers {
+    /// Starts the driver now, the total time of the `time` is the same clock the renderer evaluates drivers with.
+    pub fn start(&mut self, driver: SpriteDriver, time: &Time) -> SpriteDriverHandle {
+        self.start_at(driver, time.total().as_secs_f32())
+    }
+
+    fn start_at(&mut self, driver: SpriteDriver, start: f32) -> SpriteDriverHandle {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        self.running.push(RunningDriver { id, start, driver });
+        SpriteDriverHandle(id)
+    }
+
+    pub fn stop(&mut self, handle: SpriteDriverHandle) {
+        self.running.retain(|r| r.id != handle.0);
+    }
+
+    pub fn clear(&mut self) {
+        self.running.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.running.is_empty()
+    }
+
+    /// True if the driver was stopped or ran for its whole duration.
+    pub fn is_finished(&self, handle: SpriteDriverHandle, time: &Time) -> bool {
+        let now = time.total().as_secs_f32();
+        match self.running.iter().find(|r| r.id == handle.0) {
+            Some(r) => r.driver.duration().is_some_and(|d| now - r.start >= d),
+            None => true,
+        }
+    }
+
+    /// Removes finished flashes and pulses. Fades, color changes and flipbooks are kept, because their
+    /// last value stays visible.
+    pub fn remove_finished(&mut self, time: &Time) {
+        let now = time.total().as_secs_f32();
+        self.running.retain(|r| match r.driver {
+            SpriteDriver::Flash { duration, .. } => now - r.start < duration,
+            SpriteDriver::Pulse { duration, .. } => duration.is_none_or(|d| now - r.start < d),
+            _ => true,
+        });
+    }
+
+    /// Applies all drivers at the total time `now` in seconds.
+    pub(crate) fn apply(&self, now: f32, props: &mut SpriteProps) {
+        for r in self.running.iter() {
+            r.driver.apply(now - r.start, props);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::vec2;
+
+    use super::{SpriteDriver, SpriteDrivers, SpriteProps};
+    use crate::{Aabb, Color};
+
+    #[test]
+    fn drivers_apply_relative_to_their_start() {
+        let mut drivers = SpriteDrivers::default();
+        drivers.start_at(SpriteDriver::flash(Color::WHITE, 1.0), 10.0);
+        drivers.start_at(SpriteDriver::fade_out(2.0), 10.0);
+        drivers.start_at(
+            SpriteDriver::flipbook_grid(Aabb::UNIT, 2, 2, 4.0, false),
+            10.0,
+        );
+        let eval = |now: f32| {
+            let mut props = SpriteProps {
+                color: Color::BLACK,
+                uv: Aabb::UNIT,
+                size: vec2(1.0, 1.0),
+            };
+            drivers.apply(now, &mut props);
+            props
+        };
+
+        let start = eval(10.0);
+        assert_eq!(start.color, Color::WHITE);
+        assert_eq!(start.uv, Aabb::new(vec2(0.0, 0.0), vec2(0.5, 0.5)));
+
+        let later = eval(10.5);
+        assert!((later.color.r - 0.5).abs() < 1e-5);
+        assert!((later.color.a - 0.75).abs() < 1e-5);
+        assert_eq!(later.uv, Aabb::new(vec2(0.0, 0.5), vec2(0.5, 1.0)));
+
+        let end = eval(20.0);
+        assert_eq!(end.color.r, 0.0);
+        assert_eq!(end.color.a, 0.0);
+        assert_eq!(end.uv, Aabb::new(vec2(0.5, 0.5), vec2(1.0, 1.0)));
+    }
+}
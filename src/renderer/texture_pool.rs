@@ -0,0 +1,95 @@
+use std::rc::Rc;
+
+use crate::HdrTexture;
+
+/// Describes a transient render target that can be requested from a [`TexturePool`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TransientTextureDesc {
+    pub width: u32,
+    pub height: u32,
+    pub sample_count: u32,
+    pub format: wgpu::TextureFormat,
+}
+
+/// Hands out intermediate render targets (e.g. the mip chains of [`crate::Bloom`] and [`crate::BackdropBlur`])
+/// that are only needed for a few passes within a frame.
+///
+/// A texture is in use as long as the `Rc` returned by [`TexturePool::acquire`] is alive. Once it is dropped,
+/// the next `acquire` with the same [`TransientTextureDesc`] gets the same texture back, instead of allocating a new one.
+/// So post effects that run one after another share their intermediate textures, if they match in size and format.
+///
+/// Commands on the encoder are executed in the order they were recorded, so it is fine to drop a texture
+/// after recording the passes that use it, even though the gpu has not run them yet.
+///
+/// Call [`TexturePool::end_frame`] once per frame to free textures that were not used for a while, e.g. after a resize.
+#[derive(Debug, Default)]
+pub struct TexturePool {
+    entries: Vec<PoolEntry>,
+    frame: u64,
+}
+
+#[derive(Debug)]
+struct PoolEntry {
+    desc: TransientTextureDesc,
+    texture: Rc<HdrTexture>,
+    last_used_frame: u64,
+}
+
+/// Textures not used for this many frames are dropped by [`TexturePool::end_frame`].
+const UNUSED_FRAMES_BEFORE_DROP: u64 = 2;
+
+impl TexturePool {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a texture that is not in use anywhere else, allocating a new one if there is none.
+    pub fn acquire(
+        &mut self,
+        device: &wgpu::Device,
+        desc: TransientTextureDesc,
+        label: &str,
+    ) -> Rc<HdrTexture> {
+        let free = self
+            .entries
+            .iter_mut()
+            .find(|e| e.desc == desc && Rc::strong_count(&e.texture) == 1);
+        if let Some(entry) = free {
+            entry.last_used_frame = self.frame;
+            return entry.texture.clone();
+        }
+
+        let texture = Rc::new(HdrTexture::create(
+            device,
+            desc.width,
+            desc.height,
+            desc.sample_count,
+            desc.format,
+            label,
+        ));
+        self.entries.push(PoolEntry {
+            desc,
+            texture: texture.clone(),
+            last_used_frame: self.frame,
+        });
+        texture
+    }
+
+    pub fn end_frame(&mut self) {
+        self.frame += 1;
+        let frame = self.frame;
+        self.entries.retain(|e| {
+            Rc::strong_count(&e.texture) > 1
+                || e.last_used_frame + UNUSED_FRAMES_BEFORE_DROP > frame
+        });
+    }
+
+    /// Number of textures allocated by the pool, in use or not.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
@@ -2,11 +2,35 @@
 use wgpu::{PushConstantRange, ShaderStages};
 
 use crate::{
-    make_shader_source, rgba_bind_group_layout_cached, HotReload, ShaderCache, ShaderSource,
+    graphics_context::OutputMode, make_shader_source, rgba_bind_group_layout_cached, HotReload,
+    ShaderCache, ShaderSource,
 };
 
+/// Brightness of the display for HDR output modes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HdrDisplaySettings {
+    /// Brightness in nits that a value of 1.0 in the hdr scene is shown with, e.g. for the ui and white surfaces.
+    pub paper_white_nits: f32,
+    /// Peak brightness of the display. Brighter parts of the scene are compressed towards it.
+    pub max_nits: f32,
+}
+
+impl Default for HdrDisplaySettings {
+    fn default() -> Self {
+        Self {
+            paper_white_nits: 200.0,
+            max_nits: 1000.0,
+        }
+    }
+}
+
+/// Maps the hdr scene to the output. For [`OutputMode::Sdr`] this is ACES tone mapping,
+/// for the HDR output modes values above paper white roll off towards the peak brightness of the display instead.
 pub struct ToneMapping {
     pub enabled: bool,
+    /// Should match the output mode of the [`crate::GraphicsContext`] if the output is the surface.
+    pub output_mode: OutputMode,
+    pub hdr_display: HdrDisplaySettings,
     pipeline: wgpu::RenderPipeline,
     output_format: wgpu::TextureFormat,
 }
@@ -24,6 +48,8 @@ impl ToneMapping {
         let pipeline = create_pipeline(&shader, device, output_format);
         Self {
             enabled: true,
+            output_mode: OutputMode::Sdr,
+            hdr_display: HdrDisplaySettings::default(),
             pipeline,
             output_format,
         }
@@ -58,6 +84,9 @@ impl ToneMapping {
             0,
             bytemuck::cast_slice(&[PushContants {
                 enabled: if self.enabled { 1 } else { 0 },
+                output_mode: self.output_mode as u32,
+                paper_white_nits: self.hdr_display.paper_white_nits,
+                max_nits: self.hdr_display.max_nits,
             }]),
         );
         tone_mapping_pass.draw(0..3, 0..1);
@@ -127,4 +156,8 @@ fn create_pipeline(
 pub struct PushContants {
     // 0 is off, 1 is enabled
     enabled: u32,
+    // 0 is sdr, 1 is scRGB, 2 is HDR10
+    output_mode: u32,
+    paper_white_nits: f32,
+    max_nits: f32,
 }
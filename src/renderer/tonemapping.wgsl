@@ -6,18 +6,69 @@ var hdr_image: texture_2d<f32>;
 @binding(1)
 var hdr_sampler: sampler;
 
-// 0 is off, 1 is aces
-var<push_constant> enabled: u32;
+struct ToneMappingParams {
+    // 0 is off, 1 is enabled
+    enabled: u32,
+    // see OutputMode: 0 is sdr, 1 is scRGB, 2 is HDR10
+    output_mode: u32,
+    paper_white_nits: f32,
+    max_nits: f32,
+}
+
+const OUTPUT_SDR: u32 = 0u;
+const OUTPUT_SCRGB: u32 = 1u;
+const OUTPUT_HDR10: u32 = 2u;
+
+var<push_constant> params: ToneMappingParams;
 
 @fragment
 fn fs_main(vs: VertexOutput) -> @location(0) vec4<f32> {
     let color_with_a: vec4<f32> = textureSample(hdr_image, hdr_sampler, vs.uv);
-    if enabled == 1u{
-        let color = aces_tone_map(color_with_a.rgb);
-        return vec4(color, color_with_a.a);   
-    }else{
-        return color_with_a;
+    if params.output_mode == OUTPUT_SDR {
+        if params.enabled == 1u {
+            let color = aces_tone_map(color_with_a.rgb);
+            return vec4(color, color_with_a.a);
+        } else {
+            return color_with_a;
+        }
+    }
+
+    // hdr output: 1.0 in the scene is paper white, brighter values roll off towards the max brightness of the display.
+    var color = max(color_with_a.rgb, vec3(0.0));
+    if params.enabled == 1u {
+        color = hdr_roll_off(color, params.max_nits / params.paper_white_nits);
+    }
+    let nits = color * params.paper_white_nits;
+    if params.output_mode == OUTPUT_SCRGB {
+        // scRGB: linear rec709 primaries, 1.0 is 80 nits.
+        return vec4(nits / 80.0, color_with_a.a);
     }
+    let rec2020 = REC709_TO_REC2020 * nits;
+    return vec4(pq_encode(rec2020 / 10000.0), color_with_a.a);
+}
+
+// Keeps values below 1.0 unchanged and compresses brighter values, such that they approach `max_value`.
+fn hdr_roll_off(color: vec3<f32>, max_value: f32) -> vec3<f32> {
+    let headroom = max(max_value - 1.0, 0.0001);
+    let t = max(color - 1.0, vec3(0.0)) / headroom;
+    return min(color, vec3(1.0)) + headroom * t / (1.0 + t);
+}
+
+const REC709_TO_REC2020 = mat3x3<f32>(
+    0.6274040, 0.0690970, 0.0163916,
+    0.3292820, 0.9195400, 0.0880132,
+    0.0433136, 0.0113612, 0.8955950,
+);
+
+// SMPTE ST.2084 (PQ) inverse EOTF, input is linear luminance where 1.0 is 10000 nits.
+fn pq_encode(linear: vec3<f32>) -> vec3<f32> {
+    let m1 = 0.1593017578125;
+    let m2 = 78.84375;
+    let c1 = 0.8359375;
+    let c2 = 18.8515625;
+    let c3 = 18.6875;
+    let y = pow(clamp(linear, vec3(0.0), vec3(1.0)), vec3(m1));
+    return pow((c1 + c2 * y) / (1.0 + c3 * y), vec3(m2));
 }
 
 // Maps HDR values to linear values
@@ -0,0 +1,363 @@
+use std::time::Duration;
+
+use glam::Vec2;
+use wgpu::{PushConstantRange, ShaderStages};
+use winit::dpi::PhysicalSize;
+
+use crate::{
+    make_shader_source, rgba_bind_group_layout_cached, Color, Easing, HdrTexture, HotReload,
+    Resizable, ShaderCache, ShaderSource, Time, ToneMapping,
+};
+
+const SHADER_SOURCE: ShaderSource = make_shader_source!("screen.wgsl", "transition.wgsl");
+
+/// How the screen changes from the old scene to the new one during a [`ScreenTransition`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransitionKind {
+    /// Fades the screen to the color. The color stays on screen after the transition is finished,
+    /// until the next transition starts or [`ScreenTransition::stop`] is called.
+    FadeOut(Color),
+    /// Fades from the color to the screen.
+    FadeIn(Color),
+    /// Fades to the color and back, the scene should be switched in the middle when the screen is covered.
+    FadeThrough(Color),
+    /// Blends the captured old frame into the new frames.
+    Crossfade,
+    /// Moves an edge over the screen in `direction` (in uv space, y down), revealing the new frames behind it.
+    /// `softness` is the width of the edge as a fraction of the screen.
+    Wipe { direction: Vec2, softness: f32 },
+}
+
+impl TransitionKind {
+    fn needs_capture(&self) -> bool {
+        matches!(
+            self,
+            TransitionKind::Crossfade | TransitionKind::Wipe { .. }
+        )
+    }
+}
+
+#[derive(Debug)]
+struct ActiveTransition {
+    kind: TransitionKind,
+    duration: f32,
+    elapsed: f32,
+    easing: Easing,
+    captured: bool,
+    switched: bool,
+}
+
+impl ActiveTransition {
+    /// Progress in 0.0..=1.0 without easing.
+    fn linear_progress(&self) -> f32 {
+        if self.duration <= 0.0 {
+            return 1.0;
+        }
+        (self.elapsed / self.duration).clamp(0.0, 1.0)
+    }
+
+    /// The point at which the app should switch to the new scene.
+    fn switch_point_reached(&self) -> bool {
+        match self.kind {
+            TransitionKind::FadeOut(_) => self.linear_progress() >= 1.0,
+            TransitionKind::FadeIn(_) => true,
+            TransitionKind::FadeThrough(_) => self.linear_progress() >= 0.5,
+            TransitionKind::Crossfade | TransitionKind::Wipe { .. } => self.captured,
+        }
+    }
+}
+
+/// Fades, crossfades and wipes between scenes, drawn as a fullscreen pass on top of the tone mapped frame, before the ui.
+///
+/// Start a transition with [`ScreenTransition::start`] and switch the scene once [`ScreenTransition::switch_scene_now`]
+/// returns true. For crossfades and wipes, the old frame is captured in the frame the transition was started,
+/// so the old scene should still be rendered in that frame:
+///
+/// ```rust,ignore
+/// if input.keys().just_pressed(KeyCode::Enter) {
+///     world.transition.borrow_mut().start(TransitionKind::Crossfade, Duration::from_secs(1), Easing::EaseInOut);
+/// }
+/// if world.transition.borrow().switch_scene_now() {
+///     self.scene = Scene::Game;
+/// }
+/// ```
+pub struct ScreenTransition {
+    size: PhysicalSize<u32>,
+    active: Option<ActiveTransition>,
+    switch_pending: bool,
+    captured: Option<HdrTexture>,
+    /// bound instead of the captured frame when nothing was captured.
+    placeholder: HdrTexture,
+    pipeline: wgpu::RenderPipeline,
+    color_format: wgpu::TextureFormat,
+}
+
+impl ScreenTransition {
+    /// The `color_format` should be the output format of the [`ToneMapping`] used in [`ScreenTransition::capture`].
+    pub fn new(
+        device: &wgpu::Device,
+        width: u32,
+        height: u32,
+        color_format: wgpu::TextureFormat,
+        shader_cache: &mut ShaderCache,
+    ) -> Self {
+        let shader = shader_cache.register(SHADER_SOURCE, device);
+        let pipeline = create_pipeline(&shader, device, color_format);
+        Self {
+            size: PhysicalSize::new(width, height),
+            active: None,
+            switch_pending: false,
+            captured: None,
+            placeholder: HdrTexture::create(
+                device,
+                1,
+                1,
+                1,
+                color_format,
+                "transition placeholder",
+            ),
+            pipeline,
+            color_format,
+        }
+    }
+
+    /// Starts a new transition, replacing the current one.
+    pub fn start(&mut self, kind: TransitionKind, duration: Duration, easing: Easing) {
+        self.active = Some(ActiveTransition {
+            kind,
+            duration: duration.as_secs_f32(),
+            elapsed: 0.0,
+            easing,
+            captured: false,
+            switched: false,
+        });
+        self.switch_pending = false;
+    }
+
+    /// Removes the current transition, e.g. the color left on screen by a [`TransitionKind::FadeOut`].
+    pub fn stop(&mut self) {
+        self.active = None;
+        self.switch_pending = false;
+    }
+
+    /// True while a transition is running or a [`TransitionKind::FadeOut`] covers the screen.
+    pub fn is_active(&self) -> bool {
+        self.active.is_some()
+    }
+
+    /// Eased progress of the current transition in 0.0..=1.0, None if there is none.
+    pub fn progress(&self) -> Option<f32> {
+        let active = self.active.as_ref()?;
+        Some(active.easing.y(active.linear_progress()))
+    }
+
+    /// Returns true once per transition, in the frame the app should switch to the new scene:
+    ///
+    /// - [`TransitionKind::FadeOut`]: when the screen is fully covered
+    /// - [`TransitionKind::FadeIn`]: right away
+    /// - [`TransitionKind::FadeThrough`]: in the middle of the transition
+    /// - [`TransitionKind::Crossfade`] and [`TransitionKind::Wipe`]: in the frame after the old frame was captured
+    pub fn switch_scene_now(&mut self) -> bool {
+        std::mem::take(&mut self.switch_pending)
+    }
+
+    /// True if the old frame should be captured with [`ScreenTransition::capture`] this frame.
+    pub fn needs_capture(&self) -> bool {
+        self.active
+            .as_ref()
+            .is_some_and(|a| a.kind.needs_capture() && !a.captured)
+    }
+
+    /// Advances the transition, call this once per frame before the update logic of the app.
+    pub fn update(&mut self, time: &Time) {
+        let Some(active) = &mut self.active else {
+            return;
+        };
+        // the transition starts in the frame after the old frame is captured.
+        let waits_for_capture = active.kind.needs_capture() && !active.captured;
+        if !waits_for_capture {
+            active.elapsed += time.delta().as_secs_f32();
+        }
+        if !active.switched && active.switch_point_reached() {
+            active.switched = true;
+            self.switch_pending = true;
+        }
+        let finished = active.linear_progress() >= 1.0 && !waits_for_capture;
+        if finished && !matches!(active.kind, TransitionKind::FadeOut(_)) {
+            self.active = None;
+            self.captured = None;
+        }
+    }
+
+    /// Tone maps the hdr scene into a texture that is shown on top of the following frames, if [`ScreenTransition::needs_capture`].
+    pub fn capture(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        device: &wgpu::Device,
+        tone_mapping: &mut ToneMapping,
+        input_texture: &wgpu::BindGroup,
+    ) {
+        if !self.needs_capture() {
+            return;
+        }
+        let captured = self.captured.get_or_insert_with(|| {
+            HdrTexture::create(
+                device,
+                self.size.width,
+                self.size.height,
+                1,
+                self.color_format,
+                "transition captured frame",
+            )
+        });
+        tone_mapping.apply(encoder, input_texture, captured.view());
+        if let Some(active) = &mut self.active {
+            active.captured = true;
+        }
+    }
+
+    /// Draws the transition on top of the `output_texture`. Does nothing if no transition is active.
+    pub fn render(&self, encoder: &mut wgpu::CommandEncoder, output_texture: &wgpu::TextureView) {
+        let Some(active) = &self.active else {
+            return;
+        };
+        let progress = active.easing.y(active.linear_progress());
+        let mut params = PushConstants {
+            color: Color::TRANSPARENT,
+            direction: Vec2::X,
+            amount: progress,
+            softness: 0.0,
+            mode: MODE_FADE,
+            _pad: [0; 3],
+        };
+        match active.kind {
+            TransitionKind::FadeOut(color) => {
+                params.color = color;
+            }
+            TransitionKind::FadeIn(color) => {
+                params.color = color;
+                params.amount = 1.0 - progress;
+            }
+            TransitionKind::FadeThrough(color) => {
+                params.color = color;
+                params.amount = 1.0 - (progress * 2.0 - 1.0).abs();
+            }
+            TransitionKind::Crossfade => {
+                params.mode = MODE_CROSSFADE;
+            }
+            TransitionKind::Wipe {
+                direction,
+                softness,
+            } => {
+                params.mode = MODE_WIPE;
+                params.direction = direction.normalize_or_zero();
+                params.softness = softness;
+            }
+        }
+        let texture = match &self.captured {
+            Some(captured) if active.captured => captured,
+            _ if params.mode != MODE_FADE => return,
+            _ => &self.placeholder,
+        };
+
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Screen transition"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: output_texture,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, texture.bind_group(), &[]);
+        pass.set_push_constants(ShaderStages::FRAGMENT, 0, bytemuck::cast_slice(&[params]));
+        pass.draw(0..3, 0..1);
+    }
+}
+
+impl Resizable for ScreenTransition {
+    fn on_resize(&mut self, size: PhysicalSize<u32>, _device: &wgpu::Device) {
+        self.size = size;
+        // a frame captured before the resize is stretched over the screen until the transition ends.
+        if !self.active.as_ref().is_some_and(|a| a.captured) {
+            self.captured = None;
+        }
+    }
+}
+
+impl HotReload for ScreenTransition {
+    fn source(&self) -> ShaderSource {
+        SHADER_SOURCE
+    }
+
+    fn hot_reload(&mut self, shader: &wgpu::ShaderModule, device: &wgpu::Device) {
+        self.pipeline = create_pipeline(shader, device, self.color_format);
+    }
+}
+
+const MODE_FADE: u32 = 0;
+const MODE_CROSSFADE: u32 = 1;
+const MODE_WIPE: u32 = 2;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+pub struct PushConstants {
+    color: Color,
+    direction: Vec2,
+    amount: f32,
+    softness: f32,
+    mode: u32,
+    _pad: [u32; 3],
+}
+
+fn create_pipeline(
+    shader: &wgpu::ShaderModule,
+    device: &wgpu::Device,
+    color_format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[rgba_bind_group_layout_cached(device)],
+        push_constant_ranges: &[PushConstantRange {
+            stages: ShaderStages::FRAGMENT,
+            range: 0..std::mem::size_of::<PushConstants>() as u32,
+        }],
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Screen transition"),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format: color_format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
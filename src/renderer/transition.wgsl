@@ -0,0 +1,41 @@
+struct TransitionParams {
+    color: vec4<f32>,
+    direction: vec2<f32>,
+    // alpha of the color for fades, progress of the transition otherwise.
+    amount: f32,
+    softness: f32,
+    mode: u32,
+}
+
+const MODE_FADE: u32 = 0u;
+const MODE_CROSSFADE: u32 = 1u;
+const MODE_WIPE: u32 = 2u;
+
+var<push_constant> params: TransitionParams;
+
+@group(0)
+@binding(0)
+var captured_image: texture_2d<f32>;
+
+@group(0)
+@binding(1)
+var captured_sampler: sampler;
+
+// Drawn on top of the new frame with alpha blending: either a color or the captured old frame.
+@fragment
+fn fs_main(vs: VertexOutput) -> @location(0) vec4<f32> {
+    if params.mode == MODE_FADE {
+        return vec4(params.color.rgb, params.color.a * params.amount);
+    }
+    let old = textureSample(captured_image, captured_sampler, vs.uv);
+    if params.mode == MODE_CROSSFADE {
+        return vec4(old.rgb, 1.0 - params.amount);
+    }
+    // wipe: t goes from 0 at the corner the wipe starts at, to 1 at the opposite corner.
+    let d = params.direction;
+    let t = dot(vs.uv - 0.5, d) / (abs(d.x) + abs(d.y)) + 0.5;
+    let softness = max(params.softness, 0.0001);
+    let edge = params.amount * (1.0 + softness);
+    let old_alpha = smoothstep(edge - softness, edge, t);
+    return vec4(old.rgb, old_alpha);
+}
@@ -1,6 +1,34 @@
-var<push_constant> push_color: vec4<f32>;
+struct UiPush {
+    color: vec4<f32>,
+    // board layout space -> ui layout space: layout_pos * viewport.z + viewport.xy, see UiViewport.
+    viewport: vec4<f32>,
+    // min x, min y, max x, max y of the batch in board layout space, see clip_pos.
+    clip: vec4<f32>,
+}
+var<push_constant> ui_push: UiPush;
+
+// Moves a corner of an instance quad onto the clip rect of its batch (set by divs with clip_children), which cuts
+// the quad off at the clip rect. Everything interpolated over the quad is linear in the position, so computing it
+// from the moved corner (with clip_uv for uvs) keeps the visible part of the quad exactly like before.
+fn clip_pos(pos: vec2<f32>, clip: vec4<f32>) -> vec2<f32> {
+    return clamp(pos, clip.xy, max(clip.xy, clip.zw));
+}
 
-const UI_REFERENCE_Y_HEIGHT: f32 = 1080.0;
+// The `uv` rect (min and max) spread over the `aabb`, at the position `pos`.
+fn clip_uv(pos: vec2<f32>, aabb: vec4<f32>, uv: vec4<f32>) -> vec2<f32> {
+    let t = (pos - aabb.xy) / max(aabb.zw - aabb.xy, vec2<f32>(0.000001));
+    return mix(uv.xy, uv.zw, t);
+}
+
+// board layout space -> ui layout space -> screen px (with the UiTransform computed on the rust side) -> ndc.
+fn ui_layout_to_ndc(layout_pos: vec2<f32>) -> vec2<f32> {
+    let ui_pos = layout_pos * ui_push.viewport.z + ui_push.viewport.xy;
+    return ui_screen_to_ndc(ui_pos * screen.ui_scale + screen.ui_offset);
+}
+
+fn ui_screen_to_ndc(screen_pos: vec2<f32>) -> vec2<f32> {
+    return vec2<f32>((screen_pos.x / screen.width) * 2.0 - 1.0, 1.0 - (screen_pos.y / screen.height) * 2.0);
+}
 
 @group(1) @binding(0)
 var t_diffuse: texture_2d<f32>;
@@ -15,6 +43,8 @@ struct RectInstance {
     // border_width, border_softness, shadow_width, shadow_curve
     @location(4) others: vec4<f32>,
     @location(5) shadow_color: vec4<f32>,
+    @location(6) effect_params: vec4<f32>,
+    @location(7) effect: u32, // see ui_hooks.wgsl
 }
 
 struct RectVertexOutput {
@@ -27,6 +57,9 @@ struct RectVertexOutput {
     // border_width, border_softness, shadow_width, shadow_curve
     @location(5) others: vec4<f32>,
     @location(6) shadow_color: vec4<f32>,
+    @location(7) @interpolate(flat) effect_params: vec4<f32>,
+    @location(8) @interpolate(flat) effect: u32,
+    @location(9) layout_pos: vec2<f32>,
 };
 
 struct TexturedRectInstance {
@@ -37,8 +70,11 @@ struct TexturedRectInstance {
     // border_width, border_softness, shadow_width, shadow_curve
     @location(4) others: vec4<f32>,
     @location(5) shadow_color: vec4<f32>,
+    @location(6) effect_params: vec4<f32>,
+    @location(7) effect: u32,
     // for the texture
-    @location(6) uv: vec4<f32>,
+    @location(8) uv: vec4<f32>,
+    @location(9) tiling: vec2<f32>, // how often the uv region is repeated, (1,1) if not tiled
 }
 
 struct TexturedRectVertexOutput {
@@ -52,6 +88,48 @@ struct TexturedRectVertexOutput {
     @location(5) others: vec4<f32>,
     @location(6) shadow_color: vec4<f32>,
     @location(7) uv: vec2<f32>,
+    @location(8) @interpolate(flat) uv_region: vec4<f32>,
+    @location(9) @interpolate(flat) tiling: vec2<f32>,
+    @location(10) tile_uv: vec2<f32>, // goes from (0,0) to tiling over the rect
+    @location(11) @interpolate(flat) effect_params: vec4<f32>,
+    @location(12) @interpolate(flat) effect: u32,
+    @location(13) layout_pos: vec2<f32>,
+};
+
+struct GaugeRectInstance {
+    @location(0) aabb: vec4<f32>,
+    @location(1) color: vec4<f32>, // background behind the fill
+    @location(2) border_radius: vec4<f32>,
+    @location(3) border_color: vec4<f32>,
+    // border_width, border_softness, shadow_width, shadow_curve
+    @location(4) others: vec4<f32>,
+    @location(5) shadow_color: vec4<f32>,
+    @location(6) effect_params: vec4<f32>,
+    @location(7) effect: u32,
+    // for the fill
+    @location(8) fill_color: vec4<f32>,
+    @location(9) fill_end_color: vec4<f32>,
+    @location(10) fill: vec2<f32>, // fraction, inset
+    @location(11) direction: u32, // 0: left to right, 1: right to left, 2: top to bottom, 3: bottom to top
+}
+
+struct GaugeRectVertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) offset: vec2<f32>, // offset from center
+    @location(1) size: vec2<f32>,
+    @location(2) color: vec4<f32>,
+    @location(3) border_radius: vec4<f32>,
+    @location(4) border_color: vec4<f32>,
+    // border_width, border_softness, shadow_width, shadow_curve
+    @location(5) others: vec4<f32>,
+    @location(6) shadow_color: vec4<f32>,
+    @location(7) @interpolate(flat) effect_params: vec4<f32>,
+    @location(8) @interpolate(flat) effect: u32,
+    @location(9) layout_pos: vec2<f32>,
+    @location(10) @interpolate(flat) fill_color: vec4<f32>,
+    @location(11) @interpolate(flat) fill_end_color: vec4<f32>,
+    @location(12) @interpolate(flat) fill: vec2<f32>,
+    @location(13) @interpolate(flat) direction: u32,
 };
 
 struct AlphaSdfRectInstance {
@@ -69,6 +147,9 @@ struct GlyphInstance {
     @location(1) color: vec4<f32>,
     @location(2) uv: vec4<f32>,    // uv aabb in the texture atlas
     @location(3) shadow_intensity: f32,
+    @location(4) hinting: vec2<f32>, // snap_to_pixels (0 or 1), sharpness
+    @location(5) effect_params: vec4<f32>,
+    @location(6) effect: u32,
 }
 
 struct GlyphVertexOutput {
@@ -76,6 +157,11 @@ struct GlyphVertexOutput {
     @location(0) color: vec4<f32>,
     @location(1) uv: vec2<f32>,
     @location(2) shadow_intensity: f32,
+    @location(3) sharpness: f32,
+    @location(4) @interpolate(flat) effect_params: vec4<f32>,
+    @location(5) @interpolate(flat) effect: u32,
+    @location(6) layout_pos: vec2<f32>,
+    @location(7) local: vec2<f32>, // (0,0) at the top left to (1,1) at the bottom right of the glyph
 };
 
 // we calculate the vertices here in the shader instead of passing a vertex buffer
@@ -95,27 +181,29 @@ fn rect_vs(
     instance: RectInstance,
 ) -> RectVertexOutput {
     let vertex = pos_vertex_with_shadow(vertex_index, instance.aabb, instance.others[2]); // instance.others[2] is shadow width
-    // the vertex is in ui layout space, lets transform it into screen px space:
-    let screen_pos = vertex.pos * screen.height / UI_REFERENCE_Y_HEIGHT;
-    let device_pos = vec2<f32>((screen_pos.x / screen.width) * 2.0 - 1.0, 1.0 - (screen_pos.y / screen.height) * 2.0) ;
+    let pos = clip_pos(vertex.pos, ui_push.clip);
     let center = (instance.aabb.xy + instance.aabb.zw) * 0.5;
+    let device_pos = ui_layout_to_ndc(user_ui_effect_vertex(pos, center, instance.effect, instance.effect_params));
 
     var out: RectVertexOutput;
     out.clip_position = vec4<f32>(device_pos, 0.0, 1.0);
-    out.offset = vertex.pos - center;
+    out.offset = pos - center;
     out.size = instance.aabb.zw - instance.aabb.xy;
 
-    out.color = instance.color * push_color;
+    out.color = instance.color * ui_push.color;
     out.border_radius = instance.border_radius;
-    out.border_color = instance.border_color * push_color;
+    out.border_color = instance.border_color * ui_push.color;
     out.others = instance.others;
-    out.shadow_color = instance.shadow_color * push_color;
+    out.shadow_color = instance.shadow_color * ui_push.color;
+    out.effect_params = instance.effect_params;
+    out.effect = instance.effect;
+    out.layout_pos = pos;
     return out;
 }
  
 @fragment
 fn rect_fs(in: RectVertexOutput) -> @location(0) vec4<f32> {
-    let smoothness = 0.5; // half a pixel of antialiasing
+    let smoothness = in.others[1] * 0.5; // border_softness is the full width of the antialiased edge
 
     let sdf = rounded_box_sdf(in.offset, in.size, in.border_radius);
     let border_width = in.others[0];
@@ -130,10 +218,139 @@ fn rect_fs(in: RectVertexOutput) -> @location(0) vec4<f32> {
     let shadow_factor2 = smoothstep(0.0, 1.0, shadow_factor);
     let shadow_color = vec4(in.shadow_color.rgb, in.shadow_color.a * shadow_factor2);
     let color = mix(rect_color, shadow_color, inside_factor);
-    return color;
+    return rect_effect_color(color, in);
     // return vec4(rect_color.rgb, rect_color.a * inside_factor);
 }
 
+fn rect_effect_color(color: vec4<f32>, in: RectVertexOutput) -> vec4<f32> {
+    if in.effect == 0u {
+        return color;
+    }
+    let local = in.offset / in.size + 0.5;
+    return user_ui_effect_color(color, in.layout_pos, local, in.effect, in.effect_params);
+}
+
+// like rect_fs, but the inside of the rect shows the blurred scene (bound as t_diffuse) behind it,
+// with the rect color mixed on top according to its alpha.
+@fragment
+fn backdrop_rect_fs(in: RectVertexOutput) -> @location(0) vec4<f32> {
+    let smoothness = in.others[1] * 0.5;
+
+    let screen_uv = in.clip_position.xy / vec2(screen.width, screen.height);
+    let backdrop: vec3<f32> = textureSample(t_diffuse, s_diffuse, screen_uv).rgb;
+    let inner_color = vec4(mix(backdrop, in.color.rgb, in.color.a), 1.0);
+
+    let sdf = rounded_box_sdf(in.offset, in.size, in.border_radius);
+    let border_width = in.others[0];
+    let border_sdf = sdf + border_width;
+    let border_factor = smoothstep(0.0 - smoothness, 0.0 + smoothness, border_sdf);
+    let rect_color: vec4<f32> = mix(inner_color, in.border_color, border_factor);
+
+    let inside_factor = smoothstep(0.0 - smoothness, 0.0 + smoothness, sdf);
+
+    let shadow_width = in.others[2];
+    let shadow_factor = 1.0 - (sdf / shadow_width);
+    let shadow_factor2 = smoothstep(0.0, 1.0, shadow_factor);
+    let shadow_color = vec4(in.shadow_color.rgb, in.shadow_color.a * shadow_factor2);
+    return rect_effect_color(mix(rect_color, shadow_color, inside_factor), in);
+}
+
+@vertex
+fn gauge_rect_vs(
+    @builtin(vertex_index) vertex_index: u32,
+    instance: GaugeRectInstance,
+) -> GaugeRectVertexOutput {
+    let vertex = pos_vertex_with_shadow(vertex_index, instance.aabb, instance.others[2]);
+    let pos = clip_pos(vertex.pos, ui_push.clip);
+    let center = (instance.aabb.xy + instance.aabb.zw) * 0.5;
+    let device_pos = ui_layout_to_ndc(user_ui_effect_vertex(pos, center, instance.effect, instance.effect_params));
+
+    var out: GaugeRectVertexOutput;
+    out.clip_position = vec4<f32>(device_pos, 0.0, 1.0);
+    out.offset = pos - center;
+    out.size = instance.aabb.zw - instance.aabb.xy;
+
+    out.color = instance.color * ui_push.color;
+    out.border_radius = instance.border_radius;
+    out.border_color = instance.border_color * ui_push.color;
+    out.others = instance.others;
+    out.shadow_color = instance.shadow_color * ui_push.color;
+    out.effect_params = instance.effect_params;
+    out.effect = instance.effect;
+    out.layout_pos = pos;
+    out.fill_color = instance.fill_color * ui_push.color;
+    out.fill_end_color = instance.fill_end_color * ui_push.color;
+    out.fill = instance.fill;
+    out.direction = instance.direction;
+    return out;
+}
+
+// like rect_fs, but the inside of the rect shows the fill of the gauge on top of the background color.
+@fragment
+fn gauge_rect_fs(in: GaugeRectVertexOutput) -> @location(0) vec4<f32> {
+    let smoothness = in.others[1] * 0.5;
+    let border_width = in.others[0];
+
+    let inner_color = blend_over(gauge_fill_color(in, smoothness), in.color);
+
+    let sdf = rounded_box_sdf(in.offset, in.size, in.border_radius);
+    let border_sdf = sdf + border_width;
+    let border_factor = smoothstep(0.0 - smoothness, 0.0 + smoothness, border_sdf);
+    let rect_color: vec4<f32> = mix(inner_color, in.border_color, border_factor);
+
+    let inside_factor = smoothstep(0.0 - smoothness, 0.0 + smoothness, sdf);
+
+    let shadow_width = in.others[2];
+    let shadow_factor = 1.0 - (sdf / shadow_width);
+    let shadow_factor2 = smoothstep(0.0, 1.0, shadow_factor);
+    let shadow_color = vec4(in.shadow_color.rgb, in.shadow_color.a * shadow_factor2);
+    let color = mix(rect_color, shadow_color, inside_factor);
+    if in.effect == 0u {
+        return color;
+    }
+    let local = in.offset / in.size + 0.5;
+    return user_ui_effect_color(color, in.layout_pos, local, in.effect, in.effect_params);
+}
+
+// The fill is a rounded box inside of the border and inset, shortened along the direction of the gauge.
+// Its corners follow the border radius, so the end of the fill stays round while it shrinks.
+fn gauge_fill_color(in: GaugeRectVertexOutput, smoothness: f32) -> vec4<f32> {
+    let fraction = clamp(in.fill.x, 0.0, 1.0);
+    if fraction <= 0.0 {
+        return vec4(0.0);
+    }
+    let shrink = in.others[0] + in.fill.y; // border width + inset
+    let half_size = max(in.size * 0.5 - shrink, vec2(0.0));
+    let radius = max(in.border_radius - shrink, vec4(0.0));
+
+    var dir: vec2<f32>;
+    switch in.direction {
+        case 1u: { dir = vec2(-1.0, 0.0); }
+        case 2u: { dir = vec2(0.0, 1.0); }
+        case 3u: { dir = vec2(0.0, -1.0); }
+        default: { dir = vec2(1.0, 0.0); }
+    }
+    let axis = abs(dir);
+    let fill_half_size = mix(half_size, half_size * fraction, axis);
+    let fill_center = -dir * half_size * (1.0 - fraction);
+    let fill_sdf = rounded_box_sdf(in.offset - fill_center, fill_half_size * 2.0, min(radius, vec4(min(fill_half_size.x, fill_half_size.y))));
+    let fill_factor = 1.0 - smoothstep(0.0 - smoothness, 0.0 + smoothness, fill_sdf);
+
+    // the gradient goes over the full gauge, so the color at the end of the fill shows the fraction.
+    let t = clamp(dot(in.offset, dir) / max(dot(half_size, axis), 0.0001) * 0.5 + 0.5, 0.0, 1.0);
+    let fill_color = mix(in.fill_color, in.fill_end_color, t);
+    return vec4(fill_color.rgb, fill_color.a * fill_factor);
+}
+
+// `top` composited over `bottom`, both with straight alpha.
+fn blend_over(top: vec4<f32>, bottom: vec4<f32>) -> vec4<f32> {
+    let alpha = top.a + bottom.a * (1.0 - top.a);
+    if alpha <= 0.0 {
+        return vec4(bottom.rgb, 0.0);
+    }
+    let rgb = (top.rgb * top.a + bottom.rgb * bottom.a * (1.0 - top.a)) / alpha;
+    return vec4(rgb, alpha);
+}
 
 @vertex
 fn textured_rect_vs(
@@ -141,31 +358,53 @@ fn textured_rect_vs(
     instance: TexturedRectInstance,
 ) -> TexturedRectVertexOutput {
     let vertex = pos_uv_vertex(vertex_index, instance.aabb, instance.uv);
-    let screen_pos = vertex.pos * screen.height / UI_REFERENCE_Y_HEIGHT; // pos on actual screen.
-    let device_pos = vec2<f32>((screen_pos.x / screen.width) * 2.0 - 1.0, 1.0 - (screen_pos.y / screen.height) * 2.0) ;
+    let pos = clip_pos(vertex.pos, ui_push.clip);
     let center = (instance.aabb.xy + instance.aabb.zw) * 0.5;
+    let device_pos = ui_layout_to_ndc(user_ui_effect_vertex(pos, center, instance.effect, instance.effect_params));
 
     var out: TexturedRectVertexOutput;
     out.clip_position = vec4<f32>(device_pos, 0.0, 1.0);
-    out.offset = vertex.pos - center;
+    out.offset = pos - center;
     out.size = instance.aabb.zw - instance.aabb.xy;
 
-    out.color = instance.color * push_color;
+    out.color = instance.color * ui_push.color;
     out.border_radius = instance.border_radius;
-    out.border_color = instance.border_color * push_color;
+    out.border_color = instance.border_color * ui_push.color;
     out.others = instance.others;
-    out.shadow_color = instance.shadow_color * push_color;
-    out.uv = vertex.uv;
+    out.shadow_color = instance.shadow_color * ui_push.color;
+    out.uv = clip_uv(pos, instance.aabb, instance.uv);
+    out.uv_region = instance.uv;
+    out.tiling = instance.tiling;
+    out.tile_uv = clip_uv(pos, instance.aabb, vec4(0.0, 0.0, instance.tiling));
+    out.effect_params = instance.effect_params;
+    out.effect = instance.effect;
+    out.layout_pos = pos;
     return out;
 }
 
 @fragment
 fn textured_rect_fs(in: TexturedRectVertexOutput) -> @location(0) vec4<f32> {
     let sdf = rounded_box_sdf(in.offset, in.size, in.border_radius);
-    let image_color: vec4<f32> = textureSample(t_diffuse, s_diffuse, in.uv);
+    let image_color: vec4<f32> = sample_tiled(in);
     let color: vec4<f32> = mix(image_color, in.border_color, smoothstep(0.0, 1.0, ((sdf + in.others[0]) / in.others[1]) ));
     // todo! add borders and other fancy stuff from above in rect_fs
-    return color  * in.color;
+    if in.effect == 0u {
+        return color * in.color;
+    }
+    let local = in.offset / in.size + 0.5;
+    return user_ui_effect_color(color * in.color, in.layout_pos, local, in.effect, in.effect_params);
+}
+
+// samples the texture at the position in the current tile, mapped into the uv region.
+// For untiled rects (tiling = (1,1)) this is the same as sampling at `in.uv`.
+fn sample_tiled(in: TexturedRectVertexOutput) -> vec4<f32> {
+    // clamp the tile index, such that the last tile ends at exactly 1.0 instead of wrapping around to 0.0
+    let tile_idx = min(floor(in.tile_uv), ceil(in.tiling) - 1.0);
+    let local_uv = in.tile_uv - tile_idx;
+    let region_size = in.uv_region.zw - in.uv_region.xy;
+    let uv = in.uv_region.xy + local_uv * region_size;
+    // use gradients of the continuous tile_uv, to avoid mip artifacts at the tile seams:
+    return textureSampleGrad(t_diffuse, s_diffuse, uv, dpdx(in.tile_uv) * region_size, dpdy(in.tile_uv) * region_size);
 }
 
 @vertex
@@ -174,16 +413,16 @@ fn alpha_sdf_rect_vs(
     instance: AlphaSdfRectInstance,
 ) -> AlphaSdfVertexOutput {
     let vertex = pos_uv_vertex(vertex_index, instance.aabb, instance.uv);
-    let screen_pos = vertex.pos * screen.height / UI_REFERENCE_Y_HEIGHT; // pos on actual screen.
-    let device_pos = vec2<f32>((screen_pos.x / screen.width) * 2.0 - 1.0, 1.0 - (screen_pos.y / screen.height) * 2.0) ;
+    let pos = clip_pos(vertex.pos, ui_push.clip);
+    let device_pos = ui_layout_to_ndc(pos);
 
     var out: AlphaSdfVertexOutput;
     out.clip_position = vec4<f32>(device_pos, 0.0, 1.0);
 
-    out.color = instance.color * push_color;
-    out.border_color = instance.border_color * push_color;
+    out.color = instance.color * ui_push.color;
+    out.border_color = instance.border_color * ui_push.color;
     out.params = instance.params;
-    out.uv = vertex.uv;
+    out.uv = clip_uv(pos, instance.aabb, instance.uv);
     return out;
 }
 
@@ -195,16 +434,28 @@ fn glyph_vs(
     instance: GlyphInstance,
 ) -> GlyphVertexOutput {
     let vertex = pos_uv_vertex(vertex_index, instance.aabb, instance.uv);
+    let pos = clip_pos(vertex.pos, ui_push.clip);
+    let center = (instance.aabb.xy + instance.aabb.zw) * 0.5;
+    let layout_pos = user_ui_effect_vertex(pos, center, instance.effect, instance.effect_params);
    
-    let scale_factor = screen.height / UI_REFERENCE_Y_HEIGHT;
-    let screen_pos = vertex.pos * scale_factor;
-    let device_pos = vec2<f32>((screen_pos.x / screen.width) * 2.0 - 1.0, 1.0 - (screen_pos.y / screen.height) * 2.0) ;
+    var screen_pos = layout_pos * screen.ui_scale + screen.ui_offset;
+    if instance.hinting.x > 0.5 {
+        // move the whole quad, such that its top left corner is on a whole pixel of the screen.
+        let origin = instance.aabb.xy * screen.ui_scale + screen.ui_offset;
+        screen_pos += round(origin) - origin;
+    }
+    let device_pos = ui_screen_to_ndc(screen_pos);
 
     var out: GlyphVertexOutput;
     out.clip_position = vec4<f32>(device_pos, 0.0, 1.0);
-    out.color = instance.color * push_color;
-    out.uv = vertex.uv; 
-    out.shadow_intensity = instance.shadow_intensity * push_color.a;
+    out.color = instance.color * ui_push.color;
+    out.uv = clip_uv(pos, instance.aabb, instance.uv); 
+    out.shadow_intensity = instance.shadow_intensity * ui_push.color.a;
+    out.sharpness = instance.hinting.y;
+    out.effect_params = instance.effect_params;
+    out.effect = instance.effect;
+    out.layout_pos = pos;
+    out.local = clip_uv(pos, instance.aabb, vec4(0.0, 0.0, 1.0, 1.0));
     return out;
 }
 
@@ -228,13 +479,18 @@ fn glyph_fs(in: GlyphVertexOutput) -> @location(0) vec4<f32> {
     var sz : vec2<u32> = textureDimensions(t_diffuse, 0);
     var dx : f32 = dpdx(in.uv.x) * f32(sz.x);
     var dy : f32 = dpdy(in.uv.y) * f32(sz.y);
-    var to_pixels : f32 = 32.0 * inverseSqrt(dx * dx + dy * dy);
+    var to_pixels : f32 = 32.0 * inverseSqrt(dx * dx + dy * dy) * in.sharpness;
     let inside_factor = clamp((sdf - 0.5) * to_pixels + 0.5, 0.0, 1.0);
     
     // smoothstep(0.5 - smoothing, 0.5 + smoothing, sample);
     let shadow_alpha = (1.0 - (pow(1.0 - sdf, 2.0)) )* in.shadow_intensity * in.color.a;
-    let shadow_color = vec4(0.0,0.0,0.0, shadow_alpha);
-    let color = mix(shadow_color, in.color, inside_factor);
+    var text_color = in.color;
+    if in.effect != 0u {
+        text_color = user_ui_effect_color(in.color, in.layout_pos, in.local, in.effect, in.effect_params);
+    }
+    // the shadow fades with the text, e.g. when it dissolves.
+    let shadow_color = vec4(0.0, 0.0, 0.0, shadow_alpha * text_color.a / max(in.color.a, 0.0001));
+    let color = mix(shadow_color, text_color, inside_factor);
     return color; // * vec4(1.0,1.0,1.0,5.0);
 }
 
@@ -1,34 +1,334 @@
 
+use crate::camera3d::{ProjectionKind, Ray};
 use crate::{
-    make_shader_source, rgba_bind_group_layout_cached, Color, HotReload, RenderFormat,
-    ShaderCache, ShaderSource, ToRaw, Transform, TransformRaw, Uniforms, VertexT, VertsLayout,
+    make_shader_source, renderer::ui_screen::DEFAULT_UI_SHADER_HOOKS,
+    rgba_bind_group_layout_cached, Camera3d, Color, HotReload, Input, MouseButtonState, RenderFormat,
+    ScreenTextures, ShaderCache, ShaderFile, ShaderSource, Time, ToRaw, Transform, TransformRaw,
+    Uniforms, VertexT, VertsLayout,
 };
 use crate::{
     ui::{
         batching::{
-            AlphaSdfRectRaw, Batch, BatchKind, ElementBatchesGR, GlyphRaw, RectRaw, TexturedRectRaw,
+            AlphaSdfRectRaw, Batch, BatchKind, ElementBatchesGR, GaugeRectRaw, GlyphRaw, RectRaw,
+            TexturedRectRaw,
         },
-        Board,
+        Board, ElementBox, ElementContext,
     },
 };
+use glam::{dvec2, vec3, DVec2, Mat4, Vec2, Vec3};
 
 use wgpu::{RenderPipelineDescriptor, TextureView, VertexState};
 
+/// How many ui units make up one world unit for a [`Board3d`] with a transform scale of 1 (see `ui_3d.wgsl`).
+pub const UI_UNITS_PER_WORLD_UNIT: f32 = 100.0;
+
 #[derive(Debug)]
 pub struct Board3d {
+    /// Position and orientation of the board. If `scaling` is not [`Board3dScaling::Manual`], the scale is ignored
+    /// and if `billboard` is not [`Billboard::None`], the rotation is ignored when rendering with [`Ui3DRenderer::render_board_for_camera`].
     pub transform: Transform,
     pub board: Board,
     pub render_order_z_offset: f32,
     pub batches_gr: ElementBatchesGR,
     pub color: Color,
+    pub scaling: Board3dScaling,
+    /// Rotate the board to always face the camera, e.g. for labels and health bars above units.
+    pub billboard: Billboard,
+    /// Fade the board out with distance to the camera.
+    pub fade: Option<DistanceFade>,
+    /// Point of the board (0..1 in both directions, (0,0) is the top left) that is placed at `transform.position`.
+    /// Use (0.5, 0.5) to center the board around its position.
+    pub pivot: Vec2,
+    /// How the board interacts with the depth buffer of the scene.
+    pub depth: Board3dDepth,
+}
+
+/// How a [`Board3d`] is turned towards the camera.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Billboard {
+    /// Uses the rotation of the boards transform.
+    #[default]
+    None,
+    /// Faces the camera completely, like a sprite.
+    Spherical,
+    /// Only turns around the y axis, so the board stays upright when looking down at it.
+    Cylindrical,
+}
+
+/// Depth test and depth write of a [`Board3d`]. Only has an effect if the [`Ui3DRenderer`] was created with a
+/// [`RenderFormat`] that has a depth format and the render pass has the scene depth buffer attached,
+/// e.g. when rendering in the hdr pass or in a pass from [`Ui3DRenderer::new_render_pass_with_depth`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Board3dDepth {
+    /// Drawn on top of all geometry, even through walls.
+    Overlay,
+    /// Occluded by geometry in front of the board, but does not occlude anything itself.
+    #[default]
+    Test,
+    /// Occluded by geometry and occludes everything rendered after it. Meant for opaque panels,
+    /// because transparent parts of the board (e.g. around glyphs) also write depth.
+    TestAndWrite,
+}
+
+impl Board3dDepth {
+    const ALL: [Board3dDepth; 3] = [
+        Board3dDepth::Overlay,
+        Board3dDepth::Test,
+        Board3dDepth::TestAndWrite,
+    ];
+
+    fn depth_stencil_state(&self, format: wgpu::TextureFormat) -> wgpu::DepthStencilState {
+        // LessEqual, such that elements of a board drawn later are still drawn on top of earlier ones at the same depth.
+        let (depth_write_enabled, depth_compare) = match self {
+            Board3dDepth::Overlay => (false, wgpu::CompareFunction::Always),
+            Board3dDepth::Test => (false, wgpu::CompareFunction::LessEqual),
+            Board3dDepth::TestAndWrite => (true, wgpu::CompareFunction::LessEqual),
+        };
+        wgpu::DepthStencilState {
+            format,
+            depth_write_enabled,
+            depth_compare,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }
+    }
+}
+
+/// How large the ui of a [`Board3d`] appears in the world.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Board3dScaling {
+    /// Uses the scale of the boards transform: 100 ui units are 1 world unit at scale 1.
+    #[default]
+    Manual,
+    /// 1 ui unit is this size in world units.
+    WorldSize(f32),
+    /// 1 ui unit is this many pixels on screen, regardless of the distance to the camera.
+    /// Useful for labels that should stay readable (and crisp) at any distance.
+    ScreenPixels(f32),
+}
+
+/// Fades out a [`Board3d`] between `start` and `end` distance to the camera.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DistanceFade {
+    pub start: f32,
+    pub end: f32,
+}
+
+impl DistanceFade {
+    pub fn new(start: f32, end: f32) -> Self {
+        Self { start, end }
+    }
+
+    /// 1.0 when closer than `start`, 0.0 when further away than `end`.
+    pub fn factor(&self, distance: f32) -> f32 {
+        if distance <= self.start {
+            return 1.0;
+        }
+        if distance >= self.end {
+            return 0.0;
+        }
+        1.0 - (distance - self.start) / (self.end - self.start)
+    }
+}
+
+impl Board3d {
+    pub fn new(board: Board, transform: Transform, device: &wgpu::Device) -> Self {
+        let batches_gr = ElementBatchesGR::new(&board.batches, device);
+        Board3d {
+            transform,
+            board,
+            render_order_z_offset: 0.0,
+            batches_gr,
+            color: Color::WHITE,
+            scaling: Board3dScaling::Manual,
+            billboard: Billboard::None,
+            fade: None,
+            pivot: Vec2::ZERO,
+            depth: Board3dDepth::default(),
+        }
+    }
+
+    /// Transform used for rendering the board with this camera, with scaling, billboarding and the pivot applied.
+    pub fn transform_for_camera(&self, camera: &Camera3d) -> Transform {
+        let mut transform = self.transform;
+        match self.billboard {
+            Billboard::None => {}
+            Billboard::Spherical => {
+                transform.rotation = camera.transform.rotation_facing_camera();
+            }
+            Billboard::Cylindrical => {
+                transform.rotation = camera.transform.rotation_facing_camera_upright();
+            }
+        }
+        match self.scaling {
+            Board3dScaling::Manual => {}
+            Board3dScaling::WorldSize(world_size) => {
+                transform.scale = Vec3::splat(scale_for_world_size(world_size));
+            }
+            Board3dScaling::ScreenPixels(pixels) => {
+                let depth =
+                    (transform.position - camera.transform.pos).dot(camera.transform.direction());
+                transform.scale = Vec3::splat(scale_for_screen_pixels(camera, pixels, depth));
+            }
+        }
+        if self.pivot != Vec2::ZERO {
+            let size = self.board.size.as_vec2() * self.pivot / UI_UNITS_PER_WORLD_UNIT;
+            // the ui y axis points down, the world y axis up:
+            let offset = vec3(-size.x, size.y, 0.0);
+            transform.position += transform.rotation * (transform.scale * offset);
+        }
+        transform
+    }
+
+    /// Color of the board, with the distance fade applied.
+    pub fn color_for_camera(&self, camera: &Camera3d) -> Color {
+        let Some(fade) = self.fade else {
+            return self.color;
+        };
+        let distance = self.transform.position.distance(camera.transform.pos);
+        let mut color = self.color;
+        color.a *= fade.factor(distance);
+        color
+    }
+
+    /// Where the `ray` hits the board as it is rendered for the `camera`, None if it misses the board.
+    pub fn raycast(&self, camera: &Camera3d, ray: &Ray) -> Option<Board3dHit> {
+        let transform = self.transform_for_camera(camera);
+        let (layout_pos, distance) = ray_to_layout_pos(&transform, ray)?;
+        let size = self.board.size;
+        let inside = layout_pos.x >= 0.0
+            && layout_pos.y >= 0.0
+            && layout_pos.x <= size.x
+            && layout_pos.y <= size.y;
+        inside.then_some(Board3dHit {
+            layout_pos,
+            distance,
+        })
+    }
+
+    /// Where the cursor at `cursor_pos` (in physical pixels, like [`Input::cursor_pos`]) points at the board.
+    pub fn cursor_hit(&self, camera: &Camera3d, cursor_pos: Vec2) -> Option<Board3dHit> {
+        self.raycast(camera, &camera.ray_from_screen_pos(cursor_pos))
+    }
+
+    /// Starts the frame of the boards [`crate::ui::ElementContext`] with the cursor at the `hit`. Nothing is hovered
+    /// if it is None, but buttons pressed on the board still get released. `delta` is the frame time in seconds.
+    pub fn start_frame(&mut self, hit: Option<Board3dHit>, mouse: MouseButtonState, delta: f32) {
+        let cursor_pos = hit.map(|hit| hit.layout_pos).unwrap_or(DVec2::INFINITY);
+        self.board.ctx.start_frame(cursor_pos, mouse, delta);
+    }
+
+    /// Like [`Board::update`] for a board in the world: hover and clicks come from the cursor ray of the `camera`.
+    /// Returns where the cursor hit the board.
+    ///
+    /// If multiple boards can overlap on screen, get all their [`Board3d::cursor_hit`]s first and call
+    /// [`Board3d::start_frame`] with the closest hit for its board and None for the others. Scene geometry in
+    /// front of the board does not block the ray, check the distance against your own raycasts for that.
+    pub fn update(
+        &mut self,
+        input: &Input,
+        time: &Time,
+        camera: &Camera3d,
+        build: impl FnOnce(&mut ElementContext) -> ElementBox,
+    ) -> Option<Board3dHit> {
+        let hit = self.cursor_hit(camera, input.cursor_pos());
+        self.start_frame(hit, input.mouse_buttons(), time.delta().as_secs_f32());
+        self.board.build(build);
+        hit
+    }
+}
+
+/// Where a ray hits a [`Board3d`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Board3dHit {
+    /// Position on the board in layout units, (0,0) is the top left corner.
+    pub layout_pos: DVec2,
+    /// Distance along the ray in world units.
+    pub distance: f32,
+}
+
+/// Where the `ray` hits the plane of a board rendered with `transform`, in layout units, and the distance along the ray.
+fn ray_to_layout_pos(transform: &Transform, ray: &Ray) -> Option<(DVec2, f32)> {
+    let normal = transform.rotation * Vec3::Z;
+    let distance = ray.intersect_plane(transform.position, normal)?;
+    let model = Mat4::from_scale_rotation_translation(
+        transform.scale,
+        transform.rotation,
+        transform.position,
+    );
+    let local = model.inverse().transform_point3(ray.get_point(distance));
+    // inverse of the vertex shaders in `ui_3d.wgsl`: world = model * (x / 100, -y / 100, 0)
+    let layout_pos = dvec2(local.x as f64, -local.y as f64) * UI_UNITS_PER_WORLD_UNIT as f64;
+    Some((layout_pos, distance))
+}
+
+/// Transform scale of a [`Board3d`], such that 1 ui unit has a size of `world_size` in world space.
+pub fn scale_for_world_size(world_size: f32) -> f32 {
+    world_size * UI_UNITS_PER_WORLD_UNIT
+}
+
+/// Transform scale of a [`Board3d`], such that 1 ui unit is `pixels` px tall on screen,
+/// if the board is `depth` world units in front of the camera (distance along the view direction).
+pub fn scale_for_screen_pixels(camera: &Camera3d, pixels: f32, depth: f32) -> f32 {
+    let projection = &camera.projection;
+    let visible_world_height = match projection.kind {
+        ProjectionKind::Perspective { fov_y_radians } => {
+            2.0 * depth.max(projection.znear) * (fov_y_radians * 0.5).tan()
+        }
+        ProjectionKind::Orthographic { y_height } => y_height,
+    };
+    let world_size = pixels * visible_world_height / projection.height as f32;
+    scale_for_world_size(world_size)
 }
 
 pub struct Ui3DRenderer {
+    /// one set of pipelines for each [`Board3dDepth`], in the order of [`Board3dDepth::ALL`].
+    pipelines: [Pipelines; 3],
+    render_format: RenderFormat,
+    shader_source: ShaderSource,
+}
+
+struct Pipelines {
     rect_pipeline: wgpu::RenderPipeline,
     textured_rect_pipeline: wgpu::RenderPipeline,
+    gauge_rect_pipeline: wgpu::RenderPipeline,
     alpha_sdf_rect_pipeline: wgpu::RenderPipeline,
     glyph_pipeline: wgpu::RenderPipeline,
-    render_format: RenderFormat,
+}
+
+impl Pipelines {
+    fn new(
+        shader: &wgpu::ShaderModule,
+        device: &wgpu::Device,
+        render_format: RenderFormat,
+        depth: Board3dDepth,
+    ) -> Self {
+        Pipelines {
+            rect_pipeline: create_rect_pipeline(shader, device, render_format, depth),
+            textured_rect_pipeline: create_textured_rect_pipeline(
+                shader,
+                device,
+                render_format,
+                depth,
+            ),
+            gauge_rect_pipeline: create_gauge_rect_pipeline(shader, device, render_format, depth),
+            alpha_sdf_rect_pipeline: create_alpha_sdf_rect_pipeline(
+                shader,
+                device,
+                render_format,
+                depth,
+            ),
+            glyph_pipeline: create_glyph_pipeline(shader, device, render_format, depth),
+        }
+    }
+
+    fn all(
+        shader: &wgpu::ShaderModule,
+        device: &wgpu::Device,
+        render_format: RenderFormat,
+    ) -> [Pipelines; 3] {
+        Board3dDepth::ALL.map(|depth| Pipelines::new(shader, device, render_format, depth))
+    }
 }
 
 const SHADER_SOURCE: ShaderSource =
@@ -46,24 +346,31 @@ impl Ui3DRenderer {
         render_format: RenderFormat,
         shader_cache: &mut ShaderCache,
     ) -> Self {
-        let shader = shader_cache.register(SHADER_SOURCE, device);
-
-        let glyph_pipeline = create_glyph_pipeline(&shader, device, render_format);
-        let rect_pipeline = create_rect_pipeline(&shader, device, render_format);
-        let textured_rect_pipeline = create_textured_rect_pipeline(&shader, device, render_format);
-
-        let alpha_sdf_rect_pipeline =
-            create_alpha_sdf_rect_pipeline(&shader, device, render_format);
+        Self::with_shader_hooks(device, render_format, shader_cache, DEFAULT_UI_SHADER_HOOKS)
+    }
 
+    /// Like [`crate::renderer::ui_screen::UiScreenRenderer::with_shader_hooks`], the same hooks can be used for both.
+    pub fn with_shader_hooks(
+        device: &wgpu::Device,
+        render_format: RenderFormat,
+        shader_cache: &mut ShaderCache,
+        shader_hooks: ShaderFile,
+    ) -> Self {
+        let shader_source = SHADER_SOURCE.with_user_chunk(shader_hooks);
+        let shader = shader_cache.register(shader_source, device);
+        let pipelines = Pipelines::all(&shader, device, render_format);
         Ui3DRenderer {
-            rect_pipeline,
-            textured_rect_pipeline,
-            glyph_pipeline,
+            pipelines,
             render_format,
-            alpha_sdf_rect_pipeline,
+            shader_source,
         }
     }
 
+    fn pipelines(&self, depth: Board3dDepth) -> &Pipelines {
+        let index = Board3dDepth::ALL.iter().position(|d| *d == depth).unwrap();
+        &self.pipelines[index]
+    }
+
     pub fn new_render_pass<'a>(
         &'a self,
         encoder: &'a mut wgpu::CommandEncoder,
@@ -86,6 +393,23 @@ impl Ui3DRenderer {
         render_pass
     }
 
+    /// Render pass that draws into the hdr target of `screen_textures` (after the scene was rendered into it)
+    /// and has the scene depth buffer attached, such that boards can be occluded by geometry, see [`Board3dDepth`].
+    /// The renderer needs to be created with the same [`RenderFormat`] as the screen textures.
+    pub fn new_render_pass_with_depth<'a>(
+        &'a self,
+        encoder: &'a mut wgpu::CommandEncoder,
+        screen_textures: &'a ScreenTextures,
+    ) -> wgpu::RenderPass<'a> {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Ui 3d Render Pass"),
+            color_attachments: &[Some(screen_textures.color_attachment(wgpu::LoadOp::Load))],
+            depth_stencil_attachment: screen_textures.depth_attachment(wgpu::LoadOp::Load),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        })
+    }
+
     pub fn begin_render_pass<'a>(
         &'a self,
         encoder: &'a mut wgpu::CommandEncoder,
@@ -120,10 +444,36 @@ impl Ui3DRenderer {
             &board.board.batches.batches,
             &board.transform,
             board.color,
+            board.depth,
+            uniforms,
+        )
+    }
+
+    /// Like [`Ui3DRenderer::render_board`], but applies the scaling, billboarding and fading of the board for the camera.
+    /// The camera should be the one that the `uniforms` were prepared with.
+    pub fn render_board_for_camera<'a>(
+        &'a self,
+        pass: &mut wgpu::RenderPass<'a>,
+        board: &'a Board3d,
+        camera: &Camera3d,
+        uniforms: &'a Uniforms,
+    ) {
+        let color = board.color_for_camera(camera);
+        if color.a <= 0.0 {
+            return;
+        }
+        self.render_batches(
+            pass,
+            &board.batches_gr,
+            &board.board.batches.batches,
+            &board.transform_for_camera(camera),
+            color,
+            board.depth,
             uniforms,
         )
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn render_batches<'a>(
         &'a self,
         pass: &mut wgpu::RenderPass<'a>,
@@ -131,20 +481,25 @@ impl Ui3DRenderer {
         batches: &'a Vec<Batch>,
         transform: &Transform,
         color: Color,
+        depth: Board3dDepth,
         uniforms: &'a Uniforms,
     ) {
         pass.set_bind_group(0, uniforms.bind_group(), &[]);
+        let pipelines = self.pipelines(depth);
 
         const VERTEX_COUNT: u32 = 4;
-        let push_constants = PushConstants {
+        let mut push_constants = PushConstants {
             transform: transform.to_raw(),
             color,
+            clip: [0.0; 4],
         };
         for batch in batches.iter() {
             let range = batch.range.start as u32..batch.range.end as u32;
+            push_constants.clip = batch.clip_push();
             match &batch.kind {
-                BatchKind::Rect => {
-                    pass.set_pipeline(&self.rect_pipeline);
+                // there is no backdrop to blur for boards in 3d space, so these are just normal rects.
+                BatchKind::Rect | BatchKind::BackdropRect => {
+                    pass.set_pipeline(&pipelines.rect_pipeline);
                     // set the instance buffer (no vertex buffer used, vertex positions computed from instances)
                     pass.set_vertex_buffer(0, buffers.rects.buffer().slice(..));
                     // todo!() maybe not set entire buffer and then adjust the instance indexes that are drawn???
@@ -156,8 +511,8 @@ impl Ui3DRenderer {
                     pass.draw(0..VERTEX_COUNT, range);
                 }
                 BatchKind::TexturedRect(texture) => {
-                    pass.set_bind_group(1, &texture.bind_group, &[]);
-                    pass.set_pipeline(&self.textured_rect_pipeline);
+                    pass.set_bind_group(1, texture.bind_group(), &[]);
+                    pass.set_pipeline(&pipelines.textured_rect_pipeline);
                     pass.set_vertex_buffer(0, buffers.textured_rects.buffer().slice(..));
                     pass.set_push_constants(
                         wgpu::ShaderStages::VERTEX,
@@ -166,9 +521,19 @@ impl Ui3DRenderer {
                     );
                     pass.draw(0..VERTEX_COUNT, range);
                 }
+                BatchKind::GaugeRect => {
+                    pass.set_pipeline(&pipelines.gauge_rect_pipeline);
+                    pass.set_vertex_buffer(0, buffers.gauge_rects.buffer().slice(..));
+                    pass.set_push_constants(
+                        wgpu::ShaderStages::VERTEX,
+                        0,
+                        bytemuck::cast_slice(&[push_constants]),
+                    );
+                    pass.draw(0..VERTEX_COUNT, range);
+                }
                 BatchKind::AlphaSdfRect(texture) => {
-                    pass.set_bind_group(1, &texture.bind_group, &[]);
-                    pass.set_pipeline(&self.alpha_sdf_rect_pipeline);
+                    pass.set_bind_group(1, texture.bind_group(), &[]);
+                    pass.set_pipeline(&pipelines.alpha_sdf_rect_pipeline);
                     pass.set_vertex_buffer(0, buffers.alpha_sdf_rects.buffer().slice(..));
                     pass.set_push_constants(
                         wgpu::ShaderStages::VERTEX,
@@ -177,9 +542,11 @@ impl Ui3DRenderer {
                     );
                     pass.draw(0..VERTEX_COUNT, range);
                 }
-                BatchKind::Glyph(text) => {
-                    pass.set_bind_group(1, &text.atlas_texture().bind_group, &[]);
-                    pass.set_pipeline(&self.glyph_pipeline);
+                // custom primitives are only rendered in screen space, see `crate::ui::custom_primitive`.
+                BatchKind::Custom(_) => {}
+                BatchKind::Glyph(text, page) => {
+                    pass.set_bind_group(1, &text.page_texture(*page).bind_group, &[]);
+                    pass.set_pipeline(&pipelines.glyph_pipeline);
                     pass.set_vertex_buffer(0, buffers.glyphs.buffer().slice(..));
                     pass.set_push_constants(
                         wgpu::ShaderStages::VERTEX,
@@ -195,16 +562,11 @@ impl Ui3DRenderer {
 
 impl HotReload for Ui3DRenderer {
     fn source(&self) -> ShaderSource {
-        SHADER_SOURCE
+        self.shader_source
     }
 
     fn hot_reload(&mut self, shader: &wgpu::ShaderModule, device: &wgpu::Device) {
-        let render_format = self.render_format;
-        self.glyph_pipeline = create_glyph_pipeline(&shader, device, render_format);
-        self.rect_pipeline = create_rect_pipeline(&shader, device, render_format);
-        self.textured_rect_pipeline = create_textured_rect_pipeline(&shader, device, render_format);
-        self.alpha_sdf_rect_pipeline =
-            create_alpha_sdf_rect_pipeline(&shader, device, render_format);
+        self.pipelines = Pipelines::all(shader, device, self.render_format);
         println!("Hot reloaded Ui 3d Shader");
     }
 }
@@ -212,8 +574,8 @@ impl HotReload for Ui3DRenderer {
 fn create_rect_pipeline(
     shader_module: &wgpu::ShaderModule,
     device: &wgpu::Device,
-
     render_format: RenderFormat,
+    depth: Board3dDepth,
 ) -> wgpu::RenderPipeline {
     create_pipeline::<RectRaw>(
         shader_module,
@@ -222,14 +584,15 @@ fn create_rect_pipeline(
         device,
         &[Uniforms::cached_layout()],
         render_format,
+        depth,
     )
 }
 
 fn create_textured_rect_pipeline(
     shader_module: &wgpu::ShaderModule,
     device: &wgpu::Device,
-
     render_format: RenderFormat,
+    depth: Board3dDepth,
 ) -> wgpu::RenderPipeline {
     create_pipeline::<TexturedRectRaw>(
         shader_module,
@@ -241,14 +604,32 @@ fn create_textured_rect_pipeline(
             rgba_bind_group_layout_cached(device),
         ],
         render_format,
+        depth,
     )
 }
 
-fn create_alpha_sdf_rect_pipeline(
+fn create_gauge_rect_pipeline(
     shader_module: &wgpu::ShaderModule,
     device: &wgpu::Device,
+    render_format: RenderFormat,
+    depth: Board3dDepth,
+) -> wgpu::RenderPipeline {
+    create_pipeline::<GaugeRectRaw>(
+        shader_module,
+        "gauge_rect_vs_3d",
+        "gauge_rect_fs",
+        device,
+        &[Uniforms::cached_layout()],
+        render_format,
+        depth,
+    )
+}
 
+fn create_alpha_sdf_rect_pipeline(
+    shader_module: &wgpu::ShaderModule,
+    device: &wgpu::Device,
     render_format: RenderFormat,
+    depth: Board3dDepth,
 ) -> wgpu::RenderPipeline {
     create_pipeline::<AlphaSdfRectRaw>(
         shader_module,
@@ -260,14 +641,15 @@ fn create_alpha_sdf_rect_pipeline(
             rgba_bind_group_layout_cached(device),
         ],
         render_format,
+        depth,
     )
 }
 
 fn create_glyph_pipeline(
     shader_module: &wgpu::ShaderModule,
     device: &wgpu::Device,
-
     render_format: RenderFormat,
+    depth: Board3dDepth,
 ) -> wgpu::RenderPipeline {
     create_pipeline::<GlyphRaw>(
         shader_module,
@@ -279,6 +661,7 @@ fn create_glyph_pipeline(
             rgba_bind_group_layout_cached(device),
         ],
         render_format,
+        depth,
     )
 }
 
@@ -288,6 +671,8 @@ struct PushConstants {
     transform: TransformRaw,
     /// Note: alpha of color used as transparency
     color: Color,
+    /// clip rect of the current batch, see [`Batch::clip`].
+    clip: [f32; 4],
 }
 
 pub fn create_pipeline<Instance: VertexT>(
@@ -297,6 +682,7 @@ pub fn create_pipeline<Instance: VertexT>(
     device: &wgpu::Device,
     bind_group_layouts: &[&wgpu::BindGroupLayout],
     render_format: RenderFormat,
+    depth: Board3dDepth,
 ) -> wgpu::RenderPipeline {
     let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
         label: Some(std::any::type_name::<Instance>()),
@@ -335,13 +721,9 @@ pub fn create_pipeline<Instance: VertexT>(
             polygon_mode: wgpu::PolygonMode::Fill,
             conservative: false,
         },
-        depth_stencil: render_format.depth.map(|format| wgpu::DepthStencilState {
-            format,
-            depth_write_enabled: false,
-            depth_compare: wgpu::CompareFunction::Less,
-            stencil: wgpu::StencilState::default(),
-            bias: wgpu::DepthBiasState::default(),
-        }),
+        depth_stencil: render_format
+            .depth
+            .map(|format| depth.depth_stencil_state(format)),
         multisample: wgpu::MultisampleState {
             alpha_to_coverage_enabled: false,
             count: render_format.msaa_sample_count,
@@ -351,3 +733,32 @@ pub fn create_pipeline<Instance: VertexT>(
     });
     pipeline
 }
+
+#[cfg(test)]
+mod tests {
+    use glam::{dvec2, vec3};
+
+    use super::ray_to_layout_pos;
+    use crate::{camera3d::Ray, Transform};
+
+    #[test]
+    fn rays_hit_boards_in_layout_space() {
+        let transform = Transform::new(0.0, 0.0, -5.0).with_scale(2.0);
+        let ray = Ray {
+            origin: vec3(1.0, -0.5, 0.0),
+            direction: vec3(0.0, 0.0, -1.0),
+        };
+        let (layout_pos, distance) = ray_to_layout_pos(&transform, &ray).unwrap();
+        assert!(
+            layout_pos.distance(dvec2(50.0, 25.0)) < 1e-4,
+            "{layout_pos}"
+        );
+        assert!((distance - 5.0).abs() < 1e-5);
+
+        let parallel = Ray {
+            origin: vec3(0.0, 0.0, 0.0),
+            direction: vec3(1.0, 0.0, 0.0),
+        };
+        assert!(ray_to_layout_pos(&transform, &parallel).is_none());
+    }
+}
@@ -4,6 +4,8 @@ struct PushData {
    col3: vec4<f32>,
    translation: vec4<f32>,
    color: vec4<f32>,
+   // clip rect of the batch in layout space, see clip_pos in ui.wgsl.
+   clip: vec4<f32>,
 }
 var<push_constant> data: PushData;
 
@@ -13,7 +15,10 @@ fn rect_vs_3d(
     instance: RectInstance,
 ) -> RectVertexOutput {
     let vertex = pos_vertex_with_shadow(vertex_index, instance.aabb, instance.others[2]); // instance.others[2] is shadow width
-    let xy_plane_offset = vec2<f32>(vertex.pos.x / 100.0, -vertex.pos.y / 100.0);
+    let pos = clip_pos(vertex.pos, data.clip);
+    let center = (instance.aabb.xy + instance.aabb.zw) * 0.5;
+    let moved = user_ui_effect_vertex(pos, center, instance.effect, instance.effect_params);
+    let xy_plane_offset = vec2<f32>(moved.x / 100.0, -moved.y / 100.0);
     let model_matrix = mat4x4<f32>(
         data.col1,
         data.col2,
@@ -25,8 +30,7 @@ fn rect_vs_3d(
     var out: RectVertexOutput;
     out.clip_position = camera.view_proj * model_matrix * world_position;
 
-    let center = (instance.aabb.xy + instance.aabb.zw) * 0.5;
-    out.offset = vertex.pos - center;
+    out.offset = pos - center;
 
     out.size = instance.aabb.zw - instance.aabb.xy;
     out.color = instance.color * data.color; // (apply push constants color)
@@ -34,6 +38,9 @@ fn rect_vs_3d(
     out.border_color = instance.border_color * data.color; // (apply push constants color) 
     out.others = instance.others;
     out.shadow_color = instance.shadow_color * data.color; // (apply push constants color)
+    out.effect_params = instance.effect_params;
+    out.effect = instance.effect;
+    out.layout_pos = pos;
     return out;
 }
 
@@ -43,7 +50,10 @@ fn textured_rect_vs_3d(
     instance: TexturedRectInstance,
 ) -> TexturedRectVertexOutput {
     let vertex = pos_uv_vertex(vertex_index, instance.aabb, instance.uv);
-    let xy_plane_offset = vec2<f32>(vertex.pos.x / 100.0, -vertex.pos.y / 100.0);
+    let pos = clip_pos(vertex.pos, data.clip);
+    let center = (instance.aabb.xy + instance.aabb.zw) * 0.5;
+    let moved = user_ui_effect_vertex(pos, center, instance.effect, instance.effect_params);
+    let xy_plane_offset = vec2<f32>(moved.x / 100.0, -moved.y / 100.0);
     let model_matrix = mat4x4<f32>(
         data.col1,
         data.col2,
@@ -55,8 +65,7 @@ fn textured_rect_vs_3d(
     var out: TexturedRectVertexOutput;
     out.clip_position = camera.view_proj * model_matrix * world_position;
     
-    let center = (instance.aabb.xy + instance.aabb.zw) * 0.5;
-    out.offset = vertex.pos - center;
+    out.offset = pos - center;
 
     out.size = instance.aabb.zw - instance.aabb.xy;
     out.color = instance.color * data.color; // (apply push constants color)
@@ -64,10 +73,54 @@ fn textured_rect_vs_3d(
     out.border_color = instance.border_color * data.color; // (apply push constants color)
     out.others = instance.others;
     out.shadow_color = instance.shadow_color * data.color; // (apply push constants color)
-    out.uv = vertex.uv;
+    out.uv = clip_uv(pos, instance.aabb, instance.uv);
+    out.uv_region = instance.uv;
+    out.tiling = instance.tiling;
+    out.tile_uv = clip_uv(pos, instance.aabb, vec4(0.0, 0.0, instance.tiling));
+    out.effect_params = instance.effect_params;
+    out.effect = instance.effect;
+    out.layout_pos = pos;
     return out;
 }
 
+@vertex
+fn gauge_rect_vs_3d(
+    @builtin(vertex_index) vertex_index: u32,
+    instance: GaugeRectInstance,
+) -> GaugeRectVertexOutput {
+    let vertex = pos_vertex_with_shadow(vertex_index, instance.aabb, instance.others[2]);
+    let pos = clip_pos(vertex.pos, data.clip);
+    let center = (instance.aabb.xy + instance.aabb.zw) * 0.5;
+    let moved = user_ui_effect_vertex(pos, center, instance.effect, instance.effect_params);
+    let xy_plane_offset = vec2<f32>(moved.x / 100.0, -moved.y / 100.0);
+    let model_matrix = mat4x4<f32>(
+        data.col1,
+        data.col2,
+        data.col3,
+        data.translation,
+    );
+    let world_position = vec4<f32>(xy_plane_offset, 0.0, 1.0);
+
+    var out: GaugeRectVertexOutput;
+    out.clip_position = camera.view_proj * model_matrix * world_position;
+
+    out.offset = pos - center;
+
+    out.size = instance.aabb.zw - instance.aabb.xy;
+    out.color = instance.color * data.color; // (apply push constants color)
+    out.border_radius = instance.border_radius;
+    out.border_color = instance.border_color * data.color; // (apply push constants color)
+    out.others = instance.others;
+    out.shadow_color = instance.shadow_color * data.color; // (apply push constants color)
+    out.effect_params = instance.effect_params;
+    out.effect = instance.effect;
+    out.layout_pos = pos;
+    out.fill_color = instance.fill_color * data.color; // (apply push constants color)
+    out.fill_end_color = instance.fill_end_color * data.color; // (apply push constants color)
+    out.fill = instance.fill;
+    out.direction = instance.direction;
+    return out;
+}
 
 
 @vertex
@@ -76,7 +129,8 @@ fn alpha_sdf_rect_vs_3d(
     instance: AlphaSdfRectInstance,
 ) -> AlphaSdfVertexOutput {
     let vertex = pos_uv_vertex(vertex_index, instance.aabb, instance.uv);
-    let xy_plane_offset = vec2<f32>(vertex.pos.x / 100.0, -vertex.pos.y / 100.0);
+    let pos = clip_pos(vertex.pos, data.clip);
+    let xy_plane_offset = vec2<f32>(pos.x / 100.0, -pos.y / 100.0);
     let model_matrix = mat4x4<f32>(
         data.col1,
         data.col2,
@@ -90,7 +144,7 @@ fn alpha_sdf_rect_vs_3d(
     out.color = instance.color * data.color;
     out.border_color = instance.border_color * data.color;
     out.params = instance.params;
-    out.uv = vertex.uv;
+    out.uv = clip_uv(pos, instance.aabb, instance.uv);
     return out;
 }
 
@@ -101,7 +155,10 @@ fn glyph_vs_3d(
     instance: GlyphInstance,
 ) -> GlyphVertexOutput {
     let vertex = pos_uv_vertex(vertex_index, instance.aabb, instance.uv);
-    let xy_plane_offset = vec2<f32>(vertex.pos.x / 100.0, -vertex.pos.y / 100.0);
+    let pos = clip_pos(vertex.pos, data.clip);
+    let center = (instance.aabb.xy + instance.aabb.zw) * 0.5;
+    let moved = user_ui_effect_vertex(pos, center, instance.effect, instance.effect_params);
+    let xy_plane_offset = vec2<f32>(moved.x / 100.0, -moved.y / 100.0);
     let model_matrix = mat4x4<f32>(
         data.col1,
         data.col2,
@@ -114,8 +171,14 @@ fn glyph_vs_3d(
     out.clip_position = camera.view_proj * model_matrix * world_position;
 
     out.color = instance.color * data.color; // (apply push constants color)
-    out.uv = vertex.uv; 
+    out.uv = clip_uv(pos, instance.aabb, instance.uv); 
     out.shadow_intensity = instance.shadow_intensity * data.color.a;
+    // snapping to pixels has no meaning for boards in 3d space.
+    out.sharpness = instance.hinting.y;
+    out.effect_params = instance.effect_params;
+    out.effect = instance.effect;
+    out.layout_pos = pos;
+    out.local = clip_uv(pos, instance.aabb, vec4(0.0, 0.0, 1.0, 1.0));
     return out;
 }
 
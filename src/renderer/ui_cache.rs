@@ -0,0 +1,188 @@
+use winit::dpi::PhysicalSize;
+
+use crate::{
+    make_shader_source,
+    renderer::ui_screen::UiScreenRenderer,
+    rgba_bind_group_layout_cached,
+    ui::{batching::ElementBatchesGR, Board},
+    uniforms::Uniforms,
+    Color, HotReload, RenderTarget, Resizable, ShaderCache, ShaderSource,
+};
+
+const SHADER_SOURCE: ShaderSource = make_shader_source!("screen.wgsl", "ui_cache.wgsl");
+
+/// Keeps a rendered [`Board`] in a texture and renders it again only when the board changed
+/// (see [`Board::content_version`]), for mostly static ui like in tools and editors.
+/// Every frame the texture is just drawn on top of the frame.
+///
+/// Layout, batching and interaction still happen every frame with [`Board::set_element`], only the gpu work is saved:
+///
+/// ```rust,ignore
+/// let mut ui_cache = UiCache::new(&device, &mut shader_cache, &ui_renderer, surface_format, window_size);
+/// // every frame:
+/// board.set_element(element);
+/// if ui_cache.is_outdated(&board) {
+///     ui_gr.prepare(&board.batches, &device, &queue);
+/// }
+/// ui_cache.update(&mut encoder, &ui_renderer, &board, &ui_gr, &uniforms);
+/// ui_cache.composite(&mut encoder, &surface_view);
+/// ```
+///
+/// Divs with `backdrop_blur` are rendered as normal rects, because the scene behind them changes every frame.
+pub struct UiCache {
+    target: RenderTarget,
+    rendered_version: Option<u64>,
+    pipeline: wgpu::RenderPipeline,
+    output_format: wgpu::TextureFormat,
+}
+
+impl UiCache {
+    /// The texture has the render format of the `ui_renderer`, `output_format` is the format of the views
+    /// passed to [`UiCache::composite`], e.g. the surface format.
+    pub fn new(
+        device: &wgpu::Device,
+        shader_cache: &mut ShaderCache,
+        ui_renderer: &UiScreenRenderer,
+        output_format: wgpu::TextureFormat,
+        size: PhysicalSize<u32>,
+    ) -> Self {
+        let shader = shader_cache.register(SHADER_SOURCE, device);
+        let pipeline = create_pipeline(&shader, device, output_format);
+        UiCache {
+            target: RenderTarget::new(
+                device,
+                size.width,
+                size.height,
+                ui_renderer.render_format(),
+                "ui cache",
+            ),
+            rendered_version: None,
+            pipeline,
+            output_format,
+        }
+    }
+
+    /// True if the texture does not show the current state of the `board`.
+    pub fn is_outdated(&self, board: &Board) -> bool {
+        self.rendered_version != Some(board.content_version())
+    }
+
+    /// Renders the board again on the next [`UiCache::update`], e.g. after the ui shaders were hot reloaded.
+    pub fn invalidate(&mut self) {
+        self.rendered_version = None;
+    }
+
+    /// Renders the `board` into the texture if it is outdated and returns whether it did.
+    /// The `buffers` need to be prepared with the current batches of the board.
+    pub fn update(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        ui_renderer: &UiScreenRenderer,
+        board: &Board,
+        buffers: &ElementBatchesGR,
+        uniforms: &Uniforms,
+    ) -> bool {
+        if !self.is_outdated(board) {
+            return false;
+        }
+        let mut pass = self.target.begin_pass(encoder, Color::TRANSPARENT);
+        ui_renderer.render_batches(
+            &mut pass,
+            buffers,
+            &board.batches.batches,
+            uniforms,
+            Color::WHITE,
+        );
+        drop(pass);
+        self.rendered_version = Some(board.content_version());
+        true
+    }
+
+    /// Draws the cached ui on top of what is in the `view`.
+    pub fn composite(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Ui cache composite"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            occlusion_query_set: None,
+            timestamp_writes: None,
+        });
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, self.target.resolved().bind_group(), &[]);
+        pass.draw(0..3, 0..1);
+    }
+
+    /// The cached ui with premultiplied alpha.
+    pub fn target(&self) -> &RenderTarget {
+        &self.target
+    }
+}
+
+impl Resizable for UiCache {
+    fn on_resize(&mut self, size: PhysicalSize<u32>, device: &wgpu::Device) {
+        if size != self.target.size() {
+            self.target.resize(device, size);
+            self.invalidate();
+        }
+    }
+}
+
+impl HotReload for UiCache {
+    fn source(&self) -> ShaderSource {
+        SHADER_SOURCE
+    }
+
+    fn hot_reload(&mut self, shader: &wgpu::ShaderModule, device: &wgpu::Device) {
+        self.pipeline = create_pipeline(shader, device, self.output_format);
+    }
+}
+
+fn create_pipeline(
+    shader: &wgpu::ShaderModule,
+    device: &wgpu::Device,
+    output_format: wgpu::TextureFormat,
+) -> wgpu::RenderPipeline {
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: None,
+        bind_group_layouts: &[rgba_bind_group_layout_cached(device)],
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("Ui cache composite"),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format: output_format,
+                blend: Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: Some(wgpu::Face::Back),
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
@@ -0,0 +1,13 @@
+@group(0)
+@binding(0)
+var ui_texture: texture_2d<f32>;
+
+@group(0)
+@binding(1)
+var ui_sampler: sampler;
+
+// the cached ui has premultiplied alpha, because the ui pipelines blend into a transparent target.
+@fragment
+fn fs_main(vs: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(ui_texture, ui_sampler, vs.uv);
+}
@@ -0,0 +1,61 @@
+// Default effect hooks called by ui.wgsl for divs and text sections with a UiEffect.
+// To add effects, copy this file, add cases for your own effect ids (from UiEffect::FIRST_CUSTOM_ID on)
+// and pass it as `shader_hooks` when creating the UiScreenRenderer or Ui3DRenderer.
+// Animations can use `time.total` from the uniforms.
+
+// Moves a vertex of a rect or glyph, in layout space. `center` is the center of the rect or glyph,
+// so moving all vertices by an offset that only depends on the center keeps the shape intact.
+fn user_ui_effect_vertex(pos: vec2<f32>, center: vec2<f32>, effect: u32, params: vec4<f32>) -> vec2<f32> {
+    switch effect {
+        // wave: params = (amplitude, wavelength, speed, _)
+        case 1u: {
+            let phase = center.x / max(params.y, 0.0001) - time.total * params.z;
+            return pos + vec2(0.0, params.x * sin(phase * 6.2831853));
+        }
+        default: {
+            return pos;
+        }
+    }
+}
+
+// Changes the color of a fragment. `pos` is in layout space, `local` goes from (0,0) at the top left
+// to (1,1) at the bottom right of the rect or glyph.
+fn user_ui_effect_color(color: vec4<f32>, pos: vec2<f32>, local: vec2<f32>, effect: u32, params: vec4<f32>) -> vec4<f32> {
+    switch effect {
+        // rainbow: params = (wavelength, speed, _, _), multiplies the color, so black stays black.
+        case 2u: {
+            let hue = fract(pos.x / max(params.x, 0.0001) - time.total * params.y);
+            let rainbow = clamp(abs(fract(hue + vec3(0.0, 2.0 / 3.0, 1.0 / 3.0)) * 6.0 - 3.0) - 1.0, vec3(0.0), vec3(1.0));
+            return vec4(color.rgb * rainbow, color.a);
+        }
+        // dissolve: params = (progress, noise_scale, edge_width, _), progress goes from 0 (visible) to 1 (gone).
+        case 3u: {
+            let noise = ui_value_noise(pos / max(params.y, 0.0001));
+            let edge = noise - params.x;
+            if edge < 0.0 {
+                return vec4(color.rgb, 0.0);
+            }
+            let glow = 1.0 - smoothstep(0.0, max(params.z, 0.0001), edge);
+            return vec4(color.rgb + glow * vec3(1.0, 0.5, 0.1), color.a);
+        }
+        default: {
+            return color;
+        }
+    }
+}
+
+fn ui_hash(p: vec2<f32>) -> f32 {
+    return fract(sin(dot(p, vec2(127.1, 311.7))) * 43758.5453);
+}
+
+// smooth noise in 0..1 with features of size 1.
+fn ui_value_noise(p: vec2<f32>) -> f32 {
+    let i = floor(p);
+    let f = fract(p);
+    let u = f * f * (3.0 - 2.0 * f);
+    let a = ui_hash(i);
+    let b = ui_hash(i + vec2(1.0, 0.0));
+    let c = ui_hash(i + vec2(0.0, 1.0));
+    let d = ui_hash(i + vec2(1.0, 1.0));
+    return mix(mix(a, b, u.x), mix(c, d, u.x), u.y);
+}
@@ -1,23 +1,54 @@
+use std::any::TypeId;
+
 use crate::{
-    make_shader_source, rgba_bind_group_layout_cached, uniforms::Uniforms, Color, HotReload,
-    RenderFormat, ShaderCache, ShaderSource, VertexT, VertsLayout,
+    make_shader_file, make_shader_source, rgba_bind_group_layout_cached, uniforms::Uniforms, Color,
+    HotReload, RenderFormat, Screen, ShaderCache, ShaderFile, ShaderSource, UiViewport, VertexT,
+    VertsLayout,
 };
 
 use wgpu::{PushConstantRange, RenderPipelineDescriptor, ShaderStages, TextureView, VertexState};
 
-use crate::ui::batching::{
-    AlphaSdfRectRaw, Batch, BatchKind, ElementBatchesGR, GlyphRaw, RectRaw, TexturedRectRaw,
+use crate::ui::{
+    batching::{
+        AlphaSdfRectRaw, Batch, BatchKind, ElementBatchesGR, GaugeRectRaw, GlyphRaw, RectRaw,
+        TexturedRectRaw,
+    },
+    CustomPrimitive,
 };
 
 const SHADER_SOURCE: ShaderSource =
     make_shader_source!("uniforms.wgsl", "ui.wgsl", "alpha_sdf.wgsl");
+/// The effect hooks of [`crate::ui::UiEffect`] used if no other hooks are given, see `ui_hooks.wgsl`.
+/// Also used by [`crate::renderer::ui_3d::Ui3DRenderer`].
+pub const DEFAULT_UI_SHADER_HOOKS: ShaderFile = make_shader_file!("ui_hooks.wgsl");
 
 pub struct UiScreenRenderer {
     rect_pipeline: wgpu::RenderPipeline,
+    backdrop_rect_pipeline: wgpu::RenderPipeline,
     textured_rect_pipeline: wgpu::RenderPipeline,
+    gauge_rect_pipeline: wgpu::RenderPipeline,
     alpha_sdf_rect_pipeline: wgpu::RenderPipeline,
     glyph_pipeline: wgpu::RenderPipeline,
+    custom_pipelines: Vec<CustomPipeline>,
     render_format: RenderFormat,
+    shader_source: ShaderSource,
+}
+
+/// `UiPush` in `ui.wgsl`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct UiPush {
+    color: Color,
+    /// offset and scale of the [`UiViewport`], `[0.0, 0.0, 1.0, 0.0]` for boards on the whole screen.
+    viewport: [f32; 4],
+    /// clip rect of the current batch, see [`Batch::clip`].
+    clip: [f32; 4],
+}
+
+/// Pipeline of a [`CustomPrimitive`] type, see [`UiScreenRenderer::register_custom_primitive`].
+struct CustomPipeline {
+    primitive: TypeId,
+    pipeline: wgpu::RenderPipeline,
 }
 
 impl UiScreenRenderer {
@@ -27,22 +58,56 @@ impl UiScreenRenderer {
         shader_cache: &mut ShaderCache,
         render_format: RenderFormat,
     ) -> Self {
-        let shader = shader_cache.register(SHADER_SOURCE, device);
+        Self::with_shader_hooks(device, shader_cache, render_format, DEFAULT_UI_SHADER_HOOKS)
+    }
+
+    /// `shader_hooks` defines the effect functions called by `ui.wgsl`, like `ui_hooks.wgsl` does,
+    /// for custom [`crate::ui::UiEffect`]s.
+    pub fn with_shader_hooks(
+        device: &wgpu::Device,
+        shader_cache: &mut ShaderCache,
+        render_format: RenderFormat,
+        shader_hooks: ShaderFile,
+    ) -> Self {
+        let shader_source = SHADER_SOURCE.with_user_chunk(shader_hooks);
+        let shader = shader_cache.register(shader_source, device);
         let glyph_pipeline = create_glyph_pipeline(&shader, device, render_format);
         let rect_pipeline = create_rect_pipeline(&shader, device, render_format);
+        let backdrop_rect_pipeline = create_backdrop_rect_pipeline(&shader, device, render_format);
         let textured_rect_pipeline = create_textured_rect_pipeline(&shader, device, render_format);
+        let gauge_rect_pipeline = create_gauge_rect_pipeline(&shader, device, render_format);
         let alpha_sdf_rect_pipeline =
             create_alpha_sdf_rect_pipeline(&shader, device, render_format);
 
         UiScreenRenderer {
             rect_pipeline,
+            backdrop_rect_pipeline,
             textured_rect_pipeline,
+            gauge_rect_pipeline,
             alpha_sdf_rect_pipeline,
             glyph_pipeline,
+            custom_pipelines: vec![],
             render_format,
+            shader_source,
         }
     }
 
+    /// Creates the pipeline of a [`CustomPrimitive`] type, which is needed to render batches of it.
+    /// Registering a type again recreates its pipeline, e.g. after changing its shader.
+    pub fn register_custom_primitive<P: CustomPrimitive>(&mut self, device: &wgpu::Device) {
+        let primitive = TypeId::of::<P>();
+        let pipeline = P::create_pipeline(device, self.render_format);
+        self.custom_pipelines.retain(|p| p.primitive != primitive);
+        self.custom_pipelines.push(CustomPipeline {
+            primitive,
+            pipeline,
+        });
+    }
+
+    pub fn render_format(&self) -> RenderFormat {
+        self.render_format
+    }
+
     pub fn render_in_new_pass<'a>(
         &'a self,
         encoder: &'a mut wgpu::CommandEncoder,
@@ -85,6 +150,69 @@ impl UiScreenRenderer {
         batches: &'a Vec<Batch>,
         uniforms: &'a Uniforms,
         color: Color,
+    ) {
+        self.render_batches_with_backdrop(pass, buffers, batches, uniforms, color, None);
+    }
+
+    /// The `backdrop` is the bind group of a blurred version of the scene, e.g. [`crate::BackdropBlur::bind_group`].
+    /// It is sampled by all divs with `backdrop_blur` set. If `None`, these divs are rendered as normal rects.
+    pub fn render_batches_with_backdrop<'a>(
+        &'a self,
+        pass: &mut wgpu::RenderPass<'a>,
+        buffers: &'a ElementBatchesGR,
+        batches: &'a [Batch],
+        uniforms: &'a Uniforms,
+        color: Color,
+        backdrop: Option<&'a wgpu::BindGroup>,
+    ) {
+        let push = UiPush {
+            color,
+            viewport: [0.0, 0.0, 1.0, 0.0],
+            clip: [0.0; 4],
+        };
+        self.render_batches_with_push(pass, buffers, batches, uniforms, push, backdrop);
+    }
+
+    /// Renders a board that was laid out with [`crate::ui::Board::update_in_viewport`] into the `viewport`,
+    /// clipped to its clip rect. The `screen` gives the transform of the surrounding screen ui and the size of
+    /// the render target. Resets the scissor rect to the whole screen afterwards.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_batches_in_viewport<'a>(
+        &'a self,
+        pass: &mut wgpu::RenderPass<'a>,
+        buffers: &'a ElementBatchesGR,
+        batches: &'a [Batch],
+        uniforms: &'a Uniforms,
+        color: Color,
+        viewport: &UiViewport,
+        screen: &Screen,
+    ) {
+        let scissor = viewport.scissor_rect(&screen.ui_transform(), screen.size());
+        if let Some([x, y, width, height]) = scissor {
+            if width == 0 || height == 0 {
+                return;
+            }
+            pass.set_scissor_rect(x, y, width, height);
+        }
+        let push = UiPush {
+            color,
+            viewport: viewport.to_push(),
+            clip: [0.0; 4],
+        };
+        self.render_batches_with_push(pass, buffers, batches, uniforms, push, None);
+        if scissor.is_some() {
+            pass.set_scissor_rect(0, 0, screen.width, screen.height);
+        }
+    }
+
+    fn render_batches_with_push<'a>(
+        &'a self,
+        pass: &mut wgpu::RenderPass<'a>,
+        buffers: &'a ElementBatchesGR,
+        batches: &'a [Batch],
+        uniforms: &'a Uniforms,
+        mut push: UiPush,
+        backdrop: Option<&'a wgpu::BindGroup>,
     ) {
         if batches.is_empty() {
             return;
@@ -96,67 +224,93 @@ impl UiScreenRenderer {
 
         for batch in batches.iter() {
             let range = batch.range.start as u32..batch.range.end as u32;
+            push.clip = batch.clip_push();
 
             match &batch.kind {
                 BatchKind::Rect => {
                     pass.set_pipeline(&self.rect_pipeline);
-                    pass.set_push_constants(
-                        ShaderStages::VERTEX,
-                        0,
-                        bytemuck::cast_slice(&[color]),
-                    );
+                    pass.set_push_constants(ShaderStages::VERTEX, 0, bytemuck::bytes_of(&push));
                     // set the instance buffer (no vertex buffer used, vertex positions computed from instances)
                     pass.set_vertex_buffer(0, buffers.rects.buffer().slice(..));
                     // todo!() maybe not set entire buffer and then adjust the instance indexes that are drawn???
                     pass.draw(0..VERTEX_COUNT, range);
                 }
+                BatchKind::BackdropRect => {
+                    if let Some(backdrop) = backdrop {
+                        pass.set_bind_group(1, backdrop, &[]);
+                        pass.set_pipeline(&self.backdrop_rect_pipeline);
+                    } else {
+                        pass.set_pipeline(&self.rect_pipeline);
+                    }
+                    pass.set_push_constants(ShaderStages::VERTEX, 0, bytemuck::bytes_of(&push));
+                    pass.set_vertex_buffer(0, buffers.rects.buffer().slice(..));
+                    pass.draw(0..VERTEX_COUNT, range);
+                }
                 BatchKind::TexturedRect(texture) => {
-                    pass.set_bind_group(1, &texture.bind_group, &[]);
+                    pass.set_bind_group(1, texture.bind_group(), &[]);
                     pass.set_pipeline(&self.textured_rect_pipeline);
-                    pass.set_push_constants(
-                        ShaderStages::VERTEX,
-                        0,
-                        bytemuck::cast_slice(&[color]),
-                    );
+                    pass.set_push_constants(ShaderStages::VERTEX, 0, bytemuck::bytes_of(&push));
                     pass.set_vertex_buffer(0, buffers.textured_rects.buffer().slice(..));
                     pass.draw(0..VERTEX_COUNT, range);
                 }
+                BatchKind::GaugeRect => {
+                    pass.set_pipeline(&self.gauge_rect_pipeline);
+                    pass.set_push_constants(ShaderStages::VERTEX, 0, bytemuck::bytes_of(&push));
+                    pass.set_vertex_buffer(0, buffers.gauge_rects.buffer().slice(..));
+                    pass.draw(0..VERTEX_COUNT, range);
+                }
                 BatchKind::AlphaSdfRect(texture) => {
-                    pass.set_bind_group(1, &texture.bind_group, &[]);
+                    pass.set_bind_group(1, texture.bind_group(), &[]);
                     pass.set_pipeline(&self.alpha_sdf_rect_pipeline);
-                    pass.set_push_constants(
-                        ShaderStages::VERTEX,
-                        0,
-                        bytemuck::cast_slice(&[color]),
-                    );
+                    pass.set_push_constants(ShaderStages::VERTEX, 0, bytemuck::bytes_of(&push));
                     pass.set_vertex_buffer(0, buffers.alpha_sdf_rects.buffer().slice(..));
                     pass.draw(0..VERTEX_COUNT, range);
                 }
-                BatchKind::Glyph(text) => {
-                    pass.set_bind_group(1, &text.atlas_texture().bind_group, &[]);
+                BatchKind::Glyph(text, page) => {
+                    pass.set_bind_group(1, &text.page_texture(*page).bind_group, &[]);
                     pass.set_pipeline(&self.glyph_pipeline);
-                    pass.set_push_constants(
-                        ShaderStages::VERTEX,
-                        0,
-                        bytemuck::cast_slice(&[color]),
-                    );
+                    pass.set_push_constants(ShaderStages::VERTEX, 0, bytemuck::bytes_of(&push));
                     pass.set_vertex_buffer(0, buffers.glyphs.buffer().slice(..));
                     pass.draw(0..VERTEX_COUNT, range);
                 }
+                BatchKind::Custom(primitive) => {
+                    let Some(custom) = self
+                        .custom_pipelines
+                        .iter()
+                        .find(|p| p.primitive == primitive.type_id())
+                    else {
+                        panic!(
+                            "custom ui primitive {} is not registered, see UiScreenRenderer::register_custom_primitive",
+                            primitive.type_name()
+                        );
+                    };
+                    // no buffer if the batches were never prepared.
+                    let Some(instances) = buffers.custom_buffer(primitive.type_id()) else {
+                        continue;
+                    };
+                    pass.set_pipeline(&custom.pipeline);
+                    primitive.set_bind_groups(pass);
+                    pass.set_push_constants(ShaderStages::VERTEX, 0, bytemuck::bytes_of(&push));
+                    pass.set_vertex_buffer(0, instances.buffer().slice(..));
+                    pass.draw(0..VERTEX_COUNT, range);
+                }
             }
         }
     }
 }
 impl HotReload for UiScreenRenderer {
     fn source(&self) -> ShaderSource {
-        SHADER_SOURCE
+        self.shader_source
     }
 
     fn hot_reload(&mut self, shader: &wgpu::ShaderModule, device: &wgpu::Device) {
         self.glyph_pipeline = create_glyph_pipeline(&shader, device, self.render_format);
         self.rect_pipeline = create_rect_pipeline(&shader, device, self.render_format);
+        self.backdrop_rect_pipeline =
+            create_backdrop_rect_pipeline(shader, device, self.render_format);
         self.textured_rect_pipeline =
             create_textured_rect_pipeline(&shader, device, self.render_format);
+        self.gauge_rect_pipeline = create_gauge_rect_pipeline(shader, device, self.render_format);
         self.alpha_sdf_rect_pipeline =
             create_alpha_sdf_rect_pipeline(&shader, device, self.render_format);
     }
@@ -177,6 +331,24 @@ fn create_rect_pipeline(
     )
 }
 
+fn create_backdrop_rect_pipeline(
+    shader_module: &wgpu::ShaderModule,
+    device: &wgpu::Device,
+    render_format: RenderFormat,
+) -> wgpu::RenderPipeline {
+    create_pipeline::<RectRaw>(
+        shader_module,
+        "rect_vs",
+        "backdrop_rect_fs",
+        device,
+        &[
+            Uniforms::cached_layout(),
+            rgba_bind_group_layout_cached(device),
+        ],
+        render_format,
+    )
+}
+
 fn create_textured_rect_pipeline(
     shader_module: &wgpu::ShaderModule,
     device: &wgpu::Device,
@@ -195,6 +367,21 @@ fn create_textured_rect_pipeline(
     )
 }
 
+fn create_gauge_rect_pipeline(
+    shader_module: &wgpu::ShaderModule,
+    device: &wgpu::Device,
+    render_format: RenderFormat,
+) -> wgpu::RenderPipeline {
+    create_pipeline::<GaugeRectRaw>(
+        shader_module,
+        "gauge_rect_vs",
+        "gauge_rect_fs",
+        device,
+        &[Uniforms::cached_layout()],
+        render_format,
+    )
+}
+
 fn create_alpha_sdf_rect_pipeline(
     shader_module: &wgpu::ShaderModule,
     device: &wgpu::Device,
@@ -244,7 +431,7 @@ pub fn create_pipeline<Instance: VertexT>(
         bind_group_layouts,
         push_constant_ranges: &[wgpu::PushConstantRange {
             stages: ShaderStages::VERTEX,
-            range: 0..std::mem::size_of::<Color>() as u32,
+            range: 0..std::mem::size_of::<UiPush>() as u32,
         }],
     });
 
@@ -276,10 +463,18 @@ pub fn create_pipeline<Instance: VertexT>(
             polygon_mode: wgpu::PolygonMode::Fill,
             conservative: false,
         },
-        depth_stencil: None,
+        // screen space ui is drawn on top of everything, but needs to be compatible with the depth buffer,
+        // if rendered into the hdr pass of the 3d scene (see `UiCompositing::PreToneMapping`).
+        depth_stencil: render_format.depth.map(|format| wgpu::DepthStencilState {
+            format,
+            depth_write_enabled: false,
+            depth_compare: wgpu::CompareFunction::Always,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
         multisample: wgpu::MultisampleState {
             alpha_to_coverage_enabled: false,
-            count: 1,
+            count: render_format.msaa_sample_count,
             mask: !0,
         },
         multiview: None,
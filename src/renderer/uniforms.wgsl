@@ -8,6 +8,11 @@ struct Screen {
     width: f32,
     height: f32,
     aspect: f32,
+    scale_factor: f32,
+    // ui layout space to screen px: layout_pos * ui_scale + ui_offset, see UiTransform.
+    ui_offset: vec2<f32>,
+    ui_scale: f32,
+    ui_reference_scale: f32, // layout units per reference pixel, see Screen::set_reference_size
 }
 struct Time {
     delta: f32, // in seconds
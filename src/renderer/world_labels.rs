@@ -0,0 +1,190 @@
+use std::{collections::hash_map::Entry, hash::Hash};
+
+use ahash::AHashMap;
+use glam::{dvec2, vec2, DVec2, Vec2, Vec3};
+
+use crate::{
+    renderer::ui_3d::{
+        Billboard, Board3d, Board3dDepth, Board3dScaling, DistanceFade, Ui3DRenderer,
+    },
+    ui::{Board, IntoElementBox},
+    Camera3d, Transform, Uniforms,
+};
+
+/// Small [`Board3d`]s anchored to world positions, e.g. name tags and health bars above units.
+///
+/// Every frame, call [`WorldLabels::show`] for each label that should be visible, identified by a key
+/// like the entity id. Labels that were not shown in a frame are removed in [`WorldLabels::prepare`]
+/// and their boards are reused for new labels later:
+///
+/// ```rust,ignore
+/// let mut labels: WorldLabels<Entity> = WorldLabels::new();
+/// labels.offset = Vec3::Y * 2.0;
+/// // every frame:
+/// for (entity, unit) in units.iter() {
+///     let bar = div().style(|s| {
+///         s.width = Some(Len::Px(100.0));
+///         s.height = Some(Len::Px(10.0));
+///         s.extras_mut().gauge = Some(DivGauge::new(unit.health, Color::GREEN));
+///     });
+///     labels.show(entity, unit.pos, bar, &device);
+/// }
+/// labels.prepare(&device, &queue);
+/// // in a pass from `Ui3DRenderer::new_render_pass_with_depth`:
+/// labels.render(&ui_3d_renderer, &mut pass, &camera, &uniforms);
+/// ```
+///
+/// The settings are applied to all labels in [`WorldLabels::show`].
+pub struct WorldLabels<K> {
+    /// Layout size of each board.
+    pub size: DVec2,
+    /// Added to the anchor positions, e.g. to place labels above the heads of units.
+    pub offset: Vec3,
+    pub scaling: Board3dScaling,
+    pub billboard: Billboard,
+    pub fade: Option<DistanceFade>,
+    /// By default the bottom center of a label is at its anchor.
+    pub pivot: Vec2,
+    pub depth: Board3dDepth,
+    labels: AHashMap<K, Label>,
+    /// boards of removed labels, for reuse.
+    pool: Vec<Board3d>,
+}
+
+struct Label {
+    board: Board3d,
+    shown: bool,
+}
+
+impl<K: Hash + Eq> Default for WorldLabels<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq> WorldLabels<K> {
+    pub fn new() -> Self {
+        WorldLabels {
+            size: dvec2(200.0, 50.0),
+            offset: Vec3::ZERO,
+            scaling: Board3dScaling::ScreenPixels(1.0),
+            billboard: Billboard::Spherical,
+            fade: Some(DistanceFade::new(30.0, 40.0)),
+            pivot: vec2(0.5, 1.0),
+            depth: Board3dDepth::Test,
+            labels: AHashMap::new(),
+            pool: vec![],
+        }
+    }
+
+    /// Shows the label of `key` at `anchor` this frame, with `element` as its content.
+    pub fn show(
+        &mut self,
+        key: K,
+        anchor: Vec3,
+        element: impl IntoElementBox,
+        device: &wgpu::Device,
+    ) {
+        let element = element.store();
+        let label = match self.labels.entry(key) {
+            Entry::Occupied(entry) => {
+                let label = entry.into_mut();
+                label.board.board.resize_dvec2(self.size);
+                label.board.board.set_element(element);
+                label
+            }
+            Entry::Vacant(entry) => {
+                let board = match self.pool.pop() {
+                    Some(mut board) => {
+                        board.board.resize_dvec2(self.size);
+                        board.board.set_element(element);
+                        board
+                    }
+                    None => {
+                        Board3d::new(Board::new(element, self.size), Transform::default(), device)
+                    }
+                };
+                entry.insert(Label {
+                    board,
+                    shown: false,
+                })
+            }
+        };
+        label.shown = true;
+        let board = &mut label.board;
+        board.transform.position = anchor + self.offset;
+        board.scaling = self.scaling;
+        board.billboard = self.billboard;
+        board.fade = self.fade;
+        board.pivot = self.pivot;
+        board.depth = self.depth;
+    }
+
+    /// Removes the labels that were not shown since the last call and uploads the batches of the others.
+    /// Call once per frame, after all [`WorldLabels::show`] calls.
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        // the boards keep their buffers for the next labels that use them.
+        let hidden = self.labels.extract_if(|_, label| !label.shown);
+        self.pool.extend(hidden.map(|(_, label)| label.board));
+        for label in self.labels.values_mut() {
+            label.shown = false;
+            let board = &mut label.board;
+            board
+                .batches_gr
+                .prepare(&board.board.batches, device, queue);
+        }
+    }
+
+    /// Renders all labels with the camera the `uniforms` were prepared with, from back to front,
+    /// such that close labels are drawn on top of labels behind them.
+    pub fn render<'a>(
+        &'a self,
+        renderer: &'a Ui3DRenderer,
+        pass: &mut wgpu::RenderPass<'a>,
+        camera: &Camera3d,
+        uniforms: &'a Uniforms,
+    ) {
+        let camera_pos = camera.transform.pos;
+        let mut boards: Vec<(f32, &Board3d)> = self
+            .labels
+            .values()
+            .map(|l| {
+                (
+                    l.board.transform.position.distance_squared(camera_pos),
+                    &l.board,
+                )
+            })
+            .collect();
+        boards.sort_by(|a, b| b.0.total_cmp(&a.0));
+        for (_, board) in boards {
+            renderer.render_board_for_camera(pass, board, camera, uniforms);
+        }
+    }
+
+    pub fn get(&self, key: &K) -> Option<&Board3d> {
+        self.labels.get(key).map(|l| &l.board)
+    }
+
+    pub fn get_mut(&mut self, key: &K) -> Option<&mut Board3d> {
+        self.labels.get_mut(key).map(|l| &mut l.board)
+    }
+
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    /// Number of boards kept for reuse.
+    pub fn pooled(&self) -> usize {
+        self.pool.len()
+    }
+
+    /// Removes all labels, keeping their boards for reuse.
+    pub fn clear(&mut self) {
+        self.pool
+            .extend(self.labels.drain().map(|(_, label)| label.board));
+    }
+}
@@ -0,0 +1,391 @@
+use std::{ops::Range, time::Instant};
+
+use ahash::AHashMap;
+use glam::{DVec2, Vec3};
+use wgpu::{BufferUsages, ShaderStages};
+
+use crate::{
+    make_shader_source, rgba_bind_group_layout_cached,
+    ui::{
+        element::{Section, Text, TextSection, UiString},
+        font::SdfFontRef,
+        layout::layout_text,
+        UiEffect, VerticalAlign,
+    },
+    Aabb, Color, GraphicsContext, GrowableBuffer, HotReload, RenderFormat, ShaderCache,
+    ShaderSource, Time, Uniforms, VertexT, VertsLayout,
+};
+
+use super::RenderStats;
+
+const SHADER_SOURCE: ShaderSource = make_shader_source!("uniforms.wgsl", "world_text.wgsl");
+
+/// Strings are laid out once in this font size and scaled to the size of each text.
+const LAYOUT_FONT_SIZE: f32 = 32.0;
+/// Layouts of strings that were not drawn for this many frames are dropped.
+const KEEP_LAYOUT_FRAMES: u64 = 120;
+/// Floating texts fade out in this last fraction of their lifetime.
+const FADE_OUT_FRACTION: f32 = 0.3;
+
+/// A single line of text at a position in the world, always facing the camera.
+#[derive(Debug, Clone)]
+pub struct WorldText {
+    /// Center of the text.
+    pub pos: Vec3,
+    pub string: UiString,
+    pub font: SdfFontRef,
+    /// In screen pixels or world units, see [`WorldTextRenderer::size`].
+    pub font_size: f32,
+    pub color: Color,
+}
+
+/// How the `font_size` of [`WorldText`]s is measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WorldTextSize {
+    /// The same size on screen at any distance, e.g. for damage numbers that should stay readable.
+    #[default]
+    ScreenPixels,
+    /// Smaller with distance, like everything else in the world.
+    WorldUnits,
+}
+
+/// Lots of short texts in the world without a [`crate::renderer::ui_3d::Board3d`] per text, e.g. floating
+/// combat text. All glyphs of the same font are drawn in one instanced draw call:
+///
+/// ```rust,ignore
+/// // on hit:
+/// world_text.spawn(WorldText { pos, string: damage.to_string().into(), font, font_size: 24.0, color: Color::RED },
+///     Vec3::Y * 2.0, 1.0);
+/// // every frame, texts that are only shown in this frame:
+/// world_text.draw(WorldText { pos: chest.pos, string: "E".into(), font, font_size: 16.0, color: Color::WHITE });
+/// world_text.prepare(&time);
+/// // in the hdr pass:
+/// world_text.render(&mut pass, &uniforms);
+/// ```
+///
+/// Each unique string is laid out once and kept while it is drawn.
+pub struct WorldTextRenderer {
+    pub size: WorldTextSize,
+    /// drawn in the current frame only.
+    texts: Vec<WorldText>,
+    floating: Vec<FloatingText>,
+    /// by the address of the font, then by the string.
+    layouts: AHashMap<usize, AHashMap<Box<str>, CachedLayout>>,
+    frame: u64,
+    instances: Vec<WorldGlyphRaw>,
+    instance_buffer: GrowableBuffer<WorldGlyphRaw>,
+    batches: Vec<WorldTextBatch>,
+    pipeline: wgpu::RenderPipeline,
+    render_format: RenderFormat,
+    ctx: GraphicsContext,
+    stats: RenderStats,
+}
+
+#[derive(Debug, Clone)]
+struct FloatingText {
+    text: WorldText,
+    velocity: Vec3,
+    age: f32,
+    lifetime: f32,
+}
+
+#[derive(Debug)]
+struct CachedLayout {
+    /// centered around (0,0) in [`LAYOUT_FONT_SIZE`], y down.
+    glyphs: Vec<CachedGlyph>,
+    last_used: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct CachedGlyph {
+    rect: Aabb,
+    uv: Aabb,
+    page: u32,
+}
+
+#[derive(Debug)]
+struct WorldTextBatch {
+    font: SdfFontRef,
+    page: u32,
+    range: Range<u32>,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct WorldGlyphRaw {
+    pos: Vec3,
+    rect: Aabb,
+    uv: Aabb,
+    color: Color,
+}
+
+impl VertexT for WorldGlyphRaw {
+    const ATTRIBUTES: &'static [wgpu::VertexFormat] = &[
+        wgpu::VertexFormat::Float32x3, // "pos"
+        wgpu::VertexFormat::Float32x4, // "rect"
+        wgpu::VertexFormat::Float32x4, // "uv"
+        wgpu::VertexFormat::Float32x4, // "color"
+    ];
+}
+
+impl WorldTextRenderer {
+    pub fn new(
+        ctx: &GraphicsContext,
+        render_format: RenderFormat,
+        cache: &mut ShaderCache,
+    ) -> Self {
+        let shader = cache.register(SHADER_SOURCE, &ctx.device);
+        let pipeline = create_pipeline(&shader, &ctx.device, render_format);
+        WorldTextRenderer {
+            size: WorldTextSize::default(),
+            texts: vec![],
+            floating: vec![],
+            layouts: AHashMap::new(),
+            frame: 0,
+            instances: vec![],
+            instance_buffer: GrowableBuffer::new(&ctx.device, 512, BufferUsages::VERTEX),
+            batches: vec![],
+            pipeline,
+            render_format,
+            ctx: ctx.clone(),
+            stats: RenderStats::default(),
+        }
+    }
+
+    /// Shows the text in the next [`WorldTextRenderer::prepare`] only.
+    pub fn draw(&mut self, text: WorldText) {
+        self.texts.push(text);
+    }
+
+    /// Shows the text for `lifetime` seconds, moving with `velocity` in world units per second and
+    /// fading out at the end, e.g. for damage numbers.
+    pub fn spawn(&mut self, text: WorldText, velocity: Vec3, lifetime: f32) {
+        self.floating.push(FloatingText {
+            text,
+            velocity,
+            age: 0.0,
+            lifetime,
+        });
+    }
+
+    /// Number of texts spawned with [`WorldTextRenderer::spawn`] that are still visible.
+    pub fn n_floating(&self) -> usize {
+        self.floating.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.texts.clear();
+        self.floating.clear();
+    }
+
+    /// Moves the floating texts, collects the glyphs of all texts by font and uploads them.
+    pub fn prepare(&mut self, time: &Time) {
+        let start = Instant::now();
+        let delta = time.delta().as_secs_f32();
+        self.frame += 1;
+        for f in self.floating.iter_mut() {
+            f.age += delta;
+            f.text.pos += f.velocity * delta;
+        }
+        self.floating.retain(|f| f.age < f.lifetime);
+
+        // (font, page) of each instance, to sort them into batches.
+        let mut keyed: Vec<((usize, u32), SdfFontRef, WorldGlyphRaw)> = vec![];
+        let floating = self.floating.iter().map(|f| {
+            let fade_duration = f.lifetime * FADE_OUT_FRACTION;
+            let alpha = ((f.lifetime - f.age) / fade_duration.max(f32::EPSILON)).clamp(0.0, 1.0);
+            (&f.text, alpha)
+        });
+        let texts = self.texts.iter().map(|t| (t, 1.0));
+        for (text, alpha) in texts.chain(floating) {
+            let font_key = text.font as *const _ as usize;
+            let layout = self
+                .layouts
+                .entry(font_key)
+                .or_default()
+                .entry(Box::from(&*text.string))
+                .or_insert_with(|| CachedLayout {
+                    glyphs: layout_glyphs(text),
+                    last_used: 0,
+                });
+            layout.last_used = self.frame;
+            let scale = text.font_size / LAYOUT_FONT_SIZE;
+            let mut color = text.color;
+            color.a *= alpha;
+            for glyph in layout.glyphs.iter() {
+                let raw = WorldGlyphRaw {
+                    pos: text.pos,
+                    rect: Aabb::new(glyph.rect.min * scale, glyph.rect.max * scale),
+                    uv: glyph.uv,
+                    color,
+                };
+                keyed.push(((font_key, glyph.page), text.font, raw));
+            }
+        }
+        self.texts.clear();
+        let frame = self.frame;
+        for layouts in self.layouts.values_mut() {
+            layouts.retain(|_, layout| frame - layout.last_used <= KEEP_LAYOUT_FRAMES);
+        }
+        self.layouts.retain(|_, layouts| !layouts.is_empty());
+
+        keyed.sort_by_key(|(key, _, _)| *key);
+        self.instances.clear();
+        self.batches.clear();
+        let mut last_key = None;
+        for (key, font, raw) in keyed {
+            let i = self.instances.len() as u32;
+            if last_key == Some(key) {
+                self.batches.last_mut().unwrap().range.end = i + 1;
+            } else {
+                self.batches.push(WorldTextBatch {
+                    font,
+                    page: key.1,
+                    range: i..i + 1,
+                });
+                last_key = Some(key);
+            }
+            self.instances.push(raw);
+        }
+        self.instance_buffer
+            .prepare(&self.instances, &self.ctx.device, &self.ctx.queue);
+        for batch in self.batches.iter() {
+            batch
+                .font
+                .write_atlas_to_texture(&self.ctx.device, &self.ctx.queue);
+        }
+        self.stats = RenderStats {
+            draw_calls: self.batches.len() as u32,
+            instances: self.instances.len() as u32,
+            vertices: 4 * self.instances.len() as u32,
+            upload_bytes: self.instance_buffer.byte_len(),
+            prepare_time: start.elapsed(),
+        };
+    }
+
+    /// Stats of the last [`WorldTextRenderer::prepare`].
+    pub fn stats(&self) -> RenderStats {
+        self.stats
+    }
+
+    pub fn render<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>, uniforms: &'a Uniforms) {
+        if self.batches.is_empty() {
+            return;
+        }
+        pass.set_pipeline(&self.pipeline);
+        pass.set_bind_group(0, uniforms.bind_group(), &[]);
+        let screen_pixels: f32 = match self.size {
+            WorldTextSize::ScreenPixels => 1.0,
+            WorldTextSize::WorldUnits => 0.0,
+        };
+        pass.set_push_constants(ShaderStages::VERTEX, 0, bytemuck::bytes_of(&screen_pixels));
+        pass.set_vertex_buffer(0, self.instance_buffer.buffer().slice(..));
+        for batch in self.batches.iter() {
+            pass.set_bind_group(1, &batch.font.page_texture(batch.page).bind_group, &[]);
+            pass.draw(0..4, batch.range.clone());
+        }
+    }
+}
+
+/// Lays out the string of the `text` in one line, centered around (0,0).
+fn layout_glyphs(text: &WorldText) -> Vec<CachedGlyph> {
+    let section = TextSection {
+        string: text.string.clone(),
+        font: text.font.into(),
+        color: Color::WHITE,
+        font_size: LAYOUT_FONT_SIZE,
+        shadow_intensity: 0.0,
+        hinting: None,
+        vertical_align: VerticalAlign::Baseline,
+        effect: UiEffect::NONE,
+    };
+    let mut text = Text {
+        sections: smallvec::smallvec![Section::Text(section)],
+        offset: DVec2::ZERO,
+        additional_line_gap: 0.0,
+        selectable: false,
+    };
+    let computed = layout_text(&mut text, f32::MAX);
+    let center = computed.bounds.size.as_vec2() * 0.5;
+    computed
+        .glyphs
+        .iter()
+        .map(|g| {
+            let min = g.bounds.pos - center;
+            CachedGlyph {
+                rect: Aabb::new(min, min + g.bounds.size),
+                uv: g.uv,
+                page: g.page,
+            }
+        })
+        .collect()
+}
+
+impl HotReload for WorldTextRenderer {
+    fn source(&self) -> ShaderSource {
+        SHADER_SOURCE
+    }
+
+    fn hot_reload(&mut self, shader: &wgpu::ShaderModule, device: &wgpu::Device) {
+        self.pipeline = create_pipeline(shader, device, self.render_format);
+    }
+}
+
+fn create_pipeline(
+    shader: &wgpu::ShaderModule,
+    device: &wgpu::Device,
+    render_format: RenderFormat,
+) -> wgpu::RenderPipeline {
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("WorldTextRenderer"),
+        bind_group_layouts: &[
+            Uniforms::cached_layout(),
+            rgba_bind_group_layout_cached(device),
+        ],
+        push_constant_ranges: &[wgpu::PushConstantRange {
+            stages: ShaderStages::VERTEX,
+            range: 0..std::mem::size_of::<f32>() as u32,
+        }],
+    });
+    let verts = VertsLayout::new().instance::<WorldGlyphRaw>();
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("WorldTextRenderer"),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: verts.layout(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format: render_format.color,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleStrip,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Ccw,
+            cull_mode: None,
+            unclipped_depth: false,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            conservative: false,
+        },
+        // occluded by the scene, but texts do not occlude each other.
+        depth_stencil: render_format.depth.map(|format| wgpu::DepthStencilState {
+            format,
+            depth_write_enabled: false,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: render_format.msaa_sample_count,
+            ..Default::default()
+        },
+        multiview: None,
+    })
+}
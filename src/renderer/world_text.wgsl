@@ -0,0 +1,58 @@
+@group(1) @binding(0)
+var t_diffuse: texture_2d<f32>;
+@group(1) @binding(1)
+var s_diffuse: sampler;
+
+// 1.0 if the glyph rects are in screen pixels, 0.0 if they are in world units, see `WorldTextSize`.
+var<push_constant> screen_pixels: f32;
+
+struct WorldGlyph {
+    @location(0) pos: vec3<f32>,      // anchor of the text in the world
+    @location(1) rect: vec4<f32>,     // min and max offset from the anchor, x right and y down
+    @location(2) uv: vec4<f32>,
+    @location(3) color: vec4<f32>,
+}
+
+struct WorldGlyphVertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex_index: u32, glyph: WorldGlyph) -> WorldGlyphVertexOutput {
+    // triangle strip: top left, top right, bottom left, bottom right
+    let corner = vec2<f32>(f32(vertex_index & 1u), f32(vertex_index >> 1u));
+    let offset = mix(glyph.rect.xy, glyph.rect.zw, corner);
+
+    var clip_position: vec4<f32>;
+    if screen_pixels > 0.5 {
+        clip_position = camera.view_proj * vec4<f32>(glyph.pos, 1.0);
+        let ndc_offset = vec2<f32>(offset.x, -offset.y) * 2.0 / vec2<f32>(screen.width, screen.height);
+        clip_position = vec4<f32>(clip_position.xy + ndc_offset * clip_position.w, clip_position.zw);
+    } else {
+        // billboard: move along the right and up axes of the camera.
+        let right = vec3<f32>(camera.view[0][0], camera.view[1][0], camera.view[2][0]);
+        let up = vec3<f32>(camera.view[0][1], camera.view[1][1], camera.view[2][1]);
+        let world_pos = glyph.pos + right * offset.x - up * offset.y;
+        clip_position = camera.view_proj * vec4<f32>(world_pos, 1.0);
+    }
+
+    var out: WorldGlyphVertexOutput;
+    out.clip_position = clip_position;
+    out.uv = mix(glyph.uv.xy, glyph.uv.zw, corner);
+    out.color = glyph.color;
+    return out;
+}
+
+// like `glyph_fs` in ui.wgsl, without shadows and effects.
+@fragment
+fn fs_main(in: WorldGlyphVertexOutput) -> @location(0) vec4<f32> {
+    let sdf: f32 = textureSample(t_diffuse, s_diffuse, in.uv).r;
+    let sz: vec2<u32> = textureDimensions(t_diffuse, 0);
+    let dx: f32 = dpdx(in.uv.x) * f32(sz.x);
+    let dy: f32 = dpdy(in.uv.y) * f32(sz.y);
+    let to_pixels: f32 = 32.0 * inverseSqrt(dx * dx + dy * dy);
+    let inside_factor = clamp((sdf - 0.5) * to_pixels + 0.5, 0.0, 1.0);
+    return vec4<f32>(in.color.rgb, in.color.a * inside_factor);
+}
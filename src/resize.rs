@@ -0,0 +1,58 @@
+use std::{cell::RefCell, rc::Weak};
+
+use winit::dpi::PhysicalSize;
+
+/// Anything that depends on the size of the window surface, e.g. the camera projection or screen sized textures.
+pub trait Resizable {
+    /// Called after the window was resized. `size` is in physical pixels.
+    fn on_resize(&mut self, size: PhysicalSize<u32>, device: &wgpu::Device);
+}
+
+/// Forwards a single resize call to all size dependent subsystems, so none of them can be forgotten.
+///
+/// Subsystems that are shared (`Rc<RefCell<T>>`) can be registered once and are only held weakly,
+/// registrations of dropped subsystems are removed automatically.
+/// Subsystems owned directly by your app can be passed to [`ResizeDispatcher::resize`],
+/// similar to how [`crate::ShaderCache::hot_reload`] takes hot reloadable renderers next to the registered ones.
+#[derive(Default)]
+pub struct ResizeDispatcher {
+    registered: Vec<Weak<RefCell<dyn Resizable>>>,
+    last_size: Option<PhysicalSize<u32>>,
+}
+
+impl ResizeDispatcher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, resizable: Weak<RefCell<dyn Resizable>>) {
+        self.registered.push(resizable);
+    }
+
+    /// The size passed to the last call of [`ResizeDispatcher::resize`].
+    pub fn last_size(&self) -> Option<PhysicalSize<u32>> {
+        self.last_size
+    }
+
+    /// Resizes the given subsystems in order, followed by all registered ones that are still alive.
+    ///
+    /// Note: put the [`crate::GraphicsContext`] first, such that the surface is reconfigured before anything else.
+    pub fn resize(
+        &mut self,
+        size: PhysicalSize<u32>,
+        device: &wgpu::Device,
+        owned: &mut [&mut dyn Resizable],
+    ) {
+        self.last_size = Some(size);
+        for r in owned.iter_mut() {
+            r.on_resize(size, device);
+        }
+        self.registered.retain(|r| {
+            let Some(r) = r.upgrade() else {
+                return false;
+            };
+            r.borrow_mut().on_resize(size, device);
+            true
+        });
+    }
+}
@@ -0,0 +1,269 @@
+use std::{
+    fmt::Debug,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, bail};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+const MAGIC: &str = "tgf-save";
+const EXTENSION: &str = "save";
+
+/// Turns the data of a save from one version into the data of the next version.
+pub type Migration = Box<dyn Fn(Value) -> anyhow::Result<Value>>;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SaveHeader {
+    magic: String,
+    version: u32,
+    checksum: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SaveEnvelope {
+    header: SaveHeader,
+    data: Value,
+}
+
+/// Saves and loads serde serializable game state into named slots in a directory.
+///
+/// Each save starts with a header containing the version of the save format and a checksum of the data,
+/// such that corrupted or manipulated saves are detected. Saves are written to a temporary file first
+/// and then renamed, so a crash while saving never leaves a half written save behind.
+///
+/// When the format of the state changes, bump the version and add a migration that converts
+/// the json of the old version into the new one:
+///
+/// ```rust,ignore
+/// let saves = SaveFile::new("my_game", 2).with_migration(1, |mut data| {
+///     data["gold"] = data["coins"].take();
+///     Ok(data)
+/// });
+/// saves.save("slot1", &state)?;
+/// let state: GameState = saves.load("slot1")?;
+/// ```
+pub struct SaveFile {
+    dir: PathBuf,
+    version: u32,
+    /// migrations[i] migrates from version i to version i + 1
+    migrations: Vec<Option<Migration>>,
+}
+
+impl Debug for SaveFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SaveFile")
+            .field("dir", &self.dir)
+            .field("version", &self.version)
+            .finish()
+    }
+}
+
+impl SaveFile {
+    /// Saves into the `saves` folder in the [`data_dir`] of the app.
+    /// Falls back to a `saves` folder in the working directory if there is no data directory.
+    pub fn new(app_name: &str, version: u32) -> Self {
+        let dir = data_dir(app_name)
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("saves");
+        Self::in_dir(dir, version)
+    }
+
+    pub fn in_dir(dir: impl Into<PathBuf>, version: u32) -> Self {
+        Self {
+            dir: dir.into(),
+            version,
+            migrations: vec![],
+        }
+    }
+
+    /// Registers how saves of `from_version` are converted into `from_version + 1`.
+    pub fn with_migration(
+        mut self,
+        from_version: u32,
+        migration: impl Fn(Value) -> anyhow::Result<Value> + 'static,
+    ) -> Self {
+        let i = from_version as usize;
+        if self.migrations.len() <= i {
+            self.migrations.resize_with(i + 1, || None);
+        }
+        self.migrations[i] = Some(Box::new(migration));
+        self
+    }
+
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn path(&self, slot: &str) -> PathBuf {
+        self.dir.join(format!("{slot}.{EXTENSION}"))
+    }
+
+    pub fn exists(&self, slot: &str) -> bool {
+        self.path(slot).exists()
+    }
+
+    pub fn save<T: Serialize>(&self, slot: &str, state: &T) -> anyhow::Result<()> {
+        let data = serde_json::to_value(state)?;
+        let envelope = SaveEnvelope {
+            header: SaveHeader {
+                magic: MAGIC.to_string(),
+                version: self.version,
+                checksum: checksum(&data)?,
+            },
+            data,
+        };
+        let json = serde_json::to_vec(&envelope)?;
+        std::fs::create_dir_all(&self.dir)?;
+        write_atomic(&self.path(slot), &json)
+    }
+
+    /// Loads the save, migrating it to the current version if it is older.
+    pub fn load<T: DeserializeOwned>(&self, slot: &str) -> anyhow::Result<T> {
+        let bytes = std::fs::read(self.path(slot))?;
+        let envelope: SaveEnvelope = serde_json::from_slice(&bytes)?;
+        let header = envelope.header;
+        if header.magic != MAGIC {
+            bail!("{slot} is not a save file");
+        }
+        if checksum(&envelope.data)? != header.checksum {
+            bail!("checksum of save {slot} does not match, the file is corrupted");
+        }
+        if header.version > self.version {
+            bail!(
+                "save {slot} has version {}, which is newer than the supported version {}",
+                header.version,
+                self.version
+            );
+        }
+        let mut data = envelope.data;
+        for version in header.version..self.version {
+            let migration = self
+                .migrations
+                .get(version as usize)
+                .and_then(|m| m.as_ref())
+                .ok_or_else(|| anyhow!("no migration from save version {version}"))?;
+            data = migration(data)?;
+        }
+        Ok(serde_json::from_value(data)?)
+    }
+
+    pub fn delete(&self, slot: &str) -> anyhow::Result<()> {
+        std::fs::remove_file(self.path(slot))?;
+        Ok(())
+    }
+
+    /// Names of all saves in the directory.
+    pub fn slots(&self) -> Vec<String> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return vec![];
+        };
+        let mut slots: Vec<String> = entries
+            .filter_map(|e| {
+                let path = e.ok()?.path();
+                if path.extension()? != EXTENSION {
+                    return None;
+                }
+                Some(path.file_stem()?.to_str()?.to_string())
+            })
+            .collect();
+        slots.sort();
+        slots
+    }
+}
+
+/// FNV-1a hash of the json of the data. Keys of json objects are sorted, so this does not depend on the field order.
+fn checksum(data: &Value) -> anyhow::Result<u64> {
+    let bytes = serde_json::to_vec(data)?;
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for b in bytes {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    Ok(hash)
+}
+
+/// Writes to a temporary file next to `path` and renames it, such that `path` always contains either
+/// the old or the new content, even if the game crashes while writing.
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> anyhow::Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+    {
+        let mut file = std::fs::File::create(&tmp_path)?;
+        file.write_all(bytes)?;
+        file.sync_all()?;
+    }
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+/// Directory for persistent data of the app, depending on the platform:
+///
+/// - Windows: `%APPDATA%\<app_name>`
+/// - macOS: `~/Library/Application Support/<app_name>`
+/// - Linux and others: `$XDG_DATA_HOME/<app_name>` or `~/.local/share/<app_name>`
+pub fn data_dir(app_name: &str) -> Option<PathBuf> {
+    let env_dir = |key: &str| {
+        std::env::var_os(key)
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+    };
+    let base = if cfg!(target_os = "windows") {
+        env_dir("APPDATA")?
+    } else if cfg!(target_os = "macos") {
+        env_dir("HOME")?.join("Library/Application Support")
+    } else {
+        env_dir("XDG_DATA_HOME").or_else(|| Some(env_dir("HOME")?.join(".local/share")))?
+    };
+    Some(base.join(app_name))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::SaveFile;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct StateV1 {
+        coins: u32,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct StateV2 {
+        gold: u32,
+        level: u32,
+    }
+
+    #[test]
+    fn save_migrate_and_detect_corruption() {
+        let dir = std::env::temp_dir().join(format!("tgf-saves-test-{}", std::process::id()));
+        let v1 = SaveFile::in_dir(&dir, 1);
+        v1.save("slot", &StateV1 { coins: 7 }).unwrap();
+        assert_eq!(v1.load::<StateV1>("slot").unwrap(), StateV1 { coins: 7 });
+        assert_eq!(v1.slots(), vec!["slot".to_string()]);
+
+        let v2 = SaveFile::in_dir(&dir, 2).with_migration(1, |mut data| {
+            data["gold"] = data["coins"].take();
+            data["level"] = 1.into();
+            Ok(data)
+        });
+        let state: StateV2 = v2.load("slot").unwrap();
+        assert_eq!(state, StateV2 { gold: 7, level: 1 });
+
+        let path = v1.path("slot");
+        let tampered = std::fs::read_to_string(&path)
+            .unwrap()
+            .replace("\"coins\":7", "\"coins\":9");
+        std::fs::write(&path, tampered).unwrap();
+        assert!(v1.load::<StateV1>("slot").is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
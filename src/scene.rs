@@ -0,0 +1,345 @@
+use std::{cell::RefMut, time::Duration};
+
+use winit::event::WindowEvent;
+
+use crate::{Easing, ScreenTransition, TransitionKind};
+
+/// A screen of the app, e.g. the main menu, the game or a pause menu, managed by a [`SceneStack`].
+///
+/// `C` is the context that is shared between all scenes, e.g. [`crate::DefaultWorld`].
+pub trait Scene<C> {
+    /// Called when the scene is added to the stack.
+    fn on_enter(&mut self, _ctx: &mut C) {}
+
+    /// Called when the scene is removed from the stack.
+    fn on_exit(&mut self, _ctx: &mut C) {}
+
+    /// Called when another scene is pushed on top of this one.
+    fn on_pause(&mut self, _ctx: &mut C) {}
+
+    /// Called when this scene becomes the topmost scene again.
+    fn on_resume(&mut self, _ctx: &mut C) {}
+
+    /// Only the topmost scene receives window events.
+    fn receive_window_event(&mut self, _event: &WindowEvent, _ctx: &mut C) {}
+
+    /// Only the topmost scene is updated. The returned command changes the stack.
+    fn update(&mut self, ctx: &mut C) -> SceneCommand<C>;
+
+    /// Called for the topmost scene and all scenes below it that are visible through overlays, from bottom to top.
+    fn render(&mut self, _ctx: &mut C) {}
+
+    /// If true, the scene below is rendered too, e.g. for a pause menu on top of the game.
+    fn is_overlay(&self) -> bool {
+        false
+    }
+}
+
+/// Gives the [`SceneStack`] access to the [`ScreenTransition`] of the app, for [`SceneCommand::with_transition`].
+pub trait SceneContext {
+    /// Without a screen transition, scene changes with transitions happen immediately.
+    fn screen_transition(&mut self) -> Option<RefMut<'_, ScreenTransition>> {
+        None
+    }
+}
+
+/// A transition that is played while the scene changes, see [`ScreenTransition`].
+#[derive(Debug, Clone)]
+pub struct SceneTransition {
+    pub kind: TransitionKind,
+    pub duration: Duration,
+    pub easing: Easing,
+}
+
+/// Returned from [`Scene::update`] to change the [`SceneStack`].
+pub enum SceneCommand<C> {
+    None,
+    /// Pauses the current scene and puts a new one on top.
+    Push(Box<dyn Scene<C>>),
+    /// Removes the current scene, resuming the one below.
+    Pop,
+    /// Replaces the current scene.
+    Replace(Box<dyn Scene<C>>),
+    /// Removes all scenes and adds a new one, e.g. to go back to the main menu.
+    ReplaceAll(Box<dyn Scene<C>>),
+    /// Starts the transition and performs the command once the screen transition says the scene should switch.
+    WithTransition(Box<SceneCommand<C>>, SceneTransition),
+}
+
+impl<C> SceneCommand<C> {
+    pub fn push(scene: impl Scene<C> + 'static) -> Self {
+        SceneCommand::Push(Box::new(scene))
+    }
+
+    pub fn replace(scene: impl Scene<C> + 'static) -> Self {
+        SceneCommand::Replace(Box::new(scene))
+    }
+
+    pub fn replace_all(scene: impl Scene<C> + 'static) -> Self {
+        SceneCommand::ReplaceAll(Box::new(scene))
+    }
+
+    pub fn with_transition(self, kind: TransitionKind, duration: Duration, easing: Easing) -> Self {
+        SceneCommand::WithTransition(
+            Box::new(self),
+            SceneTransition {
+                kind,
+                duration,
+                easing,
+            },
+        )
+    }
+}
+
+/// Stack of [`Scene`]s (menu -> game -> pause). Only the topmost scene receives input and is updated.
+///
+/// ```rust,ignore
+/// impl Scene<DefaultWorld> for MainMenu {
+///     fn update(&mut self, world: &mut DefaultWorld) -> SceneCommand<DefaultWorld> {
+///         if world.input.keys().just_pressed(KeyCode::Enter) {
+///             let fade = TransitionKind::FadeThrough(Color::BLACK);
+///             return SceneCommand::replace(Game::new()).with_transition(fade, Duration::from_secs(1), Easing::EaseInOut);
+///         }
+///         SceneCommand::None
+///     }
+/// }
+///
+/// // in AppT::update:
+/// world.start_frame();
+/// scenes.update(&mut world);
+/// scenes.render(&mut world);
+/// world.render();
+/// world.end_frame();
+/// ```
+pub struct SceneStack<C> {
+    scenes: Vec<Box<dyn Scene<C>>>,
+    /// command waiting for its transition to reach the point where the scene switches.
+    pending: Option<SceneCommand<C>>,
+}
+
+impl<C> std::fmt::Debug for SceneStack<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SceneStack")
+            .field("len", &self.scenes.len())
+            .field("pending", &self.pending.is_some())
+            .finish()
+    }
+}
+
+impl<C: SceneContext> SceneStack<C> {
+    pub fn new() -> Self {
+        Self {
+            scenes: vec![],
+            pending: None,
+        }
+    }
+
+    /// Number of scenes on the stack.
+    pub fn len(&self) -> usize {
+        self.scenes.len()
+    }
+
+    /// An empty stack is a good time to exit the app.
+    pub fn is_empty(&self) -> bool {
+        self.scenes.is_empty()
+    }
+
+    /// True while a scene change waits for its transition.
+    pub fn is_changing(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    pub fn top(&self) -> Option<&dyn Scene<C>> {
+        self.scenes.last().map(|s| s.as_ref())
+    }
+
+    pub fn top_mut(&mut self) -> Option<&mut (dyn Scene<C> + 'static)> {
+        self.scenes.last_mut().map(|s| s.as_mut())
+    }
+
+    /// Routes the event to the topmost scene.
+    pub fn receive_window_event(&mut self, event: &WindowEvent, ctx: &mut C) {
+        if let Some(top) = self.scenes.last_mut() {
+            top.receive_window_event(event, ctx);
+        }
+    }
+
+    /// Performs a pending scene change if its transition is far enough, then updates the topmost scene.
+    /// Call this after the [`ScreenTransition`] of the context was updated for this frame.
+    pub fn update(&mut self, ctx: &mut C) {
+        if self.pending.is_some() {
+            let switch = match ctx.screen_transition() {
+                Some(mut transition) => transition.switch_scene_now() || !transition.is_active(),
+                None => true,
+            };
+            if switch {
+                let command = self.pending.take().unwrap();
+                self.apply(command, ctx);
+            }
+        }
+        let Some(top) = self.scenes.last_mut() else {
+            return;
+        };
+        let command = top.update(ctx);
+        self.apply(command, ctx);
+    }
+
+    /// Renders the topmost scene and the scenes below it that are visible through overlays.
+    pub fn render(&mut self, ctx: &mut C) {
+        let first_visible = self
+            .scenes
+            .iter()
+            .rposition(|s| !s.is_overlay())
+            .unwrap_or(0);
+        for scene in self.scenes[first_visible..].iter_mut() {
+            scene.render(ctx);
+        }
+    }
+
+    /// Performs a command as if it was returned from the topmost scene, e.g. to push the first scene.
+    pub fn apply(&mut self, command: SceneCommand<C>, ctx: &mut C) {
+        match command {
+            SceneCommand::None => {}
+            SceneCommand::Push(mut scene) => {
+                if let Some(top) = self.scenes.last_mut() {
+                    top.on_pause(ctx);
+                }
+                scene.on_enter(ctx);
+                self.scenes.push(scene);
+            }
+            SceneCommand::Pop => {
+                if let Some(mut top) = self.scenes.pop() {
+                    top.on_exit(ctx);
+                }
+                if let Some(top) = self.scenes.last_mut() {
+                    top.on_resume(ctx);
+                }
+            }
+            SceneCommand::Replace(mut scene) => {
+                if let Some(mut top) = self.scenes.pop() {
+                    top.on_exit(ctx);
+                }
+                scene.on_enter(ctx);
+                self.scenes.push(scene);
+            }
+            SceneCommand::ReplaceAll(mut scene) => {
+                while let Some(mut top) = self.scenes.pop() {
+                    top.on_exit(ctx);
+                }
+                scene.on_enter(ctx);
+                self.scenes.push(scene);
+            }
+            SceneCommand::WithTransition(command, transition) => {
+                let started = match ctx.screen_transition() {
+                    Some(mut screen_transition) => {
+                        screen_transition.start(
+                            transition.kind,
+                            transition.duration,
+                            transition.easing,
+                        );
+                        true
+                    }
+                    None => false,
+                };
+                if started {
+                    // a change that is still waiting is replaced by the new one.
+                    self.pending = Some(*command);
+                } else {
+                    self.apply(*command, ctx);
+                }
+            }
+        }
+    }
+}
+
+impl<C: SceneContext> Default for SceneStack<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Scene, SceneCommand, SceneContext, SceneStack};
+
+    #[derive(Default)]
+    struct Log(Vec<String>);
+
+    impl SceneContext for Log {}
+
+    struct Named {
+        name: &'static str,
+        overlay: bool,
+        next: Option<SceneCommand<Log>>,
+    }
+
+    fn named(name: &'static str, overlay: bool) -> Named {
+        Named {
+            name,
+            overlay,
+            next: None,
+        }
+    }
+
+    impl Scene<Log> for Named {
+        fn on_enter(&mut self, ctx: &mut Log) {
+            ctx.0.push(format!("enter {}", self.name));
+        }
+
+        fn on_exit(&mut self, ctx: &mut Log) {
+            ctx.0.push(format!("exit {}", self.name));
+        }
+
+        fn on_pause(&mut self, ctx: &mut Log) {
+            ctx.0.push(format!("pause {}", self.name));
+        }
+
+        fn on_resume(&mut self, ctx: &mut Log) {
+            ctx.0.push(format!("resume {}", self.name));
+        }
+
+        fn update(&mut self, ctx: &mut Log) -> SceneCommand<Log> {
+            ctx.0.push(format!("update {}", self.name));
+            self.next.take().unwrap_or(SceneCommand::None)
+        }
+
+        fn render(&mut self, ctx: &mut Log) {
+            ctx.0.push(format!("render {}", self.name));
+        }
+
+        fn is_overlay(&self) -> bool {
+            self.overlay
+        }
+    }
+
+    #[test]
+    fn push_pop_and_overlay_rendering() {
+        let mut log = Log::default();
+        let mut stack: SceneStack<Log> = SceneStack::new();
+        let mut game = named("game", false);
+        game.next = Some(SceneCommand::push(named("pause", true)));
+        stack.apply(SceneCommand::push(named("menu", false)), &mut log);
+        stack.apply(SceneCommand::replace(game), &mut log);
+        stack.update(&mut log);
+        assert_eq!(stack.len(), 2);
+        stack.update(&mut log);
+        stack.render(&mut log);
+        stack.apply(SceneCommand::Pop, &mut log);
+        assert_eq!(
+            log.0,
+            vec![
+                "enter menu",
+                "exit menu",
+                "enter game",
+                "update game",
+                "pause game",
+                "enter pause",
+                "update pause",
+                "render game",
+                "render pause",
+                "exit pause",
+                "resume game",
+            ]
+        );
+    }
+}
@@ -1,14 +1,24 @@
 use std::sync::Arc;
 
+use glam::{dvec2, DVec2};
 use winit::dpi::PhysicalSize;
 
-use crate::{GraphicsContext, ToRaw, UniformBuffer};
+use crate::{
+    ui::{Edges, DEFAULT_REFERENCE_SIZE},
+    Aabb, GraphicsContext, Resizable, ToRaw, UniformBuffer,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Screen {
     pub width: u32,
     pub height: u32,
     pub scale_factor: f64,
+    /// how the screen space ui is scaled to the screen, see [`Screen::ui_transform`].
+    pub ui_scaling: UiScaling,
+    /// Physical pixels at the edges of the screen covered by notches or rounded corners, see [`Screen::set_safe_area_insets`].
+    pub safe_area_insets: Edges<f64>,
+    /// The design resolution of the screen space ui, see [`Screen::set_reference_size`].
+    pub reference_size: DVec2,
 }
 
 impl Screen {
@@ -17,15 +27,46 @@ impl Screen {
             width: size.width,
             height: size.height,
             scale_factor,
+            ui_scaling: UiScaling::default(),
+            safe_area_insets: Edges::all(0.0),
+            reference_size: DEFAULT_REFERENCE_SIZE,
         }
     }
 
     pub fn from_window(window: &winit::window::Window) -> Self {
-        Self {
-            width: window.inner_size().width,
-            height: window.inner_size().height,
-            scale_factor: window.scale_factor(),
-        }
+        Self::new(window.inner_size(), window.scale_factor())
+    }
+
+    pub fn size(&self) -> PhysicalSize<u32> {
+        PhysicalSize::new(self.width, self.height)
+    }
+
+    /// Maps between the layout space of the screen space ui and pixels on the screen.
+    /// The same transform is uploaded to the shaders, so hit testing and rendering always agree.
+    pub fn ui_transform(&self) -> UiTransform {
+        let mut transform = UiTransform::new(self.size(), self.ui_scaling);
+        transform.set_safe_area_insets(self.size(), self.safe_area_insets);
+        transform
+    }
+
+    /// Sets the parts of the screen that are not fully visible, in physical pixels, e.g. from the platform apis
+    /// of mobile devices or from a setting for tvs with overscan. winit does not report them, so they are zero
+    /// unless set here. Kept on resize, set them again if the screen rotates.
+    pub fn set_safe_area_insets(&mut self, insets: Edges<f64>) {
+        self.safe_area_insets = insets;
+    }
+
+    /// Sets the screen size the ui is designed for, e.g. 1080x1920 for a portrait game. The layout then always
+    /// has the height of the reference, like the default [`UiScaling::FixedHeight`] of 1080. Boards need the same
+    /// size in [`crate::ui::Board::set_reference_size`].
+    pub fn set_reference_size(&mut self, reference_size: DVec2) {
+        self.reference_size = reference_size;
+        self.ui_scaling = UiScaling::FixedHeight(reference_size.y);
+    }
+
+    /// Layout units of the screen space ui per reference pixel, see [`Screen::set_reference_size`].
+    pub fn ui_reference_scale(&self) -> f64 {
+        self.ui_transform().layout_size.y / self.reference_size.y.max(1.0)
     }
 
     pub fn resize(&mut self, size: PhysicalSize<u32>) {
@@ -39,6 +80,175 @@ impl Screen {
     }
 }
 
+impl Resizable for Screen {
+    fn on_resize(&mut self, size: PhysicalSize<u32>, _device: &wgpu::Device) {
+        self.resize(size);
+    }
+}
+
+/// How the layout space of the screen space ui maps to the pixels of the screen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UiScaling {
+    /// The layout is always this high and as wide as the aspect ratio of the screen allows.
+    /// The default is the height of [`crate::ui::DEFAULT_REFERENCE_SIZE`], what the ui of this crate is designed for.
+    FixedHeight(f64),
+    /// The layout has exactly this size and is scaled to fit into the screen, centered with bars on the sides.
+    Letterbox(DVec2),
+    /// One layout unit is one physical pixel.
+    Pixels,
+}
+
+impl Default for UiScaling {
+    fn default() -> Self {
+        UiScaling::FixedHeight(DEFAULT_REFERENCE_SIZE.y)
+    }
+}
+
+/// Uniform scale and offset from ui layout space to screen pixels: `screen_pos = layout_pos * scale + offset`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UiTransform {
+    pub scale: f64,
+    /// in screen pixels, non-zero for letterboxing.
+    pub offset: DVec2,
+    /// size of the layout space, the size the ui [`crate::ui::Board`] should be laid out in.
+    pub layout_size: DVec2,
+    /// Insets of the safe area from the edges of the layout space, in layout units, see [`Screen::set_safe_area_insets`].
+    pub safe_area: Edges<f64>,
+}
+
+impl UiTransform {
+    pub fn new(screen_size: PhysicalSize<u32>, scaling: UiScaling) -> Self {
+        let screen = dvec2(screen_size.width as f64, screen_size.height as f64).max(DVec2::ONE);
+        match scaling {
+            UiScaling::FixedHeight(height) => {
+                let scale = screen.y / height;
+                UiTransform {
+                    scale,
+                    offset: DVec2::ZERO,
+                    layout_size: dvec2(screen.x / scale, height),
+                    safe_area: Edges::all(0.0),
+                }
+            }
+            UiScaling::Letterbox(layout_size) => {
+                let scale = (screen / layout_size).min_element();
+                UiTransform {
+                    scale,
+                    offset: ((screen - layout_size * scale) * 0.5).floor(),
+                    layout_size,
+                    safe_area: Edges::all(0.0),
+                }
+            }
+            UiScaling::Pixels => UiTransform {
+                scale: 1.0,
+                offset: DVec2::ZERO,
+                layout_size: screen,
+                safe_area: Edges::all(0.0),
+            },
+        }
+    }
+
+    /// Converts the safe area `insets` of the screen from pixels into layout units. Letterbox bars
+    /// count as outside of the safe area already, so only the part of an inset that reaches into the layout space is kept.
+    pub fn set_safe_area_insets(&mut self, screen_size: PhysicalSize<u32>, insets: Edges<f64>) {
+        let screen = dvec2(screen_size.width as f64, screen_size.height as f64);
+        let bar_min = self.offset;
+        let bar_max = screen - self.offset - self.layout_size * self.scale;
+        let inset = |px: f64, bar: f64| (px - bar).max(0.0) / self.scale;
+        self.safe_area = Edges {
+            left: inset(insets.left, bar_min.x),
+            right: inset(insets.right, bar_max.x),
+            top: inset(insets.top, bar_min.y),
+            bottom: inset(insets.bottom, bar_max.y),
+        };
+    }
+
+    pub fn layout_to_screen(&self, layout_pos: DVec2) -> DVec2 {
+        layout_pos * self.scale + self.offset
+    }
+
+    /// E.g. for the cursor position, which is in screen pixels.
+    pub fn screen_to_layout(&self, screen_pos: DVec2) -> DVec2 {
+        (screen_pos - self.offset) / self.scale
+    }
+}
+
+/// Places a [`crate::ui::Board`] in a rect of the screen space ui instead of on the whole screen, e.g. the
+/// display of an in-game computer. Hit tested with [`crate::ui::Board::update_in_viewport`] and rendered with
+/// [`crate::renderer::ui_screen::UiScreenRenderer::render_batches_in_viewport`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UiViewport {
+    /// Layout space of the board to the layout space of the screen ui: `layout_pos * scale + offset`.
+    pub scale: f64,
+    pub offset: DVec2,
+    /// size the board is laid out in.
+    pub layout_size: DVec2,
+    /// Nothing of the board is drawn or hovered outside of this rect in the layout space of the screen ui.
+    pub clip: Option<Aabb>,
+}
+
+impl UiViewport {
+    /// Fits the `layout_size` into the `rect` in the layout space of the screen ui, centered with bars
+    /// like [`UiScaling::Letterbox`] and clipped to the `rect`.
+    pub fn letterbox(rect: Aabb, layout_size: DVec2) -> Self {
+        let min = rect.min.as_dvec2();
+        let size = rect.size().as_dvec2();
+        let scale = (size / layout_size.max(DVec2::ONE)).min_element();
+        UiViewport {
+            scale,
+            offset: min + (size - layout_size * scale) * 0.5,
+            layout_size,
+            clip: Some(rect),
+        }
+    }
+
+    /// Layout space of the board to screen pixels, with the `screen` transform of the surrounding ui,
+    /// e.g. [`Screen::ui_transform`].
+    pub fn ui_transform(&self, screen: &UiTransform) -> UiTransform {
+        UiTransform {
+            scale: self.scale * screen.scale,
+            offset: screen.layout_to_screen(self.offset),
+            layout_size: self.layout_size,
+            safe_area: Edges::all(0.0),
+        }
+    }
+
+    /// Whether the board can be hovered at `screen_pos` in screen pixels, i.e. it is inside of the clip rect.
+    pub fn contains(&self, screen: &UiTransform, screen_pos: DVec2) -> bool {
+        self.clip
+            .is_none_or(|clip| clip.contains(screen.screen_to_layout(screen_pos).as_vec2()))
+    }
+
+    /// The clip rect as `[x, y, width, height]` in screen pixels, clamped to the `screen_size`.
+    pub fn scissor_rect(
+        &self,
+        screen: &UiTransform,
+        screen_size: PhysicalSize<u32>,
+    ) -> Option<[u32; 4]> {
+        let clip = self.clip?;
+        let screen_size = dvec2(screen_size.width as f64, screen_size.height as f64);
+        let min = screen
+            .layout_to_screen(clip.min.as_dvec2())
+            .round()
+            .clamp(DVec2::ZERO, screen_size);
+        let max = screen
+            .layout_to_screen(clip.max.as_dvec2())
+            .round()
+            .clamp(min, screen_size);
+        let size = max - min;
+        Some([min.x as u32, min.y as u32, size.x as u32, size.y as u32])
+    }
+
+    /// `offset` and `scale` as pushed to `ui.wgsl`.
+    pub(crate) fn to_push(self) -> [f32; 4] {
+        [
+            self.offset.x as f32,
+            self.offset.y as f32,
+            self.scale as f32,
+            0.0,
+        ]
+    }
+}
+
 /// the stuff that gets sent to the shader
 #[repr(C)]
 #[derive(Debug, Clone, Copy, bytemuck::Pod, bytemuck::Zeroable, PartialEq)]
@@ -47,17 +257,25 @@ pub struct ScreenRaw {
     height: f32,
     aspect: f32,
     scale_factor: f32,
+    ui_offset: [f32; 2],
+    ui_scale: f32,
+    ui_reference_scale: f32,
 }
 
 impl ToRaw for Screen {
     type Raw = ScreenRaw;
 
     fn to_raw(&self) -> Self::Raw {
+        let ui_transform = self.ui_transform();
         ScreenRaw {
             width: self.width as f32,
             height: self.height as f32,
             aspect: self.aspect(),
             scale_factor: self.scale_factor as f32,
+            ui_offset: ui_transform.offset.as_vec2().into(),
+            ui_scale: ui_transform.scale as f32,
+            ui_reference_scale: (ui_transform.layout_size.y / self.reference_size.y.max(1.0))
+                as f32,
         }
     }
 }
@@ -115,3 +333,129 @@ impl ScreenGR {
         &self.bind_group
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use glam::dvec2;
+    use winit::dpi::PhysicalSize;
+
+    use super::{Screen, UiScaling, UiTransform, UiViewport};
+    use crate::{
+        ui::{div, Board, Edges, ElementId, IntoElementBox},
+        Aabb, Input, Time,
+    };
+
+    #[test]
+    fn letterboxed_ui_round_trips() {
+        let transform = UiTransform::new(
+            PhysicalSize::new(1920, 1200),
+            UiScaling::Letterbox(dvec2(1920.0, 1080.0)),
+        );
+        assert_eq!(transform.scale, 1.0);
+        assert_eq!(transform.offset, dvec2(0.0, 60.0));
+        let cursor = dvec2(100.0, 70.0);
+        assert_eq!(transform.screen_to_layout(cursor), dvec2(100.0, 10.0));
+        assert_eq!(
+            transform.layout_to_screen(transform.screen_to_layout(cursor)),
+            cursor
+        );
+
+        let fixed = UiTransform::new(PhysicalSize::new(2560, 1440), UiScaling::default());
+        assert_eq!(fixed.layout_size, dvec2(1920.0, 1080.0));
+    }
+
+    #[test]
+    fn portrait_reference_size_scales_boards() {
+        let mut screen = Screen::new(PhysicalSize::new(540, 960), 1.0);
+        screen.set_reference_size(dvec2(1080.0, 1920.0));
+        let transform = screen.ui_transform();
+        assert_eq!(transform.layout_size, dvec2(1080.0, 1920.0));
+        assert_eq!(screen.ui_reference_scale(), 1.0);
+
+        let mut board = Board::new(div().store(), dvec2(1920.0, 1080.0));
+        board.resize_to_ui_transform(&transform);
+        assert_eq!(board.ctx.reference_scale(), 1920.0 / 1080.0);
+        board.set_reference_size(screen.reference_size);
+        assert_eq!(board.ctx.reference_scale(), 1.0);
+    }
+
+    #[test]
+    fn absolute_divs_stay_in_the_safe_area() {
+        let mut screen = Screen::new(PhysicalSize::new(1920, 1200), 1.0);
+        screen.ui_scaling = UiScaling::Letterbox(dvec2(1920.0, 1080.0));
+        // the top inset is covered by the 60px letterbox bar:
+        screen.set_safe_area_insets(Edges {
+            left: 40.0,
+            right: 0.0,
+            top: 50.0,
+            bottom: 80.0,
+        });
+        let transform = screen.ui_transform();
+        assert_eq!(
+            transform.safe_area,
+            Edges {
+                left: 40.0,
+                right: 0.0,
+                top: 0.0,
+                bottom: 20.0
+            }
+        );
+
+        let corner = ElementId::from("corner");
+        let element = || {
+            div()
+                .full()
+                .child_with_id(
+                    corner,
+                    div().style(|s| {
+                        s.size(100, 100);
+                        s.absolute = Some(glam::vec2(0.0, 1.0));
+                        s.safe_area = true;
+                    }),
+                )
+                .store()
+        };
+        let mut board = Board::new(element(), dvec2(1.0, 1.0));
+        board.resize_to_ui_transform(&transform);
+        board.set_element(element());
+        let bounds = board.ctx.computed_bounds(corner).unwrap();
+        assert_eq!(bounds.pos, dvec2(40.0, 960.0));
+    }
+
+    #[test]
+    fn boards_in_viewports_are_hovered_through_the_viewport() {
+        let mut screen = Screen::new(PhysicalSize::new(2000, 1000), 1.0);
+        screen.ui_scaling = UiScaling::FixedHeight(500.0);
+        // a 400x200 layout shown in a 200x200 panel: scaled by 0.5 with bars above and below.
+        let panel = Aabb::new(glam::vec2(100.0, 100.0), glam::vec2(300.0, 300.0));
+        let viewport = UiViewport::letterbox(panel, dvec2(400.0, 200.0));
+        assert_eq!(viewport.scale, 0.5);
+        assert_eq!(viewport.offset, dvec2(100.0, 150.0));
+        assert_eq!(
+            viewport.scissor_rect(&screen.ui_transform(), screen.size()),
+            Some([200, 200, 400, 400])
+        );
+
+        let button = ElementId::from("button");
+        let element = || {
+            div()
+                .full()
+                .child_with_id(button, div().style(|s| s.size(100, 100)))
+                .store()
+        };
+        let mut board = Board::new(element(), dvec2(1.0, 1.0));
+        let mut hovered_at = |cursor: glam::Vec2| {
+            let mut input = Input::default();
+            input.receive_input_event(&crate::InputEvent::CursorMoved(cursor));
+            board.update_in_viewport(&input, &Time::new(), &screen, &viewport, |_| element());
+            board.update_in_viewport(&input, &Time::new(), &screen, &viewport, |_| element());
+            board.ctx.state_of(button).hovered
+        };
+        // layout (50, 50) of the board is (125, 175) in the screen ui and (250, 350) on the screen:
+        assert!(hovered_at(glam::vec2(250.0, 350.0)));
+        assert!(!hovered_at(glam::vec2(150.0, 350.0)));
+        // in the bar above the board, which is still inside of the clip rect:
+        assert!(!hovered_at(glam::vec2(250.0, 250.0)));
+        assert_eq!(board.size, dvec2(400.0, 200.0));
+    }
+}
@@ -1,8 +1,16 @@
-use std::{borrow::Cow, collections::HashMap, sync::Arc};
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    collections::HashMap,
+    fmt::{Debug, Display},
+    rc::{Rc, Weak},
+    sync::Arc,
+};
 
 use egui::ahash::{HashSet, HashSetExt};
 
 use crate::FileChangeWatcher;
+use wgpu::naga;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct ShaderFile {
@@ -13,15 +21,42 @@ pub struct ShaderFile {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct ShaderSource {
     pub files: &'static [ShaderFile],
+    /// An optional chunk of wgsl that is appended after `files`.
+    /// Renderers use this to let users supply hook functions (e.g. `fn user_modify_color(...)`)
+    /// that the built-in shaders call, without having to fork the entire shader.
+    pub user_chunk: Option<ShaderFile>,
 }
 
-impl ShaderSource {}
+impl ShaderSource {
+    /// Returns the same source with the given chunk appended after the built-in files, replacing a previous chunk.
+    pub const fn with_user_chunk(mut self, chunk: ShaderFile) -> Self {
+        self.user_chunk = Some(chunk);
+        self
+    }
+
+    /// All files in the order they are concatenated, including the user chunk.
+    pub fn iter(&self) -> impl Iterator<Item = &ShaderFile> {
+        self.files.iter().chain(self.user_chunk.iter())
+    }
+}
 
 #[macro_export]
 macro_rules! make_shader_source {
     ($($file:literal),+) => {{
         $crate::ShaderSource{
-             files: &[$(   $crate::ShaderFile { wgsl: include_str!($file), file: $file, }    ),+]
+             files: &[$(   $crate::ShaderFile { wgsl: include_str!($file), file: $file, }    ),+],
+             user_chunk: None,
+        }
+    }};
+}
+
+/// Creates a single [`ShaderFile`], e.g. to be used as a user chunk of a [`ShaderSource`].
+#[macro_export]
+macro_rules! make_shader_file {
+    ($file:literal) => {{
+        $crate::ShaderFile {
+            wgsl: include_str!($file),
+            file: $file,
         }
     }};
 }
@@ -31,13 +66,97 @@ pub trait HotReload {
     fn hot_reload(&mut self, shader: &wgpu::ShaderModule, device: &wgpu::Device);
 }
 
+/// Hot reloadable renderers that are shared (`Rc<RefCell<T>>`) and registered once in the [`ShaderCache`],
+/// such that they can't be forgotten in the list passed to [`ShaderCache::hot_reload`].
+///
+/// Renderers are only held weakly, like the registered subsystems of a [`crate::ResizeDispatcher`],
+/// registrations of dropped renderers are removed automatically.
+#[derive(Default)]
+pub struct HotReloadRegistry {
+    registered: Vec<Weak<RefCell<dyn HotReload>>>,
+}
+
+impl HotReloadRegistry {
+    pub fn register(&mut self, renderer: Weak<RefCell<dyn HotReload>>) {
+        self.registered.push(renderer);
+    }
+
+    /// Number of registered renderers that are still alive.
+    pub fn len(&self) -> usize {
+        self.registered
+            .iter()
+            .filter(|r| r.strong_count() > 0)
+            .count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The renderers that are still alive, registrations of dropped ones are removed.
+    fn alive(&mut self) -> Vec<Rc<RefCell<dyn HotReload>>> {
+        let mut alive = Vec::with_capacity(self.registered.len());
+        self.registered.retain(|r| {
+            let Some(r) = r.upgrade() else {
+                return false;
+            };
+            alive.push(r);
+            true
+        });
+        alive
+    }
+}
+
+impl Debug for HotReloadRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HotReloadRegistry")
+            .field("alive", &self.len())
+            .finish()
+    }
+}
+
+/// A renderer that kept its previous pipelines, because the changed wgsl of its shader could not be used,
+/// see [`ShaderCache::hot_reload`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct HotReloadError {
+    /// The files of the shader source of the renderer.
+    pub files: Vec<&'static str>,
+    pub kind: HotReloadErrorKind,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HotReloadErrorKind {
+    /// The wgsl does not parse or validate.
+    InvalidWgsl,
+    /// An entry point of the previous version of the shader was removed or renamed.
+    MissingEntryPoint,
+    /// The device rejected the shader module or the pipelines of the renderer.
+    Pipeline,
+}
+
+impl Display for HotReloadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind = match self.kind {
+            HotReloadErrorKind::InvalidWgsl => "invalid wgsl",
+            HotReloadErrorKind::MissingEntryPoint => "missing entry point",
+            HotReloadErrorKind::Pipeline => "pipeline creation failed",
+        };
+        write!(f, "{kind} in shader of {:?}: {}", self.files, self.message)
+    }
+}
+
 #[derive(Debug)]
 pub struct ShaderCache {
     /// maps each file to the current wgsl content.
     current_wgsl: HashMap<ShaderFile, String>,
     module_cache: HashMap<String, std::sync::Weak<wgpu::ShaderModule>>,
+    /// the last wgsl of each source that renderers were successfully built with, to roll back failed hot reloads.
+    last_good_wgsl: HashMap<ShaderSource, String>,
+    hot_reload_errors: Vec<HotReloadError>,
     hot_reload_watcher: Option<FileChangeWatcher>,
     hot_reload_shaders_dir: &'static str,
+    hot_reload_registry: HotReloadRegistry,
 }
 
 impl ShaderCache {
@@ -45,6 +164,8 @@ impl ShaderCache {
         ShaderCache {
             current_wgsl: HashMap::new(),
             module_cache: HashMap::new(),
+            last_good_wgsl: HashMap::new(),
+            hot_reload_errors: vec![],
             hot_reload_watcher: if let Some(dir) = hot_reload_shaders_dir {
                 std::fs::create_dir_all(dir).unwrap();
                 Some(FileChangeWatcher::new(&[]))
@@ -52,40 +173,87 @@ impl ShaderCache {
                 None
             },
             hot_reload_shaders_dir: hot_reload_shaders_dir.unwrap_or("no_hot_reload"),
+            hot_reload_registry: HotReloadRegistry::default(),
         }
     }
 
+    /// Panics if the wgsl is invalid, use [`ShaderCache::try_register`] for shaders that are not part of the app,
+    /// e.g. user provided ones.
     pub fn register(
         &mut self,
         source: ShaderSource,
         device: &wgpu::Device,
     ) -> Arc<wgpu::ShaderModule> {
-        for file in source.files {
+        match self.try_register(source, device) {
+            Ok(shader) => shader,
+            Err(err) => panic!("Error: {err}"),
+        }
+    }
+
+    pub fn try_register(
+        &mut self,
+        source: ShaderSource,
+        device: &wgpu::Device,
+    ) -> crate::Result<Arc<wgpu::ShaderModule>> {
+        for file in source.iter() {
             self.add_file(*file);
         }
 
         // combine the files into one wgsl string to generate (or get the cached) shader module:
-        let mut wgsl = String::new();
-        for f in source.files {
-            wgsl.push_str(self.current_wgsl.get(f).unwrap());
-        }
+        let wgsl = self.combined_wgsl(&source);
         if let Err(err) = validate_wgsl(&wgsl) {
-            panic!("Error: {err}");
+            return Err(crate::Error::InvalidShader {
+                files: source.iter().map(|f| f.file).collect(),
+                message: err.to_string(),
+            });
         }
-        self.get_shader_module(wgsl, device)
+        self.last_good_wgsl.insert(source, wgsl.clone());
+        Ok(self.get_shader_module(wgsl, device))
+    }
+
+    /// Makes [`ShaderCache::hot_reload`] and [`ShaderCache::hot_reload_all`] update the shared renderer for as
+    /// long as it is alive, e.g. `shader_cache.register_hot_reload(Rc::downgrade(&particles))`.
+    pub fn register_hot_reload(&mut self, renderer: Weak<RefCell<dyn HotReload>>) {
+        self.hot_reload_registry.register(renderer);
+    }
+
+    /// Shares the `renderer` and registers it, such that [`ShaderCache::hot_reload_all`] updates it, e.g. for the
+    /// renderers of the [`crate::DefaultWorld`] which are created together with the cache.
+    pub fn hot_reloaded<T: HotReload + 'static>(&mut self, renderer: T) -> Rc<RefCell<T>> {
+        let renderer = Rc::new(RefCell::new(renderer));
+        let weak: Weak<RefCell<dyn HotReload>> = Rc::downgrade(&renderer) as _;
+        self.register_hot_reload(weak);
+        renderer
+    }
+
+    pub fn hot_reload_registry(&self) -> &HotReloadRegistry {
+        &self.hot_reload_registry
+    }
+
+    /// Updates all registered renderers that are still alive, see [`ShaderCache::hot_reload`].
+    pub fn hot_reload_all(&mut self, device: &wgpu::Device) -> &[HotReloadError] {
+        self.hot_reload(&mut [], device)
     }
 
-    /// checks for changes in the watched paths and if so, updates all the hotreloadable renderers.
-    pub fn hot_reload(&mut self, reload: &mut [&mut dyn HotReload], device: &wgpu::Device) {
+    /// Checks for changes in the watched paths and if so, updates the hot reloadable renderers that use the changed files:
+    /// the given ones that are owned by your app, followed by all registered ones, see [`ShaderCache::register_hot_reload`].
+    ///
+    /// Each renderer is updated on its own: if its new wgsl is invalid, lacks an entry point of the previous version or
+    /// the device rejects its pipelines, it keeps (or is rebuilt with) the last working shader, while the others are updated.
+    /// Returns the errors of the last reload, which stay available in [`ShaderCache::hot_reload_errors`] until the
+    /// next change, e.g. to show them on screen.
+    pub fn hot_reload(
+        &mut self,
+        reload: &mut [&mut dyn HotReload],
+        device: &wgpu::Device,
+    ) -> &[HotReloadError] {
         let Some(watcher) = &mut self.hot_reload_watcher else {
-            return;
+            return &[];
         };
         let Some(paths_changed) = watcher.check_for_changes() else {
-            return;
+            return &self.hot_reload_errors;
         };
 
-        dbg!(&paths_changed);
-
         let mut files_to_reload = HashSet::new();
         for p in paths_changed {
             for e in self.current_wgsl.keys() {
@@ -103,22 +271,84 @@ impl ShaderCache {
             }
         }
 
-        dbg!(reload.len());
+        self.hot_reload_errors.clear();
         for r in reload {
-            let source = r.source();
+            self.hot_reload_if_changed(&mut **r, &files_to_reload, device);
+        }
+        for r in self.hot_reload_registry.alive() {
+            self.hot_reload_if_changed(&mut *r.borrow_mut(), &files_to_reload, device);
+        }
+        &self.hot_reload_errors
+    }
 
-            let mut wgsl = String::new();
-            for f in source.files {
-                wgsl.push_str(self.current_wgsl.get(f).unwrap());
-            }
+    /// The renderers that kept their previous shader in the last hot reload with changes.
+    pub fn hot_reload_errors(&self) -> &[HotReloadError] {
+        &self.hot_reload_errors
+    }
 
-            if let Err(err) = validate_wgsl(&wgsl) {
-                println!("Hot-Reload-Error: {err}");
-            } else {
-                let shader = self.get_shader_module(wgsl, device);
-                r.hot_reload(&shader, device);
+    fn hot_reload_if_changed(
+        &mut self,
+        renderer: &mut dyn HotReload,
+        files_to_reload: &HashSet<ShaderFile>,
+        device: &wgpu::Device,
+    ) {
+        let source = renderer.source();
+        if !source.iter().any(|f| files_to_reload.contains(f)) {
+            return;
+        }
+        if let Err(err) = self.hot_reload_renderer(renderer, source, device) {
+            log::error!("Hot-Reload-Error: {err}");
+            self.hot_reload_errors.push(err);
+        }
+    }
+
+    /// Rebuilds the renderer with the current wgsl of its source, or leaves it with its last working shader.
+    fn hot_reload_renderer(
+        &mut self,
+        renderer: &mut dyn HotReload,
+        source: ShaderSource,
+        device: &wgpu::Device,
+    ) -> Result<(), HotReloadError> {
+        let error = |kind: HotReloadErrorKind, message: String| HotReloadError {
+            files: source.iter().map(|f| f.file).collect(),
+            kind,
+            message,
+        };
+        let wgsl = self.combined_wgsl(&source);
+        let module = validate_wgsl(&wgsl)
+            .map_err(|err| error(HotReloadErrorKind::InvalidWgsl, err.to_string()))?;
+        let last_good = self.last_good_wgsl.get(&source).cloned();
+        if let Some(last_good) = &last_good {
+            if let Ok(last_module) = validate_wgsl(last_good) {
+                if let Some(missing) = missing_entry_points(&last_module, &module) {
+                    return Err(error(HotReloadErrorKind::MissingEntryPoint, missing));
+                }
             }
         }
+
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let shader = self.get_shader_module(wgsl.clone(), device);
+        renderer.hot_reload(&shader, device);
+        let Some(err) = pollster::block_on(device.pop_error_scope()) else {
+            self.last_good_wgsl.insert(source, wgsl);
+            return Ok(());
+        };
+
+        // the renderer may have replaced some of its pipelines already, so it is rebuilt with the last working shader:
+        self.module_cache.remove(&wgsl);
+        if let Some(last_good) = last_good {
+            let shader = self.get_shader_module(last_good, device);
+            renderer.hot_reload(&shader, device);
+        }
+        Err(error(HotReloadErrorKind::Pipeline, err.to_string()))
+    }
+
+    fn combined_wgsl(&self, source: &ShaderSource) -> String {
+        let mut wgsl = String::new();
+        for f in source.iter() {
+            wgsl.push_str(self.current_wgsl.get(f).unwrap());
+        }
+        wgsl
     }
 
     fn add_file(&mut self, file: ShaderFile) {
@@ -162,7 +392,86 @@ impl ShaderCache {
     }
 }
 
-fn validate_wgsl(wgsl: &str) -> anyhow::Result<()> {
-    wgpu::naga::front::wgsl::parse_str(&wgsl)?;
-    Ok(())
+/// Parses and validates the wgsl, like the device does when creating a shader module.
+fn validate_wgsl(wgsl: &str) -> anyhow::Result<naga::Module> {
+    let module = naga::front::wgsl::parse_str(wgsl)?;
+    naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::all(),
+    )
+    .validate(&module)?;
+    Ok(module)
+}
+
+/// A description of the entry points of `old` that `new` does not have, pipelines reference them by name.
+fn missing_entry_points(old: &naga::Module, new: &naga::Module) -> Option<String> {
+    let missing: Vec<String> = old
+        .entry_points
+        .iter()
+        .filter(|e| {
+            !new.entry_points
+                .iter()
+                .any(|n| n.name == e.name && n.stage == e.stage)
+        })
+        .map(|e| format!("{:?} entry point `{}`", e.stage, e.name))
+        .collect();
+    if missing.is_empty() {
+        None
+    } else {
+        Some(missing.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::{missing_entry_points, validate_wgsl, HotReload, HotReloadRegistry, ShaderSource};
+
+    struct Dummy;
+
+    impl HotReload for Dummy {
+        fn source(&self) -> ShaderSource {
+            ShaderSource {
+                files: &[],
+                user_chunk: None,
+            }
+        }
+
+        fn hot_reload(&mut self, _shader: &wgpu::ShaderModule, _device: &wgpu::Device) {}
+    }
+
+    #[test]
+    fn dropped_renderers_are_unregistered() {
+        let mut registry = HotReloadRegistry::default();
+        let kept = Rc::new(RefCell::new(Dummy));
+        let dropped = Rc::new(RefCell::new(Dummy));
+        registry.register(Rc::downgrade(&kept) as _);
+        registry.register(Rc::downgrade(&dropped) as _);
+        assert_eq!(registry.len(), 2);
+        drop(dropped);
+        assert_eq!(registry.alive().len(), 1);
+        assert_eq!(registry.registered.len(), 1);
+    }
+
+    #[test]
+    fn removed_entry_points_are_detected() {
+        let old = validate_wgsl(
+            "@vertex fn vs() -> @builtin(position) vec4<f32> { return vec4(0.0); }
+             @fragment fn fs() -> @location(0) vec4<f32> { return vec4(1.0); }",
+        )
+        .unwrap();
+        let renamed = validate_wgsl(
+            "@vertex fn vs() -> @builtin(position) vec4<f32> { return vec4(0.0); }
+             @fragment fn fs_main() -> @location(0) vec4<f32> { return vec4(1.0); }",
+        )
+        .unwrap();
+        assert_eq!(missing_entry_points(&old, &old), None);
+        assert_eq!(
+            missing_entry_points(&old, &renamed).as_deref(),
+            Some("Fragment entry point `fs`")
+        );
+        // parses, but does not validate:
+        assert!(validate_wgsl("fn f() -> f32 { return 1u; }").is_err());
+    }
 }
@@ -0,0 +1,360 @@
+use std::ops::{Add, Mul, Sub};
+
+use glam::{Vec2, Vec3};
+
+/// Points that curves can be built from, implemented for [`Vec2`] and [`Vec3`].
+///
+/// The default is the origin, the position of curves without any points.
+pub trait SplinePoint:
+    Copy + Default + Add<Output = Self> + Sub<Output = Self> + Mul<f32, Output = Self>
+{
+    fn length(self) -> f32;
+    fn dot(self, other: Self) -> f32;
+}
+
+impl SplinePoint for Vec2 {
+    #[inline]
+    fn length(self) -> f32 {
+        Vec2::length(self)
+    }
+
+    #[inline]
+    fn dot(self, other: Self) -> f32 {
+        Vec2::dot(self, other)
+    }
+}
+
+impl SplinePoint for Vec3 {
+    #[inline]
+    fn length(self) -> f32 {
+        Vec3::length(self)
+    }
+
+    #[inline]
+    fn dot(self, other: Self) -> f32 {
+        Vec3::dot(self, other)
+    }
+}
+
+/// A curve made of cubic segments.
+///
+/// Positions on the whole curve are given by `u` in 0.0..=1.0, where each segment covers the same range of `u`.
+/// That means `u` does not move at constant speed along the curve, use an [`ArcLengthTable`] for that.
+pub trait Curve<P: SplinePoint> {
+    fn segment_count(&self) -> usize;
+    /// Position on a segment at `t` in 0.0..=1.0.
+    fn segment_position(&self, segment: usize, t: f32) -> P;
+    /// Derivative of the position on a segment at `t` in 0.0..=1.0.
+    fn segment_derivative(&self, segment: usize, t: f32) -> P;
+
+    fn position(&self, u: f32) -> P {
+        let (segment, t) = split_u(u, self.segment_count());
+        self.segment_position(segment, t)
+    }
+
+    /// Not normalized, the length is the speed at which the curve is traversed at `u`.
+    fn derivative(&self, u: f32) -> P {
+        let (segment, t) = split_u(u, self.segment_count());
+        self.segment_derivative(segment, t)
+    }
+
+    /// Approximates the curve by `samples_per_segment` lines per segment.
+    fn polyline(&self, samples_per_segment: usize) -> Vec<P> {
+        let n = self.segment_count() * samples_per_segment.max(1);
+        if n == 0 {
+            return vec![];
+        }
+        (0..=n)
+            .map(|i| self.position(i as f32 / n as f32))
+            .collect()
+    }
+
+    /// Returns the `u` of the point on the curve closest to `point`.
+    ///
+    /// Searches the closest of `samples_per_segment` samples per segment and refines around it,
+    /// so it can miss the global minimum for curves that loop back very tightly.
+    fn closest_u(&self, point: P, samples_per_segment: usize) -> f32 {
+        let n = self.segment_count() * samples_per_segment.max(1);
+        if n == 0 {
+            return 0.0;
+        }
+        let dist = |u: f32| (self.position(u) - point).length();
+        let step = 1.0 / n as f32;
+        let best = (0..=n)
+            .map(|i| i as f32 * step)
+            .min_by(|a, b| dist(*a).total_cmp(&dist(*b)))
+            .unwrap();
+
+        // golden section search in the neighborhood of the best sample:
+        const INV_PHI: f32 = 0.618_034;
+        let mut lo = (best - step).max(0.0);
+        let mut hi = (best + step).min(1.0);
+        for _ in 0..24 {
+            let a = hi - (hi - lo) * INV_PHI;
+            let b = lo + (hi - lo) * INV_PHI;
+            if dist(a) < dist(b) {
+                hi = b;
+            } else {
+                lo = a;
+            }
+        }
+        (lo + hi) * 0.5
+    }
+
+    fn closest_point(&self, point: P, samples_per_segment: usize) -> P {
+        self.position(self.closest_u(point, samples_per_segment))
+    }
+
+    fn arc_length_table(&self, samples_per_segment: usize) -> ArcLengthTable {
+        ArcLengthTable::new(self, samples_per_segment)
+    }
+}
+
+/// Maps a `u` in 0.0..=1.0 to a segment and the `t` on that segment.
+fn split_u(u: f32, segment_count: usize) -> (usize, f32) {
+    if segment_count == 0 {
+        return (0, 0.0);
+    }
+    let x = u.clamp(0.0, 1.0) * segment_count as f32;
+    let segment = (x as usize).min(segment_count - 1);
+    (segment, x - segment as f32)
+}
+
+/// Piecewise cubic bezier curve. Consecutive segments share their end and start points.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CubicBezier<P: SplinePoint> {
+    pub segments: Vec<[P; 4]>,
+}
+
+impl<P: SplinePoint> CubicBezier<P> {
+    pub fn new(start: P, control_1: P, control_2: P, end: P) -> Self {
+        Self {
+            segments: vec![[start, control_1, control_2, end]],
+        }
+    }
+
+    /// Builds the curve from `3n + 1` points: start, control, control, end, control, control, end, ...
+    pub fn from_points(points: &[P]) -> Self {
+        assert!(
+            points.len() >= 4 && (points.len() - 1).is_multiple_of(3),
+            "a cubic bezier needs 3n + 1 points, got {}",
+            points.len()
+        );
+        let segments = points
+            .windows(4)
+            .step_by(3)
+            .map(|w| [w[0], w[1], w[2], w[3]])
+            .collect();
+        Self { segments }
+    }
+
+    /// Appends a segment starting at the end of the curve.
+    pub fn add_segment(&mut self, control_1: P, control_2: P, end: P) {
+        let start = self.segments.last().expect("curve has no segments")[3];
+        self.segments.push([start, control_1, control_2, end]);
+    }
+}
+
+impl<P: SplinePoint> Curve<P> for CubicBezier<P> {
+    fn segment_count(&self) -> usize {
+        self.segments.len()
+    }
+
+    fn segment_position(&self, segment: usize, t: f32) -> P {
+        let [a, b, c, d] = self.segments[segment];
+        let s = 1.0 - t;
+        a * (s * s * s) + b * (3.0 * s * s * t) + c * (3.0 * s * t * t) + d * (t * t * t)
+    }
+
+    fn segment_derivative(&self, segment: usize, t: f32) -> P {
+        let [a, b, c, d] = self.segments[segment];
+        let s = 1.0 - t;
+        (b - a) * (3.0 * s * s) + (c - b) * (6.0 * s * t) + (d - c) * (3.0 * t * t)
+    }
+}
+
+/// Catmull-Rom spline, that passes through all of its points.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatmullRom<P: SplinePoint> {
+    pub points: Vec<P>,
+    /// If true, the last point is connected back to the first one.
+    pub closed: bool,
+}
+
+impl<P: SplinePoint> CatmullRom<P> {
+    pub fn new(points: Vec<P>) -> Self {
+        Self {
+            points,
+            closed: false,
+        }
+    }
+
+    pub fn closed(points: Vec<P>) -> Self {
+        Self {
+            points,
+            closed: true,
+        }
+    }
+
+    /// The 4 points influencing a segment. For open curves, the end points are repeated.
+    /// None if the curve has no points.
+    fn segment_points(&self, segment: usize) -> Option<[P; 4]> {
+        if self.points.is_empty() {
+            return None;
+        }
+        let n = self.points.len() as isize;
+        let i = segment as isize;
+        let get = |i: isize| {
+            let i = if self.closed {
+                i.rem_euclid(n)
+            } else {
+                i.clamp(0, n - 1)
+            };
+            self.points[i as usize]
+        };
+        Some([get(i - 1), get(i), get(i + 1), get(i + 2)])
+    }
+}
+
+impl<P: SplinePoint> Curve<P> for CatmullRom<P> {
+    fn segment_count(&self) -> usize {
+        match (self.points.len(), self.closed) {
+            (0 | 1, _) => 0,
+            (n, true) => n,
+            (n, false) => n - 1,
+        }
+    }
+
+    fn segment_position(&self, segment: usize, t: f32) -> P {
+        let Some([p0, p1, p2, p3]) = self.segment_points(segment) else {
+            return P::default();
+        };
+        let t2 = t * t;
+        let t3 = t2 * t;
+        (p1 * 2.0
+            + (p2 - p0) * t
+            + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * t2
+            + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * t3)
+            * 0.5
+    }
+
+    fn segment_derivative(&self, segment: usize, t: f32) -> P {
+        let Some([p0, p1, p2, p3]) = self.segment_points(segment) else {
+            return P::default();
+        };
+        ((p2 - p0)
+            + (p0 * 2.0 - p1 * 5.0 + p2 * 4.0 - p3) * (2.0 * t)
+            + (p1 * 3.0 - p0 - p2 * 3.0 + p3) * (3.0 * t * t))
+            * 0.5
+    }
+}
+
+/// Cumulative lengths along a curve, to move along it at constant speed.
+///
+/// Build it once with [`Curve::arc_length_table`] and rebuild it when the curve changes.
+#[derive(Debug, Clone)]
+pub struct ArcLengthTable {
+    /// distance from the start of the curve at `u = i / (lengths.len() - 1)`.
+    lengths: Vec<f32>,
+}
+
+impl ArcLengthTable {
+    pub fn new<P: SplinePoint, C: Curve<P> + ?Sized>(
+        curve: &C,
+        samples_per_segment: usize,
+    ) -> Self {
+        let n = curve.segment_count() * samples_per_segment.max(1);
+        let mut lengths = Vec::with_capacity(n + 1);
+        lengths.push(0.0);
+        if n == 0 {
+            return Self { lengths };
+        }
+        let mut prev = curve.position(0.0);
+        let mut total = 0.0;
+        for i in 1..=n {
+            let p = curve.position(i as f32 / n as f32);
+            total += (p - prev).length();
+            lengths.push(total);
+            prev = p;
+        }
+        Self { lengths }
+    }
+
+    /// Total length of the curve.
+    pub fn length(&self) -> f32 {
+        *self.lengths.last().unwrap()
+    }
+
+    /// The `u` of the point that is `distance` along the curve from its start.
+    pub fn u_at_distance(&self, distance: f32) -> f32 {
+        let n = self.lengths.len() - 1;
+        if n == 0 || distance <= 0.0 {
+            return 0.0;
+        }
+        if distance >= self.length() {
+            return 1.0;
+        }
+        let i = self.lengths.partition_point(|l| *l < distance).max(1);
+        let (l0, l1) = (self.lengths[i - 1], self.lengths[i]);
+        let frac = if l1 > l0 {
+            (distance - l0) / (l1 - l0)
+        } else {
+            0.0
+        };
+        (i as f32 - 1.0 + frac) / n as f32
+    }
+
+    /// Distance along the curve from its start to the point at `u`.
+    pub fn distance_at_u(&self, u: f32) -> f32 {
+        let n = self.lengths.len() - 1;
+        let x = u.clamp(0.0, 1.0) * n as f32;
+        let i = (x as usize).min(n.saturating_sub(1));
+        let frac = x - i as f32;
+        let next = self.lengths[(i + 1).min(n)];
+        self.lengths[i] + (next - self.lengths[i]) * frac
+    }
+
+    /// Converts a fraction of the total length (0.0..=1.0) into a `u`, for constant speed movement.
+    pub fn u_at_fraction(&self, fraction: f32) -> f32 {
+        self.u_at_distance(fraction * self.length())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{vec2, Vec2};
+
+    use super::{CatmullRom, CubicBezier, Curve};
+
+    #[test]
+    fn arc_length_of_straight_bezier() {
+        // control points bunched up at the start, so u does not move at constant speed:
+        let curve = CubicBezier::new(Vec2::ZERO, vec2(1.0, 0.0), vec2(2.0, 0.0), vec2(10.0, 0.0));
+        let table = curve.arc_length_table(64);
+        assert!((table.length() - 10.0).abs() < 0.001);
+        let half = curve.position(table.u_at_distance(5.0));
+        assert!((half.x - 5.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn catmull_rom_passes_through_points() {
+        let points = vec![Vec2::ZERO, vec2(1.0, 1.0), vec2(2.0, 0.0), vec2(3.0, 1.0)];
+        let curve = CatmullRom::new(points.clone());
+        assert_eq!(curve.segment_count(), 3);
+        for (i, p) in points.iter().enumerate() {
+            assert!((curve.position(i as f32 / 3.0) - *p).length() < 0.0001);
+        }
+        let closest = curve.closest_point(vec2(1.0, 3.0), 16);
+        assert!((closest - vec2(1.0, 1.0)).length() < 0.01);
+    }
+
+    #[test]
+    fn empty_catmull_rom_is_at_the_origin() {
+        for curve in [CatmullRom::<Vec2>::new(vec![]), CatmullRom::closed(vec![])] {
+            assert_eq!(curve.segment_count(), 0);
+            assert_eq!(curve.position(0.5), Vec2::ZERO);
+            assert_eq!(curve.derivative(0.5), Vec2::ZERO);
+            assert!(curve.polyline(8).is_empty());
+        }
+    }
+}
@@ -2,6 +2,7 @@
 
 use std::{borrow::Cow, sync::OnceLock};
 
+use anyhow::anyhow;
 use glam::{vec2, Vec2};
 use image::RgbaImage;
 use wgpu::{BindGroupDescriptor, BindGroupLayout};
@@ -10,6 +11,19 @@ use crate::GraphicsContext;
 
 pub type BindableTextureRef = &'static BindableTexture;
 
+/// Something the ui can draw: a bind group with the [`rgba_bind_group_layout_cached`] layout and the size of the texture.
+///
+/// Implemented by [`BindableTexture`] for textures owned by the bind group, and [`TextureViewBinding`]
+/// for textures owned by something else, e.g. a [`crate::RenderTarget`] with a live 3d preview.
+pub trait UiTexture: std::fmt::Debug {
+    fn bind_group(&self) -> &wgpu::BindGroup;
+    /// size in px, used to tile or keep the aspect ratio of the texture in a div.
+    fn size(&self) -> Vec2;
+}
+
+/// The texture of a [`crate::ui::TextureRegion`], `'static` because the batches of the ui point to it.
+pub type UiTextureRef = &'static dyn UiTexture;
+
 #[derive(Debug)]
 pub struct BindableTexture {
     pub texture: Texture,
@@ -123,6 +137,108 @@ impl BindableTexture {
     }
 }
 
+impl UiTexture for BindableTexture {
+    fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+
+    fn size(&self) -> Vec2 {
+        BindableTexture::size(self)
+    }
+}
+
+/// Binds a view and sampler of a texture that is owned elsewhere with the [`rgba_bind_group_layout_cached`] layout,
+/// so the ui can show it without copying it into an rgba texture, e.g. the resolved texture of a [`crate::RenderTarget`]
+/// rendered from a minimap or portrait camera:
+///
+/// ```rust,ignore
+/// let binding = leak(TextureViewBinding::from_texture(&device, &target.resolved().bindable().texture)?);
+/// div().style(|s| s.texture(binding.texture_region()))
+/// ```
+///
+/// The bind group keeps the texture alive, but it does not follow the owner when it recreates its textures
+/// (e.g. [`crate::RenderTarget::resize`]), so create a new binding after that.
+#[derive(Debug)]
+pub struct TextureViewBinding {
+    pub bind_group: wgpu::BindGroup,
+    pub size: Vec2,
+}
+
+impl TextureViewBinding {
+    /// Fails if the texture cannot be bound with the rgba layout: it needs to be a single sampled 2d texture
+    /// with a filterable float format (e.g. rgba8, the hdr formats or r8) and `TEXTURE_BINDING` usage.
+    pub fn new(
+        device: &wgpu::Device,
+        texture: &wgpu::Texture,
+        view: &wgpu::TextureView,
+        sampler: &wgpu::Sampler,
+    ) -> anyhow::Result<Self> {
+        if texture.dimension() != wgpu::TextureDimension::D2 || texture.sample_count() != 1 {
+            return Err(anyhow!(
+                "Texture needs to be 2d and single sampled to be shown in the ui, got {:?} with {} samples",
+                texture.dimension(),
+                texture.sample_count()
+            ));
+        }
+        if !texture
+            .usage()
+            .contains(wgpu::TextureUsages::TEXTURE_BINDING)
+        {
+            return Err(anyhow!(
+                "Texture needs TEXTURE_BINDING usage to be shown in the ui"
+            ));
+        }
+        let format = texture.format();
+        let sample_type = format.sample_type(None, Some(device.features()));
+        if sample_type != Some(wgpu::TextureSampleType::Float { filterable: true }) {
+            return Err(anyhow!(
+                "Texture format {format:?} is not filterable, cannot be shown in the ui"
+            ));
+        }
+        let bind_group = device.create_bind_group(&BindGroupDescriptor {
+            label: None,
+            layout: rgba_bind_group_layout_cached(device),
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+        });
+        Ok(TextureViewBinding {
+            bind_group,
+            size: vec2(texture.width() as f32, texture.height() as f32),
+        })
+    }
+
+    /// Binds the view and sampler of `texture`, e.g. `HdrTexture::bindable().texture`.
+    pub fn from_texture(device: &wgpu::Device, texture: &Texture) -> anyhow::Result<Self> {
+        Self::new(device, &texture.texture, &texture.view, &texture.sampler)
+    }
+
+    /// The entire texture, to be used in [`crate::ui::element::DivStyle::texture`].
+    pub fn texture_region(&'static self) -> crate::ui::TextureRegion {
+        crate::ui::TextureRegion {
+            texture: self,
+            uv: crate::Aabb::UNIT,
+        }
+    }
+}
+
+impl UiTexture for TextureViewBinding {
+    fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+
+    fn size(&self) -> Vec2 {
+        self.size
+    }
+}
+
 pub fn create_white_px_texture(device: &wgpu::Device, queue: &wgpu::Queue) -> BindableTexture {
     let texture = Texture::create_white_px_texture(device, queue);
     BindableTexture::new(device, texture)
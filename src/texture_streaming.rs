@@ -0,0 +1,401 @@
+//! Loading very large images without stalling the frame.
+//!
+//! Decoding an 8k png and copying it to the gpu with [`crate::Texture::from_image`] takes long enough
+//! to drop frames. A [`TextureStreamer`] decodes images and builds their mip chains on a background thread,
+//! then uploads the mip levels over multiple frames, starting with the smallest. While that happens the
+//! [`StreamedTexture`] shows a placeholder color and then the most detailed level that is complete:
+//!
+//! ```rust,ignore
+//! let mut streamer = TextureStreamer::new(&ctx, Color::u8_srgb(40, 40, 50));
+//! let background = streamer.load("assets/background_8k.png");
+//! // every frame:
+//! streamer.update();
+//! let region = streamer.get(&background).unwrap().texture_region();
+//! div().style(|s| s.texture(region))
+//! ```
+
+use std::{
+    collections::VecDeque,
+    sync::mpsc::{channel, Receiver, Sender},
+};
+
+use glam::{uvec2, UVec2};
+use image::{imageops::FilterType, RgbaImage};
+
+use crate::{
+    asset::{AssetId, AssetServer, AssetT, Handle},
+    leak,
+    texture::UiTextureRef,
+    ui::TextureRegion,
+    Aabb, BindableTexture, Color, GraphicsContext, Texture, TextureViewBinding,
+};
+
+/// Default of [`TextureStreamer::budget_per_frame`]: 4 MiB, a 1024x1024 rgba image.
+pub const DEFAULT_STREAMING_BUDGET: usize = 4 << 20;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamState {
+    /// The image is loaded and decoded on the background thread, the placeholder is shown.
+    Decoding,
+    /// Mip levels are uploaded, `level` is the most detailed one that is shown, if any.
+    Streaming {
+        level: Option<u32>,
+    },
+    /// All mip levels are uploaded.
+    Done,
+    Failed(String),
+}
+
+/// An image that is streamed to the gpu by a [`TextureStreamer`].
+#[derive(Debug)]
+pub struct StreamedTexture {
+    /// the placeholder or the binding of the most detailed complete mip level.
+    binding: UiTextureRef,
+    /// the whole mip chain, created when the image is decoded.
+    texture: Option<Texture>,
+    state: StreamState,
+}
+
+impl StreamedTexture {
+    /// The placeholder or the image with the mip levels that are uploaded so far.
+    pub fn texture_region(&self) -> TextureRegion {
+        TextureRegion {
+            texture: self.binding,
+            uv: Aabb::UNIT,
+        }
+    }
+
+    /// Bind group with the [`crate::texture::rgba_bind_group_layout_cached`] layout, for other renderers than the ui.
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        self.binding.bind_group()
+    }
+
+    /// Size of the full image in px, None while it is decoded.
+    pub fn size(&self) -> Option<UVec2> {
+        let size = self.texture.as_ref()?.size;
+        Some(uvec2(size.width, size.height))
+    }
+
+    pub fn state(&self) -> &StreamState {
+        &self.state
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.state == StreamState::Done
+    }
+}
+
+/// Loads images on a background thread and uploads them to the gpu with a budget per frame, see the module docs.
+///
+/// Call [`TextureStreamer::update`] once per frame. The bindings for the mip levels are leaked to be usable
+/// in the ui, like the pages of [`crate::ui::UiTextureAtlas`], so unloading a streamed texture does not free its gpu memory.
+pub struct TextureStreamer {
+    ctx: GraphicsContext,
+    assets: AssetServer<StreamedTexture>,
+    /// Bytes copied to textures per frame. At least one row of a mip level is copied in each frame, so
+    /// streaming always makes progress.
+    pub budget_per_frame: usize,
+    placeholder: UiTextureRef,
+    jobs: Sender<(AssetId<StreamedTexture>, String)>,
+    decoded: Receiver<(AssetId<StreamedTexture>, anyhow::Result<Vec<RgbaImage>>)>,
+    uploads: VecDeque<Upload>,
+    n_decoding: usize,
+    bytes_last_frame: usize,
+}
+
+/// Mip levels of a decoded image that are not uploaded yet.
+struct Upload {
+    id: AssetId<StreamedTexture>,
+    /// index is the mip level, levels are dropped when they are uploaded.
+    mips: Vec<RgbaImage>,
+    /// level that is uploaded right now, counts down to 0.
+    level: usize,
+    /// first row of `level` that is not uploaded yet.
+    row: u32,
+}
+
+impl TextureStreamer {
+    /// Textures show a single pixel of `placeholder` until their first mip levels are uploaded.
+    pub fn new(ctx: &GraphicsContext, placeholder: Color) -> Self {
+        let pixel = RgbaImage::from_pixel(1, 1, image::Rgba(placeholder.to_u8_srgb()));
+        let placeholder = Texture::from_image(
+            &ctx.device,
+            &ctx.queue,
+            &pixel,
+            wgpu::FilterMode::Nearest,
+            wgpu::AddressMode::ClampToEdge,
+        );
+        let placeholder: &'static BindableTexture =
+            leak(BindableTexture::new(&ctx.device, placeholder));
+
+        let (jobs, job_rx) = channel::<(AssetId<StreamedTexture>, String)>();
+        let (decoded_tx, decoded) = channel();
+        std::thread::Builder::new()
+            .name("texture streaming".into())
+            .spawn(move || {
+                // ends when the streamer and with it the sender of the jobs is dropped.
+                while let Ok((id, path)) = job_rx.recv() {
+                    let mips = RgbaImage::load(&path).map(mip_chain);
+                    if decoded_tx.send((id, mips)).is_err() {
+                        break;
+                    }
+                }
+            })
+            .expect("could not spawn the texture streaming thread");
+
+        TextureStreamer {
+            ctx: ctx.clone(),
+            assets: AssetServer::new(),
+            budget_per_frame: DEFAULT_STREAMING_BUDGET,
+            placeholder,
+            jobs,
+            decoded,
+            uploads: VecDeque::new(),
+            n_decoding: 0,
+            bytes_last_frame: 0,
+        }
+    }
+
+    /// Starts loading the image at `path` in the background. If a texture from this path is still loaded,
+    /// a handle to it is returned instead.
+    pub fn load(&mut self, path: &str) -> Handle<StreamedTexture> {
+        if let Some(handle) = self.assets.reuse_path(path) {
+            return handle;
+        }
+        let texture = StreamedTexture {
+            binding: self.placeholder,
+            texture: None,
+            state: StreamState::Decoding,
+        };
+        let handle = self.assets.insert(texture, Some(path.to_string()));
+        _ = self.jobs.send((handle.id(), path.to_string()));
+        self.n_decoding += 1;
+        handle
+    }
+
+    pub fn get(&self, handle: &Handle<StreamedTexture>) -> Option<&StreamedTexture> {
+        self.assets.get(handle)
+    }
+
+    /// The streamed textures, e.g. to unload the unused ones. [`crate::AssetEvent::Modified`] is sent whenever
+    /// a more detailed mip level is shown.
+    pub fn assets(&mut self) -> &mut AssetServer<StreamedTexture> {
+        &mut self.assets
+    }
+
+    /// Number of textures that are decoded or uploaded right now.
+    pub fn n_streaming(&self) -> usize {
+        self.n_decoding + self.uploads.len()
+    }
+
+    /// Bytes copied to textures in the last [`TextureStreamer::update`].
+    pub fn bytes_last_frame(&self) -> usize {
+        self.bytes_last_frame
+    }
+
+    /// Creates textures for the images that finished decoding and uploads mip levels within the budget.
+    pub fn update(&mut self) {
+        while let Ok((id, mips)) = self.decoded.try_recv() {
+            self.n_decoding -= 1;
+            self.start_upload(id, mips);
+        }
+
+        let mut budget = self.budget_per_frame;
+        let mut uploaded_any = false;
+        self.bytes_last_frame = 0;
+        while let Some(upload) = self.uploads.front_mut() {
+            if budget == 0 && uploaded_any {
+                break;
+            }
+            let Some(texture) = self.assets.get_by_id(upload.id) else {
+                // unloaded before it was done
+                self.uploads.pop_front();
+                continue;
+            };
+            let texture = texture.texture.as_ref().unwrap();
+            let bytes = upload_rows(&self.ctx.queue, texture, upload, budget);
+            budget = budget.saturating_sub(bytes);
+            self.bytes_last_frame += bytes;
+            uploaded_any = true;
+
+            let mip = &upload.mips[upload.level];
+            if upload.row < mip.height() {
+                continue;
+            }
+            // level complete, show it and continue with the next larger one.
+            upload.mips[upload.level] = RgbaImage::new(0, 0);
+            let level = upload.level as u32;
+            let id = upload.id;
+            let done = upload.level == 0;
+            if done {
+                self.uploads.pop_front();
+            } else {
+                upload.level -= 1;
+                upload.row = 0;
+            }
+            self.show_level(id, level, done);
+        }
+    }
+
+    fn start_upload(&mut self, id: AssetId<StreamedTexture>, mips: anyhow::Result<Vec<RgbaImage>>) {
+        let device = &self.ctx.device;
+        let Some(streamed) = self.assets.handle(id) else {
+            return;
+        };
+        let Some(streamed) = self.assets.get_mut(&streamed) else {
+            return;
+        };
+        let mips = match mips {
+            Ok(mips) => mips,
+            Err(err) => {
+                log::error!("Could not load streamed texture: {err}");
+                streamed.state = StreamState::Failed(err.to_string());
+                return;
+            }
+        };
+        let size = wgpu::Extent3d {
+            width: mips[0].width(),
+            height: mips[0].height(),
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("streamed texture"),
+            size,
+            mip_level_count: mips.len() as u32,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&Default::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            mipmap_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        streamed.texture = Some(Texture {
+            label: None,
+            texture,
+            view,
+            sampler,
+            size,
+        });
+        streamed.state = StreamState::Streaming { level: None };
+        self.uploads.push_back(Upload {
+            id,
+            level: mips.len() - 1,
+            mips,
+            row: 0,
+        });
+    }
+
+    /// Binds the mip levels from `level` on, the texture is sampled only from the uploaded levels.
+    fn show_level(&mut self, id: AssetId<StreamedTexture>, level: u32, done: bool) {
+        let Some(handle) = self.assets.handle(id) else {
+            return;
+        };
+        let Some(streamed) = self.assets.get_mut(&handle) else {
+            return;
+        };
+        let texture = streamed.texture.as_ref().unwrap();
+        let view = texture.texture.create_view(&wgpu::TextureViewDescriptor {
+            base_mip_level: level,
+            ..Default::default()
+        });
+        let binding =
+            TextureViewBinding::new(&self.ctx.device, &texture.texture, &view, &texture.sampler)
+                .expect("streamed textures are rgba8 with texture binding usage");
+        streamed.binding = leak(binding);
+        streamed.state = if done {
+            StreamState::Done
+        } else {
+            StreamState::Streaming { level: Some(level) }
+        };
+    }
+}
+
+/// Copies rows of the current mip level to the texture, as many as fit into the `budget` but at least one.
+/// Returns the number of bytes copied.
+fn upload_rows(
+    queue: &wgpu::Queue,
+    texture: &Texture,
+    upload: &mut Upload,
+    budget: usize,
+) -> usize {
+    let mip = &upload.mips[upload.level];
+    let (width, height) = mip.dimensions();
+    let row_bytes = 4 * width as usize;
+    let rows = (budget / row_bytes).clamp(1, (height - upload.row) as usize) as u32;
+    let start = upload.row as usize * row_bytes;
+    let end = start + rows as usize * row_bytes;
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            aspect: wgpu::TextureAspect::All,
+            texture: &texture.texture,
+            mip_level: upload.level as u32,
+            origin: wgpu::Origin3d {
+                x: 0,
+                y: upload.row,
+                z: 0,
+            },
+        },
+        &mip.as_raw()[start..end],
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(row_bytes as u32),
+            rows_per_image: Some(rows),
+        },
+        wgpu::Extent3d {
+            width,
+            height: rows,
+            depth_or_array_layers: 1,
+        },
+    );
+    upload.row += rows;
+    end - start
+}
+
+/// The image and its downscaled versions, each half the size of the one before (rounded down), down to 1x1.
+/// Matches the sizes of the mip levels of a texture.
+fn mip_chain(image: RgbaImage) -> Vec<RgbaImage> {
+    let mut mips = vec![image];
+    loop {
+        let last = mips.last().unwrap();
+        let (width, height) = last.dimensions();
+        if width <= 1 && height <= 1 {
+            break;
+        }
+        let next = image::imageops::resize(
+            last,
+            (width / 2).max(1),
+            (height / 2).max(1),
+            FilterType::Triangle,
+        );
+        mips.push(next);
+    }
+    mips
+}
+
+#[cfg(test)]
+mod tests {
+    use image::RgbaImage;
+
+    use super::mip_chain;
+
+    #[test]
+    fn mip_chain_matches_texture_mip_sizes() {
+        let mips = mip_chain(RgbaImage::new(10, 3));
+        let sizes: Vec<(u32, u32)> = mips.iter().map(|m| m.dimensions()).collect();
+        assert_eq!(sizes, vec![(10, 3), (5, 1), (2, 1), (1, 1)]);
+        let size = wgpu::Extent3d {
+            width: 10,
+            height: 3,
+            depth_or_array_layers: 1,
+        };
+        assert_eq!(mips.len() as u32, size.max_mips(wgpu::TextureDimension::D2));
+    }
+}
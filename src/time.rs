@@ -72,6 +72,14 @@ impl Time {
         self.frame_count += 1;
         self.stats.recalculate(&self.delta_times);
     }
+
+    /// Advances by a fixed `delta` instead of the measured frame time, for simulating ahead of time,
+    /// e.g. in [`crate::ParticleSystem::warm_up`]. Does not touch the fps stats.
+    pub fn advance(&mut self, delta: Duration) {
+        self.delta_time = delta;
+        self.total_time += delta;
+        self.frame_count += 1;
+    }
 }
 
 impl Time {
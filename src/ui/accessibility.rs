@@ -0,0 +1,396 @@
+//! Exposing the ui to screen readers and other assistive technology.
+//!
+//! Divs get a role and a label with [`crate::ui::element::DivStyle::accessible`], texts are exposed with their content.
+//! With the `accessibility` feature, a [`crate::ui::Board`] exports its elements as an [AccessKit](https://accesskit.dev)
+//! tree that a platform adapter like `accesskit_winit` hands to the operating system:
+//!
+//! ```rust,ignore
+//! div().style(|s| s.accessible(AccessRole::Button, "Start game")).child(text("Start"))
+//!
+//! // initial tree, when the adapter asks for it:
+//! board.accessibility_tree(&screen.ui_transform())
+//! // every frame after the board was built, only the nodes that changed:
+//! if let Some(update) = board.accessibility_update(&screen.ui_transform()) {
+//!     adapter.update_if_active(|| update);
+//! }
+//! // activation requests of assistive technology, e.g. from `accesskit_winit::ActionRequestEvent`:
+//! board.ctx.handle_access_action(&request);
+//! ```
+//!
+//! Activating an element counts as a click on it in the next frame, see [`crate::ui::ElementContext::activate`].
+//! Divs without role are not part of the tree, their accessible children are attached to the closest
+//! accessible ancestor. Node ids are the [`crate::ui::ElementId`]s of the elements, so activation only works for
+//! elements stored with an id, like all interaction.
+
+use super::element::UiString;
+
+/// What a div is for assistive technology, see [`crate::ui::element::DivStyle::accessible`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessRole {
+    Button,
+    CheckBox,
+    Link,
+    Slider,
+    ProgressBar,
+    TextInput,
+    Image,
+    Heading,
+    List,
+    ListItem,
+    /// A group of related elements, e.g. a panel or a dialog.
+    Group,
+}
+
+impl AccessRole {
+    /// Roles that react to clicks and can be activated by assistive technology.
+    pub fn is_clickable(&self) -> bool {
+        matches!(
+            self,
+            AccessRole::Button | AccessRole::CheckBox | AccessRole::Link
+        )
+    }
+}
+
+/// Role and label of an accessible div.
+#[derive(Debug, Clone)]
+pub struct AccessInfo {
+    pub role: AccessRole,
+    /// Read out by screen readers. If None, the texts inside of the div are used.
+    pub label: Option<UiString>,
+}
+
+#[cfg(feature = "accessibility")]
+pub use export::*;
+
+#[cfg(feature = "accessibility")]
+mod export {
+    use accesskit::{
+        Action, ActionRequest, DefaultActionVerb, Node, NodeBuilder, NodeClassSet, NodeId, Rect,
+        Role, Tree, TreeUpdate,
+    };
+    use ahash::AHashMap;
+
+    use crate::{
+        ui::{
+            element::Section, element_id::ElementId, Board, ElementBox, ElementContext,
+            ElementWithComputed,
+        },
+        UiTransform,
+    };
+
+    use super::AccessRole;
+
+    /// Id of the root node of every board, element ids are never 0.
+    pub const ROOT_NODE_ID: NodeId = NodeId(0);
+
+    /// The nodes of the last [`Board::accessibility_update`], to only send what changed.
+    pub(crate) type AccessNodes = AHashMap<NodeId, Node>;
+
+    impl From<AccessRole> for Role {
+        fn from(role: AccessRole) -> Self {
+            match role {
+                AccessRole::Button => Role::Button,
+                AccessRole::CheckBox => Role::CheckBox,
+                AccessRole::Link => Role::Link,
+                AccessRole::Slider => Role::Slider,
+                AccessRole::ProgressBar => Role::ProgressIndicator,
+                AccessRole::TextInput => Role::TextInput,
+                AccessRole::Image => Role::Image,
+                AccessRole::Heading => Role::Heading,
+                AccessRole::List => Role::List,
+                AccessRole::ListItem => Role::ListItem,
+                AccessRole::Group => Role::Group,
+            }
+        }
+    }
+
+    impl Board {
+        /// The whole accessibility tree of the board, with bounds in screen pixels of the `transform`.
+        pub fn accessibility_tree(&self, transform: &UiTransform) -> TreeUpdate {
+            let nodes = self.access_nodes(transform);
+            TreeUpdate {
+                nodes,
+                tree: Some(Tree {
+                    toolkit_name: Some("tgf".into()),
+                    ..Tree::new(ROOT_NODE_ID)
+                }),
+                focus: ROOT_NODE_ID,
+            }
+        }
+
+        /// The nodes that changed since the last call, e.g. because elements moved or are hovered now.
+        /// None if nothing changed. The first call contains all nodes.
+        pub fn accessibility_update(&mut self, transform: &UiTransform) -> Option<TreeUpdate> {
+            let nodes = self.access_nodes(transform);
+            let changed: Vec<(NodeId, Node)> = nodes
+                .iter()
+                .filter(|(id, node)| self.last_access_nodes.get(id) != Some(node))
+                .cloned()
+                .collect();
+            let is_first = self.last_access_nodes.is_empty();
+            self.last_access_nodes = nodes.into_iter().collect();
+            if changed.is_empty() {
+                return None;
+            }
+            Some(TreeUpdate {
+                nodes: changed,
+                tree: is_first.then(|| Tree {
+                    toolkit_name: Some("tgf".into()),
+                    ..Tree::new(ROOT_NODE_ID)
+                }),
+                focus: ROOT_NODE_ID,
+            })
+        }
+
+        fn access_nodes(&self, transform: &UiTransform) -> Vec<(NodeId, Node)> {
+            let mut exporter = Exporter {
+                ctx: &self.ctx,
+                transform,
+                classes: NodeClassSet::new(),
+                nodes: vec![],
+            };
+            let mut root = NodeBuilder::new(Role::Window);
+            let mut children = vec![];
+            exporter.visit(&self.element, ElementId::from(0u64), &mut children);
+            root.set_children(children);
+            let root = root.build(&mut exporter.classes);
+            exporter.nodes.push((ROOT_NODE_ID, root));
+            exporter.nodes
+        }
+    }
+
+    impl ElementContext {
+        /// Routes a request of assistive technology to the elements: [`Action::Default`] activates the element
+        /// like a click, see [`ElementContext::activate`]. Other actions are ignored.
+        pub fn handle_access_action(&mut self, request: &ActionRequest) {
+            if request.action != Action::Default {
+                return;
+            }
+            if let Some(id) = std::num::NonZeroU64::new(request.target.0) {
+                self.activate(ElementId(id));
+            }
+        }
+    }
+
+    struct Exporter<'a> {
+        ctx: &'a ElementContext,
+        transform: &'a UiTransform,
+        classes: NodeClassSet,
+        nodes: Vec<(NodeId, Node)>,
+    }
+
+    impl Exporter<'_> {
+        /// Adds the accessible elements of the subtree, pushing the ids of the topmost ones to `parent_children`.
+        /// Elements without id get ids from their position in the tree, `path` is the id of the parent.
+        fn visit(
+            &mut self,
+            element: &ElementBox,
+            path: ElementId,
+            parent_children: &mut Vec<NodeId>,
+        ) {
+            let id = if element.id.is_none() {
+                path
+            } else {
+                element.id
+            };
+            match &element.element {
+                ElementWithComputed::Div(d) => {
+                    let (div, computed) = &**d;
+                    let Some(access) = div.extras.as_ref().and_then(|e| e.access.as_ref()) else {
+                        for (i, child) in div.children.iter().enumerate() {
+                            self.visit(child, id + i, parent_children);
+                        }
+                        return;
+                    };
+                    let mut node = NodeBuilder::new(access.role.into());
+                    let name = match &access.label {
+                        Some(label) => Some(label.to_string()),
+                        None => {
+                            let mut name = String::new();
+                            collect_text(element, &mut name);
+                            (!name.is_empty()).then_some(name)
+                        }
+                    };
+                    let mut children = vec![];
+                    if let Some(name) = name {
+                        node.set_name(name);
+                    }
+                    // texts inside of elements without label are their name already.
+                    if access.label.is_some() {
+                        for (i, child) in div.children.iter().enumerate() {
+                            self.visit(child, id + i, &mut children);
+                        }
+                    } else {
+                        for (i, child) in div.children.iter().enumerate() {
+                            self.visit_divs(child, id + i, &mut children);
+                        }
+                    }
+                    node.set_children(children);
+                    let min = self.transform.layout_to_screen(computed.bounds.pos);
+                    let max = self
+                        .transform
+                        .layout_to_screen(computed.bounds.pos + computed.bounds.size);
+                    node.set_bounds(Rect::new(min.x, min.y, max.x, max.y));
+                    if self.ctx.state_of(id).hovered {
+                        node.set_hovered();
+                    }
+                    if access.role.is_clickable() && !element.id.is_none() {
+                        node.add_action(Action::Default);
+                        node.set_default_action_verb(DefaultActionVerb::Click);
+                    }
+                    let node_id = NodeId(id.0.get());
+                    self.nodes.push((node_id, node.build(&mut self.classes)));
+                    parent_children.push(node_id);
+                }
+                ElementWithComputed::Text(t) => {
+                    let (text, computed) = &**t;
+                    let mut name = String::new();
+                    collect_text(element, &mut name);
+                    if !name.is_empty() {
+                        let mut node = NodeBuilder::new(Role::StaticText);
+                        node.set_name(name);
+                        let min = self.transform.layout_to_screen(computed.bounds.pos);
+                        let max = self
+                            .transform
+                            .layout_to_screen(computed.bounds.pos + computed.bounds.size);
+                        node.set_bounds(Rect::new(min.x, min.y, max.x, max.y));
+                        let node_id = NodeId(id.0.get());
+                        self.nodes.push((node_id, node.build(&mut self.classes)));
+                        parent_children.push(node_id);
+                    }
+                    let elements = text.sections.iter().filter_map(|s| match s {
+                        Section::Element { element, .. } => Some(element),
+                        Section::Text(_) => None,
+                    });
+                    for (i, child) in elements.enumerate() {
+                        self.visit_divs(child, id + i, parent_children);
+                    }
+                }
+            }
+        }
+
+        /// Like [`Exporter::visit`], but skips texts, for the children of divs that are named by their texts.
+        fn visit_divs(
+            &mut self,
+            element: &ElementBox,
+            path: ElementId,
+            parent_children: &mut Vec<NodeId>,
+        ) {
+            let id = if element.id.is_none() {
+                path
+            } else {
+                element.id
+            };
+            match &element.element {
+                ElementWithComputed::Div(d)
+                    if d.0.extras.as_ref().is_some_and(|e| e.access.is_some()) =>
+                {
+                    self.visit(element, path, parent_children)
+                }
+                ElementWithComputed::Div(d) => {
+                    for (i, child) in d.0.children.iter().enumerate() {
+                        self.visit_divs(child, id + i, parent_children);
+                    }
+                }
+                ElementWithComputed::Text(_) => {}
+            }
+        }
+    }
+
+    /// Appends the strings of all texts in the subtree, separated by spaces.
+    fn collect_text(element: &ElementBox, out: &mut String) {
+        match &element.element {
+            ElementWithComputed::Div(d) => {
+                for child in d.0.children.iter() {
+                    collect_text(child, out);
+                }
+            }
+            ElementWithComputed::Text(t) => {
+                for section in t.0.sections.iter() {
+                    match section {
+                        Section::Text(section) => {
+                            let string = section.string.trim();
+                            if !string.is_empty() {
+                                if !out.is_empty() {
+                                    out.push(' ');
+                                }
+                                out.push_str(string);
+                            }
+                        }
+                        Section::Element { element, .. } => collect_text(element, out),
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "accessibility"))]
+mod tests {
+    use accesskit::{Action, ActionRequest, NodeId, Role};
+    use glam::dvec2;
+
+    use crate::{
+        ui::{div, AccessRole, Board, ElementId, IntoElementBox},
+        MouseButtonState, UiScaling, UiTransform,
+    };
+
+    use super::ROOT_NODE_ID;
+
+    #[test]
+    fn buttons_are_exported_and_activated() {
+        let button = ElementId::from("start");
+        let element = || {
+            div()
+                .style(|s| s.padding.left = 10.0)
+                .child(div().child_with_id(
+                    button,
+                    div().style(|s| {
+                        s.size(100, 40);
+                        s.accessible(AccessRole::Button, "Start game");
+                    }),
+                ))
+                .store()
+        };
+        let mut board = Board::new(element(), dvec2(400.0, 300.0));
+        let transform = UiTransform::new(
+            winit::dpi::PhysicalSize::new(800, 600),
+            UiScaling::Letterbox(dvec2(400.0, 300.0)),
+        );
+
+        let tree = board.accessibility_tree(&transform);
+        let (_, root) = tree
+            .nodes
+            .iter()
+            .find(|(id, _)| *id == ROOT_NODE_ID)
+            .unwrap();
+        assert_eq!(root.children(), &[NodeId(button.0.get())]);
+        let (_, node) = tree
+            .nodes
+            .iter()
+            .find(|(id, _)| id.0 == button.0.get())
+            .unwrap();
+        assert_eq!(node.role(), Role::Button);
+        assert_eq!(node.name(), Some("Start game"));
+        let bounds = node.bounds().unwrap();
+        assert_eq!((bounds.x0, bounds.x1, bounds.y1), (20.0, 220.0, 80.0));
+
+        assert!(board.accessibility_update(&transform).is_some());
+        board.set_element(element());
+        assert!(board.accessibility_update(&transform).is_none());
+
+        board.ctx.handle_access_action(&ActionRequest {
+            action: Action::Default,
+            target: NodeId(button.0.get()),
+            data: None,
+        });
+        board
+            .ctx
+            .start_frame(dvec2(-1.0, -1.0), MouseButtonState::default(), 0.0);
+        assert!(board.ctx.state_of(button).just_ended_click);
+        board
+            .ctx
+            .start_frame(dvec2(-1.0, -1.0), MouseButtonState::default(), 0.0);
+        assert!(!board.ctx.state_of(button).just_ended_click);
+    }
+}
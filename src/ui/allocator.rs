@@ -1,24 +1,25 @@
-use std::mem::size_of;
-use std::ptr::{read, write, NonNull};
+use std::mem::{align_of, size_of};
+use std::ptr::{null_mut, read, write, NonNull};
 
 /// The slab allocator allocates a region of memory upfront.
 /// You can allocate an element into it, or remove it from the allocator.
 /// This can leave holes in the allocated space, but they are filled as
 /// soon as the next element is allocated, because all the holes form a
-/// linked list of indices, the head of the list being `self.next_slot`
-/// and the tail of the list always being the usize::MAX value.
+/// linked list, the head of the list being `self.next_free` and each hole
+/// storing a pointer to the next one, the tail pointing at null.
+///
+/// When all regions are full, another region of the same size is allocated.
+/// Elements never move, so pointers to them stay valid until they are deallocated.
 pub struct SlabAllocator<T> {
-    ptr: *mut u8,
-
-    /// we have reserved memory for `cap` elements.
-    cap: usize,
+    /// regions with space for `chunk_cap` elements each.
+    chunks: Vec<*mut T>,
+    chunk_cap: usize,
+    /// slots of the last chunk that were ever used, filled or holes.
+    /// Always: last_chunk_len <= chunk_cap.
+    last_chunk_len: usize,
     /// there are len elements with actual data in them
     len: usize,
-    /// There are max_len elements in total, some of them filled, some of them empty.
-    /// Always: len <= max_len <= cap.
-    max_len: usize,
-    next_slot: usize,
-    marker: std::marker::PhantomData<T>,
+    next_free: *mut T,
 }
 
 #[derive(Debug)]
@@ -37,64 +38,80 @@ impl<T> SlabPtr<T> {
 }
 
 impl<T> SlabAllocator<T> {
-    pub fn new(cap: usize) -> Self {
-        // (important to to have empty cells be big enough to write usize values into them pointing at the next free slot).
-        assert!(size_of::<T>() >= size_of::<usize>());
-        let layout = std::alloc::Layout::array::<T>(cap).unwrap();
-        let ptr = unsafe { std::alloc::alloc(layout) };
-        unsafe {
-            write(ptr as *mut usize, 1);
-        }
-
-        SlabAllocator {
-            cap,
+    /// `chunk_cap` elements fit into the first region and into every region allocated later.
+    pub fn new(chunk_cap: usize) -> Self {
+        // (important to to have empty cells be big enough to write pointers into them pointing at the next free slot).
+        assert!(size_of::<T>() >= size_of::<*mut T>());
+        assert!(align_of::<T>() >= align_of::<*mut T>());
+        assert!(chunk_cap > 0);
+        let mut allocator = SlabAllocator {
+            chunks: vec![],
+            chunk_cap,
+            last_chunk_len: 0,
             len: 0,
-            max_len: 0,
-            ptr,
-            next_slot: usize::MAX,
-            marker: std::marker::PhantomData,
+            next_free: null_mut(),
+        };
+        allocator.add_chunk();
+        allocator
+    }
+
+    /// Number of allocated elements.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Number of elements that fit without allocating another region.
+    pub fn capacity(&self) -> usize {
+        self.chunks.len() * self.chunk_cap
+    }
+
+    fn add_chunk(&mut self) {
+        let layout = std::alloc::Layout::array::<T>(self.chunk_cap).unwrap();
+        let ptr = unsafe { std::alloc::alloc(layout) } as *mut T;
+        if ptr.is_null() {
+            std::alloc::handle_alloc_error(layout);
         }
+        self.chunks.push(ptr);
+        self.last_chunk_len = 0;
     }
 
     pub unsafe fn alloc(&mut self, value: T) -> SlabPtr<T> {
-        let slot = self.next_slot;
-        // Note: slot == usize::MAX indicates all slabs up to len == max_len are full
-        if slot == usize::MAX {
-            debug_assert_eq!(self.len, self.max_len);
-            if self.max_len >= self.cap {
-                panic!("Slab Allocator is out of memory. Regrowing is not implemented yet.");
+        let slot_ptr = if self.next_free.is_null() {
+            // no holes, take the next never used slot:
+            if self.last_chunk_len >= self.chunk_cap {
+                self.add_chunk();
             }
-            let slot_ptr = self.ptr.add(size_of::<T>() * self.max_len) as *mut T;
-            write(slot_ptr, value);
-            self.len += 1;
-            self.max_len += 1;
-            SlabPtr(NonNull::new_unchecked(slot_ptr))
+            let chunk = *self.chunks.last().unwrap();
+            let slot_ptr = chunk.add(self.last_chunk_len);
+            self.last_chunk_len += 1;
+            slot_ptr
         } else {
-            // Otherwise, slot points to an empty cell. In that cell the index next empty cell is found (usize::MAX if no more empty cells up to len_end).
-            let slot_ptr = self.ptr.add(size_of::<T>() * slot) as *mut usize;
-            self.next_slot = read(slot_ptr);
-            let slot_ptr = slot_ptr as *mut T;
-            write(slot_ptr, value);
-            self.len += 1;
-            SlabPtr(NonNull::new_unchecked(slot_ptr))
-        }
+            // Otherwise, fill the first hole. It contains the pointer to the next hole (null if there are no more).
+            let slot_ptr = self.next_free;
+            self.next_free = read(slot_ptr as *mut *mut T);
+            slot_ptr
+        };
+        write(slot_ptr, value);
+        self.len += 1;
+        SlabPtr(NonNull::new_unchecked(slot_ptr))
     }
 
     pub unsafe fn dealloc(&mut self, element: &SlabPtr<T>) {
         // read, such that it is dropped properly.
-
         std::mem::drop(read(element.0.as_ptr()));
-        let byte_offset = element.0.as_ptr() as usize - self.ptr as usize;
-
-        debug_assert_eq!(byte_offset % size_of::<T>(), 0);
-        let element_index = byte_offset / size_of::<T>();
+        debug_assert!(self.chunks.iter().any(|c| {
+            let ptr = element.0.as_ptr();
+            ptr >= *c && ptr < c.add(self.chunk_cap)
+        }));
 
         self.len -= 1;
-        // Note: self.max_len never shrinks. Even if all elements before are filled, it is okay to have the last element being an empty element, pointing at usize::MAX instead.
-
         // insert into the linked list:
-        write(element.0.as_ptr() as *mut usize, self.next_slot);
-        self.next_slot = element_index;
+        write(element.0.as_ptr() as *mut *mut T, self.next_free);
+        self.next_free = element.0.as_ptr();
     }
 }
 
@@ -115,3 +132,29 @@ if value at free_pos is
 
 
 */
+
+#[cfg(test)]
+mod tests {
+    use super::SlabAllocator;
+
+    #[test]
+    fn grows_without_moving_elements() {
+        let mut slab: SlabAllocator<[u64; 2]> = SlabAllocator::new(4);
+        let ptrs: Vec<_> = (0..10u64)
+            .map(|i| unsafe { slab.alloc([i, i * 2]) })
+            .collect();
+        assert_eq!(slab.len(), 10);
+        assert_eq!(slab.capacity(), 12);
+        for (i, p) in ptrs.iter().enumerate() {
+            assert_eq!(unsafe { *p.as_ptr() }, [i as u64, i as u64 * 2]);
+        }
+
+        // holes are filled first:
+        let hole = ptrs[5].as_ptr();
+        unsafe { slab.dealloc(&ptrs[5]) };
+        let p = unsafe { slab.alloc([100, 200]) };
+        assert_eq!(p.as_ptr(), hole);
+        assert_eq!(slab.capacity(), 12);
+        assert_eq!(unsafe { *ptrs[4].as_ptr() }, [4, 8]);
+    }
+}
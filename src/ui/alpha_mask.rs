@@ -0,0 +1,119 @@
+use glam::{DVec2, Vec2};
+use image::RgbaImage;
+
+use crate::Aabb;
+
+/// Low resolution cpu copy of the alpha channel of a texture, 1 bit per pixel.
+///
+/// Set it on a textured div with [`crate::ui::element::DivStyle::alpha_hit_test`], such that the div is only
+/// hovered/clicked where its texture is opaque and the cursor passes through transparent regions.
+#[derive(Debug, Clone)]
+pub struct AlphaMask {
+    width: u32,
+    height: u32,
+    bits: Vec<u64>,
+}
+
+impl AlphaMask {
+    /// Downsamples the alpha of `image` such that the mask is at most `max_resolution` pixels wide and tall.
+    /// A mask pixel is opaque, if any of the image pixels it covers has an alpha of at least `threshold`,
+    /// so thin opaque features do not get lost in the downsampling.
+    pub fn from_image(image: &RgbaImage, max_resolution: u32, threshold: u8) -> Self {
+        let max_resolution = max_resolution.max(1);
+        let width = image.width().clamp(1, max_resolution);
+        let height = image.height().clamp(1, max_resolution);
+        let mut mask = AlphaMask {
+            width,
+            height,
+            bits: vec![0; (width * height).div_ceil(64) as usize],
+        };
+        for (x, y, px) in image.enumerate_pixels() {
+            if px.0[3] >= threshold {
+                let mx = (x as u64 * width as u64 / image.width() as u64) as u32;
+                let my = (y as u64 * height as u64 / image.height() as u64) as u32;
+                mask.set(mx, my);
+            }
+        }
+        mask
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn set(&mut self, x: u32, y: u32) {
+        let i = (y * self.width + x) as usize;
+        self.bits[i / 64] |= 1 << (i % 64);
+    }
+
+    pub fn is_opaque(&self, x: u32, y: u32) -> bool {
+        if x >= self.width || y >= self.height {
+            return false;
+        }
+        let i = (y * self.width + x) as usize;
+        self.bits[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    /// Samples the mask at uv coordinates of the texture, (0,0) is the top left corner.
+    pub fn is_opaque_at_uv(&self, uv: Vec2) -> bool {
+        if uv.x < 0.0 || uv.y < 0.0 || uv.x > 1.0 || uv.y > 1.0 {
+            return false;
+        }
+        let x = ((uv.x * self.width as f32) as u32).min(self.width - 1);
+        let y = ((uv.y * self.height as f32) as u32).min(self.height - 1);
+        self.is_opaque(x, y)
+    }
+}
+
+/// Where the texture of a div was placed in the last layout, to map cursor positions to texture uvs.
+#[derive(Debug, Clone)]
+pub(crate) struct AlphaHitTest {
+    pub mask: std::sync::Arc<AlphaMask>,
+    /// bounds of the textured rect (smaller than the div for [`crate::ui::TextureFill::Contain`]).
+    pub bounds: Aabb,
+    pub uv: Aabb,
+    pub tiling: Vec2,
+}
+
+impl AlphaHitTest {
+    pub fn hit(&self, pos: &DVec2) -> bool {
+        let size = self.bounds.size();
+        if size.x <= 0.0 || size.y <= 0.0 {
+            return false;
+        }
+        let rel = (pos.as_vec2() - self.bounds.min) / size;
+        if rel.x < 0.0 || rel.y < 0.0 || rel.x > 1.0 || rel.y > 1.0 {
+            return false;
+        }
+        let mut t = rel * self.tiling;
+        if self.tiling != Vec2::ONE {
+            t = t.fract();
+        }
+        let uv = self.uv.min + t * (self.uv.max - self.uv.min);
+        self.mask.is_opaque_at_uv(uv)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::vec2;
+    use image::{Rgba, RgbaImage};
+
+    use super::AlphaMask;
+
+    #[test]
+    fn downsampled_mask_keeps_opaque_pixels() {
+        let mut image = RgbaImage::from_pixel(64, 32, Rgba([255, 255, 255, 0]));
+        // a single opaque pixel in the bottom right quarter:
+        image.put_pixel(50, 20, Rgba([255, 255, 255, 255]));
+        let mask = AlphaMask::from_image(&image, 16, 128);
+        assert_eq!((mask.width(), mask.height()), (16, 16));
+        assert!(mask.is_opaque_at_uv(vec2(50.5 / 64.0, 20.5 / 32.0)));
+        assert!(!mask.is_opaque_at_uv(vec2(0.1, 0.1)));
+        assert!(!mask.is_opaque_at_uv(vec2(1.5, 0.5)));
+    }
+}
@@ -1,16 +1,24 @@
+use std::any::TypeId;
 use std::rc::Rc;
+use std::time::Duration;
 
 use crate::{
-    renderer::sdf_sprite::AlphaSdfParams, texture::BindableTextureRef, utils::addr_as_u64, Aabb,
-    BindableTexture, Color, GrowableBuffer, VertexT,
+    renderer::sdf_sprite::AlphaSdfParams, texture::UiTextureRef, utils::addr_as_u64, Aabb,
+    BindableTexture, Color, GrowableBuffer, RenderStats, VertexT,
 };
 use wgpu::BufferUsages;
 
 use crate::ui::{
-    element::{ComputedBounds, DivComputed, SdfTextureRegion, Section, TextureRegion},
+    custom_primitive::{CustomInstances, DivPrimitive},
+    element::{ComputedBounds, DivComputed, SdfTextureRegion, Section, TextureFill, TextureRegion},
+    element_store::StoredElement,
     layout::GlyphBoundsAndUv,
-    Corners, Div, DivTexture, ElementWithComputed, SdfFont, TextSection,
+    text_selection::selection_rects,
+    Corners, Div, DivBorder, DivGauge, DivStyleOverlay, DivTexture, ElementContext, ElementId,
+    ElementWithComputed, SdfFont, TextSection, UiEffect,
 };
+use crate::Lerp;
+use glam::Vec2;
 
 use crate::utils::rc_addr_as_u64;
 
@@ -29,6 +37,7 @@ pub struct RectRaw {
     shadow_width: f32,
     shadow_curve: f32,
     shadow_color: Color,
+    effect: UiEffectRaw,
 }
 
 impl VertexT for RectRaw {
@@ -39,30 +48,123 @@ impl VertexT for RectRaw {
         wgpu::VertexFormat::Float32x4, // "border_color"
         wgpu::VertexFormat::Float32x4, // "border_width", "border_softness", "shadow_width", "shadow_curve"
         wgpu::VertexFormat::Float32x4, // "shadow_color",
+        wgpu::VertexFormat::Float32x4, // "effect_params"
+        wgpu::VertexFormat::Uint32,    // "effect"
     ];
 }
 
+/// [`UiEffect`] without the 16 byte alignment of `Vec4`, so it can be packed into instances.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+pub struct UiEffectRaw {
+    pub params: [f32; 4],
+    pub id: u32,
+}
+
+impl From<UiEffect> for UiEffectRaw {
+    fn from(effect: UiEffect) -> Self {
+        UiEffectRaw {
+            params: effect.params.to_array(),
+            id: effect.id,
+        }
+    }
+}
+
 impl RectRaw {
-    fn new(div: &Div, computed: &DivComputed) -> Self {
+    /// `reference_scale` converts the softness and shadow width from reference pixels into layout units.
+    fn new(div: &Div, computed: &DivComputed, visuals: &DivVisuals, reference_scale: f32) -> Self {
         RectRaw {
-            bounds: bounds_from_computed(&computed.bounds),
-            color: div.color,
+            bounds: visuals.bounds(&computed.bounds),
+            color: visuals.color,
             border_radius: div.border.radius,
-            border_color: div.border.color,
-            border_width: div.border.width,
-            border_softness: div.border.softness,
-            shadow_width: div.shadow.width,
+            border_color: visuals.border_color,
+            border_width: visuals.border_width,
+            border_softness: div.border.effective_softness() * reference_scale,
+            shadow_width: div.shadow.width * reference_scale,
             shadow_curve: div.shadow.curve_param,
             shadow_color: div.shadow.color,
+            effect: div.effect.into(),
+        }
+    }
+
+    /// Rect without border and shadow, e.g. for text selection highlights.
+    fn plain(bounds: Aabb, color: Color, reference_scale: f32) -> Self {
+        RectRaw {
+            bounds,
+            color,
+            border_radius: Corners::all(0.0),
+            border_color: Color::TRANSPARENT,
+            border_width: 0.0,
+            border_softness: DivBorder::DEFAULT_SOFTNESS * reference_scale,
+            shadow_width: 0.0,
+            shadow_curve: 0.0,
+            shadow_color: Color::TRANSPARENT,
+            effect: UiEffect::NONE.into(),
         }
     }
 }
 
-#[inline(always)]
-fn bounds_from_computed(computed: &ComputedBounds) -> Aabb {
-    let pos = computed.pos.as_vec2();
-    let size = computed.size.as_vec2();
-    Aabb::new(pos, pos + size)
+/// The visual properties of a div, after its `hover_style` and `active_style` have been blended in.
+#[derive(Debug, Clone, Copy)]
+pub struct DivVisuals {
+    pub color: Color,
+    pub border_color: Color,
+    pub border_width: f32,
+    pub offset: Vec2,
+    pub scale: f32,
+}
+
+impl DivVisuals {
+    pub fn new(div: &Div, hover: f32, active: f32) -> Self {
+        let mut visuals = DivVisuals {
+            color: div.color,
+            border_color: div.border.color,
+            border_width: div.border.width,
+            offset: Vec2::ZERO,
+            scale: 1.0,
+        };
+        let Some(extras) = &div.extras else {
+            return visuals;
+        };
+        if let Some(overlay) = &extras.hover_style {
+            visuals.blend(overlay, hover);
+        }
+        if let Some(overlay) = &extras.active_style {
+            visuals.blend(overlay, active);
+        }
+        visuals
+    }
+
+    fn blend(&mut self, overlay: &DivStyleOverlay, factor: f32) {
+        if factor <= 0.0 {
+            return;
+        }
+        if let Some(color) = &overlay.color {
+            self.color = self.color.lerp(color, factor);
+        }
+        if let Some(border_color) = &overlay.border_color {
+            self.border_color = self.border_color.lerp(border_color, factor);
+        }
+        if let Some(border_width) = &overlay.border_width {
+            self.border_width = self.border_width.lerp(border_width, factor);
+        }
+        if let Some(offset) = overlay.offset {
+            self.offset = self.offset.lerp(offset, factor);
+        }
+        if let Some(scale) = &overlay.scale {
+            self.scale = self.scale.lerp(scale, factor);
+        }
+    }
+
+    fn bounds(&self, computed: &ComputedBounds) -> Aabb {
+        let bounds = computed.as_aabb();
+        if self.offset == Vec2::ZERO && self.scale == 1.0 {
+            return bounds;
+        }
+        let center = (bounds.min + bounds.max) * 0.5 + self.offset;
+        let half_size = (bounds.max - bounds.min) * 0.5 * self.scale;
+        Aabb::new(center - half_size, center + half_size)
+    }
 }
 
 #[repr(C)]
@@ -70,6 +172,67 @@ fn bounds_from_computed(computed: &ComputedBounds) -> Aabb {
 pub struct TexturedRectRaw {
     pub rect: RectRaw,
     pub uv: Aabb,
+    /// How often the uv region is repeated in x and y direction. (1,1) if not tiled.
+    pub tiling: Vec2,
+}
+
+impl TexturedRectRaw {
+    fn new(rect: RectRaw, texture: &TextureRegion, fill: TextureFill) -> Self {
+        let (bounds, uv, tiling) = texture_placement(rect.bounds, texture, fill);
+        TexturedRectRaw {
+            rect: RectRaw { bounds, ..rect },
+            uv,
+            tiling,
+        }
+    }
+}
+
+/// Returns the bounds, uv region and tiling of a texture drawn into a rect with `bounds` according to `fill`.
+pub(crate) fn texture_placement(
+    bounds: Aabb,
+    texture: &TextureRegion,
+    fill: TextureFill,
+) -> (Aabb, Aabb, Vec2) {
+    let mut placement = (bounds, texture.uv, Vec2::ONE);
+    let rect_size = bounds.size();
+    let region_px = (texture.uv.size() * texture.texture.size()).abs();
+    if rect_size.x <= 0.0 || rect_size.y <= 0.0 || region_px.x <= 0.0 || region_px.y <= 0.0 {
+        return placement;
+    }
+    // aspect ratio of the rect relative to the aspect ratio of the texture region.
+    // > 1.0 means the rect is wider than the texture.
+    let rel_aspect = (rect_size.x / rect_size.y) / (region_px.x / region_px.y);
+    match fill {
+        TextureFill::Stretch => {}
+        TextureFill::Tile => {
+            placement.2 = rect_size / region_px;
+        }
+        TextureFill::Cover => {
+            let keep = if rel_aspect > 1.0 {
+                Vec2::new(1.0, 1.0 / rel_aspect)
+            } else {
+                Vec2::new(rel_aspect, 1.0)
+            };
+            placement.1 = shrink_around_center(texture.uv, keep);
+        }
+        TextureFill::Contain => {
+            let keep = if rel_aspect > 1.0 {
+                Vec2::new(1.0 / rel_aspect, 1.0)
+            } else {
+                Vec2::new(1.0, rel_aspect)
+            };
+            placement.0 = shrink_around_center(bounds, keep);
+        }
+    }
+    placement
+}
+
+/// Scales the size of the aabb by `factor` (component wise), keeping its center.
+/// Works for flipped aabbs (min > max) too, as they are used for uvs.
+fn shrink_around_center(aabb: Aabb, factor: Vec2) -> Aabb {
+    let center = aabb.center();
+    let half_size = aabb.size() * 0.5 * factor;
+    Aabb::new(center - half_size, center + half_size)
 }
 
 impl VertexT for TexturedRectRaw {
@@ -80,7 +243,53 @@ impl VertexT for TexturedRectRaw {
         wgpu::VertexFormat::Float32x4, // "border_color"
         wgpu::VertexFormat::Float32x4, // "border_width", "border_softness", "shadow_width", "shadow_curve"
         wgpu::VertexFormat::Float32x4, // "shadow_color",
+        wgpu::VertexFormat::Float32x4, // "effect_params"
+        wgpu::VertexFormat::Uint32,    // "effect"
         wgpu::VertexFormat::Float32x4, // "uv"
+        wgpu::VertexFormat::Float32x2, // "tiling"
+    ];
+}
+
+/// A rect with the fill of a [`DivGauge`] on top of its color.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+pub struct GaugeRectRaw {
+    pub rect: RectRaw,
+    pub fill_color: Color,
+    pub fill_end_color: Color,
+    pub fraction: f32,
+    pub inset: f32,
+    /// a [`crate::ui::GaugeDirection`] as u32.
+    pub direction: u32,
+}
+
+impl GaugeRectRaw {
+    fn new(rect: RectRaw, gauge: &DivGauge) -> Self {
+        GaugeRectRaw {
+            rect,
+            fill_color: gauge.color,
+            fill_end_color: gauge.end_color,
+            fraction: gauge.fraction.clamp(0.0, 1.0),
+            inset: gauge.inset,
+            direction: gauge.direction as u32,
+        }
+    }
+}
+
+impl VertexT for GaugeRectRaw {
+    const ATTRIBUTES: &'static [wgpu::VertexFormat] = &[
+        wgpu::VertexFormat::Float32x4, // "pos"
+        wgpu::VertexFormat::Float32x4, // "color"
+        wgpu::VertexFormat::Float32x4, // "border_radius"
+        wgpu::VertexFormat::Float32x4, // "border_color"
+        wgpu::VertexFormat::Float32x4, // "border_width", "border_softness", "shadow_width", "shadow_curve"
+        wgpu::VertexFormat::Float32x4, // "shadow_color",
+        wgpu::VertexFormat::Float32x4, // "effect_params"
+        wgpu::VertexFormat::Uint32,    // "effect"
+        wgpu::VertexFormat::Float32x4, // "fill_color"
+        wgpu::VertexFormat::Float32x4, // "fill_end_color"
+        wgpu::VertexFormat::Float32x2, // "fraction", "inset"
+        wgpu::VertexFormat::Uint32,    // "direction"
     ];
 }
 
@@ -110,6 +319,9 @@ pub struct GlyphRaw {
     pub color: Color,
     pub uv: Aabb,
     pub shadow_intensity: f32,
+    /// snap_to_pixels (0.0 or 1.0) and sharpness, see [`crate::ui::font::TextHinting`].
+    pub hinting: [f32; 2],
+    pub effect: UiEffectRaw,
 }
 
 impl VertexT for GlyphRaw {
@@ -118,6 +330,9 @@ impl VertexT for GlyphRaw {
         wgpu::VertexFormat::Float32x4, // "color"
         wgpu::VertexFormat::Float32x4, // "uv"
         wgpu::VertexFormat::Float32,   // "shadow_intensity"
+        wgpu::VertexFormat::Float32x2, // "hinting"
+        wgpu::VertexFormat::Float32x4, // "effect_params"
+        wgpu::VertexFormat::Uint32,    // "effect"
     ];
 }
 
@@ -128,46 +343,182 @@ pub struct Batch {
     pub key: u64,
     pub range: std::ops::Range<usize>,
     pub kind: BatchKind,
+    /// Instances are cut off outside of this rect in layout space, set for children of divs with `clip_children`.
+    pub clip: Option<Aabb>,
+}
+
+impl Batch {
+    /// The clip rect as pushed to the ui shaders: min x, min y, max x, max y. Unclipped batches get
+    /// a rect that contains everything.
+    pub(crate) fn clip_push(&self) -> [f32; 4] {
+        match self.clip {
+            Some(clip) => [clip.min.x, clip.min.y, clip.max.x, clip.max.y],
+            None => [f32::MIN, f32::MIN, f32::MAX, f32::MAX],
+        }
+    }
 }
 
 #[derive(Debug)]
 pub enum BatchKind {
     Rect,
-    TexturedRect(BindableTextureRef),
-    AlphaSdfRect(BindableTextureRef),
-    Glyph(SdfFontRef),
+    /// Rects that show a blurred version of the scene behind them. Uses the same buffer as `Rect`.
+    BackdropRect,
+    TexturedRect(UiTextureRef),
+    /// Rects of divs with a [`DivGauge`].
+    GaugeRect,
+    AlphaSdfRect(UiTextureRef),
+    /// Glyphs on one atlas page of the font, see [`crate::ui::SdfFont::page_texture`].
+    Glyph(SdfFontRef, u32),
+    /// Divs with a [`crate::ui::CustomPrimitive`] of the same type and batch key, the range is in the
+    /// [`CustomInstances`] of that type. The primitive is the one of the first div in the batch.
+    Custom(DivPrimitive),
 }
 
 #[derive(Debug, Default)]
 pub struct ElementBatches {
     pub rects: Vec<RectRaw>,
     pub textured_rects: Vec<TexturedRectRaw>,
+    pub gauge_rects: Vec<GaugeRectRaw>,
     pub alpha_sdf_rects: Vec<AlphaSdfRectRaw>,
     pub glyphs: Vec<GlyphRaw>,
+    /// one entry per [`crate::ui::CustomPrimitive`] type.
+    pub custom: Vec<CustomInstances>,
     pub batches: Vec<Batch>,
 }
 
+impl ElementBatches {
+    /// One draw call per batch, the upload bytes are what [`ElementBatchesGR::prepare`] writes for these batches.
+    /// The prepare time is zero, layout and batching happen in [`crate::ui::Board::set_element`].
+    pub fn render_stats(&self) -> RenderStats {
+        let instances: u32 = self.batches.iter().map(|b| b.range.len() as u32).sum();
+        let upload_bytes = std::mem::size_of_val(&self.rects[..])
+            + std::mem::size_of_val(&self.textured_rects[..])
+            + std::mem::size_of_val(&self.gauge_rects[..])
+            + std::mem::size_of_val(&self.glyphs[..])
+            + self.custom.iter().map(|c| c.bytes.len()).sum::<usize>();
+        RenderStats {
+            draw_calls: self.batches.len() as u32,
+            instances,
+            // two triangles per instance, see `ui_screen.rs`.
+            vertices: 6 * instances,
+            upload_bytes: upload_bytes as u64,
+            prepare_time: Duration::ZERO,
+        }
+    }
+
+    /// Returns true if any div in these batches wants a blurred backdrop behind it.
+    /// Can be used to skip blurring the scene if no element needs it.
+    pub fn has_backdrop(&self) -> bool {
+        self.batches
+            .iter()
+            .any(|b| matches!(b.kind, BatchKind::BackdropRect))
+    }
+
+    /// True if any rect or glyph has a [`UiEffect`], whose animations can change the rendered image every frame.
+    pub fn has_effects(&self) -> bool {
+        let none = UiEffect::NONE.id;
+        self.rects.iter().any(|r| r.effect.id != none)
+            || self.textured_rects.iter().any(|r| r.rect.effect.id != none)
+            || self.gauge_rects.iter().any(|r| r.rect.effect.id != none)
+            || self.glyphs.iter().any(|g| g.effect.id != none)
+    }
+
+    /// True if both would render exactly the same, assuming the contents of their textures stay the same.
+    /// Compares the instances byte by byte, so this is cheap compared to rendering.
+    pub fn same_content(&self, other: &ElementBatches) -> bool {
+        fn same_bytes<T: bytemuck::Pod>(a: &[T], b: &[T]) -> bool {
+            bytemuck::cast_slice::<T, u8>(a) == bytemuck::cast_slice::<T, u8>(b)
+        }
+        same_bytes(&self.rects, &other.rects)
+            && same_bytes(&self.textured_rects, &other.textured_rects)
+            && same_bytes(&self.gauge_rects, &other.gauge_rects)
+            && same_bytes(&self.alpha_sdf_rects, &other.alpha_sdf_rects)
+            && same_bytes(&self.glyphs, &other.glyphs)
+            && self.custom.len() == other.custom.len()
+            && self
+                .custom
+                .iter()
+                .zip(other.custom.iter())
+                .all(|(a, b)| a.primitive == b.primitive && a.bytes == b.bytes)
+            && self.batches.len() == other.batches.len()
+            && self
+                .batches
+                .iter()
+                .zip(other.batches.iter())
+                .all(|(a, b)| a.key == b.key && a.range == b.range && a.clip == b.clip)
+    }
+}
+
 pub enum PrimElement<'a> {
-    Rect(&'a (Div, DivComputed)),
-    TexturedRect(&'a (Div, DivComputed), &'a TextureRegion),
-    AlphaSdfRect(&'a (Div, DivComputed), &'a SdfTextureRegion),
+    Rect(&'a (Div, DivComputed), DivVisuals),
+    BackdropRect(&'a (Div, DivComputed), DivVisuals),
+    TexturedRect(&'a (Div, DivComputed), &'a TextureRegion, DivVisuals),
+    GaugeRect(&'a (Div, DivComputed), &'a DivGauge, DivVisuals),
+    AlphaSdfRect(&'a (Div, DivComputed), &'a SdfTextureRegion, DivVisuals),
+    Custom(&'a (Div, DivComputed), &'a DivPrimitive, DivVisuals),
     Text(&'a TextSection, &'a [GlyphBoundsAndUv]),
+    /// highlight behind selected text.
+    SelectionRect(Aabb, Color),
+    /// track or thumb of the [`crate::ui::Scrollbar`] of a scroll area, with round ends.
+    ScrollbarRect(Aabb, Color),
 }
 
 impl<'a> PrimElement<'a> {
+    /// Whether anything of the element is drawn inside of `cull_rect`. Rects include their shadow,
+    /// but not vertex offsets of a [`UiEffect`], so strongly moving effects can pop in at the edges.
+    pub(crate) fn intersects(&self, cull_rect: &Aabb, reference_scale: f32) -> bool {
+        match self {
+            PrimElement::Rect((div, computed), visuals)
+            | PrimElement::BackdropRect((div, computed), visuals)
+            | PrimElement::TexturedRect((div, computed), _, visuals)
+            | PrimElement::GaugeRect((div, computed), _, visuals) => {
+                let shadow_width = Vec2::splat(div.shadow.width.max(0.0) * reference_scale);
+                let bounds = visuals.bounds(&computed.bounds);
+                cull_rect.intersects(&Aabb::new(
+                    bounds.min - shadow_width,
+                    bounds.max + shadow_width,
+                ))
+            }
+            PrimElement::AlphaSdfRect((_, computed), _, visuals)
+            | PrimElement::Custom((_, computed), _, visuals) => {
+                cull_rect.intersects(&visuals.bounds(&computed.bounds))
+            }
+            PrimElement::Text(_, glyphs) => glyphs
+                .iter()
+                .any(|g| cull_rect.intersects(&g.bounds.into())),
+            PrimElement::SelectionRect(bounds, _) | PrimElement::ScrollbarRect(bounds, _) => {
+                cull_rect.intersects(bounds)
+            }
+        }
+    }
+
     fn batch_key(&self) -> u64 {
         match self {
-            PrimElement::Rect(_) => 0,
-            PrimElement::TexturedRect(_, texture) => addr_as_u64(&texture.texture),
-            PrimElement::Text(text, _) => addr_as_u64(text.font),
-            PrimElement::AlphaSdfRect(_, sdf_texture) => {
-                addr_as_u64(&sdf_texture.region.texture) ^ 21891209983212317
+            PrimElement::Rect(..)
+            | PrimElement::SelectionRect(..)
+            | PrimElement::ScrollbarRect(..) => 0,
+            PrimElement::BackdropRect(..) => 1,
+            PrimElement::GaugeRect(..) => 2,
+            PrimElement::Custom(_, primitive, _) => primitive.batch_key(),
+            // rects with the same bind group can share a batch.
+            PrimElement::TexturedRect(_, texture, _) => addr_as_u64(texture.texture.bind_group()),
+            // pages are far fewer than the size of a font, so keys of different fonts don't collide.
+            PrimElement::Text(text, glyphs) => {
+                addr_as_u64(text.font.get()) + glyph_page(glyphs) as u64
+            }
+            PrimElement::AlphaSdfRect(_, sdf_texture, _) => {
+                addr_as_u64(sdf_texture.region.texture.bind_group()) ^ 21891209983212317
                 // this is such that we do not confuse a key for a AlphaSdfRect with a key for a TexturedRect
             }
         }
     }
 }
 
+/// The atlas page of a run of glyphs that are all on the same page.
+fn glyph_page(glyphs: &[GlyphBoundsAndUv]) -> u32 {
+    glyphs.first().map_or(0, |g| g.page)
+}
+
 /// In the stacking order, this is the priority order:
 /// - high z-index in front of low z-index
 /// - text in front of rects, if z-index is the same
@@ -228,15 +579,20 @@ impl Ord for StackingLevel {
 }
 
 impl ElementWithComputed {
+    /// Note: does not apply hover and active styles, use [`get_batches_with_ctx`] for that.
     pub fn get_batches(&self) -> ElementBatches {
-        get_batches(&[&self])
+        get_batches(&[&self], None)
     }
 
-    /// The `level` passed in is the level of the parent
+    /// The `level` passed in is the level of the parent, the `clip` is the clip rect of the divs around it.
+    /// If a `ctx` is given, hover and active styles of divs are applied based on their interaction state.
     fn collect_prim_elements<'a>(
         &'a self,
+        id: ElementId,
         mut level: StackingLevel,
-        prim_elements: &mut Vec<(StackingLevel, PrimElement<'a>)>,
+        clip: Option<Aabb>,
+        prim_elements: &mut Vec<(StackingLevel, Option<Aabb>, PrimElement<'a>)>,
+        mut ctx: Option<&mut ElementContext>,
     ) {
         level.nesting_level += 1;
 
@@ -244,26 +600,102 @@ impl ElementWithComputed {
             ElementWithComputed::Div(div) => {
                 level.z_index += div.0.z_index;
 
+                let extras = div.0.extras.as_deref();
+                let visuals = match (ctx.as_deref_mut(), extras) {
+                    (Some(ctx), Some(extras))
+                        if (extras.hover_style.is_some() || extras.active_style.is_some())
+                            && !id.is_none() =>
+                    {
+                        let t = ctx.advance_style_transition(id, extras.style_transition);
+                        DivVisuals::new(&div.0, t.hover, t.active)
+                    }
+                    _ => DivVisuals::new(&div.0, 0.0, 0.0),
+                };
+
                 // Note: elements with color = 0,0,0,0 will be discarded even if they have a colored border or shadow!!!
-                if div.0.color != Color::TRANSPARENT {
+                if div.0.backdrop_blur {
+                    prim_elements.push((level, clip, PrimElement::BackdropRect(div, visuals)));
+                } else if let Some(gauge) = extras.and_then(|e| e.gauge.as_ref()) {
+                    // drawn even with a transparent background, the fill is visible on its own.
+                    prim_elements.push((level, clip, PrimElement::GaugeRect(div, gauge, visuals)));
+                } else if let Some(primitive) = extras.and_then(|e| e.primitive.as_ref()) {
+                    prim_elements.push((level, clip, PrimElement::Custom(div, primitive, visuals)));
+                } else if visuals.color != Color::TRANSPARENT {
                     let prim = match &div.0.texture {
-                        DivTexture::None => PrimElement::Rect(div),
-                        DivTexture::Texture(texture) => PrimElement::TexturedRect(div, texture),
+                        DivTexture::None => PrimElement::Rect(div, visuals),
+                        DivTexture::Texture(texture) => {
+                            PrimElement::TexturedRect(div, texture, visuals)
+                        }
                         DivTexture::AlphaSdfTexture(sdf_texture) => {
-                            PrimElement::AlphaSdfRect(div, sdf_texture)
+                            PrimElement::AlphaSdfRect(div, sdf_texture, visuals)
                         }
                     };
 
-                    prim_elements.push((level, prim));
+                    prim_elements.push((level, clip, prim));
                 }
 
+                let children_clip = if div.0.clips_children() {
+                    let bounds = div.1.bounds.as_aabb();
+                    match clip {
+                        Some(clip) => clip.intersection(&bounds),
+                        None => Some(bounds),
+                    }
+                } else {
+                    clip
+                };
+                // children of a div with `clip_children` whose clip rect is empty are invisible.
+                if div.0.clips_children() && children_clip.is_none() {
+                    return;
+                }
+                // the scrollbar is above all children of the scroll area, unless they have a higher z_index.
+                if let Some(scrollbar) = extras.and_then(|e| e.scroll.as_ref()?.scrollbar.as_ref())
+                {
+                    let bounds = div.1.bounds.as_aabb();
+                    if let Some((track, thumb)) =
+                        scrollbar.rects(bounds, div.1.scroll, div.1.max_scroll)
+                    {
+                        let level = StackingLevel {
+                            z_index: level.z_index,
+                            text_level: u16::MAX,
+                            nesting_level: u16::MAX,
+                        };
+                        if scrollbar.track_color != Color::TRANSPARENT {
+                            let prim = PrimElement::ScrollbarRect(track, scrollbar.track_color);
+                            prim_elements.push((level, clip, prim));
+                        }
+                        let prim = PrimElement::ScrollbarRect(thumb, scrollbar.thumb_color);
+                        prim_elements.push((level, clip, prim));
+                    }
+                }
                 for ch in div.0.children.iter() {
-                    ch.element.collect_prim_elements(level, prim_elements);
+                    ch.element.collect_prim_elements(
+                        ch.id,
+                        level,
+                        children_clip,
+                        prim_elements,
+                        ctx.as_deref_mut(),
+                    );
                 }
             }
             ElementWithComputed::Text(text) => {
                 level.text_level += 1;
 
+                // highlights are pushed before the glyphs of the same level, so they are rendered behind them.
+                if let Some(ctx) = ctx.as_deref_mut() {
+                    if text.0.selectable && !id.is_none() {
+                        if let Some(range) = ctx.update_text_selection(id, &text.0, &text.1) {
+                            let color = ctx.selection_color();
+                            for bounds in selection_rects(&text.1.glyphs, range) {
+                                prim_elements.push((
+                                    level,
+                                    clip,
+                                    PrimElement::SelectionRect(bounds, color),
+                                ));
+                            }
+                        }
+                    }
+                }
+
                 let mut i: usize = 0;
                 for section in text.0.sections.iter() {
                     match section {
@@ -271,11 +703,20 @@ impl ElementWithComputed {
                             let glyph_range = text.1.text_section_glyphs[i].clone();
                             i += 1;
                             let glyphs = &text.1.glyphs[glyph_range];
-                            let prim = PrimElement::Text(text_section, glyphs);
-                            prim_elements.push((level, prim));
+                            // one element per run of glyphs on the same atlas page, they need different textures.
+                            for run in glyphs.chunk_by(|a, b| a.page == b.page) {
+                                let prim = PrimElement::Text(text_section, run);
+                                prim_elements.push((level, clip, prim));
+                            }
                         }
                         Section::Element { element, .. } => {
-                            element.element.collect_prim_elements(level, prim_elements);
+                            element.element.collect_prim_elements(
+                                element.id,
+                                level,
+                                clip,
+                                prim_elements,
+                                ctx.as_deref_mut(),
+                            );
                         }
                     }
                 }
@@ -284,35 +725,97 @@ impl ElementWithComputed {
     }
 }
 
-pub fn get_batches(elements: &[&ElementWithComputed]) -> ElementBatches {
+/// Note: does not apply hover and active styles, use [`get_batches_with_ctx`] for that.
+/// Softness and shadow widths are converted with a reference scale of 1.
+///
+/// Rects and glyphs entirely outside of the `cull_rect` (in layout space) are skipped, e.g. pass the area
+/// of the board to not emit the offscreen items of long scrolled lists. None keeps all of them.
+pub fn get_batches(elements: &[&ElementWithComputed], cull_rect: Option<Aabb>) -> ElementBatches {
     // step 1: create an array with pointers to all elements and their z-order:
-    let mut prim_elements: Vec<(StackingLevel, PrimElement)> = vec![];
+    let mut prim_elements: Vec<(StackingLevel, Option<Aabb>, PrimElement)> = vec![];
+    for element in elements {
+        element.collect_prim_elements(
+            ElementId::NONE,
+            StackingLevel::ZERO,
+            None,
+            &mut prim_elements,
+            None,
+        );
+    }
+    batches_from_prim_elements(prim_elements, 1.0, cull_rect, false)
+}
+
+/// Like [`get_batches`], but applies the `hover_style` and `active_style` of divs,
+/// based on the interaction state in the `ctx`. Should be called once per frame.
+pub fn get_batches_with_ctx(
+    elements: &[&StoredElement],
+    ctx: &mut ElementContext,
+    cull_rect: Option<Aabb>,
+) -> ElementBatches {
+    let mut prim_elements: Vec<(StackingLevel, Option<Aabb>, PrimElement)> = vec![];
     for element in elements {
-        element.collect_prim_elements(StackingLevel::ZERO, &mut prim_elements);
+        element.element.collect_prim_elements(
+            element.id,
+            StackingLevel::ZERO,
+            None,
+            &mut prim_elements,
+            Some(ctx),
+        );
+    }
+    let reference_scale = ctx.reference_scale() as f32;
+    batches_from_prim_elements(prim_elements, reference_scale, cull_rect, ctx.parallel())
+}
+
+fn batches_from_prim_elements(
+    mut prim_elements: Vec<(StackingLevel, Option<Aabb>, PrimElement)>,
+    reference_scale: f32,
+    cull_rect: Option<Aabb>,
+    parallel: bool,
+) -> ElementBatches {
+    // skip elements cut off entirely by the clip rect of their parents:
+    prim_elements.retain(|(_, clip, element)| {
+        clip.is_none_or(|clip| element.intersects(&clip, reference_scale))
+    });
+    // skip elements that are not visible before they can start new batches:
+    if let Some(cull_rect) = &cull_rect {
+        prim_elements.retain(|(_, _, element)| element.intersects(cull_rect, reference_scale));
     }
 
     // step 2: sort the array by the stacking level, from back to forth, to render them in correct order:
-    prim_elements.sort_by(|a, b| a.0.cmp(&b.0));
+    #[cfg(feature = "parallel")]
+    let sorted = parallel && super::parallel::sort_by_level(&mut prim_elements, |e| e.0);
+    #[cfg(not(feature = "parallel"))]
+    let sorted = {
+        _ = parallel;
+        false
+    };
+    if !sorted {
+        prim_elements.sort_by(|a, b| a.0.cmp(&b.0));
+    }
 
     // step 3: create actual badges by merging prim elements of the same type together into one batch:
     let mut rects: Vec<RectRaw> = vec![];
     let mut textured_rects: Vec<TexturedRectRaw> = vec![];
+    let mut gauge_rects: Vec<GaugeRectRaw> = vec![];
     let mut alpha_sdf_rects: Vec<AlphaSdfRectRaw> = vec![];
     let mut glyphs: Vec<GlyphRaw> = vec![];
+    let mut custom: Vec<CustomInstances> = vec![];
     let mut batches: Vec<Batch> = vec![];
 
-    for (_level, element) in prim_elements {
+    for (_level, clip, element) in prim_elements {
         let key = element.batch_key();
 
         let add_new_batch = match batches.last_mut() {
             Some(batch) => {
-                if batch.key != key {
+                if batch.key != key || batch.clip != clip {
                     // incompatible, finish the last batch:
-                    let batch_end = match batch.kind {
-                        BatchKind::Rect => rects.len(),
+                    let batch_end = match &batch.kind {
+                        BatchKind::Rect | BatchKind::BackdropRect => rects.len(),
                         BatchKind::TexturedRect(_) => textured_rects.len(),
-                        BatchKind::Glyph(_) => glyphs.len(),
+                        BatchKind::GaugeRect => gauge_rects.len(),
+                        BatchKind::Glyph(..) => glyphs.len(),
                         BatchKind::AlphaSdfRect(_) => alpha_sdf_rects.len(),
+                        BatchKind::Custom(primitive) => n_custom(&custom, primitive.type_id()),
                     };
                     batch.range.end = batch_end;
                     true
@@ -327,60 +830,120 @@ pub fn get_batches(elements: &[&ElementWithComputed]) -> ElementBatches {
         // add a new batch, if last batch in
         if add_new_batch {
             let batch = match &element {
-                PrimElement::Rect(_) => Batch {
+                PrimElement::Rect(..)
+                | PrimElement::SelectionRect(..)
+                | PrimElement::ScrollbarRect(..) => Batch {
                     key,
+                    clip,
                     range: rects.len()..rects.len(),
                     kind: BatchKind::Rect,
                 },
-                PrimElement::TexturedRect(_, texture) => Batch {
+                PrimElement::BackdropRect(..) => Batch {
+                    key,
+                    clip,
+                    range: rects.len()..rects.len(),
+                    kind: BatchKind::BackdropRect,
+                },
+                PrimElement::TexturedRect(_, texture, _) => Batch {
                     key,
+                    clip,
                     range: textured_rects.len()..textured_rects.len(),
                     kind: BatchKind::TexturedRect(texture.texture),
                 },
-                PrimElement::AlphaSdfRect(_, sdf_texture) => Batch {
+                PrimElement::GaugeRect(..) => Batch {
+                    key,
+                    clip,
+                    range: gauge_rects.len()..gauge_rects.len(),
+                    kind: BatchKind::GaugeRect,
+                },
+                PrimElement::AlphaSdfRect(_, sdf_texture, _) => Batch {
                     key,
+                    clip,
                     range: alpha_sdf_rects.len()..alpha_sdf_rects.len(),
                     kind: BatchKind::AlphaSdfRect(sdf_texture.region.texture),
                 },
-                PrimElement::Text(section, _) => Batch {
+                PrimElement::Text(section, text_glyphs) => Batch {
                     key,
+                    clip,
                     range: glyphs.len()..glyphs.len(),
-                    kind: BatchKind::Glyph(section.font),
+                    kind: BatchKind::Glyph(section.font.get(), glyph_page(text_glyphs)),
                 },
+                PrimElement::Custom(_, primitive, _) => {
+                    let start = n_custom(&custom, primitive.type_id());
+                    Batch {
+                        key,
+                        clip,
+                        range: start..start,
+                        kind: BatchKind::Custom((*primitive).clone()),
+                    }
+                }
             };
             batches.push(batch);
         }
 
         // add primitives to the respective arrays:
         match element {
-            PrimElement::Rect((div, computed)) => {
-                let rect = RectRaw::new(div, computed);
+            PrimElement::Rect((div, computed), visuals)
+            | PrimElement::BackdropRect((div, computed), visuals) => {
+                let rect = RectRaw::new(div, computed, &visuals, reference_scale);
                 rects.push(rect);
             }
-            PrimElement::TexturedRect((div, computed), texture) => {
-                let rect = RectRaw::new(div, computed);
-                let textured_rect = TexturedRectRaw {
-                    rect,
-                    uv: texture.uv,
-                };
+            PrimElement::SelectionRect(bounds, color) => {
+                rects.push(RectRaw::plain(bounds, color, reference_scale));
+            }
+            PrimElement::ScrollbarRect(bounds, color) => {
+                let mut rect = RectRaw::plain(bounds, color, reference_scale);
+                rect.border_radius = Corners::all(bounds.size().x * 0.5);
+                rects.push(rect);
+            }
+            PrimElement::TexturedRect((div, computed), texture, visuals) => {
+                let rect = RectRaw::new(div, computed, &visuals, reference_scale);
+                let textured_rect = TexturedRectRaw::new(rect, texture, div.texture_fill);
                 textured_rects.push(textured_rect);
             }
-            PrimElement::AlphaSdfRect((div, computed), sdf_texture) => {
+            PrimElement::GaugeRect((div, computed), gauge, visuals) => {
+                let rect = RectRaw::new(div, computed, &visuals, reference_scale);
+                gauge_rects.push(GaugeRectRaw::new(rect, gauge));
+            }
+            PrimElement::AlphaSdfRect((_, computed), sdf_texture, visuals) => {
                 let alpha_sdf_rect = AlphaSdfRectRaw {
-                    bounds: bounds_from_computed(&computed.bounds),
-                    color: div.color,
+                    bounds: visuals.bounds(&computed.bounds),
+                    color: visuals.color,
                     params: sdf_texture.params,
                     uv: sdf_texture.region.uv,
                 };
                 alpha_sdf_rects.push(alpha_sdf_rect);
             }
+            PrimElement::Custom((_, computed), primitive, visuals) => {
+                let instances = CustomInstances::of(&mut custom, primitive);
+                let bounds = visuals.bounds(&computed.bounds);
+                primitive.push_instance(bounds, &visuals, reference_scale, instances);
+            }
             PrimElement::Text(section, text_glyphs) => {
+                let hinting = section.hinting.unwrap_or(section.font.get().hinting());
+                let hinting = [
+                    if hinting.snap_to_pixels { 1.0 } else { 0.0 },
+                    hinting.sharpness,
+                ];
+                let effect = UiEffectRaw::from(section.effect);
+                // fades during a crossfade of its font slot:
+                let alpha = section.font.alpha();
+                let mut color = section.color;
+                color.a *= alpha;
                 for g in text_glyphs {
+                    let glyph_bounds = g.bounds.into();
+                    if cull_rect.is_some_and(|cull_rect| !cull_rect.intersects(&glyph_bounds))
+                        || clip.is_some_and(|clip| !clip.intersects(&glyph_bounds))
+                    {
+                        continue;
+                    }
                     let glyph_raw = GlyphRaw {
                         bounds: g.bounds.into(),
-                        color: section.color,
+                        color,
                         uv: g.uv,
-                        shadow_intensity: section.shadow_intensity,
+                        shadow_intensity: section.shadow_intensity * alpha,
+                        hinting,
+                        effect,
                     };
                     glyphs.push(glyph_raw);
                 }
@@ -390,11 +953,13 @@ pub fn get_batches(elements: &[&ElementWithComputed]) -> ElementBatches {
 
     // finish the last batch:
     if let Some(batch) = batches.last_mut() {
-        let batch_end = match batch.kind {
-            BatchKind::Rect => rects.len(),
+        let batch_end = match &batch.kind {
+            BatchKind::Rect | BatchKind::BackdropRect => rects.len(),
             BatchKind::TexturedRect(_) => textured_rects.len(),
+            BatchKind::GaugeRect => gauge_rects.len(),
             BatchKind::AlphaSdfRect(_) => alpha_sdf_rects.len(),
-            BatchKind::Glyph(_) => glyphs.len(),
+            BatchKind::Glyph(..) => glyphs.len(),
+            BatchKind::Custom(primitive) => n_custom(&custom, primitive.type_id()),
         };
         batch.range.end = batch_end;
     }
@@ -402,18 +967,31 @@ pub fn get_batches(elements: &[&ElementWithComputed]) -> ElementBatches {
     ElementBatches {
         rects,
         textured_rects,
+        gauge_rects,
         glyphs,
+        custom,
         batches,
         alpha_sdf_rects,
     }
 }
 
+/// Number of instances of the custom primitive type so far.
+fn n_custom(custom: &[CustomInstances], primitive: TypeId) -> usize {
+    custom
+        .iter()
+        .find(|c| c.primitive == primitive)
+        .map_or(0, |c| c.len())
+}
+
 #[derive(Debug)]
 pub struct ElementBatchesGR {
     pub rects: GrowableBuffer<RectRaw>,
     pub textured_rects: GrowableBuffer<TexturedRectRaw>,
+    pub gauge_rects: GrowableBuffer<GaugeRectRaw>,
     pub alpha_sdf_rects: GrowableBuffer<AlphaSdfRectRaw>,
     pub glyphs: GrowableBuffer<GlyphRaw>,
+    /// instance buffers of the [`crate::ui::CustomPrimitive`] types, created when a type is batched the first time.
+    pub custom: Vec<(TypeId, GrowableBuffer<u8>)>,
 }
 
 impl ElementBatchesGR {
@@ -422,18 +1000,38 @@ impl ElementBatchesGR {
             GrowableBuffer::new_from_data(device, BufferUsages::VERTEX, &batches.rects);
         let textured_rects =
             GrowableBuffer::new_from_data(device, BufferUsages::VERTEX, &batches.textured_rects);
+        let gauge_rects =
+            GrowableBuffer::new_from_data(device, BufferUsages::VERTEX, &batches.gauge_rects);
         let alpha_sdf_rects =
             GrowableBuffer::new_from_data(device, BufferUsages::VERTEX, &batches.alpha_sdf_rects);
         let glyphs = GrowableBuffer::new_from_data(device, BufferUsages::VERTEX, &batches.glyphs);
+        let custom = batches
+            .custom
+            .iter()
+            .map(|c| {
+                let buffer = GrowableBuffer::new_from_data(device, BufferUsages::VERTEX, &c.bytes);
+                (c.primitive, buffer)
+            })
+            .collect();
 
         ElementBatchesGR {
             rects,
             textured_rects,
+            gauge_rects,
             glyphs,
             alpha_sdf_rects,
+            custom,
         }
     }
 
+    /// The instance buffer of a [`crate::ui::CustomPrimitive`] type, None if it was never batched.
+    pub fn custom_buffer(&self, primitive: TypeId) -> Option<&GrowableBuffer<u8>> {
+        self.custom
+            .iter()
+            .find(|(t, _)| *t == primitive)
+            .map(|(_, buffer)| buffer)
+    }
+
     pub fn prepare(
         &mut self,
         batches: &ElementBatches,
@@ -443,6 +1041,272 @@ impl ElementBatchesGR {
         self.rects.prepare(&batches.rects, device, queue);
         self.textured_rects
             .prepare(&batches.textured_rects, device, queue);
+        self.gauge_rects
+            .prepare(&batches.gauge_rects, device, queue);
         self.glyphs.prepare(&batches.glyphs, device, queue);
+        // glyphs that were rasterized during layout are uploaded before they are rendered.
+        for batch in batches.batches.iter() {
+            if let BatchKind::Glyph(font, _) = batch.kind {
+                font.write_atlas_to_texture(device, queue);
+            }
+        }
+        for instances in batches.custom.iter() {
+            match self
+                .custom
+                .iter_mut()
+                .find(|(t, _)| *t == instances.primitive)
+            {
+                Some((_, buffer)) => buffer.prepare(&instances.bytes, device, queue),
+                None => self.custom.push((
+                    instances.primitive,
+                    GrowableBuffer::new_from_data(device, BufferUsages::VERTEX, &instances.bytes),
+                )),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::dvec2;
+
+    use crate::{
+        ui::{
+            div, red_box, Board, CustomPrimitive, DivGauge, DivPrimitive, DivScroll,
+            DivStyleOverlay, ElementId, IntoElementBox, Scrollbar, UiCulling,
+        },
+        Aabb, Color, Lerp, MouseButtonState, RenderFormat, VertexT,
+    };
+
+    use super::{BatchKind, DivVisuals};
+
+    #[test]
+    fn culls_offscreen_rects() {
+        // a list of 100 boxes, 48 layout units high each, of which 10 fit into the board.
+        let mut list = div();
+        for _ in 0..100 {
+            list.push(red_box());
+        }
+        let mut board = Board::new(
+            list.style(|s| s.color = Color::BLUE).store(),
+            dvec2(200.0, 480.0),
+        );
+        // the list itself and the boxes up to the one touching the bottom edge.
+        assert_eq!(board.batches.rects.len(), 12);
+
+        board.culling = UiCulling::None;
+        let element = std::mem::replace(&mut board.element, div().store());
+        board.set_element(element);
+        assert_eq!(board.batches.rects.len(), 101);
+    }
+
+    #[test]
+    fn clipping_divs_cut_off_their_children() {
+        // a 100x100 panel at the top of the board with a list of 10 boxes that are 48 high each.
+        let mut list = div();
+        for _ in 0..10 {
+            list.push(red_box());
+        }
+        let panel = div()
+            .style(|s| {
+                s.size(100, 100);
+                s.color = Color::BLUE;
+                s.clip_children = true;
+            })
+            .child(list);
+        let board = Board::new(div().child(panel).store(), dvec2(200.0, 480.0));
+        // the panel and the 3 boxes that are at least partially inside of it.
+        assert_eq!(board.batches.rects.len(), 4);
+        let clips: Vec<Option<Aabb>> = board.batches.batches.iter().map(|b| b.clip).collect();
+        let panel_rect = Aabb::new(glam::Vec2::ZERO, glam::Vec2::splat(100.0));
+        assert_eq!(clips, vec![None, Some(panel_rect)]);
+    }
+
+    #[test]
+    fn scroll_areas_keep_their_offset_in_the_context() {
+        // a 100x100 scroll area with a list of 10 boxes that are 48 high each, 380 more than fit.
+        let panel = || {
+            let mut list = div();
+            for _ in 0..10 {
+                list.push(red_box());
+            }
+            div()
+                .style(|s| {
+                    s.size(100, 100);
+                    s.extras_mut().scroll =
+                        Some(DivScroll::default().scrollbar(Scrollbar::default()));
+                })
+                .child(list)
+                .store_with_id("panel")
+        };
+        let mut board = Board::new(div().child_box(panel()).store(), dvec2(200.0, 480.0));
+        let id = ElementId::from("panel");
+        assert_eq!(board.ctx.max_scroll(id), Some(380.0));
+
+        // offsets past the end are clamped during layout.
+        board.ctx.set_scroll_offset(id, 1000.0);
+        board.build(|_| div().child_box(panel()).store());
+        assert_eq!(board.ctx.scroll_offset(id), 380.0);
+        // the 3 boxes at the bottom of the list and the thumb of the scrollbar.
+        assert_eq!(board.batches.rects.len(), 4);
+
+        board
+            .ctx
+            .start_frame(dvec2(50.0, 50.0), MouseButtonState::default(), 0.0);
+        assert!(!board.ctx.scroll_hovered(-1.0));
+        assert!(board.ctx.scroll_hovered(0.5));
+        assert_eq!(board.ctx.scroll_offset(id), 360.0);
+        board
+            .ctx
+            .start_frame(dvec2(150.0, 50.0), MouseButtonState::default(), 0.0);
+        assert!(!board.ctx.scroll_hovered(1.0));
+    }
+
+    #[test]
+    fn style_transitions_follow_the_frame_delta() {
+        let button = || {
+            div()
+                .style(|s| {
+                    s.size(100, 100);
+                    s.color = Color::BLACK;
+                    let extras = s.extras_mut();
+                    extras.hover_style = Some(DivStyleOverlay {
+                        color: Some(Color::WHITE),
+                        ..Default::default()
+                    });
+                    extras.style_transition = 1.0;
+                })
+                .store_with_id("button")
+        };
+        let mut board = Board::new(div().child_box(button()).store(), dvec2(200.0, 200.0));
+        let hovered_color = Color::BLACK.lerp(&Color::WHITE, 0.25);
+
+        board
+            .ctx
+            .start_frame(dvec2(50.0, 50.0), MouseButtonState::default(), 0.25);
+        board.build(|_| div().child_box(button()).store());
+        assert_eq!(board.batches.rects[0].color, hovered_color);
+
+        // the button is not built while it is fading in, its transition is dropped in the next frame.
+        board
+            .ctx
+            .start_frame(dvec2(50.0, 50.0), MouseButtonState::default(), 0.25);
+        board.build(|_| div().store());
+        board
+            .ctx
+            .start_frame(dvec2(150.0, 150.0), MouseButtonState::default(), 0.0);
+        board.build(|_| div().child_box(button()).store());
+        assert_eq!(board.batches.rects[0].color, Color::BLACK);
+    }
+
+    #[test]
+    fn gauges_are_drawn_without_background() {
+        let gauge = div().style(|s| {
+            s.size(200, 16);
+            s.extras_mut().gauge = Some(DivGauge::new(1.5, Color::GREEN).gradient(Color::RED));
+        });
+        let board = Board::new(gauge.store(), dvec2(200.0, 480.0));
+        assert!(board.batches.rects.is_empty());
+        assert_eq!(board.batches.gauge_rects.len(), 1);
+        assert_eq!(board.batches.gauge_rects[0].fraction, 1.0);
+        assert!(matches!(
+            board.batches.batches[0].kind,
+            BatchKind::GaugeRect
+        ));
+    }
+
+    #[test]
+    fn content_version_changes_with_content() {
+        let mut board = Board::new(red_box().store(), dvec2(200.0, 480.0));
+        let version = board.content_version();
+        let element = std::mem::replace(&mut board.element, div().store());
+        board.set_element(element);
+        assert_eq!(board.content_version(), version);
+
+        board.set_element(div().style(|s| s.color = Color::BLUE).store());
+        assert_ne!(board.content_version(), version);
+    }
+
+    #[derive(Debug)]
+    struct Dot(u64);
+
+    #[repr(C)]
+    #[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+    struct DotRaw {
+        bounds: Aabb,
+        color: Color,
+    }
+
+    impl VertexT for DotRaw {
+        const ATTRIBUTES: &'static [wgpu::VertexFormat] =
+            &[wgpu::VertexFormat::Float32x4, wgpu::VertexFormat::Float32x4];
+    }
+
+    impl CustomPrimitive for Dot {
+        type Raw = DotRaw;
+
+        fn batch_key(&self) -> u64 {
+            self.0
+        }
+
+        fn instance(&self, bounds: Aabb, visuals: &DivVisuals, _reference_scale: f32) -> DotRaw {
+            DotRaw {
+                bounds,
+                color: visuals.color,
+            }
+        }
+
+        fn create_pipeline(_: &wgpu::Device, _: RenderFormat) -> wgpu::RenderPipeline {
+            unreachable!("not rendered in tests")
+        }
+    }
+
+    #[test]
+    fn custom_primitives_are_sorted_and_batched() {
+        let dot = |key: u64, z_index: i16| {
+            div().style(move |s| {
+                s.size(10, 10);
+                s.z_index = z_index;
+                s.color = if z_index > 0 {
+                    Color::BLUE
+                } else {
+                    Color::GREEN
+                };
+                s.extras_mut().primitive = Some(DivPrimitive::new(Dot(key)));
+            })
+        };
+        let list = div()
+            .child(dot(0, 0))
+            .child(dot(0, 0))
+            .child(dot(1, 0))
+            .child(red_box())
+            .child(dot(0, 1));
+        let board = Board::new(list.store(), dvec2(200.0, 480.0));
+        let batches = &board.batches;
+
+        let kinds: Vec<_> = batches
+            .batches
+            .iter()
+            .map(|b| match &b.kind {
+                BatchKind::Custom(p) => (true, b.range.clone(), p.type_name()),
+                _ => (false, b.range.clone(), ""),
+            })
+            .collect();
+        let name = std::any::type_name::<Dot>();
+        assert_eq!(
+            kinds,
+            vec![
+                (true, 0..2, name),
+                (true, 2..3, name),
+                (false, 0..1, ""),
+                (true, 3..4, name),
+            ]
+        );
+        assert_eq!(batches.custom.len(), 1);
+        assert_eq!(batches.custom[0].len(), 4);
+        let raw: &[DotRaw] = bytemuck::cast_slice(&batches.custom[0].bytes);
+        assert_eq!(raw[3].bounds.min.y, 10.0 * 3.0 + 48.0);
+        assert_eq!(raw[2].color, Color::GREEN);
+        assert_eq!(raw[3].color, Color::BLUE);
     }
 }
@@ -0,0 +1,216 @@
+//! Ui primitives defined outside of tgf, e.g. vector shapes, charts or rects with a special shader.
+//!
+//! A type implementing [`CustomPrimitive`] is set on a div with [`DivPrimitive`]. The div is then drawn
+//! by the primitive instead of as a rect, sorted into the stacking order with all other elements and
+//! batched together with neighbouring divs of the same primitive type and batch key:
+//!
+//! ```rust,ignore
+//! #[derive(Debug)]
+//! struct Pie { fraction: f32 }
+//!
+//! #[repr(C)]
+//! #[derive(Debug, Clone, Copy, bytemuck::Zeroable, bytemuck::Pod)]
+//! struct PieRaw { bounds: Aabb, color: Color, fraction: f32 }
+//!
+//! impl VertexT for PieRaw {
+//!     const ATTRIBUTES: &'static [wgpu::VertexFormat] = &[
+//!         wgpu::VertexFormat::Float32x4, // "bounds"
+//!         wgpu::VertexFormat::Float32x4, // "color"
+//!         wgpu::VertexFormat::Float32,   // "fraction"
+//!     ];
+//! }
+//!
+//! impl CustomPrimitive for Pie {
+//!     type Raw = PieRaw;
+//!
+//!     fn instance(&self, bounds: Aabb, visuals: &DivVisuals, _reference_scale: f32) -> PieRaw {
+//!         PieRaw { bounds, color: visuals.color, fraction: self.fraction }
+//!     }
+//!
+//!     fn create_pipeline(device: &wgpu::Device, render_format: RenderFormat) -> wgpu::RenderPipeline {
+//!         let shader = device.create_shader_module(wgpu::include_wgsl!("pie.wgsl"));
+//!         create_pipeline::<PieRaw>(&shader, "vs", "fs", device, &[Uniforms::cached_layout()], render_format)
+//!     }
+//! }
+//!
+//! ui_renderer.register_custom_primitive::<Pie>(&device);
+//! // in the ui:
+//! div().style(|s| {
+//!     s.size(64, 64);
+//!     s.color = Color::RED;
+//!     s.extras_mut().primitive = Some(DivPrimitive::new(Pie { fraction: 0.3 }));
+//! })
+//! ```
+//!
+//! Custom primitives are only rendered by the [`crate::renderer::ui_screen::UiScreenRenderer`],
+//! boards in 3d space skip them.
+
+use std::{
+    any::{type_name, TypeId},
+    fmt::Debug,
+    hash::{Hash, Hasher},
+    rc::Rc,
+};
+
+use ahash::AHasher;
+
+use crate::{ui::batching::DivVisuals, Aabb, RenderFormat, VertexT};
+
+/// A kind of ui primitive with its own instance type and render pipeline, see the [module docs](self).
+pub trait CustomPrimitive: Debug + 'static {
+    /// One instance is drawn per div, with the vertex attributes of [`VertexT::ATTRIBUTES`].
+    type Raw: VertexT + bytemuck::Pod;
+
+    /// Neighbouring divs with the same primitive type are only drawn in one batch if their keys are the same,
+    /// e.g. the address of a texture they sample.
+    fn batch_key(&self) -> u64 {
+        0
+    }
+
+    /// The instance of a div with these `bounds` in layout space. The `visuals` have the `hover_style` and
+    /// `active_style` blended in already, `reference_scale` converts reference pixels into layout units.
+    fn instance(&self, bounds: Aabb, visuals: &DivVisuals, reference_scale: f32) -> Self::Raw;
+
+    /// Sets the resources of a batch, e.g. a texture in bind group 1. The [`crate::Uniforms`] are bound in group 0.
+    fn set_bind_groups<'a>(&'a self, _pass: &mut wgpu::RenderPass<'a>) {}
+
+    /// Creates the pipeline that draws the instances as a triangle strip of 6 vertices per instance, with the
+    /// color and the [`crate::UiViewport`] of the board in the vertex push constants (`UiPush` in `ui.wgsl`).
+    /// [`crate::renderer::ui_screen::create_pipeline`] sets all of that up for a shader that follows `ui.wgsl`.
+    /// Inside of a div with [`crate::ui::DivStyle::clip_children`] the clip rect is in `UiPush.clip`, pass the
+    /// vertex positions through `clip_pos` to cut the instances off like the builtin primitives.
+    fn create_pipeline(device: &wgpu::Device, render_format: RenderFormat) -> wgpu::RenderPipeline
+    where
+        Self: Sized;
+}
+
+/// A [`CustomPrimitive`] set on a div, see [`crate::ui::DivExtras::primitive`].
+#[derive(Debug, Clone)]
+pub struct DivPrimitive(Rc<dyn ErasedPrimitive>);
+
+impl DivPrimitive {
+    pub fn new(primitive: impl CustomPrimitive) -> Self {
+        DivPrimitive(Rc::new(primitive))
+    }
+
+    /// Identifies the primitive type, the instance buffers and pipelines are per type.
+    pub fn type_id(&self) -> TypeId {
+        self.0.primitive_type()
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        self.0.primitive_type_name()
+    }
+
+    /// Divs can share a batch if this is the same, it combines the type with [`CustomPrimitive::batch_key`].
+    pub(crate) fn batch_key(&self) -> u64 {
+        let mut hasher = AHasher::default();
+        (self.type_id(), self.0.batch_key()).hash(&mut hasher);
+        hasher.finish()
+    }
+
+    pub(crate) fn push_instance(
+        &self,
+        bounds: Aabb,
+        visuals: &DivVisuals,
+        reference_scale: f32,
+        instances: &mut CustomInstances,
+    ) {
+        self.0
+            .push_instance(bounds, visuals, reference_scale, &mut instances.bytes);
+    }
+
+    pub(crate) fn instance_size(&self) -> usize {
+        self.0.instance_size()
+    }
+
+    pub fn set_bind_groups<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>) {
+        self.0.set_bind_groups(pass);
+    }
+}
+
+/// [`CustomPrimitive`] without the associated instance type, such that primitives of different types can be stored in divs.
+trait ErasedPrimitive: Debug {
+    fn primitive_type(&self) -> TypeId;
+    fn primitive_type_name(&self) -> &'static str;
+    fn batch_key(&self) -> u64;
+    fn instance_size(&self) -> usize;
+    fn push_instance(
+        &self,
+        bounds: Aabb,
+        visuals: &DivVisuals,
+        reference_scale: f32,
+        bytes: &mut Vec<u8>,
+    );
+    fn set_bind_groups<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>);
+}
+
+impl<P: CustomPrimitive> ErasedPrimitive for P {
+    fn primitive_type(&self) -> TypeId {
+        TypeId::of::<P>()
+    }
+
+    fn primitive_type_name(&self) -> &'static str {
+        type_name::<P>()
+    }
+
+    fn batch_key(&self) -> u64 {
+        CustomPrimitive::batch_key(self)
+    }
+
+    fn instance_size(&self) -> usize {
+        std::mem::size_of::<P::Raw>()
+    }
+
+    fn push_instance(
+        &self,
+        bounds: Aabb,
+        visuals: &DivVisuals,
+        reference_scale: f32,
+        bytes: &mut Vec<u8>,
+    ) {
+        let raw = self.instance(bounds, visuals, reference_scale);
+        bytes.extend_from_slice(bytemuck::bytes_of(&raw));
+    }
+
+    fn set_bind_groups<'a>(&'a self, pass: &mut wgpu::RenderPass<'a>) {
+        CustomPrimitive::set_bind_groups(self, pass);
+    }
+}
+
+/// The instances of all divs with one [`CustomPrimitive`] type, as bytes.
+#[derive(Debug, Clone)]
+pub struct CustomInstances {
+    pub primitive: TypeId,
+    pub instance_size: usize,
+    pub bytes: Vec<u8>,
+}
+
+impl CustomInstances {
+    pub fn len(&self) -> usize {
+        self.bytes.len() / self.instance_size.max(1)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// The instances of the type of `primitive` in `all`, added if there are none yet.
+    pub(crate) fn of<'a>(
+        all: &'a mut Vec<CustomInstances>,
+        primitive: &DivPrimitive,
+    ) -> &'a mut CustomInstances {
+        let type_id = primitive.type_id();
+        match all.iter().position(|c| c.primitive == type_id) {
+            Some(i) => &mut all[i],
+            None => {
+                all.push(CustomInstances {
+                    primitive: type_id,
+                    instance_size: primitive.instance_size(),
+                    bytes: vec![],
+                });
+                all.last_mut().unwrap()
+            }
+        }
+    }
+}
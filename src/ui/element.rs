@@ -3,13 +3,16 @@ use std::{ops::DerefMut, sync::Arc};
 
 use std::rc::Rc;
 
-use crate::texture::BindableTextureRef;
-use crate::{Aabb, AlphaSdfParams, BindableTexture, Color};
+use crate::texture::UiTextureRef;
+use crate::{Aabb, AlphaSdfParams, BindableTexture, Color, DRect};
 
-use glam::{vec2, DVec2, Vec2};
+use glam::{vec2, vec4, DVec2, Vec2, Vec4};
 use smallvec::{smallvec, SmallVec};
 
 use crate::ui::{
+    accessibility::{AccessInfo, AccessRole},
+    alpha_mask::AlphaMask,
+    custom_primitive::DivPrimitive,
     element_id::ElementId,
     element_store::{ElementBox, ElementWithComputed, IntoElementBox},
     layout::GlyphBoundsAndUv,
@@ -17,7 +20,7 @@ use crate::ui::{
 };
 
 use super::element_store::StoredElement;
-use super::font::SdfFontRef;
+use super::{font::TextHinting, font_slot::SectionFont};
 
 #[repr(C)]
 pub enum Element {
@@ -158,10 +161,15 @@ pub struct DivStyle {
     pub padding: Edges<f64>,
     /// the Vec2 should be in the unit square. (0,0) is the top right corner, (1,0) the top left corner and so on...
     pub absolute: Option<Vec2>,
+    /// Keeps an absolute div inside of the safe area of its [`crate::ui::Board`], e.g. for hud elements
+    /// anchored to the corners of the screen that would be cut off by a notch. No effect on other divs.
+    pub safe_area: bool,
     pub offset: DVec2,
     pub color: Color,
     pub border: DivBorder,
     pub texture: DivTexture,
+    /// How a [`DivTexture::Texture`] fills the div. Has no effect on other textures.
+    pub texture_fill: TextureFill,
     pub z_index: i16,
     pub shadow: DivShadow,
     /// gap is padding inserted *between* children of this div.
@@ -170,6 +178,72 @@ pub struct DivStyle {
     ///
     /// Note: gap has no effect if `MainAlign::SpaceBetween`` or `MainAlign::SpaceAround`!
     pub gap: f64,
+    /// If true, the scene behind this div is blurred and shown through it (frosted glass).
+    /// The div's `color` is mixed on top of the blurred backdrop according to its alpha.
+    ///
+    /// Only has an effect for screen space ui rendered with a backdrop, see [`crate::BackdropBlur`].
+    pub backdrop_blur: bool,
+    /// Shader effect of the rect, not inherited by children. Has no effect on divs with a [`DivTexture::AlphaSdfTexture`].
+    pub effect: UiEffect,
+    /// Cuts off the children at the bounds of the div (overflow: hidden), they are neither drawn nor hovered
+    /// outside of it. The div itself, e.g. its shadow, is not clipped. Nested clip rects intersect.
+    pub clip_children: bool,
+    /// Hover styles, gauges, scrolling and the other things most divs don't have, see [`DivStyle::extras_mut`].
+    /// Boxed, because every element in a [`crate::ui::Board`] takes as much space as the biggest div.
+    pub extras: Option<Box<DivExtras>>,
+}
+
+/// The parts of a [`DivStyle`] that only few divs use, see [`DivStyle::extras`].
+#[derive(Debug)]
+pub struct DivExtras {
+    /// Overrides applied automatically while the div is hovered. Only works for divs stored with an id.
+    pub hover_style: Option<DivStyleOverlay>,
+    /// Overrides applied automatically while the div is pressed, on top of the `hover_style`. Only works for divs stored with an id.
+    pub active_style: Option<DivStyleOverlay>,
+    /// Duration in seconds of the transition between normal, hovered and pressed style.
+    pub style_transition: f32,
+    /// If set and the div has a [`DivTexture::Texture`], the div is only hit by the cursor where the mask is opaque.
+    /// The mask is sampled like the texture (with its uv region and [`TextureFill`]), so it should be created from the same image.
+    pub alpha_hit_mask: Option<Arc<AlphaMask>>,
+    /// Draws the div as a progress bar: the fill is rendered on top of `color` in the shader.
+    /// Takes precedence over the `texture` of the div.
+    pub gauge: Option<DivGauge>,
+    /// Draws the div with a [`crate::ui::CustomPrimitive`] instead of as a rect, even if its `color` is transparent.
+    /// Takes precedence over the `texture` of the div, but not over a `gauge`.
+    pub primitive: Option<DivPrimitive>,
+    /// Role and label for screen readers, divs without are not part of the accessibility tree, see [`crate::ui::accessibility`].
+    pub access: Option<AccessInfo>,
+    /// Makes the div scroll its children vertically when they are taller than the div, see [`DivScroll`].
+    /// The children are clipped like with `clip_children`. Only works for divs stored with an id.
+    pub scroll: Option<DivScroll>,
+}
+
+impl Default for DivExtras {
+    fn default() -> Self {
+        Self {
+            hover_style: None,
+            active_style: None,
+            style_transition: 0.1,
+            alpha_hit_mask: None,
+            gauge: None,
+            primitive: None,
+            access: None,
+            scroll: None,
+        }
+    }
+}
+
+/// Partial overrides of a [`DivStyle`], used for [`DivExtras::hover_style`] and [`DivExtras::active_style`].
+///
+/// Note: `offset` and `scale` only affect how the div itself is rendered, not the layout or its children.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DivStyleOverlay {
+    pub color: Option<Color>,
+    pub border_color: Option<Color>,
+    pub border_width: Option<f32>,
+    pub offset: Option<Vec2>,
+    /// scales the rendered rect around its center.
+    pub scale: Option<f32>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -177,6 +251,9 @@ pub struct DivBorder {
     pub color: Color,
     pub radius: Corners<f32>,
     pub width: f32,
+    /// Width of the antialiased edge in reference pixels (see [`crate::ui::Board::reference_size`]),
+    /// converted into layout units with [`crate::ui::ElementContext::reference_scale`] during batching.
+    /// Values <= 0.0 use [`DivBorder::DEFAULT_SOFTNESS`].
     pub softness: f32,
 }
 
@@ -187,12 +264,24 @@ impl DivBorder {
         width: 0.0,
         softness: 0.0,
     };
+
+    /// One reference pixel, about one pixel of antialiasing at the reference screen size.
+    pub const DEFAULT_SOFTNESS: f32 = 1.0;
+
+    /// The softness used for rendering, in reference pixels.
+    pub fn effective_softness(&self) -> f32 {
+        if self.softness > 0.0 {
+            self.softness
+        } else {
+            Self::DEFAULT_SOFTNESS
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct DivShadow {
     pub color: Color,
-    // an outer padding in each of the 4 directions.
+    /// an outer padding in each of the 4 directions, in reference pixels like [`DivBorder::softness`].
     pub width: f32,
     // how intense a simple sdf shadow should be
     pub curve_param: f32,
@@ -204,6 +293,73 @@ impl DivShadow {
         width: 0.0,
         curve_param: 1.0,
     };
+
+    /// A shadow that fits a div with the corner `radius` (in reference pixels): it is wide enough to
+    /// continue the curve of the corners, so rounder divs get softer shadows.
+    pub fn for_radius(color: Color, radius: f32) -> Self {
+        DivShadow {
+            color,
+            width: 4.0 + radius.max(0.0) * 0.75,
+            curve_param: 1.0,
+        }
+    }
+}
+
+/// A shader effect of a div or text section, e.g. wavy text or a dissolving panel.
+///
+/// Effects are implemented by the shader hooks of the ui renderers, a switch over the effect id in wgsl.
+/// The default hooks in `ui_hooks.wgsl` implement the effects of the constructors below, a copy of that file
+/// with additional cases can be passed to [`crate::renderer::ui_screen::UiScreenRenderer::with_shader_hooks`]:
+///
+/// ```rust,ignore
+/// const GLITCH: u32 = UiEffect::FIRST_CUSTOM_ID;
+/// text("Game Over").effect(UiEffect::custom(GLITCH, vec4(0.5, 0.0, 0.0, 0.0)))
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct UiEffect {
+    /// 0 means no effect.
+    pub id: u32,
+    pub params: Vec4,
+}
+
+impl UiEffect {
+    pub const NONE: UiEffect = UiEffect {
+        id: 0,
+        params: Vec4::ZERO,
+    };
+    /// Ids below are reserved for the built-in effects.
+    pub const FIRST_CUSTOM_ID: u32 = 16;
+
+    /// Moves rects or glyphs up and down in a sine wave along the x axis. `wavelength` in layout units,
+    /// `speed` in waves per second.
+    pub fn wave(amplitude: f32, wavelength: f32, speed: f32) -> Self {
+        UiEffect {
+            id: 1,
+            params: vec4(amplitude, wavelength, speed, 0.0),
+        }
+    }
+
+    /// Multiplies the color with a rainbow that moves along the x axis.
+    pub fn rainbow(wavelength: f32, speed: f32) -> Self {
+        UiEffect {
+            id: 2,
+            params: vec4(wavelength, speed, 0.0, 0.0),
+        }
+    }
+
+    /// Noisy dissolve with a glowing edge, `progress` goes from 0.0 (fully visible) to 1.0 (gone).
+    /// `noise_scale` is the size of the noise features in layout units.
+    pub fn dissolve(progress: f32, noise_scale: f32, edge_width: f32) -> Self {
+        UiEffect {
+            id: 3,
+            params: vec4(progress, noise_scale, edge_width, 0.0),
+        }
+    }
+
+    /// An effect implemented in custom shader hooks, `id` should be at least [`UiEffect::FIRST_CUSTOM_ID`].
+    pub fn custom(id: u32, params: Vec4) -> Self {
+        UiEffect { id, params }
+    }
 }
 
 impl Default for DivStyle {
@@ -216,22 +372,52 @@ impl Default for DivStyle {
             main_align: MainAlign::Start,
             cross_align: Align::Start,
             absolute: None,
+            safe_area: false,
             color: Color::TRANSPARENT,
             border: DivBorder::ZERO,
             offset: DVec2::ZERO,
             texture: DivTexture::None,
+            texture_fill: TextureFill::Stretch,
             z_index: 0,
             shadow: DivShadow::ZERO,
             gap: 0.0,
+            backdrop_blur: false,
+            effect: UiEffect::NONE,
+            clip_children: false,
+            extras: None,
         }
     }
 }
 
 impl DivStyle {
+    /// The [`DivExtras`] of the div, created with their defaults if it has none yet.
+    pub fn extras_mut(&mut self) -> &mut DivExtras {
+        self.extras.get_or_insert_with(Default::default)
+    }
+
+    /// Exposes the div to screen readers, see [`crate::ui::accessibility`].
+    pub fn accessible(&mut self, role: AccessRole, label: impl Into<UiString>) {
+        self.extras_mut().access = Some(AccessInfo {
+            role,
+            label: Some(label.into()),
+        });
+    }
+
     pub fn texture(&mut self, region: TextureRegion) {
         self.texture = DivTexture::Texture(region);
     }
 
+    /// Sets a texture that is filled into the div according to `fill`, see [`TextureFill`].
+    pub fn texture_with_fill(&mut self, region: TextureRegion, fill: TextureFill) {
+        self.texture = DivTexture::Texture(region);
+        self.texture_fill = fill;
+    }
+
+    /// Only registers hits where the texture of the div is opaque according to `mask`, see [`AlphaMask`].
+    pub fn alpha_hit_test(&mut self, mask: Arc<AlphaMask>) {
+        self.extras_mut().alpha_hit_mask = Some(mask);
+    }
+
     pub fn alpha_sdf(&mut self, region: TextureRegion, params: AlphaSdfParams) {
         self.texture = DivTexture::AlphaSdfTexture(SdfTextureRegion { region, params });
     }
@@ -246,17 +432,205 @@ impl DivStyle {
         self.main_align = MainAlign::Center;
         self.cross_align = Align::Center;
     }
+
+    /// True for divs that cut off their children, see `clip_children` and [`DivExtras::scroll`].
+    #[inline]
+    pub fn clips_children(&self) -> bool {
+        self.clip_children || self.extras.as_ref().is_some_and(|e| e.scroll.is_some())
+    }
 }
 
 #[derive(Debug, Clone)]
 pub enum DivTexture {
     None,
-    /// RGBA texture
+    /// RGBA texture, or any other texture with a filterable format, see [`crate::UiTexture`].
     Texture(TextureRegion),
     /// RGBA texture where the alpha channel stores sdf information.
     AlphaSdfTexture(SdfTextureRegion),
 }
 
+/// The fill of a div used as a progress bar or gauge, see [`DivExtras::gauge`].
+///
+/// The fill is a rounded rect inside of the border, with the border radius of the div, so the fill keeps
+/// its round ends while it shrinks. Changing `fraction` every frame animates it smoothly:
+///
+/// ```rust,ignore
+/// div().style(|s| {
+///     s.size(200, 16);
+///     s.color = Color::from_hex("#202020");
+///     s.border.radius = Corners::all(8.0);
+///     s.extras_mut().gauge = Some(DivGauge::new(health / max_health, Color::RED).gradient(Color::GREEN).inset(2.0));
+/// })
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DivGauge {
+    /// How much of the gauge is filled, from 0.0 to 1.0. Values outside are clamped.
+    pub fraction: f32,
+    /// Color at the start of the gauge.
+    pub color: Color,
+    /// Color at the end of the gauge. The gradient goes over the whole gauge, not just the filled part,
+    /// so the color at the end of the fill depends on the fraction. Same as `color` for a solid fill.
+    pub end_color: Color,
+    pub direction: GaugeDirection,
+    /// Space between the border of the div and the fill, in layout units.
+    pub inset: f32,
+}
+
+impl DivGauge {
+    pub fn new(fraction: f32, color: Color) -> Self {
+        DivGauge {
+            fraction,
+            color,
+            end_color: color,
+            direction: GaugeDirection::LeftToRight,
+            inset: 0.0,
+        }
+    }
+
+    pub fn gradient(mut self, end_color: Color) -> Self {
+        self.end_color = end_color;
+        self
+    }
+
+    pub fn direction(mut self, direction: GaugeDirection) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    pub fn inset(mut self, inset: f32) -> Self {
+        self.inset = inset;
+        self
+    }
+}
+
+/// A scroll area: a div with a fixed height whose children can be taller than the div, see [`DivExtras::scroll`].
+///
+/// The scroll offset is stored in the [`crate::ui::ElementContext`] under the id of the div, such that it stays
+/// the same when the elements are built again in the next frame. Hovering the div and turning the mouse wheel
+/// scrolls it, [`crate::ui::Board::update`] does that automatically:
+///
+/// ```rust,ignore
+/// let list = div()
+///     .style(|s| {
+///         s.height = Some(Len::Px(400.0));
+///         s.extras_mut().scroll = Some(DivScroll::default().scrollbar(Scrollbar::default()));
+///     })
+///     .child(long_content);
+/// root.child_with_id("inventory", list)
+/// // jump back to the top:
+/// ctx.set_scroll_offset("inventory".into(), 0.0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DivScroll {
+    /// Layout units scrolled per line of mouse wheel scrolling, see [`crate::Input::scroll`].
+    pub line_height: f64,
+    /// Drawn on the right edge of the div while its children are taller than the div. None shows no scrollbar.
+    pub scrollbar: Option<Scrollbar>,
+}
+
+impl DivScroll {
+    pub const DEFAULT_LINE_HEIGHT: f64 = 40.0;
+
+    pub fn line_height(mut self, line_height: f64) -> Self {
+        self.line_height = line_height;
+        self
+    }
+
+    pub fn scrollbar(mut self, scrollbar: Scrollbar) -> Self {
+        self.scrollbar = Some(scrollbar);
+        self
+    }
+}
+
+impl Default for DivScroll {
+    fn default() -> Self {
+        DivScroll {
+            line_height: Self::DEFAULT_LINE_HEIGHT,
+            scrollbar: None,
+        }
+    }
+}
+
+/// Style of the scrollbar of a [`DivScroll`], all sizes in layout units.
+///
+/// The scrollbar only shows where the content is, it cannot be dragged.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scrollbar {
+    pub width: f32,
+    /// Space between the scrollbar and the edges of the div.
+    pub inset: f32,
+    pub thumb_color: Color,
+    /// Drawn behind the thumb over the whole height of the scrollbar.
+    pub track_color: Color,
+    /// The thumb gets shorter the longer the content is, but not shorter than this.
+    pub min_thumb_length: f32,
+}
+
+impl Default for Scrollbar {
+    fn default() -> Self {
+        Scrollbar {
+            width: 6.0,
+            inset: 2.0,
+            thumb_color: Color::from_hex("#ffffff80"),
+            track_color: Color::TRANSPARENT,
+            min_thumb_length: 24.0,
+        }
+    }
+}
+
+impl Scrollbar {
+    /// The track and the thumb in a div with the `bounds`, scrolled by `offset` of `max_scroll`.
+    /// None if the children fit into the div, then there is nothing to scroll.
+    pub fn rects(&self, bounds: Aabb, offset: f64, max_scroll: f64) -> Option<(Aabb, Aabb)> {
+        if max_scroll <= 0.0 {
+            return None;
+        }
+        let track = Aabb::new(
+            vec2(
+                bounds.max.x - self.inset - self.width,
+                bounds.min.y + self.inset,
+            ),
+            vec2(bounds.max.x - self.inset, bounds.max.y - self.inset),
+        );
+        let track_len = track.size().y.max(0.0);
+        let visible = bounds.size().y as f64;
+        let thumb_len = (track_len as f64 * visible / (visible + max_scroll)) as f32;
+        let thumb_len = thumb_len.max(self.min_thumb_length).min(track_len);
+        let thumb_y = track.min.y + (track_len - thumb_len) * (offset / max_scroll) as f32;
+        let thumb = Aabb::new(
+            vec2(track.min.x, thumb_y),
+            vec2(track.max.x, thumb_y + thumb_len),
+        );
+        Some((track, thumb))
+    }
+}
+
+/// The direction in which a [`DivGauge`] fills up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u32)]
+pub enum GaugeDirection {
+    #[default]
+    LeftToRight = 0,
+    RightToLeft = 1,
+    TopToBottom = 2,
+    BottomToTop = 3,
+}
+
+/// Determines how the uv region of a [`DivTexture::Texture`] is mapped onto the div's rect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TextureFill {
+    /// The uv region is stretched over the entire rect, ignoring the aspect ratio.
+    #[default]
+    Stretch,
+    /// The uv region is repeated, such that one texture pixel covers one ui unit.
+    Tile,
+    /// The uv region is cropped, such that it covers the entire rect with the aspect ratio of the texture.
+    Cover,
+    /// The rendered rect is shrunk, such that the entire uv region is visible with the aspect ratio of the texture.
+    /// This also shrinks the background color and border of the div.
+    Contain,
+}
+
 #[derive(Debug, Clone)]
 pub struct SdfTextureRegion {
     pub region: TextureRegion,
@@ -265,7 +639,8 @@ pub struct SdfTextureRegion {
 
 #[derive(Debug, Clone, Copy)]
 pub struct TextureRegion {
-    pub texture: BindableTextureRef,
+    /// any [`crate::UiTexture`], e.g. a `&'static BindableTexture` or a [`crate::TextureViewBinding`].
+    pub texture: UiTextureRef,
     pub uv: Aabb,
 }
 
@@ -330,6 +705,21 @@ pub enum Align {
     End,
 }
 
+/// Where a text section or inline element sits in its line, when the line has sections of different sizes.
+/// The line is as tall as the largest ascent plus the largest descent of its sections.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum VerticalAlign {
+    /// Text sits on the common baseline of the line, inline elements stand on it.
+    #[default]
+    Baseline,
+    /// The top of the section (its ascent) or element is at the top of the line.
+    Top,
+    /// Centered between the top and bottom of the line.
+    Middle,
+    /// The bottom of the section (its descent) or element is at the bottom of the line.
+    Bottom,
+}
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy, Default)]
 pub struct Corners<T> {
@@ -353,7 +743,7 @@ unsafe impl<T: bytemuck::Zeroable> bytemuck::Zeroable for Corners<T> {}
 unsafe impl<T: bytemuck::Pod> bytemuck::Pod for Corners<T> {}
 
 #[repr(C)]
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub struct Edges<T> {
     pub left: T,
     pub right: T,
@@ -411,6 +801,9 @@ pub struct Text {
     pub sections: SmallVec<[Section; 1]>,
     pub offset: DVec2,
     pub additional_line_gap: f32,
+    /// If true, the text can be selected by dragging over it, see [`crate::ui::ElementContext::selected_text`].
+    /// Needs an id, like all interactive elements.
+    pub selectable: bool,
 }
 
 impl IntoElementBox for Text {
@@ -429,11 +822,17 @@ impl Default for Text {
             sections: Default::default(),
             offset: Default::default(),
             additional_line_gap: 0.0,
+            selectable: false,
         }
     }
 }
 
 impl Text {
+    pub fn selectable(mut self) -> Self {
+        self.selectable = true;
+        self
+    }
+
     pub fn element_sections_mut(&mut self) -> impl Iterator<Item = &mut StoredElement> {
         self.sections.iter_mut().filter_map(|s| match s {
             Section::Element { element, .. } => Some(element.deref_mut()),
@@ -455,9 +854,22 @@ pub enum Section {
     Element {
         element: ElementBox,
         sets_line_height: bool,
+        /// [`VerticalAlign::Bottom`] puts the element on the bottom of the line, below the descent of the text.
+        align: VerticalAlign,
     },
 }
 
+impl Section {
+    /// An inline element that makes its line at least as tall as itself, standing on the bottom of the line.
+    pub fn element(element: ElementBox) -> Self {
+        Section::Element {
+            element,
+            sets_line_height: true,
+            align: VerticalAlign::Bottom,
+        }
+    }
+}
+
 impl From<Div> for Element {
     fn from(value: Div) -> Self {
         Element::Div(value)
@@ -476,6 +888,7 @@ impl From<TextSection> for Element {
             sections: smallvec![Section::Text(value)],
             offset: DVec2::ZERO,
             additional_line_gap: 0.0,
+            selectable: false,
         })
     }
 }
@@ -539,10 +952,17 @@ impl From<Rc<str>> for UiString {
 #[derive(Debug, Clone)]
 pub struct TextSection {
     pub string: UiString,
-    pub font: SdfFontRef,
+    /// A fixed font or a [`crate::ui::FontSlot`], convert either with `.into()`.
+    pub font: SectionFont,
     pub color: Color,
     pub font_size: f32,
     pub shadow_intensity: f32,
+    /// Overrides the hinting of the font, see [`SdfFont::set_hinting`].
+    pub hinting: Option<TextHinting>,
+    /// Shader effect applied to every glyph, e.g. [`UiEffect::wave`].
+    pub effect: UiEffect,
+    /// Where the section sits in lines with larger sections.
+    pub vertical_align: VerticalAlign,
 }
 
 impl IntoElementBox for TextSection {
@@ -573,6 +993,10 @@ impl ElementT for Text {
 pub struct DivComputed {
     pub bounds: ComputedBounds,
     pub content_size: DVec2,
+    /// For divs with [`DivExtras::scroll`]: how far the children are scrolled up, at most `max_scroll`.
+    pub scroll: f64,
+    /// How much taller the children (with padding) are than the div, 0.0 if they fit.
+    pub max_scroll: f64,
 }
 
 #[derive(Debug, Default)]
@@ -581,19 +1005,13 @@ pub struct TextComputed {
     /// Should have the same length as the number of text-sections in this text. Should point to ranges of the glyphs vec below.
     pub text_section_glyphs: SmallVec<[std::ops::Range<usize>; 2]>,
     pub glyphs: Vec<GlyphBoundsAndUv>,
+    /// The max width the glyphs were laid out in. Texts that were retained from the last frame
+    /// (see [`crate::ui::retained`]) are only laid out again if it changes.
+    pub(crate) layout_width: Option<f32>,
+    /// Sum of the generations of the [`crate::ui::FontSlot`]s of the sections when they were laid out,
+    /// retained texts are laid out again when a font is swapped.
+    pub(crate) font_generation: u64,
 }
 
-#[derive(Debug, Clone, Copy, Default)]
-pub struct ComputedBounds {
-    pub pos: DVec2,
-    pub size: DVec2,
-}
-
-impl ComputedBounds {
-    pub fn contains(&self, point: &DVec2) -> bool {
-        point.x >= self.pos.x
-            && point.y >= self.pos.y
-            && point.x <= self.pos.x + self.size.x
-            && point.y <= self.pos.y + self.size.y
-    }
-}
+/// Position (top left corner) and size of an element in layout space.
+pub type ComputedBounds = DRect;
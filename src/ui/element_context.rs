@@ -1,13 +1,26 @@
-use crate::{Input, MouseButtonState, PhysicalSize, PressState};
+use std::{
+    hash::Hash,
+    ops::{Deref, DerefMut, Range},
+};
+
+use crate::{
+    Aabb, ClickThrough, Color, Input, InputCategories, InputLayer, MouseButtonState, PhysicalSize,
+    PressState, Resizable, Screen, Time, UiScaling, UiTransform, UiViewport, Window,
+};
+use ahash::{AHashMap, AHashSet};
 use etagere::euclid::default;
 use glam::{dvec2, DVec2, Vec2};
+use log::warn;
 
 use crate::ui::{
-    batching::ElementBatches,
+    alpha_mask::AlphaHitTest,
+    batching::{get_batches_with_ctx, texture_placement, ElementBatches, ElementBatchesGR},
     div,
-    element::{ComputedBounds, Element},
+    element::{ComputedBounds, DivComputed, DivScroll, Element, TextComputed},
     element_id::ElementId,
-    ElementBox, IntoElementBox,
+    retained::RetainedElements,
+    text_selection::{caret_at, selected_string, TextSelection},
+    Div, DivTexture, Edges, ElementBox, IntoElementBox, Text, DEFAULT_REFERENCE_SIZE,
 };
 
 use super::layout::ComputedBoundsVisitor;
@@ -21,20 +34,320 @@ pub struct ElementContext {
     // a `StoredElement::set_position()` pass. That means, that children, come first, then their parents. Explicit z index is not regarded here...
     // To find the first element hit by a mouse cursor, search from front to back.
     id_bounds: Vec<(ElementId, ComputedBounds)>,
+    /// for divs with an `alpha_hit_mask`, checked after the bounds in `hovered_element`.
+    alpha_hit_tests: AHashMap<ElementId, AlphaHitTest>,
+    /// for elements inside of divs with `clip_children`, they are only hovered inside of the clip rect.
+    id_clips: AHashMap<ElementId, Aabb>,
+    /// clip rects of the divs with `clip_children` around the elements visited during layout, intersected.
+    clip_stack: Vec<Aabb>,
+    /// scroll offsets of divs with `scroll`, kept between frames.
+    scroll_offsets: AHashMap<ElementId, f64>,
+    /// divs with `scroll` in the last layout, inner ones before the ones around them.
+    scroll_areas: Vec<ScrollArea>,
     interaction_state: InteractionState<ElementId>,
+    /// transition state of divs with a `hover_style` or `active_style` that are not in their normal style right now.
+    style_transitions: AHashMap<ElementId, StyleTransition>,
+    /// elements whose style transition was advanced since the last `start_frame`.
+    style_transitions_seen: AHashSet<ElementId>,
+    /// seconds since last frame, passed to `start_frame`.
+    frame_delta: f32,
+    /// layout units per reference pixel.
+    reference_scale: f64,
+    /// in layout space, set in `start_frame`.
+    cursor_pos: DVec2,
+    text_selection: Option<TextSelection>,
+    selection_color: Color,
+    /// in layout space, for absolute divs with `safe_area` set.
+    safe_area: Option<Aabb>,
+    /// elements that move the window when pressed, registered during the last frame.
+    window_drag_areas: Vec<ElementId>,
+    /// a window drag area was pressed in this frame.
+    drag_window: bool,
+    /// subtrees reused from the tree of the last frame, see [`ElementContext::retained`].
+    retained: RetainedElements,
+    /// clicked in the next frame without the mouse, see [`ElementContext::activate`].
+    pending_activation: Option<ElementId>,
+    /// modals built in the last frame, see [`ElementContext::modal`].
+    modals: Vec<InputLayer>,
+    /// modals registered while building the current frame.
+    next_modals: Vec<InputLayer>,
+    /// sort the batched elements on the rayon thread pool, see [`ElementContext::set_parallel`].
+    parallel: bool,
+}
+
+/// A div with `scroll` as laid out in the last frame.
+#[derive(Debug, Clone, Copy)]
+struct ScrollArea {
+    id: ElementId,
+    /// where the wheel scrolls the div: its bounds, cut off by the clip rects around it.
+    hit_bounds: Aabb,
+    line_height: f64,
+    offset: f64,
+    max_scroll: f64,
+}
+
+/// How far a div has transitioned into its hover and active styles, each in 0..=1.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct StyleTransition {
+    pub hover: f32,
+    pub active: f32,
 }
 
 impl ElementContext {
     pub fn new() -> Self {
         ElementContext {
             id_bounds: vec![],
+            alpha_hit_tests: AHashMap::new(),
+            id_clips: AHashMap::new(),
+            clip_stack: vec![],
+            scroll_offsets: AHashMap::new(),
+            scroll_areas: vec![],
             interaction_state: InteractionState::default(),
+            style_transitions: AHashMap::new(),
+            style_transitions_seen: AHashSet::new(),
+            frame_delta: 0.0,
+            reference_scale: 1.0,
+            cursor_pos: DVec2::ZERO,
+            text_selection: None,
+            selection_color: ElementContext::DEFAULT_SELECTION_COLOR,
+            safe_area: None,
+            window_drag_areas: vec![],
+            drag_window: false,
+            retained: RetainedElements::default(),
+            pending_activation: None,
+            modals: vec![],
+            next_modals: vec![],
+            #[cfg(feature = "parallel")]
+            parallel: super::parallel::is_enabled(),
+            #[cfg(not(feature = "parallel"))]
+            parallel: false,
+        }
+    }
+
+    pub const DEFAULT_SELECTION_COLOR: Color = Color::from_hex("#3390ff80");
+
+    /// The area absolute divs with `safe_area` set are kept inside of during layout. Set by [`Board`],
+    /// only needs to be set when laying out elements without a board.
+    pub fn set_safe_area(&mut self, safe_area: Option<Aabb>) {
+        self.safe_area = safe_area;
+    }
+
+    /// Sorts the elements of large boards by their stacking level on the rayon thread pool, on by default.
+    /// Only has an effect with the `parallel` feature, see `ui::parallel`.
+    pub fn set_parallel(&mut self, parallel: bool) {
+        self.parallel = parallel;
+    }
+
+    pub fn parallel(&self) -> bool {
+        self.parallel
+    }
+
+    /// The current selection in a selectable [`crate::ui::Text`]. There is at most one selection,
+    /// starting to drag over another text or clicking somewhere else removes it.
+    pub fn text_selection(&self) -> Option<&TextSelection> {
+        self.text_selection.as_ref()
+    }
+
+    /// The selected substring, None if nothing is selected. E.g. to copy it to the clipboard on Ctrl+C:
+    ///
+    /// ```rust,ignore
+    /// if input.shortcut(Modifiers::CTRL, KeyCode::KeyC) {
+    ///     board.ctx.copy_selection_to_clipboard()?;
+    /// }
+    /// ```
+    pub fn selected_text(&self) -> Option<&str> {
+        self.text_selection
+            .as_ref()
+            .filter(|s| !s.is_empty())
+            .map(|s| s.text.as_str())
+    }
+
+    pub fn clear_text_selection(&mut self) {
+        self.text_selection = None;
+    }
+
+    /// Color of the highlights behind selected glyphs.
+    pub fn selection_color(&self) -> Color {
+        self.selection_color
+    }
+
+    pub fn set_selection_color(&mut self, color: Color) {
+        self.selection_color = color;
+    }
+
+    /// Copies the [`ElementContext::selected_text`] to the clipboard of the system. Returns false if nothing is selected.
+    #[cfg(feature = "clipboard")]
+    pub fn copy_selection_to_clipboard(&self) -> crate::Result<bool> {
+        let Some(text) = self.selected_text() else {
+            return Ok(false);
+        };
+        arboard::Clipboard::new()?.set_text(text)?;
+        Ok(true)
+    }
+
+    /// Starts the selection when a drag starts on the text and moves its focus while dragging.
+    /// Called during batching for selectable texts with an id, returns the selected glyphs.
+    pub(crate) fn update_text_selection(
+        &mut self,
+        id: ElementId,
+        text: &Text,
+        computed: &TextComputed,
+    ) -> Option<Range<usize>> {
+        let interaction = self.interaction_state.of(id);
+        let cursor_pos = self.cursor_pos.as_vec2();
+        if interaction.just_started_click {
+            let caret = caret_at(&computed.glyphs, cursor_pos);
+            self.text_selection = Some(TextSelection {
+                id,
+                anchor: caret,
+                focus: caret,
+                text: String::new(),
+            });
+        }
+        let selection = self.text_selection.as_mut().filter(|s| s.id == id)?;
+        if interaction.hot_active == HotActive::Active {
+            selection.focus = caret_at(&computed.glyphs, cursor_pos);
+        }
+        // the text could have become shorter since the selection was made.
+        let n_glyphs = computed.glyphs.len();
+        selection.anchor = selection.anchor.min(n_glyphs);
+        selection.focus = selection.focus.min(n_glyphs);
+        selection.text = selected_string(text, computed, selection.glyph_range());
+        Some(selection.glyph_range())
+    }
+
+    /// Layout units per reference pixel (see [`Board::reference_size`]). Used in batching to convert
+    /// values that are specified in reference pixels, like [`crate::ui::DivBorder::softness`], into layout units.
+    pub fn reference_scale(&self) -> f64 {
+        self.reference_scale
+    }
+
+    /// Set automatically by the resize functions of [`Board`]. Should stay 1.0 for boards in world space,
+    /// whose layout units are not related to the screen.
+    pub fn set_reference_scale(&mut self, reference_scale: f64) {
+        self.reference_scale = reference_scale;
+    }
+
+    /// Moves the style transition of the element towards its current interaction state and returns it.
+    /// Called during batching, so it should happen once per frame for each element.
+    pub fn advance_style_transition(&mut self, id: ElementId, duration: f32) -> StyleTransition {
+        let hot_active = self.interaction_state.of(id).hot_active;
+        let hover_target = if hot_active == HotActive::None {
+            0.0
+        } else {
+            1.0
+        };
+        let active_target = if hot_active == HotActive::Active {
+            1.0
+        } else {
+            0.0
+        };
+        let step = if duration > 0.0 {
+            self.frame_delta / duration
+        } else {
+            1.0
+        };
+
+        self.style_transitions_seen.insert(id);
+        let mut transition = self.style_transitions.get(&id).copied().unwrap_or_default();
+        transition.hover = move_towards(transition.hover, hover_target, step);
+        transition.active = move_towards(transition.active, active_target, step);
+        if transition == StyleTransition::default() {
+            self.style_transitions.remove(&id);
+        } else {
+            self.style_transitions.insert(id, transition);
+        }
+        transition
+    }
+
+    /// Bounds of the element with the `id` in the last layout, None if it was not laid out.
+    pub fn computed_bounds(&self, id: ElementId) -> Option<ComputedBounds> {
+        self.id_bounds
+            .iter()
+            .find(|(i, _)| *i == id)
+            .map(|(_, bounds)| *bounds)
+    }
+
+    /// In layout space, as passed to [`ElementContext::start_frame`].
+    pub fn cursor_pos(&self) -> DVec2 {
+        self.cursor_pos
+    }
+
+    /// The subtree built by `build`, or the same subtree as in the last frame if it was retained with the
+    /// same `id` and `key` then. The `key` needs to cover all state the subtree depends on, see [`crate::ui::retained`].
+    pub fn retained(
+        &mut self,
+        id: impl Into<ElementId>,
+        key: impl Hash,
+        build: impl FnOnce(&mut ElementContext) -> ElementBox,
+    ) -> ElementBox {
+        let id: ElementId = id.into();
+        if let Some(element) = self.retained.take(id, &key) {
+            return element;
         }
+        let mut element = build(self);
+        element.id = id;
+        element
+    }
+
+    /// How many subtrees of the last [`Board::build`] were reused from the frame before and how many were built.
+    pub fn retained_counts(&self) -> (usize, usize) {
+        self.retained.counts()
     }
 
     #[inline(always)]
     pub fn clear_id_bounds(&mut self) {
-        self.id_bounds.clear()
+        self.id_bounds.clear();
+        self.alpha_hit_tests.clear();
+        self.id_clips.clear();
+        self.scroll_areas.clear();
+    }
+
+    /// How far the children of the div with [`crate::ui::DivExtras::scroll`] and the `id` are scrolled up,
+    /// in layout units. Clamped to the height of the children in the last layout.
+    pub fn scroll_offset(&self, id: ElementId) -> f64 {
+        self.scroll_offsets.get(&id).copied().unwrap_or(0.0)
+    }
+
+    /// Scrolls the div with the `id` to the `offset` in the next layout, e.g. 0.0 to jump to the top.
+    /// Offsets past the end are clamped, so [`f64::MAX`] scrolls to the bottom.
+    pub fn set_scroll_offset(&mut self, id: ElementId, offset: f64) {
+        self.scroll_offsets.insert(id, offset.max(0.0));
+    }
+
+    /// The largest scroll offset of the div with the `id` in the last layout, None if it was not laid out.
+    pub fn max_scroll(&self, id: ElementId) -> Option<f64> {
+        self.scroll_areas
+            .iter()
+            .find(|a| a.id == id)
+            .map(|a| a.max_scroll)
+    }
+
+    /// Scrolls the innermost scroll area under the cursor that can still move in the direction of `lines`
+    /// (positive is up, like the mouse wheel), see [`crate::Input::scroll`]. Returns false if no area was scrolled.
+    /// Called by [`Board::update`], after [`ElementContext::start_frame`].
+    pub fn scroll_hovered(&mut self, lines: f32) -> bool {
+        let cursor_pos = self.cursor_pos.as_vec2();
+        let Some(area) = self.scroll_areas.iter_mut().find(|a| {
+            let can_move = if lines > 0.0 {
+                a.offset > 0.0
+            } else {
+                a.offset < a.max_scroll
+            };
+            can_move && a.hit_bounds.contains(cursor_pos)
+        }) else {
+            return false;
+        };
+        area.offset = (area.offset - lines as f64 * area.line_height).clamp(0.0, area.max_scroll);
+        self.scroll_offsets.insert(area.id, area.offset);
+        true
+    }
+
+    /// Makes the element count as clicked in the next frame, both `just_started_click` and `just_ended_click`
+    /// are set for it. E.g. for activation by assistive technology (see [`crate::ui::accessibility`]) or for
+    /// keyboard and gamepad navigation.
+    pub fn activate(&mut self, id: ElementId) {
+        self.pending_activation = Some(id);
     }
 
     #[inline(always)]
@@ -56,36 +369,230 @@ impl ElementContext {
         &mut self,
         cursor_pos: DVec2,
         mouse: MouseButtonState,
+        delta: f32,
         screen_px_size: PhysicalSize<u32>,
         fixed_layout_height: f64,
     ) {
-        let cursor_pos = cursor_pos * fixed_layout_height / screen_px_size.height as f64;
-        self.start_frame(cursor_pos, mouse);
+        let transform =
+            UiTransform::new(screen_px_size, UiScaling::FixedHeight(fixed_layout_height));
+        self.start_frame_with_transform(cursor_pos, mouse, delta, &transform);
+    }
+
+    /// Maps the `cursor_pos` in screen pixels into layout space with the same transform the shaders use,
+    /// see [`crate::Screen::ui_transform`].
+    pub fn start_frame_with_transform(
+        &mut self,
+        cursor_pos: DVec2,
+        mouse: MouseButtonState,
+        delta: f32,
+        transform: &UiTransform,
+    ) {
+        self.start_frame(transform.screen_to_layout(cursor_pos), mouse, delta);
+    }
+
+    /// Like [`ElementContext::start_frame_with_transform`] for a board shown in a [`UiViewport`] of the screen ui
+    /// with the `screen` transform. Nothing is hovered while the cursor is outside of the clip rect of the viewport.
+    pub fn start_frame_in_viewport(
+        &mut self,
+        cursor_pos: DVec2,
+        mouse: MouseButtonState,
+        delta: f32,
+        screen: &UiTransform,
+        viewport: &UiViewport,
+    ) {
+        let cursor_pos = if viewport.contains(screen, cursor_pos) {
+            viewport.ui_transform(screen).screen_to_layout(cursor_pos)
+        } else {
+            DVec2::INFINITY
+        };
+        self.start_frame(cursor_pos, mouse, delta);
     }
 
     /// Note: cursor_pos needs to be in layout space, which could be different from the pixel space on screen.
-    pub fn start_frame(&mut self, cursor_pos: DVec2, mouse: MouseButtonState) {
+    ///
+    /// `delta` is the time since the last frame in seconds, e.g. from [`Time::delta`], it advances the transitions
+    /// between the normal, hover and active styles of divs. Recorded deltas replay them exactly.
+    pub fn start_frame(&mut self, cursor_pos: DVec2, mouse: MouseButtonState, delta: f32) {
+        self.frame_delta = delta;
+        // elements that were not built in the last frame don't need their transition anymore:
+        let seen = &self.style_transitions_seen;
+        self.style_transitions.retain(|id, _| seen.contains(id));
+        self.style_transitions_seen.clear();
+
         // find element hovered:
         let hovered = self.hovered_element(&cursor_pos);
         let left_mouse_down = mouse.left().pressed();
         self.interaction_state.transition(hovered, left_mouse_down);
+        if let Some(id) = self.pending_activation.take() {
+            self.interaction_state.just_started_click = Some(id);
+            self.interaction_state.just_ended_click = Some(id);
+        }
+        self.cursor_pos = cursor_pos;
+        self.drag_window = self
+            .interaction_state
+            .just_started_click
+            .is_some_and(|id| self.window_drag_areas.contains(&id));
+        self.window_drag_areas.clear();
+        self.modals.clear();
+        std::mem::swap(&mut self.modals, &mut self.next_modals);
+
+        // clicking anywhere but on the selected text removes the selection:
+        if mouse.left().just_pressed()
+            && self
+                .text_selection
+                .as_ref()
+                .is_some_and(|s| Some(s.id) != hovered)
+        {
+            self.text_selection = None;
+        }
+    }
+
+    /// Makes the element with the `id` move the window when it is pressed, e.g. the title bar of an undecorated
+    /// window. Call it every frame while building the elements, children with an id of their own, like a close
+    /// button, still get their clicks. See [`ElementContext::update_window`].
+    pub fn window_drag_area(&mut self, id: ElementId) {
+        self.window_drag_areas.push(id);
+    }
+
+    /// Marks the ui as showing a modal (pause menu, dialog, ...) that consumes the input `categories`. Call it
+    /// every frame while building the modal, [`crate::InputStack::update_from_ui`] then puts a layer with the `name`
+    /// above all other layers from the next [`ElementContext::start_frame`] on, until the modal is not built anymore.
+    pub fn modal(&mut self, name: &'static str, consumes: InputCategories) {
+        self.next_modals.retain(|m| m.name != name);
+        self.next_modals.push(InputLayer { name, consumes });
+    }
+
+    /// The modals built in the last frame, bottom to top.
+    pub fn modals(&self) -> &[InputLayer] {
+        &self.modals
+    }
+
+    /// True in the frame a [`ElementContext::window_drag_area`] was pressed.
+    pub fn drag_window_requested(&self) -> bool {
+        self.drag_window
+    }
+
+    /// Applies what the ui of this frame wants from the window: starts moving it if a
+    /// [`ElementContext::window_drag_area`] was pressed and, for [`ClickThrough::OutsideOfUi`], lets the cursor
+    /// through unless an element with an id is hovered. Call it once per frame after [`ElementContext::start_frame`].
+    pub fn update_window(&self, window: &Window, click_through: ClickThrough) {
+        if self.drag_window {
+            if let Err(err) = window.drag_window() {
+                warn!("Window cannot be dragged: {err}");
+            }
+        }
+        if click_through == ClickThrough::OutsideOfUi {
+            let catch_cursor = self.interaction_state.hovered.is_some()
+                || !self.interaction_state.hot_state.is_none();
+            _ = window.set_cursor_hittest(catch_cursor);
+        }
+    }
+
+    /// Whether an element with an id is at `layout_pos` or one is pressed right now, for [`crate::AppT::catches_cursor`].
+    pub fn catches_cursor(&self, layout_pos: DVec2) -> bool {
+        self.hovered_element(&layout_pos).is_some() || !self.interaction_state.hot_state.is_none()
     }
 
     pub fn hovered_element(&self, cursor_pos: &DVec2) -> Option<ElementId> {
         for (id, bounds) in self.id_bounds.iter() {
-            if bounds.contains(cursor_pos) {
-                return Some(*id);
+            if !bounds.contains(*cursor_pos) {
+                continue;
+            }
+            if let Some(hit_test) = self.alpha_hit_tests.get(id) {
+                if !hit_test.hit(cursor_pos) {
+                    continue;
+                }
+            }
+            if let Some(clip) = self.id_clips.get(id) {
+                if !clip.contains(cursor_pos.as_vec2()) {
+                    continue;
+                }
             }
+            return Some(*id);
         }
         None
     }
 }
 
+fn move_towards(current: f32, target: f32, step: f32) -> f32 {
+    if current < target {
+        (current + step).min(target)
+    } else {
+        (current - step).max(target)
+    }
+}
+
 pub struct IdElementBounds {}
 impl ComputedBoundsVisitor for ElementContext {
     fn visit(&mut self, id: ElementId, computed_bounds: &ComputedBounds) {
         if !id.is_none() {
             self.id_bounds.push((id, *computed_bounds));
+            if let Some(clip) = self.clip_stack.last() {
+                self.id_clips.insert(id, *clip);
+            }
+        }
+    }
+
+    fn push_clip(&mut self, clip: Aabb) {
+        let clip = match self.clip_stack.last() {
+            // an empty rect that contains no position, if the clip rects do not intersect.
+            Some(outer) => outer
+                .intersection(&clip)
+                .unwrap_or(Aabb::new(Vec2::INFINITY, Vec2::NEG_INFINITY)),
+            None => clip,
+        };
+        self.clip_stack.push(clip);
+    }
+
+    fn pop_clip(&mut self) {
+        self.clip_stack.pop();
+    }
+
+    fn scroll_offset(&self, id: ElementId) -> f64 {
+        ElementContext::scroll_offset(self, id)
+    }
+
+    fn visit_scroll_area(&mut self, id: ElementId, scroll: &DivScroll, computed: &DivComputed) {
+        if id.is_none() {
+            return;
+        }
+        let bounds = computed.bounds.as_aabb();
+        let hit_bounds = match self.clip_stack.last() {
+            Some(clip) => clip
+                .intersection(&bounds)
+                .unwrap_or(Aabb::new(Vec2::INFINITY, Vec2::NEG_INFINITY)),
+            None => bounds,
+        };
+        self.scroll_offsets.insert(id, computed.scroll);
+        self.scroll_areas.push(ScrollArea {
+            id,
+            hit_bounds,
+            line_height: scroll.line_height,
+            offset: computed.scroll,
+            max_scroll: computed.max_scroll,
+        });
+    }
+
+    fn safe_area(&self) -> Option<Aabb> {
+        self.safe_area
+    }
+
+    fn visit_div(&mut self, id: ElementId, div: &Div, computed_bounds: &ComputedBounds) {
+        self.visit(id, computed_bounds);
+        if id.is_none() {
+            return;
+        }
+        let mask = div.extras.as_ref().and_then(|e| e.alpha_hit_mask.as_ref());
+        if let (Some(mask), DivTexture::Texture(texture)) = (mask, &div.texture) {
+            let (bounds, uv, tiling) =
+                texture_placement(computed_bounds.as_aabb(), texture, div.texture_fill);
+            let hit_test = AlphaHitTest {
+                mask: mask.clone(),
+                bounds,
+                uv,
+                tiling,
+            };
+            self.alpha_hit_tests.insert(id, hit_test);
         }
     }
 }
@@ -363,11 +870,67 @@ pub struct Board {
     pub pos_offset: DVec2,
     pub element: ElementBox,
     pub batches: ElementBatches,
+    /// Whether the board should be rendered before or after tone mapping. Only a hint for the render code of your app,
+    /// the board itself does not render anything.
+    pub compositing: UiCompositing,
+    /// Which rects and glyphs are skipped during batching because they are not visible.
+    pub culling: UiCulling,
+    /// Insets of the safe area from the edges of the board in layout units, set from the [`Screen`] in
+    /// [`Board::resize_to_ui_transform`]. Absolute divs with `safe_area` set always stay inside of it.
+    pub safe_area_insets: Edges<f64>,
+    /// Lays out the whole element inside of the safe area instead of the full size of the board.
+    /// Off by default, such that backgrounds can still cover the whole screen.
+    pub layout_in_safe_area: bool,
+    /// The screen size the ui is designed for, [`DEFAULT_REFERENCE_SIZE`] by default. Values in reference pixels,
+    /// like border softness, are scaled by the height of the board relative to it, set it with
+    /// [`Board::set_reference_size`], e.g. to 1080x1920 for a portrait game.
+    reference_size: DVec2,
+    /// increased whenever the batches render differently than before.
+    content_version: u64,
+    #[cfg(feature = "accessibility")]
+    pub(crate) last_access_nodes: crate::ui::accessibility::AccessNodes,
+}
+
+/// Area of a [`Board`] outside of which rects and glyphs are not batched, see [`get_batches_with_ctx`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum UiCulling {
+    /// `pos_offset` to `pos_offset + size` of the board, which is the visible area of screen space boards.
+    #[default]
+    BoardSize,
+    /// A custom area in layout space, e.g. the part of a large world space board that is in front of the camera.
+    Rect(Aabb),
+    /// Everything is batched, e.g. for boards whose content intentionally overflows its size.
+    None,
+}
+
+/// When a screen space [`Board`] is composited into the final image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UiCompositing {
+    /// Rendered on top of the tone mapped image: crisp and exactly in the specified colors.
+    /// Needs a [`crate::renderer::ui_screen::UiScreenRenderer`] created with the ldr surface format.
+    #[default]
+    PostToneMapping,
+    /// Rendered into the hdr scene before bloom and tone mapping, e.g. for glowing, hologram like ui.
+    /// Colors with values > 1.0 produce bloom. Needs a [`crate::renderer::ui_screen::UiScreenRenderer`]
+    /// created with the [`crate::RenderFormat`] of the hdr scene.
+    ///
+    /// Note: divs with `backdrop_blur` are rendered as normal rects in this mode.
+    PreToneMapping,
+}
+
+/// Resizes like [`Board::resize_scaled_to_fixed_height`], which is what screen space ui designed
+/// for its [`Board::reference_size`] needs.
+impl Resizable for Board {
+    fn on_resize(&mut self, size: PhysicalSize<u32>, _device: &wgpu::Device) {
+        self.resize_scaled_to_fixed_height(size);
+    }
 }
 
 impl Board {
+    /// Layout units are pixels of the screen.
     pub fn resize(&mut self, size: PhysicalSize<u32>) {
         self.size = dvec2(size.width as f64, size.height as f64);
+        self.update_reference_scale();
     }
 
     /// resizes, to get the right proportion from `size`, but will always keep the same fixed height.
@@ -376,18 +939,168 @@ impl Board {
     /// set to 1920px because this reflects the same 16:9 screen ratio
     pub fn resize_scaled_to_fixed_height(&mut self, size: PhysicalSize<u32>) {
         self.size.x = size.width as f64 / size.height as f64 * self.size.y;
+        self.update_reference_scale();
     }
 
     pub fn resize_dvec2(&mut self, size: DVec2) {
         self.size = size;
     }
 
+    /// Resizes to the layout size of the transform, e.g. [`crate::Screen::ui_transform`].
+    pub fn resize_to_ui_transform(&mut self, transform: &UiTransform) {
+        self.size = transform.layout_size;
+        self.safe_area_insets = transform.safe_area;
+        self.update_reference_scale();
+    }
+
+    pub fn reference_size(&self) -> DVec2 {
+        self.reference_size
+    }
+
+    /// Sets the design resolution of this board, see [`Board::reference_size`]. Use the same size
+    /// for the [`Screen`] with [`Screen::set_reference_size`], such that the layout has the height of the reference.
+    pub fn set_reference_size(&mut self, reference_size: DVec2) {
+        self.reference_size = reference_size;
+        self.update_reference_scale();
+    }
+
+    /// For screen space boards the height of the layout corresponds to the height of the reference screen,
+    /// no matter how the layout is scaled onto the screen.
+    fn update_reference_scale(&mut self) {
+        if self.size.y > 0.0 && self.reference_size.y > 0.0 {
+            self.ctx
+                .set_reference_scale(self.size.y / self.reference_size.y);
+        }
+    }
+
+    /// The part of the board that is not covered by notches or rounded corners of the screen, in layout space.
+    pub fn safe_area(&self) -> Aabb {
+        let (min, size) = self.safe_area_min_and_size();
+        Aabb::new(min.as_vec2(), (min + size).as_vec2())
+    }
+
+    fn safe_area_min_and_size(&self) -> (DVec2, DVec2) {
+        let insets = &self.safe_area_insets;
+        let min = self.pos_offset + dvec2(insets.left, insets.top);
+        let size = self.size - dvec2(insets.left + insets.right, insets.top + insets.bottom);
+        (min, size.max(DVec2::ZERO))
+    }
+
+    /// Lays the element out in the board, or in its safe area with `layout_in_safe_area`.
+    fn layout_element(&mut self) {
+        self.ctx.set_safe_area(Some(self.safe_area()));
+        let (pos, size) = if self.layout_in_safe_area {
+            self.safe_area_min_and_size()
+        } else {
+            (self.pos_offset, self.size)
+        };
+        self.element.layout_in_size(size, pos, &mut self.ctx);
+    }
+
+    /// The cull rect for batching, in layout space. None if culling is disabled.
+    pub fn cull_rect(&self) -> Option<Aabb> {
+        match self.culling {
+            UiCulling::BoardSize => {
+                let min = self.pos_offset.as_vec2();
+                Some(Aabb::new(min, min + self.size.as_vec2()))
+            }
+            UiCulling::Rect(rect) => Some(rect),
+            UiCulling::None => None,
+        }
+    }
+
+    /// The whole per-frame flow of a screen space board in the right order: resizes to the
+    /// [`Screen::ui_transform`], starts the frame of the [`ElementContext`] with the cursor and mouse
+    /// buttons of the `input` and the frame delta of the `time`, scrolls the hovered scroll area with the mouse wheel, builds the elements and lays
+    /// them out with [`Board::set_element`].
+    ///
+    /// ```rust,ignore
+    /// board.update(&input, &time, &screen, |ctx| {
+    ///     let color = if ctx.state_of(BUTTON_ID).hovered { Color::RED } else { Color::WHITE };
+    ///     div().child_with_id(BUTTON_ID, button(color)).store()
+    /// });
+    /// ```
+    ///
+    /// The batches still need to be uploaded, [`BoardGR::update`] does that as well.
+    pub fn update(
+        &mut self,
+        input: &Input,
+        time: &Time,
+        screen: &Screen,
+        build: impl FnOnce(&mut ElementContext) -> ElementBox,
+    ) {
+        let transform = screen.ui_transform();
+        self.resize_to_ui_transform(&transform);
+        self.ctx.start_frame_with_transform(
+            input.cursor_pos().as_dvec2(),
+            input.mouse_buttons(),
+            time.delta().as_secs_f32(),
+            &transform,
+        );
+        if let Some(lines) = input.scroll() {
+            self.ctx.scroll_hovered(lines);
+        }
+        self.build(build);
+    }
+
+    /// Like [`Board::update`] for a board shown in the `viewport` instead of on the whole screen. Render it with
+    /// [`crate::renderer::ui_screen::UiScreenRenderer::render_batches_in_viewport`] and the same viewport.
+    pub fn update_in_viewport(
+        &mut self,
+        input: &Input,
+        time: &Time,
+        screen: &Screen,
+        viewport: &UiViewport,
+        build: impl FnOnce(&mut ElementContext) -> ElementBox,
+    ) {
+        let screen_transform = screen.ui_transform();
+        self.resize_to_ui_transform(&viewport.ui_transform(&screen_transform));
+        self.ctx.start_frame_in_viewport(
+            input.cursor_pos().as_dvec2(),
+            input.mouse_buttons(),
+            time.delta().as_secs_f32(),
+            &screen_transform,
+            viewport,
+        );
+        if let Some(lines) = input.scroll() {
+            self.ctx.scroll_hovered(lines);
+        }
+        self.build(build);
+    }
+
+    /// Builds the elements and lays them out with [`Board::set_element`]. Subtrees built with
+    /// [`ElementContext::retained`] are moved over from the elements of the last frame if they did not change.
+    pub fn build(&mut self, build: impl FnOnce(&mut ElementContext) -> ElementBox) {
+        let previous = std::mem::replace(&mut self.element, div().store());
+        self.ctx.retained.begin(previous);
+        let element = build(&mut self.ctx);
+        self.ctx.retained.end();
+        self.set_element(element);
+    }
+
     pub fn set_element(&mut self, element: ElementBox) {
         self.element = element;
         self.ctx.clear_id_bounds();
-        self.element
-            .layout_in_size(self.size, self.pos_offset, &mut self.ctx);
-        self.batches = self.element.element.get_batches();
+        self.layout_element();
+        let cull_rect = self.cull_rect();
+        let batches = get_batches_with_ctx(&[&self.element], &mut self.ctx, cull_rect);
+        if batches.has_effects() || !batches.same_content(&self.batches) {
+            self.content_version += 1;
+        }
+        self.batches = batches;
+    }
+
+    /// Changes whenever the board looks different than before, e.g. because elements moved or a hover style
+    /// transitions. Boards with animated [`crate::ui::UiEffect`]s change every frame.
+    /// Used by [`crate::renderer::ui_cache::UiCache`] to only render the board when needed.
+    pub fn content_version(&self) -> u64 {
+        self.content_version
+    }
+
+    /// Makes the board count as changed, for changes that can't be seen in the batches,
+    /// like new contents of a texture that is shown in the ui.
+    pub fn mark_changed(&mut self) {
+        self.content_version += 1;
     }
 
     // pub fn render(&mut self, element: &mut impl IntoElement) {
@@ -397,18 +1110,97 @@ impl Board {
     //     self.batches = self.element.element.get_batches();
     // }
 
-    pub fn new(mut element: ElementBox, size: DVec2) -> Self {
-        let pos_offset = DVec2::ZERO;
-        let mut ctx = ElementContext::new();
-        element.layout_in_size(size, pos_offset, &mut ctx);
-        let batches = element.element.get_batches();
-        Board {
-            ctx,
+    pub fn new(element: ElementBox, size: DVec2) -> Self {
+        let mut board = Board {
+            ctx: ElementContext::new(),
             element,
-            batches,
+            batches: ElementBatches::default(),
             size,
-            pos_offset,
-        }
+            pos_offset: DVec2::ZERO,
+            compositing: UiCompositing::default(),
+            culling: UiCulling::default(),
+            safe_area_insets: Edges::all(0.0),
+            layout_in_safe_area: false,
+            reference_size: DEFAULT_REFERENCE_SIZE,
+            content_version: 0,
+            #[cfg(feature = "accessibility")]
+            last_access_nodes: Default::default(),
+        };
+        board.layout_element();
+        let cull_rect = board.cull_rect();
+        board.batches = get_batches_with_ctx(&[&board.element], &mut board.ctx, cull_rect);
+        board
+    }
+}
+
+/// A [`Board`] together with the gpu buffers of its batches, such that updating and uploading is one call:
+///
+/// ```rust,ignore
+/// let mut hud = BoardGR::new(Board::new(div().store(), DVec2::ZERO), &device);
+/// // every frame:
+/// hud.update(&input, &time, &screen, &device, &queue, |ctx| hud_element(ctx, &game).store());
+/// // in a render pass:
+/// ui_renderer.render_batches(&mut pass, &hud.gr, &hud.batches.batches, &uniforms, Color::WHITE);
+/// ```
+pub struct BoardGR {
+    pub board: Board,
+    pub gr: ElementBatchesGR,
+}
+
+impl BoardGR {
+    pub fn new(board: Board, device: &wgpu::Device) -> Self {
+        let gr = ElementBatchesGR::new(&board.batches, device);
+        BoardGR { board, gr }
+    }
+
+    /// [`Board::update`] followed by [`BoardGR::prepare`].
+    pub fn update(
+        &mut self,
+        input: &Input,
+        time: &Time,
+        screen: &Screen,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        build: impl FnOnce(&mut ElementContext) -> ElementBox,
+    ) {
+        self.board.update(input, time, screen, build);
+        self.prepare(device, queue);
+    }
+
+    /// [`Board::update_in_viewport`] followed by [`BoardGR::prepare`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_in_viewport(
+        &mut self,
+        input: &Input,
+        time: &Time,
+        screen: &Screen,
+        viewport: &UiViewport,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        build: impl FnOnce(&mut ElementContext) -> ElementBox,
+    ) {
+        self.board
+            .update_in_viewport(input, time, screen, viewport, build);
+        self.prepare(device, queue);
+    }
+
+    /// Uploads the batches, needed after changing the board without [`BoardGR::update`].
+    pub fn prepare(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.gr.prepare(&self.board.batches, device, queue);
+    }
+}
+
+impl Deref for BoardGR {
+    type Target = Board;
+
+    fn deref(&self) -> &Self::Target {
+        &self.board
+    }
+}
+
+impl DerefMut for BoardGR {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.board
     }
 }
 
@@ -95,39 +95,38 @@ pub struct StoredElement {
     pub id: ElementId,
 }
 
-#[repr(C)]
 #[derive(Debug)]
 pub enum ElementWithComputed {
-    Div((Div, DivComputed)),
-    Text((Text, TextComputed)),
+    Div(Box<(Div, DivComputed)>),
+    Text(Box<(Text, TextComputed)>),
 }
 
 impl ElementWithComputed {
     pub fn from_element(element: Element) -> Self {
         match element {
-            Element::Div(div) => ElementWithComputed::Div((div, Default::default())),
-            Element::Text(text) => ElementWithComputed::Text((text, Default::default())),
+            Element::Div(div) => ElementWithComputed::Div(Box::new((div, Default::default()))),
+            Element::Text(text) => ElementWithComputed::Text(Box::new((text, Default::default()))),
         }
     }
 
     pub fn computed_bounds_mut(&mut self) -> &mut ComputedBounds {
         match self {
-            ElementWithComputed::Div((_, c)) => &mut c.bounds,
-            ElementWithComputed::Text((_, c)) => &mut c.bounds,
+            ElementWithComputed::Div(d) => &mut d.1.bounds,
+            ElementWithComputed::Text(t) => &mut t.1.bounds,
         }
     }
 
     #[inline(always)]
     pub fn computed_size(&self) -> DVec2 {
         match self {
-            ElementWithComputed::Div((_, c)) => c.bounds.size,
-            ElementWithComputed::Text((_, c)) => c.bounds.size,
+            ElementWithComputed::Div(d) => d.1.bounds.size,
+            ElementWithComputed::Text(t) => t.1.bounds.size,
         }
     }
 
     pub fn div(&mut self) -> Option<&mut (Div, DivComputed)> {
         match self {
-            ElementWithComputed::Div(e) => Some(e),
+            ElementWithComputed::Div(e) => Some(&mut **e),
             ElementWithComputed::Text(_) => None,
         }
     }
@@ -135,26 +134,11 @@ impl ElementWithComputed {
     pub fn text(&mut self) -> Option<&mut (Text, TextComputed)> {
         match self {
             ElementWithComputed::Div(_) => None,
-            ElementWithComputed::Text(e) => Some(e),
+            ElementWithComputed::Text(e) => Some(&mut **e),
         }
     }
 }
 
-/// works because memory layout of Element and ElementWithComputed is the same at the start.
-impl Deref for ElementWithComputed {
-    type Target = Element;
-
-    fn deref(&self) -> &Self::Target {
-        unsafe { &*(self as *const ElementWithComputed as *const Element) }
-    }
-}
-
-impl DerefMut for ElementWithComputed {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        unsafe { &mut *(self as *mut ElementWithComputed as *mut Element) }
-    }
-}
-
 impl IntoElementBox for ElementWithComputed {
     fn store(self) -> ElementBox {
         ElementBox::new(StoredElement {
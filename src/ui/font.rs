@@ -1,32 +1,85 @@
-use std::fmt::Debug;
-
-use ahash::AHashMap;
+use std::{
+    fmt::Debug,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        OnceLock, RwLock,
+    },
+};
+
+use ahash::{AHashMap, AHashSet};
+use anyhow::anyhow;
 
 use crate::{utils::next_pow2_number, Aabb, BindableTexture, Texture};
 use etagere::Size;
 use fontdue::LineMetrics;
 use glam::vec2;
 use image::GenericImage;
+use log::warn;
 use sdfer::{Image2d, Unorm8};
+use serde::{Deserialize, Serialize};
 use wgpu::Extent3d;
 
 pub type SdfFontRef = &'static SdfFont;
 
-/// An SdfFont is meant to be created once with all the characters that you need.
-/// A
+/// An SdfFont is usually created once with the characters that you need. Characters that are missing are
+/// rasterized when text with them is laid out, see [`SdfFont::glyph_info`]. Their sdfs are uploaded in
+/// [`SdfFont::write_atlas_to_texture`], which the renderers call for the fonts they draw.
+///
+/// Glyphs are packed into atlas pages of the same size, when a page is full a new one is started.
+/// Each page has its own texture, text with glyphs on several pages is split into one batch per page.
 pub struct SdfFont {
     font: fontdue::Font,
     /// fontsize the sdf is rasterized at. 32 or 64 is recommended.
     font_size: u32,
     /// How far out the pad_size should extend in each of the 4 directions. A value of font_size / 8 is recommended.
     pad_size: u32,
+    /// behind a lock, such that fonts shared as [`SdfFontRef`] can add glyphs during layout.
+    atlas: RwLock<FontAtlas>,
+    /// set when glyphs were added to the atlas since the last upload.
+    dirty: AtomicBool,
+    /// one texture per page, [`SdfFont::MAX_PAGES`] slots, such that textures can be added through `&self`.
+    /// Pages without a texture yet get one in [`SdfFont::write_atlas_to_texture`].
+    page_textures: Box<[OnceLock<BindableTexture>]>,
+    hinting: TextHinting,
+}
+
+/// The glyphs of an [`SdfFont`] and the atlas pages they are in.
+struct FontAtlas {
     glyphs: AHashMap<char, GlyphInfo>,
     /// a subset of glyphs
     sdf_glyphs: AHashMap<char, SdfGlyph>,
-    atlas_allocator: etagere::AtlasAllocator,
-    atlas_image: image::GrayImage,
-    _atlas_dbg: image::RgbaImage,
-    atlas_texture: BindableTexture,
+    /// at least one page.
+    pages: Vec<SdfAtlas>,
+}
+
+/// How glyphs are placed and sharpened, mostly to make small ui text (< 14px) more readable.
+/// Set for all text of a font with [`SdfFont::set_hinting`] or per [`crate::ui::TextSection`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextHinting {
+    /// Moves each glyph to a whole pixel on the screen, so glyphs are not blurred differently depending on their position.
+    /// Done in the glyph vertex shader of screen space ui, has no effect for ui in 3d space.
+    pub snap_to_pixels: bool,
+    /// Steepness of the edge of the glyphs, values above 1.0 make them sharper.
+    pub sharpness: f32,
+}
+
+impl TextHinting {
+    pub const NONE: TextHinting = TextHinting {
+        snap_to_pixels: false,
+        sharpness: 1.0,
+    };
+
+    pub const SMALL_TEXT: TextHinting = TextHinting {
+        snap_to_pixels: true,
+        sharpness: 1.5,
+    };
+}
+
+impl Default for TextHinting {
+    fn default() -> Self {
+        TextHinting::NONE
+    }
 }
 
 impl Debug for SdfFont {
@@ -118,31 +171,60 @@ fn create_sdf_atlas_texture(width: u32, height: u32, device: &wgpu::Device) -> B
 }
 
 impl SdfFont {
+    /// Glyphs that do not fit into this many atlas pages are not drawn.
+    pub const MAX_PAGES: usize = 64;
+
     pub fn new(font: fontdue::Font, font_size: u32, pad_size: u32, device: &wgpu::Device) -> Self {
         let atlas_size = next_pow2_number((font_size + 2 * pad_size) as usize * 16); // this gives us space for at least 256 glyphs, which should be enough in most cases
-        let atlas_allocator =
-            etagere::AtlasAllocator::new(Size::new(atlas_size as i32, atlas_size as i32));
-        let atlas_image = image::GrayImage::new(atlas_size as u32, atlas_size as u32);
-        let atlas_texture = create_sdf_atlas_texture(atlas_size as u32, atlas_size as u32, device);
+        let atlas = SdfAtlas::new(atlas_size as u32, atlas_size as u32);
+        Self::with_atlas(font, font_size, pad_size, AHashMap::new(), atlas, device)
+    }
 
+    fn with_atlas(
+        font: fontdue::Font,
+        font_size: u32,
+        pad_size: u32,
+        glyphs: AHashMap<char, GlyphInfo>,
+        first_page: SdfAtlas,
+        device: &wgpu::Device,
+    ) -> Self {
+        let page_textures: Box<[OnceLock<BindableTexture>]> =
+            (0..Self::MAX_PAGES).map(|_| OnceLock::new()).collect();
+        let (width, height) = first_page.image.dimensions();
+        _ = page_textures[0].set(create_sdf_atlas_texture(width, height, device));
+        let dirty = first_page.dirty.is_some();
         SdfFont {
             font,
             font_size,
-            glyphs: AHashMap::new(),
-            sdf_glyphs: AHashMap::new(),
-            atlas_allocator,
-            atlas_image,
-            atlas_texture,
-            _atlas_dbg: image::RgbaImage::new(atlas_size as u32, atlas_size as u32),
             pad_size,
+            atlas: RwLock::new(FontAtlas {
+                glyphs,
+                sdf_glyphs: AHashMap::new(),
+                pages: vec![first_page],
+            }),
+            dirty: AtomicBool::new(dirty),
+            page_textures,
+            hinting: TextHinting::NONE,
         }
     }
 
+    /// Panics if the `data` is not a valid ttf or otf font, see [`SdfFont::try_from_bytes`].
     pub fn from_bytes(data: &[u8], device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
-        let font =
-            fontdue::Font::from_bytes(data, Default::default()).expect("data must be valid ttf");
-        let sdf_font = Self::new_with_default_chars(font, 64, 16, device, queue);
-        sdf_font
+        match Self::try_from_bytes(data, device, queue) {
+            Ok(sdf_font) => sdf_font,
+            Err(err) => panic!("{err}"),
+        }
+    }
+
+    /// Rasterizes the ascii characters of the font `data` at a font size of 64px.
+    pub fn try_from_bytes(
+        data: &[u8],
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> crate::Result<Self> {
+        let font = fontdue::Font::from_bytes(data, Default::default())
+            .map_err(crate::Error::InvalidFont)?;
+        Ok(Self::new_with_default_chars(font, 64, 16, device, queue))
     }
 
     pub fn new_with_default_chars(
@@ -152,85 +234,224 @@ impl SdfFont {
         device: &wgpu::Device,
         queue: &wgpu::Queue,
     ) -> Self {
-        let mut sdf_font = Self::new(font, fontsize, pad_size, device);
+        let sdf_font = Self::new(font, fontsize, pad_size, device);
 
-        // rasterize all the letters in the given alphabet, currently do not support any other letters:
+        // rasterize the letters of the alphabet up front, others are added when they are first laid out:
         const ALPHABET: &str =
           "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789.,!:;/?|(){}[]!+-_=* \n\t'\"><~`";
         for ch in ALPHABET.chars() {
             sdf_font.add_char(ch);
         }
-        sdf_font.write_atlas_to_texture(queue);
+        sdf_font.write_atlas_to_texture(device, queue);
         // sdf_font.atlas_image.save("atlas.png");
         sdf_font
     }
 
+    /// Loads a font baked with [`bake_sdf_font`] or [`SdfFont::save_baked`], without rasterizing any glyphs.
+    /// The `font` is still needed for line metrics and for glyphs added later with [`SdfFont::add_char`].
+    ///
+    /// `atlas_png` and `glyphs_json` are the contents of the two baked files, so they can also be embedded with `include_bytes!`.
+    pub fn from_baked(
+        font: fontdue::Font,
+        atlas_png: &[u8],
+        glyphs_json: &str,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> anyhow::Result<Self> {
+        let baked: BakedSdfFont = serde_json::from_str(glyphs_json)?;
+        let atlas_image =
+            image::load_from_memory_with_format(atlas_png, image::ImageFormat::Png)?.to_luma8();
+        let [width, height] = baked.atlas_size;
+        if atlas_image.dimensions() != (width, height) {
+            return Err(anyhow!(
+                "baked atlas image has size {:?}, but the glyphs were baked for {width}x{height}",
+                atlas_image.dimensions()
+            ));
+        }
+
+        let mut atlas = SdfAtlas::new(width, height);
+        atlas.image = atlas_image;
+        atlas.dirty = Some([0, 0, width, height]);
+        let mut glyphs = AHashMap::new();
+        let mut allocations_match = true;
+        for glyph in baked.glyphs {
+            let uv = match glyph.rect {
+                Some(rect) => {
+                    // replaying the allocations in the same order gives the same rects, so glyphs can be added later.
+                    let allocation = atlas
+                        .allocator
+                        .allocate(Size::new(rect[2] as i32, rect[3] as i32));
+                    allocations_match &= allocation.is_some_and(|a| {
+                        a.rectangle.min.x as u32 == rect[0] && a.rectangle.min.y as u32 == rect[1]
+                    });
+                    Some(atlas.uv(rect))
+                }
+                None => None,
+            };
+            glyphs.insert(
+                glyph.ch,
+                GlyphInfo {
+                    metrics: glyph.metrics,
+                    uv,
+                    page: 0,
+                },
+            );
+            atlas.baked.push(glyph);
+        }
+        if !allocations_match {
+            warn!("Baked sdf font atlas could not be restored exactly, characters added later may overlap baked ones");
+        }
+
+        let sdf_font =
+            Self::with_atlas(font, baked.font_size, baked.pad_size, glyphs, atlas, device);
+        sdf_font.write_atlas_to_texture(device, queue);
+        Ok(sdf_font)
+    }
+
+    /// Like [`SdfFont::from_baked`], but reads the atlas png and glyphs json from files.
+    pub fn load_baked(
+        font: fontdue::Font,
+        atlas_png_path: impl AsRef<Path>,
+        glyphs_json_path: impl AsRef<Path>,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> anyhow::Result<Self> {
+        let atlas_png = std::fs::read(atlas_png_path)?;
+        let glyphs_json = std::fs::read_to_string(glyphs_json_path)?;
+        Self::from_baked(font, &atlas_png, &glyphs_json, device, queue)
+    }
+
+    /// Writes the atlas as a png and the glyph metrics as json, to be loaded with [`SdfFont::load_baked`].
+    ///
+    /// Fails for fonts with more than one atlas page, use [`bake_sdf_font`] for large charsets,
+    /// which grows a single atlas until all glyphs fit.
+    pub fn save_baked(
+        &self,
+        atlas_png_path: impl AsRef<Path>,
+        glyphs_json_path: impl AsRef<Path>,
+    ) -> anyhow::Result<()> {
+        let atlas = self.atlas.read().unwrap();
+        if atlas.pages.len() > 1 {
+            return Err(anyhow!(
+                "the font has {} atlas pages, only fonts with a single page can be baked",
+                atlas.pages.len()
+            ));
+        }
+        atlas.pages[0].save(
+            self.font_size,
+            self.pad_size,
+            atlas_png_path,
+            glyphs_json_path,
+        )
+    }
+
+    /// Hinting for all text sections with this font that do not set their own.
+    pub fn set_hinting(&mut self, hinting: TextHinting) {
+        self.hinting = hinting;
+    }
+
+    pub fn hinting(&self) -> TextHinting {
+        self.hinting
+    }
+
+    /// The texture of the first atlas page.
     pub fn atlas_texture(&self) -> &BindableTexture {
-        &self.atlas_texture
-    }
-
-    /// Copies the atlas image that contains all glyphs to the gpu.
-    /// Should be called, after all characters that you might want have been added to the font
-    pub fn write_atlas_to_texture(&self, queue: &wgpu::Queue) {
-        queue.write_texture(
-            wgpu::ImageCopyTexture {
-                aspect: wgpu::TextureAspect::All,
-                texture: &self.atlas_texture.texture.texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d { x: 0, y: 0, z: 0 },
-            },
-            &self.atlas_image,
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(self.atlas_image.width()),
-                rows_per_image: None,
-            },
-            self.atlas_texture.texture.size,
-        );
+        self.page_texture(0)
+    }
+
+    /// The texture of the atlas page with the index [`GlyphInfo::page`]. Pages that were added since the last
+    /// [`SdfFont::write_atlas_to_texture`] have no texture yet, the first page is returned for them.
+    pub fn page_texture(&self, page: u32) -> &BindableTexture {
+        match self.page_textures.get(page as usize).and_then(|t| t.get()) {
+            Some(texture) => texture,
+            None => self.page_textures[0]
+                .get()
+                .expect("first page is created in new"),
+        }
+    }
+
+    pub fn n_pages(&self) -> usize {
+        self.atlas.read().unwrap().pages.len()
+    }
+
+    /// True if glyphs were added since the last [`SdfFont::write_atlas_to_texture`].
+    pub fn needs_upload(&self) -> bool {
+        self.dirty.load(Ordering::Acquire)
+    }
+
+    /// Copies the parts of the atlas images with glyphs added since the last call to the gpu, creating textures
+    /// for new pages. Cheap if nothing changed, the ui and world text renderers call it for the fonts they draw
+    /// before rendering.
+    pub fn write_atlas_to_texture(&self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        if !self.dirty.swap(false, Ordering::AcqRel) {
+            return;
+        }
+        let mut atlas = self.atlas.write().unwrap();
+        for (page, slot) in atlas.pages.iter_mut().zip(self.page_textures.iter()) {
+            let (width, height) = page.image.dimensions();
+            let texture = slot.get_or_init(|| create_sdf_atlas_texture(width, height, device));
+            let Some([x, y, w, h]) = page.dirty.take() else {
+                continue;
+            };
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    aspect: wgpu::TextureAspect::All,
+                    texture: &texture.texture.texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d { x, y, z: 0 },
+                },
+                &page.image,
+                wgpu::ImageDataLayout {
+                    offset: (y * width + x) as u64,
+                    bytes_per_row: Some(width),
+                    rows_per_image: None,
+                },
+                Extent3d {
+                    width: w,
+                    height: h,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
     }
 
     /// Adds a char to this sdf font. If it is not whitespace it is rasterized and an sdf image is computed.
-    pub fn add_char(&mut self, ch: char) {
-        if ch.is_whitespace() {
+    /// Chars that were added before are skipped. The glyph is visible after the next
+    /// [`SdfFont::write_atlas_to_texture`].
+    pub fn add_char(&self, ch: char) {
+        let mut atlas = self.atlas.write().unwrap();
+        // another thread might have added it since the caller checked.
+        if atlas.glyphs.contains_key(&ch) {
+            return;
+        }
+        let glyph = if ch.is_whitespace() {
             let metrics = self.font.metrics(ch, self.font_size as f32);
             let metrics = Metrics::from(metrics);
-            let glyph = GlyphInfo { metrics, uv: None };
-            self.glyphs.insert(ch, glyph);
+            atlas.pages[0].add_whitespace(ch, metrics);
+            GlyphInfo {
+                metrics,
+                uv: None,
+                page: 0,
+            }
         } else {
             let sdf_glyph = SdfGlyph::new(ch, &self.font, self.font_size, self.pad_size);
-
-            let (w, h) = sdf_glyph.sdf.dimensions();
-            let allocation = self
-                .atlas_allocator
-                .allocate(Size::new(w as i32, h as i32))
-                .expect("allocation failed");
-            let atlas_size = self.atlas_allocator.size();
-            let atlas_size = vec2(atlas_size.width as f32, atlas_size.height as f32);
-            let uv_min_pos = vec2(
-                allocation.rectangle.min.x as f32,
-                allocation.rectangle.min.y as f32,
-            );
-            let uv_max_pos = uv_min_pos + vec2(w as f32, h as f32);
-            // warning: the allocation.rectangle might be larger than the (w,h) of the sdf image.
-            // so we can only use the top left corner reliably, and need to add the width and height on top ourselves.
-            let uv = Aabb::new(uv_min_pos / atlas_size, uv_max_pos / atlas_size);
-
-            // write the sdf into the big texture image
-            self.atlas_image
-                .copy_from(
-                    &sdf_glyph.sdf,
-                    allocation.rectangle.min.x as u32,
-                    allocation.rectangle.min.y as u32,
-                )
-                .expect("copy from sdf_glyph image to atlas_image failed");
-
+            let placed = add_to_pages(&mut atlas.pages, Self::MAX_PAGES, ch, &sdf_glyph);
+            if placed.is_none() {
+                warn!(
+                    "All {} atlas pages of the font are full, {ch:?} is not drawn",
+                    Self::MAX_PAGES
+                );
+            }
             let glyph = GlyphInfo {
                 metrics: sdf_glyph.metrics_with_pad,
-                uv: Some(uv),
+                uv: placed.map(|(uv, _)| uv),
+                page: placed.map_or(0, |(_, page)| page),
             };
-            self.sdf_glyphs.insert(ch, sdf_glyph);
-            self.glyphs.insert(ch, glyph);
-        }
+            atlas.sdf_glyphs.insert(ch, sdf_glyph);
+            self.dirty.store(true, Ordering::Release);
+            glyph
+        };
+        atlas.glyphs.insert(ch, glyph);
     }
 
     pub fn line_metrics(&self, font_size_px: f32) -> LineMetrics {
@@ -239,20 +460,27 @@ impl SdfFont {
             .expect("Line Metrics need to be found")
     }
 
+    /// The glyph of `ch` scaled to `font_size_px`. Characters that are not in the font yet are rasterized now,
+    /// characters the font does not have get the glyph for missing characters of the font.
     pub fn glyph_info(&self, ch: char, font_size_px: f32) -> GlyphInfo {
-        if let Some(glyph) = self.glyphs.get(&ch) {
-            let scale = font_size_px / self.font_size as f32;
-            GlyphInfo {
-                metrics: glyph.metrics.scale(scale),
-                uv: glyph.uv,
+        let glyph = self.atlas.read().unwrap().glyphs.get(&ch).copied();
+        let glyph = match glyph {
+            Some(glyph) => glyph,
+            None => {
+                self.add_char(ch);
+                self.atlas.read().unwrap().glyphs[&ch]
             }
-        } else {
-            panic!("the character {ch} is not rasterized yet");
+        };
+        let scale = font_size_px / self.font_size as f32;
+        GlyphInfo {
+            metrics: glyph.metrics.scale(scale),
+            uv: glyph.uv,
+            page: glyph.page,
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Metrics {
     pub xmin: f32,
     pub ymin: f32,
@@ -293,6 +521,187 @@ pub struct GlyphInfo {
     pub metrics: Metrics,
     /// None if whitespace
     pub uv: Option<Aabb>,
+    /// index of the atlas page the uv refers to, see [`SdfFont::page_texture`].
+    pub page: u32,
+}
+
+/// Rasterizes all chars of `charset` and writes the sdf atlas as a png and the glyph metrics as json,
+/// e.g. in a build step, such that shipped builds can load the font instantly with [`SdfFont::load_baked`].
+///
+/// The atlas grows until all glyphs fit into it.
+pub fn bake_sdf_font(
+    font: &fontdue::Font,
+    charset: &str,
+    font_size: u32,
+    pad_size: u32,
+    atlas_png_path: impl AsRef<Path>,
+    glyphs_json_path: impl AsRef<Path>,
+) -> anyhow::Result<()> {
+    let mut seen = AHashSet::new();
+    let chars: Vec<char> = charset.chars().filter(|ch| seen.insert(*ch)).collect();
+    let sdf_glyphs: Vec<Option<SdfGlyph>> = chars
+        .iter()
+        .map(|ch| (!ch.is_whitespace()).then(|| SdfGlyph::new(*ch, font, font_size, pad_size)))
+        .collect();
+
+    let mut atlas_size = next_pow2_number((font_size + 2 * pad_size) as usize * 16) as u32;
+    'grow: loop {
+        let mut atlas = SdfAtlas::new(atlas_size, atlas_size);
+        for (ch, sdf_glyph) in chars.iter().zip(sdf_glyphs.iter()) {
+            match sdf_glyph {
+                Some(sdf_glyph) => {
+                    if atlas.add(*ch, sdf_glyph).is_none() {
+                        atlas_size *= 2;
+                        continue 'grow;
+                    }
+                }
+                None => {
+                    let metrics = Metrics::from(font.metrics(*ch, font_size as f32));
+                    atlas.add_whitespace(*ch, metrics);
+                }
+            }
+        }
+        return atlas.save(font_size, pad_size, atlas_png_path, glyphs_json_path);
+    }
+}
+
+/// Adds the glyph to the last page, or to a new page of the same size if the last one is full. The new page is
+/// larger if the glyph does not fit into an empty page, e.g. after a small baked atlas.
+/// Returns the uv and the index of the page, None if all `max_pages` are full.
+fn add_to_pages(
+    pages: &mut Vec<SdfAtlas>,
+    max_pages: usize,
+    ch: char,
+    sdf_glyph: &SdfGlyph,
+) -> Option<(Aabb, u32)> {
+    let n_pages = pages.len();
+    let last = pages.last_mut().expect("there is at least one page");
+    if let Some(uv) = last.add(ch, sdf_glyph) {
+        return Some((uv, n_pages as u32 - 1));
+    }
+    if n_pages >= max_pages {
+        return None;
+    }
+    let (width, height) = last.image.dimensions();
+    let (glyph_width, glyph_height) = sdf_glyph.sdf.dimensions();
+    let width = width.max(next_pow2_number(glyph_width as usize) as u32);
+    let height = height.max(next_pow2_number(glyph_height as usize) as u32);
+    let mut page = SdfAtlas::new(width, height);
+    let uv = page
+        .add(ch, sdf_glyph)
+        .expect("an empty page fits the glyph");
+    pages.push(page);
+    Some((uv, pages.len() as u32 - 1))
+}
+
+/// The cpu side of the glyph atlas of an [`SdfFont`].
+struct SdfAtlas {
+    allocator: etagere::AtlasAllocator,
+    image: image::GrayImage,
+    /// all glyphs in the order they were added, for saving the atlas.
+    baked: Vec<BakedGlyph>,
+    /// x, y, width and height in px of the part of the image that changed since the last upload.
+    dirty: Option<[u32; 4]>,
+}
+
+impl SdfAtlas {
+    fn new(width: u32, height: u32) -> Self {
+        SdfAtlas {
+            allocator: etagere::AtlasAllocator::new(Size::new(width as i32, height as i32)),
+            image: image::GrayImage::new(width, height),
+            baked: vec![],
+            dirty: None,
+        }
+    }
+
+    /// uv coordinates of a rect in px (x, y, width, height).
+    fn uv(&self, rect: [u32; 4]) -> Aabb {
+        let atlas_size = vec2(self.image.width() as f32, self.image.height() as f32);
+        let uv_min_pos = vec2(rect[0] as f32, rect[1] as f32);
+        let uv_max_pos = uv_min_pos + vec2(rect[2] as f32, rect[3] as f32);
+        Aabb::new(uv_min_pos / atlas_size, uv_max_pos / atlas_size)
+    }
+
+    /// Copies the sdf of the glyph into the atlas, returns None if there is no space left.
+    fn add(&mut self, ch: char, sdf_glyph: &SdfGlyph) -> Option<Aabb> {
+        let (w, h) = sdf_glyph.sdf.dimensions();
+        let allocation = self.allocator.allocate(Size::new(w as i32, h as i32))?;
+        // warning: the allocation.rectangle might be larger than the (w,h) of the sdf image.
+        // so we can only use the top left corner reliably, and need to add the width and height on top ourselves.
+        let rect = [
+            allocation.rectangle.min.x as u32,
+            allocation.rectangle.min.y as u32,
+            w,
+            h,
+        ];
+
+        // write the sdf into the big texture image
+        self.image
+            .copy_from(&sdf_glyph.sdf, rect[0], rect[1])
+            .expect("copy from sdf_glyph image to atlas_image failed");
+
+        self.baked.push(BakedGlyph {
+            ch,
+            metrics: sdf_glyph.metrics_with_pad,
+            rect: Some(rect),
+        });
+        self.dirty = Some(match self.dirty {
+            Some(dirty) => {
+                let min = [dirty[0].min(rect[0]), dirty[1].min(rect[1])];
+                let max = [
+                    (dirty[0] + dirty[2]).max(rect[0] + rect[2]),
+                    (dirty[1] + dirty[3]).max(rect[1] + rect[3]),
+                ];
+                [min[0], min[1], max[0] - min[0], max[1] - min[1]]
+            }
+            None => rect,
+        });
+        Some(self.uv(rect))
+    }
+
+    fn add_whitespace(&mut self, ch: char, metrics: Metrics) {
+        self.baked.push(BakedGlyph {
+            ch,
+            metrics,
+            rect: None,
+        });
+    }
+
+    fn save(
+        &self,
+        font_size: u32,
+        pad_size: u32,
+        atlas_png_path: impl AsRef<Path>,
+        glyphs_json_path: impl AsRef<Path>,
+    ) -> anyhow::Result<()> {
+        self.image
+            .save_with_format(atlas_png_path, image::ImageFormat::Png)?;
+        let baked = BakedSdfFont {
+            font_size,
+            pad_size,
+            atlas_size: [self.image.width(), self.image.height()],
+            glyphs: self.baked.clone(),
+        };
+        std::fs::write(glyphs_json_path, serde_json::to_string(&baked)?)?;
+        Ok(())
+    }
+}
+
+/// Contents of the glyphs json of a baked [`SdfFont`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BakedSdfFont {
+    font_size: u32,
+    pad_size: u32,
+    atlas_size: [u32; 2],
+    glyphs: Vec<BakedGlyph>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct BakedGlyph {
+    ch: char,
+    metrics: Metrics,
+    /// x, y, width and height in the atlas in px, None for whitespace.
+    rect: Option<[u32; 4]>,
 }
 
 struct SdfGlyph {
@@ -350,3 +759,75 @@ impl SdfGlyph {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{add_to_pages, bake_sdf_font, BakedSdfFont, SdfAtlas, SdfGlyph};
+
+    fn test_font() -> fontdue::Font {
+        fontdue::Font::from_bytes(
+            include_bytes!("../../assets/MarkoOne-Regular.ttf") as &[u8],
+            Default::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn bake_writes_atlas_and_glyphs() {
+        let font = test_font();
+        let dir = std::env::temp_dir().join(format!("tgf_bake_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (png, json) = (dir.join("atlas.png"), dir.join("glyphs.json"));
+        bake_sdf_font(&font, "abba c", 32, 4, &png, &json).unwrap();
+
+        let baked: BakedSdfFont =
+            serde_json::from_str(&std::fs::read_to_string(&json).unwrap()).unwrap();
+        let chars: String = baked.glyphs.iter().map(|g| g.ch).collect();
+        assert_eq!(chars, "ab c");
+        assert!(baked.glyphs[2].rect.is_none());
+        let atlas = image::open(&png).unwrap();
+        assert_eq!([atlas.width(), atlas.height()], baked.atlas_size);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn full_pages_start_new_pages() {
+        let font = test_font();
+        // room for about 4 glyphs per page:
+        let mut pages = vec![SdfAtlas::new(64, 64)];
+        let mut last_page = 0;
+        for ch in "abcdefghijklmnopqrstuvwxyz".chars() {
+            let (uv, page) =
+                add_to_pages(&mut pages, 64, ch, &SdfGlyph::new(ch, &font, 24, 3)).unwrap();
+            assert!(page == last_page || page == last_page + 1);
+            assert!(uv.min.cmpge(glam::Vec2::ZERO).all() && uv.max.cmple(glam::Vec2::ONE).all());
+            last_page = page;
+        }
+        assert!(pages.len() > 1);
+        assert_eq!(last_page as usize, pages.len() - 1);
+
+        // glyphs larger than a page get a larger page instead of panicking:
+        let mut pages = vec![SdfAtlas::new(16, 16)];
+        let (_, page) =
+            add_to_pages(&mut pages, 64, 'W', &SdfGlyph::new('W', &font, 48, 6)).unwrap();
+        assert_eq!(page, 1);
+        assert!(pages[1].image.width() > 16 && pages[1].image.height() > 16);
+    }
+
+    #[test]
+    fn added_glyphs_mark_their_region_for_upload() {
+        let font = test_font();
+        let mut atlas = SdfAtlas::new(256, 256);
+        assert_eq!(atlas.dirty, None);
+        let a = SdfGlyph::new('a', &font, 24, 3);
+        atlas.add('a', &a).unwrap();
+        let (w, h) = a.sdf.dimensions();
+        assert_eq!(atlas.dirty, Some([0, 0, w, h]));
+
+        // a second glyph grows the region, such that both are uploaded together.
+        atlas.add('b', &SdfGlyph::new('b', &font, 24, 3)).unwrap();
+        let [x, y, dirty_w, dirty_h] = atlas.dirty.unwrap();
+        assert_eq!([x, y], [0, 0]);
+        assert!(dirty_w > w || dirty_h > h);
+    }
+}
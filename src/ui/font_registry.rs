@@ -0,0 +1,204 @@
+use std::{cell::RefCell, path::Path, time::Duration};
+
+use ahash::AHashMap;
+use anyhow::anyhow;
+
+use crate::{
+    leak,
+    ui::{
+        element::UiString,
+        font::{SdfFont, SdfFontRef},
+        FontSlot, TextSection, UiEffect, VerticalAlign,
+    },
+    Color,
+};
+
+/// Refers to a font in the [`FontRegistry`]. Stays valid when the font behind it is replaced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FontHandle(u32);
+
+/// Fonts by name, with a default font for text created with [`text`].
+///
+/// Each font is kept in a [`FontSlot`] and text from [`text`] or [`TextSection::font`] refers to the slot, so all
+/// text, also text that is kept between frames, picks up a font swapped with [`FontRegistry::replace`] or
+/// [`FontRegistry::crossfade`] in its next layout, e.g. to switch to a larger print font for accessibility.
+///
+/// The handles and slots take the place of weak references to the fonts: they don't own a font and text using them
+/// follows replacements, without walking the ui. Fonts are not kept in `Rc`s, because text refers to them as
+/// [`SdfFontRef`] in its layout and batches.
+///
+/// Fonts are leaked to get a [`SdfFontRef`], also the replaced ones, because elements of the current frame
+/// or a [`SdfFontRef`] from [`FontRegistry::get`] might still point to them.
+#[derive(Debug, Default)]
+pub struct FontRegistry {
+    fonts: Vec<&'static FontSlot>,
+    names: AHashMap<String, FontHandle>,
+    default: Option<FontHandle>,
+}
+
+impl FontRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a font under the name. If a font with this name exists already, it is replaced and keeps its handle.
+    /// The first font added becomes the default font.
+    pub fn insert(&mut self, name: impl Into<String>, font: SdfFont) -> FontHandle {
+        let name: String = name.into();
+        if let Some(handle) = self.names.get(&name).copied() {
+            self.replace(handle, font);
+            return handle;
+        }
+        let handle = FontHandle(self.fonts.len() as u32);
+        self.fonts.push(FontSlot::new(leak(font)));
+        self.names.insert(name, handle);
+        if self.default.is_none() {
+            self.default = Some(handle);
+        }
+        handle
+    }
+
+    /// Loads a ttf or otf file and adds it with the default characters, see [`SdfFont::new_with_default_chars`].
+    pub fn load(
+        &mut self,
+        name: impl Into<String>,
+        path: impl AsRef<Path>,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> anyhow::Result<FontHandle> {
+        let bytes = std::fs::read(path.as_ref())?;
+        let font = fontdue::Font::from_bytes(bytes, Default::default())
+            .map_err(|err| anyhow!("invalid font {:?}: {err}", path.as_ref()))?;
+        let sdf_font = SdfFont::new_with_default_chars(font, 64, 16, device, queue);
+        Ok(self.insert(name, sdf_font))
+    }
+
+    /// Swaps the font behind the handle, all text using it is laid out with the new font from now on.
+    pub fn replace(&mut self, handle: FontHandle, font: SdfFont) {
+        self.slot(handle).swap(leak(font));
+    }
+
+    /// Like [`FontRegistry::replace`], but fades the text out and in with the new font, see [`FontSlot::crossfade`].
+    /// Needs [`FontRegistry::update`] every frame.
+    pub fn crossfade(&mut self, handle: FontHandle, font: SdfFont, duration: Duration) {
+        self.slot(handle).crossfade(leak(font), duration);
+    }
+
+    /// Advances the crossfades of all fonts by `dt` seconds. Call it once per frame, before building the ui.
+    pub fn update(&mut self, dt: f32) {
+        for slot in self.fonts.iter() {
+            slot.update(dt);
+        }
+    }
+
+    pub fn handle(&self, name: &str) -> Option<FontHandle> {
+        self.names.get(name).copied()
+    }
+
+    /// The current font behind the handle. Text built with it keeps this font when it is replaced,
+    /// use the [`FontRegistry::slot`] to follow replacements.
+    pub fn get(&self, handle: FontHandle) -> SdfFontRef {
+        self.slot(handle).font()
+    }
+
+    pub fn slot(&self, handle: FontHandle) -> &'static FontSlot {
+        self.fonts[handle.0 as usize]
+    }
+
+    pub fn by_name(&self, name: &str) -> Option<SdfFontRef> {
+        self.handle(name).map(|h| self.get(h))
+    }
+
+    pub fn set_default(&mut self, handle: FontHandle) {
+        self.default = Some(handle);
+    }
+
+    pub fn default_handle(&self) -> Option<FontHandle> {
+        self.default
+    }
+
+    /// None if no font was added yet.
+    pub fn default_font(&self) -> Option<SdfFontRef> {
+        self.default.map(|h| self.get(h))
+    }
+
+    pub fn default_slot(&self) -> Option<&'static FontSlot> {
+        self.default.map(|h| self.slot(h))
+    }
+}
+
+thread_local! {
+    static FONTS: RefCell<FontRegistry> = RefCell::new(FontRegistry::new());
+}
+
+/// Access to the global [`FontRegistry`] of this thread, which is used by [`text`].
+pub fn with_fonts<R>(f: impl FnOnce(&mut FontRegistry) -> R) -> R {
+    FONTS.with(|fonts| f(&mut fonts.borrow_mut()))
+}
+
+/// The default font of the global [`FontRegistry`].
+///
+/// Panics if no font was added to it yet.
+pub fn default_font() -> SdfFontRef {
+    with_fonts(|fonts| fonts.default_font())
+        .expect("no default font, add a font to the global FontRegistry with `with_fonts` first")
+}
+
+/// A white text section in the default font of the global [`FontRegistry`], with a font size of 24.
+///
+/// ```rust,ignore
+/// div().child(text("Start").size(32.0).color(Color::YELLOW))
+/// ```
+pub fn text(string: impl Into<UiString>) -> TextSection {
+    let font = with_fonts(|fonts| fonts.default_slot())
+        .expect("no default font, add a font to the global FontRegistry with `with_fonts` first");
+    TextSection {
+        string: string.into(),
+        font: font.into(),
+        color: Color::WHITE,
+        font_size: 24.0,
+        shadow_intensity: 0.0,
+        hinting: None,
+        vertical_align: VerticalAlign::Baseline,
+        effect: UiEffect::NONE,
+    }
+}
+
+impl TextSection {
+    pub fn size(mut self, font_size: f32) -> Self {
+        self.font_size = font_size;
+        self
+    }
+
+    pub fn color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn shadow(mut self, shadow_intensity: f32) -> Self {
+        self.shadow_intensity = shadow_intensity;
+        self
+    }
+
+    pub fn effect(mut self, effect: UiEffect) -> Self {
+        self.effect = effect;
+        self
+    }
+
+    pub fn vertical_align(mut self, align: VerticalAlign) -> Self {
+        self.vertical_align = align;
+        self
+    }
+
+    /// Uses the font of the handle in the global [`FontRegistry`], also after it is replaced.
+    pub fn font(mut self, handle: FontHandle) -> Self {
+        self.font = with_fonts(|fonts| fonts.slot(handle)).into();
+        self
+    }
+
+    /// Uses the font in the slot, also after it is swapped.
+    pub fn font_slot(mut self, slot: &'static FontSlot) -> Self {
+        self.font = slot.into();
+        self
+    }
+}
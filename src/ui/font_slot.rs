@@ -0,0 +1,231 @@
+//! Fonts that can be swapped at runtime, e.g. when the player picks another font in the settings or the
+//! language changes to one that needs other glyphs.
+//!
+//! [`TextSection`](crate::ui::TextSection)s refer to their font through a [`SectionFont`]. A fixed font stays
+//! the same forever, a [`FontSlot`] is looked up again in every layout and batching. So elements that are kept
+//! between frames, like retained subtrees, switch to the new font as well:
+//!
+//! ```rust,ignore
+//! let slot = FontSlot::new(leak(SdfFont::new_with_default_chars(font, 64, 16, &device, &queue)));
+//! div().child(text("Settings").font_slot(slot));
+//! // later, fades the text out in the old font and in with the new one:
+//! slot.crossfade(leak(large_print_font), Duration::from_millis(300));
+//! // every frame:
+//! slot.update(time.delta().as_secs_f32());
+//! ```
+//!
+//! The fonts of the [`FontRegistry`](crate::ui::FontRegistry) are slots, so [`crate::ui::FontRegistry::replace`]
+//! also updates text that was built before.
+
+use std::{
+    sync::{
+        atomic::{AtomicPtr, AtomicU32, AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use super::font::{SdfFont, SdfFontRef};
+
+/// A font that can be swapped while text refers to it, see the module docs.
+///
+/// Slots are shared by reference between all text using them, so they are changed through `&self`.
+/// Swaps and fades only take effect in [`FontSlot::swap`] and [`FontSlot::update`], never between the layout and
+/// the batching of a frame, such that glyphs are always batched with the font they were laid out with.
+#[derive(Debug)]
+pub struct FontSlot {
+    /// always points to a leaked [`SdfFont`].
+    font: AtomicPtr<SdfFont>,
+    /// increased every time the font changes, texts laid out with an older generation are laid out again.
+    generation: AtomicU64,
+    /// bits of the f32 that the alpha of the glyphs is multiplied with during a crossfade.
+    alpha: AtomicU32,
+    fade: Mutex<Option<Fade<SdfFontRef>>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Fade<F> {
+    next: F,
+    duration: f32,
+    elapsed: f32,
+}
+
+impl<F> Fade<F> {
+    /// Moves the fade `dt` further, returns if the font should switch now and the new alpha (None when done).
+    fn advance(&mut self, dt: f32) -> (bool, Option<f32>) {
+        let half = self.duration * 0.5;
+        let was_first_half = self.elapsed < half;
+        self.elapsed += dt;
+        let switch = was_first_half && self.elapsed >= half;
+        if self.elapsed >= self.duration {
+            return (switch, None);
+        }
+        (switch, Some(((self.elapsed - half).abs() / half).min(1.0)))
+    }
+}
+
+impl FontSlot {
+    /// Leaks a new slot that shows `font`.
+    pub fn new(font: SdfFontRef) -> &'static FontSlot {
+        Box::leak(Box::new(FontSlot {
+            font: AtomicPtr::new(font as *const SdfFont as *mut SdfFont),
+            generation: AtomicU64::new(0),
+            alpha: AtomicU32::new(1.0f32.to_bits()),
+            fade: Mutex::new(None),
+        }))
+    }
+
+    /// The font text is laid out and batched with right now.
+    #[inline]
+    pub fn font(&self) -> SdfFontRef {
+        // SAFETY: the pointer always comes from a `&'static SdfFont`.
+        unsafe { &*self.font.load(Ordering::Acquire) }
+    }
+
+    /// Multiplied with the alpha of the glyphs, below 1.0 only during a [`FontSlot::crossfade`].
+    #[inline]
+    pub fn alpha(&self) -> f32 {
+        f32::from_bits(self.alpha.load(Ordering::Relaxed))
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Replaces the font right away, texts use it from their next layout on. Cancels a running crossfade.
+    pub fn swap(&self, font: SdfFontRef) {
+        *self.fade.lock().unwrap() = None;
+        self.set_alpha(1.0);
+        self.set_font(font);
+    }
+
+    /// Fades the text out in the current font over the first half of the `duration` and in with the new
+    /// `font` over the second half. Progresses in [`FontSlot::update`].
+    pub fn crossfade(&self, font: SdfFontRef, duration: Duration) {
+        let duration = duration.as_secs_f32();
+        if duration <= 0.0 {
+            self.swap(font);
+            return;
+        }
+        let mut fade = self.fade.lock().unwrap();
+        // a fade that is already in its second half starts from the alpha it reached.
+        let elapsed = match *fade {
+            Some(f) if f.elapsed > f.duration * 0.5 => {
+                (f.duration - f.elapsed) / f.duration * duration
+            }
+            Some(f) => f.elapsed / f.duration * duration,
+            None => 0.0,
+        };
+        *fade = Some(Fade {
+            next: font,
+            duration,
+            elapsed,
+        });
+    }
+
+    /// True while a crossfade is running.
+    pub fn is_fading(&self) -> bool {
+        self.fade.lock().unwrap().is_some()
+    }
+
+    /// Advances a running crossfade by `dt` seconds. Call it once per frame, before building the ui.
+    pub fn update(&self, dt: f32) {
+        let mut fade = self.fade.lock().unwrap();
+        let Some(f) = fade.as_mut() else {
+            return;
+        };
+        let (switch, alpha) = f.advance(dt);
+        if switch {
+            self.set_font(f.next);
+        }
+        if alpha.is_none() {
+            *fade = None;
+        }
+        self.set_alpha(alpha.unwrap_or(1.0));
+    }
+
+    fn set_font(&self, font: SdfFontRef) {
+        if std::ptr::eq(self.font(), font) {
+            return;
+        }
+        self.font
+            .store(font as *const SdfFont as *mut SdfFont, Ordering::Release);
+        self.generation.fetch_add(1, Ordering::AcqRel);
+    }
+
+    fn set_alpha(&self, alpha: f32) {
+        self.alpha.store(alpha.to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// The font of a [`TextSection`](crate::ui::TextSection): fixed, or a [`FontSlot`] that can be swapped.
+#[derive(Debug, Clone, Copy)]
+pub enum SectionFont {
+    Fixed(SdfFontRef),
+    Slot(&'static FontSlot),
+}
+
+impl SectionFont {
+    /// The font to lay out and batch the text with right now.
+    #[inline]
+    pub fn get(&self) -> SdfFontRef {
+        match self {
+            SectionFont::Fixed(font) => font,
+            SectionFont::Slot(slot) => slot.font(),
+        }
+    }
+
+    #[inline]
+    pub fn alpha(&self) -> f32 {
+        match self {
+            SectionFont::Fixed(_) => 1.0,
+            SectionFont::Slot(slot) => slot.alpha(),
+        }
+    }
+
+    /// 0 for fixed fonts, changes whenever the font of a slot is swapped.
+    #[inline]
+    pub fn generation(&self) -> u64 {
+        match self {
+            SectionFont::Fixed(_) => 0,
+            SectionFont::Slot(slot) => slot.generation(),
+        }
+    }
+}
+
+impl From<SdfFontRef> for SectionFont {
+    fn from(font: SdfFontRef) -> Self {
+        SectionFont::Fixed(font)
+    }
+}
+
+impl From<&'static FontSlot> for SectionFont {
+    fn from(slot: &'static FontSlot) -> Self {
+        SectionFont::Slot(slot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Fade;
+
+    #[test]
+    fn crossfades_switch_the_font_halfway() {
+        let mut fade = Fade {
+            next: (),
+            duration: 1.0,
+            elapsed: 0.0,
+        };
+        assert_eq!(fade.advance(0.25), (false, Some(0.5)));
+        assert_eq!(fade.advance(0.5), (true, Some(0.5)));
+        assert_eq!(fade.advance(0.5), (false, None));
+
+        // a fade that is shorter than a frame still switches the font.
+        let mut fade = Fade {
+            next: (),
+            duration: 0.01,
+            elapsed: 0.0,
+        };
+        assert_eq!(fade.advance(0.1), (true, None));
+    }
+}
@@ -9,7 +9,8 @@ use crate::ui::{
     element::{ComputedBounds, DivComputed, Section, TextComputed},
     element_store::ElementBox,
     font::GlyphInfo,
-    Align, Axis, Div, ElementWithComputed, MainAlign, SdfFont, Text, TextSection,
+    Align, Axis, Div, DivScroll, ElementWithComputed, MainAlign, SdfFont, Text, TextSection,
+    VerticalAlign,
 };
 
 use super::element_store::StoredElement;
@@ -54,8 +55,12 @@ impl ElementBox {
 impl StoredElement {
     pub fn get_and_set_size(&mut self, max_size: DVec2) -> DVec2 {
         match &mut self.element {
-            ElementWithComputed::Div((div, computed)) => div.get_and_set_size(max_size, computed),
-            ElementWithComputed::Text((text, computed)) => {
+            ElementWithComputed::Div(d) => {
+                let (div, computed) = &mut **d;
+                div.get_and_set_size(max_size, computed)
+            }
+            ElementWithComputed::Text(t) => {
+                let (text, computed) = &mut **t;
                 text.get_and_set_size(max_size, computed)
             }
         }
@@ -64,11 +69,20 @@ impl StoredElement {
     /// assumes all sizes have been calculated
     fn set_position(&mut self, pos: DVec2, visitor: &mut impl ComputedBoundsVisitor) {
         match &mut self.element {
-            ElementWithComputed::Div((div, computed)) => {
+            ElementWithComputed::Div(d) => {
+                let (div, computed) = &mut **d;
+                let scroll = div.extras.as_ref().and_then(|e| e.scroll);
+                if scroll.is_some() {
+                    computed.scroll = visitor.scroll_offset(self.id);
+                }
                 div.set_position(pos, computed, visitor);
-                visitor.visit(self.id, &computed.bounds);
+                visitor.visit_div(self.id, div, &computed.bounds);
+                if let Some(scroll) = &scroll {
+                    visitor.visit_scroll_area(self.id, scroll, computed);
+                }
             }
-            ElementWithComputed::Text((text, computed)) => {
+            ElementWithComputed::Text(t) => {
+                let (text, computed) = &mut **t;
                 text.set_position(pos, computed, visitor);
                 visitor.visit(self.id, &computed.bounds);
             }
@@ -117,6 +131,9 @@ impl Div {
                 *size = dvec2(content_size.x + pad_x, content_size.y + pad_y);
             }
         };
+        if self.extras.as_ref().is_some_and(|e| e.scroll.is_some()) {
+            computed.max_scroll = (content_size.y + pad_y - size.y).max(0.0);
+        }
 
         *size
     }
@@ -158,8 +175,15 @@ impl Div {
     ) {
         // set own position:
         computed.bounds.pos = pos + self.offset;
+        computed.scroll = computed.scroll.clamp(0.0, computed.max_scroll);
         // set childrens positions:
-        self.set_child_positions(computed, visitor)
+        if self.clips_children() {
+            visitor.push_clip(computed.bounds.as_aabb());
+            self.set_child_positions(computed, visitor);
+            visitor.pop_clip();
+        } else {
+            self.set_child_positions(computed, visitor)
+        }
     }
 
     #[inline]
@@ -229,7 +253,9 @@ impl Div {
             // top left corner of the inner area instead of the top left corner of the div itself
 
             let inner_size = dvec2(div_size.x - pad_x, div_size.y - pad_y); // div size - padding size on all sides
-            let inner_pos = div_pos + dvec2(div.padding.left, div.padding.top);
+
+            // the children of scroll areas are moved up by the scroll offset, it is 0.0 for other divs.
+            let inner_pos = div_pos + dvec2(div.padding.left, div.padding.top - computed.scroll);
 
             let (main_size, cross_size) = A::disassemble(inner_size);
             let (main_content_size, _) = A::disassemble(content_size);
@@ -257,18 +283,23 @@ impl Div {
                 let (ch_main_size, ch_cross_size) = A::disassemble(ch_size);
                 let cross = calc_cross_offset(cross_size, ch_cross_size);
 
-                let ch_rel_pos: DVec2;
-
+                let mut ch_pos: DVec2;
                 if let Some(unit_pos) = absolute_unit_pos(&ch.element) {
                     // absolute positioning still considers padding of parent (inner size);
                     let inner_offset = (inner_size - ch_size) * unit_pos.as_dvec2();
-                    ch_rel_pos = inner_offset;
+                    ch_pos = inner_offset + inner_pos;
+                    if let Some(safe_area) = respected_safe_area(&ch.element, visitor) {
+                        // pushed inwards, the top left corner wins if the div does not fit:
+                        ch_pos = ch_pos
+                            .min(safe_area.max.as_dvec2() - ch_size)
+                            .max(safe_area.min.as_dvec2());
+                    }
                 } else {
-                    ch_rel_pos = A::assemble(main_offset, cross);
+                    ch_pos = A::assemble(main_offset, cross) + inner_pos;
                     main_offset += ch_main_size + main_step;
                 }
 
-                ch.set_position(ch_rel_pos + inner_pos, visitor);
+                ch.set_position(ch_pos, visitor);
             }
         }
 
@@ -327,25 +358,60 @@ impl Div {
 #[inline(always)]
 fn is_absolute(element: &ElementWithComputed) -> bool {
     match &element {
-        ElementWithComputed::Div((d, _)) => d.absolute.is_some(),
+        ElementWithComputed::Div(d) => d.0.absolute.is_some(),
         ElementWithComputed::Text(_) => false,
     }
 }
 
+/// The safe area of the visitor, if the element is an absolute div that should stay inside of it.
+#[inline(always)]
+fn respected_safe_area(
+    element: &ElementWithComputed,
+    visitor: &impl ComputedBoundsVisitor,
+) -> Option<Aabb> {
+    match &element {
+        ElementWithComputed::Div(d) if d.0.safe_area => visitor.safe_area(),
+        _ => None,
+    }
+}
+
 #[inline(always)]
 fn absolute_unit_pos(element: &ElementWithComputed) -> Option<Vec2> {
     match &element {
-        ElementWithComputed::Div((d, _)) => d.absolute,
+        ElementWithComputed::Div(d) => d.0.absolute,
         ElementWithComputed::Text(_) => None,
     }
 }
 
 impl Text {
     fn get_and_set_size(&mut self, max_size: DVec2, computed: &mut TextComputed) -> DVec2 {
-        *computed = layout_text(self, max_size.x as f32);
+        let max_width = max_size.x as f32;
+        // texts retained from the last frame keep their glyphs, inline elements might have changed their size though.
+        let font_generation = self.font_generation();
+        if computed.layout_width == Some(max_width)
+            && computed.font_generation == font_generation
+            && self.element_sections().next().is_none()
+        {
+            computed.remove_position();
+            return computed.bounds.size;
+        }
+        *computed = layout_text(self, max_width);
+        computed.layout_width = Some(max_width);
+        computed.font_generation = font_generation;
         computed.bounds.size
     }
 
+    /// Changes when the font of a section in a [`crate::ui::FontSlot`] is swapped.
+    fn font_generation(&self) -> u64 {
+        self.sections
+            .iter()
+            .map(|s| match s {
+                Section::Text(text) => text.font.generation(),
+                Section::Element { .. } => 0,
+            })
+            .sum()
+    }
+
     fn set_position(
         &mut self,
         pos: DVec2,
@@ -362,11 +428,24 @@ impl Text {
             element.set_position(computed.bounds.pos + relative_pos_in_text, visitor)
         }
 
-        for g in computed.glyphs.iter_mut() {
-            g.bounds.pos.x += computed.bounds.pos.x as f32;
-            g.bounds.pos.y += computed.bounds.pos.y as f32;
+        computed.offset_glyphs(computed.bounds.pos.as_vec2());
+    }
+}
+
+impl TextComputed {
+    fn offset_glyphs(&mut self, offset: Vec2) {
+        for g in self.glyphs.iter_mut() {
+            g.bounds.pos += offset;
+            g.line_top += offset.y;
+            g.line_bottom += offset.y;
         }
     }
+
+    /// Moves the glyphs back to where they were right after the layout, relative to the text.
+    fn remove_position(&mut self) {
+        self.offset_glyphs(-self.bounds.pos.as_vec2());
+        self.bounds.pos = DVec2::ZERO;
+    }
 }
 
 pub fn layout_text(text: &mut Text, mut max_width: f32) -> TextComputed {
@@ -381,6 +460,7 @@ pub fn layout_text(text: &mut Text, mut max_width: f32) -> TextComputed {
         last_non_ws_glyph_advances: smallvec![],
         element_line_indices: smallvec![],
         text_section_glyphs: smallvec![],
+        aligned_sections: smallvec![],
     };
     text_layout.layout(text);
     text_layout.finalize(text)
@@ -396,6 +476,8 @@ struct TextLayout {
     /// last chars added to the layout that stick together on linebreaks, e.g. a word.
     last_non_ws_glyph_advances: SmallVec<[XOffsetAndAdance; 16]>,
     element_line_indices: SmallVec<[usize; 4]>,
+    /// glyphs, alignment and metrics of the text sections that are not aligned to the baseline.
+    aligned_sections: SmallVec<[(std::ops::Range<usize>, VerticalAlign, LineMetrics); 2]>,
 }
 
 #[derive(Debug)]
@@ -408,6 +490,13 @@ struct XOffsetAndAdance {
 pub struct GlyphBoundsAndUv {
     pub bounds: Rect,
     pub uv: Aabb,
+    /// atlas page of the font the uv refers to.
+    pub page: u32,
+    /// byte index of the char in the string of its text section.
+    pub byte_index: usize,
+    /// y of the top and bottom of the line the glyph is on, e.g. for selection highlights.
+    pub line_top: f32,
+    pub line_bottom: f32,
 }
 
 #[derive(Debug)]
@@ -460,6 +549,7 @@ impl TextLayout {
                 Section::Element {
                     element,
                     sets_line_height,
+                    ..
                 } => self.layout_element_section(element, *sets_line_height),
             }
         }
@@ -469,11 +559,11 @@ impl TextLayout {
         let glyphs_len_before = self.glyphs.len();
 
         let font_size = text.font_size;
-        let font: &SdfFont = &text.font;
+        let font: &SdfFont = text.font.get();
         let line_metrics = font.line_metrics(font_size);
         self.current_line.merge_metrics_take_max(&line_metrics);
 
-        for ch in text.string.chars() {
+        for (byte_index, ch) in text.string.char_indices() {
             let g = font.glyph_info(ch, font_size);
             let is_white_space = ch.is_whitespace();
             debug_assert_eq!(g.uv.is_some(), !is_white_space);
@@ -515,20 +605,24 @@ impl TextLayout {
                     }
 
                     // Also note that we do not clear the current word chars here. Should we? This is now a bit buggy maybe, if any word is longer than the
-                    self.add_glyph_to_current_line(&g);
+                    self.add_glyph_to_current_line(&g, byte_index);
                 }
             } else {
-                self.add_glyph_to_current_line(&g);
+                self.add_glyph_to_current_line(&g, byte_index);
             }
         }
-        self.text_section_glyphs
-            .push(glyphs_len_before..self.glyphs.len())
+        let glyph_range = glyphs_len_before..self.glyphs.len();
+        if text.vertical_align != VerticalAlign::Baseline {
+            self.aligned_sections
+                .push((glyph_range.clone(), text.vertical_align, line_metrics));
+        }
+        self.text_section_glyphs.push(glyph_range)
     }
 
     // if the glyph_info provided contains the texture uv coords (means: this is not whitespace),
     // then push the glyph onto the current line, increasing the advance of the current line.
     // if glyph is whitespace, just advance the `advance` pointer of the current line, but do not push a glyph onto the vec.
-    fn add_glyph_to_current_line(&mut self, g: &GlyphInfo) {
+    fn add_glyph_to_current_line(&mut self, g: &GlyphInfo, byte_index: usize) {
         if let Some(uv) = g.uv {
             // non-whitespace character
             let x_offset = g.metrics.xmin;
@@ -544,6 +638,11 @@ impl TextLayout {
             let primitive = GlyphBoundsAndUv {
                 bounds: Rect { pos, size },
                 uv,
+                page: g.page,
+                byte_index,
+                // set in `finalize`, when the lines are known.
+                line_top: 0.0,
+                line_bottom: 0.0,
             };
             self.glyphs.push(primitive);
             self.last_non_ws_glyph_advances.push(XOffsetAndAdance {
@@ -572,6 +671,7 @@ impl TextLayout {
 
     fn layout_element_section(&mut self, element: &mut ElementBox, sets_line_height: bool) {
         // currently only y-bounded in-text elements supported. Do not use an element with unbounded size as part of some text section.
+        // elements in texts are small, they are always sized on the current thread.
         let element_size = element.get_and_set_size(dvec2(self.max_width as f64, f64::MAX));
         // add line break if the element does not fit into this line anymore:
         let line_break = self.current_line.advance + element_size.x as f32 > self.max_width;
@@ -606,6 +706,7 @@ impl TextLayout {
             mut current_line,
             text_section_glyphs,
             element_line_indices,
+            aligned_sections,
             ..
         } = self;
 
@@ -619,14 +720,18 @@ impl TextLayout {
 
         let len = lines.len();
         for (i, line) in lines.iter_mut().enumerate() {
+            let line_top = base_y;
             base_y += line.max_metrics.ascent;
             line.baseline_y = base_y;
 
             max_line_width = max_line_width.max(line.advance);
 
+            let line_bottom = base_y - line.max_metrics.descent;
             for i in line.glyph_range.clone() {
                 let glyph = &mut glyphs[i];
                 glyph.bounds.pos.y += base_y;
+                glyph.line_top = line_top;
+                glyph.line_bottom = line_bottom;
             }
             base_y += -line.max_metrics.descent + line.max_metrics.line_gap;
             if i < len - 1 {
@@ -634,11 +739,31 @@ impl TextLayout {
             }
         }
 
-        // go over all inline elements and set their position to the baseline - descent (so the total bottom of a line).
-        for (i, element) in text.element_sections_mut().enumerate() {
-            let line = &lines[element_line_indices[i]];
-            let bottom_y = line.baseline_y - line.max_metrics.descent; // this is > baseline_y (so more down), because descent negative.
+        // move the glyphs of sections that are not on the baseline, the lines are large enough for all sections.
+        for (glyph_range, align, metrics) in aligned_sections {
+            for i in glyph_range {
+                let line = &lines[lines.partition_point(|l| l.glyph_range.end <= i)];
+                let shift = aligned_baseline(line, align, metrics.ascent, metrics.descent)
+                    - line.baseline_y;
+                glyphs[i].bounds.pos.y += shift;
+            }
+        }
+
+        // go over all inline elements and set their y position, relative to the text, according to their alignment.
+        let mut element_index = 0;
+        for section in text.sections.iter_mut() {
+            let Section::Element { element, align, .. } = section else {
+                continue;
+            };
+            let line = &lines[element_line_indices[element_index]];
+            element_index += 1;
             let computed = element.element.computed_bounds_mut();
+            let height = computed.size.y as f32;
+            // the element is treated like a glyph that stands on the baseline without descent:
+            let bottom_y = match align {
+                VerticalAlign::Bottom => line.baseline_y - line.max_metrics.descent, // this is > baseline_y (so more down), because descent negative.
+                align => aligned_baseline(line, *align, height, 0.0),
+            };
             computed.pos.y = bottom_y as f64 - computed.size.y;
         }
 
@@ -654,14 +779,90 @@ impl TextLayout {
             },
             glyphs,
             text_section_glyphs,
+            layout_width: None,
+            font_generation: 0,
         }
     }
 }
 
+/// The baseline of a section with `ascent` and `descent` in the `line`, such that it is aligned like `align`.
+fn aligned_baseline(line: &LineRun, align: VerticalAlign, ascent: f32, descent: f32) -> f32 {
+    let line_top = line.baseline_y - line.max_metrics.ascent;
+    let line_bottom = line.baseline_y - line.max_metrics.descent;
+    match align {
+        VerticalAlign::Baseline => line.baseline_y,
+        VerticalAlign::Top => line_top + ascent,
+        VerticalAlign::Middle => (line_top + line_bottom + ascent + descent) * 0.5,
+        VerticalAlign::Bottom => line_bottom + descent,
+    }
+}
+
 pub trait ComputedBoundsVisitor {
     fn visit(&mut self, id: ElementId, computed_bounds: &ComputedBounds);
+
+    /// Called for divs instead of `visit`, for visitors that need more than the bounds, e.g. for alpha hit testing.
+    #[inline]
+    fn visit_div(&mut self, id: ElementId, _div: &Div, computed_bounds: &ComputedBounds) {
+        self.visit(id, computed_bounds);
+    }
+
+    /// Area in layout space that absolute divs with `safe_area` set are kept inside of.
+    #[inline]
+    fn safe_area(&self) -> Option<Aabb> {
+        None
+    }
+
+    /// Called before the children of a div with `clip_children` are visited, with the bounds of the div.
+    #[inline]
+    fn push_clip(&mut self, _clip: Aabb) {}
+
+    /// Called after the children of a div with `clip_children` were visited.
+    #[inline]
+    fn pop_clip(&mut self) {}
+
+    /// The scroll offset of the div with [`crate::ui::DivExtras::scroll`] and the `id`, clamped during layout.
+    #[inline]
+    fn scroll_offset(&self, _id: ElementId) -> f64 {
+        0.0
+    }
+
+    /// Called for divs with [`crate::ui::DivExtras::scroll`] after their children were visited, the `computed`
+    /// values contain the clamped scroll offset.
+    #[inline]
+    fn visit_scroll_area(&mut self, _id: ElementId, _scroll: &DivScroll, _computed: &DivComputed) {}
 }
 impl ComputedBoundsVisitor for () {
     #[inline]
     fn visit(&mut self, _id: ElementId, _computed_bounds: &ComputedBounds) {}
 }
+
+#[cfg(test)]
+mod tests {
+    use fontdue::LineMetrics;
+
+    use super::{aligned_baseline, LineRun};
+    use crate::ui::VerticalAlign;
+
+    #[test]
+    fn small_sections_align_in_large_lines() {
+        // a line of 32px text with the baseline at 24, 8 below it for descenders.
+        let line = LineRun {
+            baseline_y: 24.0,
+            advance: 0.0,
+            max_metrics: LineMetrics {
+                ascent: 24.0,
+                descent: -8.0,
+                line_gap: 0.0,
+                new_line_size: 32.0,
+            },
+            glyph_range: 0..0,
+        };
+        // a section of 16px text:
+        let (ascent, descent) = (12.0, -4.0);
+        let baseline = |align| aligned_baseline(&line, align, ascent, descent);
+        assert_eq!(baseline(VerticalAlign::Baseline), 24.0);
+        assert_eq!(baseline(VerticalAlign::Top), 12.0);
+        assert_eq!(baseline(VerticalAlign::Middle), 20.0);
+        assert_eq!(baseline(VerticalAlign::Bottom), 28.0);
+    }
+}
@@ -1,28 +1,57 @@
+pub mod accessibility;
 pub mod allocator;
+pub mod alpha_mask;
 pub mod batching;
+pub mod custom_primitive;
 pub mod element;
 pub mod element_context;
 pub mod element_id;
 pub mod element_store;
 pub mod font;
+pub mod font_registry;
+pub mod font_slot;
 pub mod layout;
+#[cfg(feature = "parallel")]
+pub mod parallel;
+pub mod retained;
+pub mod text_selection;
+pub mod texture_atlas;
+pub mod virtual_list;
 
+pub use accessibility::{AccessInfo, AccessRole};
+pub use alpha_mask::AlphaMask;
+pub use custom_primitive::{CustomPrimitive, DivPrimitive};
 pub use element::{
-    div, red_box, Align, Axis, Corners, Div, DivTexture, Edges, Element, Len, MainAlign,
-    SdfTextureRegion, Text, TextSection, TextureRegion,
+    div, red_box, Align, Axis, Corners, Div, DivBorder, DivExtras, DivGauge, DivScroll, DivShadow,
+    DivStyleOverlay, DivTexture, Edges, Element, GaugeDirection, Len, MainAlign, Scrollbar,
+    SdfTextureRegion, Text, TextSection, TextureFill, TextureRegion, UiEffect, VerticalAlign,
 };
-pub use element_context::{Board, ElementContext, IntoElement};
+pub use element_context::{Board, BoardGR, ElementContext, IntoElement, UiCompositing, UiCulling};
 pub use element_id::ElementId;
 pub use element_store::{ElementBox, ElementWithComputed, IntoElementBox};
-pub use font::SdfFont;
+pub use font::{bake_sdf_font, SdfFont, TextHinting};
+pub use font_registry::{default_font, text, with_fonts, FontHandle, FontRegistry};
+pub use font_slot::{FontSlot, SectionFont};
+pub use text_selection::TextSelection;
+pub use texture_atlas::UiTextureAtlas;
+pub use virtual_list::{ItemHeight, VirtualList};
 
 pub use fontdue::{Font, FontSettings};
 
-pub use batching::get_batches;
+pub use batching::{get_batches, get_batches_with_ctx};
 use glam::{dvec2, DVec2, Vec2};
 
-pub const REFERENCE_SCREEN_SIZE_D: DVec2 = dvec2(1920.0, 1080.0); // this is the reference we design all ui for.
+/// The design resolution of the ui if nothing else is set, see [`Board::reference_size`] and [`crate::Screen::set_reference_size`].
+pub const DEFAULT_REFERENCE_SIZE: DVec2 = dvec2(1920.0, 1080.0);
+
+#[deprecated(
+    note = "the reference size is set per board now, see `Board::reference_size` and `DEFAULT_REFERENCE_SIZE`"
+)]
+pub const REFERENCE_SCREEN_SIZE_D: DVec2 = DEFAULT_REFERENCE_SIZE;
+#[deprecated(
+    note = "the reference size is set per board now, see `Board::reference_size` and `DEFAULT_REFERENCE_SIZE`"
+)]
 pub const REFERENCE_SCREEN_SIZE: Vec2 = Vec2 {
-    x: REFERENCE_SCREEN_SIZE_D.x as f32,
-    y: REFERENCE_SCREEN_SIZE_D.y as f32,
+    x: DEFAULT_REFERENCE_SIZE.x as f32,
+    y: DEFAULT_REFERENCE_SIZE.y as f32,
 };
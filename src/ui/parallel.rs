@@ -0,0 +1,112 @@
+//! Multithreaded ui batching with rayon, enabled by the `parallel` feature.
+//!
+//! Elements are not `Send`: they live in a thread local store and contain `Rc`s and references to fonts and
+//! textures that are not shared between threads. So layout and the collection of the prim elements stay serial,
+//! only plain data copied out of the elements goes to the thread pool. For large boards that is the stable sort
+//! of the prim elements by their stacking level: the levels are sorted together with the index of their element,
+//! the elements are then moved into the sorted order on the current thread.
+//!
+//! Results are identical to the serial path, the index makes the sort stable.
+//!
+//! Each [`crate::ui::ElementContext`] decides whether its board uses the thread pool, see
+//! [`crate::ui::ElementContext::set_parallel`].
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use rayon::slice::ParallelSliceMut;
+
+use crate::ui::batching::StackingLevel;
+
+/// Boards with fewer prim elements are sorted on the current thread.
+pub const MIN_PARALLEL_PRIM_ELEMENTS: usize = 4096;
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Switches the parallel path on (the default) or off for the [`crate::ui::ElementContext`]s created afterwards,
+/// use [`crate::ui::ElementContext::set_parallel`] for a single board.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Stable sort of the `elements` by their stacking level. Returns false without doing anything if there are
+/// too few elements, the caller sorts them then.
+pub(crate) fn sort_by_level<T>(elements: &mut Vec<T>, level: impl Fn(&T) -> StackingLevel) -> bool {
+    if elements.len() < MIN_PARALLEL_PRIM_ELEMENTS {
+        return false;
+    }
+    let mut order: Vec<(StackingLevel, usize)> = elements
+        .iter()
+        .enumerate()
+        .map(|(i, e)| (level(e), i))
+        .collect();
+    order.par_sort_unstable();
+    let mut unsorted: Vec<Option<T>> = elements.drain(..).map(Some).collect();
+    elements.extend(order.into_iter().map(|(_, i)| unsorted[i].take().unwrap()));
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::dvec2;
+
+    use crate::{
+        ui::{div, Axis, Board, Corners, Div, IntoElementBox, Len},
+        Color,
+    };
+
+    /// rows of boxes with sizes depending on their position, some of them outside of the board.
+    fn grid(rows: usize, cols: usize) -> Div {
+        let mut grid = div().style(|s| s.gap = 1.0);
+        for row in 0..rows {
+            let mut r = div().style(|s| {
+                s.axis = Axis::X;
+                s.gap = 2.0;
+                s.padding.left = row as f64 % 3.0;
+            });
+            for col in 0..cols {
+                let mut cell = div().style(|s| {
+                    s.width = Some(Len::Px(4.0 + (col % 5) as f64));
+                    s.height = Some(Len::Px(4.0 + (row % 7) as f64));
+                    s.color = Color::from_hsv(((row * cols + col) % 360) as f64, 0.8, 0.9);
+                    s.border.radius = Corners::all(2.0);
+                    s.z_index = (col % 3) as i16 - 1;
+                });
+                if col % 4 == 0 {
+                    cell = cell.child(div().style(|s| {
+                        s.width = Some(Len::Fraction(0.5));
+                        s.height = Some(Len::Px(2.0));
+                        s.color = Color::RED;
+                    }));
+                }
+                r.push(cell);
+            }
+            grid.push(r);
+        }
+        grid
+    }
+
+    #[test]
+    fn parallel_layout_and_batching_match_the_serial_path() {
+        let board_with = |parallel: bool| {
+            let mut board = Board::new(div().store(), dvec2(400.0, 600.0));
+            board.ctx.set_parallel(parallel);
+            board.set_element(grid(120, 60).store());
+            board
+        };
+        let serial = board_with(false);
+        let parallel = board_with(true);
+        assert!(serial.batches.rects.len() > super::MIN_PARALLEL_PRIM_ELEMENTS);
+        assert_eq!(
+            bytemuck::cast_slice::<_, u8>(&serial.batches.rects),
+            bytemuck::cast_slice::<_, u8>(&parallel.batches.rects)
+        );
+        assert_eq!(
+            format!("{:?}", serial.batches.batches),
+            format!("{:?}", parallel.batches.batches)
+        );
+    }
+}
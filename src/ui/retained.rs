@@ -0,0 +1,159 @@
+//! Reusing parts of the element tree between frames instead of building them again.
+//!
+//! Usually the whole tree is built every frame and the tree of the last frame is dropped, which allocates
+//! and frees every element in the thread local slab again. Subtrees that rarely change (inventories,
+//! skill bars, chat logs) can be built with [`crate::ui::ElementContext::retained`] instead: the subtree is built once
+//! and moved from the tree of the last frame into the new one as long as its id and key stay the same.
+//!
+//! ```rust,ignore
+//! board.update(&input, &time, &screen, |ctx| {
+//!     let inventory = ctx.retained("inventory", (&game.items, game.gold), |ctx| inventory(ctx, &game));
+//!     div().child_box(inventory).child(health_bar(game.health)).store()
+//! });
+//! ```
+//!
+//! The `key` has to cover everything the subtree is built from. Ids and keys are compared exactly, so
+//! the same state always gives the same tree, no matter which frame it is built in. Each id can only be
+//! retained once per frame, the second subtree with the same id is always built.
+//!
+//! Retained subtrees are still laid out and batched every frame, so hover styles, transitions and the
+//! positions of their parents work like for all other elements. Texts in them are not laid out again
+//! as long as their max width stays the same, which is the most expensive part of the layout. Elements
+//! returned by [`crate::ui::ElementContext::retained`] should not be mutated, changes would be kept in the next frames.
+//!
+//! Only boards built with [`crate::ui::Board::build`] (or [`crate::ui::Board::update`]) pass the tree of the last frame on,
+//! [`crate::ui::Board::set_element`] alone always builds everything.
+
+use std::hash::{Hash, Hasher};
+
+use ahash::{AHashMap, AHasher};
+
+use super::{div, element_id::ElementId, ElementBox, ElementWithComputed, IntoElementBox};
+
+/// The keys of the retained subtrees and the tree of the last frame, owned by the [`crate::ui::ElementContext`].
+#[derive(Debug, Default)]
+pub(crate) struct RetainedElements {
+    /// keys of the subtrees retained in the last frame.
+    keys: AHashMap<ElementId, u64>,
+    /// keys of the subtrees retained in the frame that is built right now.
+    next_keys: AHashMap<ElementId, u64>,
+    /// the tree of the last frame while the new one is built, retained subtrees are moved out of it.
+    previous: Option<ElementBox>,
+    n_reused: usize,
+    n_built: usize,
+}
+
+/// Element trees can't be cloned, a cloned [`crate::ui::ElementContext`] builds all retained subtrees again.
+impl Clone for RetainedElements {
+    fn clone(&self) -> Self {
+        RetainedElements::default()
+    }
+}
+
+impl RetainedElements {
+    pub fn begin(&mut self, previous: ElementBox) {
+        self.previous = Some(previous);
+        self.n_reused = 0;
+        self.n_built = 0;
+    }
+
+    /// Drops what is left of the last tree and forgets the keys of subtrees that were not built again.
+    pub fn end(&mut self) {
+        self.previous = None;
+        std::mem::swap(&mut self.keys, &mut self.next_keys);
+        self.next_keys.clear();
+    }
+
+    /// The subtree with `id` from the last frame if it was built with the same `key`, or None.
+    pub fn take(&mut self, id: ElementId, key: &impl Hash) -> Option<ElementBox> {
+        let key = hash_key(key);
+        if self.next_keys.insert(id, key).is_some() {
+            // retained twice in this frame, the first one got the subtree of the last frame.
+            return None;
+        }
+        let reused = match self.keys.get(&id) {
+            Some(last_key) if *last_key == key => self
+                .previous
+                .as_mut()
+                .and_then(|previous| take_with_id(previous, id)),
+            _ => None,
+        };
+        if reused.is_some() {
+            self.n_reused += 1;
+        } else {
+            self.n_built += 1;
+        }
+        reused
+    }
+
+    /// Number of subtrees that were reused and built in the last build.
+    pub fn counts(&self) -> (usize, usize) {
+        (self.n_reused, self.n_built)
+    }
+}
+
+fn hash_key(key: &impl Hash) -> u64 {
+    let mut hasher = AHasher::default();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Moves the element with the `id` out of the tree, leaving an empty div in its place.
+fn take_with_id(root: &mut ElementBox, id: ElementId) -> Option<ElementBox> {
+    if root.id == id {
+        return Some(std::mem::replace(root, div().store()));
+    }
+    match &mut root.element {
+        ElementWithComputed::Div(d) => {
+            d.0.children
+                .iter_mut()
+                .find_map(|child| take_with_id(child, id))
+        }
+        ElementWithComputed::Text(t) => t.0.sections.iter_mut().find_map(|s| match s {
+            super::element::Section::Element { element, .. } => take_with_id(element, id),
+            super::element::Section::Text(_) => None,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::dvec2;
+
+    use crate::ui::{div, Board, ElementContext, IntoElementBox};
+
+    fn build(board: &mut Board, n_items: usize) -> *const () {
+        let mut list_ptr = std::ptr::null();
+        board.build(|ctx: &mut ElementContext| {
+            let list = ctx.retained("list", n_items, |_| {
+                let mut list = div();
+                for _ in 0..n_items {
+                    list = list.child(div().style(|s| s.size(10, 10)));
+                }
+                list.store()
+            });
+            list_ptr = &*list as *const _ as *const ();
+            div()
+                .child(div().style(|s| s.size(5, 5)))
+                .child_box(list)
+                .store()
+        });
+        list_ptr
+    }
+
+    #[test]
+    fn unchanged_subtrees_are_reused() {
+        let mut board = Board::new(div().store(), dvec2(100.0, 100.0));
+        let first = build(&mut board, 3);
+        assert_eq!(board.ctx.retained_counts(), (0, 1));
+        let second = build(&mut board, 3);
+        assert_eq!(board.ctx.retained_counts(), (1, 0));
+        assert_eq!(first, second);
+        let list_size = |board: &Board| board.ctx.computed_bounds("list".into()).unwrap().size;
+        assert_eq!(list_size(&board), dvec2(10.0, 30.0));
+
+        build(&mut board, 4);
+        assert_eq!(board.ctx.retained_counts(), (0, 1));
+        assert_eq!(list_size(&board), dvec2(10.0, 40.0));
+    }
+}
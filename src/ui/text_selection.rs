@@ -0,0 +1,162 @@
+use std::ops::Range;
+
+use glam::{vec2, Vec2};
+
+use crate::{
+    ui::{
+        element::{Section, TextComputed},
+        layout::GlyphBoundsAndUv,
+        ElementId, Text,
+    },
+    Aabb,
+};
+
+/// The selected part of a [`Text`] with `selectable` set, see [`crate::ui::ElementContext::text_selection`].
+///
+/// Positions are carets between the glyphs of the text: 0 is before the first glyph, `n` after the last one.
+/// White space has no glyphs, so it is only selected if it is between selected glyphs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextSelection {
+    pub id: ElementId,
+    /// where the drag started.
+    pub anchor: usize,
+    /// where the drag is now, or ended.
+    pub focus: usize,
+    /// the selected substring of the text sections, including the white space between the selected glyphs.
+    pub text: String,
+}
+
+impl TextSelection {
+    /// Indices of the selected glyphs in [`TextComputed::glyphs`].
+    pub fn glyph_range(&self) -> Range<usize> {
+        self.anchor.min(self.focus)..self.anchor.max(self.focus)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.anchor == self.focus
+    }
+}
+
+/// The caret closest to `pos` (in layout space): on the closest line, before the first glyph whose center is right of `pos`.
+pub fn caret_at(glyphs: &[GlyphBoundsAndUv], pos: Vec2) -> usize {
+    let mut line_top: Option<(f32, f32)> = None;
+    for g in glyphs {
+        let dist = (g.line_top - pos.y).max(pos.y - g.line_bottom).max(0.0);
+        if line_top.is_none_or(|(best, _)| dist < best) {
+            line_top = Some((dist, g.line_top));
+        }
+    }
+    let Some((_, line_top)) = line_top else {
+        return 0;
+    };
+
+    let mut caret = 0;
+    for (i, g) in glyphs.iter().enumerate() {
+        if g.line_top != line_top {
+            continue;
+        }
+        if pos.x < g.bounds.pos.x + g.bounds.size.x * 0.5 {
+            return i;
+        }
+        caret = i + 1;
+    }
+    caret
+}
+
+/// One highlight rect per line of the selected glyphs, from the left edge of the first to the right edge of the last glyph.
+pub fn selection_rects(glyphs: &[GlyphBoundsAndUv], range: Range<usize>) -> Vec<Aabb> {
+    let mut rects: Vec<Aabb> = vec![];
+    let mut last_line_top: Option<f32> = None;
+    for g in &glyphs[range] {
+        let min = vec2(g.bounds.pos.x, g.line_top);
+        let max = vec2(g.bounds.pos.x + g.bounds.size.x, g.line_bottom);
+        match rects.last_mut() {
+            Some(rect) if last_line_top == Some(g.line_top) => {
+                rect.min.x = rect.min.x.min(min.x);
+                rect.max.x = rect.max.x.max(max.x);
+            }
+            _ => rects.push(Aabb::new(min, max)),
+        }
+        last_line_top = Some(g.line_top);
+    }
+    rects
+}
+
+/// The substring of the text sections covered by the glyphs in `range`. Sections that are completely inside
+/// of the selection are copied as a whole, so line breaks and white space at their ends are kept.
+pub fn selected_string(text: &Text, computed: &TextComputed, range: Range<usize>) -> String {
+    let mut string = String::new();
+    if range.is_empty() {
+        return string;
+    }
+    let text_sections = text.sections.iter().filter_map(|s| match s {
+        Section::Text(section) => Some(section),
+        Section::Element { .. } => None,
+    });
+    for (section, glyphs) in text_sections.zip(computed.text_section_glyphs.iter()) {
+        if glyphs.end <= range.start || glyphs.start >= range.end {
+            continue;
+        }
+        let s: &str = &section.string;
+        let start = if range.start <= glyphs.start {
+            0
+        } else {
+            computed.glyphs[range.start].byte_index
+        };
+        let end = if range.end >= glyphs.end {
+            s.len()
+        } else {
+            let last = computed.glyphs[range.end - 1].byte_index;
+            last + s[last..].chars().next().map_or(0, char::len_utf8)
+        };
+        string.push_str(&s[start..end]);
+    }
+    string
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::vec2;
+
+    use super::{caret_at, selection_rects};
+    use crate::{rect::Rect, ui::layout::GlyphBoundsAndUv, Aabb};
+
+    fn glyph(x: f32, line: f32) -> GlyphBoundsAndUv {
+        GlyphBoundsAndUv {
+            bounds: Rect {
+                pos: vec2(x, line * 10.0 + 2.0),
+                size: vec2(4.0, 6.0),
+            },
+            uv: Aabb::UNIT,
+            page: 0,
+            byte_index: 0,
+            line_top: line * 10.0,
+            line_bottom: line * 10.0 + 10.0,
+        }
+    }
+
+    #[test]
+    fn carets_and_highlights() {
+        // "ab c" on the first line, "de" on the second one.
+        let glyphs = [
+            glyph(0.0, 0.0),
+            glyph(5.0, 0.0),
+            glyph(15.0, 0.0),
+            glyph(0.0, 1.0),
+            glyph(5.0, 1.0),
+        ];
+        assert_eq!(caret_at(&glyphs, vec2(-3.0, 5.0)), 0);
+        assert_eq!(caret_at(&glyphs, vec2(8.0, 5.0)), 2);
+        assert_eq!(caret_at(&glyphs, vec2(100.0, 5.0)), 3);
+        assert_eq!(caret_at(&glyphs, vec2(6.0, 50.0)), 4);
+
+        let rects = selection_rects(&glyphs, 1..4);
+        assert_eq!(
+            rects,
+            vec![
+                Aabb::new(vec2(5.0, 0.0), vec2(19.0, 10.0)),
+                Aabb::new(vec2(0.0, 10.0), vec2(4.0, 20.0))
+            ]
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use glam::{uvec2, UVec2};
+use image::RgbaImage;
+
+use crate::{
+    leak,
+    utils::rect_packer::{PackedRect, RectPacker},
+    Aabb, BindableTexture, Texture,
+};
+
+use super::TextureRegion;
+
+/// Packs small images uploaded at runtime (icons, portraits, ...) into shared atlas pages, such that divs
+/// showing them end up in the same batch instead of one batch per texture:
+///
+/// ```rust,ignore
+/// let mut atlas = UiTextureAtlas::default();
+/// let sword = atlas.upload(&image::open("sword.png")?.to_rgba8(), &device, &queue)?;
+/// let shield = atlas.upload(&image::open("shield.png")?.to_rgba8(), &device, &queue)?;
+/// div().style(|s| s.texture(sword))
+/// ```
+///
+/// Images larger than [`UiTextureAtlas::max_image_size`] in any dimension get a texture of their own.
+/// Pages are never freed, like all textures in the ui they are leaked to be `'static`.
+#[derive(Debug)]
+pub struct UiTextureAtlas {
+    page_size: u32,
+    max_image_size: u32,
+    pages: Vec<AtlasPage>,
+    n_standalone: usize,
+}
+
+#[derive(Debug)]
+struct AtlasPage {
+    texture: &'static BindableTexture,
+    packer: RectPacker,
+}
+
+/// Space around each image in the atlas, filled with its edge pixels, such that linear filtering
+/// never samples the neighbouring image.
+const PADDING: u32 = 1;
+
+impl Default for UiTextureAtlas {
+    /// 1024x1024 pages for images up to 256x256.
+    fn default() -> Self {
+        UiTextureAtlas::new(1024, 256)
+    }
+}
+
+impl UiTextureAtlas {
+    /// Pages of `page_size` x `page_size` px are created when the images do not fit into the existing ones.
+    pub fn new(page_size: u32, max_image_size: u32) -> Self {
+        UiTextureAtlas {
+            page_size,
+            max_image_size: max_image_size.min(page_size - 2 * PADDING),
+            pages: vec![],
+            n_standalone: 0,
+        }
+    }
+
+    pub fn max_image_size(&self) -> u32 {
+        self.max_image_size
+    }
+
+    pub fn n_pages(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Number of images that were too large for the atlas and got their own texture.
+    pub fn n_standalone(&self) -> usize {
+        self.n_standalone
+    }
+
+    /// Copies the image into an atlas page and returns the region of the page that shows it.
+    /// Fails for images with a width or height of 0.
+    pub fn upload(
+        &mut self,
+        image: &RgbaImage,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+    ) -> anyhow::Result<TextureRegion> {
+        let (width, height) = image.dimensions();
+        if width == 0 || height == 0 {
+            anyhow::bail!("cannot upload an empty {width}x{height} image to the atlas");
+        }
+        if width > self.max_image_size || height > self.max_image_size {
+            self.n_standalone += 1;
+            let texture = Texture::from_image(
+                device,
+                queue,
+                image,
+                wgpu::FilterMode::Linear,
+                wgpu::AddressMode::ClampToEdge,
+            );
+            let texture: &'static BindableTexture = leak(BindableTexture::new(device, texture));
+            return Ok(TextureRegion {
+                texture,
+                uv: Aabb::UNIT,
+            });
+        }
+
+        let padded_size = uvec2(width, height) + 2 * PADDING;
+        let (page, rect) = self.allocate(padded_size, device);
+        let padded = padded_image(image);
+        queue.write_texture(
+            wgpu::ImageCopyTexture {
+                aspect: wgpu::TextureAspect::All,
+                texture: &page.texture.texture.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: rect.min.x,
+                    y: rect.min.y,
+                    z: 0,
+                },
+            },
+            &padded,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * padded_size.x),
+                rows_per_image: Some(padded_size.y),
+            },
+            wgpu::Extent3d {
+                width: padded_size.x,
+                height: padded_size.y,
+                depth_or_array_layers: 1,
+            },
+        );
+        let inner = PackedRect {
+            min: rect.min + PADDING,
+            size: uvec2(width, height),
+        };
+        Ok(TextureRegion {
+            texture: page.texture,
+            uv: inner.uv(page.packer.size()),
+        })
+    }
+
+    /// A rect of `size` in the first page with space for it, or in a new page.
+    fn allocate(&mut self, size: UVec2, device: &wgpu::Device) -> (&AtlasPage, PackedRect) {
+        let found = self.pages.iter_mut().enumerate().find_map(|(i, page)| {
+            let id = page.packer.allocate(size.x, size.y)?;
+            Some((i, page.packer.get(id).unwrap()))
+        });
+        if let Some((i, rect)) = found {
+            return (&self.pages[i], rect);
+        }
+
+        let texture = Texture::create_2d_texture(
+            device,
+            self.page_size,
+            self.page_size,
+            wgpu::TextureFormat::Rgba8UnormSrgb,
+            wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            wgpu::FilterMode::Linear,
+            wgpu::AddressMode::ClampToEdge,
+        );
+        let mut page = AtlasPage {
+            texture: leak(BindableTexture::new(device, texture)),
+            packer: RectPacker::new(self.page_size, self.page_size),
+        };
+        let id = page
+            .packer
+            .allocate(size.x, size.y)
+            .expect("images up to the max image size fit into an empty page");
+        let rect = page.packer.get(id).unwrap();
+        self.pages.push(page);
+        (self.pages.last().unwrap(), rect)
+    }
+}
+
+/// The image with [`PADDING`] px on each side, repeating the pixels at its edges. The image must not be empty.
+fn padded_image(image: &RgbaImage) -> RgbaImage {
+    let (width, height) = image.dimensions();
+    RgbaImage::from_fn(width + 2 * PADDING, height + 2 * PADDING, |x, y| {
+        let x = x.saturating_sub(PADDING).min(width - 1);
+        let y = y.saturating_sub(PADDING).min(height - 1);
+        *image.get_pixel(x, y)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgba, RgbaImage};
+
+    use super::padded_image;
+
+    #[test]
+    fn padding_repeats_the_edge_pixels() {
+        let image = RgbaImage::from_fn(2, 3, |x, y| Rgba([x as u8, y as u8, 0, 255]));
+        let padded = padded_image(&image);
+        assert_eq!(padded.dimensions(), (4, 5));
+        assert_eq!(padded.get_pixel(0, 0), image.get_pixel(0, 0));
+        assert_eq!(padded.get_pixel(3, 4), image.get_pixel(1, 2));
+        assert_eq!(padded.get_pixel(2, 1), image.get_pixel(1, 0));
+        assert_eq!(padded.get_pixel(1, 4), image.get_pixel(0, 2));
+    }
+}
@@ -0,0 +1,264 @@
+use std::ops::Range;
+
+use glam::dvec2;
+
+use crate::ui::{div, element_id::ElementId, element_store::ElementBox, Div, IntoElementBox, Len};
+
+/// Height of the items in a [`VirtualList`].
+pub enum ItemHeight<'a> {
+    /// All items have the same height, the cheapest option.
+    Fixed(f64),
+    /// Height of the item at the index. Called for every item each time the list is built,
+    /// so it should not do any layout itself.
+    Measure(Box<dyn Fn(usize) -> f64 + 'a>),
+}
+
+/// A vertical list that only builds and lays out the items visible in its viewport, plus `overscan` items
+/// before and after. Lists with thousands of items cost about as much as the few items on screen.
+///
+/// The scroll offset is owned by the app and passed in every frame. Each item is wrapped in a div
+/// with the id `id + index`, so hover and click states stick to the logical item while scrolling.
+///
+/// ```rust
+/// # use tgf::{ui::{text, virtual_list::{ItemHeight, VirtualList}, Div}, Input};
+/// # struct Inventory { items: Vec<String>, scroll: f64 }
+/// # impl Inventory {
+/// # fn build(&mut self, input: &Input, root: Div) -> Div {
+/// let items = &self.items;
+/// let list = VirtualList::new("inventory", items.len(), ItemHeight::Fixed(48.0), |i| {
+///     text(items[i].clone())
+/// })
+/// .viewport_height(600.0)
+/// .scroll(self.scroll);
+/// self.scroll = list.clamp_scroll(self.scroll - input.scroll().unwrap_or(0.0) as f64 * 48.0);
+/// root.child(list)
+/// # }
+/// # }
+/// ```
+///
+/// Note: partially visible items at the edges are drawn outside of the viewport, unless the list is put into a div
+/// with [`crate::ui::DivStyle::clip_children`].
+pub struct VirtualList<F> {
+    id: ElementId,
+    count: usize,
+    heights: Heights,
+    builder: F,
+    scroll: f64,
+    viewport_height: f64,
+    width: Option<Len>,
+    overscan: usize,
+}
+
+enum Heights {
+    Fixed(f64),
+    /// Offsets of the items from the top of the list, with the total height as the last element.
+    Measured(Vec<f64>),
+}
+
+impl<F, E> VirtualList<F>
+where
+    F: FnMut(usize) -> E,
+    E: IntoElementBox,
+{
+    pub fn new(
+        id: impl Into<ElementId>,
+        count: usize,
+        item_height: ItemHeight,
+        builder: F,
+    ) -> Self {
+        let heights = match item_height {
+            ItemHeight::Fixed(height) => Heights::Fixed(height.max(0.0)),
+            ItemHeight::Measure(measure) => {
+                let mut offsets = Vec::with_capacity(count + 1);
+                let mut offset = 0.0;
+                offsets.push(offset);
+                for i in 0..count {
+                    offset += measure(i).max(0.0);
+                    offsets.push(offset);
+                }
+                Heights::Measured(offsets)
+            }
+        };
+        VirtualList {
+            id: id.into(),
+            count,
+            heights,
+            builder,
+            scroll: 0.0,
+            viewport_height: 0.0,
+            width: Some(Len::FULL),
+            overscan: 2,
+        }
+    }
+
+    /// Scroll offset in px from the top of the list, clamped to the scrollable range.
+    pub fn scroll(mut self, scroll: f64) -> Self {
+        self.scroll = self.clamp_scroll(scroll);
+        self
+    }
+
+    /// Height of the visible area of the list.
+    pub fn viewport_height(mut self, height: f64) -> Self {
+        self.viewport_height = height.max(0.0);
+        self.scroll = self.clamp_scroll(self.scroll);
+        self
+    }
+
+    /// Width of the list, the full width of the parent by default. None means the width of the widest visible item.
+    pub fn width(mut self, width: Option<Len>) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Number of items built before and after the visible ones, 2 by default.
+    pub fn overscan(mut self, overscan: usize) -> Self {
+        self.overscan = overscan;
+        self
+    }
+
+    /// Height of all items together.
+    pub fn content_height(&self) -> f64 {
+        self.item_offset(self.count)
+    }
+
+    /// Largest scroll offset, where the last item is at the bottom of the viewport.
+    pub fn max_scroll(&self) -> f64 {
+        (self.content_height() - self.viewport_height).max(0.0)
+    }
+
+    pub fn clamp_scroll(&self, scroll: f64) -> f64 {
+        scroll.clamp(0.0, self.max_scroll())
+    }
+
+    /// Scroll offset that brings the item at `index` into view, changing the current `scroll` as little as possible.
+    pub fn scroll_to_item(&self, index: usize, scroll: f64) -> f64 {
+        let index = index.min(self.count.saturating_sub(1));
+        let top = self.item_offset(index);
+        let bottom = self.item_offset(index + 1);
+        let scroll = if top < scroll {
+            top
+        } else if bottom > scroll + self.viewport_height {
+            bottom - self.viewport_height
+        } else {
+            scroll
+        };
+        self.clamp_scroll(scroll)
+    }
+
+    /// Indices of the items that are built, the visible ones plus overscan.
+    pub fn visible_range(&self) -> Range<usize> {
+        if self.count == 0 {
+            return 0..0;
+        }
+        let top = self.scroll;
+        let bottom = self.scroll + self.viewport_height;
+        let (first, end) = match &self.heights {
+            Heights::Fixed(height) => {
+                if *height <= 0.0 {
+                    return 0..0;
+                }
+                let first = (top / height).floor() as usize;
+                let end = (bottom / height).ceil() as usize;
+                (first, end)
+            }
+            Heights::Measured(offsets) => {
+                // offsets[i + 1] is the bottom of item i.
+                let first = offsets[1..].partition_point(|bottom_of_item| *bottom_of_item <= top);
+                let end =
+                    offsets[..self.count].partition_point(|top_of_item| *top_of_item < bottom);
+                (first, end)
+            }
+        };
+        let first = first.min(self.count).saturating_sub(self.overscan);
+        let end = (end.max(first) + self.overscan).min(self.count);
+        first..end
+    }
+
+    /// Offset of the top of the item from the top of the list, `index == count` gives the content height.
+    fn item_offset(&self, index: usize) -> f64 {
+        match &self.heights {
+            Heights::Fixed(height) => index.min(self.count) as f64 * height,
+            Heights::Measured(offsets) => offsets[index.min(self.count)],
+        }
+    }
+
+    fn item_height(&self, index: usize) -> f64 {
+        self.item_offset(index + 1) - self.item_offset(index)
+    }
+
+    fn build(mut self) -> Div {
+        let range = self.visible_range();
+        let mut items = div().style(|s| {
+            s.width = self.width.map(|_| Len::FULL);
+            s.offset = dvec2(0.0, self.item_offset(range.start) - self.scroll);
+        });
+        for i in range {
+            let height = self.item_height(i);
+            let item = (self.builder)(i);
+            let wrapper = div()
+                .style(|s| {
+                    s.width = self.width.map(|_| Len::FULL);
+                    s.height = Some(Len::Px(height));
+                })
+                .child(item);
+            items = items.child_with_id(self.id + i, wrapper);
+        }
+        div()
+            .style(|s| {
+                s.width = self.width;
+                s.height = Some(Len::Px(self.viewport_height));
+            })
+            .child(items)
+    }
+}
+
+impl<F, E> IntoElementBox for VirtualList<F>
+where
+    F: FnMut(usize) -> E,
+    E: IntoElementBox,
+{
+    fn store(self) -> ElementBox {
+        self.build().store()
+    }
+
+    fn store_with_id(self, id: impl Into<ElementId>) -> ElementBox {
+        self.build().store_with_id(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ui::{div, Div};
+
+    use super::{ItemHeight, VirtualList};
+
+    fn make_list(count: usize, item_height: ItemHeight) -> VirtualList<impl FnMut(usize) -> Div> {
+        VirtualList::new("list", count, item_height, |_| div())
+            .overscan(0)
+            .viewport_height(100.0)
+    }
+
+    #[test]
+    fn visible_range_fixed_height() {
+        let list = make_list(10_000, ItemHeight::Fixed(30.0)).scroll(95.0);
+        assert_eq!(list.visible_range(), 3..7);
+        assert_eq!(list.overscan(2).visible_range(), 1..9);
+
+        let end = make_list(10, ItemHeight::Fixed(30.0)).scroll(1000.0);
+        assert_eq!(end.max_scroll(), 200.0);
+        assert_eq!(end.visible_range(), 6..10);
+    }
+
+    #[test]
+    fn visible_range_measured_height() {
+        // items are 10, 20, 30, ... px high, so they start at 0, 10, 30, 60, 100, 150.
+        let list = make_list(
+            100,
+            ItemHeight::Measure(Box::new(|i| (i + 1) as f64 * 10.0)),
+        )
+        .scroll(35.0);
+        assert_eq!(list.visible_range(), 2..5);
+        assert_eq!(list.scroll_to_item(6, 35.0), 180.0);
+        assert_eq!(list.scroll_to_item(0, 35.0), 0.0);
+    }
+}
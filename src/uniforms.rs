@@ -9,6 +9,11 @@ use crate::{
 
 static GLOBAL_UNIFORMS_BIND_GROUP_LAYOUT: OnceLock<Arc<wgpu::BindGroupLayout>> = OnceLock::new();
 
+/// Bind group 0 of most renderers: camera, screen, time and input.
+///
+/// To render the scene from multiple cameras (e.g. for a minimap), create one [`Uniforms`] per camera
+/// and pass the matching one to the `render` functions of the renderers, see [`crate::OffscreenCamera`].
+/// All of them share the same bind group layout.
 pub struct Uniforms {
     camera: UniformBuffer<Camera3dRaw>,
     screen: UniformBuffer<ScreenRaw>,
@@ -101,6 +106,21 @@ impl Uniforms {
         self.input.update_and_prepare(input.to_raw(), queue);
     }
 
+    /// Like [`Uniforms::prepare`], but with camera matrices computed elsewhere, see [`Camera3dRaw::from_matrices`].
+    pub fn prepare_camera_raw(
+        &mut self,
+        queue: &wgpu::Queue,
+        camera: Camera3dRaw,
+        screen: &Screen,
+        time: &Time,
+        input: &Input,
+    ) {
+        self.camera.update_and_prepare(camera, queue);
+        self.screen.update_and_prepare(screen.to_raw(), queue);
+        self.time.update_and_prepare(time.to_raw(), queue);
+        self.input.update_and_prepare(input.to_raw(), queue);
+    }
+
     pub fn bind_group_layout(&self) -> &Arc<wgpu::BindGroupLayout> {
         &self.bind_group_layout
     }
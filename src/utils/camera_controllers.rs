@@ -3,6 +3,9 @@ use crate::{Camera3d, Input, Time};
 pub struct FlyCamController {
     pub speed: f32,
     pub angle_speed: f32,
+    /// Radians the camera turns per unit of [`Input::mouse_motion`], only while the cursor is locked,
+    /// see [`Input::set_cursor_locked`].
+    pub mouse_sensitivity: f32,
 }
 
 impl Default for FlyCamController {
@@ -10,6 +13,7 @@ impl Default for FlyCamController {
         FlyCamController {
             speed: 10.0,
             angle_speed: 1.8,
+            mouse_sensitivity: 0.002,
         }
     }
 }
@@ -31,5 +35,12 @@ impl FlyCamController {
 
         cam.pitch += arrows.y * self.angle_speed * delta_time;
         cam.yaw += arrows.x * self.angle_speed * delta_time;
+        if input.cursor_locked() {
+            // a bit less than straight up or down, where the yaw would flip.
+            const MAX_PITCH: f32 = std::f32::consts::FRAC_PI_2 - 0.01;
+            let motion = input.mouse_motion() * self.mouse_sensitivity;
+            cam.pitch = (cam.pitch - motion.y).clamp(-MAX_PITCH, MAX_PITCH);
+            cam.yaw += motion.x;
+        }
     }
 }
@@ -3,7 +3,7 @@ use std::collections::BTreeMap;
 use std::ops::Range;
 
 use egui::Checkbox;
-use glam::Vec3;
+use glam::{DVec2, Quat, Vec2, Vec3};
 
 use crate::ext::egui::Context;
 use crate::ext::egui::{self, Slider};
@@ -11,6 +11,8 @@ use crate::Color;
 
 use crate::YoloCell;
 
+pub use tgf_macros::EditableEnum;
+
 thread_local! {
     pub static GLOBAL_VALUES: YoloCell<GlobalValues> = YoloCell::new(GlobalValues::new());
 }
@@ -50,18 +52,63 @@ macro_rules! show {
     }};
 }
 
+// contains: data pointer, params data pointer, ptr to the EditableValue::edit fn, ptr to a fn that converts the data into a string.
+type EditableEntry = (
+    *mut (),
+    *mut (),
+    fn(*mut (), *const (), &mut egui::Ui),
+    fn(*mut ()) -> String,
+);
+
+/// Labels can be hierarchical, e.g. `edit!(0.5, "bloom/threshold")` shows up as `threshold` in the collapsible group `bloom`.
 struct GlobalValues {
-    // contains: data pointer, params data pointer, ptr to the EditableValue::edit fn, ptr to a fn that converts the data into a string.
-    editable_values: BTreeMap<
-        &'static str,
-        (
-            *mut (),
-            *mut (),
-            fn(*mut (), *const (), &mut egui::Ui),
-            fn(*mut ()) -> String,
-        ),
-    >,
+    editable_values: BTreeMap<&'static str, EditableEntry>,
     show_only_values: BTreeMap<&'static str, String>,
+    /// only values whose label contains this string (case insensitive) are shown.
+    search: String,
+    /// if true, each top level group gets its own window.
+    separate_windows: bool,
+}
+
+/// Values grouped by the `/` separated segments of their labels.
+#[derive(Default)]
+struct ValueGroup {
+    /// (full label, last segment of the label, entry)
+    values: Vec<(&'static str, &'static str, EditableEntry)>,
+    children: BTreeMap<&'static str, ValueGroup>,
+}
+
+impl ValueGroup {
+    fn insert(&mut self, label: &'static str, entry: EditableEntry) {
+        let mut group = self;
+        let mut segments = label.split('/').peekable();
+        while let Some(segment) = segments.next() {
+            if segments.peek().is_none() {
+                group.values.push((label, segment, entry));
+                break;
+            }
+            group = group.children.entry(segment).or_default();
+        }
+    }
+
+    fn show(&self, ui: &mut egui::Ui, force_open: bool) {
+        for (_, name, (data_ptr, params_ptr, edit_fn_ptr, _)) in self.values.iter() {
+            ui.separator();
+            ui.label(*name);
+            edit_fn_ptr(*data_ptr, *params_ptr, ui);
+        }
+        for (name, child) in self.children.iter() {
+            child.show_collapsing(ui, name, force_open);
+        }
+    }
+
+    fn show_collapsing(&self, ui: &mut egui::Ui, name: &str, force_open: bool) {
+        let mut header = egui::CollapsingHeader::new(name);
+        if force_open {
+            header = header.open(Some(true));
+        }
+        header.show(ui, |ui| self.show(ui, force_open));
+    }
 }
 
 unsafe impl Send for GlobalValues {}
@@ -71,6 +118,8 @@ impl GlobalValues {
         GlobalValues {
             editable_values: BTreeMap::new(),
             show_only_values: BTreeMap::new(),
+            search: String::new(),
+            separate_windows: false,
         }
     }
 
@@ -131,26 +180,58 @@ impl GlobalValues {
     }
 
     fn create_window(&mut self, ctx: &mut Context) {
-        egui::Window::new("Editable Global Values").show(ctx, |ui| {
-            if ui.button("Export Values").clicked() {
-                self.export_values("./editable_values_dump.txt");
+        let search = self.search.to_lowercase();
+        let matches_search =
+            |label: &str| search.is_empty() || label.to_lowercase().contains(&search);
+
+        let mut root = ValueGroup::default();
+        for (label, entry) in self.editable_values.iter() {
+            if matches_search(label) {
+                root.insert(label, *entry);
             }
+        }
+        // open all groups while searching, such that the results are visible.
+        let force_open = !search.is_empty();
 
-            for (label, (data_ptr, params_ptr, edit_fn_ptr, _)) in self.editable_values.iter_mut() {
-                let label = *label;
-                let data_ptr = *data_ptr;
-                let params_ptr = *params_ptr;
-                let edit_fn_ptr = *edit_fn_ptr;
-
-                ui.separator();
-                ui.label(label);
-                edit_fn_ptr(data_ptr, params_ptr, ui);
-            }
+        egui::Window::new("Editable Global Values").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("Export Values").clicked() {
+                    self.export_values("./editable_values_dump.txt");
+                }
+                ui.checkbox(&mut self.separate_windows, "Separate windows");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Search");
+                ui.text_edit_singleline(&mut self.search);
+            });
+
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                if self.separate_windows {
+                    // only ungrouped values, groups are shown in their own windows below.
+                    ValueGroup {
+                        values: std::mem::take(&mut root.values),
+                        children: BTreeMap::new(),
+                    }
+                    .show(ui, force_open);
+                } else {
+                    root.show(ui, force_open);
+                }
+
+                for (label, val) in self.show_only_values.iter() {
+                    if matches_search(label) {
+                        ui.label(format!("{label}: {val}"));
+                    }
+                }
+            });
+        });
 
-            for (label, val) in self.show_only_values.iter() {
-                ui.label(format!("{label}: {val}"));
+        if self.separate_windows {
+            for (name, group) in root.children.iter() {
+                egui::Window::new(*name).show(ctx, |ui| {
+                    egui::ScrollArea::vertical().show(ui, |ui| group.show(ui, force_open));
+                });
             }
-        });
+        }
         self.show_only_values.clear();
     }
 
@@ -281,3 +362,137 @@ impl EditableValue for bool {
         ui.add(Checkbox::new(self, "active"));
     }
 }
+
+impl EditableValue for u32 {
+    type Params = Range<u32>;
+
+    fn edit(&mut self, params: &Self::Params, ui: &mut egui::Ui) {
+        ui.add(Slider::new(self, params.start..=params.end));
+    }
+}
+impl DefaultParams for Range<u32> {
+    fn default_params() -> Self {
+        0..100
+    }
+}
+
+impl EditableValue for Vec2 {
+    type Params = (Vec2, Vec2);
+
+    fn edit(&mut self, params: &Self::Params, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("x");
+            ui.add(Slider::new(&mut self.x, params.0.x..=params.1.x));
+
+            ui.label("y");
+            ui.add(Slider::new(&mut self.y, params.0.y..=params.1.y));
+        });
+    }
+}
+
+impl DefaultParams for (Vec2, Vec2) {
+    fn default_params() -> Self {
+        (Vec2::splat(-100.0), Vec2::splat(100.0))
+    }
+}
+
+impl EditableValue for DVec2 {
+    type Params = (DVec2, DVec2);
+
+    fn edit(&mut self, params: &Self::Params, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("x");
+            ui.add(Slider::new(&mut self.x, params.0.x..=params.1.x));
+
+            ui.label("y");
+            ui.add(Slider::new(&mut self.y, params.0.y..=params.1.y));
+        });
+    }
+}
+
+impl DefaultParams for (DVec2, DVec2) {
+    fn default_params() -> Self {
+        (DVec2::splat(-100.0), DVec2::splat(100.0))
+    }
+}
+
+/// Edited as euler angles (yaw, pitch, roll) in degrees.
+impl EditableValue for Quat {
+    type Params = ();
+
+    fn edit(&mut self, _params: &Self::Params, ui: &mut egui::Ui) {
+        let (yaw, pitch, roll) = self.to_euler(glam::EulerRot::YXZ);
+        let mut degrees = [yaw.to_degrees(), pitch.to_degrees(), roll.to_degrees()];
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            for (label, angle) in ["yaw", "pitch", "roll"].iter().zip(degrees.iter_mut()) {
+                ui.label(*label);
+                changed |= ui.add(Slider::new(angle, -180.0..=180.0)).changed();
+            }
+        });
+        if changed {
+            let [yaw, pitch, roll] = degrees.map(f32::to_radians);
+            *self = Quat::from_euler(glam::EulerRot::YXZ, yaw, pitch, roll);
+        }
+    }
+}
+
+impl EditableValue for String {
+    type Params = ();
+
+    fn edit(&mut self, _params: &Self::Params, ui: &mut egui::Ui) {
+        ui.text_edit_singleline(self);
+    }
+}
+
+/// Edits start and end, both within the bounds given as params.
+impl EditableValue for Range<f32> {
+    type Params = Range<f32>;
+
+    fn edit(&mut self, params: &Self::Params, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("start");
+            ui.add(Slider::new(&mut self.start, params.start..=params.end));
+
+            ui.label("end");
+            ui.add(Slider::new(&mut self.end, params.start..=params.end));
+        });
+    }
+}
+
+/// Enums without fields, edited with a combo box. Use `#[derive(EditableEnum)]` to implement it:
+///
+/// ```rust,ignore
+/// use tgf::utils::global_values::EditableEnum;
+///
+/// #[derive(Debug, Clone, PartialEq, EditableEnum)]
+/// enum Weather {
+///     Sunny,
+///     Rainy,
+/// }
+///
+/// let weather = edit!(Weather::Sunny, "weather");
+/// ```
+pub trait EditableEnum: std::fmt::Debug + Clone + PartialEq + 'static {
+    const VARIANTS: &'static [Self];
+}
+
+impl<T: EditableEnum> EditableValue for T {
+    type Params = ();
+
+    fn edit(&mut self, _params: &Self::Params, ui: &mut egui::Ui) {
+        egui::ComboBox::from_id_source(ui.next_auto_id())
+            .selected_text(format!("{self:?}"))
+            .show_ui(ui, |ui| {
+                for variant in T::VARIANTS {
+                    let selected = variant == self;
+                    if ui
+                        .selectable_label(selected, format!("{variant:?}"))
+                        .clicked()
+                    {
+                        *self = variant.clone();
+                    }
+                }
+            });
+    }
+}
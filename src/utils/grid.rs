@@ -0,0 +1,295 @@
+//! Conversions between the cells of a 2d grid and positions in the world, for tile and board games.
+//!
+//! A [`GridMap`] places the grid in the xy or xz plane of the world. Cells are [`IVec2`]s, like in
+//! [`crate::utils::pathfinding`], the cell (0,0) has its min corner at the origin of the map:
+//!
+//! ```rust,ignore
+//! let grid = GridMap::new(2.0, GridPlane::XZ);
+//! let ray = camera.ray_from_screen_pos(input.cursor_pos());
+//! if let Some(cell) = grid.cell_under_ray(&ray) {
+//!     gizmos.draw_grid_cell(&grid, cell, Color::YELLOW);
+//!     let blocked = grid.traverse(player.pos, grid.cell_to_world(cell)).any(|c| walls.contains(&c));
+//! }
+//! ```
+
+use glam::{ivec2, vec2, IVec2, Vec2, Vec3};
+
+use super::pathfinding::Neighborhood;
+use crate::{camera3d::Ray, Aabb};
+
+/// The plane of the world a [`GridMap`] lies in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GridPlane {
+    /// Cells go along x and y, for 2d games.
+    #[default]
+    XY,
+    /// Cells go along x and z, for the ground of 3d games. The y of a cell is the z in the world.
+    XZ,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridMap {
+    pub cell_size: Vec2,
+    /// World position of the min corner of the cell (0,0). Its coordinate that is not in the plane is the
+    /// height of the grid.
+    pub origin: Vec3,
+    pub plane: GridPlane,
+}
+
+impl GridMap {
+    /// Square cells with the cell (0,0) at the world origin.
+    pub fn new(cell_size: f32, plane: GridPlane) -> Self {
+        GridMap {
+            cell_size: Vec2::splat(cell_size),
+            origin: Vec3::ZERO,
+            plane,
+        }
+    }
+
+    pub fn with_origin(mut self, origin: Vec3) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    /// The world position projected into the plane, relative to the origin, in world units.
+    pub fn to_plane(&self, world: Vec3) -> Vec2 {
+        let offset = world - self.origin;
+        match self.plane {
+            GridPlane::XY => vec2(offset.x, offset.y),
+            GridPlane::XZ => vec2(offset.x, offset.z),
+        }
+    }
+
+    /// The world position of a point in the plane, the inverse of [`GridMap::to_plane`].
+    pub fn from_plane(&self, pos: Vec2) -> Vec3 {
+        let offset = match self.plane {
+            GridPlane::XY => pos.extend(0.0),
+            GridPlane::XZ => Vec3::new(pos.x, 0.0, pos.y),
+        };
+        self.origin + offset
+    }
+
+    /// The world position in cell units, e.g. (2.5, 0.5) is the center of the cell (2,0).
+    pub fn to_grid(&self, world: Vec3) -> Vec2 {
+        self.to_plane(world) / self.cell_size
+    }
+
+    /// The cell that contains the world position.
+    pub fn world_to_cell(&self, world: Vec3) -> IVec2 {
+        self.to_grid(world).floor().as_ivec2()
+    }
+
+    /// World position of the center of the cell.
+    pub fn cell_to_world(&self, cell: IVec2) -> Vec3 {
+        self.from_plane((cell.as_vec2() + 0.5) * self.cell_size)
+    }
+
+    /// World position of the min corner of the cell.
+    pub fn cell_corner(&self, cell: IVec2) -> Vec3 {
+        self.from_plane(cell.as_vec2() * self.cell_size)
+    }
+
+    /// The bounds of the cell in plane coordinates, see [`GridMap::to_plane`].
+    pub fn cell_bounds(&self, cell: IVec2) -> Aabb {
+        self.region_bounds(cell, cell)
+    }
+
+    /// The bounds of all cells from `min` to `max` (inclusive) in plane coordinates.
+    pub fn region_bounds(&self, min: IVec2, max: IVec2) -> Aabb {
+        Aabb::new(
+            min.as_vec2() * self.cell_size,
+            (max + IVec2::ONE).as_vec2() * self.cell_size,
+        )
+    }
+
+    /// Moves the world position to the center of its cell, keeping its height above the plane.
+    pub fn snap_to_center(&self, world: Vec3) -> Vec3 {
+        self.with_height_of(self.cell_to_world(self.world_to_cell(world)), world)
+    }
+
+    /// Moves the world position to the closest corner between cells, keeping its height above the plane.
+    pub fn snap_to_corner(&self, world: Vec3) -> Vec3 {
+        let corner = self.to_grid(world).round() * self.cell_size;
+        self.with_height_of(self.from_plane(corner), world)
+    }
+
+    /// The cell where the ray hits the plane of the grid, e.g. the cell under the cursor.
+    pub fn cell_under_ray(&self, ray: &Ray) -> Option<IVec2> {
+        let normal = match self.plane {
+            GridPlane::XY => Vec3::Z,
+            GridPlane::XZ => Vec3::Y,
+        };
+        let distance = ray.intersect_plane(self.origin, normal)?;
+        Some(self.world_to_cell(ray.get_point(distance)))
+    }
+
+    /// All cells the straight line from `from` to `to` passes through, in order. Unlike [`line_cells`], no
+    /// cell that the line touches is skipped, which is what line of sight and projectile checks need.
+    pub fn traverse(&self, from: Vec3, to: Vec3) -> GridTraversal {
+        GridTraversal::new(self.to_grid(from), self.to_grid(to))
+    }
+
+    /// `pos` with the height above the plane of `height_of`.
+    fn with_height_of(&self, mut pos: Vec3, height_of: Vec3) -> Vec3 {
+        match self.plane {
+            GridPlane::XY => pos.z = height_of.z,
+            GridPlane::XZ => pos.y = height_of.y,
+        }
+        pos
+    }
+}
+
+/// Iterator over the cells a line passes through, from [`GridMap::traverse`].
+///
+/// Steps from cell to cell at the grid lines the line crosses (Amanatides & Woo), so a line exactly through a
+/// corner enters one of the two side cells before the diagonal one.
+#[derive(Debug, Clone)]
+pub struct GridTraversal {
+    cell: IVec2,
+    step: IVec2,
+    /// fraction of the line at which the next vertical and horizontal grid lines are crossed.
+    t_max: Vec2,
+    /// fraction of the line between two vertical and two horizontal grid lines.
+    t_delta: Vec2,
+    /// steps left in x and y direction, None when the last cell was returned.
+    remaining: Option<IVec2>,
+}
+
+impl GridTraversal {
+    /// `from` and `to` are in cell units, see [`GridMap::to_grid`].
+    pub fn new(from: Vec2, to: Vec2) -> Self {
+        let cell = from.floor().as_ivec2();
+        let end = to.floor().as_ivec2();
+        let delta = to - from;
+        let axis = |from: f32, delta: f32| -> (i32, f32, f32) {
+            if delta > 0.0 {
+                (1, (from.floor() + 1.0 - from) / delta, 1.0 / delta)
+            } else if delta < 0.0 {
+                (-1, (from - from.floor()) / -delta, -1.0 / delta)
+            } else {
+                (0, f32::INFINITY, f32::INFINITY)
+            }
+        };
+        let (step_x, t_max_x, t_delta_x) = axis(from.x, delta.x);
+        let (step_y, t_max_y, t_delta_y) = axis(from.y, delta.y);
+        GridTraversal {
+            cell,
+            step: ivec2(step_x, step_y),
+            t_max: vec2(t_max_x, t_max_y),
+            t_delta: vec2(t_delta_x, t_delta_y),
+            remaining: Some((end - cell).abs()),
+        }
+    }
+}
+
+impl Iterator for GridTraversal {
+    type Item = IVec2;
+
+    fn next(&mut self) -> Option<IVec2> {
+        let remaining = self.remaining.as_mut()?;
+        let cell = self.cell;
+        // the step counts decide when to stop, so float errors can't overshoot the last cell.
+        let step_x = match (remaining.x > 0, remaining.y > 0) {
+            (false, false) => {
+                self.remaining = None;
+                return Some(cell);
+            }
+            (true, false) => true,
+            (false, true) => false,
+            (true, true) => self.t_max.x < self.t_max.y,
+        };
+        if step_x {
+            self.cell.x += self.step.x;
+            self.t_max.x += self.t_delta.x;
+            remaining.x -= 1;
+        } else {
+            self.cell.y += self.step.y;
+            self.t_max.y += self.t_delta.y;
+            remaining.y -= 1;
+        }
+        Some(cell)
+    }
+}
+
+/// The cells of a line from `from` to `to` (both included) with one cell per column or row (Bresenham),
+/// e.g. for drawing walls or roads. Diagonal steps can skip cells the line touches, see [`GridMap::traverse`].
+pub fn line_cells(from: IVec2, to: IVec2) -> Vec<IVec2> {
+    let d = (to - from).abs();
+    let step = (to - from).signum();
+    let mut cells = Vec::with_capacity(d.x.max(d.y) as usize + 1);
+    let mut cell = from;
+    let mut err = d.x - d.y;
+    loop {
+        cells.push(cell);
+        if cell == to {
+            return cells;
+        }
+        let e2 = 2 * err;
+        if e2 > -d.y {
+            err -= d.y;
+            cell.x += step.x;
+        }
+        if e2 < d.x {
+            err += d.x;
+            cell.y += step.y;
+        }
+    }
+}
+
+/// The neighbors of the cell, the orthogonal ones first.
+pub fn neighbors(cell: IVec2, neighborhood: Neighborhood) -> impl Iterator<Item = IVec2> {
+    const OFFSETS: [IVec2; 8] = [
+        ivec2(1, 0),
+        ivec2(-1, 0),
+        ivec2(0, 1),
+        ivec2(0, -1),
+        ivec2(1, 1),
+        ivec2(-1, 1),
+        ivec2(1, -1),
+        ivec2(-1, -1),
+    ];
+    let n = match neighborhood {
+        Neighborhood::Four => 4,
+        Neighborhood::Eight => 8,
+    };
+    OFFSETS[..n].iter().map(move |offset| cell + *offset)
+}
+
+/// All cells from `min` to `max` (inclusive), row by row.
+pub fn cells_in(min: IVec2, max: IVec2) -> impl Iterator<Item = IVec2> {
+    (min.y..=max.y).flat_map(move |y| (min.x..=max.x).map(move |x| ivec2(x, y)))
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{ivec2, vec2, vec3, IVec2};
+
+    use super::{line_cells, GridMap, GridPlane, GridTraversal};
+
+    #[test]
+    fn cells_and_world_positions() {
+        let grid = GridMap::new(2.0, GridPlane::XZ).with_origin(vec3(0.0, 1.0, 0.0));
+        assert_eq!(grid.world_to_cell(vec3(3.0, 7.0, -0.5)), ivec2(1, -1));
+        assert_eq!(grid.cell_to_world(ivec2(1, -1)), vec3(3.0, 1.0, -1.0));
+        assert_eq!(
+            grid.snap_to_corner(vec3(2.9, 5.0, 0.9)),
+            vec3(2.0, 5.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn traversals_visit_every_touched_cell() {
+        let cells: Vec<IVec2> = GridTraversal::new(vec2(0.5, 0.5), vec2(2.5, 1.5)).collect();
+        assert_eq!(
+            cells,
+            vec![ivec2(0, 0), ivec2(1, 0), ivec2(1, 1), ivec2(2, 1)]
+        );
+        let back: Vec<IVec2> = GridTraversal::new(vec2(-0.5, 0.2), vec2(-2.5, 0.8)).collect();
+        assert_eq!(back, vec![ivec2(-1, 0), ivec2(-2, 0), ivec2(-3, 0)]);
+
+        assert_eq!(
+            line_cells(ivec2(0, 0), ivec2(3, 1)),
+            vec![ivec2(0, 0), ivec2(1, 0), ivec2(2, 1), ivec2(3, 1)]
+        );
+    }
+}
@@ -1,6 +1,10 @@
 use std::{rc::Rc, sync::Arc};
 
 pub mod camera_controllers;
+pub mod grid;
+pub mod pathfinding;
+pub mod rect_packer;
+pub mod visibility;
 
 #[cfg(feature = "eguimod")]
 pub mod global_values;
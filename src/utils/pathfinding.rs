@@ -0,0 +1,330 @@
+//! Pathfinding on 2d grids with user supplied costs: A*, Dijkstra and flow fields.
+//!
+//! All functions take the `size` of the grid (cells are in `0..size.x` and `0..size.y`) and a cost function
+//! that returns the cost of entering a cell, or None if the cell cannot be entered.
+//! Diagonal steps cost `sqrt(2)` times the cost of the cell that is entered.
+
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+use glam::{ivec2, IVec2};
+
+/// Which cells are neighbors of a cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Neighborhood {
+    /// up, down, left, right
+    #[default]
+    Four,
+    /// also diagonals. Diagonal steps are only allowed if both adjacent orthogonal cells can be entered,
+    /// so paths do not cut corners of walls.
+    Eight,
+}
+
+const ORTHOGONAL: [IVec2; 4] = [ivec2(1, 0), ivec2(-1, 0), ivec2(0, 1), ivec2(0, -1)];
+const DIAGONAL: [IVec2; 4] = [ivec2(1, 1), ivec2(-1, 1), ivec2(1, -1), ivec2(-1, -1)];
+
+fn in_bounds(pos: IVec2, size: IVec2) -> bool {
+    pos.x >= 0 && pos.y >= 0 && pos.x < size.x && pos.y < size.y
+}
+
+/// Calls `f(neighbor, neighbor_cost, step_length)` for each neighbor of `pos` that can be entered.
+fn for_each_neighbor(
+    pos: IVec2,
+    size: IVec2,
+    neighborhood: Neighborhood,
+    cost: &impl Fn(IVec2) -> Option<f32>,
+    mut f: impl FnMut(IVec2, f32, f32),
+) {
+    let enter = |p: IVec2| if in_bounds(p, size) { cost(p) } else { None };
+    for dir in ORTHOGONAL {
+        if let Some(c) = enter(pos + dir) {
+            f(pos + dir, c, 1.0);
+        }
+    }
+    if neighborhood == Neighborhood::Eight {
+        for dir in DIAGONAL {
+            let corners_free =
+                enter(pos + ivec2(dir.x, 0)).is_some() && enter(pos + ivec2(0, dir.y)).is_some();
+            if !corners_free {
+                continue;
+            }
+            if let Some(c) = enter(pos + dir) {
+                f(pos + dir, c, std::f32::consts::SQRT_2);
+            }
+        }
+    }
+}
+
+/// Estimated distance for A*, assuming all cells cost at least 1.0.
+fn heuristic(a: IVec2, b: IVec2, neighborhood: Neighborhood) -> f32 {
+    let d = (a - b).abs();
+    match neighborhood {
+        Neighborhood::Four => (d.x + d.y) as f32,
+        Neighborhood::Eight => {
+            let (min, max) = (d.x.min(d.y) as f32, d.x.max(d.y) as f32);
+            max + (std::f32::consts::SQRT_2 - 1.0) * min
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct HeapEntry {
+    priority: f32,
+    index: usize,
+}
+
+impl PartialEq for HeapEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.priority == other.priority
+    }
+}
+impl Eq for HeapEntry {}
+impl PartialOrd for HeapEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for HeapEntry {
+    /// reversed, such that the BinaryHeap is a min heap.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.priority.total_cmp(&self.priority)
+    }
+}
+
+/// Costs from a start cell to every reachable cell of the grid, see [`dijkstra`].
+#[derive(Debug, Clone)]
+pub struct DistanceMap {
+    size: IVec2,
+    costs: Vec<f32>,
+    previous: Vec<Option<usize>>,
+}
+
+impl DistanceMap {
+    fn new(size: IVec2) -> Self {
+        let len = (size.x.max(0) * size.y.max(0)) as usize;
+        Self {
+            size,
+            costs: vec![f32::INFINITY; len],
+            previous: vec![None; len],
+        }
+    }
+
+    #[inline]
+    fn index(&self, pos: IVec2) -> usize {
+        (pos.y * self.size.x + pos.x) as usize
+    }
+
+    #[inline]
+    fn pos(&self, index: usize) -> IVec2 {
+        ivec2(index as i32 % self.size.x, index as i32 / self.size.x)
+    }
+
+    pub fn size(&self) -> IVec2 {
+        self.size
+    }
+
+    /// Cost of the cheapest path to `pos`, None if it is not reachable.
+    pub fn cost(&self, pos: IVec2) -> Option<f32> {
+        if !in_bounds(pos, self.size) {
+            return None;
+        }
+        let cost = self.costs[self.index(pos)];
+        cost.is_finite().then_some(cost)
+    }
+
+    /// Cheapest path from the start to `goal`, including both.
+    pub fn path_to(&self, goal: IVec2) -> Option<Vec<IVec2>> {
+        self.cost(goal)?;
+        let mut path = vec![goal];
+        let mut index = self.index(goal);
+        while let Some(prev) = self.previous[index] {
+            path.push(self.pos(prev));
+            index = prev;
+        }
+        path.reverse();
+        Some(path)
+    }
+}
+
+/// Shared by A* and Dijkstra, stops when `goal` is reached if it is given.
+fn search(
+    size: IVec2,
+    starts: &[IVec2],
+    goal: Option<IVec2>,
+    neighborhood: Neighborhood,
+    cost: &impl Fn(IVec2) -> Option<f32>,
+) -> DistanceMap {
+    let mut map = DistanceMap::new(size);
+    let mut heap = BinaryHeap::new();
+    let h = |pos: IVec2| goal.map_or(0.0, |goal| heuristic(pos, goal, neighborhood));
+    for &start in starts {
+        if !in_bounds(start, size) {
+            continue;
+        }
+        let index = map.index(start);
+        map.costs[index] = 0.0;
+        heap.push(HeapEntry {
+            priority: h(start),
+            index,
+        });
+    }
+
+    while let Some(HeapEntry { priority, index }) = heap.pop() {
+        let pos = map.pos(index);
+        let pos_cost = map.costs[index];
+        if priority > pos_cost + h(pos) {
+            // outdated entry, the cell was reached cheaper in the meantime.
+            continue;
+        }
+        if Some(pos) == goal {
+            break;
+        }
+        for_each_neighbor(
+            pos,
+            size,
+            neighborhood,
+            cost,
+            |next, next_cell_cost, step_len| {
+                let next_index = map.index(next);
+                let next_cost = pos_cost + next_cell_cost * step_len;
+                if next_cost < map.costs[next_index] {
+                    map.costs[next_index] = next_cost;
+                    map.previous[next_index] = Some(index);
+                    heap.push(HeapEntry {
+                        priority: next_cost + h(next),
+                        index: next_index,
+                    });
+                }
+            },
+        );
+    }
+    map
+}
+
+/// Cheapest path from `start` to `goal` (both included), None if the goal is not reachable.
+///
+/// The heuristic assumes that entering a cell costs at least 1.0, otherwise the path might not be the cheapest one.
+pub fn astar(
+    size: IVec2,
+    start: IVec2,
+    goal: IVec2,
+    neighborhood: Neighborhood,
+    cost: impl Fn(IVec2) -> Option<f32>,
+) -> Option<Vec<IVec2>> {
+    search(size, &[start], Some(goal), neighborhood, &cost).path_to(goal)
+}
+
+/// Costs and paths from `start` to all reachable cells, e.g. to show the movement range of a unit.
+pub fn dijkstra(
+    size: IVec2,
+    start: IVec2,
+    neighborhood: Neighborhood,
+    cost: impl Fn(IVec2) -> Option<f32>,
+) -> DistanceMap {
+    search(size, &[start], None, neighborhood, &cost)
+}
+
+/// For every cell, the direction to step in to get to the closest target. Computed once for many agents
+/// that move towards the same targets, instead of searching a path for each agent.
+#[derive(Debug, Clone)]
+pub struct FlowField {
+    distances: DistanceMap,
+}
+
+impl FlowField {
+    pub fn new(
+        size: IVec2,
+        targets: &[IVec2],
+        neighborhood: Neighborhood,
+        cost: impl Fn(IVec2) -> Option<f32>,
+    ) -> Self {
+        // searches backwards from the targets: an agent at `next` steps into `pos`, so it pays the cost of `pos`.
+        let mut field = FlowField {
+            distances: DistanceMap::new(size),
+        };
+        let mut heap = BinaryHeap::new();
+        for &target in targets {
+            if in_bounds(target, size) {
+                let index = field.distances.index(target);
+                field.distances.costs[index] = 0.0;
+                heap.push(HeapEntry {
+                    priority: 0.0,
+                    index,
+                });
+            }
+        }
+        while let Some(HeapEntry { priority, index }) = heap.pop() {
+            if priority > field.distances.costs[index] {
+                continue;
+            }
+            let pos = field.distances.pos(index);
+            // agents can not step into a target that can not be entered, so it does not attract neighbors.
+            let Some(pos_cell_cost) = cost(pos) else {
+                continue;
+            };
+            for_each_neighbor(pos, size, neighborhood, &cost, |next, _, step_len| {
+                let next_index = field.distances.index(next);
+                let next_cost = priority + pos_cell_cost * step_len;
+                if next_cost < field.distances.costs[next_index] {
+                    field.distances.costs[next_index] = next_cost;
+                    field.distances.previous[next_index] = Some(index);
+                    heap.push(HeapEntry {
+                        priority: next_cost,
+                        index: next_index,
+                    });
+                }
+            });
+        }
+        field
+    }
+
+    /// Step (e.g. (1,0) or (-1,1)) towards the closest target. None for targets and unreachable cells.
+    pub fn direction(&self, pos: IVec2) -> Option<IVec2> {
+        self.distances.cost(pos)?;
+        let next = self.distances.previous[self.distances.index(pos)]?;
+        Some(self.distances.pos(next) - pos)
+    }
+
+    /// Cost of moving from `pos` to the closest target.
+    pub fn cost(&self, pos: IVec2) -> Option<f32> {
+        self.distances.cost(pos)
+    }
+
+    pub fn size(&self) -> IVec2 {
+        self.distances.size
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{ivec2, IVec2};
+
+    use super::{astar, dijkstra, FlowField, Neighborhood};
+
+    #[test]
+    fn path_around_wall() {
+        // a wall at x = 2 with a gap at y = 4:
+        let size = ivec2(5, 5);
+        let cost = |p: IVec2| (p.x != 2 || p.y == 4).then_some(1.0);
+
+        let path = astar(size, ivec2(0, 0), ivec2(4, 0), Neighborhood::Four, cost).unwrap();
+        assert_eq!(path.first(), Some(&ivec2(0, 0)));
+        assert_eq!(path.last(), Some(&ivec2(4, 0)));
+        assert!(path.contains(&ivec2(2, 4)));
+        assert_eq!(path.len(), 13);
+
+        let map = dijkstra(size, ivec2(0, 0), Neighborhood::Four, cost);
+        assert_eq!(map.cost(ivec2(4, 0)), Some(12.0));
+        assert_eq!(map.cost(ivec2(2, 0)), None);
+
+        let field = FlowField::new(size, &[ivec2(4, 0)], Neighborhood::Four, cost);
+        assert_eq!(field.cost(ivec2(0, 0)), Some(12.0));
+        assert_eq!(field.direction(ivec2(4, 0)), None);
+        let mut pos = ivec2(0, 0);
+        let mut steps = 0;
+        while let Some(dir) = field.direction(pos) {
+            pos += dir;
+            steps += 1;
+        }
+        assert_eq!((pos, steps), (ivec2(4, 0), 12));
+    }
+}
@@ -0,0 +1,216 @@
+use ahash::AHashMap;
+use etagere::{AllocId, AtlasAllocator, Size};
+use glam::{uvec2, UVec2};
+
+use crate::Aabb;
+
+/// What a [`RectPacker`] does when a rect does not fit anymore.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GrowStrategy {
+    /// allocation fails.
+    #[default]
+    Fixed,
+    /// doubles the width and height, up to `max_size`, and packs all rects again.
+    Double { max_size: UVec2 },
+}
+
+/// Position and size of an allocated rect in px.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PackedRect {
+    pub min: UVec2,
+    pub size: UVec2,
+}
+
+impl PackedRect {
+    /// uv coordinates of the rect in an atlas of `atlas_size`, like in [`crate::ui::TextureRegion`].
+    pub fn uv(&self, atlas_size: UVec2) -> Aabb {
+        let atlas_size = atlas_size.as_vec2();
+        let min = self.min.as_vec2() / atlas_size;
+        Aabb::new(min, min + self.size.as_vec2() / atlas_size)
+    }
+}
+
+/// Packs rects into a 2d area, for building texture atlases (lightmaps, minimap icons, ...).
+/// The same allocator that packs the glyphs of [`crate::ui::SdfFont`].
+///
+/// Rects are referred to by u32 ids that stay the same when the packer grows. Growing packs all rects
+/// again, which can move them, so the contents of the atlas need to be copied again when
+/// [`RectPacker::generation`] changes:
+///
+/// ```rust,ignore
+/// let mut packer = RectPacker::new(256, 256).grow_strategy(GrowStrategy::Double { max_size: uvec2(4096, 4096) });
+/// let id = packer.allocate(icon.width(), icon.height()).expect("atlas full");
+/// if packer.generation() != last_generation {
+///     // recreate the atlas texture with packer.size() and copy all images to packer.get(id).
+/// }
+/// ```
+pub struct RectPacker {
+    allocator: AtlasAllocator,
+    grow_strategy: GrowStrategy,
+    rects: AHashMap<u32, (AllocId, PackedRect)>,
+    next_id: u32,
+    generation: u32,
+}
+
+impl std::fmt::Debug for RectPacker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RectPacker")
+            .field("size", &self.size())
+            .field("grow_strategy", &self.grow_strategy)
+            .field("n_rects", &self.n_rects())
+            .field("generation", &self.generation)
+            .finish()
+    }
+}
+
+impl RectPacker {
+    pub fn new(width: u32, height: u32) -> Self {
+        RectPacker {
+            allocator: AtlasAllocator::new(to_size(uvec2(width, height))),
+            grow_strategy: GrowStrategy::Fixed,
+            rects: AHashMap::new(),
+            next_id: 0,
+            generation: 0,
+        }
+    }
+
+    pub fn grow_strategy(mut self, grow_strategy: GrowStrategy) -> Self {
+        self.grow_strategy = grow_strategy;
+        self
+    }
+
+    pub fn size(&self) -> UVec2 {
+        let size = self.allocator.size();
+        uvec2(size.width as u32, size.height as u32)
+    }
+
+    /// Increases every time the packer grows and rects might have moved.
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+
+    /// Number of allocated rects.
+    pub fn n_rects(&self) -> usize {
+        self.rects.len()
+    }
+
+    /// Allocates a rect of exactly `width` x `height` px. None if it does not fit, even after growing.
+    pub fn allocate(&mut self, width: u32, height: u32) -> Option<u32> {
+        let size = uvec2(width, height);
+        let rect = match allocate(&mut self.allocator, size) {
+            Some(rect) => rect,
+            None => self.grow_and_allocate(size)?,
+        };
+        let id = self.next_id;
+        self.next_id += 1;
+        self.rects.insert(id, rect);
+        Some(id)
+    }
+
+    /// Frees the rect, its space can be used by later allocations. Does nothing for unknown ids.
+    pub fn deallocate(&mut self, id: u32) {
+        if let Some((alloc_id, _)) = self.rects.remove(&id) {
+            self.allocator.deallocate(alloc_id);
+        }
+    }
+
+    pub fn get(&self, id: u32) -> Option<PackedRect> {
+        self.rects.get(&id).map(|(_, rect)| *rect)
+    }
+
+    /// uv coordinates of the rect in the current size of the packer.
+    pub fn uv(&self, id: u32) -> Option<Aabb> {
+        self.get(id).map(|rect| rect.uv(self.size()))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u32, PackedRect)> + '_ {
+        self.rects.iter().map(|(id, (_, rect))| (*id, *rect))
+    }
+
+    /// Removes all rects, the size stays the same.
+    pub fn clear(&mut self) {
+        self.allocator.clear();
+        self.rects.clear();
+    }
+
+    /// Doubles the size until all rects and the new one of `size` fit, which is allocated.
+    /// None if that needs more than the max size.
+    fn grow_and_allocate(&mut self, size: UVec2) -> Option<(AllocId, PackedRect)> {
+        let GrowStrategy::Double { max_size } = self.grow_strategy else {
+            return None;
+        };
+        // packing larger rects first is tighter, ties are broken by id to be deterministic.
+        let mut sizes: Vec<(u32, UVec2)> = self
+            .rects
+            .iter()
+            .map(|(id, (_, rect))| (*id, rect.size))
+            .collect();
+        sizes.sort_by_key(|(id, size)| (std::cmp::Reverse(size.x * size.y), *id));
+
+        let mut new_size = self.size();
+        while new_size != max_size {
+            new_size = (new_size * 2).min(max_size);
+            let mut allocator = AtlasAllocator::new(to_size(new_size));
+            let rects: Option<AHashMap<u32, (AllocId, PackedRect)>> = sizes
+                .iter()
+                .map(|(id, size)| Some((*id, allocate(&mut allocator, *size)?)))
+                .collect();
+            let Some(rects) = rects else {
+                continue;
+            };
+            let Some(new_rect) = allocate(&mut allocator, size) else {
+                continue;
+            };
+            self.allocator = allocator;
+            self.rects = rects;
+            self.generation += 1;
+            return Some(new_rect);
+        }
+        None
+    }
+}
+
+/// the rectangle of the allocation can be larger than requested, only its min corner is reliable.
+fn allocate(allocator: &mut AtlasAllocator, size: UVec2) -> Option<(AllocId, PackedRect)> {
+    let allocation = allocator.allocate(to_size(size))?;
+    let rect = PackedRect {
+        min: uvec2(
+            allocation.rectangle.min.x as u32,
+            allocation.rectangle.min.y as u32,
+        ),
+        size,
+    };
+    Some((allocation.id, rect))
+}
+
+fn to_size(size: UVec2) -> Size {
+    Size::new(size.x as i32, size.y as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::uvec2;
+
+    use super::{GrowStrategy, RectPacker};
+
+    #[test]
+    fn grows_and_keeps_ids() {
+        let mut packer = RectPacker::new(64, 64).grow_strategy(GrowStrategy::Double {
+            max_size: uvec2(256, 256),
+        });
+        let ids: Vec<u32> = (0..4).map(|_| packer.allocate(32, 32).unwrap()).collect();
+        assert_eq!(packer.generation(), 0);
+
+        let fifth = packer.allocate(32, 32).unwrap();
+        assert_eq!(packer.generation(), 1);
+        assert_eq!(packer.size(), uvec2(128, 128));
+        for id in ids.iter().chain([&fifth]) {
+            assert_eq!(packer.get(*id).unwrap().size, uvec2(32, 32));
+        }
+
+        packer.deallocate(ids[0]);
+        assert!(packer.get(ids[0]).is_none());
+        assert!(packer.allocate(512, 8).is_none());
+        assert_eq!(packer.n_rects(), 4);
+    }
+}
@@ -0,0 +1,213 @@
+//! 2d visibility polygons and line of sight checks against walls, e.g. for fog of war or the view cones
+//! of guards in stealth games.
+//!
+//! Walls are line segments, [`aabb_segments`] turns boxes into their 4 edges:
+//!
+//! ```rust,ignore
+//! let mut walls: Vec<Segment> = vec![];
+//! for obstacle in obstacles.iter() {
+//!     walls.extend(aabb_segments(obstacle.aabb));
+//! }
+//! let visible = VisibilityPolygon::new(player.pos, &walls, level_bounds);
+//! let (vertices, indices) = visible.triangle_fan(|p| vec3(p.x, p.y, 0.0), Color::WHITE);
+//! color_mesh_renderer.draw_geometry(&vertices, &indices, &[(Transform::default(), Color::WHITE)]);
+//! if visible.contains(enemy.pos) { .. }
+//! ```
+//!
+//! The polygon is computed by casting rays to all segment end points, which is `O(n^2)` in the number of
+//! segments. That is fine for a few hundred walls, filter far away walls out first for bigger levels.
+
+use std::f32::consts::TAU;
+
+use glam::{vec2, Vec2, Vec3};
+
+use crate::{renderer::color_mesh, Aabb, Color};
+
+/// A wall between two points.
+pub type Segment = (Vec2, Vec2);
+
+/// Rays are cast this many radians left and right of each segment end point, to see past corners.
+const ANGLE_EPSILON: f32 = 0.0001;
+
+/// The 4 edges of an aabb, as walls for [`VisibilityPolygon::new`].
+pub fn aabb_segments(aabb: Aabb) -> [Segment; 4] {
+    let (min, max) = (aabb.min, aabb.max);
+    let (a, b, c, d) = (min, vec2(max.x, min.y), max, vec2(min.x, max.y));
+    [(a, b), (b, c), (c, d), (d, a)]
+}
+
+/// The area visible from `origin`, a star shaped polygon around it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VisibilityPolygon {
+    pub origin: Vec2,
+    /// Corners of the polygon, sorted counter clockwise by their angle around the `origin` (x right, y up).
+    /// Empty if the origin is outside of the bounds.
+    pub points: Vec<Vec2>,
+}
+
+impl VisibilityPolygon {
+    /// Computes what is visible from `origin` with the `segments` blocking the view. Nothing outside
+    /// of `bounds` is visible, so the polygon is always closed.
+    pub fn new(origin: Vec2, segments: &[Segment], bounds: Aabb) -> Self {
+        if !bounds.contains(origin) {
+            return VisibilityPolygon {
+                origin,
+                points: vec![],
+            };
+        }
+        let segments: Vec<Segment> = segments
+            .iter()
+            .copied()
+            .chain(aabb_segments(bounds))
+            .collect();
+
+        let mut angles: Vec<f32> = segments
+            .iter()
+            .flat_map(|(a, b)| [*a, *b])
+            .filter(|p| *p != origin)
+            .map(|p| {
+                let to_p = p - origin;
+                to_p.y.atan2(to_p.x)
+            })
+            .collect();
+        angles.sort_by(f32::total_cmp);
+        angles.dedup_by(|a, b| (*a - *b).abs() < ANGLE_EPSILON * 0.5);
+
+        let mut hits: Vec<(f32, Vec2)> = vec![];
+        for angle in angles {
+            for angle in [angle - ANGLE_EPSILON, angle, angle + ANGLE_EPSILON] {
+                let dir = Vec2::from_angle(angle);
+                if let Some(dist) = cast_ray(origin, dir, &segments) {
+                    hits.push((angle.rem_euclid(TAU), origin + dir * dist));
+                }
+            }
+        }
+        hits.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut points: Vec<Vec2> = Vec::with_capacity(hits.len());
+        for (_, p) in hits {
+            if points
+                .last()
+                .is_none_or(|last| last.distance_squared(p) > 1e-8)
+            {
+                points.push(p);
+            }
+        }
+        if points.len() > 1 && points[0].distance_squared(points[points.len() - 1]) <= 1e-8 {
+            points.pop();
+        }
+        VisibilityPolygon { origin, points }
+    }
+
+    /// Whether `pos` is visible from the origin, i.e. inside of the polygon.
+    pub fn contains(&self, pos: Vec2) -> bool {
+        let mut inside = false;
+        let n = self.points.len();
+        for i in 0..n {
+            let a = self.points[i];
+            let b = self.points[(i + 1) % n];
+            if (a.y > pos.y) != (b.y > pos.y) {
+                let x = a.x + (pos.y - a.y) / (b.y - a.y) * (b.x - a.x);
+                if pos.x < x {
+                    inside = !inside;
+                }
+            }
+        }
+        inside
+    }
+
+    /// The triangles from the origin to each edge of the polygon, counter clockwise in 2d.
+    pub fn triangles(&self) -> impl Iterator<Item = [Vec2; 3]> + '_ {
+        let n = self.points.len();
+        (0..n).map(move |i| [self.origin, self.points[i], self.points[(i + 1) % n]])
+    }
+
+    /// Vertices and indices of the polygon as a triangle fan around the origin, for
+    /// [`color_mesh::ColorMeshRenderer::draw_geometry`]. `to_world` places the 2d points in the world,
+    /// e.g. `|p| vec3(p.x, 0.0, -p.y)` for the ground plane, which keeps the triangles facing up.
+    pub fn triangle_fan(
+        &self,
+        to_world: impl Fn(Vec2) -> Vec3,
+        color: Color,
+    ) -> (Vec<color_mesh::Vertex>, Vec<u32>) {
+        let n = self.points.len() as u32;
+        if n < 2 {
+            return (vec![], vec![]);
+        }
+        let vertices = std::iter::once(self.origin)
+            .chain(self.points.iter().copied())
+            .map(|p| color_mesh::Vertex {
+                pos: to_world(p),
+                color,
+            })
+            .collect();
+        let indices = (0..n).flat_map(|i| [0, i + 1, (i + 1) % n + 1]).collect();
+        (vertices, indices)
+    }
+}
+
+/// True if no segment is between `from` and `to`. Segments that only touch the line of sight block it too.
+pub fn line_of_sight(from: Vec2, to: Vec2, segments: &[Segment]) -> bool {
+    let dir = to - from;
+    !segments
+        .iter()
+        .any(|(a, b)| ray_segment_intersection(from, dir, *a, *b).is_some_and(|t| t <= 1.0))
+}
+
+/// The distance along `dir` (normalized) to the closest segment hit by the ray from `origin`.
+fn cast_ray(origin: Vec2, dir: Vec2, segments: &[Segment]) -> Option<f32> {
+    segments
+        .iter()
+        .filter_map(|(a, b)| ray_segment_intersection(origin, dir, *a, *b))
+        .min_by(f32::total_cmp)
+}
+
+/// The `t` where `origin + dir * t` hits the segment from `a` to `b`, if `t >= 0`.
+fn ray_segment_intersection(origin: Vec2, dir: Vec2, a: Vec2, b: Vec2) -> Option<f32> {
+    let edge = b - a;
+    let denom = dir.perp_dot(edge);
+    if denom.abs() < 1e-12 {
+        // parallel, a ray along the segment sees past it.
+        return None;
+    }
+    let to_a = a - origin;
+    let t = to_a.perp_dot(edge) / denom;
+    let u = to_a.perp_dot(dir) / denom;
+    (t >= 0.0 && (0.0..=1.0).contains(&u)).then_some(t)
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{vec2, vec3, Vec2};
+
+    use super::{aabb_segments, line_of_sight, VisibilityPolygon};
+    use crate::{Aabb, Color};
+
+    #[test]
+    fn box_casts_a_shadow() {
+        let bounds = Aabb::new(vec2(-10.0, -10.0), vec2(10.0, 10.0));
+        let walls = aabb_segments(Aabb::new(vec2(4.0, -1.0), vec2(6.0, 1.0)));
+        let visible = VisibilityPolygon::new(Vec2::ZERO, &walls, bounds);
+
+        for (pos, expected) in [
+            (vec2(2.0, 0.0), true),
+            (vec2(8.0, 0.0), false),
+            (vec2(9.0, 1.2), false),
+            (vec2(8.0, 5.0), true),
+            (vec2(-9.0, -9.0), true),
+            (vec2(11.0, 5.0), false),
+        ] {
+            assert_eq!(visible.contains(pos), expected, "{pos}");
+            if bounds.contains(pos) {
+                assert_eq!(line_of_sight(Vec2::ZERO, pos, &walls), expected, "{pos}");
+            }
+        }
+
+        let (vertices, indices) = visible.triangle_fan(|p| vec3(p.x, p.y, 0.0), Color::WHITE);
+        assert_eq!(vertices.len(), visible.points.len() + 1);
+        assert_eq!(indices.len(), visible.points.len() * 3);
+        for [o, a, b] in visible.triangles() {
+            assert!((a - o).perp_dot(b - o) >= 0.0);
+        }
+    }
+}